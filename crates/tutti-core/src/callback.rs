@@ -4,20 +4,22 @@
 //! The CPAL buffer is split at event boundaries (MIDI frame offsets, loop wrap
 //! points) and each segment is processed independently.
 
-#[cfg(feature = "midi")]
-use crate::compat::Vec;
-use crate::compat::{Arc, AtomicU64, Ordering, UnsafeCell};
+use crate::compat::{Arc, AtomicU64, Ordering, UnsafeCell, Vec};
 use crate::metering::MeteringManager;
 use crate::transport::{
-    ClickNode, ClickSettings, TransportClock, TransportHandle, TransportManager,
+    ClickNode, ClickSettings, SyncBlock, TransportClock, TransportHandle, TransportManager,
+    TransportSyncOutput,
 };
+use arc_swap::ArcSwapOption;
 use fundsp::audionode::AudioNode;
 use fundsp::audiounit::AudioUnit;
 use fundsp::realnet::NetBackend;
 use std::time::Instant;
 
 #[cfg(feature = "midi")]
-use crate::midi::{MidiInputSource, MidiRegistry, MidiRoutingSnapshot};
+use crate::midi::{
+    effect::MidiEffectRack, MidiInputSource, MidiRegistry, MidiRoutingSnapshot, TransportMidiSource,
+};
 #[cfg(feature = "midi")]
 use arc_swap::ArcSwap;
 #[cfg(feature = "midi")]
@@ -30,6 +32,21 @@ const MIDI_EVENT_BUFFER_CAPACITY: usize = 512;
 /// Maximum number of split points per callback (MIDI events + loop boundary).
 const MAX_SPLIT_POINTS: usize = 258;
 
+/// Pre-allocated per-frame beat buffer for the sync output. Larger callbacks
+/// skip sync output rather than allocate.
+const SYNC_BEAT_BUFFER_CAPACITY: usize = 16384;
+
+/// Shared slot for the transport sync output, swappable from any thread.
+pub(crate) type SyncOutputSlot = Arc<ArcSwapOption<Arc<dyn TransportSyncOutput>>>;
+
+/// Shared slot for the transport-driven MIDI source, swappable from any thread.
+#[cfg(feature = "midi")]
+pub(crate) type TransportMidiSlot = Arc<ArcSwapOption<Arc<dyn TransportMidiSource>>>;
+
+/// Shared per-unit MIDI effect chains, swappable from any thread.
+#[cfg(feature = "midi")]
+pub(crate) type MidiEffectSlot = Arc<ArcSwap<MidiEffectRack>>;
+
 /// State for the real-time audio callback.
 /// Uses `UnsafeCell` for interior mutability. Only access from the audio thread.
 pub(crate) struct AudioCallbackState {
@@ -37,7 +54,6 @@ pub(crate) struct AudioCallbackState {
     net_backend: UnsafeCell<Option<NetBackend>>,
     pub(crate) metering: Arc<MeteringManager>,
     pub(crate) sample_position: AtomicU64,
-    #[allow(dead_code)]
     pub(crate) sample_rate: f64,
 
     /// Click node for metronome - mixed into output automatically
@@ -46,6 +62,12 @@ pub(crate) struct AudioCallbackState {
     /// Transport clock - ticked per-sample for sample-accurate position
     transport_clock: UnsafeCell<Option<TransportClock>>,
 
+    /// MIDI Clock / MTC generator fed with per-frame transport positions
+    sync_output: SyncOutputSlot,
+
+    /// Pre-allocated per-frame beats for `sync_output`. Audio thread only.
+    sync_beats: UnsafeCell<Vec<f64>>,
+
     /// MIDI input source (hardware/virtual ports) - optional
     #[cfg(feature = "midi")]
     midi_input: Option<Arc<dyn MidiInputSource>>,
@@ -58,6 +80,15 @@ pub(crate) struct AudioCallbackState {
     #[cfg(feature = "midi")]
     midi_routing: Arc<ArcSwap<MidiRoutingSnapshot>>,
 
+    /// MIDI file player / sequencer fed with each frame's transport position
+    #[cfg(feature = "midi")]
+    transport_midi: TransportMidiSlot,
+
+    /// Per-unit MIDI effect chains, run after everything else has queued
+    /// the frame's MIDI
+    #[cfg(feature = "midi")]
+    midi_effects: MidiEffectSlot,
+
     /// Pre-allocated buffer for sorted MIDI events: (frame_offset, port, event).
     /// Only accessed from the audio thread.
     #[cfg(feature = "midi")]
@@ -81,6 +112,8 @@ impl AudioCallbackState {
             sample_rate,
             click_node: UnsafeCell::new(None),
             transport_clock: UnsafeCell::new(None),
+            sync_output: Arc::new(ArcSwapOption::empty()),
+            sync_beats: UnsafeCell::new(Vec::with_capacity(SYNC_BEAT_BUFFER_CAPACITY)),
             #[cfg(feature = "midi")]
             midi_input: None,
             #[cfg(feature = "midi")]
@@ -88,6 +121,10 @@ impl AudioCallbackState {
             #[cfg(feature = "midi")]
             midi_routing: Arc::new(ArcSwap::from_pointee(MidiRoutingSnapshot::empty())),
             #[cfg(feature = "midi")]
+            transport_midi: Arc::new(ArcSwapOption::empty()),
+            #[cfg(feature = "midi")]
+            midi_effects: Arc::new(ArcSwap::from_pointee(MidiEffectRack::default())),
+            #[cfg(feature = "midi")]
             midi_event_buffer: UnsafeCell::new(Vec::with_capacity(MIDI_EVENT_BUFFER_CAPACITY)),
         }
     }
@@ -118,6 +155,10 @@ impl AudioCallbackState {
         &mut *self.transport_clock.get()
     }
 
+    pub(crate) fn set_sync_output(&mut self, slot: SyncOutputSlot) {
+        self.sync_output = slot;
+    }
+
     pub(crate) fn set_net_backend(&mut self, backend: NetBackend) {
         unsafe { *self.net_backend.get() = Some(backend) }
     }
@@ -137,6 +178,16 @@ impl AudioCallbackState {
         self.midi_routing = routing;
     }
 
+    #[cfg(feature = "midi")]
+    pub(crate) fn set_transport_midi(&mut self, slot: TransportMidiSlot) {
+        self.transport_midi = slot;
+    }
+
+    #[cfg(feature = "midi")]
+    pub(crate) fn set_midi_effects(&mut self, slot: MidiEffectSlot) {
+        self.midi_effects = slot;
+    }
+
     #[inline]
     #[allow(clippy::mut_from_ref)]
     pub(crate) unsafe fn net_backend_mut(&self) -> &mut Option<NetBackend> {
@@ -200,7 +251,27 @@ fn process_audio_inner(state: &AudioCallbackState, output: &mut [f32], buffer_st
     let transport_clock = unsafe { state.transport_clock_mut() };
     let paused = state.transport.is_paused();
 
-    let mut clock_output = [0.0f32];
+    let sync_output = state.sync_output.load();
+    let sync_beats = unsafe { &mut *state.sync_beats.get() };
+    sync_beats.clear();
+    let record_beats = sync_output.is_some() && frames <= sync_beats.capacity();
+
+    #[cfg(feature = "midi")]
+    let transport_midi = state.transport_midi.load();
+    #[cfg(feature = "midi")]
+    let transport_midi = transport_midi
+        .as_ref()
+        .zip(state.midi_registry.as_ref())
+        .map(|(source, registry)| (source.as_ref().as_ref(), registry));
+    #[cfg(feature = "midi")]
+    let midi_effects = state.midi_effects.load();
+    #[cfg(feature = "midi")]
+    let midi_effects = state
+        .midi_registry
+        .as_ref()
+        .filter(|_| !midi_effects.hosts().is_empty())
+        .map(|registry| (midi_effects.hosts(), registry));
+
     let mut segment_start = 0;
     let mut split_idx = 0;
 
@@ -224,7 +295,26 @@ fn process_audio_inner(state: &AudioCallbackState, output: &mut [f32], buffer_st
                     // Tick transport clock BEFORE graph — updates current_beat atomic
                     // so graph nodes see per-sample-accurate position
                     if let Some(ref mut clock) = transport_clock {
-                        clock.tick(&[], &mut clock_output);
+                        let beat = clock.next_beat();
+                        if record_beats {
+                            sync_beats.push(beat);
+                        }
+
+                        // Transport-driven MIDI lands in the registry before
+                        // the graph renders this frame
+                        #[cfg(feature = "midi")]
+                        if let Some((source, registry)) = transport_midi {
+                            source.process_frame(beat, !paused, registry);
+                        }
+
+                        // Effect chains see everything queued for their unit
+                        // this frame, including the transport source's output
+                        #[cfg(feature = "midi")]
+                        if let Some((hosts, registry)) = midi_effects {
+                            for host in hosts {
+                                host.process_frame(beat, !paused, registry);
+                            }
+                        }
                     }
 
                     let (l, r) = backend.get_stereo();
@@ -248,7 +338,15 @@ fn process_audio_inner(state: &AudioCallbackState, output: &mut [f32], buffer_st
                 // No graph backend — still tick the transport clock to keep position advancing
                 if let Some(ref mut clock) = transport_clock {
                     for _ in 0..segment_frames {
-                        clock.tick(&[], &mut clock_output);
+                        let beat = clock.next_beat();
+                        if record_beats {
+                            sync_beats.push(beat);
+                        }
+
+                        #[cfg(feature = "midi")]
+                        if let Some((source, registry)) = transport_midi {
+                            source.process_frame(beat, !paused, registry);
+                        }
                     }
                 }
             }
@@ -262,6 +360,18 @@ fn process_audio_inner(state: &AudioCallbackState, output: &mut [f32], buffer_st
         split_idx += 1;
     }
 
+    // 5. Hand the block's per-frame positions to the sync generator
+    if let Some(output) = sync_output.as_ref() {
+        if !sync_beats.is_empty() {
+            output.process_block(&SyncBlock {
+                beats: sync_beats,
+                playing: !paused,
+                sample_rate: state.sample_rate,
+                buffer_start,
+            });
+        }
+    }
+
     state
         .sample_position
         .fetch_add(frames as u64, Ordering::Relaxed);
@@ -320,7 +430,7 @@ fn route_midi_events_in_range(state: &AudioCallbackState, start: usize, end: usi
             break; // Buffer is sorted by offset
         }
         if offset >= start {
-            for target in routing.route(port, &event) {
+            for (target, event) in routing.route_events(port, &event) {
                 midi_registry.queue(target, &[event]);
             }
         }
@@ -407,6 +517,54 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "midi")]
+    #[test]
+    fn test_transport_midi_source_sees_every_frame() {
+        struct RecordingSource(std::sync::Mutex<Vec<(f64, bool)>>);
+
+        impl TransportMidiSource for RecordingSource {
+            fn process_frame(&self, beat: f64, playing: bool, registry: &MidiRegistry) {
+                self.0.lock().unwrap().push((beat, playing));
+                if beat == 0.0 {
+                    registry.queue(7, &[MidiEvent::note_on(0, 0, 60, 100)]);
+                }
+            }
+        }
+
+        let sample_rate = 48000.0;
+        let transport = Arc::new(TransportManager::new(sample_rate));
+        let metering = Arc::new(MeteringManager::new(sample_rate));
+        let mut state = AudioCallbackState::new(transport, metering, sample_rate);
+        let clock = TransportClock::new(
+            state.transport.tempo().clone(),
+            state.transport.paused().clone(),
+            sample_rate,
+        );
+        state.set_transport_clock(clock);
+        let registry = MidiRegistry::new();
+        registry.register_unit(7);
+        state.set_midi_registry(registry.clone());
+
+        let source = Arc::new(RecordingSource(Default::default()));
+        let slot: TransportMidiSlot = Arc::new(ArcSwapOption::empty());
+        slot.store(Some(Arc::new(
+            source.clone() as Arc<dyn TransportMidiSource>
+        )));
+        state.set_transport_midi(slot);
+
+        state.transport.set_paused(false);
+        state.transport.set_tempo(120.0);
+        let mut output = vec![0.0f32; 128 * 2];
+        process_audio(&state, &mut output, Instant::now());
+
+        let frames = source.0.lock().unwrap();
+        assert_eq!(frames.len(), 128);
+        assert!(frames.iter().all(|&(_, playing)| playing));
+        let step = 2.0 / sample_rate;
+        assert!((frames[127].0 - 127.0 * step).abs() < 1e-12);
+        assert!(registry.has_events(7));
+    }
+
     #[test]
     fn test_transport_clock_loop_wrapping_in_callback() {
         // Verify TransportClock handles loop wrapping per-sample
@@ -12,7 +12,7 @@ pub use alloc::{
 pub use core::{
     any,
     cell::UnsafeCell,
-    sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicU8, AtomicUsize, Ordering},
+    sync::atomic::{AtomicBool, AtomicI8, AtomicU32, AtomicU64, AtomicU8, AtomicUsize, Ordering},
 };
 
 pub use hashbrown::HashMap;
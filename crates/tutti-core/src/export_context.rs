@@ -56,6 +56,16 @@ impl ExportContext {
         }
     }
 
+    /// Context around an already configured timeline, e.g. one following a
+    /// tempo map.
+    pub fn from_timeline(timeline: ExportTimeline) -> Self {
+        Self {
+            timeline: Arc::new(timeline),
+            #[cfg(feature = "midi")]
+            midi_snapshot: MidiSnapshot::new(),
+        }
+    }
+
     #[cfg(feature = "midi")]
     pub fn with_midi_snapshot(config: ExportConfig, midi_snapshot: MidiSnapshot) -> Self {
         Self {
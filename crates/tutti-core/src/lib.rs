@@ -12,7 +12,8 @@
 //!
 //! - `"neural"`: [`NeuralNodeManager`], [`BatchingStrategy`] for GPU-accelerated audio
 //! - `"midi"`: [`MidiRegistry`], [`MidiEvent`] for MIDI routing
-//! - `"std"`: CPAL audio I/O (enabled by default)
+//! - `"std"`: CPAL audio I/O (enabled by default), plus the headless
+//!   [`AudioBackend::Null`] for machines without audio hardware
 //!
 //! # Example
 //!
@@ -51,18 +52,23 @@ pub use net_frontend::{NodeInfo, TuttiNet};
 
 pub(crate) mod transport;
 pub use transport::{
-    click, AutomationEnvelopeFn, AutomationReaderInput, ClickNode, ClickSettings, ClickState,
-    Direction, ExportConfig, ExportTimeline, MetronomeHandle, MetronomeMode, MotionState,
-    SmpteFrameRate, SyncSnapshot, SyncSource, SyncState, SyncStatus, TempoMap, TimeSignature,
-    TransportClock, TransportHandle, TransportManager, TransportReader, BBT,
+    click, ltc_generator, ltc_reader, AutomationEnvelopeFn, AutomationReaderInput, ClickNode,
+    ClickSettings, ClickState, Direction, ExportConfig, ExportTimeline, LtcDecoder, LtcEncoder,
+    LtcFrame, LtcGenerator, LtcReader, LtcReaderConfig, MeterChange, MetronomeHandle,
+    MetronomeMode, MotionState, SmpteFrameRate, SyncSnapshot, SyncSource, SyncState, SyncStatus,
+    TempoCurve, TempoMap, TempoMapSnapshot, TimeSignature, Timecode, TransportClock,
+    TransportHandle, TransportManager, TransportReader, BBT,
 };
+#[cfg(feature = "std")]
+pub use transport::{SyncBlock, TransportSyncOutput};
 
 mod export_context;
 pub use export_context::ExportContext;
 
 pub(crate) mod metering;
 pub use metering::{
-    analyze_loudness, analyze_true_peak, AtomicAmplitude, AtomicStereoAnalysis, CpuMeter,
+    analyze_loudness, analyze_loudness_multichannel, analyze_true_peak,
+    analyze_true_peak_multichannel, AtomicAmplitude, AtomicStereoAnalysis, ChannelWeight, CpuMeter,
     CpuMetrics, LoudnessResult, MeteringHandle, MeteringManager, StereoAnalysisSnapshot,
 };
 
@@ -116,14 +122,21 @@ pub(crate) mod callback;
 #[cfg(feature = "std")]
 pub(crate) mod output;
 
+#[cfg(feature = "std")]
+pub(crate) mod null_output;
+#[cfg(feature = "std")]
+pub use null_output::{AudioBackend, NullBackendConfig, NullPacing, OutputTap};
+
 #[cfg(feature = "midi")]
 pub mod midi;
 
 #[cfg(feature = "midi")]
 pub use midi::{
-    Channel, ChannelVoiceMsg, ControlChange, MidiEvent, MidiEventBuilder, MidiInputSource, MidiMsg,
-    MidiRegistry, MidiRoute, MidiRoutingSnapshot, MidiRoutingTable, MidiSnapshot,
-    MidiSnapshotReader, MidiSource, NoMidiInput, RawMidiEvent, TimedMidiEvent,
+    ArpMode, Arpeggiator, Channel, ChannelVoiceMsg, ChordGenerator, ControlChange, MidiEffect,
+    MidiEvent, MidiEventBuilder, MidiInputSource, MidiMsg, MidiRegistry, MidiRoute,
+    MidiRoutingSnapshot, MidiRoutingTable, MidiSnapshot, MidiSnapshotReader, MidiSource,
+    MidiTransform, NoMidiInput, NoteRepeat, OctaveShift, RawMidiEvent, TimedMidiEvent,
+    TransportMidiSource, VelocityCurve,
 };
 
 #[cfg(feature = "neural")]
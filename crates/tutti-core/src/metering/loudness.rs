@@ -5,7 +5,7 @@
 
 extern crate alloc;
 use alloc::vec::Vec;
-use ebur128::{EbuR128, Mode};
+use ebur128::{Channel, EbuR128, Mode};
 
 /// Result of loudness analysis.
 #[derive(Debug, Clone, Copy)]
@@ -18,52 +18,112 @@ pub struct LoudnessResult {
     pub loudness_range_lu: f64,
 }
 
+/// Per-channel weighting for BS.1770 loudness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelWeight {
+    /// Front channels (L, R, C), heights and anything else at full weight.
+    Unity,
+    /// Side surrounds (azimuth 60-120°), weighted +1.5 dB.
+    Surround,
+    /// Left out of the measurement (LFE).
+    Excluded,
+}
+
+impl ChannelWeight {
+    fn to_ebur128(self) -> Channel {
+        match self {
+            ChannelWeight::Unity => Channel::Center,
+            ChannelWeight::Surround => Channel::LeftSurround,
+            ChannelWeight::Excluded => Channel::Unused,
+        }
+    }
+}
+
 /// One-shot EBU R128 loudness analysis for offline processing.
 /// For real-time metering, use `MeteringManager` instead.
 pub fn analyze_loudness(left: &[f32], right: &[f32], sample_rate: u32) -> LoudnessResult {
-    let mut meter = EbuR128::new(2, sample_rate, Mode::I | Mode::LRA | Mode::TRUE_PEAK)
-        .expect("Failed to create EBU R128 meter");
+    analyze_loudness_multichannel(
+        &[left, right],
+        &[ChannelWeight::Unity, ChannelWeight::Unity],
+        sample_rate,
+    )
+}
+
+/// [`analyze_loudness`] for any channel count, each channel weighted as
+/// BS.1770 specifies for its speaker position. True peak covers every
+/// channel, including excluded ones.
+pub fn analyze_loudness_multichannel(
+    channels: &[&[f32]],
+    weights: &[ChannelWeight],
+    sample_rate: u32,
+) -> LoudnessResult {
+    let silent = LoudnessResult {
+        integrated_lufs: -70.0,
+        true_peak_dbtp: -144.0,
+        loudness_range_lu: 0.0,
+    };
+    if channels.is_empty() {
+        return silent;
+    }
 
-    let len = left.len().min(right.len());
+    let mut meter = EbuR128::new(
+        channels.len() as u32,
+        sample_rate,
+        Mode::I | Mode::LRA | Mode::TRUE_PEAK,
+    )
+    .expect("Failed to create EBU R128 meter");
+
+    let map: Vec<Channel> = (0..channels.len())
+        .map(|ch| {
+            weights
+                .get(ch)
+                .copied()
+                .unwrap_or(ChannelWeight::Unity)
+                .to_ebur128()
+        })
+        .collect();
+    let _ = meter.set_channel_map(&map);
+
+    let len = channels.iter().map(|c| c.len()).min().unwrap_or(0);
     if len > 0 {
-        let frames_data: Vec<&[f32]> = vec![&left[..len], &right[..len]];
+        let frames_data: Vec<&[f32]> = channels.iter().map(|c| &c[..len]).collect();
         let _ = meter.add_frames_planar_f32(&frames_data);
     }
 
-    let integrated_lufs = meter.loudness_global().unwrap_or(-70.0);
-    let loudness_range_lu = meter.loudness_range().unwrap_or(0.0);
-
-    let true_peak_l = meter.true_peak(0).unwrap_or(0.0);
-    let true_peak_r = meter.true_peak(1).unwrap_or(0.0);
-    let true_peak_linear = true_peak_l.max(true_peak_r);
-
-    let true_peak_dbtp = if true_peak_linear > 0.0 {
-        20.0 * true_peak_linear.log10()
-    } else {
-        -144.0
-    };
-
     LoudnessResult {
-        integrated_lufs,
-        true_peak_dbtp,
-        loudness_range_lu,
+        integrated_lufs: meter.loudness_global().unwrap_or(silent.integrated_lufs),
+        true_peak_dbtp: peak_dbtp(&meter, channels.len()),
+        loudness_range_lu: meter.loudness_range().unwrap_or(0.0),
     }
 }
 
 /// Returns true peak level in dBTP (uses 4x oversampling).
 pub fn analyze_true_peak(left: &[f32], right: &[f32]) -> f64 {
-    let mut meter =
-        EbuR128::new(2, 48000, Mode::TRUE_PEAK).expect("Failed to create EBU R128 meter for peak");
+    analyze_true_peak_multichannel(&[left, right])
+}
+
+/// [`analyze_true_peak`] across any number of channels.
+pub fn analyze_true_peak_multichannel(channels: &[&[f32]]) -> f64 {
+    if channels.is_empty() {
+        return -144.0;
+    }
+
+    let mut meter = EbuR128::new(channels.len() as u32, 48000, Mode::TRUE_PEAK)
+        .expect("Failed to create EBU R128 meter for peak");
 
-    let len = left.len().min(right.len());
+    let len = channels.iter().map(|c| c.len()).min().unwrap_or(0);
     if len > 0 {
-        let frames_data: Vec<&[f32]> = vec![&left[..len], &right[..len]];
+        let frames_data: Vec<&[f32]> = channels.iter().map(|c| &c[..len]).collect();
         let _ = meter.add_frames_planar_f32(&frames_data);
     }
 
-    let true_peak_l = meter.true_peak(0).unwrap_or(0.0);
-    let true_peak_r = meter.true_peak(1).unwrap_or(0.0);
-    let true_peak_linear = true_peak_l.max(true_peak_r);
+    peak_dbtp(&meter, channels.len())
+}
+
+fn peak_dbtp(meter: &EbuR128, channels: usize) -> f64 {
+    let true_peak_linear = (0..channels as u32)
+        .map(|ch| meter.true_peak(ch).unwrap_or(0.0))
+        .fold(0.0, f64::max);
 
     if true_peak_linear > 0.0 {
         20.0 * true_peak_linear.log10()
@@ -113,4 +173,35 @@ mod tests {
             result.integrated_lufs
         );
     }
+
+    #[test]
+    fn test_surround_weighting() {
+        let sample_rate = 48000;
+        let tone: Vec<f32> = (0..sample_rate as usize * 2)
+            .map(|i| 0.25 * (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / 48000.0).sin())
+            .collect();
+        let silence = vec![0.0f32; tone.len()];
+
+        let front = analyze_loudness_multichannel(
+            &[&tone, &silence],
+            &[ChannelWeight::Unity, ChannelWeight::Surround],
+            sample_rate,
+        );
+        let surround = analyze_loudness_multichannel(
+            &[&silence, &tone],
+            &[ChannelWeight::Unity, ChannelWeight::Surround],
+            sample_rate,
+        );
+        let lfe = analyze_loudness_multichannel(
+            &[&silence, &tone],
+            &[ChannelWeight::Unity, ChannelWeight::Excluded],
+            sample_rate,
+        );
+
+        let boost = surround.integrated_lufs - front.integrated_lufs;
+        assert!((boost - 1.5).abs() < 0.1, "Surround boost: {}", boost);
+        assert!(lfe.integrated_lufs <= -70.0);
+        // Peak still sees the excluded channel
+        assert!(lfe.true_peak_dbtp > -13.0);
+    }
 }
@@ -15,7 +15,10 @@ mod stereo;
 pub use amplitude::AtomicAmplitude;
 pub use cpu::{CpuMeter, CpuMetrics};
 pub use handle::MeteringHandle;
-pub use loudness::{analyze_loudness, analyze_true_peak, LoudnessResult};
+pub use loudness::{
+    analyze_loudness, analyze_loudness_multichannel, analyze_true_peak,
+    analyze_true_peak_multichannel, ChannelWeight, LoudnessResult,
+};
 pub use manager::MeteringManager;
 #[cfg(feature = "std")]
 pub(crate) use rt::MeteringContext;
@@ -0,0 +1,409 @@
+//! Transport-synced arpeggiator.
+
+use super::MidiEffect;
+use crate::compat::Vec;
+use tutti_midi::MidiEvent;
+
+/// Most keys the arpeggiator tracks at once; further note-ons are ignored.
+const MAX_HELD: usize = 32;
+
+/// Order in which held notes are stepped through.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ArpMode {
+    #[default]
+    Up,
+    Down,
+    /// Up then down, without repeating the top and bottom notes.
+    UpDown,
+    /// A random held note each step. The choice depends only on the step's
+    /// grid position, so renders repeat exactly.
+    Random,
+    /// The order the keys were pressed.
+    AsPlayed,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct HeldNote {
+    channel: u8,
+    note: u8,
+    velocity: u8,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Sounding {
+    channel: u8,
+    note: u8,
+    off_beat: f64,
+}
+
+/// Steps through the held notes on a beat grid.
+///
+/// Step `k` falls on beat `k * rate`, with odd steps pushed late by `swing`.
+/// A new run starts on the first grid step at or after the first key. Other
+/// messages pass straight through.
+///
+/// ```ignore
+/// let arp = Arpeggiator::new()
+///     .mode(ArpMode::UpDown)
+///     .rate(0.25) // sixteenths
+///     .octaves(2)
+///     .gate(0.5)
+///     .swing(0.2);
+/// ```
+#[derive(Debug)]
+pub struct Arpeggiator {
+    mode: ArpMode,
+    rate: f64,
+    octaves: u8,
+    gate: f64,
+    swing: f64,
+    /// Held keys in the order they were pressed
+    played: Vec<HeldNote>,
+    /// Held keys sorted by note
+    sorted: Vec<HeldNote>,
+    /// Grid index of the next step, `None` while idle
+    next_step: Option<i64>,
+    /// Grid index of the current run's first step
+    first_step: i64,
+    sounding: Option<Sounding>,
+}
+
+impl Arpeggiator {
+    pub fn new() -> Self {
+        Self {
+            mode: ArpMode::Up,
+            rate: 0.25,
+            octaves: 1,
+            gate: 0.5,
+            swing: 0.0,
+            played: Vec::with_capacity(MAX_HELD),
+            sorted: Vec::with_capacity(MAX_HELD),
+            next_step: None,
+            first_step: 0,
+            sounding: None,
+        }
+    }
+
+    pub fn mode(mut self, mode: ArpMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Step length in beats (0.25 = sixteenth notes at 4/4).
+    pub fn rate(mut self, beats: f64) -> Self {
+        self.rate = beats.max(1.0 / 256.0);
+        self
+    }
+
+    /// Number of octaves the pattern spans (1-4).
+    pub fn octaves(mut self, octaves: u8) -> Self {
+        self.octaves = octaves.clamp(1, 4);
+        self
+    }
+
+    /// Note length as a fraction of the step (0.01-1.0).
+    pub fn gate(mut self, gate: f64) -> Self {
+        self.gate = gate.clamp(0.01, 1.0);
+        self
+    }
+
+    /// How late odd steps play, as a fraction of the step (0.0-0.5).
+    pub fn swing(mut self, swing: f64) -> Self {
+        self.swing = swing.clamp(0.0, 0.5);
+        self
+    }
+
+    fn step_beat(&self, step: i64) -> f64 {
+        let swing = if step.rem_euclid(2) == 1 {
+            self.swing
+        } else {
+            0.0
+        };
+        (step as f64 + swing) * self.rate
+    }
+
+    fn hold(&mut self, channel: u8, note: u8, velocity: u8) {
+        self.unhold(channel, note);
+        if self.played.len() >= MAX_HELD {
+            return;
+        }
+        let key = HeldNote {
+            channel,
+            note,
+            velocity,
+        };
+        self.played.push(key);
+        let at = self.sorted.partition_point(|held| held.note <= note);
+        self.sorted.insert(at, key);
+    }
+
+    fn unhold(&mut self, channel: u8, note: u8) {
+        let is_key = |held: &HeldNote| held.channel == channel && held.note == note;
+        if let Some(i) = self.played.iter().position(is_key) {
+            self.played.remove(i);
+        }
+        if let Some(i) = self.sorted.iter().position(is_key) {
+            self.sorted.remove(i);
+        }
+    }
+
+    /// Note to play at grid step `step`. `None` if nothing is held.
+    fn pick(&self, step: i64) -> Option<(HeldNote, u8)> {
+        let keys = match self.mode {
+            ArpMode::AsPlayed => &self.played,
+            _ => &self.sorted,
+        };
+        let count = keys.len();
+        if count == 0 {
+            return None;
+        }
+
+        let length = count * self.octaves as usize;
+        let position = (step - self.first_step).max(0) as usize;
+        let index = match self.mode {
+            ArpMode::Up | ArpMode::AsPlayed => position % length,
+            ArpMode::Down => length - 1 - position % length,
+            ArpMode::UpDown => {
+                let period = (2 * length).saturating_sub(2).max(1);
+                let phase = position % period;
+                if phase < length {
+                    phase
+                } else {
+                    period - phase
+                }
+            }
+            ArpMode::Random => (split_mix(step as u64) % length as u64) as usize,
+        };
+
+        let key = keys[index % count];
+        let shifted = key.note as usize + 12 * (index / count);
+        let note = if shifted <= 127 {
+            shifted as u8
+        } else {
+            key.note
+        };
+        Some((key, note))
+    }
+
+    fn end_sounding(&mut self, output: &mut dyn FnMut(MidiEvent)) {
+        if let Some(sounding) = self.sounding.take() {
+            output(MidiEvent::note_off(0, sounding.channel, sounding.note, 0));
+        }
+    }
+}
+
+// Manual so copies keep their full capacity and never allocate on the audio thread
+impl Clone for Arpeggiator {
+    fn clone(&self) -> Self {
+        let mut played = Vec::with_capacity(MAX_HELD);
+        played.extend_from_slice(&self.played);
+        let mut sorted = Vec::with_capacity(MAX_HELD);
+        sorted.extend_from_slice(&self.sorted);
+        Self {
+            played,
+            sorted,
+            ..*self
+        }
+    }
+}
+
+impl Default for Arpeggiator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MidiEffect for Arpeggiator {
+    fn process(&mut self, beat: f64, input: &[MidiEvent], output: &mut dyn FnMut(MidiEvent)) {
+        for event in input {
+            if event.is_note_on() {
+                let (note, velocity) = (event.note().unwrap_or(0), event.velocity().unwrap_or(0));
+                self.hold(event.channel_num(), note, velocity);
+            } else if event.is_note_off() {
+                self.unhold(event.channel_num(), event.note().unwrap_or(0));
+            } else {
+                output(*event);
+            }
+        }
+
+        if self.next_step.is_none() && !self.played.is_empty() {
+            let mut step = (beat / self.rate - 1e-9).ceil() as i64;
+            // A swung step just before the straight grid line may still be ahead
+            if self.step_beat(step - 1) >= beat {
+                step -= 1;
+            }
+            self.next_step = Some(step);
+            self.first_step = step;
+        }
+
+        loop {
+            let off = self.sounding.map(|s| s.off_beat);
+            let step = self.next_step.map(|k| (k, self.step_beat(k)));
+
+            match (off, step) {
+                (Some(off), step) if off <= beat && step.is_none_or(|(_, at)| off <= at) => {
+                    self.end_sounding(output);
+                }
+                (_, Some((k, at))) if at <= beat => {
+                    self.end_sounding(output);
+                    match self.pick(k) {
+                        Some((key, note)) => {
+                            output(MidiEvent::note_on(0, key.channel, note, key.velocity));
+                            self.sounding = Some(Sounding {
+                                channel: key.channel,
+                                note,
+                                off_beat: at + self.gate * self.rate,
+                            });
+                            self.next_step = Some(k + 1);
+                        }
+                        None => self.next_step = None,
+                    }
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn next_event_beat(&self) -> Option<f64> {
+        let off = self.sounding.map(|s| s.off_beat);
+        let step = self.next_step.map(|k| self.step_beat(k));
+        match (off, step) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    fn release(&mut self, output: &mut dyn FnMut(MidiEvent)) {
+        self.end_sounding(output);
+        self.next_step = None;
+    }
+}
+
+/// SplitMix64 finalizer: a well-mixed hash of the step index.
+fn split_mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Drive the arpeggiator on a 1/64-beat grid and collect `(beat, note-on note)`.
+    fn run(arp: &mut Arpeggiator, keys: &[u8], until: f64) -> Vec<(f64, u8)> {
+        let input: Vec<_> = keys
+            .iter()
+            .map(|&note| MidiEvent::note_on(0, 0, note, 100))
+            .collect();
+        let mut ons = Vec::new();
+        let mut beat = 0.0;
+        let mut first = true;
+        while beat < until {
+            let frame_input: &[MidiEvent] = if first { &input[..] } else { &[] };
+            arp.process(beat, frame_input, &mut |event| {
+                if event.is_note_on() {
+                    ons.push((beat, event.note().unwrap()));
+                }
+            });
+            first = false;
+            beat += 1.0 / 64.0;
+        }
+        ons
+    }
+
+    fn notes(ons: &[(f64, u8)]) -> Vec<u8> {
+        ons.iter().map(|&(_, note)| note).collect()
+    }
+
+    #[test]
+    fn test_modes() {
+        let mut up = Arpeggiator::new().octaves(2);
+        assert_eq!(
+            notes(&run(&mut up, &[64, 60], 2.0)),
+            vec![60, 64, 72, 76, 60, 64, 72, 76]
+        );
+
+        let mut down = Arpeggiator::new().mode(ArpMode::Down);
+        assert_eq!(
+            notes(&run(&mut down, &[60, 64, 67], 1.0)),
+            vec![67, 64, 60, 67]
+        );
+
+        let mut up_down = Arpeggiator::new().mode(ArpMode::UpDown);
+        assert_eq!(
+            notes(&run(&mut up_down, &[60, 64, 67], 1.5)),
+            vec![60, 64, 67, 64, 60, 64]
+        );
+
+        let mut as_played = Arpeggiator::new().mode(ArpMode::AsPlayed);
+        assert_eq!(
+            notes(&run(&mut as_played, &[67, 60, 64], 0.75)),
+            vec![67, 60, 64]
+        );
+
+        let random = |arp: &mut Arpeggiator| notes(&run(arp, &[60, 64, 67, 72], 4.0));
+        let a = random(&mut Arpeggiator::new().mode(ArpMode::Random));
+        assert_eq!(a, random(&mut Arpeggiator::new().mode(ArpMode::Random)));
+        assert!(a.iter().all(|n| [60, 64, 67, 72].contains(n)));
+    }
+
+    #[test]
+    fn test_swing_and_gate() {
+        let mut arp = Arpeggiator::new().rate(0.5).swing(0.5).gate(0.25);
+        let mut events = Vec::new();
+        let mut beat = 0.0;
+        let keys = [MidiEvent::note_on(0, 0, 60, 100)];
+        while beat < 2.0 {
+            let input: &[MidiEvent] = if beat == 0.0 { &keys } else { &[] };
+            arp.process(beat, input, &mut |event| {
+                events.push((beat, event.is_note_on()))
+            });
+            beat += 1.0 / 64.0;
+        }
+        assert_eq!(
+            events,
+            vec![
+                (0.0, true),
+                (0.125, false),
+                (0.75, true),
+                (0.875, false),
+                (1.0, true),
+                (1.125, false),
+                (1.75, true),
+                (1.875, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_release_and_passthrough() {
+        let mut arp = Arpeggiator::new();
+        let mut out = Vec::new();
+        let cc = MidiEvent::control_change(0, 0, 1, 20);
+        arp.process(0.0, &[MidiEvent::note_on(0, 0, 60, 100), cc], &mut |e| {
+            out.push(e)
+        });
+        assert_eq!(out, vec![cc, MidiEvent::note_on(0, 0, 60, 100)]);
+
+        out.clear();
+        arp.release(&mut |e| out.push(e));
+        assert_eq!(out, vec![MidiEvent::note_off(0, 0, 60, 0)]);
+        assert_eq!(arp.next_event_beat(), None);
+
+        // Key still held: the run restarts on the grid
+        out.clear();
+        arp.process(8.1, &[], &mut |e| out.push(e));
+        assert!(out.is_empty());
+        assert_eq!(arp.next_event_beat(), Some(8.25));
+
+        // Releasing the key ends the run at the next step
+        arp.process(8.2, &[MidiEvent::note_off(0, 0, 60, 0)], &mut |e| {
+            out.push(e)
+        });
+        arp.process(8.25, &[], &mut |e| out.push(e));
+        assert!(out.is_empty());
+        assert_eq!(arp.next_event_beat(), None);
+    }
+}
@@ -0,0 +1,178 @@
+//! Chord generator / chord memory.
+
+use super::MidiEffect;
+use crate::compat::{Box, Vec};
+use tutti_midi::MidiEvent;
+
+/// Most notes in one chord.
+const MAX_CHORD_NOTES: usize = 12;
+
+/// Plays a fixed chord shape on every key.
+///
+/// Each note-on becomes one note-on per interval, transposed to the key.
+/// Overlapping chords that share a pitch are reference counted, so a shared
+/// note only stops when the last key using it is released. Other messages
+/// pass straight through.
+///
+/// ```ignore
+/// let minor7 = ChordGenerator::new(&[0, 3, 7, 10]);
+/// // Chord memory: store a voicing, play it from any key
+/// let voicing = ChordGenerator::from_notes(&[48, 55, 64, 67]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ChordGenerator {
+    intervals: Vec<i8>,
+    /// Keys currently sounding each output pitch, indexed by `channel * 128 + note`
+    counts: Box<[u8; 16 * 128]>,
+}
+
+impl ChordGenerator {
+    /// Semitone offsets from the played key (include 0 to keep the key itself).
+    pub fn new(intervals: &[i8]) -> Self {
+        let mut unique: Vec<i8> = Vec::with_capacity(MAX_CHORD_NOTES);
+        for &interval in intervals {
+            if unique.len() < MAX_CHORD_NOTES && !unique.contains(&interval) {
+                unique.push(interval);
+            }
+        }
+        Self {
+            intervals: unique,
+            counts: Box::new([0; 16 * 128]),
+        }
+    }
+
+    pub fn major() -> Self {
+        Self::new(&[0, 4, 7])
+    }
+
+    pub fn minor() -> Self {
+        Self::new(&[0, 3, 7])
+    }
+
+    /// Chord memory: the shape of `notes` relative to the lowest one, played
+    /// from whichever key is pressed.
+    pub fn from_notes(notes: &[u8]) -> Self {
+        let root = notes.iter().copied().min().unwrap_or(0) as i16;
+        let intervals: Vec<i8> = notes
+            .iter()
+            .map(|&note| (note as i16 - root).clamp(0, 127) as i8)
+            .collect();
+        Self::new(&intervals)
+    }
+
+    pub fn intervals(&self) -> &[i8] {
+        &self.intervals
+    }
+}
+
+impl MidiEffect for ChordGenerator {
+    fn process(&mut self, _beat: f64, input: &[MidiEvent], output: &mut dyn FnMut(MidiEvent)) {
+        let Self { intervals, counts } = self;
+
+        for event in input {
+            let channel = event.channel_num();
+            let Some(key) = event
+                .note()
+                .filter(|_| event.is_note_on() || event.is_note_off())
+            else {
+                output(*event);
+                continue;
+            };
+
+            let base = (channel as usize & 0x0F) * 128;
+            let velocity = event.velocity().unwrap_or(0);
+            for note in intervals.iter().filter_map(|&i| transpose(key, i)) {
+                let count = &mut counts[base + note as usize];
+                if event.is_note_on() {
+                    if *count == 0 {
+                        output(MidiEvent::note_on(0, channel, note, velocity));
+                    }
+                    *count = count.saturating_add(1);
+                } else {
+                    if *count == 1 {
+                        output(MidiEvent::note_off(0, channel, note, 0));
+                    }
+                    *count = count.saturating_sub(1);
+                }
+            }
+        }
+    }
+
+    fn release(&mut self, output: &mut dyn FnMut(MidiEvent)) {
+        for (index, count) in self.counts.iter_mut().enumerate() {
+            if *count > 0 {
+                *count = 0;
+                output(MidiEvent::note_off(
+                    0,
+                    (index / 128) as u8,
+                    (index % 128) as u8,
+                    0,
+                ));
+            }
+        }
+    }
+}
+
+fn transpose(note: u8, interval: i8) -> Option<u8> {
+    let shifted = note as i16 + interval as i16;
+    (0..=127).contains(&shifted).then_some(shifted as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(chord: &mut ChordGenerator, input: &[MidiEvent]) -> Vec<MidiEvent> {
+        let mut out = Vec::new();
+        chord.process(0.0, input, &mut |e| out.push(e));
+        out
+    }
+
+    #[test]
+    fn test_chord_and_shared_notes() {
+        let mut chord = ChordGenerator::major();
+        let c = process(&mut chord, &[MidiEvent::note_on(0, 1, 60, 90)]);
+        assert_eq!(
+            c,
+            vec![
+                MidiEvent::note_on(0, 1, 60, 90),
+                MidiEvent::note_on(0, 1, 64, 90),
+                MidiEvent::note_on(0, 1, 67, 90),
+            ]
+        );
+
+        // E major shares E (64) with C major; it isn't restarted
+        let e = process(&mut chord, &[MidiEvent::note_on(0, 1, 64, 90)]);
+        assert_eq!(
+            e,
+            vec![
+                MidiEvent::note_on(0, 1, 68, 90),
+                MidiEvent::note_on(0, 1, 71, 90)
+            ]
+        );
+
+        // Releasing C keeps the shared E sounding
+        let off = process(&mut chord, &[MidiEvent::note_off(0, 1, 60, 0)]);
+        assert_eq!(
+            off,
+            vec![
+                MidiEvent::note_off(0, 1, 60, 0),
+                MidiEvent::note_off(0, 1, 67, 0)
+            ]
+        );
+
+        let mut released = Vec::new();
+        chord.release(&mut |e| released.push(e));
+        assert_eq!(released.len(), 3);
+        assert!(process(&mut chord, &[MidiEvent::note_off(0, 1, 64, 0)]).is_empty());
+    }
+
+    #[test]
+    fn test_chord_memory() {
+        let mut chord = ChordGenerator::from_notes(&[55, 48, 64, 48]);
+        assert_eq!(chord.intervals(), &[7, 0, 16]);
+
+        let cc = MidiEvent::control_change(0, 0, 64, 127);
+        assert_eq!(process(&mut chord, &[cc]), vec![cc]);
+    }
+}
@@ -0,0 +1,422 @@
+//! Transport-locked MIDI effects (arpeggiator, chord generator, note repeat).
+//!
+//! An effect chain is installed per target unit. On the audio thread the chain
+//! takes over the unit's [`MidiRegistry`] slot: every frame it drains whatever
+//! routing, transport sources and `queue_midi` delivered, runs it through the
+//! chain at that frame's beat, and queues the result back for the unit to
+//! read on the same frame. The unit has to drain its slot every frame, as all
+//! of Tutti's instruments do; chains only run while a graph is rendering.
+//!
+//! Effects are written against beats, not frames. Offline renders drive a
+//! fresh copy of the chain through [`render_offline`] with the same calls, so
+//! generated events land on the same beats as they do live.
+
+mod arpeggiator;
+mod chord;
+mod note_repeat;
+
+pub use arpeggiator::{ArpMode, Arpeggiator};
+pub use chord::ChordGenerator;
+pub use note_repeat::NoteRepeat;
+
+use crate::compat::{Arc, AtomicBool, Box, Ordering, UnsafeCell, Vec};
+use dyn_clone::DynClone;
+use tutti_midi::MidiEvent;
+
+use super::registry::MidiRegistry;
+use super::snapshot::TimedMidiEvent;
+
+/// Events drained from a unit's registry slot per frame (matches the slot capacity).
+const HOST_INPUT_CAPACITY: usize = 256;
+
+/// Largest per-frame advance treated as continuous playback. Anything else
+/// (backwards, or a bigger jump) is a locate or loop wrap.
+const MAX_FRAME_STEP_BEATS: f64 = 1.0 / 64.0;
+
+/// How far past the last input an offline render keeps generating while
+/// notes are still held.
+const OFFLINE_TAIL_BEATS: f64 = 64.0;
+
+/// A real-time MIDI processor locked to the transport.
+///
+/// # Safety
+///
+/// `process` and `release` run on the audio thread: no allocation, no locks,
+/// no blocking I/O. Allocate buffers up front in the constructor.
+pub trait MidiEffect: DynClone + Send {
+    /// Handle `input` arriving at `beat`, then emit everything the effect
+    /// generates at or before `beat`.
+    fn process(&mut self, beat: f64, input: &[MidiEvent], output: &mut dyn FnMut(MidiEvent));
+
+    /// Beat of the next generated event, if one is pending.
+    ///
+    /// Must be later than the `beat` of the last `process` call. Effects that
+    /// only react to input return `None`.
+    fn next_event_beat(&self) -> Option<f64> {
+        None
+    }
+
+    /// The transport stopped or jumped: end every note this effect started.
+    /// Timed effects re-align to the grid on the next `process` call.
+    fn release(&mut self, output: &mut dyn FnMut(MidiEvent));
+}
+
+dyn_clone::clone_trait_object!(MidiEffect);
+
+/// Run `input` through `effects` at `beat`, sending the chain's output on.
+fn run_chain(
+    effects: &mut [Box<dyn MidiEffect>],
+    beat: f64,
+    input: &[MidiEvent],
+    output: &mut dyn FnMut(MidiEvent),
+) {
+    match effects.split_first_mut() {
+        None => input.iter().for_each(|&event| output(event)),
+        Some((first, rest)) => {
+            first.process(beat, input, &mut |event| {
+                run_chain(rest, beat, &[event], output)
+            });
+            // Later stages advance even when the first emitted nothing
+            run_chain(rest, beat, &[], output);
+        }
+    }
+}
+
+/// Release every stage, passing each stage's note-offs through the rest.
+fn release_chain(
+    effects: &mut [Box<dyn MidiEffect>],
+    beat: f64,
+    output: &mut dyn FnMut(MidiEvent),
+) {
+    if let Some((first, rest)) = effects.split_first_mut() {
+        first.release(&mut |event| run_chain(rest, beat, &[event], output));
+        release_chain(rest, beat, output);
+    }
+}
+
+fn chain_next_event_beat(effects: &[Box<dyn MidiEffect>]) -> Option<f64> {
+    effects
+        .iter()
+        .filter_map(|effect| effect.next_event_beat())
+        .min_by(|a, b| a.total_cmp(b))
+}
+
+/// Render beat-stamped input through a copy of `effects`, the way the audio
+/// callback would play it.
+///
+/// Generation stops once the input is exhausted and nothing is pending, or
+/// 64 beats after the last input if notes are still held; anything still
+/// sounding then gets its note-off.
+pub fn render_offline(
+    effects: &[Box<dyn MidiEffect>],
+    input: &[TimedMidiEvent],
+) -> Vec<TimedMidiEvent> {
+    let mut effects: Vec<Box<dyn MidiEffect>> = effects.to_vec();
+    let mut output = Vec::new();
+    let limit = input.last().map_or(0.0, |e| e.beat) + OFFLINE_TAIL_BEATS;
+    let mut batch = Vec::new();
+    let mut next = 0;
+    let mut last_beat = f64::NEG_INFINITY;
+
+    loop {
+        let next_input = input.get(next).map(|e| e.beat);
+        let next_generated =
+            chain_next_event_beat(&effects).filter(|&beat| beat <= limit && beat > last_beat);
+        let beat = match (next_input, next_generated) {
+            (Some(a), Some(b)) => a.min(b),
+            (Some(a), None) => a,
+            (None, Some(b)) => b,
+            (None, None) => break,
+        };
+
+        batch.clear();
+        while next < input.len() && input[next].beat <= beat {
+            batch.push(input[next].event);
+            next += 1;
+        }
+
+        run_chain(&mut effects, beat, &batch, &mut |event| {
+            output.push(TimedMidiEvent { event, beat })
+        });
+        last_beat = beat;
+    }
+
+    release_chain(&mut effects, last_beat.max(0.0), &mut |event| {
+        output.push(TimedMidiEvent {
+            event,
+            beat: last_beat.max(0.0),
+        })
+    });
+    output
+}
+
+/// Audio-thread state for one unit's chain.
+struct HostState {
+    effects: Vec<Box<dyn MidiEffect>>,
+    input: Vec<MidiEvent>,
+    last_beat: Option<f64>,
+    was_playing: bool,
+}
+
+/// One unit's effect chain, driven once per frame by the audio callback.
+pub(crate) struct MidiEffectHost {
+    unit_id: u64,
+    state: UnsafeCell<HostState>,
+    /// Replaced or removed: release held notes on the next frame, then go idle
+    retired: AtomicBool,
+    /// Set by the audio thread once the retired chain's note-offs are sent
+    released: AtomicBool,
+}
+
+// SAFETY: `state` is only touched from the audio thread via `process_frame`.
+unsafe impl Sync for MidiEffectHost {}
+
+impl MidiEffectHost {
+    pub(crate) fn new(unit_id: u64, effects: Vec<Box<dyn MidiEffect>>) -> Self {
+        Self {
+            unit_id,
+            state: UnsafeCell::new(HostState {
+                effects,
+                input: vec![MidiEvent::note_off(0, 0, 0, 0); HOST_INPUT_CAPACITY],
+                last_beat: None,
+                was_playing: false,
+            }),
+            retired: AtomicBool::new(false),
+            released: AtomicBool::new(false),
+        }
+    }
+
+    pub(crate) fn unit_id(&self) -> u64 {
+        self.unit_id
+    }
+
+    fn retire(&self) {
+        self.retired.store(true, Ordering::Release);
+    }
+
+    fn is_retired(&self) -> bool {
+        self.retired.load(Ordering::Acquire)
+    }
+
+    fn is_released(&self) -> bool {
+        self.released.load(Ordering::Acquire)
+    }
+
+    /// RT-safe. Call once per frame, after everything else has queued its
+    /// MIDI for the frame and before the graph renders it.
+    #[inline]
+    pub(crate) fn process_frame(&self, beat: f64, playing: bool, registry: &MidiRegistry) {
+        let state = unsafe { &mut *self.state.get() };
+        let unit_id = self.unit_id;
+
+        // A retired chain leaves the slot to its replacement and only ends
+        // the notes it still has sounding
+        if self.is_retired() {
+            if !self.released.load(Ordering::Relaxed) {
+                release_chain(&mut state.effects, beat, &mut |event| {
+                    registry.queue(unit_id, &[event]);
+                });
+                self.released.store(true, Ordering::Release);
+            }
+            return;
+        }
+
+        // Drain before sending anything, so the chain never sees its own output
+        let count = registry.poll_into(unit_id, &mut state.input);
+        let mut send = |event: MidiEvent| registry.queue(unit_id, &[event]);
+
+        let jumped = state.last_beat.is_some_and(|last| {
+            let delta = beat - last;
+            !(0.0..=MAX_FRAME_STEP_BEATS).contains(&delta)
+        });
+        if (state.was_playing && !playing) || (playing && jumped) {
+            release_chain(&mut state.effects, beat, &mut send);
+        }
+        state.was_playing = playing;
+        state.last_beat = Some(beat);
+
+        run_chain(&mut state.effects, beat, &state.input[..count], &mut send);
+    }
+}
+
+/// Immutable set of effect hosts shared with the audio callback.
+#[derive(Default)]
+pub(crate) struct MidiEffectRack {
+    hosts: Vec<Arc<MidiEffectHost>>,
+}
+
+impl MidiEffectRack {
+    /// A rack with `unit_id`'s chain replaced. Other units keep their running
+    /// hosts (and state).
+    ///
+    /// The outgoing chain is retired rather than dropped: it stays in the rack,
+    /// after the live hosts, until the audio thread has sent note-offs for
+    /// everything it left sounding.
+    pub(crate) fn with_chain(&self, unit_id: u64, effects: Vec<Box<dyn MidiEffect>>) -> Self {
+        let (mut hosts, mut retired): (Vec<_>, Vec<_>) = self
+            .hosts
+            .iter()
+            .filter(|host| !host.is_released())
+            .cloned()
+            .partition(|host| !host.is_retired() && host.unit_id() != unit_id);
+        for host in &retired {
+            host.retire();
+        }
+        if !effects.is_empty() {
+            hosts.push(Arc::new(MidiEffectHost::new(unit_id, effects)));
+        }
+        // Retired hosts run last, so the replacement never drains their note-offs
+        hosts.append(&mut retired);
+        Self { hosts }
+    }
+
+    #[inline]
+    pub(crate) fn hosts(&self) -> &[Arc<MidiEffectHost>] {
+        &self.hosts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: f64 = 1.0 / 32768.0;
+
+    fn chain() -> Vec<Box<dyn MidiEffect>> {
+        let arp = Arpeggiator::new()
+            .mode(ArpMode::UpDown)
+            .rate(0.25)
+            .swing(0.25);
+        vec![Box::new(arp), Box::new(ChordGenerator::new(&[0, 7]))]
+    }
+
+    #[test]
+    fn test_offline_matches_realtime() {
+        let input = vec![
+            TimedMidiEvent {
+                event: MidiEvent::note_on(0, 0, 60, 100),
+                beat: 0.1,
+            },
+            TimedMidiEvent {
+                event: MidiEvent::note_on(0, 0, 64, 90),
+                beat: 0.3,
+            },
+            TimedMidiEvent {
+                event: MidiEvent::note_off(0, 0, 60, 0),
+                beat: 1.6,
+            },
+            TimedMidiEvent {
+                event: MidiEvent::note_off(0, 0, 64, 0),
+                beat: 2.2,
+            },
+        ];
+        let offline = render_offline(&chain(), &input);
+        assert!(!offline.is_empty());
+
+        // Real time: one process call per frame; each input arrives on the
+        // first frame at or after its beat.
+        let registry = MidiRegistry::new();
+        registry.register_unit(1);
+        let host = MidiEffectHost::new(1, chain());
+        let mut realtime = Vec::new();
+        let mut next = 0;
+        let mut buffer = [MidiEvent::note_off(0, 0, 0, 0); 16];
+        for frame in 0..(3.0 / STEP) as usize {
+            let beat = frame as f64 * STEP;
+            while next < input.len() && input[next].beat <= beat {
+                registry.queue(1, &[input[next].event]);
+                next += 1;
+            }
+            host.process_frame(beat, true, &registry);
+            let count = registry.poll_into(1, &mut buffer);
+            realtime.extend(buffer[..count].iter().map(|&event| (beat, event)));
+        }
+
+        assert_eq!(realtime.len(), offline.len());
+        for ((frame_beat, event), expected) in realtime.iter().zip(&offline) {
+            assert_eq!(*event, expected.event);
+            // Lands on the first frame at or after the offline beat
+            assert!(*frame_beat >= expected.beat && *frame_beat - expected.beat < STEP);
+        }
+    }
+
+    #[test]
+    fn test_stop_and_locate_release_generated_notes() {
+        let registry = MidiRegistry::new();
+        registry.register_unit(1);
+        let repeat: Box<dyn MidiEffect> = Box::new(NoteRepeat::new().rate(0.5).gate(1.0));
+        let host = MidiEffectHost::new(1, vec![repeat]);
+        let mut buffer = [MidiEvent::note_off(0, 0, 0, 0); 16];
+
+        registry.queue(1, &[MidiEvent::note_on(0, 0, 36, 100)]);
+        host.process_frame(0.0, true, &registry);
+        assert_eq!(registry.poll_into(1, &mut buffer), 1);
+        assert!(buffer[0].is_note_on());
+
+        // Locate to beat 4: the held hit is released
+        host.process_frame(4.0, true, &registry);
+        assert_eq!(registry.poll_into(1, &mut buffer), 1);
+        assert!(buffer[0].is_note_off());
+
+        // Repeats resume on the grid after the jump
+        let mut hits = 0;
+        for frame in 1..=32 {
+            host.process_frame(4.0 + frame as f64 / 64.0, true, &registry);
+            hits += registry.poll_into(1, &mut buffer);
+        }
+        assert_eq!(hits, 1);
+        assert!(buffer[0].is_note_on());
+
+        // Stop
+        host.process_frame(4.5, false, &registry);
+        assert_eq!(registry.poll_into(1, &mut buffer), 1);
+        assert!(buffer[0].is_note_off());
+    }
+
+    #[test]
+    fn test_rack_keeps_other_units() {
+        let rack = MidiEffectRack::default()
+            .with_chain(1, chain())
+            .with_chain(2, chain());
+        let first = rack.hosts()[0].clone();
+        let rack = rack.with_chain(2, Vec::new());
+        assert!(Arc::ptr_eq(&rack.hosts()[0], &first));
+        assert!(rack.hosts()[1..].iter().all(|host| host.is_retired()));
+    }
+
+    #[test]
+    fn test_replaced_chain_releases_held_notes() {
+        let registry = MidiRegistry::new();
+        registry.register_unit(1);
+        let repeat: Box<dyn MidiEffect> = Box::new(NoteRepeat::new().rate(0.5).gate(1.0));
+        let rack = MidiEffectRack::default().with_chain(1, vec![repeat]);
+        let mut buffer = [MidiEvent::note_off(0, 0, 0, 0); 16];
+
+        registry.queue(1, &[MidiEvent::note_on(0, 0, 36, 100)]);
+        for host in rack.hosts() {
+            host.process_frame(0.0, true, &registry);
+        }
+        assert_eq!(registry.poll_into(1, &mut buffer), 1);
+        assert!(buffer[0].is_note_on());
+
+        // Swap in a chord chain while the hit is still sounding
+        let chord: Box<dyn MidiEffect> = Box::new(ChordGenerator::new(&[0, 7]));
+        let rack = rack.with_chain(1, vec![chord]);
+        assert_eq!(rack.hosts().len(), 2);
+        for host in rack.hosts() {
+            host.process_frame(STEP, true, &registry);
+        }
+        assert_eq!(registry.poll_into(1, &mut buffer), 1);
+        assert!(buffer[0].is_note_off());
+        assert_eq!(buffer[0].note(), Some(36));
+
+        // Released once, then dropped by the next swap
+        for host in rack.hosts() {
+            host.process_frame(2.0 * STEP, true, &registry);
+        }
+        assert_eq!(registry.poll_into(1, &mut buffer), 0);
+        let rack = rack.with_chain(2, chain());
+        assert_eq!(rack.hosts().len(), 2);
+        assert!(rack.hosts().iter().all(|host| !host.is_retired()));
+    }
+}
@@ -0,0 +1,231 @@
+//! Note repeat (drum-machine style retrigger).
+
+use super::MidiEffect;
+use crate::compat::Vec;
+use tutti_midi::MidiEvent;
+
+/// Most keys repeated at once; further note-ons pass through unrepeated.
+const MAX_HELD: usize = 16;
+
+#[derive(Clone, Copy, Debug)]
+struct HeldNote {
+    channel: u8,
+    note: u8,
+    velocity: u8,
+    /// When the current hit ends, `None` between hits
+    off_beat: Option<f64>,
+}
+
+/// Retriggers every held key on a beat grid.
+///
+/// A key plays immediately when pressed and then again on every grid step
+/// (`k * rate`) while held. Other messages pass straight through.
+#[derive(Debug)]
+pub struct NoteRepeat {
+    rate: f64,
+    gate: f64,
+    held: Vec<HeldNote>,
+    /// Grid index of the next retrigger, `None` while nothing is held
+    next_step: Option<i64>,
+}
+
+impl NoteRepeat {
+    pub fn new() -> Self {
+        Self {
+            rate: 0.25,
+            gate: 0.5,
+            held: Vec::with_capacity(MAX_HELD),
+            next_step: None,
+        }
+    }
+
+    /// Repeat interval in beats (0.25 = sixteenth notes at 4/4).
+    pub fn rate(mut self, beats: f64) -> Self {
+        self.rate = beats.max(1.0 / 256.0);
+        self
+    }
+
+    /// Hit length as a fraction of the interval (0.01-1.0).
+    pub fn gate(mut self, gate: f64) -> Self {
+        self.gate = gate.clamp(0.01, 1.0);
+        self
+    }
+
+    fn next_off(&self) -> Option<(usize, f64)> {
+        self.held
+            .iter()
+            .enumerate()
+            .filter_map(|(i, held)| held.off_beat.map(|beat| (i, beat)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+}
+
+// Manual so copies keep their full capacity and never allocate on the audio thread
+impl Clone for NoteRepeat {
+    fn clone(&self) -> Self {
+        let mut held = Vec::with_capacity(MAX_HELD);
+        held.extend_from_slice(&self.held);
+        Self { held, ..*self }
+    }
+}
+
+impl Default for NoteRepeat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MidiEffect for NoteRepeat {
+    fn process(&mut self, beat: f64, input: &[MidiEvent], output: &mut dyn FnMut(MidiEvent)) {
+        for event in input {
+            let channel = event.channel_num();
+            let note = event.note().unwrap_or(0);
+            let position = self
+                .held
+                .iter()
+                .position(|h| h.channel == channel && h.note == note);
+
+            if event.is_note_on() {
+                if let Some(i) = position {
+                    if self.held.swap_remove(i).off_beat.is_some() {
+                        output(MidiEvent::note_off(0, channel, note, 0));
+                    }
+                }
+                output(*event);
+                if self.held.len() < MAX_HELD {
+                    self.held.push(HeldNote {
+                        channel,
+                        note,
+                        velocity: event.velocity().unwrap_or(0),
+                        off_beat: Some(beat + self.gate * self.rate),
+                    });
+                }
+            } else if event.is_note_off() {
+                match position {
+                    Some(i) => {
+                        if self.held.swap_remove(i).off_beat.is_some() {
+                            output(*event);
+                        }
+                    }
+                    None => output(*event),
+                }
+            } else {
+                output(*event);
+            }
+        }
+
+        if self.held.is_empty() {
+            self.next_step = None;
+        } else if self.next_step.is_none() {
+            // First repeat is the next grid step strictly after now
+            self.next_step = Some((beat / self.rate + 1e-9).floor() as i64 + 1);
+        }
+
+        loop {
+            let off = self.next_off();
+            let step = self.next_step.map(|k| (k, k as f64 * self.rate));
+
+            match (off, step) {
+                (Some((i, off)), step) if off <= beat && step.is_none_or(|(_, at)| off <= at) => {
+                    let held = &mut self.held[i];
+                    held.off_beat = None;
+                    output(MidiEvent::note_off(0, held.channel, held.note, 0));
+                }
+                (_, Some((k, at))) if at <= beat => {
+                    for held in self.held.iter_mut() {
+                        if held.off_beat.is_some() {
+                            output(MidiEvent::note_off(0, held.channel, held.note, 0));
+                        }
+                        output(MidiEvent::note_on(
+                            0,
+                            held.channel,
+                            held.note,
+                            held.velocity,
+                        ));
+                        held.off_beat = Some(at + self.gate * self.rate);
+                    }
+                    self.next_step = Some(k + 1);
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn next_event_beat(&self) -> Option<f64> {
+        let off = self.next_off().map(|(_, beat)| beat);
+        let step = self.next_step.map(|k| k as f64 * self.rate);
+        match (off, step) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    fn release(&mut self, output: &mut dyn FnMut(MidiEvent)) {
+        for held in self.held.iter_mut() {
+            if held.off_beat.take().is_some() {
+                output(MidiEvent::note_off(0, held.channel, held.note, 0));
+            }
+        }
+        self.next_step = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeats_while_held() {
+        let mut repeat = NoteRepeat::new().rate(0.5).gate(0.5);
+        let press = [MidiEvent::note_on(0, 9, 38, 110)];
+        let lift = [MidiEvent::note_off(0, 9, 38, 0)];
+        let mut events = Vec::new();
+        let mut beat = 0.125;
+        while beat < 2.0 {
+            let input: &[MidiEvent] = if beat == 0.125 {
+                &press
+            } else if beat == 1.25 {
+                &lift
+            } else {
+                &[]
+            };
+            repeat.process(beat, input, &mut |e| events.push((beat, e.is_note_on())));
+            beat += 1.0 / 64.0;
+        }
+
+        assert_eq!(
+            events,
+            vec![
+                (0.125, true),  // played straight away
+                (0.375, false), // gate
+                (0.5, true),
+                (0.75, false),
+                (1.0, true),
+                (1.25, false), // key released mid-hit
+            ]
+        );
+        assert_eq!(repeat.next_event_beat(), None);
+    }
+
+    #[test]
+    fn test_release_ends_hits_and_realigns() {
+        let mut repeat = NoteRepeat::new().rate(0.25).gate(1.0);
+        let mut out = Vec::new();
+        repeat.process(0.0, &[MidiEvent::note_on(0, 0, 42, 80)], &mut |e| {
+            out.push(e)
+        });
+        repeat.release(&mut |e| out.push(e));
+        assert_eq!(
+            out,
+            vec![
+                MidiEvent::note_on(0, 0, 42, 80),
+                MidiEvent::note_off(0, 0, 42, 0)
+            ]
+        );
+
+        out.clear();
+        repeat.process(10.1, &[], &mut |e| out.push(e));
+        assert!(out.is_empty());
+        assert_eq!(repeat.next_event_beat(), Some(10.25));
+    }
+}
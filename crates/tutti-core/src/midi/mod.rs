@@ -4,14 +4,19 @@
 //! the `tutti-midi` crate and are re-exported here for convenience.
 //!
 //! This module adds higher-level infrastructure that depends on tutti-core internals:
+//! - [`effect`]: Transport-locked MIDI effects (arpeggiator, chord, note repeat)
 //! - [`registry`]: RT-safe MIDI event routing system (depends on crossbeam/dashmap)
 //! - [`routing`]: Lock-free MIDI routing table (depends on arc_swap)
+//! - [`transform`]: Per-route transform stages (transpose, velocity, remaps)
 
+pub mod effect;
 pub mod registry;
 pub mod routing;
 pub mod snapshot;
 pub mod snapshot_reader;
 pub mod source;
+pub mod transform;
+pub mod transport_source;
 
 pub use tutti_midi::{
     event, input_source, Channel, ChannelModeMsg, ChannelVoiceMsg, ControlChange, MidiEvent,
@@ -19,8 +24,11 @@ pub use tutti_midi::{
     SystemRealTimeMsg,
 };
 
+pub use effect::{ArpMode, Arpeggiator, ChordGenerator, MidiEffect, NoteRepeat};
 pub use registry::MidiRegistry;
 pub use routing::{MidiRoute, MidiRoutingSnapshot, MidiRoutingTable};
 pub use snapshot::{MidiSnapshot, TimedMidiEvent};
 pub use snapshot_reader::MidiSnapshotReader;
 pub use source::MidiSource;
+pub use transform::{MidiTransform, OctaveShift, VelocityCurve};
+pub use transport_source::TransportMidiSource;
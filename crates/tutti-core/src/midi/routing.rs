@@ -1,7 +1,9 @@
 //! RT-safe MIDI routing system for channel-based, port-based, and layered routing.
 //!
 //! This module provides a lock-free routing table that maps MIDI events to target
-//! audio units based on channel, port, or custom routing rules.
+//! audio units based on channel, port, or custom routing rules. Routes can also
+//! carry note-range and velocity-range filters (keyboard splits, velocity layers)
+//! and a [`MidiTransform`] chain that is compiled when the table is committed.
 //!
 //! # Architecture
 //!
@@ -21,6 +23,8 @@
 //!
 //! - `MidiRoutingSnapshot::route()`: Called on the audio thread. Zero allocations,
 //!   returns an iterator over target unit IDs.
+//! - `MidiRoutingSnapshot::route_events()`: Same, but yields each target together
+//!   with the event after its route's transforms.
 //! - `MidiRoutingTable::commit()`: Called from the UI thread. Atomically swaps
 //!   the snapshot using `arc_swap::ArcSwap`.
 
@@ -29,20 +33,33 @@ use arc_swap::ArcSwap;
 
 use tutti_midi::MidiEvent;
 
+use super::transform::{CompiledTransform, MidiTransform};
+
 /// Maximum number of targets per routing rule.
 /// Supports layering up to 8 synths on a single channel.
 const MAX_TARGETS_PER_ROUTE: usize = 8;
 
 /// A single MIDI routing rule.
 ///
-/// Routes can filter by port, channel, or both. Events matching the filter
-/// are sent to all target units.
+/// Routes can filter by port, channel, note range and velocity range. Events
+/// matching the filter are passed through the route's transforms and sent to
+/// all target units.
+///
+/// The note range applies to note and poly-pressure messages; other messages
+/// always pass it. The velocity range applies to note-ons only, so a note-off
+/// reaches every velocity layer and releases whichever one started the note.
 #[derive(Clone, Debug)]
 pub struct MidiRoute {
     /// Port filter: `None` = any port, `Some(n)` = port n only
     pub port: Option<usize>,
     /// Channel filter: `None` = any channel, `Some(n)` = channel n only (0-15)
     pub channel: Option<u8>,
+    /// Inclusive note filter: `None` = all notes
+    pub note_range: Option<(u8, u8)>,
+    /// Inclusive note-on velocity filter: `None` = all velocities
+    pub velocity_range: Option<(u8, u8)>,
+    /// Transforms applied to matching events, in order
+    pub transforms: Vec<MidiTransform>,
     /// Target unit IDs to receive matching events
     pub targets: Vec<u64>,
     /// Whether this route is enabled
@@ -54,6 +71,9 @@ impl MidiRoute {
         Self {
             port: None,
             channel: None,
+            note_range: None,
+            velocity_range: None,
+            transforms: Vec::new(),
             targets: Vec::new(),
             enabled: true,
         }
@@ -63,6 +83,9 @@ impl MidiRoute {
         Self {
             port: None,
             channel: Some(channel),
+            note_range: None,
+            velocity_range: None,
+            transforms: Vec::new(),
             targets: Vec::new(),
             enabled: true,
         }
@@ -72,6 +95,9 @@ impl MidiRoute {
         Self {
             port: Some(port),
             channel: None,
+            note_range: None,
+            velocity_range: None,
+            transforms: Vec::new(),
             targets: Vec::new(),
             enabled: true,
         }
@@ -81,6 +107,9 @@ impl MidiRoute {
         Self {
             port: Some(port),
             channel: Some(channel),
+            note_range: None,
+            velocity_range: None,
+            transforms: Vec::new(),
             targets: Vec::new(),
             enabled: true,
         }
@@ -103,6 +132,24 @@ impl MidiRoute {
         self
     }
 
+    /// Only pass notes in `low..=high` (a keyboard split zone).
+    pub fn with_note_range(mut self, low: u8, high: u8) -> Self {
+        self.note_range = Some((low.min(high), low.max(high)));
+        self
+    }
+
+    /// Only pass note-ons with velocity in `low..=high` (a velocity layer).
+    pub fn with_velocity_range(mut self, low: u8, high: u8) -> Self {
+        self.velocity_range = Some((low.min(high), low.max(high)));
+        self
+    }
+
+    /// Append a transform stage.
+    pub fn with_transform(mut self, transform: MidiTransform) -> Self {
+        self.transforms.push(transform);
+        self
+    }
+
     #[inline]
     pub fn matches(&self, port: usize, event: &MidiEvent) -> bool {
         if !self.enabled {
@@ -110,7 +157,26 @@ impl MidiRoute {
         }
         let port_matches = self.port.is_none_or(|p| p == port);
         let channel_matches = self.channel.is_none_or(|c| c == event.channel_num());
-        port_matches && channel_matches
+        let note_matches = self
+            .note_range
+            .is_none_or(|(low, high)| event.note().is_none_or(|n| (low..=high).contains(&n)));
+        let velocity_matches = self.velocity_range.is_none_or(|(low, high)| {
+            !event.is_note_on() || event.velocity().is_none_or(|v| (low..=high).contains(&v))
+        });
+        port_matches && channel_matches && note_matches && velocity_matches
+    }
+
+    /// No note/velocity ranges and no transforms.
+    #[inline]
+    fn is_unshaped(&self) -> bool {
+        self.note_range.is_none() && self.velocity_range.is_none() && self.transforms.is_empty()
+    }
+
+    /// Whether this route can go in the channel lookup: no port, range or
+    /// transform to evaluate per event.
+    #[inline]
+    fn is_channel_only(&self) -> bool {
+        self.port.is_none() && self.is_unshaped()
     }
 }
 
@@ -128,6 +194,8 @@ impl Default for MidiRoute {
 pub struct MidiRoutingSnapshot {
     /// All routing rules
     routes: Vec<MidiRoute>,
+    /// Compiled transform chain for each route (same indices as `routes`)
+    transforms: Vec<CompiledTransform>,
     /// Precomputed channel→targets lookup (17 entries: 0-15 + "any channel")
     /// Index 16 is for routes that match any channel.
     channel_lookup: [Vec<u64>; 17],
@@ -139,14 +207,20 @@ impl MidiRoutingSnapshot {
     pub fn empty() -> Self {
         Self {
             routes: Vec::new(),
+            transforms: Vec::new(),
             channel_lookup: Default::default(),
             fallback_target: None,
         }
     }
 
     fn from_routes(routes: Vec<MidiRoute>, fallback: Option<u64>) -> Self {
+        let transforms = routes
+            .iter()
+            .map(|route| CompiledTransform::compile(&route.transforms))
+            .collect();
         let mut snapshot = Self {
             routes,
+            transforms,
             channel_lookup: Default::default(),
             fallback_target: fallback,
         };
@@ -165,9 +239,9 @@ impl MidiRoutingSnapshot {
             if !route.enabled {
                 continue;
             }
-            // Only add to lookup if route has no port filter (port-filtered routes
-            // must go through the full route matching path)
-            if route.port.is_some() {
+            // Only add to lookup if route is channel-only (port filters, ranges
+            // and transforms must go through the full route matching path)
+            if !route.is_channel_only() {
                 continue;
             }
 
@@ -181,6 +255,9 @@ impl MidiRoutingSnapshot {
     }
 
     /// RT-safe. Returns an iterator over target unit IDs. Zero allocations.
+    ///
+    /// Filters are applied, transforms are not; use [`route_events`](Self::route_events)
+    /// to get what each target should actually receive.
     #[inline]
     pub fn route<'a>(&'a self, port: usize, event: &'a MidiEvent) -> RouteIterator<'a> {
         RouteIterator {
@@ -192,9 +269,24 @@ impl MidiRoutingSnapshot {
             target_idx: 0,
             seen: [0u64; 16], // Track seen targets to avoid duplicates
             seen_count: 0,
+            matched: false,
+            apply_transforms: false,
+            transformed: None,
         }
     }
 
+    /// RT-safe. Returns an iterator over `(target, event)` pairs, with each
+    /// event passed through its route's transforms. Zero allocations.
+    ///
+    /// A target reached through several routes with different transforms
+    /// receives one event per route (e.g. an octave-doubling layer).
+    #[inline]
+    pub fn route_events<'a>(&'a self, port: usize, event: &'a MidiEvent) -> RoutedEvents<'a> {
+        let mut inner = self.route(port, event);
+        inner.apply_transforms = true;
+        RoutedEvents { inner }
+    }
+
     /// Returns the first matching target, or the fallback if none match.
     #[inline]
     pub fn route_single(&self, port: usize, event: &MidiEvent) -> Option<u64> {
@@ -248,8 +340,8 @@ enum RoutePhase {
     ChannelLookup,
     /// Checking "any channel" lookup
     AnyChannelLookup,
-    /// Iterating through port-filtered routes
-    PortRoutes,
+    /// Iterating through routes with port filters, ranges or transforms
+    FilteredRoutes,
     /// Checking fallback target
     Fallback,
     /// Done iterating
@@ -268,6 +360,13 @@ pub struct RouteIterator<'a> {
     target_idx: usize,
     seen: [u64; 16], // Small buffer for deduplication
     seen_count: usize,
+    /// Whether any route matched (suppresses the fallback)
+    matched: bool,
+    /// Run route transforms (`route_events`) or only report targets (`route`)
+    apply_transforms: bool,
+    /// Current filtered route's event after transforms; `None` if the route
+    /// has no transforms or dropped the event
+    transformed: Option<MidiEvent>,
 }
 
 impl<'a> RouteIterator<'a> {
@@ -278,18 +377,17 @@ impl<'a> RouteIterator<'a> {
 
     #[inline]
     fn mark_seen(&mut self, target: u64) {
+        self.matched = true;
         if self.seen_count < self.seen.len() {
             self.seen[self.seen_count] = target;
             self.seen_count += 1;
         }
     }
-}
-
-impl Iterator for RouteIterator<'_> {
-    type Item = u64;
 
+    /// Next target, with the transformed event if it came from a route with
+    /// transforms.
     #[inline]
-    fn next(&mut self) -> Option<Self::Item> {
+    fn next_delivery(&mut self) -> Option<(u64, Option<MidiEvent>)> {
         loop {
             match self.phase {
                 RoutePhase::ChannelLookup => {
@@ -300,7 +398,7 @@ impl Iterator for RouteIterator<'_> {
                         self.target_idx += 1;
                         if !self.is_seen(target) {
                             self.mark_seen(target);
-                            return Some(target);
+                            return Some((target, None));
                         }
                     }
                     self.target_idx = 0;
@@ -313,23 +411,40 @@ impl Iterator for RouteIterator<'_> {
                         self.target_idx += 1;
                         if !self.is_seen(target) {
                             self.mark_seen(target);
-                            return Some(target);
+                            return Some((target, None));
                         }
                     }
                     self.target_idx = 0;
-                    self.phase = RoutePhase::PortRoutes;
+                    self.phase = RoutePhase::FilteredRoutes;
                 }
-                RoutePhase::PortRoutes => {
+                RoutePhase::FilteredRoutes => {
                     while self.route_idx < self.snapshot.routes.len() {
                         let route = &self.snapshot.routes[self.route_idx];
-                        // Only process port-filtered routes (non-port routes are in lookup)
-                        if route.port.is_some() && route.matches(self.port, self.event) {
-                            while self.target_idx < route.targets.len() {
-                                let target = route.targets[self.target_idx];
-                                self.target_idx += 1;
-                                if !self.is_seen(target) {
-                                    self.mark_seen(target);
-                                    return Some(target);
+                        // Channel-only routes were already served by the lookup
+                        if !route.is_channel_only() && route.matches(self.port, self.event) {
+                            let transform = &self.snapshot.transforms[self.route_idx];
+                            if transform.is_identity() || !self.apply_transforms {
+                                while self.target_idx < route.targets.len() {
+                                    let target = route.targets[self.target_idx];
+                                    self.target_idx += 1;
+                                    if !self.is_seen(target) {
+                                        self.mark_seen(target);
+                                        return Some((target, None));
+                                    }
+                                }
+                            } else {
+                                // Transform once per route, then fan out. Transformed
+                                // events are distinct, so they skip deduplication.
+                                if self.target_idx == 0 {
+                                    self.matched = true;
+                                    self.transformed = transform.apply(self.event);
+                                }
+                                if let Some(event) = self.transformed {
+                                    if self.target_idx < route.targets.len() {
+                                        let target = route.targets[self.target_idx];
+                                        self.target_idx += 1;
+                                        return Some((target, Some(event)));
+                                    }
                                 }
                             }
                         }
@@ -341,9 +456,9 @@ impl Iterator for RouteIterator<'_> {
                 RoutePhase::Fallback => {
                     self.phase = RoutePhase::Done;
                     // Only use fallback if no routes matched
-                    if self.seen_count == 0 {
+                    if !self.matched {
                         if let Some(target) = self.snapshot.fallback_target {
-                            return Some(target);
+                            return Some((target, None));
                         }
                     }
                 }
@@ -355,6 +470,32 @@ impl Iterator for RouteIterator<'_> {
     }
 }
 
+impl Iterator for RouteIterator<'_> {
+    type Item = u64;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_delivery().map(|(target, _)| target)
+    }
+}
+
+/// RT-safe iterator over `(target, event)` pairs with route transforms applied.
+pub struct RoutedEvents<'a> {
+    inner: RouteIterator<'a>,
+}
+
+impl Iterator for RoutedEvents<'_> {
+    type Item = (u64, MidiEvent);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let event = *self.inner.event;
+        self.inner
+            .next_delivery()
+            .map(|(target, transformed)| (target, transformed.unwrap_or(event)))
+    }
+}
+
 /// Mutable routing table for configuration from the UI thread.
 ///
 /// Changes are staged and then atomically committed via `commit()`.
@@ -408,7 +549,7 @@ impl MidiRoutingTable {
     pub fn channel(&mut self, channel: u8, unit_id: u64) -> &mut Self {
         // Check if a route for this channel already exists
         for route in &mut self.routes {
-            if route.port.is_none() && route.channel == Some(channel) {
+            if route.is_channel_only() && route.channel == Some(channel) {
                 if !route.targets.contains(&unit_id) {
                     route.targets.push(unit_id);
                 }
@@ -430,7 +571,7 @@ impl MidiRoutingTable {
     pub fn port(&mut self, port: usize, unit_id: u64) -> &mut Self {
         // Check if a route for this port already exists
         for route in &mut self.routes {
-            if route.port == Some(port) && route.channel.is_none() {
+            if route.port == Some(port) && route.channel.is_none() && route.is_unshaped() {
                 if !route.targets.contains(&unit_id) {
                     route.targets.push(unit_id);
                 }
@@ -450,7 +591,7 @@ impl MidiRoutingTable {
     pub fn port_channel(&mut self, port: usize, channel: u8, unit_id: u64) -> &mut Self {
         // Check if a route for this port+channel already exists
         for route in &mut self.routes {
-            if route.port == Some(port) && route.channel == Some(channel) {
+            if route.port == Some(port) && route.channel == Some(channel) && route.is_unshaped() {
                 if !route.targets.contains(&unit_id) {
                     route.targets.push(unit_id);
                 }
@@ -466,14 +607,28 @@ impl MidiRoutingTable {
         self
     }
 
+    /// Add a fully specified route, e.g. a key split zone or velocity layer.
+    ///
+    /// ```ignore
+    /// table
+    ///     .add_route(MidiRoute::for_channel(0).with_note_range(0, 59).with_target(bass))
+    ///     .add_route(MidiRoute::for_channel(0).with_note_range(60, 127).with_target(piano));
+    /// ```
+    pub fn add_route(&mut self, route: MidiRoute) -> &mut Self {
+        self.routes.push(route);
+        self.dirty = true;
+        self
+    }
+
     /// Route all MIDI to multiple targets (global layer).
     ///
     /// All incoming MIDI (any port, any channel) will be routed to all targets.
-    /// Replaces any existing global layer.
+    /// Replaces any existing global layer. Routes added with
+    /// [`add_route`](Self::add_route) that carry ranges or transforms are kept.
     pub fn layer(&mut self, targets: &[u64]) -> &mut Self {
         // Remove existing "any" routes
         self.routes
-            .retain(|r| r.port.is_some() || r.channel.is_some());
+            .retain(|r| r.port.is_some() || r.channel.is_some() || !r.is_unshaped());
 
         // Add new layer route
         if !targets.is_empty() {
@@ -486,11 +641,11 @@ impl MidiRoutingTable {
     /// Route a MIDI channel to multiple targets (channel layer).
     ///
     /// Events on the specified channel will be routed to all targets.
-    /// Replaces any existing routes for this channel.
+    /// Replaces any existing plain routes for this channel.
     pub fn channel_layer(&mut self, channel: u8, targets: &[u64]) -> &mut Self {
         // Remove existing routes for this channel
         self.routes
-            .retain(|r| !(r.port.is_none() && r.channel == Some(channel)));
+            .retain(|r| !(r.is_channel_only() && r.channel == Some(channel)));
 
         // Add new layer route
         if !targets.is_empty() {
@@ -741,6 +896,105 @@ mod tests {
         assert!(targets.contains(&200));
     }
 
+    #[test]
+    fn test_key_split() {
+        let mut table = MidiRoutingTable::new();
+        table
+            .add_route(
+                MidiRoute::for_channel(0)
+                    .with_note_range(0, 59)
+                    .with_target(100),
+            )
+            .add_route(
+                MidiRoute::for_channel(0)
+                    .with_note_range(60, 127)
+                    .with_target(200),
+            )
+            .fallback(999);
+        table.commit();
+
+        let snapshot = table.load();
+        let low = note_on(0, 48);
+        assert_eq!(snapshot.route(0, &low).collect::<Vec<_>>(), vec![100]);
+        let high = note_on(0, 60);
+        assert_eq!(snapshot.route(0, &high).collect::<Vec<_>>(), vec![200]);
+
+        // Non-note messages reach both zones, not the fallback
+        let cc = MidiEvent::control_change(0, 0, 64, 127);
+        assert_eq!(snapshot.route(0, &cc).collect::<Vec<_>>(), vec![100, 200]);
+    }
+
+    #[test]
+    fn test_velocity_layers() {
+        let mut table = MidiRoutingTable::new();
+        table
+            .add_route(MidiRoute::new().with_velocity_range(1, 90).with_target(100))
+            .add_route(
+                MidiRoute::new()
+                    .with_velocity_range(91, 127)
+                    .with_target(200),
+            );
+        table.commit();
+
+        let snapshot = table.load();
+        let soft = MidiEvent::note_on(0, 0, 60, 40);
+        assert_eq!(snapshot.route(0, &soft).collect::<Vec<_>>(), vec![100]);
+        let hard = MidiEvent::note_on(0, 0, 60, 120);
+        assert_eq!(snapshot.route(0, &hard).collect::<Vec<_>>(), vec![200]);
+
+        // Note-offs go to every layer
+        let off = MidiEvent::note_off(0, 0, 60, 64);
+        assert_eq!(snapshot.route(0, &off).collect::<Vec<_>>(), vec![100, 200]);
+    }
+
+    #[test]
+    fn test_route_events_applies_transforms() {
+        let mut table = MidiRoutingTable::new();
+        table.channel(0, 100).add_route(
+            MidiRoute::for_channel(0)
+                .with_transform(MidiTransform::Transpose(12))
+                .with_transform(MidiTransform::Channel(3))
+                .with_targets(&[100, 200]),
+        );
+        table.commit();
+
+        let snapshot = table.load();
+        let event = MidiEvent::note_on(17, 0, 60, 100);
+        let routed: Vec<_> = snapshot.route_events(0, &event).collect();
+        let doubled = MidiEvent::note_on(17, 3, 72, 100);
+        assert_eq!(routed, vec![(100, event), (100, doubled), (200, doubled)]);
+
+        // Transposed out of range: only the plain route delivers
+        let top = note_on(0, 120);
+        let routed: Vec<_> = snapshot.route_events(0, &top).collect();
+        assert_eq!(routed, vec![(100, top)]);
+    }
+
+    #[test]
+    fn test_plain_helpers_leave_shaped_routes_alone() {
+        let mut table = MidiRoutingTable::new();
+        table
+            .add_route(
+                MidiRoute::for_channel(0)
+                    .with_note_range(0, 59)
+                    .with_target(100),
+            )
+            .channel(0, 200)
+            .layer(&[300])
+            .channel_layer(0, &[400]);
+        table.commit();
+
+        let snapshot = table.load();
+        assert_eq!(table.route_count(), 3);
+        let low = note_on(0, 40);
+        assert_eq!(
+            snapshot.route(0, &low).collect::<Vec<_>>(),
+            vec![400, 300, 100]
+        );
+        let high = note_on(0, 80);
+        assert_eq!(snapshot.route(0, &high).collect::<Vec<_>>(), vec![400, 300]);
+    }
+
     #[test]
     fn test_chainable_api() {
         let mut table = MidiRoutingTable::new();
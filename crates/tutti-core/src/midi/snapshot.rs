@@ -119,6 +119,26 @@ impl MidiSnapshot {
         self.events.keys().copied()
     }
 
+    /// All events for a unit, sorted by beat.
+    pub fn events(&self, unit_id: u64) -> &[TimedMidiEvent] {
+        self.events.get(&unit_id).map_or(&[], |e| e.as_slice())
+    }
+
+    /// Replace all of a unit's events, returning the old ones. The new events
+    /// are sorted by beat, keeping the given order for equal beats.
+    pub fn replace_events(
+        &mut self,
+        unit_id: u64,
+        mut events: Vec<TimedMidiEvent>,
+    ) -> Vec<TimedMidiEvent> {
+        events.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+        self.cursors
+            .entry(unit_id)
+            .or_insert_with(|| AtomicUsize::new(0))
+            .store(0, Ordering::Relaxed);
+        self.events.insert(unit_id, events).unwrap_or_default()
+    }
+
     /// Reset all cursors to the beginning.
     ///
     /// Call this before re-rendering to replay all events.
@@ -294,6 +314,32 @@ mod tests {
         assert!(!snapshot.has_events(2));
     }
 
+    #[test]
+    fn test_replace_events() {
+        let mut snapshot = MidiSnapshot::new();
+        snapshot.add_event(1, 0.0, note_on(60, 100));
+        assert_eq!(snapshot.poll_range(1, 0.0, 1.0).len(), 1);
+
+        let old = snapshot.replace_events(
+            1,
+            vec![
+                TimedMidiEvent {
+                    event: note_on(64, 100),
+                    beat: 0.5,
+                },
+                TimedMidiEvent {
+                    event: note_on(62, 100),
+                    beat: 0.25,
+                },
+            ],
+        );
+        assert_eq!(old.len(), 1);
+
+        // Sorted, and the cursor starts over
+        let events = snapshot.poll_range(1, 0.0, 1.0);
+        assert_eq!(events, vec![note_on(62, 100), note_on(64, 100)]);
+    }
+
     #[test]
     fn test_poll_nonexistent_unit() {
         let mut snapshot = MidiSnapshot::new();
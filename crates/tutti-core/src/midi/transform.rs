@@ -0,0 +1,320 @@
+//! Per-route MIDI transforms (transpose, velocity shaping, channel/CC remap).
+//!
+//! A route's [`MidiTransform`] list is compiled into a flat [`CompiledTransform`]
+//! when the routing table is committed: transposes are summed, velocity stages
+//! are folded into a 128-entry lookup table and CC remaps into another. Applying
+//! the compiled form on the audio thread is a handful of table lookups.
+
+use crate::compat::{Arc, AtomicI8, Ordering};
+use core::fmt;
+
+use tutti_midi::{Channel, ChannelVoiceMsg, ControlChange, MidiEvent};
+
+/// Largest octave shift accepted by [`OctaveShift::set_octaves`].
+const MAX_OCTAVES: i8 = 10;
+
+/// A single transform stage applied to events passing through a route.
+///
+/// Stages run in the order they were added. Velocity stages only touch note-on
+/// velocity; note-offs keep their release velocity.
+#[derive(Clone, Debug)]
+pub enum MidiTransform {
+    /// Shift notes by a number of semitones. Notes pushed outside 0-127 are dropped.
+    Transpose(i8),
+    /// Multiply note-on velocity, clamped to 1-127.
+    VelocityScale(f32),
+    /// Map note-on velocity through a curve.
+    VelocityCurve(VelocityCurve),
+    /// Send everything out on this channel (0-15).
+    Channel(u8),
+    /// Rewrite one controller number to another.
+    CcRemap { from: u8, to: u8 },
+    /// Live octave shift. Changing it never strands a note: note-offs are
+    /// shifted by whatever was in effect when their note-on passed through.
+    OctaveShift(OctaveShift),
+}
+
+/// Note-on velocity response.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VelocityCurve {
+    /// `127 * (v / 127) ^ exponent`. Below 1.0 is softer to reach loud, above
+    /// 1.0 is harder.
+    Power(f32),
+    /// Every note-on gets this velocity.
+    Fixed(u8),
+    /// Rescale 1-127 into `min..=max`.
+    Range { min: u8, max: u8 },
+}
+
+impl VelocityCurve {
+    fn apply(&self, velocity: u8) -> u8 {
+        let v = match *self {
+            VelocityCurve::Power(exponent) => {
+                let normalized = velocity as f32 / 127.0;
+                (normalized.powf(exponent.max(0.01)) * 127.0).round() as i32
+            }
+            VelocityCurve::Fixed(v) => v as i32,
+            VelocityCurve::Range { min, max } => {
+                let (min, max) = (min.clamp(1, 127) as i32, max.clamp(1, 127) as i32);
+                min + ((velocity as i32 - 1) * (max - min) + 63) / 126
+            }
+        };
+        v.clamp(1, 127) as u8
+    }
+}
+
+/// Shared, live-adjustable octave shift.
+///
+/// Clone the handle into a route with [`MidiTransform::OctaveShift`] and keep
+/// another clone to change the shift from the UI without recommitting the
+/// routing table. The shift applied to each held note is remembered per input
+/// channel and note, so the matching note-off always lands on the pitch that
+/// was actually started.
+#[derive(Clone)]
+pub struct OctaveShift {
+    inner: Arc<OctaveShiftState>,
+}
+
+struct OctaveShiftState {
+    octaves: AtomicI8,
+    /// Shift (in octaves) latched at note-on, indexed by `channel * 128 + note`.
+    held: [AtomicI8; 16 * 128],
+}
+
+impl OctaveShift {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(OctaveShiftState {
+                octaves: AtomicI8::new(0),
+                held: core::array::from_fn(|_| AtomicI8::new(0)),
+            }),
+        }
+    }
+
+    pub fn octaves(&self) -> i8 {
+        self.inner.octaves.load(Ordering::Relaxed)
+    }
+
+    /// Set the shift for notes started from now on. Clamped to ±10 octaves.
+    pub fn set_octaves(&self, octaves: i8) {
+        self.inner
+            .octaves
+            .store(octaves.clamp(-MAX_OCTAVES, MAX_OCTAVES), Ordering::Relaxed);
+    }
+
+    #[inline]
+    fn note_on(&self, channel: u8, note: u8) -> i8 {
+        let octaves = self.octaves();
+        self.inner.held[Self::index(channel, note)].store(octaves, Ordering::Relaxed);
+        octaves
+    }
+
+    #[inline]
+    fn held(&self, channel: u8, note: u8) -> i8 {
+        self.inner.held[Self::index(channel, note)].load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    fn index(channel: u8, note: u8) -> usize {
+        (channel as usize & 0x0F) * 128 + (note as usize & 0x7F)
+    }
+}
+
+impl Default for OctaveShift {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for OctaveShift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OctaveShift")
+            .field("octaves", &self.octaves())
+            .finish()
+    }
+}
+
+/// A route's transform list flattened for the audio thread.
+#[derive(Clone, Debug)]
+pub(crate) struct CompiledTransform {
+    semitones: i16,
+    velocity: Option<[u8; 128]>,
+    channel: Option<u8>,
+    cc: Option<[u8; 128]>,
+    octave: Option<OctaveShift>,
+}
+
+impl CompiledTransform {
+    pub(crate) fn compile(stages: &[MidiTransform]) -> Self {
+        let mut compiled = Self {
+            semitones: 0,
+            velocity: None,
+            channel: None,
+            cc: None,
+            octave: None,
+        };
+
+        for stage in stages {
+            match stage {
+                MidiTransform::Transpose(semitones) => compiled.semitones += *semitones as i16,
+                MidiTransform::VelocityScale(scale) => {
+                    let lut = compiled.velocity.get_or_insert_with(identity_table);
+                    for v in lut.iter_mut().skip(1) {
+                        *v = ((*v as f32 * scale).round() as i32).clamp(1, 127) as u8;
+                    }
+                }
+                MidiTransform::VelocityCurve(curve) => {
+                    let lut = compiled.velocity.get_or_insert_with(identity_table);
+                    for v in lut.iter_mut().skip(1) {
+                        *v = curve.apply(*v);
+                    }
+                }
+                MidiTransform::Channel(channel) => compiled.channel = Some(channel & 0x0F),
+                MidiTransform::CcRemap { from, to } => {
+                    let lut = compiled.cc.get_or_insert_with(identity_table);
+                    for cc in lut.iter_mut() {
+                        if *cc == *from {
+                            *cc = *to & 0x7F;
+                        }
+                    }
+                }
+                MidiTransform::OctaveShift(shift) => compiled.octave = Some(shift.clone()),
+            }
+        }
+
+        compiled
+    }
+
+    #[inline]
+    pub(crate) fn is_identity(&self) -> bool {
+        self.semitones == 0
+            && self.velocity.is_none()
+            && self.channel.is_none()
+            && self.cc.is_none()
+            && self.octave.is_none()
+    }
+
+    /// RT-safe. Returns `None` when a note is transposed out of range.
+    #[inline]
+    pub(crate) fn apply(&self, event: &MidiEvent) -> Option<MidiEvent> {
+        let channel = event.channel_num();
+        let mut out = *event;
+
+        match &mut out.msg {
+            ChannelVoiceMsg::NoteOn { note, velocity } if *velocity > 0 => {
+                let octaves = self
+                    .octave
+                    .as_ref()
+                    .map_or(0, |o| o.note_on(channel, *note));
+                *note = self.shift(*note, octaves)?;
+                if let Some(lut) = &self.velocity {
+                    *velocity = lut[*velocity as usize & 0x7F];
+                }
+            }
+            ChannelVoiceMsg::NoteOn { note, .. }
+            | ChannelVoiceMsg::NoteOff { note, .. }
+            | ChannelVoiceMsg::HighResNoteOn { note, .. }
+            | ChannelVoiceMsg::HighResNoteOff { note, .. }
+            | ChannelVoiceMsg::PolyPressure { note, .. } => {
+                let octaves = self.octave.as_ref().map_or(0, |o| o.held(channel, *note));
+                *note = self.shift(*note, octaves)?;
+            }
+            ChannelVoiceMsg::ControlChange {
+                control: ControlChange::CC { control, .. },
+            } => {
+                if let Some(lut) = &self.cc {
+                    *control = lut[*control as usize & 0x7F];
+                }
+            }
+            _ => {}
+        }
+
+        if let Some(channel) = self.channel {
+            out.channel = Channel::from_u8(channel);
+        }
+        Some(out)
+    }
+
+    #[inline]
+    fn shift(&self, note: u8, octaves: i8) -> Option<u8> {
+        let shifted = note as i16 + self.semitones + octaves as i16 * 12;
+        (0..=127).contains(&shifted).then_some(shifted as u8)
+    }
+}
+
+fn identity_table() -> [u8; 128] {
+    core::array::from_fn(|i| i as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transpose_drops_out_of_range() {
+        let t = CompiledTransform::compile(&[
+            MidiTransform::Transpose(12),
+            MidiTransform::Transpose(-5),
+        ]);
+        let out = t.apply(&MidiEvent::note_on(3, 0, 60, 100)).unwrap();
+        assert_eq!(out.note(), Some(67));
+        assert_eq!(out.frame_offset, 3);
+        assert!(t.apply(&MidiEvent::note_on(0, 0, 125, 100)).is_none());
+    }
+
+    #[test]
+    fn test_velocity_stages_compose_in_order() {
+        let t = CompiledTransform::compile(&[
+            MidiTransform::VelocityScale(2.0),
+            MidiTransform::VelocityCurve(VelocityCurve::Range { min: 1, max: 64 }),
+        ]);
+        // 40 * 2 = 80, then 80 of 1..127 rescaled into 1..64
+        let out = t.apply(&MidiEvent::note_on(0, 0, 60, 40)).unwrap();
+        assert_eq!(out.velocity(), Some(41));
+        // Note-off velocity is untouched
+        let off = t.apply(&MidiEvent::note_off(0, 0, 60, 40)).unwrap();
+        assert_eq!(off.velocity(), Some(40));
+        // Fixed never yields a zero-velocity note-on
+        let fixed =
+            CompiledTransform::compile(&[MidiTransform::VelocityCurve(VelocityCurve::Fixed(0))]);
+        assert!(fixed
+            .apply(&MidiEvent::note_on(0, 0, 60, 90))
+            .unwrap()
+            .is_note_on());
+    }
+
+    #[test]
+    fn test_channel_and_cc_remap() {
+        let t = CompiledTransform::compile(&[
+            MidiTransform::Channel(9),
+            MidiTransform::CcRemap { from: 1, to: 11 },
+            MidiTransform::CcRemap { from: 11, to: 74 },
+        ]);
+        let out = t.apply(&MidiEvent::control_change(0, 2, 1, 50)).unwrap();
+        assert_eq!(out, MidiEvent::control_change(0, 9, 74, 50));
+        let untouched = t.apply(&MidiEvent::control_change(0, 2, 7, 50)).unwrap();
+        assert_eq!(untouched, MidiEvent::control_change(0, 9, 7, 50));
+    }
+
+    #[test]
+    fn test_octave_shift_keeps_note_offs_paired() {
+        let shift = OctaveShift::new();
+        let t = CompiledTransform::compile(&[MidiTransform::OctaveShift(shift.clone())]);
+
+        shift.set_octaves(1);
+        let on = t.apply(&MidiEvent::note_on(0, 0, 60, 100)).unwrap();
+        assert_eq!(on.note(), Some(72));
+
+        // Shift changes while the note is held
+        shift.set_octaves(-1);
+        let off = t.apply(&MidiEvent::note_off(0, 0, 60, 0)).unwrap();
+        assert_eq!(off.note(), Some(72));
+
+        // New notes use the new shift
+        let on = t.apply(&MidiEvent::note_on(0, 0, 60, 100)).unwrap();
+        assert_eq!(on.note(), Some(48));
+
+        shift.set_octaves(100);
+        assert_eq!(shift.octaves(), MAX_OCTAVES);
+    }
+}
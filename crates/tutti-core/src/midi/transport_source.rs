@@ -0,0 +1,32 @@
+//! Transport-driven MIDI sources (file players, sequencers).
+//!
+//! A [`TransportMidiSource`] is installed on the system and called by the audio
+//! callback for every frame, with that frame's transport position, before the
+//! graph renders it. Events it queues to the [`MidiRegistry`] reach their
+//! target nodes on exactly that frame.
+
+use super::registry::MidiRegistry;
+use super::snapshot::MidiSnapshot;
+
+/// Produces MIDI from the transport position.
+///
+/// Install with [`TuttiSystem::set_transport_midi_source`](crate::TuttiSystem::set_transport_midi_source).
+///
+/// # Safety
+///
+/// `process_frame` runs on the audio thread: no allocation, no locks, no
+/// blocking I/O.
+pub trait TransportMidiSource: Send + Sync {
+    /// Called once per rendered frame with the frame's position in beats and
+    /// whether the transport is rolling.
+    ///
+    /// Positions are not guaranteed to increase: locates, loop wraps and
+    /// scrubbing all show up as jumps.
+    fn process_frame(&self, beat: f64, playing: bool, registry: &MidiRegistry);
+
+    /// Add everything this source would play to an offline render's
+    /// snapshot, keyed by target unit and transport beat.
+    ///
+    /// Called when an export context is created while the source is installed.
+    fn export_to(&self, _snapshot: &mut MidiSnapshot) {}
+}
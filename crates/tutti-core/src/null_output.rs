@@ -0,0 +1,437 @@
+//! Headless audio backend driven by a timer thread (requires std).
+//!
+//! Runs the same `AudioCallbackState` as the CPAL backend, but without a
+//! sound card. Useful for CI runners, render servers, and integration tests
+//! that need transport, MIDI routing and metering to run end to end.
+
+#[allow(unused_extern_crates)]
+extern crate std;
+
+use crate::callback::AudioCallbackState;
+use crate::compat::{Arc, AtomicU64, Ordering, Vec};
+use crate::lockfree::AtomicFlag;
+use crate::metering::MeteringContext;
+use crate::{Error, Result};
+use ringbuf::{
+    traits::{Consumer, Observer, Producer, Split},
+    HeapCons, HeapProd, HeapRb,
+};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Largest block the null backend will render in one cycle.
+const MAX_BLOCK_SIZE: usize = 8192;
+
+/// Audio output backend selected on `TuttiSystemBuilder`.
+#[derive(Debug, Clone, Default)]
+pub enum AudioBackend {
+    /// Hardware output via CPAL (default).
+    #[default]
+    Cpal,
+    /// Headless output driven by a timer thread.
+    Null(NullBackendConfig),
+}
+
+/// How the null backend schedules callback cycles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NullPacing {
+    /// Sleep between blocks so one block takes one block's worth of wall time.
+    #[default]
+    RealTime,
+    /// Paced to the block duration like [`RealTime`](Self::RealTime), but
+    /// never skips ahead: after a stall, blocks render back to back until
+    /// the loop has caught up.
+    ///
+    /// When an output tap is enabled, rendering waits for the tap reader
+    /// instead of dropping samples, so the tapped stream has no gaps.
+    FreeRunning,
+}
+
+/// Configuration for [`AudioBackend::Null`].
+///
+/// # Example
+/// ```ignore
+/// let backend = AudioBackend::Null(
+///     NullBackendConfig::default()
+///         .sample_rate(48000.0)
+///         .block_size(256)
+///         .pacing(NullPacing::FreeRunning)
+///         .tap(48000),
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct NullBackendConfig {
+    pub sample_rate: f64,
+    pub block_size: usize,
+    pub channels: usize,
+    pub pacing: NullPacing,
+    /// Output tap capacity in frames. `None` disables the tap.
+    pub tap_frames: Option<usize>,
+}
+
+impl Default for NullBackendConfig {
+    fn default() -> Self {
+        Self {
+            sample_rate: 48000.0,
+            block_size: 512,
+            channels: 2,
+            pacing: NullPacing::RealTime,
+            tap_frames: None,
+        }
+    }
+}
+
+impl NullBackendConfig {
+    pub fn sample_rate(mut self, rate: f64) -> Self {
+        self.sample_rate = rate;
+        self
+    }
+
+    /// Frames per callback cycle (1-8192). Default: 512.
+    pub fn block_size(mut self, frames: usize) -> Self {
+        self.block_size = frames;
+        self
+    }
+
+    /// Output channels. The stereo mix goes to channels 0/1, the rest are silent.
+    pub fn channels(mut self, channels: usize) -> Self {
+        self.channels = channels;
+        self
+    }
+
+    pub fn pacing(mut self, pacing: NullPacing) -> Self {
+        self.pacing = pacing;
+        self
+    }
+
+    pub fn real_time(self) -> Self {
+        self.pacing(NullPacing::RealTime)
+    }
+
+    pub fn free_running(self) -> Self {
+        self.pacing(NullPacing::FreeRunning)
+    }
+
+    /// Capture rendered output into a ring buffer holding `frames` frames.
+    pub fn tap(mut self, frames: usize) -> Self {
+        self.tap_frames = Some(frames);
+        self
+    }
+
+    fn validate(&self) -> Result<()> {
+        if !(self.sample_rate.is_finite() && self.sample_rate > 0.0) {
+            return Err(Error::InvalidConfig(format!(
+                "Null backend sample rate must be positive, got {}",
+                self.sample_rate
+            )));
+        }
+        if self.block_size == 0 || self.block_size > MAX_BLOCK_SIZE {
+            return Err(Error::InvalidConfig(format!(
+                "Null backend block size must be 1-{MAX_BLOCK_SIZE}, got {}",
+                self.block_size
+            )));
+        }
+        if self.channels == 0 {
+            return Err(Error::InvalidConfig(
+                "Null backend needs at least one channel".into(),
+            ));
+        }
+        if self.tap_frames == Some(0) {
+            return Err(Error::InvalidConfig(
+                "Null backend tap capacity must be non-zero".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Reader side of the null backend's output tap.
+///
+/// Samples are interleaved with [`channels`](Self::channels) channels per frame.
+pub struct OutputTap {
+    consumer: HeapCons<f32>,
+    channels: usize,
+    dropped: Arc<AtomicU64>,
+}
+
+impl OutputTap {
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Frames currently buffered.
+    pub fn available_frames(&self) -> usize {
+        self.consumer.occupied_len() / self.channels
+    }
+
+    /// Pop whole frames into `buf` (interleaved). Returns the number of frames read.
+    pub fn read(&mut self, buf: &mut [f32]) -> usize {
+        let frames = (buf.len() / self.channels).min(self.available_frames());
+        let samples = frames * self.channels;
+        self.consumer.pop_slice(&mut buf[..samples]);
+        frames
+    }
+
+    /// Block until `frames` frames have been read or `timeout` elapses.
+    ///
+    /// Returns the interleaved samples read so far.
+    pub fn read_frames(&mut self, frames: usize, timeout: Duration) -> Vec<f32> {
+        let mut out = vec![0.0; frames * self.channels];
+        let mut filled = 0;
+        let deadline = Instant::now() + timeout;
+
+        while filled < frames {
+            filled += self.read(&mut out[filled * self.channels..]);
+            if filled < frames {
+                if Instant::now() >= deadline {
+                    break;
+                }
+                std::thread::sleep(Duration::from_micros(200));
+            }
+        }
+
+        out.truncate(filled * self.channels);
+        out
+    }
+
+    /// Discard everything buffered so far.
+    pub fn clear(&mut self) {
+        self.consumer.clear();
+    }
+
+    /// Frames dropped because the tap was full (real-time pacing only).
+    pub fn dropped_frames(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+struct TapWriter {
+    producer: HeapProd<f32>,
+    channels: usize,
+    dropped: Arc<AtomicU64>,
+}
+
+fn output_tap(frames: usize, channels: usize) -> (TapWriter, OutputTap) {
+    let rb = HeapRb::<f32>::new(frames * channels);
+    let (producer, consumer) = rb.split();
+    let dropped = Arc::new(AtomicU64::new(0));
+    (
+        TapWriter {
+            producer,
+            channels,
+            dropped: dropped.clone(),
+        },
+        OutputTap {
+            consumer,
+            channels,
+            dropped,
+        },
+    )
+}
+
+pub(crate) struct NullEngine {
+    config: NullBackendConfig,
+    running: Arc<AtomicFlag>,
+    thread: Option<JoinHandle<()>>,
+    tap: Option<OutputTap>,
+}
+
+impl NullEngine {
+    pub(crate) fn new(config: NullBackendConfig) -> Result<Self> {
+        config.validate()?;
+        Ok(Self {
+            config,
+            running: Arc::new(AtomicFlag::new(false)),
+            thread: None,
+            tap: None,
+        })
+    }
+
+    pub(crate) fn start(&mut self, state: AudioCallbackState) -> Result<()> {
+        if self.thread.is_some() {
+            return Ok(());
+        }
+
+        let writer = self.config.tap_frames.map(|frames| {
+            let (writer, tap) = output_tap(frames, self.config.channels);
+            self.tap = Some(tap);
+            writer
+        });
+
+        let config = self.config.clone();
+        let running = self.running.clone();
+        running.set(true);
+
+        let thread = std::thread::Builder::new()
+            .name("tutti-null-audio".into())
+            .spawn(move || run(state, config, running, writer))
+            .map_err(Error::Io)?;
+
+        self.thread = Some(thread);
+        Ok(())
+    }
+
+    pub(crate) fn sample_rate(&self) -> f64 {
+        self.config.sample_rate
+    }
+
+    pub(crate) fn channels(&self) -> usize {
+        self.config.channels
+    }
+
+    pub(crate) fn is_running(&self) -> bool {
+        self.running.get()
+    }
+
+    pub(crate) fn take_tap(&mut self) -> Option<OutputTap> {
+        self.tap.take()
+    }
+
+    fn stop(&mut self) {
+        self.running.set(false);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for NullEngine {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn run(
+    state: AudioCallbackState,
+    config: NullBackendConfig,
+    running: Arc<AtomicFlag>,
+    mut tap: Option<TapWriter>,
+) {
+    let frames = config.block_size;
+    let channels = config.channels;
+    let block_duration = Duration::from_secs_f64(frames as f64 / config.sample_rate);
+
+    let mut output_f32 = vec![0.0f32; frames * 2];
+    let mut interleaved = vec![0.0f32; frames * channels];
+    let mut metering_ctx = MeteringContext::new();
+    let mut deadline = Instant::now();
+
+    while running.get() {
+        let start = Instant::now();
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            crate::callback::process_audio(&state, &mut output_f32, start);
+            state
+                .metering
+                .update_rt(&output_f32, frames, start.elapsed(), &mut metering_ctx);
+        }));
+        if result.is_err() {
+            output_f32.fill(0.0);
+        }
+
+        if let Some(ref mut tap) = tap {
+            interleave(&output_f32, &mut interleaved, channels);
+            write_tap(tap, &interleaved, config.pacing, &running);
+        }
+
+        deadline += block_duration;
+        let now = Instant::now();
+        if deadline > now {
+            std::thread::sleep(deadline - now);
+        } else if config.pacing == NullPacing::RealTime && now - deadline > block_duration * 4 {
+            // Fell far behind (debugger, suspended VM): resync instead of bursting
+            deadline = now;
+        }
+    }
+}
+
+/// Spread the stereo mix across `channels` (extra channels are silent).
+#[inline]
+fn interleave(stereo: &[f32], out: &mut [f32], channels: usize) {
+    for (frame, chunk) in out.chunks_exact_mut(channels).enumerate() {
+        for (ch, sample) in chunk.iter_mut().enumerate() {
+            *sample = if ch < 2 { stereo[frame * 2 + ch] } else { 0.0 };
+        }
+    }
+}
+
+fn write_tap(tap: &mut TapWriter, samples: &[f32], pacing: NullPacing, running: &AtomicFlag) {
+    match pacing {
+        NullPacing::RealTime => {
+            // Only whole blocks, so the reader never sees a torn frame
+            if tap.producer.vacant_len() >= samples.len() {
+                tap.producer.push_slice(samples);
+            } else {
+                let frames = samples.len() / tap.channels;
+                tap.dropped.fetch_add(frames as u64, Ordering::Relaxed);
+            }
+        }
+        NullPacing::FreeRunning => {
+            let mut written = 0;
+            while written < samples.len() && running.get() {
+                written += tap.producer.push_slice(&samples[written..]);
+                if written < samples.len() {
+                    std::thread::sleep(Duration::from_micros(100));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_validation() {
+        assert!(NullBackendConfig::default().validate().is_ok());
+        assert!(NullBackendConfig::default()
+            .sample_rate(0.0)
+            .validate()
+            .is_err());
+        assert!(NullBackendConfig::default()
+            .block_size(0)
+            .validate()
+            .is_err());
+        assert!(NullBackendConfig::default()
+            .block_size(MAX_BLOCK_SIZE + 1)
+            .validate()
+            .is_err());
+        assert!(NullBackendConfig::default().channels(0).validate().is_err());
+        assert!(NullBackendConfig::default().tap(0).validate().is_err());
+    }
+
+    #[test]
+    fn test_interleave_extra_channels_silent() {
+        let stereo = [0.1, 0.2, 0.3, 0.4];
+        let mut out = [1.0; 8];
+        interleave(&stereo, &mut out, 4);
+        assert_eq!(out, [0.1, 0.2, 0.0, 0.0, 0.3, 0.4, 0.0, 0.0]);
+
+        let mut mono = [0.0; 2];
+        interleave(&stereo, &mut mono, 1);
+        assert_eq!(mono, [0.1, 0.3]);
+    }
+
+    #[test]
+    fn test_tap_reads_whole_frames() {
+        let (mut writer, mut tap) = output_tap(4, 2);
+        writer.producer.push_slice(&[1.0, 2.0, 3.0]);
+        assert_eq!(tap.available_frames(), 1);
+
+        let mut buf = [0.0; 8];
+        assert_eq!(tap.read(&mut buf), 1);
+        assert_eq!(&buf[..2], &[1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_real_time_tap_counts_drops() {
+        let (mut writer, tap) = output_tap(2, 2);
+        let running = AtomicFlag::new(true);
+        let block = [0.5; 6]; // 3 frames, tap holds 2
+
+        write_tap(&mut writer, &block, NullPacing::RealTime, &running);
+        assert_eq!(tap.available_frames(), 0);
+        assert_eq!(tap.dropped_frames(), 3);
+    }
+}
@@ -1,4 +1,7 @@
-//! CPAL audio output wrapper (requires std).
+//! Audio output backends (requires std).
+//!
+//! [`AudioEngine`] dispatches to either the CPAL device output or the
+//! headless [`NullEngine`](crate::null_output::NullEngine).
 
 #[allow(unused_extern_crates)]
 extern crate std;
@@ -6,6 +9,7 @@ extern crate std;
 use crate::callback::AudioCallbackState;
 use crate::compat::{String, Vec};
 use crate::metering::MeteringContext;
+use crate::null_output::{AudioBackend, NullEngine, OutputTap};
 use crate::{Error, Result};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use std::time::Instant;
@@ -19,7 +23,78 @@ struct StreamHandle(#[allow(dead_code)] cpal::Stream);
 
 unsafe impl Send for StreamHandle {}
 
-pub(crate) struct AudioEngine {
+pub(crate) enum AudioEngine {
+    Cpal(CpalEngine),
+    Null(NullEngine),
+}
+
+impl AudioEngine {
+    pub(crate) fn new(backend: AudioBackend, device_index: Option<usize>) -> Result<Self> {
+        match backend {
+            AudioBackend::Cpal => Ok(Self::Cpal(CpalEngine::new(device_index)?)),
+            AudioBackend::Null(config) => Ok(Self::Null(NullEngine::new(config)?)),
+        }
+    }
+
+    pub(crate) fn start(&mut self, state: AudioCallbackState) -> Result<()> {
+        match self {
+            Self::Cpal(engine) => engine.start(state),
+            Self::Null(engine) => engine.start(state),
+        }
+    }
+
+    pub(crate) fn sample_rate(&self) -> f64 {
+        match self {
+            Self::Cpal(engine) => engine.sample_rate,
+            Self::Null(engine) => engine.sample_rate(),
+        }
+    }
+
+    pub(crate) fn channels(&self) -> usize {
+        match self {
+            Self::Cpal(engine) => engine.channels,
+            Self::Null(engine) => engine.channels(),
+        }
+    }
+
+    pub(crate) fn is_running(&self) -> bool {
+        match self {
+            Self::Cpal(engine) => engine.is_running,
+            Self::Null(engine) => engine.is_running(),
+        }
+    }
+
+    /// Only meaningful for CPAL; the null backend has no device to select.
+    pub(crate) fn set_device(&mut self, index: Option<usize>) {
+        if let Self::Cpal(engine) = self {
+            engine.device_index = index;
+        }
+    }
+
+    pub(crate) fn device_name(&self) -> Result<String> {
+        match self {
+            Self::Cpal(engine) => Ok(get_device(engine.device_index)?.name()?),
+            Self::Null(_) => Ok("Null".into()),
+        }
+    }
+
+    pub(crate) fn take_output_tap(&mut self) -> Option<OutputTap> {
+        match self {
+            Self::Cpal(_) => None,
+            Self::Null(engine) => engine.take_tap(),
+        }
+    }
+
+    pub(crate) fn list_devices() -> Result<Vec<String>> {
+        cpal::default_host()
+            .output_devices()?
+            .enumerate()
+            .map(|(i, d)| Ok(format!("{i}: {}", d.name()?)))
+            .collect()
+    }
+}
+
+pub(crate) struct CpalEngine {
     sample_rate: f64,
     channels: usize,
     is_running: bool,
@@ -27,8 +102,8 @@ pub(crate) struct AudioEngine {
     _stream: Option<StreamHandle>,
 }
 
-impl AudioEngine {
-    pub(crate) fn new(device_index: Option<usize>) -> Result<Self> {
+impl CpalEngine {
+    fn new(device_index: Option<usize>) -> Result<Self> {
         let device = get_device(device_index)?;
         let config = device.default_output_config()?;
 
@@ -41,7 +116,7 @@ impl AudioEngine {
         })
     }
 
-    pub(crate) fn start(&mut self, state: AudioCallbackState) -> Result<()> {
+    fn start(&mut self, state: AudioCallbackState) -> Result<()> {
         if self.is_running {
             return Ok(());
         }
@@ -66,34 +141,6 @@ impl AudioEngine {
 
         Ok(())
     }
-
-    pub(crate) fn sample_rate(&self) -> f64 {
-        self.sample_rate
-    }
-
-    pub(crate) fn channels(&self) -> usize {
-        self.channels
-    }
-
-    pub(crate) fn is_running(&self) -> bool {
-        self.is_running
-    }
-
-    pub(crate) fn set_device(&mut self, index: Option<usize>) {
-        self.device_index = index;
-    }
-
-    pub(crate) fn device_name(&self) -> Result<String> {
-        Ok(get_device(self.device_index)?.name()?)
-    }
-
-    pub(crate) fn list_devices() -> Result<Vec<String>> {
-        cpal::default_host()
-            .output_devices()?
-            .enumerate()
-            .map(|(i, d)| Ok(format!("{i}: {}", d.name()?)))
-            .collect()
-    }
 }
 
 fn get_device(index: Option<usize>) -> Result<cpal::Device> {
@@ -1,7 +1,7 @@
 //! Tutti system - unified audio engine with transport, metering, and DSP graph.
 
 #[cfg(feature = "std")]
-use crate::callback::AudioCallbackState;
+use crate::callback::{AudioCallbackState, SyncOutputSlot};
 use crate::compat::{Arc, Mutex};
 #[cfg(feature = "std")]
 use crate::compat::{String, Vec};
@@ -13,11 +13,24 @@ use crate::transport::{
     ClickSettings, ClickState, TransportClock, TransportHandle, TransportManager,
 };
 
+#[cfg(feature = "std")]
+use crate::null_output::{AudioBackend, OutputTap};
 #[cfg(feature = "std")]
 use crate::output::AudioEngine;
+#[cfg(feature = "std")]
+use crate::transport::TransportSyncOutput;
 
 #[cfg(feature = "midi")]
 use crate::midi::MidiRoutingTable;
+#[cfg(all(feature = "std", feature = "midi"))]
+use crate::{
+    callback::{MidiEffectSlot, TransportMidiSlot},
+    compat::{Box, HashMap},
+    midi::{
+        effect::{render_offline, MidiEffectRack},
+        MidiEffect, MidiSnapshot, TransportMidiSource,
+    },
+};
 
 /// Complete audio system with DSP graph, transport, metering, PDC, and neural audio.
 pub struct TuttiSystem {
@@ -32,9 +45,25 @@ pub struct TuttiSystem {
     #[cfg(not(feature = "std"))]
     channels: usize,
 
+    /// Sync generator slot shared with the audio callback
+    #[cfg(feature = "std")]
+    sync_output: SyncOutputSlot,
+
     /// MIDI routing table for channel/port/layer routing
     #[cfg(feature = "midi")]
     midi_routing: Mutex<MidiRoutingTable>,
+
+    /// Transport-driven MIDI source slot shared with the audio callback
+    #[cfg(all(feature = "std", feature = "midi"))]
+    transport_midi: TransportMidiSlot,
+
+    /// Running MIDI effect chains shared with the audio callback
+    #[cfg(all(feature = "std", feature = "midi"))]
+    midi_effects: MidiEffectSlot,
+
+    /// Untouched copies of each unit's effect chain, for offline renders
+    #[cfg(all(feature = "std", feature = "midi"))]
+    midi_effect_chains: Mutex<HashMap<u64, Vec<Box<dyn MidiEffect>>>>,
 }
 
 impl TuttiSystem {
@@ -66,6 +95,72 @@ impl TuttiSystem {
         self.engine.lock().set_device(index);
     }
 
+    /// Take the reader end of the null backend's output tap.
+    ///
+    /// Returns `None` for the CPAL backend, when no tap was configured via
+    /// [`NullBackendConfig::tap`](crate::NullBackendConfig::tap), or when the
+    /// tap has already been taken.
+    #[cfg(feature = "std")]
+    pub fn take_output_tap(&self) -> Option<OutputTap> {
+        self.engine.lock().take_output_tap()
+    }
+
+    /// Install (or remove, with `None`) the sync generator fed by the audio callback.
+    ///
+    /// The output receives every rendered block's per-frame transport
+    /// positions; `tutti-midi-io`'s `MidiSyncGenerator` turns them into
+    /// MIDI Clock / SPP / MTC.
+    #[cfg(feature = "std")]
+    pub fn set_sync_output(&self, output: Option<Arc<dyn TransportSyncOutput>>) {
+        self.sync_output.store(output.map(Arc::new));
+    }
+
+    /// Install (or remove, with `None`) the MIDI source driven by the
+    /// transport, such as `tutti-midi-io`'s `MidiFilePlayer`.
+    ///
+    /// The source is called for every frame before the graph renders it, so
+    /// its events reach their targets sample-accurately. Export contexts
+    /// created while it is installed include its events.
+    #[cfg(all(feature = "std", feature = "midi"))]
+    pub fn set_transport_midi_source(&self, source: Option<Arc<dyn TransportMidiSource>>) {
+        self.transport_midi.store(source.map(Arc::new));
+    }
+
+    /// Put a MIDI effect chain in front of a unit (an empty chain removes it).
+    ///
+    /// Everything queued for the unit each frame, from routing, transport
+    /// sources or `queue_midi`, runs through the chain first. Chains on other
+    /// units keep running undisturbed; notes the replaced chain left sounding
+    /// get their note-offs on the next frame.
+    #[cfg(all(feature = "std", feature = "midi"))]
+    pub fn set_midi_effects(&self, unit_id: u64, effects: Vec<Box<dyn MidiEffect>>) {
+        let mut chains = self.midi_effect_chains.lock();
+        let rack = self
+            .midi_effects
+            .load()
+            .with_chain(unit_id, effects.clone());
+        self.midi_effects.store(Arc::new(rack));
+        if effects.is_empty() {
+            chains.remove(&unit_id);
+        } else {
+            chains.insert(unit_id, effects);
+        }
+    }
+
+    /// Replace each unit's events in an export snapshot with the output of
+    /// its MIDI effect chain, as it would play live.
+    #[cfg(all(feature = "std", feature = "midi"))]
+    pub fn render_midi_effects(&self, snapshot: &mut MidiSnapshot) {
+        for (&unit_id, effects) in self.midi_effect_chains.lock().iter() {
+            let input = snapshot.events(unit_id);
+            if input.is_empty() {
+                continue;
+            }
+            let output = render_offline(effects, input);
+            snapshot.replace_events(unit_id, output);
+        }
+    }
+
     pub fn channels(&self) -> usize {
         #[cfg(feature = "std")]
         {
@@ -163,18 +258,30 @@ impl TuttiSystem {
         self.net.lock().clone_net()
     }
 
+    /// Context for an offline render, following the live tempo map and loop
+    /// range so transport-aware nodes render exactly as they play.
     pub fn create_export_context(&self) -> crate::ExportContext {
-        use crate::transport::ExportConfig;
+        use crate::transport::{ExportConfig, ExportTimeline};
 
         let transport_handle =
             TransportHandle::new(self.transport.clone(), self.click_state.clone());
 
-        crate::ExportContext::new(ExportConfig {
+        let timeline = ExportTimeline::new(&ExportConfig {
             start_beat: 0.0,
             tempo: transport_handle.get_tempo(),
             sample_rate: self.sample_rate,
             loop_range: transport_handle.get_loop_range(),
         })
+        .with_tempo_map(self.transport.tempo_map_snapshot());
+        #[allow(unused_mut)]
+        let mut context = crate::ExportContext::from_timeline(timeline);
+
+        #[cfg(all(feature = "std", feature = "midi"))]
+        if let Some(source) = self.transport_midi.load().as_ref() {
+            source.export_to(context.midi_snapshot_mut());
+        }
+
+        context
     }
 
     /// Configure MIDI routing.
@@ -239,6 +346,8 @@ impl TuttiSystem {
 
 #[derive(Default)]
 pub struct TuttiSystemBuilder {
+    #[cfg(feature = "std")]
+    backend: AudioBackend,
     #[cfg(feature = "std")]
     device_index: Option<usize>,
     #[cfg_attr(feature = "std", allow(dead_code))]
@@ -264,6 +373,26 @@ impl TuttiSystemBuilder {
         self
     }
 
+    /// Select the audio output backend. Default: [`AudioBackend::Cpal`].
+    ///
+    /// With [`AudioBackend::Null`] the sample rate and channel count come from
+    /// the [`NullBackendConfig`](crate::NullBackendConfig) instead of a device.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let system = TuttiSystem::builder()
+    ///     .backend(AudioBackend::Null(
+    ///         NullBackendConfig::default().free_running().tap(48000),
+    ///     ))
+    ///     .build()?;
+    /// let mut tap = system.take_output_tap().unwrap();
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn backend(mut self, backend: AudioBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+
     pub fn inputs(mut self, count: usize) -> Self {
         self.inputs = count;
         self
@@ -300,7 +429,7 @@ impl TuttiSystemBuilder {
     pub fn build(self) -> Result<TuttiSystem> {
         #[cfg(feature = "std")]
         let (sample_rate, mut engine) = {
-            let engine = AudioEngine::new(self.device_index)?;
+            let engine = AudioEngine::new(self.backend, self.device_index)?;
             let sample_rate = engine.sample_rate();
             (sample_rate, engine)
         };
@@ -325,11 +454,23 @@ impl TuttiSystemBuilder {
         #[cfg(feature = "midi")]
         let midi_routing = MidiRoutingTable::new();
 
+        #[cfg(feature = "std")]
+        let sync_output: SyncOutputSlot = Arc::new(arc_swap::ArcSwapOption::empty());
+
+        #[cfg(all(feature = "std", feature = "midi"))]
+        let transport_midi: TransportMidiSlot = Arc::new(arc_swap::ArcSwapOption::empty());
+
+        #[cfg(all(feature = "std", feature = "midi"))]
+        let midi_effects: MidiEffectSlot =
+            Arc::new(arc_swap::ArcSwap::from_pointee(MidiEffectRack::default()));
+
         #[cfg(feature = "std")]
         {
             let mut callback_state =
                 AudioCallbackState::new(transport.clone(), metering.clone(), sample_rate);
 
+            callback_state.set_sync_output(sync_output.clone());
+
             callback_state.set_net_backend(backend);
 
             // Set up click node for metronome (mixed into output automatically)
@@ -351,6 +492,8 @@ impl TuttiSystemBuilder {
                 transport.loop_start_beat_atomic().clone(),
                 transport.loop_end_beat_atomic().clone(),
             )
+            .with_tempo_map(transport.tempo_map_shared().clone())
+            .with_sync_state(transport.sync_state().clone())
             .with_position_writeback(transport.current_beat().clone());
             callback_state.set_transport_clock(clock);
 
@@ -367,6 +510,9 @@ impl TuttiSystemBuilder {
 
                 // Share the routing snapshot with the callback
                 callback_state.set_midi_routing(midi_routing.snapshot_arc());
+
+                callback_state.set_transport_midi(transport_midi.clone());
+                callback_state.set_midi_effects(midi_effects.clone());
             }
 
             engine.start(callback_state)?;
@@ -386,8 +532,16 @@ impl TuttiSystemBuilder {
             sample_rate,
             #[cfg(not(feature = "std"))]
             channels: outputs,
+            #[cfg(feature = "std")]
+            sync_output,
             #[cfg(feature = "midi")]
             midi_routing: Mutex::new(midi_routing),
+            #[cfg(all(feature = "std", feature = "midi"))]
+            transport_midi,
+            #[cfg(all(feature = "std", feature = "midi"))]
+            midi_effects,
+            #[cfg(all(feature = "std", feature = "midi"))]
+            midi_effect_chains: Mutex::new(HashMap::new()),
         })
     }
 }
@@ -437,4 +591,131 @@ mod tests {
         assert_eq!(system.pdc().get_channel_compensation(0), 1024);
         assert_eq!(system.pdc().get_channel_compensation(1), 0);
     }
+
+    fn null_system(config: crate::NullBackendConfig) -> TuttiSystem {
+        TuttiSystem::builder()
+            .backend(AudioBackend::Null(config))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_null_backend_config() {
+        let system = null_system(
+            crate::NullBackendConfig::default()
+                .sample_rate(44100.0)
+                .block_size(128)
+                .channels(4),
+        );
+
+        assert_eq!(system.sample_rate(), 44100.0);
+        assert_eq!(system.channels(), 4);
+        assert!(system.is_running());
+        assert_eq!(system.current_output_device_name().unwrap(), "Null");
+        // No tap configured
+        assert!(system.take_output_tap().is_none());
+    }
+
+    #[test]
+    fn test_null_backend_tap_renders_graph() {
+        let system = null_system(
+            crate::NullBackendConfig::default()
+                .block_size(256)
+                .free_running()
+                .tap(4096),
+        );
+        let mut tap = system.take_output_tap().unwrap();
+        assert!(system.take_output_tap().is_none());
+
+        system.graph_mut(|net| {
+            use fundsp::prelude::*;
+            net.add(dc((0.25f32, -0.25f32))).master();
+        });
+
+        // Skip blocks rendered before the graph was committed
+        let _ = tap.read_frames(8192, std::time::Duration::from_secs(5));
+        let samples = tap.read_frames(1024, std::time::Duration::from_secs(5));
+        assert_eq!(samples.len(), 1024 * 2);
+        for frame in samples.chunks_exact(2) {
+            assert!((frame[0] - 0.25).abs() < 1e-6);
+            assert!((frame[1] + 0.25).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_null_backend_free_running_advances_transport() {
+        let system = null_system(crate::NullBackendConfig::default().free_running());
+
+        system.transport().tempo(120.0).play();
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while system.transport_manager().get_current_beat() < 4.0 {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "transport did not advance on the null backend"
+            );
+            std::thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn test_null_backend_free_running_is_paced() {
+        let system = null_system(crate::NullBackendConfig::default().free_running());
+
+        system.transport().tempo(120.0).play();
+        std::thread::sleep(std::time::Duration::from_millis(250));
+
+        // Half a beat in real time; an unpaced loop would be thousands of beats in
+        let beat = system.transport_manager().get_current_beat();
+        assert!(beat < 2.0, "free-running loop ran ahead to beat {beat}");
+    }
+
+    #[derive(Default)]
+    struct RecordingSync {
+        /// (playing, first beat, last beat, frames)
+        blocks: Mutex<Vec<(bool, f64, f64, usize)>>,
+    }
+
+    impl TransportSyncOutput for RecordingSync {
+        fn process_block(&self, block: &crate::SyncBlock<'_>) {
+            self.blocks.lock().push((
+                block.playing,
+                block.beats[0],
+                block.beats[block.frames() - 1],
+                block.frames(),
+            ));
+        }
+    }
+
+    #[test]
+    fn test_sync_output_receives_per_frame_beats() {
+        let system = null_system(
+            crate::NullBackendConfig::default()
+                .block_size(256)
+                .free_running(),
+        );
+        let sync = Arc::new(RecordingSync::default());
+        system.set_sync_output(Some(sync.clone()));
+
+        system.transport().tempo(120.0).play();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while system.transport_manager().get_current_beat() < 2.0 {
+            assert!(std::time::Instant::now() < deadline);
+            std::thread::yield_now();
+        }
+        system.set_sync_output(None);
+
+        let beat_per_frame = 2.0 / 48000.0;
+        let blocks = sync.blocks.lock();
+        let playing: Vec<_> = blocks.iter().filter(|b| b.0).collect();
+        assert!(playing.len() > 2);
+        for block in &playing {
+            assert_eq!(block.3, 256);
+            assert!((block.2 - block.1 - 255.0 * beat_per_frame).abs() < 1e-9);
+        }
+        // Consecutive rolling blocks continue where the previous one ended
+        for pair in playing.windows(2) {
+            assert!((pair[1].1 - pair[0].2 - beat_per_frame).abs() < 1e-9);
+        }
+    }
 }
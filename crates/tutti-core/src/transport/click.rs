@@ -1,9 +1,11 @@
 //! Metronome click AudioUnit - generates click sounds synced to transport.
 //!
 //! The click node reads transport state via [`TransportReader`] and settings
-//! (volume, accent, mode) from [`ClickSettings`].
+//! (volume, accent, mode) from [`ClickSettings`]. When the transport exposes a
+//! tempo map, clicks follow its meter: one click per denominator note, with
+//! accents counted from each bar line.
 
-use super::TransportReader;
+use super::{TempoMapSnapshot, TransportReader};
 use crate::compat::{Arc, Vec};
 use crate::{AtomicFloat, AtomicU32, AtomicU8, Ordering, TransportHandle};
 use fundsp::audionode::AudioNode;
@@ -100,7 +102,11 @@ pub struct ClickNode<R: TransportReader = TransportHandle> {
     click_accent: Vec<f32>,
     click_pos: usize,
     is_accent: bool,
-    last_click_beat: i64,
+    /// Last click as (beat, 0), or (bar, pulse) when following a meter.
+    last_click: Option<(i64, u32)>,
+    /// Meter source, refreshed once per beat.
+    tempo_map: Option<Arc<TempoMapSnapshot>>,
+    tempo_map_beat: i64,
 }
 
 impl ClickNode<TransportHandle> {
@@ -122,7 +128,9 @@ impl<R: TransportReader + Clone> ClickNode<R> {
             click_accent,
             click_pos: 0,
             is_accent: false,
-            last_click_beat: -1,
+            last_click: None,
+            tempo_map: None,
+            tempo_map_beat: i64::MIN,
         }
     }
 
@@ -156,6 +164,18 @@ impl<R: TransportReader + Clone> ClickNode<R> {
         }
         (beat as u32).is_multiple_of(accent_every)
     }
+
+    fn is_accent_pulse(&self, pulse: u32) -> bool {
+        let accent_every = self.settings.accent_every();
+        accent_every != 0 && pulse.is_multiple_of(accent_every)
+    }
+
+    /// (bar, pulse in bar) at `beat`, with pulses of the denominator note.
+    /// Taken from the map's BBT so clicks and bar/beat readouts agree.
+    fn meter_pulse(tempo_map: &TempoMapSnapshot, beat: f64) -> (i64, u32) {
+        let bbt = tempo_map.beats_to_bbt(beat);
+        (bbt.bar as i64, bbt.beat - 1)
+    }
 }
 
 impl<R: TransportReader + Clone + Send + Sync + 'static> AudioNode for ClickNode<R> {
@@ -180,19 +200,32 @@ impl<R: TransportReader + Clone + Send + Sync + 'static> AudioNode for ClickNode
 
         if !should_play {
             self.click_pos = 0;
-            self.last_click_beat = -1;
+            self.last_click = None;
             return [0.0, 0.0].into();
         }
 
         let current_beat = self.transport.current_beat();
         let beat_int = current_beat.floor() as i64;
 
-        // Trigger click on new beat. The `beat_int != self.last_click_beat` check
-        // handles both forward advancement AND backward jumps from loop wrapping.
-        if beat_int != self.last_click_beat {
-            self.last_click_beat = beat_int;
+        if beat_int != self.tempo_map_beat {
+            self.tempo_map_beat = beat_int;
+            self.tempo_map = self.transport.tempo_map();
+        }
+
+        let (click, is_accent) = match self.tempo_map {
+            Some(ref tempo_map) => {
+                let (bar, pulse) = Self::meter_pulse(tempo_map, current_beat);
+                ((bar, pulse), self.is_accent_pulse(pulse))
+            }
+            None => ((beat_int, 0), self.is_accent_beat(beat_int)),
+        };
+
+        // Trigger click on a new beat/pulse. Comparing for inequality handles
+        // both forward advancement AND backward jumps from loop wrapping.
+        if self.last_click != Some(click) {
+            self.last_click = Some(click);
             self.click_pos = 0;
-            self.is_accent = self.is_accent_beat(beat_int);
+            self.is_accent = is_accent;
         }
 
         let click_buffer = if self.is_accent {
@@ -212,7 +245,9 @@ impl<R: TransportReader + Clone + Send + Sync + 'static> AudioNode for ClickNode
 
     fn reset(&mut self) {
         self.click_pos = 0;
-        self.last_click_beat = -1;
+        self.last_click = None;
+        self.tempo_map = None;
+        self.tempo_map_beat = i64::MIN;
     }
 
     fn set_sample_rate(&mut self, sample_rate: f64) {
@@ -252,6 +287,7 @@ mod tests {
         playing: Arc<AtomicBool>,
         recording: Arc<AtomicBool>,
         in_preroll: Arc<AtomicBool>,
+        tempo_map: Option<Arc<TempoMapSnapshot>>,
     }
 
     impl MockTransport {
@@ -261,6 +297,7 @@ mod tests {
                 playing: Arc::new(AtomicBool::new(false)),
                 recording: Arc::new(AtomicBool::new(false)),
                 in_preroll: Arc::new(AtomicBool::new(false)),
+                tempo_map: None,
             }
         }
 
@@ -303,6 +340,9 @@ mod tests {
         fn tempo(&self) -> f32 {
             120.0
         }
+        fn tempo_map(&self) -> Option<Arc<TempoMapSnapshot>> {
+            self.tempo_map.clone()
+        }
     }
 
     fn make_click() -> (MockTransport, Arc<ClickSettings>, ClickNode<MockTransport>) {
@@ -373,7 +413,7 @@ mod tests {
         // Advance to beat 7
         transport.set_beat(7.0);
         let _ = node.tick(&Frame::default());
-        assert_eq!(node.last_click_beat, 7);
+        assert_eq!(node.last_click, Some((7, 0)));
 
         // Simulate loop wrap: beat jumps backward from 7 to 4
         transport.set_beat(4.0);
@@ -386,7 +426,8 @@ mod tests {
             }
         }
         assert_eq!(
-            node.last_click_beat, 4,
+            node.last_click,
+            Some((4, 0)),
             "Should reset to beat 4 after loop wrap"
         );
         assert!(found_nonzero, "Click should play after loop wrap");
@@ -402,6 +443,45 @@ mod tests {
         assert!(node.is_accent_beat(4));
     }
 
+    #[test]
+    fn test_click_follows_meter_changes() {
+        use crate::transport::TempoMap;
+
+        let mut map = TempoMap::new(120.0, 44100.0);
+        map.add_meter_change(2, 7, 8);
+        let transport = MockTransport {
+            tempo_map: Some(map.snapshot()),
+            ..MockTransport::new()
+        };
+        let settings = Arc::new(ClickSettings::new());
+        settings.set_mode(MetronomeMode::Always);
+        settings.set_accent_every(7);
+        let mut node = ClickNode::with_transport(transport.clone(), settings, 44100.0);
+        transport.set_playing(true);
+
+        // Bar 1 is 4/4: quarter-note clicks
+        transport.set_beat(3.0);
+        let _ = node.tick(&Frame::default());
+        assert_eq!(node.last_click, Some((1, 3)));
+
+        // Bar 2 is 7/8 from beat 4: eighth-note clicks, accented on the downbeat
+        transport.set_beat(4.0);
+        let _ = node.tick(&Frame::default());
+        assert_eq!(node.last_click, Some((2, 0)));
+        assert!(node.is_accent);
+
+        transport.set_beat(4.5);
+        let _ = node.tick(&Frame::default());
+        assert_eq!(node.last_click, Some((2, 1)));
+        assert!(!node.is_accent);
+
+        // Bar 3 starts 3.5 beats later
+        transport.set_beat(7.5);
+        let _ = node.tick(&Frame::default());
+        assert_eq!(node.last_click, Some((3, 0)));
+        assert!(node.is_accent);
+    }
+
     #[test]
     fn test_preroll_only_mode() {
         let (transport, settings, mut node) = make_click();
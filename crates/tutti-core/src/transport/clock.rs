@@ -1,7 +1,10 @@
 //! Sample-accurate transport clock.
 
+use super::sync::SyncState;
+use super::tempo_map::TempoMapSnapshot;
 use crate::compat::{any, Arc};
 use crate::lockfree::{AtomicDouble, AtomicFlag, AtomicFloat};
+use arc_swap::ArcSwap;
 use fundsp::prelude::*;
 
 pub struct TransportClock {
@@ -14,9 +17,13 @@ pub struct TransportClock {
     loop_enabled: Option<Arc<AtomicFlag>>,
     loop_start: Option<Arc<AtomicDouble>>,
     loop_end: Option<Arc<AtomicDouble>>,
+    tempo_map: Option<Arc<ArcSwap<TempoMapSnapshot>>>,
+    sync_state: Option<Arc<SyncState>>,
     sample_rate: f64,
     beat_per_sample: f64,
     last_tempo: f32,
+    /// Whether `beat_per_sample` came from the tempo map on the last sample.
+    following_map: bool,
 }
 
 impl TransportClock {
@@ -38,9 +45,12 @@ impl TransportClock {
             loop_enabled: None,
             loop_start: None,
             loop_end: None,
+            tempo_map: None,
+            sync_state: None,
             sample_rate,
             beat_per_sample: Self::calculate_beat_per_sample(initial_tempo, sample_rate),
             last_tempo: initial_tempo,
+            following_map: false,
         }
     }
 
@@ -66,6 +76,25 @@ impl TransportClock {
         self
     }
 
+    /// Follow tempo automation (including ramps) from the shared tempo map.
+    ///
+    /// While the map has tempo changes and the sync source is internal, the
+    /// clock advances at the map's tempo without writing the shared tempo
+    /// atomic, which stays owned by `set_tempo()` and external sync.
+    /// Otherwise the tempo atomic sets the rate.
+    pub fn with_tempo_map(mut self, tempo_map: Arc<ArcSwap<TempoMapSnapshot>>) -> Self {
+        self.tempo_map = Some(tempo_map);
+        self
+    }
+
+    /// Sync state deciding whether the tempo map is followed (only for
+    /// [`SyncSource::Internal`](super::SyncSource::Internal)). Without one
+    /// the clock always counts as internal.
+    pub fn with_sync_state(mut self, sync_state: Arc<SyncState>) -> Self {
+        self.sync_state = Some(sync_state);
+        self
+    }
+
     pub fn with_position_writeback(mut self, writeback: Arc<AtomicDouble>) -> Self {
         self.position_writeback = Some(writeback);
         self
@@ -95,12 +124,64 @@ impl TransportClock {
         self.current_beat
     }
 
+    /// Advance one sample and return the position of that sample (before
+    /// advancing). Full-precision equivalent of [`tick`](AudioUnit::tick).
+    #[inline]
+    pub fn next_beat(&mut self) -> f64 {
+        // Apply pending seek
+        self.apply_pending_seek();
+
+        // Update tempo if changed
+        if !self.follow_tempo_map() {
+            self.update_tempo_if_changed();
+        }
+
+        let beat = self.current_beat;
+
+        // Advance if not paused
+        if !self.paused.get() {
+            self.current_beat += self.beat_per_sample;
+
+            // Sample-accurate loop wrapping
+            self.apply_loop_wrap();
+        }
+
+        // Write back position for UI sync (per-sample in tick mode)
+        if let Some(ref writeback) = self.position_writeback {
+            writeback.set(self.current_beat);
+        }
+
+        beat
+    }
+
+    /// Take this sample's rate from the tempo map; false when the map has no
+    /// tempo changes or an external source drives the tempo.
+    #[inline]
+    fn follow_tempo_map(&mut self) -> bool {
+        let Some(ref tempo_map) = self.tempo_map else {
+            return false;
+        };
+        if self.sync_state.as_ref().is_some_and(|s| s.is_external()) {
+            return false;
+        }
+        let map = tempo_map.load();
+        if !map.has_tempo_changes() {
+            return false;
+        }
+        let bpm = map.tempo_at(self.current_beat);
+        self.beat_per_sample = Self::calculate_beat_per_sample(bpm, self.sample_rate);
+        self.following_map = true;
+        true
+    }
+
     #[inline]
     fn update_tempo_if_changed(&mut self) {
         let current_tempo = self.tempo.get();
-        if (current_tempo - self.last_tempo).abs() > 0.001 {
+        // Coming off the map, the rate is stale even if the atomic isn't
+        if self.following_map || (current_tempo - self.last_tempo).abs() > 0.001 {
             self.beat_per_sample = Self::calculate_beat_per_sample(current_tempo, self.sample_rate);
             self.last_tempo = current_tempo;
+            self.following_map = false;
         }
     }
 
@@ -175,35 +256,17 @@ impl AudioUnit for TransportClock {
 
     #[inline]
     fn tick(&mut self, _input: &[f32], output: &mut [f32]) {
-        // Apply pending seek
-        self.apply_pending_seek();
-
-        // Update tempo if changed
-        self.update_tempo_if_changed();
-
-        // Output current beat
-        output[0] = self.current_beat as f32;
-
-        // Advance if not paused
-        if !self.paused.get() {
-            self.current_beat += self.beat_per_sample;
-
-            // Sample-accurate loop wrapping
-            self.apply_loop_wrap();
-        }
-
-        // Write back position for UI sync (per-sample in tick mode)
-        if let Some(ref writeback) = self.position_writeback {
-            writeback.set(self.current_beat);
-        }
+        output[0] = self.next_beat() as f32;
     }
 
     fn process(&mut self, size: usize, _input: &BufferRef, output: &mut BufferMut) {
         // Apply pending seek
         self.apply_pending_seek();
 
-        // Update tempo if changed
-        self.update_tempo_if_changed();
+        // Update tempo if changed (ramps step once per buffer here)
+        if !self.follow_tempo_map() {
+            self.update_tempo_if_changed();
+        }
 
         let is_paused = self.paused.get();
 
@@ -283,9 +346,12 @@ impl Clone for TransportClock {
             loop_enabled: self.loop_enabled.as_ref().map(Arc::clone),
             loop_start: self.loop_start.as_ref().map(Arc::clone),
             loop_end: self.loop_end.as_ref().map(Arc::clone),
+            tempo_map: self.tempo_map.as_ref().map(Arc::clone),
+            sync_state: self.sync_state.as_ref().map(Arc::clone),
             sample_rate: self.sample_rate,
             beat_per_sample: self.beat_per_sample,
             last_tempo: self.last_tempo,
+            following_map: self.following_map,
         }
     }
 }
@@ -490,4 +556,92 @@ mod tests {
             output[0]
         );
     }
+
+    #[test]
+    fn test_clock_follows_tempo_ramp() {
+        use crate::transport::{TempoCurve, TempoMap};
+
+        let sample_rate = 48000.0;
+        let mut map = TempoMap::new(60.0, sample_rate);
+        map.add_tempo_point_with_curve(0.0, 60.0, TempoCurve::Linear);
+        map.add_tempo_point(4.0, 120.0);
+        let shared = Arc::new(ArcSwap::new(map.snapshot()));
+
+        let tempo = Arc::new(AtomicFloat::new(60.0));
+        let paused = Arc::new(AtomicFlag::new(false));
+        let mut clock =
+            TransportClock::new(tempo.clone(), paused, sample_rate).with_tempo_map(shared);
+
+        for _ in 0..(2.0 * sample_rate) as usize {
+            clock.next_beat();
+        }
+
+        let expected = map.seconds_to_beats(2.0);
+        assert!(
+            (clock.current_beat() - expected).abs() < 1e-3,
+            "expected {expected}, got {}",
+            clock.current_beat()
+        );
+        // The shared tempo is left for set_tempo() and external sync
+        assert_eq!(tempo.get(), 60.0);
+    }
+
+    #[test]
+    fn test_clock_process_follows_tempo_change() {
+        use crate::transport::TempoMap;
+        use fundsp::buffer::{BufferRef, BufferVec};
+
+        let sample_rate = 48000.0;
+        let mut map = TempoMap::new(60.0, sample_rate);
+        map.add_tempo_point(0.0, 60.0);
+        map.add_tempo_point(1.0, 120.0);
+        let shared = Arc::new(ArcSwap::new(map.snapshot()));
+
+        let tempo = Arc::new(AtomicFloat::new(60.0));
+        let paused = Arc::new(AtomicFlag::new(false));
+        let mut clock = TransportClock::new(tempo, paused, sample_rate).with_tempo_map(shared);
+
+        // One second at 60 BPM reaches beat 1, the next at 120 BPM beat 3
+        let mut buffer = BufferVec::new(1);
+        for _ in 0..(2.0 * sample_rate) as usize / 64 {
+            clock.process(64, &BufferRef::new(&[]), &mut buffer.buffer_mut());
+        }
+        assert!(
+            (clock.current_beat() - 3.0).abs() < 0.01,
+            "expected 3.0, got {}",
+            clock.current_beat()
+        );
+    }
+
+    #[test]
+    fn test_external_sync_overrides_tempo_map() {
+        use crate::transport::{SyncSource, TempoMap};
+
+        let sample_rate = 48000.0;
+        let mut map = TempoMap::new(60.0, sample_rate);
+        map.add_tempo_point(0.0, 60.0);
+        map.add_tempo_point(1.0, 90.0);
+        let shared = Arc::new(ArcSwap::new(map.snapshot()));
+        let sync_state = Arc::new(SyncState::new());
+        sync_state.set_source(SyncSource::MidiClock);
+
+        // MIDI clock sets 120 BPM through the tempo atomic
+        let tempo = Arc::new(AtomicFloat::new(120.0));
+        let paused = Arc::new(AtomicFlag::new(false));
+        let mut clock = TransportClock::new(tempo, paused, sample_rate)
+            .with_tempo_map(shared)
+            .with_sync_state(sync_state.clone());
+
+        for _ in 0..sample_rate as usize {
+            clock.next_beat();
+        }
+        assert!((clock.current_beat() - 2.0).abs() < 1e-6);
+
+        // Back on the internal clock, the map takes over: beat 2 is at 90 BPM
+        sync_state.set_source(SyncSource::Internal);
+        for _ in 0..sample_rate as usize {
+            clock.next_beat();
+        }
+        assert!((clock.current_beat() - 3.5).abs() < 1e-3);
+    }
 }
@@ -3,8 +3,14 @@
 //! Provides a simulated transport that advances deterministically
 //! based on sample count rather than real-time clock.
 
+use super::TempoMapSnapshot;
+use super::BBT;
+use crate::compat::{Arc, AtomicU32, Ordering};
 use crate::lockfree::{AtomicDouble, AtomicFlag, AtomicFloat};
 
+/// Wraps left on a loop that repeats forever.
+const UNLIMITED_WRAPS: u32 = u32::MAX;
+
 /// Configuration for creating an export timeline.
 #[derive(Debug, Clone)]
 pub struct ExportConfig {
@@ -64,6 +70,11 @@ pub struct ExportTimeline {
     loop_end: AtomicDouble,
     /// Whether loop is enabled.
     loop_enabled: AtomicFlag,
+    /// Loop wraps left before playing on past the loop end
+    /// ([`UNLIMITED_WRAPS`] loops forever).
+    loop_wraps_left: AtomicU32,
+    /// Tempo map; its tempo changes replace the constant tempo.
+    tempo_map: Option<Arc<TempoMapSnapshot>>,
 }
 
 impl ExportTimeline {
@@ -84,41 +95,133 @@ impl ExportTimeline {
             loop_start: AtomicDouble::new(loop_start),
             loop_end: AtomicDouble::new(loop_end),
             loop_enabled: AtomicFlag::new(loop_enabled),
+            loop_wraps_left: AtomicU32::new(UNLIMITED_WRAPS),
+            tempo_map: None,
         }
     }
 
+    /// Follow `tempo_map`, usually the live transport's snapshot. As on the
+    /// live clock, its tempo changes replace the constant config tempo; a map
+    /// without any still provides meters to the click and other readers.
+    pub fn with_tempo_map(mut self, tempo_map: Arc<TempoMapSnapshot>) -> Self {
+        self.tempo_map = Some(tempo_map);
+        self
+    }
+
+    /// The tempo map, if it has tempo changes to follow.
+    #[inline]
+    fn timing_map(&self) -> Option<&TempoMapSnapshot> {
+        self.tempo_map
+            .as_deref()
+            .filter(|map| map.has_tempo_changes())
+    }
+
+    /// Play the loop region `count` times in total, then carry on past its
+    /// end. `None` loops forever (the default).
+    pub fn set_loop_count(&self, count: Option<u32>) {
+        let wraps = count.map_or(UNLIMITED_WRAPS, |count| count.saturating_sub(1));
+        self.loop_wraps_left.store(wraps, Ordering::Relaxed);
+    }
+
     /// Advance the timeline by the given number of samples.
     ///
     /// If loop is enabled and the timeline crosses the loop end,
-    /// it will wrap back to the loop start.
+    /// it will wrap back to the loop start. The time past the end is
+    /// replayed from the loop start at the tempo there, not the tempo at
+    /// the loop end.
     pub fn advance(&self, samples: usize) {
-        let mut beat = self.current_beat.get();
-        beat += samples as f64 * self.beats_per_sample;
+        let previous = self.current_beat();
+        let elapsed = samples as f64 / self.sample_rate;
+        let mut beat = match self.timing_map() {
+            Some(map) => map.seconds_to_beats(map.beats_to_seconds(previous) + elapsed),
+            None => previous + samples as f64 * self.beats_per_sample,
+        };
 
         // Handle loop wrap
         if self.loop_enabled.get() {
             let loop_start = self.loop_start.get();
             let loop_end = self.loop_end.get();
 
-            if beat >= loop_end {
-                let loop_length = loop_end - loop_start;
-                if loop_length > 0.0 {
-                    beat = loop_start + ((beat - loop_start) % loop_length);
+            if previous < loop_end && beat >= loop_end && loop_end > loop_start {
+                let start_seconds = self.beats_to_seconds(loop_start);
+                let loop_length = self.beats_to_seconds(loop_end) - start_seconds;
+                let mut overshoot = self.beats_to_seconds(beat) - start_seconds;
+                if self.loop_wraps_left.load(Ordering::Relaxed) == UNLIMITED_WRAPS {
+                    overshoot %= loop_length;
+                } else {
+                    while overshoot >= loop_length && self.take_loop_wrap() {
+                        overshoot -= loop_length;
+                    }
                 }
+                beat = self.seconds_to_beats(start_seconds + overshoot);
             }
         }
 
         self.current_beat.set(beat);
     }
 
+    /// Use up one counted loop wrap, if any are left.
+    fn take_loop_wrap(&self) -> bool {
+        self.loop_wraps_left
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| {
+                left.checked_sub(1)
+            })
+            .is_ok()
+    }
+
     #[inline]
     pub fn current_beat(&self) -> f64 {
         self.current_beat.get()
     }
 
+    /// Current position in seconds from beat 0, following the tempo map
+    /// when one is set.
+    pub fn current_seconds(&self) -> f64 {
+        self.beats_to_seconds(self.current_beat.get())
+    }
+
+    /// Seconds from beat 0 to `beat`, following the tempo map when one is
+    /// set.
+    pub fn beats_to_seconds(&self, beat: f64) -> f64 {
+        match self.timing_map() {
+            Some(map) => map.beats_to_seconds(beat),
+            None => beat * 60.0 / self.tempo.get() as f64,
+        }
+    }
+
+    /// Beat position `seconds` after beat 0.
+    pub fn seconds_to_beats(&self, seconds: f64) -> f64 {
+        match self.timing_map() {
+            Some(map) => map.seconds_to_beats(seconds),
+            None => seconds * self.tempo.get() as f64 / 60.0,
+        }
+    }
+
+    /// Beat position of `bbt`, using the tempo map's meters (4/4 without a
+    /// map).
+    pub fn bbt_to_beats(&self, bbt: BBT) -> f64 {
+        match self.tempo_map {
+            Some(ref map) => map.bbt_to_beats(bbt),
+            None => {
+                bbt.bar.saturating_sub(1) as f64 * 4.0
+                    + bbt.beat.saturating_sub(1) as f64
+                    + bbt.ticks as f64 / BBT::TICKS_PER_BEAT as f64
+            }
+        }
+    }
+
+    /// Playing time of the beat range `start..end` (ignoring the loop).
+    pub fn seconds_between(&self, start_beat: f64, end_beat: f64) -> f64 {
+        self.beats_to_seconds(end_beat) - self.beats_to_seconds(start_beat)
+    }
+
+    /// Tempo at the current position.
     #[inline]
     pub fn tempo(&self) -> f32 {
-        self.tempo.get()
+        match self.timing_map() {
+            Some(map) => map.tempo_at(self.current_beat.get()),
+            None => self.tempo.get(),
+        }
     }
 
     #[inline]
@@ -130,6 +233,8 @@ impl ExportTimeline {
         self.current_beat.set(start_beat);
     }
 
+    /// Beats per sample at the config tempo; with a tempo map this varies
+    /// and [`advance`](Self::advance) integrates it instead.
     #[inline]
     pub fn beats_per_sample(&self) -> f64 {
         self.beats_per_sample
@@ -167,7 +272,11 @@ impl super::TransportReader for ExportTimeline {
     }
 
     fn tempo(&self) -> f32 {
-        self.tempo.get()
+        ExportTimeline::tempo(self)
+    }
+
+    fn tempo_map(&self) -> Option<Arc<TempoMapSnapshot>> {
+        self.tempo_map.clone()
     }
 }
 
@@ -214,6 +323,17 @@ mod tests {
         assert!((timeline.current_beat() - 1.0).abs() < 0.01);
     }
 
+    #[test]
+    fn test_current_seconds() {
+        let timeline = ExportTimeline::new(&ExportConfig {
+            start_beat: 8.0,
+            tempo: 120.0,
+            sample_rate: 44100.0,
+            loop_range: None,
+        });
+        assert!((timeline.current_seconds() - 4.0).abs() < 1e-9);
+    }
+
     #[test]
     fn test_timeline_no_loop() {
         let timeline = ExportTimeline::new(&ExportConfig {
@@ -297,4 +417,94 @@ mod tests {
         assert!(!timeline.is_loop_enabled());
         assert_eq!(timeline.get_loop_range(), None);
     }
+
+    #[test]
+    fn test_timeline_follows_tempo_map() {
+        use crate::transport::{TempoCurve, TempoMap};
+
+        let mut map = TempoMap::new(60.0, 48000.0);
+        map.add_tempo_point_with_curve(4.0, 60.0, TempoCurve::Linear);
+        map.add_tempo_point(8.0, 120.0);
+
+        let timeline = ExportTimeline::new(&ExportConfig {
+            start_beat: 0.0,
+            tempo: 60.0,
+            sample_rate: 48000.0,
+            loop_range: None,
+        })
+        .with_tempo_map(map.snapshot());
+
+        // Render in blocks for 10 seconds
+        for _ in 0..(10 * 48000 / 512) {
+            timeline.advance(512);
+        }
+        let elapsed = (10 * 48000 / 512 * 512) as f64 / 48000.0;
+        let expected = map.seconds_to_beats(elapsed);
+        assert!((timeline.current_beat() - expected).abs() < 1e-6);
+        assert!((timeline.tempo() - 120.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_loop_wrap_follows_tempo_map() {
+        use crate::transport::TempoMap;
+
+        let mut map = TempoMap::new(60.0, 48000.0);
+        map.add_tempo_point(4.0, 120.0);
+
+        let timeline = ExportTimeline::new(&ExportConfig {
+            start_beat: 0.0,
+            tempo: 60.0,
+            sample_rate: 48000.0,
+            loop_range: Some((2.0, 6.0)),
+        })
+        .with_tempo_map(map.snapshot());
+
+        // Beat 7 is 0.5 s past the loop end (5 s); the loop starts at 2 s,
+        // so this lands 0.5 s into it at 60 BPM, not one beat in
+        timeline.advance(48000 * 11 / 2);
+        assert!((timeline.current_beat() - 2.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_seconds_between_beats() {
+        use crate::transport::TempoMap;
+
+        let mut map = TempoMap::new(120.0, 48000.0);
+        map.add_tempo_point(4.0, 60.0);
+        let timeline = ExportTimeline::new(&ExportConfig::default()).with_tempo_map(map.snapshot());
+
+        // 4 beats at 120 BPM, then 4 at 60 BPM
+        assert!((timeline.seconds_between(0.0, 8.0) - 6.0).abs() < 1e-9);
+        assert!((timeline.seconds_to_beats(3.0) - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_loop_count() {
+        let timeline = ExportTimeline::new(&ExportConfig {
+            start_beat: 0.0,
+            tempo: 120.0,
+            sample_rate: 44100.0,
+            loop_range: Some((0.0, 4.0)),
+        });
+        timeline.set_loop_count(Some(2));
+
+        // Beats 0-4, 0-4 again, then straight on
+        let samples_per_beat = 22050;
+        timeline.advance(5 * samples_per_beat);
+        assert!((timeline.current_beat() - 1.0).abs() < 1e-6);
+        timeline.advance(5 * samples_per_beat);
+        assert!((timeline.current_beat() - 6.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_start_past_loop_does_not_wrap() {
+        let timeline = ExportTimeline::new(&ExportConfig {
+            start_beat: 16.0,
+            tempo: 120.0,
+            sample_rate: 44100.0,
+            loop_range: Some((0.0, 4.0)),
+        });
+        timeline.advance(22050);
+        assert!((timeline.current_beat() - 17.0).abs() < 1e-6);
+    }
 }
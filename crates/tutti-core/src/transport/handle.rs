@@ -303,4 +303,8 @@ impl super::TransportReader for TransportHandle {
     fn tempo(&self) -> f32 {
         self.transport.get_tempo()
     }
+
+    fn tempo_map(&self) -> Option<Arc<super::TempoMapSnapshot>> {
+        Some(self.transport.tempo_map_shared().load_full())
+    }
 }
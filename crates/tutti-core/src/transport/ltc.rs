@@ -0,0 +1,732 @@
+//! Linear Timecode (SMPTE 12M) reader and generator.
+//!
+//! LTC is an 80-bit frame per video frame, biphase-mark encoded: the level
+//! flips at every bit boundary, and again mid-bit for a `1`. Bits go out
+//! LSB first and each frame ends with a 16-bit sync word.
+//!
+//! - [`LtcEncoder`] / [`LtcDecoder`]: pure sample-level codec, usable offline
+//! - [`LtcGenerator`]: renders LTC for the transport position
+//! - [`LtcReader`]: decodes LTC from its input and chases the transport when
+//!   the sync source is [`SyncSource::Ltc`]
+
+use super::sync::{SmpteFrameRate, SyncSource, SyncStatus, Timecode};
+use super::TransportManager;
+use crate::compat::Arc;
+use fundsp::audionode::AudioNode;
+use fundsp::prelude::*;
+
+/// Bits per LTC frame.
+const FRAME_BITS: usize = 80;
+
+/// Sync word (bits 64..79 = `0011 1111 1111 1101` in send order).
+const SYNC_WORD: u128 = 0xBFFC;
+
+/// Half-bits per frame; the encoder's level table resolution.
+const HALF_BITS: usize = FRAME_BITS * 2;
+
+/// Pack a timecode into the 80 LTC bits (bit 0 sent first).
+///
+/// User bits are zero. The polarity-correction bit keeps the number of zeros
+/// even, so every frame starts on the same level.
+pub fn encode_ltc_bits(timecode: Timecode, rate: SmpteFrameRate) -> u128 {
+    let mut bits = 0u128;
+    let mut put = |value: u8, start: u32, len: u32| {
+        bits |= ((value as u128) & ((1 << len) - 1)) << start;
+    };
+
+    put(timecode.frames % 10, 0, 4);
+    put(timecode.frames / 10, 8, 2);
+    put(rate.is_drop_frame() as u8, 10, 1);
+    put(timecode.seconds % 10, 16, 4);
+    put(timecode.seconds / 10, 24, 3);
+    put(timecode.minutes % 10, 32, 4);
+    put(timecode.minutes / 10, 40, 3);
+    put(timecode.hours % 10, 48, 4);
+    put(timecode.hours / 10, 56, 2);
+
+    bits |= SYNC_WORD << 64;
+
+    if bits.count_ones() % 2 == 1 {
+        // 25 fps carries the polarity bit where the others carry BGF2
+        let polarity_bit = if rate == SmpteFrameRate::Fps25 {
+            59
+        } else {
+            27
+        };
+        bits |= 1 << polarity_bit;
+    }
+    bits
+}
+
+/// Unpack 80 LTC bits. Returns the timecode and the drop-frame flag, or
+/// `None` if the sync word is missing or a field is out of range.
+pub fn decode_ltc_bits(bits: u128) -> Option<(Timecode, bool)> {
+    if (bits >> 64) & 0xFFFF != SYNC_WORD {
+        return None;
+    }
+
+    let get = |start: u32, len: u32| ((bits >> start) & ((1 << len) - 1)) as u8;
+    let frames = get(0, 4) + get(8, 2) * 10;
+    let seconds = get(16, 4) + get(24, 3) * 10;
+    let minutes = get(32, 4) + get(40, 3) * 10;
+    let hours = get(48, 4) + get(56, 2) * 10;
+    let drop_frame = get(10, 1) == 1;
+
+    if frames >= 30 || seconds >= 60 || minutes >= 60 || hours >= 24 {
+        return None;
+    }
+    Some((Timecode::new(hours, minutes, seconds, frames), drop_frame))
+}
+
+/// Renders LTC for a timeline position, one sample at a time.
+///
+/// The waveform is a pure function of the position, so frames stay aligned
+/// to the timeline across locates.
+#[derive(Clone)]
+pub struct LtcEncoder {
+    rate: SmpteFrameRate,
+    amplitude: f32,
+    frame: Option<u64>,
+    levels: [bool; HALF_BITS],
+}
+
+impl LtcEncoder {
+    pub fn new(rate: SmpteFrameRate) -> Self {
+        Self {
+            rate,
+            amplitude: 0.5,
+            frame: None,
+            levels: [false; HALF_BITS],
+        }
+    }
+
+    /// Peak level (default 0.5, about -6 dBFS).
+    pub fn with_amplitude(mut self, amplitude: f32) -> Self {
+        self.amplitude = amplitude;
+        self
+    }
+
+    pub fn rate(&self) -> SmpteFrameRate {
+        self.rate
+    }
+
+    pub fn set_rate(&mut self, rate: SmpteFrameRate) {
+        if rate != self.rate {
+            self.rate = rate;
+            self.frame = None;
+        }
+    }
+
+    /// Sample at timeline position `seconds` (>= 0).
+    #[inline]
+    pub fn sample_at(&mut self, seconds: f64) -> f32 {
+        let position = seconds.max(0.0) * self.rate.fps();
+        let frame = position.floor();
+        let half = (((position - frame) * HALF_BITS as f64) as usize).min(HALF_BITS - 1);
+
+        let frame = frame as u64;
+        if self.frame != Some(frame) {
+            self.frame = Some(frame);
+            self.fill_levels(Timecode::from_frame_count(frame, self.rate));
+        }
+
+        if self.levels[half] {
+            self.amplitude
+        } else {
+            -self.amplitude
+        }
+    }
+
+    fn fill_levels(&mut self, timecode: Timecode) {
+        let bits = encode_ltc_bits(timecode, self.rate);
+        let mut level = false;
+        for bit in 0..FRAME_BITS {
+            level = !level;
+            self.levels[bit * 2] = level;
+            if (bits >> bit) & 1 == 1 {
+                level = !level;
+            }
+            self.levels[bit * 2 + 1] = level;
+        }
+    }
+}
+
+/// A frame recovered by [`LtcDecoder`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LtcFrame {
+    pub timecode: Timecode,
+    /// Rate from the drop-frame flag and the measured frame period.
+    pub rate: SmpteFrameRate,
+}
+
+impl LtcFrame {
+    /// Timeline position at the end of this frame, which is when the decoder
+    /// reports it.
+    pub fn end_seconds(&self) -> f64 {
+        (self.timecode.to_frame_count(self.rate) + 1) as f64 / self.rate.fps()
+    }
+}
+
+/// Biphase-mark LTC decoder, fed one sample at a time.
+///
+/// Tracks the bit period adaptively, so any rate from 24 to 30 fps locks
+/// without configuration.
+#[derive(Clone)]
+pub struct LtcDecoder {
+    sample_rate: f64,
+    /// Samples per bit, tracked from edge intervals.
+    bit_period: f64,
+    /// Samples per frame between sync words (0 until measured).
+    frame_period: f64,
+    high: bool,
+    envelope: f32,
+    since_edge: u32,
+    half_pending: bool,
+    bits: u128,
+    bit_count: usize,
+    samples: u64,
+    last_sync: Option<u64>,
+}
+
+impl LtcDecoder {
+    pub fn new(sample_rate: f64) -> Self {
+        Self {
+            sample_rate,
+            bit_period: Self::nominal_bit_period(sample_rate),
+            frame_period: 0.0,
+            high: false,
+            envelope: 0.0,
+            since_edge: 0,
+            half_pending: false,
+            bits: 0,
+            bit_count: 0,
+            samples: 0,
+            last_sync: None,
+        }
+    }
+
+    /// Middle of the 24..30 fps range.
+    fn nominal_bit_period(sample_rate: f64) -> f64 {
+        sample_rate / (27.0 * FRAME_BITS as f64)
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new(self.sample_rate);
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+        self.reset();
+    }
+
+    /// Feed one sample. Returns a frame when its sync word completes.
+    #[inline]
+    pub fn process(&mut self, sample: f32) -> Option<LtcFrame> {
+        self.samples += 1;
+        self.since_edge = self.since_edge.saturating_add(1);
+
+        // Zero crossing with hysteresis relative to the signal envelope
+        let magnitude = sample.abs();
+        self.envelope = if magnitude > self.envelope {
+            magnitude
+        } else {
+            self.envelope * 0.9995
+        };
+        let threshold = (self.envelope * 0.2).max(1e-4);
+
+        let edge = if self.high {
+            sample < -threshold
+        } else {
+            sample > threshold
+        };
+        if !edge {
+            return None;
+        }
+        self.high = !self.high;
+
+        let interval = self.since_edge as f64;
+        self.since_edge = 0;
+        self.on_edge(interval)
+    }
+
+    fn on_edge(&mut self, interval: f64) -> Option<LtcFrame> {
+        let period = self.bit_period;
+
+        if interval > period * 1.6 || interval < period * 0.25 {
+            // Dropout, noise or start of signal
+            self.half_pending = false;
+            self.bit_count = 0;
+            return None;
+        }
+
+        let bit = if interval < period * 0.75 {
+            self.track_period(interval * 2.0);
+            if !self.half_pending {
+                self.half_pending = true;
+                return None;
+            }
+            self.half_pending = false;
+            1
+        } else {
+            self.track_period(interval);
+            if self.half_pending {
+                // Lone half-bit: we were out of phase; this long cell is still a 0
+                self.half_pending = false;
+            }
+            0
+        };
+
+        self.bits = (self.bits >> 1) | ((bit as u128) << (FRAME_BITS - 1));
+        self.bit_count += 1;
+        if self.bit_count < FRAME_BITS {
+            return None;
+        }
+
+        let (timecode, drop_frame) = decode_ltc_bits(self.bits)?;
+        self.bit_count = 0;
+        self.track_frame_period();
+
+        Some(LtcFrame {
+            timecode,
+            rate: self.detect_rate(drop_frame),
+        })
+    }
+
+    #[inline]
+    fn track_period(&mut self, measured: f64) {
+        let min = self.sample_rate / (32.0 * FRAME_BITS as f64);
+        let max = self.sample_rate / (22.0 * FRAME_BITS as f64);
+        self.bit_period = (self.bit_period + 0.25 * (measured - self.bit_period)).clamp(min, max);
+    }
+
+    fn track_frame_period(&mut self) {
+        if let Some(last) = self.last_sync {
+            let measured = (self.samples - last) as f64;
+            let expected = self.bit_period * FRAME_BITS as f64;
+            if (measured - expected).abs() < expected * 0.1 {
+                self.frame_period = if self.frame_period == 0.0 {
+                    measured
+                } else {
+                    self.frame_period + 0.1 * (measured - self.frame_period)
+                };
+            }
+        }
+        self.last_sync = Some(self.samples);
+    }
+
+    fn detect_rate(&self, drop_frame: bool) -> SmpteFrameRate {
+        if drop_frame {
+            return SmpteFrameRate::Fps2997Df;
+        }
+        let fps = if self.frame_period > 0.0 {
+            self.sample_rate / self.frame_period
+        } else {
+            self.sample_rate / (self.bit_period * FRAME_BITS as f64)
+        };
+
+        [
+            SmpteFrameRate::Fps24,
+            SmpteFrameRate::Fps25,
+            SmpteFrameRate::Fps2997Ndf,
+            SmpteFrameRate::Fps30,
+        ]
+        .into_iter()
+        .min_by(|a, b| {
+            let da = (a.fps() - fps).abs();
+            let db = (b.fps() - fps).abs();
+            da.partial_cmp(&db).unwrap_or(core::cmp::Ordering::Equal)
+        })
+        .unwrap_or_default()
+    }
+}
+
+/// LTC generator AudioNode: renders timecode for the transport position.
+///
+/// Silent while the transport is stopped, as a tape machine would be.
+#[derive(Clone)]
+pub struct LtcGenerator {
+    transport: Arc<TransportManager>,
+    encoder: LtcEncoder,
+}
+
+impl LtcGenerator {
+    pub fn new(transport: Arc<TransportManager>, rate: SmpteFrameRate) -> Self {
+        Self {
+            transport,
+            encoder: LtcEncoder::new(rate),
+        }
+    }
+
+    pub fn with_amplitude(mut self, amplitude: f32) -> Self {
+        self.encoder = self.encoder.with_amplitude(amplitude);
+        self
+    }
+}
+
+impl AudioNode for LtcGenerator {
+    const ID: u64 = 0x4c544347656e_u64; // "LTCGen"
+
+    type Inputs = U0;
+    type Outputs = U1;
+
+    #[inline]
+    fn tick(&mut self, _input: &Frame<f32, Self::Inputs>) -> Frame<f32, Self::Outputs> {
+        if self.transport.is_paused() {
+            return [0.0].into();
+        }
+        let seconds = self
+            .transport
+            .beats_to_seconds(self.transport.get_current_beat());
+        [self.encoder.sample_at(seconds)].into()
+    }
+
+    fn reset(&mut self) {
+        self.encoder.frame = None;
+    }
+}
+
+/// Tuning for [`LtcReader`].
+#[derive(Debug, Clone)]
+pub struct LtcReaderConfig {
+    /// Consecutive frames required before reporting `Locked`. Default: 4.
+    pub lock_frames: u32,
+    /// Position error, in frames, tolerated before the transport is
+    /// re-located. Default: 1.0.
+    pub chase_tolerance_frames: f64,
+    /// Seconds without a valid frame before the source is considered gone.
+    /// Default: 0.25.
+    pub timeout_seconds: f64,
+}
+
+impl Default for LtcReaderConfig {
+    fn default() -> Self {
+        Self {
+            lock_frames: 4,
+            chase_tolerance_frames: 1.0,
+            timeout_seconds: 0.25,
+        }
+    }
+}
+
+/// LTC reader AudioNode: decodes LTC from its input and slaves the transport.
+///
+/// Passes the input through unchanged. Only drives the transport when its
+/// sync source is [`SyncSource::Ltc`]; starting, stopping and chasing also
+/// require `following`.
+#[derive(Clone)]
+pub struct LtcReader {
+    transport: Arc<TransportManager>,
+    decoder: LtcDecoder,
+    config: LtcReaderConfig,
+    sample_rate: f64,
+    last_frame: Option<u64>,
+    consecutive: u32,
+    since_frame: u64,
+    running: bool,
+}
+
+impl LtcReader {
+    pub fn new(transport: Arc<TransportManager>, sample_rate: f64) -> Self {
+        Self {
+            transport,
+            decoder: LtcDecoder::new(sample_rate),
+            config: LtcReaderConfig::default(),
+            sample_rate,
+            last_frame: None,
+            consecutive: 0,
+            since_frame: 0,
+            running: false,
+        }
+    }
+
+    pub fn with_config(mut self, config: LtcReaderConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    fn on_frame(&mut self, frame: LtcFrame) {
+        let count = frame.timecode.to_frame_count(frame.rate);
+        if self.last_frame.map(|last| last + 1) == Some(count) {
+            self.consecutive = self.consecutive.saturating_add(1);
+        } else {
+            self.consecutive = 0;
+        }
+        self.last_frame = Some(count);
+
+        let transport = &self.transport;
+        let state = transport.sync_state();
+        transport.set_smpte_frame_rate(frame.rate);
+
+        let beats = transport.seconds_to_beats(frame.end_seconds());
+        state.set_external_position(beats);
+
+        let current = state.status();
+        let next = if self.consecutive >= self.config.lock_frames {
+            SyncStatus::Locked
+        } else if matches!(current, SyncStatus::Locked | SyncStatus::Drifting) {
+            SyncStatus::Drifting
+        } else {
+            SyncStatus::Locking
+        };
+        if next != current {
+            state.set_status(next);
+        }
+
+        if next != SyncStatus::Locked || !state.is_following() {
+            return;
+        }
+
+        if !self.running {
+            self.running = true;
+            transport.receive_external_position(beats);
+            transport.play();
+            return;
+        }
+
+        let offset_beats = state.offset_samples() / transport.samples_per_beat();
+        let tolerance_beats =
+            transport.seconds_to_beats(self.config.chase_tolerance_frames / frame.rate.fps());
+        if (transport.get_current_beat() - (beats + offset_beats)).abs() > tolerance_beats {
+            transport.receive_external_position(beats);
+        }
+    }
+
+    fn on_timeout(&mut self) {
+        self.last_frame = None;
+        self.consecutive = 0;
+
+        let state = self.transport.sync_state();
+        if self.running && state.is_following() {
+            self.transport.stop();
+        }
+        self.running = false;
+        state.set_status(SyncStatus::Locking);
+    }
+}
+
+impl AudioNode for LtcReader {
+    const ID: u64 = 0x4c544352656164_u64; // "LTCRead"
+
+    type Inputs = U1;
+    type Outputs = U1;
+
+    #[inline]
+    fn tick(&mut self, input: &Frame<f32, Self::Inputs>) -> Frame<f32, Self::Outputs> {
+        let sample = input[0];
+        let frame = self.decoder.process(sample);
+
+        if self.transport.get_sync_source() != SyncSource::Ltc {
+            return [sample].into();
+        }
+
+        if let Some(frame) = frame {
+            self.since_frame = 0;
+            self.on_frame(frame);
+        } else if self.last_frame.is_some() {
+            self.since_frame += 1;
+            if self.since_frame as f64 > self.config.timeout_seconds * self.sample_rate {
+                self.on_timeout();
+            }
+        }
+
+        [sample].into()
+    }
+
+    fn reset(&mut self) {
+        self.decoder.reset();
+        self.last_frame = None;
+        self.consecutive = 0;
+        self.since_frame = 0;
+        self.running = false;
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+        self.decoder.set_sample_rate(sample_rate);
+    }
+}
+
+/// Create an LTC generator unit following the transport.
+pub fn ltc_generator(transport: Arc<TransportManager>, rate: SmpteFrameRate) -> An<LtcGenerator> {
+    An(LtcGenerator::new(transport, rate))
+}
+
+/// Create an LTC reader unit that chases the transport.
+pub fn ltc_reader(transport: Arc<TransportManager>, sample_rate: f64) -> An<LtcReader> {
+    An(LtcReader::new(transport, sample_rate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compat::Vec;
+    use crate::transport::TransportClock;
+
+    const SR: f64 = 48000.0;
+
+    const RATES: [SmpteFrameRate; 5] = [
+        SmpteFrameRate::Fps24,
+        SmpteFrameRate::Fps25,
+        SmpteFrameRate::Fps2997Df,
+        SmpteFrameRate::Fps2997Ndf,
+        SmpteFrameRate::Fps30,
+    ];
+
+    /// Encode `seconds` of LTC starting at `start`, then decode it.
+    fn round_trip(
+        start: Timecode,
+        rate: SmpteFrameRate,
+        seconds: f64,
+        sample_rate: f64,
+        gain: f32,
+    ) -> Vec<LtcFrame> {
+        let mut encoder = LtcEncoder::new(rate);
+        let mut decoder = LtcDecoder::new(sample_rate);
+        let offset = start.to_seconds(rate);
+
+        (0..(seconds * sample_rate) as usize)
+            .filter_map(|i| {
+                let sample = encoder.sample_at(offset + i as f64 / sample_rate) * gain;
+                // Deterministic low-level noise
+                let noise = (((i * 7919) % 101) as f32 / 101.0 - 0.5) * 0.01;
+                decoder.process(sample + noise)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_bits_round_trip_and_polarity() {
+        for rate in RATES {
+            for count in [0u64, 1, 1799, 17982, 107_892, 2_000_000] {
+                let timecode = Timecode::from_frame_count(count, rate);
+                let bits = encode_ltc_bits(timecode, rate);
+
+                assert_eq!(bits.count_ones() % 2, 0, "odd ones at {rate:?}");
+                let (decoded, drop_frame) = decode_ltc_bits(bits).unwrap();
+                assert_eq!(decoded, timecode);
+                assert_eq!(drop_frame, rate.is_drop_frame());
+            }
+        }
+        assert!(decode_ltc_bits(0).is_none());
+    }
+
+    #[test]
+    fn test_offline_round_trip_all_rates() {
+        let start = Timecode::new(1, 9, 59, 20);
+
+        for rate in RATES {
+            let frames = round_trip(start, rate, 3.0, SR, 1.0);
+            assert!(frames.len() >= 85, "{rate:?}: {} frames", frames.len());
+
+            let base = start.to_frame_count(rate);
+            for pair in frames.windows(2) {
+                assert_eq!(
+                    pair[1].timecode.to_frame_count(rate),
+                    pair[0].timecode.to_frame_count(rate) + 1,
+                    "{rate:?}: {:?} -> {:?}",
+                    pair[0].timecode,
+                    pair[1].timecode
+                );
+            }
+            // At most the first (partial) frame is lost
+            let first = frames[0].timecode.to_frame_count(rate);
+            assert!(first - base <= 1, "{rate:?}: first frame {first} vs {base}");
+            assert_eq!(frames.last().unwrap().rate, rate);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_low_level_at_44k1() {
+        let start = Timecode::new(0, 59, 59, 0);
+        let frames = round_trip(start, SmpteFrameRate::Fps25, 2.0, 44100.0, 0.1);
+        assert!(frames.len() >= 45);
+        // Crosses the hour
+        assert!(frames
+            .iter()
+            .any(|f| f.timecode == Timecode::new(1, 0, 0, 0)));
+    }
+
+    #[test]
+    fn test_reader_chases_transport() {
+        let transport = Arc::new(TransportManager::new(SR));
+        transport.set_sync_source(SyncSource::Ltc);
+        transport.set_following(true);
+        let mut reader = LtcReader::new(transport.clone(), SR);
+
+        let rate = SmpteFrameRate::Fps25;
+        let start = Timecode::new(0, 1, 0, 0);
+        let mut encoder = LtcEncoder::new(rate);
+        let offset = start.to_seconds(rate);
+
+        // Same input, same decoder state: mirrors what the reader sees
+        let mut shadow = LtcDecoder::new(SR);
+        let mut last_frame = None;
+        for i in 0..(SR as usize) {
+            let sample = encoder.sample_at(offset + i as f64 / SR);
+            if let Some(frame) = shadow.process(sample) {
+                last_frame = Some(frame);
+            }
+            reader.tick(&[sample].into());
+            transport.process_commands();
+        }
+
+        let state = transport.sync_state();
+        assert_eq!(state.status(), SyncStatus::Locked);
+        assert_eq!(state.smpte_frame_rate(), SmpteFrameRate::Fps25);
+        assert!(!transport.is_paused());
+
+        let expected = transport.seconds_to_beats(last_frame.unwrap().end_seconds());
+        assert!((state.external_position() - expected).abs() < 1e-9);
+        // No clock runs here, so the transport only moves when chased
+        let one_frame = transport.seconds_to_beats(1.0 / rate.fps());
+        assert!((transport.get_current_beat() - expected).abs() <= one_frame + 1e-9);
+
+        // Signal lost -> stop and fall back to Locking
+        for _ in 0..(SR as usize / 2) {
+            reader.tick(&[0.0].into());
+        }
+        transport.process_commands();
+        assert_eq!(state.status(), SyncStatus::Locking);
+        assert!(transport.is_paused());
+    }
+
+    #[test]
+    fn test_generator_follows_transport() {
+        let transport = Arc::new(TransportManager::new(SR));
+        let mut clock =
+            TransportClock::new(transport.tempo().clone(), transport.paused().clone(), SR)
+                .with_seek(
+                    transport.seek_target().clone(),
+                    transport.seek_pending().clone(),
+                )
+                .with_position_writeback(transport.current_beat().clone());
+        let mut generator = LtcGenerator::new(transport.clone(), SmpteFrameRate::Fps30);
+
+        // Beat 20 at 120 BPM = 10 s = 00:00:10:00
+        transport.locate(20.0);
+        transport.play();
+        transport.process_commands();
+
+        let mut decoder = LtcDecoder::new(SR);
+        let mut frames = Vec::new();
+        for _ in 0..(SR as usize / 2) {
+            clock.next_beat();
+            let sample = generator.tick(&Frame::default())[0];
+            if let Some(frame) = decoder.process(sample) {
+                frames.push(frame.timecode);
+            }
+        }
+
+        assert!(frames.len() >= 13);
+        assert_eq!(frames[0].seconds, 10);
+        assert!(frames[0].frames <= 1);
+        for pair in frames.windows(2) {
+            assert_eq!(pair[1].frames, pair[0].frames + 1);
+        }
+
+        // Silent when stopped
+        transport.stop_immediate();
+        transport.process_commands();
+        assert_eq!(generator.tick(&Frame::default())[0], 0.0);
+    }
+}
@@ -7,7 +7,7 @@ use crossbeam_channel::{unbounded, Receiver, Sender};
 use super::fsm::{TransportEvent, TransportFSM};
 use super::position::{LoopRange, MusicalPosition};
 use super::sync::{SyncSnapshot, SyncSource, SyncState};
-use super::tempo_map::{TempoMap, TempoMapSnapshot, TimeSignature, BBT};
+use super::tempo_map::{TempoCurve, TempoMap, TempoMapSnapshot, TimeSignature, BBT};
 use crate::compat::Ordering;
 use crate::{AtomicDouble, AtomicFlag, AtomicFloat, AtomicU8};
 
@@ -165,7 +165,16 @@ impl TransportManager {
         &self.tempo_map_shared
     }
 
+    /// Tempo at the current position: the tempo map's while it has tempo
+    /// changes and the transport runs on its own clock, otherwise the one set
+    /// by `set_tempo()` or received from an external source.
     pub fn get_tempo(&self) -> f32 {
+        if self.sync_state.is_internal() {
+            let map = self.tempo_map_shared.load();
+            if map.has_tempo_changes() {
+                return map.tempo_at(self.current_beat.get());
+            }
+        }
         self.tempo.get()
     }
 
@@ -388,6 +397,15 @@ impl TransportManager {
         self.tempo_map_shared.store(tempo_map.snapshot());
     }
 
+    /// Replace the whole tempo and meter map (e.g. one imported from a MIDI
+    /// file) in a single swap. The live tempo jumps to the map's initial tempo.
+    pub fn set_tempo_map(&self, mut tempo_map: TempoMap) {
+        tempo_map.set_sample_rate(self.sample_rate);
+        self.tempo.set(tempo_map.tempo());
+        self.tempo_map.store(Arc::new(tempo_map));
+        self.publish_tempo_map();
+    }
+
     pub fn add_tempo_point(&self, beat: f64, bpm: f32) {
         // Clone current tempo map, modify, and swap atomically
         let mut new_map = (**self.tempo_map.load()).clone();
@@ -396,6 +414,14 @@ impl TransportManager {
         self.publish_tempo_map();
     }
 
+    /// Add a tempo point whose `curve` shapes the tempo up to the next point.
+    pub fn add_tempo_point_with_curve(&self, beat: f64, bpm: f32, curve: TempoCurve) {
+        let mut new_map = (**self.tempo_map.load()).clone();
+        new_map.add_tempo_point_with_curve(beat, bpm, curve);
+        self.tempo_map.store(Arc::new(new_map));
+        self.publish_tempo_map();
+    }
+
     pub fn remove_tempo_point(&self, beat: f64) {
         // Clone current tempo map, modify, and swap atomically
         let mut new_map = (**self.tempo_map.load()).clone();
@@ -420,10 +446,41 @@ impl TransportManager {
         self.publish_tempo_map();
     }
 
+    /// Change meter at the start of `bar` (1-indexed).
+    pub fn add_meter_change(&self, bar: u32, numerator: u32, denominator: u32) {
+        let mut new_map = (**self.tempo_map.load()).clone();
+        new_map.add_meter_change(bar, numerator, denominator);
+        self.tempo_map.store(Arc::new(new_map));
+        self.publish_tempo_map();
+    }
+
+    pub fn remove_meter_change(&self, bar: u32) {
+        let mut new_map = (**self.tempo_map.load()).clone();
+        new_map.remove_meter_change(bar);
+        self.tempo_map.store(Arc::new(new_map));
+        self.publish_tempo_map();
+    }
+
+    pub fn clear_meter_changes(&self) {
+        let mut new_map = (**self.tempo_map.load()).clone();
+        new_map.clear_meter_changes();
+        self.tempo_map.store(Arc::new(new_map));
+        self.publish_tempo_map();
+    }
+
     pub fn time_signature(&self) -> TimeSignature {
         self.tempo_map.load().time_signature()
     }
 
+    pub fn time_signature_at(&self, beats: f64) -> TimeSignature {
+        self.tempo_map.load().time_signature_at(beats)
+    }
+
+    /// Tempo from the tempo map at `beats`, following ramps.
+    pub fn tempo_at(&self, beats: f64) -> f32 {
+        self.tempo_map.load().tempo_at(beats)
+    }
+
     pub fn beats_to_bbt(&self, beats: f64) -> BBT {
         self.tempo_map.load().beats_to_bbt(beats)
     }
@@ -453,7 +510,7 @@ impl TransportManager {
     }
 
     pub fn beats_per_second(&self) -> f64 {
-        self.tempo.get() as f64 / 60.0
+        self.get_tempo() as f64 / 60.0
     }
 
     pub fn samples_per_beat(&self) -> f64 {
@@ -495,7 +552,11 @@ impl TransportManager {
             } else {
                 0.0
             };
-            self.current_beat.set(beats + offset_beats);
+            let target = beats + offset_beats;
+            self.current_beat.set(target);
+            // Move the audio-thread clock too, otherwise its writeback overwrites us
+            self.seek_target.set(target);
+            self.seek_pending.set(true);
         }
     }
 
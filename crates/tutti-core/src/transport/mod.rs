@@ -4,19 +4,30 @@ mod clock;
 mod export_timeline;
 pub(crate) mod fsm;
 mod handle;
+mod ltc;
 pub(crate) mod manager;
 pub(crate) mod position;
 pub mod sync;
+#[cfg(feature = "std")]
+mod sync_output;
 pub(crate) mod tempo_map;
 
+use crate::compat::Arc;
+
 pub use automation_reader::{AutomationEnvelopeFn, AutomationReaderInput};
 pub use click::{click, ClickNode, ClickSettings, ClickState, MetronomeMode};
 pub use clock::TransportClock;
 pub use export_timeline::{ExportConfig, ExportTimeline};
 pub use handle::{MetronomeHandle, TransportHandle};
+pub use ltc::{
+    decode_ltc_bits, encode_ltc_bits, ltc_generator, ltc_reader, LtcDecoder, LtcEncoder, LtcFrame,
+    LtcGenerator, LtcReader, LtcReaderConfig,
+};
 pub use manager::{Direction, MotionState, TransportManager};
-pub use sync::{SmpteFrameRate, SyncSnapshot, SyncSource, SyncState, SyncStatus};
-pub use tempo_map::{TempoMap, TimeSignature, BBT};
+pub use sync::{SmpteFrameRate, SyncSnapshot, SyncSource, SyncState, SyncStatus, Timecode};
+#[cfg(feature = "std")]
+pub use sync_output::{SyncBlock, TransportSyncOutput};
+pub use tempo_map::{MeterChange, TempoCurve, TempoMap, TempoMapSnapshot, TimeSignature, BBT};
 
 /// Trait for reading transport state.
 ///
@@ -31,4 +42,10 @@ pub trait TransportReader: Send + Sync {
     fn is_recording(&self) -> bool;
     fn is_in_preroll(&self) -> bool;
     fn tempo(&self) -> f32;
+
+    /// Tempo and meter map, when the transport has one. Nodes use it to turn
+    /// beat offsets into time across tempo ramps and to find bar lines.
+    fn tempo_map(&self) -> Option<Arc<TempoMapSnapshot>> {
+        None
+    }
 }
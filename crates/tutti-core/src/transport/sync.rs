@@ -90,6 +90,110 @@ impl SmpteFrameRate {
     pub fn is_drop_frame(&self) -> bool {
         matches!(self, SmpteFrameRate::Fps2997Df)
     }
+
+    /// Frame labels per second (30 for both 29.97 variants).
+    pub fn nominal_fps(&self) -> u32 {
+        match self {
+            SmpteFrameRate::Fps24 => 24,
+            SmpteFrameRate::Fps25 => 25,
+            SmpteFrameRate::Fps2997Df | SmpteFrameRate::Fps2997Ndf | SmpteFrameRate::Fps30 => 30,
+        }
+    }
+
+    /// MTC/LTC rate code (0 = 24, 1 = 25, 2 = 29.97 drop, 3 = 30).
+    ///
+    /// MTC has no code for 29.97 non-drop; it is sent as 30.
+    pub fn mtc_code(&self) -> u8 {
+        match self {
+            SmpteFrameRate::Fps24 => 0,
+            SmpteFrameRate::Fps25 => 1,
+            SmpteFrameRate::Fps2997Df => 2,
+            SmpteFrameRate::Fps2997Ndf | SmpteFrameRate::Fps30 => 3,
+        }
+    }
+
+    pub fn from_mtc_code(code: u8) -> Self {
+        match code & 0x03 {
+            0 => SmpteFrameRate::Fps24,
+            1 => SmpteFrameRate::Fps25,
+            2 => SmpteFrameRate::Fps2997Df,
+            _ => SmpteFrameRate::Fps30,
+        }
+    }
+}
+
+/// SMPTE timecode address (hh:mm:ss:ff).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Timecode {
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: u8,
+    pub frames: u8,
+}
+
+impl Timecode {
+    pub fn new(hours: u8, minutes: u8, seconds: u8, frames: u8) -> Self {
+        Self {
+            hours,
+            minutes,
+            seconds,
+            frames,
+        }
+    }
+
+    /// Frames elapsed since 00:00:00:00, skipping dropped labels for 29.97 DF.
+    pub fn to_frame_count(&self, rate: SmpteFrameRate) -> u64 {
+        let nominal = rate.nominal_fps() as u64;
+        let total_minutes = self.hours as u64 * 60 + self.minutes as u64;
+        let labels = (total_minutes * 60 + self.seconds as u64) * nominal + self.frames as u64;
+
+        if rate.is_drop_frame() {
+            // Frames 0 and 1 are skipped every minute except each tenth minute
+            labels - 2 * (total_minutes - total_minutes / 10)
+        } else {
+            labels
+        }
+    }
+
+    /// Inverse of [`to_frame_count`](Self::to_frame_count). Wraps at 24 hours.
+    pub fn from_frame_count(count: u64, rate: SmpteFrameRate) -> Self {
+        let nominal = rate.nominal_fps() as u64;
+
+        let labels = if rate.is_drop_frame() {
+            const FRAMES_PER_10_MIN: u64 = 17982;
+            const FRAMES_PER_MIN: u64 = 1798;
+            let tens = count / FRAMES_PER_10_MIN;
+            let rem = count % FRAMES_PER_10_MIN;
+            let dropped = if rem > 1 {
+                18 * tens + 2 * ((rem - 2) / FRAMES_PER_MIN)
+            } else {
+                18 * tens
+            };
+            count + dropped
+        } else {
+            count
+        };
+
+        let frames = labels % nominal;
+        let total_seconds = labels / nominal;
+        Self {
+            hours: ((total_seconds / 3600) % 24) as u8,
+            minutes: ((total_seconds / 60) % 60) as u8,
+            seconds: (total_seconds % 60) as u8,
+            frames: frames as u8,
+        }
+    }
+
+    /// Wall-clock seconds at the start of this frame.
+    pub fn to_seconds(&self, rate: SmpteFrameRate) -> f64 {
+        self.to_frame_count(rate) as f64 / rate.fps()
+    }
+
+    /// Timecode of the frame containing `seconds`.
+    pub fn from_seconds(seconds: f64, rate: SmpteFrameRate) -> Self {
+        let count = (seconds.max(0.0) * rate.fps() + 1e-9) as u64;
+        Self::from_frame_count(count, rate)
+    }
 }
 
 /// External sync state - lock-free for RT access.
@@ -295,6 +399,50 @@ mod tests {
         assert!(!SmpteFrameRate::Fps2997Ndf.is_drop_frame());
     }
 
+    #[test]
+    fn test_timecode_round_trip() {
+        for rate in [
+            SmpteFrameRate::Fps24,
+            SmpteFrameRate::Fps25,
+            SmpteFrameRate::Fps2997Df,
+            SmpteFrameRate::Fps2997Ndf,
+            SmpteFrameRate::Fps30,
+        ] {
+            for count in [0u64, 1, 1799, 1800, 17981, 17982, 107_892, 2_589_407] {
+                let tc = Timecode::from_frame_count(count, rate);
+                assert_eq!(tc.to_frame_count(rate), count, "{rate:?} {tc:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_timecode_drop_frame_labels() {
+        let rate = SmpteFrameRate::Fps2997Df;
+
+        // 00:00:59:29 is followed by 00:01:00:02
+        let before = Timecode::new(0, 0, 59, 29);
+        let after = Timecode::from_frame_count(before.to_frame_count(rate) + 1, rate);
+        assert_eq!(after, Timecode::new(0, 1, 0, 2));
+
+        // Tenth minutes keep frames 0 and 1
+        let ten = Timecode::new(0, 10, 0, 0);
+        assert_eq!(
+            Timecode::from_frame_count(ten.to_frame_count(rate), rate),
+            ten
+        );
+
+        // One hour of drop-frame is 3600 real seconds (to within a frame)
+        let hour = Timecode::new(1, 0, 0, 0).to_seconds(rate);
+        assert!((hour - 3600.0).abs() < 1.0 / rate.fps());
+    }
+
+    #[test]
+    fn test_mtc_rate_codes() {
+        assert_eq!(SmpteFrameRate::from_mtc_code(2), SmpteFrameRate::Fps2997Df);
+        assert_eq!(SmpteFrameRate::Fps30.mtc_code(), 3);
+        assert_eq!(SmpteFrameRate::Fps2997Ndf.nominal_fps(), 30);
+    }
+
     #[test]
     fn test_snapshot() {
         let state = SyncState::new();
@@ -0,0 +1,39 @@
+//! Transport-driven sync output (MIDI Clock / MTC master).
+//!
+//! The audio callback records the transport position of every sample it
+//! renders and hands the block to a registered [`TransportSyncOutput`]. The
+//! sink decides where pulses fall, so clock output follows tempo changes,
+//! locates and loop wraps exactly as the transport clock produced them.
+
+use std::time::Instant;
+
+/// One rendered audio block, as seen by the transport clock.
+#[derive(Debug, Clone, Copy)]
+pub struct SyncBlock<'a> {
+    /// Transport position (beats) at each frame of the block.
+    pub beats: &'a [f64],
+    /// Whether the transport was rolling during this block.
+    pub playing: bool,
+    pub sample_rate: f64,
+    /// Wall-clock time of frame 0 (before output latency).
+    pub buffer_start: Instant,
+}
+
+impl SyncBlock<'_> {
+    #[inline]
+    pub fn frames(&self) -> usize {
+        self.beats.len()
+    }
+}
+
+/// Receives every rendered block from the audio thread.
+///
+/// Register with [`TuttiSystem::set_sync_output`](crate::TuttiSystem::set_sync_output).
+///
+/// # Safety
+///
+/// `process_block` runs on the audio thread: no allocation, no locks, no
+/// blocking I/O. Hand results to another thread through a lock-free queue.
+pub trait TransportSyncOutput: Send + Sync {
+    fn process_block(&self, block: &SyncBlock<'_>);
+}
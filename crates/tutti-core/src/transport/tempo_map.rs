@@ -4,25 +4,121 @@ use crate::compat::{Arc, Vec};
 
 pub(crate) const SUPERCLOCK_TICKS_PER_SECOND: u64 = 282_240_000;
 
+/// How tempo moves from one tempo point to the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TempoCurve {
+    /// Hold the tempo until the next point, then jump.
+    #[default]
+    Step,
+    /// Change tempo by a constant amount per beat (accelerando/ritardando).
+    Linear,
+    /// Change tempo by a constant ratio per beat, which sounds even across
+    /// wide ranges.
+    Exponential,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub(crate) struct TempoPoint {
     pub(crate) beat: f64,
     pub(crate) bpm: f32,
+    pub(crate) curve: TempoCurve,
+    /// Time of this point, integrated over all earlier segments.
+    seconds: f64,
     superclock: u64,
 }
 
 impl TempoPoint {
     pub(crate) fn new(beat: f64, bpm: f32) -> Self {
+        Self::with_curve(beat, bpm, TempoCurve::Step)
+    }
+
+    pub(crate) fn with_curve(beat: f64, bpm: f32, curve: TempoCurve) -> Self {
         Self {
             beat,
             bpm,
+            curve,
+            seconds: 0.0,
             superclock: 0,
         }
     }
 
+    /// Tempo function from this point towards `next`.
+    #[inline]
+    fn ramp(&self, next: Option<&TempoPoint>) -> Ramp {
+        let t0 = self.bpm as f64;
+        let Some(next) = next else {
+            return Ramp::Constant(t0);
+        };
+        let length = next.beat - self.beat;
+        let t1 = next.bpm as f64;
+        if length <= 0.0 || (t1 - t0).abs() < 1e-6 {
+            return Ramp::Constant(t0);
+        }
+
+        match self.curve {
+            TempoCurve::Step => Ramp::Constant(t0),
+            TempoCurve::Linear => Ramp::Linear {
+                t0,
+                slope: (t1 - t0) / length,
+            },
+            TempoCurve::Exponential => Ramp::Exponential {
+                t0,
+                rate: (t1 / t0).ln() / length,
+            },
+        }
+    }
+}
+
+/// Tempo over one segment, as a function of beats past its start.
+///
+/// Seconds are the integral of `60 / tempo(beat)`; both directions have
+/// closed forms so conversions stay exact however long the ramp is.
+#[derive(Debug, Clone, Copy)]
+enum Ramp {
+    Constant(f64),
+    /// `tempo = t0 + slope * beats`
+    Linear {
+        t0: f64,
+        slope: f64,
+    },
+    /// `tempo = t0 * e^(rate * beats)`
+    Exponential {
+        t0: f64,
+        rate: f64,
+    },
+}
+
+impl Ramp {
+    #[inline]
+    fn tempo(self, beats: f64) -> f64 {
+        match self {
+            Ramp::Constant(t0) => t0,
+            Ramp::Linear { t0, slope } => t0 + slope * beats,
+            Ramp::Exponential { t0, rate } => t0 * (rate * beats).exp(),
+        }
+    }
+
+    #[inline]
+    fn seconds(self, beats: f64) -> f64 {
+        match self {
+            Ramp::Constant(t0) => beats * 60.0 / t0,
+            Ramp::Linear { t0, slope } => 60.0 / slope * ((t0 + slope * beats) / t0).ln(),
+            Ramp::Exponential { t0, rate } => 60.0 / (t0 * rate) * (1.0 - (-rate * beats).exp()),
+        }
+    }
+
     #[inline]
-    fn superclock_per_beat(&self) -> f64 {
-        SUPERCLOCK_TICKS_PER_SECOND as f64 * 60.0 / self.bpm as f64
+    fn beats(self, seconds: f64) -> f64 {
+        match self {
+            Ramp::Constant(t0) => seconds * t0 / 60.0,
+            Ramp::Linear { t0, slope } => t0 * ((slope * seconds / 60.0).exp() - 1.0) / slope,
+            Ramp::Exponential { t0, rate } => {
+                -(1.0 - seconds * t0 * rate / 60.0)
+                    .max(f64::MIN_POSITIVE)
+                    .ln()
+                    / rate
+            }
+        }
     }
 }
 
@@ -44,6 +140,12 @@ impl TimeSignature {
     pub fn beats_per_bar(&self) -> f64 {
         self.numerator as f64 * 4.0 / self.denominator as f64
     }
+
+    /// Length of the denominator note in beats (quarter notes).
+    #[inline]
+    pub fn beats_per_pulse(&self) -> f64 {
+        4.0 / self.denominator as f64
+    }
 }
 
 impl Default for TimeSignature {
@@ -52,6 +154,33 @@ impl Default for TimeSignature {
     }
 }
 
+/// A time signature that takes effect at the start of a bar.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeterChange {
+    bar: u32,
+    beat: f64,
+    time_signature: TimeSignature,
+}
+
+impl MeterChange {
+    /// Bar (1-indexed) where this meter starts.
+    #[inline]
+    pub fn bar(&self) -> u32 {
+        self.bar
+    }
+
+    /// Beat position of that bar, derived from the meters before it.
+    #[inline]
+    pub fn beat(&self) -> f64 {
+        self.beat
+    }
+
+    #[inline]
+    pub fn time_signature(&self) -> TimeSignature {
+        self.time_signature
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct BBT {
     pub bar: u32,
@@ -70,7 +199,7 @@ impl BBT {
 #[derive(Debug, Clone)]
 pub struct TempoMapSnapshot {
     points: Vec<TempoPoint>,
-    time_signature: TimeSignature,
+    meters: Vec<MeterChange>,
     sample_rate: f64,
 }
 
@@ -85,30 +214,14 @@ impl TempoMapSnapshot {
     }
 
     fn beats_to_seconds_variable(&self, target_beats: f64) -> f64 {
-        let mut seconds = 0.0;
-        let mut prev_beat = 0.0;
-
-        for (i, point) in self.points.iter().enumerate() {
-            if point.beat >= target_beats {
-                let prev_tempo = if i > 0 {
-                    self.points[i - 1].bpm
-                } else {
-                    point.bpm
-                };
-                seconds += (target_beats - prev_beat) * 60.0 / prev_tempo as f64;
-                return seconds;
-            }
-
-            if i > 0 {
-                let prev_tempo = self.points[i - 1].bpm;
-                seconds += (point.beat - prev_beat) * 60.0 / prev_tempo as f64;
-            }
-            prev_beat = point.beat;
+        let i = self.segment_at_beat(target_beats);
+        let point = &self.points[i];
+        let delta = target_beats - point.beat;
+        if delta <= 0.0 {
+            // Before the map starts: extend the first tempo backwards
+            return point.seconds + delta * 60.0 / point.bpm as f64;
         }
-
-        let last_tempo = self.points.last().map(|p| p.bpm).unwrap_or(120.0);
-        seconds += (target_beats - prev_beat) * 60.0 / last_tempo as f64;
-        seconds
+        point.seconds + point.ramp(self.points.get(i + 1)).seconds(delta)
     }
 
     #[inline]
@@ -121,30 +234,48 @@ impl TempoMapSnapshot {
     }
 
     fn seconds_to_beats_variable(&self, target_seconds: f64) -> f64 {
-        let mut current_seconds = 0.0;
-        let mut current_beats = 0.0;
+        let i = self
+            .points
+            .partition_point(|p| p.seconds <= target_seconds)
+            .saturating_sub(1);
+        let point = &self.points[i];
+        let delta = target_seconds - point.seconds;
+        if delta <= 0.0 {
+            return point.beat + delta * point.bpm as f64 / 60.0;
+        }
+        point.beat + point.ramp(self.points.get(i + 1)).beats(delta)
+    }
 
-        for i in 0..self.points.len() {
-            let tempo = self.points[i].bpm;
-            let next_beat = if i + 1 < self.points.len() {
-                self.points[i + 1].beat
-            } else {
-                f64::MAX
-            };
-
-            let segment_beats = next_beat - self.points[i].beat;
-            let segment_seconds = segment_beats * 60.0 / tempo as f64;
-
-            if current_seconds + segment_seconds >= target_seconds {
-                let remaining_seconds = target_seconds - current_seconds;
-                return current_beats + remaining_seconds * tempo as f64 / 60.0;
-            }
+    /// Index of the tempo segment containing `beats`.
+    #[inline]
+    fn segment_at_beat(&self, beats: f64) -> usize {
+        self.points
+            .partition_point(|p| p.beat <= beats)
+            .saturating_sub(1)
+    }
 
-            current_seconds += segment_seconds;
-            current_beats = next_beat;
+    /// Instantaneous tempo at `beats`, following ramps.
+    #[inline]
+    pub fn tempo_at(&self, beats: f64) -> f32 {
+        if self.points.len() == 1 {
+            return self.points[0].bpm;
         }
+        let i = self.segment_at_beat(beats);
+        let point = &self.points[i];
+        let delta = (beats - point.beat).max(0.0);
+        point.ramp(self.points.get(i + 1)).tempo(delta) as f32
+    }
 
-        current_beats
+    /// Whether the map holds more than the initial tempo.
+    #[inline]
+    pub fn has_tempo_changes(&self) -> bool {
+        self.points.len() > 1
+    }
+
+    /// Tempo points as `(beat, bpm, curve)`, in beat order. The curve shapes
+    /// the segment up to the next point.
+    pub fn tempo_points(&self) -> impl Iterator<Item = (f64, f32, TempoCurve)> + '_ {
+        self.points.iter().map(|p| (p.beat, p.bpm, p.curve))
     }
 
     #[inline]
@@ -159,28 +290,61 @@ impl TempoMapSnapshot {
         self.seconds_to_beats(seconds)
     }
 
+    /// Meter in effect at `beats`.
+    #[inline]
+    pub fn meter_at(&self, beats: f64) -> MeterChange {
+        let i = self
+            .meters
+            .partition_point(|m| m.beat <= beats)
+            .saturating_sub(1);
+        self.meters[i]
+    }
+
+    /// All meter changes, starting with the one at bar 1.
+    pub fn meter_changes(&self) -> &[MeterChange] {
+        &self.meters
+    }
+
+    #[inline]
+    pub fn time_signature_at(&self, beats: f64) -> TimeSignature {
+        self.meter_at(beats).time_signature
+    }
+
+    /// Bar, beat and ticks at `beats`. Beats count the meter's denominator
+    /// note (eighths in 7/8), as the click does; ticks divide that note.
     pub fn beats_to_bbt(&self, beats: f64) -> BBT {
-        let beats_per_bar = self.time_signature.beats_per_bar();
+        let meter = self.meter_at(beats);
+        let time_signature = meter.time_signature;
+        let beats_per_bar = time_signature.beats_per_bar();
 
-        let total_bars = (beats / beats_per_bar).floor();
-        let beat_in_bar = beats - (total_bars * beats_per_bar);
-        let beat_whole = beat_in_bar.floor();
-        let ticks = ((beat_in_bar - beat_whole) * BBT::TICKS_PER_BEAT as f64) as u32;
+        let offset = beats - meter.beat;
+        let bars = (offset / beats_per_bar).floor();
+        let pulse_in_bar = (offset - bars * beats_per_bar) / time_signature.beats_per_pulse();
+        let pulse_whole = pulse_in_bar.floor();
+        let ticks = ((pulse_in_bar - pulse_whole) * BBT::TICKS_PER_BEAT as f64) as u32;
 
         BBT {
-            bar: total_bars as u32 + 1,
-            beat: beat_whole as u32 + 1, // 1-indexed
+            bar: (meter.bar as f64 + bars).max(1.0) as u32,
+            beat: pulse_whole as u32 + 1, // 1-indexed
             ticks,
         }
     }
 
+    /// Inverse of [`beats_to_bbt`](Self::beats_to_bbt).
     pub fn bbt_to_beats(&self, bbt: BBT) -> f64 {
-        let beats_per_bar = self.time_signature.beats_per_bar();
-        let bar_beats = (bbt.bar.saturating_sub(1)) as f64 * beats_per_bar;
-        let beat_beats = (bbt.beat.saturating_sub(1)) as f64;
-        let tick_beats = bbt.ticks as f64 / BBT::TICKS_PER_BEAT as f64;
+        let bar = bbt.bar.max(1);
+        let i = self
+            .meters
+            .partition_point(|m| m.bar <= bar)
+            .saturating_sub(1);
+        let meter = &self.meters[i];
+        let time_signature = meter.time_signature;
 
-        bar_beats + beat_beats + tick_beats
+        let bar_beats = (bar - meter.bar) as f64 * time_signature.beats_per_bar();
+        let pulses =
+            bbt.beat.saturating_sub(1) as f64 + bbt.ticks as f64 / BBT::TICKS_PER_BEAT as f64;
+
+        meter.beat + bar_beats + pulses * time_signature.beats_per_pulse()
     }
 
     #[inline]
@@ -198,9 +362,10 @@ impl TempoMapSnapshot {
         self.points[0].bpm
     }
 
+    /// Time signature at bar 1.
     #[inline]
     pub fn time_signature(&self) -> TimeSignature {
-        self.time_signature
+        self.meters[0].time_signature
     }
 
     #[inline]
@@ -212,7 +377,7 @@ impl TempoMapSnapshot {
 #[derive(Debug, Clone)]
 pub struct TempoMap {
     points: Vec<TempoPoint>,
-    time_signature: TimeSignature,
+    meters: Vec<MeterChange>,
     sample_rate: f64,
     snapshot: Arc<TempoMapSnapshot>,
 }
@@ -220,17 +385,21 @@ pub struct TempoMap {
 impl TempoMap {
     pub fn new(initial_bpm: f32, sample_rate: f64) -> Self {
         let points = vec![TempoPoint::new(0.0, initial_bpm)];
-        let time_signature = TimeSignature::default();
+        let meters = vec![MeterChange {
+            bar: 1,
+            beat: 0.0,
+            time_signature: TimeSignature::default(),
+        }];
 
         let snapshot = Arc::new(TempoMapSnapshot {
             points: points.clone(),
-            time_signature,
+            meters: meters.clone(),
             sample_rate,
         });
 
         Self {
             points,
-            time_signature,
+            meters,
             sample_rate,
             snapshot,
         }
@@ -252,11 +421,17 @@ impl TempoMap {
     }
 
     pub fn add_tempo_point(&mut self, beat: f64, bpm: f32) {
+        self.add_tempo_point_with_curve(beat, bpm, TempoCurve::Step);
+    }
+
+    /// Add a tempo point whose `curve` shapes the tempo from this point to
+    /// the next one. A ramp on the last point has no effect.
+    pub fn add_tempo_point_with_curve(&mut self, beat: f64, bpm: f32, curve: TempoCurve) {
         let bpm = bpm.clamp(1.0, 999.0);
 
         self.points.retain(|p| (p.beat - beat).abs() > 0.001);
 
-        self.points.push(TempoPoint::new(beat, bpm));
+        self.points.push(TempoPoint::with_curve(beat, bpm, curve));
 
         self.points.sort_by(|a, b| {
             a.beat
@@ -287,13 +462,60 @@ impl TempoMap {
         self.rebuild_snapshot();
     }
 
+    #[inline]
+    pub fn tempo_at(&self, beats: f64) -> f32 {
+        self.snapshot.tempo_at(beats)
+    }
+
+    /// Set the time signature at bar 1. Later meter changes are kept.
     pub fn set_time_signature(&mut self, numerator: u32, denominator: u32) {
-        self.time_signature = TimeSignature::new(numerator, denominator);
+        self.meters[0].time_signature = TimeSignature::new(numerator, denominator);
         self.rebuild_snapshot();
     }
 
+    /// Change meter at the start of `bar` (1-indexed). Replaces any change
+    /// already at that bar; bar 1 is the same as [`set_time_signature`](Self::set_time_signature).
+    pub fn add_meter_change(&mut self, bar: u32, numerator: u32, denominator: u32) {
+        if bar <= 1 {
+            self.set_time_signature(numerator, denominator);
+            return;
+        }
+
+        self.meters.retain(|m| m.bar != bar);
+        self.meters.push(MeterChange {
+            bar,
+            beat: 0.0,
+            time_signature: TimeSignature::new(numerator, denominator),
+        });
+        self.meters.sort_by_key(|m| m.bar);
+        self.rebuild_snapshot();
+    }
+
+    pub fn remove_meter_change(&mut self, bar: u32) {
+        if bar <= 1 {
+            return;
+        }
+        self.meters.retain(|m| m.bar != bar);
+        self.rebuild_snapshot();
+    }
+
+    pub fn clear_meter_changes(&mut self) {
+        self.meters.truncate(1);
+        self.rebuild_snapshot();
+    }
+
+    /// Time signature at bar 1.
     pub fn time_signature(&self) -> TimeSignature {
-        self.time_signature
+        self.meters[0].time_signature
+    }
+
+    #[inline]
+    pub fn time_signature_at(&self, beats: f64) -> TimeSignature {
+        self.snapshot.time_signature_at(beats)
+    }
+
+    pub fn meter_changes(&self) -> &[MeterChange] {
+        &self.meters
     }
 
     pub fn set_sample_rate(&mut self, sample_rate: f64) {
@@ -302,20 +524,28 @@ impl TempoMap {
     }
 
     fn rebuild_snapshot(&mut self) {
-        let mut superclock = 0u64;
+        let mut seconds = 0.0;
         for i in 0..self.points.len() {
-            self.points[i].superclock = superclock;
+            self.points[i].seconds = seconds;
+            self.points[i].superclock = (seconds * SUPERCLOCK_TICKS_PER_SECOND as f64) as u64;
 
             if i + 1 < self.points.len() {
                 let beat_delta = self.points[i + 1].beat - self.points[i].beat;
-                let sc_delta = (beat_delta * self.points[i].superclock_per_beat()) as u64;
-                superclock += sc_delta;
+                seconds += self.points[i]
+                    .ramp(Some(&self.points[i + 1]))
+                    .seconds(beat_delta);
             }
         }
 
+        for i in 1..self.meters.len() {
+            let prev = self.meters[i - 1];
+            let bars = (self.meters[i].bar - prev.bar) as f64;
+            self.meters[i].beat = prev.beat + bars * prev.time_signature.beats_per_bar();
+        }
+
         self.snapshot = Arc::new(TempoMapSnapshot {
             points: self.points.clone(),
-            time_signature: self.time_signature,
+            meters: self.meters.clone(),
             sample_rate: self.sample_rate,
         });
     }
@@ -526,4 +756,102 @@ mod tests {
         // Samples per beat = 44100 / 2 = 22050
         assert!((snap.samples_per_beat() - 22050.0).abs() < 0.1);
     }
+
+    #[test]
+    fn test_linear_ramp() {
+        let mut map = TempoMap::new(60.0, 48000.0);
+        map.add_tempo_point_with_curve(0.0, 60.0, TempoCurve::Linear);
+        map.add_tempo_point(4.0, 120.0);
+
+        // tempo = 60 + 15b, seconds = 60/15 * ln(tempo/60)
+        let expected = 4.0 * 2.0f64.ln();
+        let seconds = map.beats_to_seconds(4.0);
+        assert!((seconds - expected).abs() < 1e-9, "got {}", seconds);
+        assert!((map.tempo_at(2.0) - 90.0).abs() < 1e-4);
+
+        // Constant 120 after the ramp
+        assert!((map.beats_to_seconds(6.0) - (expected + 1.0)).abs() < 1e-9);
+        assert!((map.tempo_at(10.0) - 120.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_ramps_round_trip() {
+        for curve in [
+            TempoCurve::Step,
+            TempoCurve::Linear,
+            TempoCurve::Exponential,
+        ] {
+            let mut map = TempoMap::new(120.0, 44100.0);
+            map.add_tempo_point_with_curve(8.0, 80.0, curve);
+            map.add_tempo_point_with_curve(24.0, 200.0, curve);
+            map.add_tempo_point(40.0, 140.0);
+
+            let mut prev = -1.0;
+            for i in 0..200 {
+                let beats = i as f64 * 0.25;
+                let seconds = map.beats_to_seconds(beats);
+                assert!(seconds > prev, "{curve:?}: not monotonic at {beats}");
+                prev = seconds;
+
+                let back = map.seconds_to_beats(seconds);
+                assert!((back - beats).abs() < 1e-9, "{curve:?}: {beats} -> {back}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_exponential_ramp_matches_integration() {
+        let mut map = TempoMap::new(100.0, 48000.0);
+        map.add_tempo_point_with_curve(0.0, 100.0, TempoCurve::Exponential);
+        map.add_tempo_point(16.0, 200.0);
+
+        // Halfway in beats is the geometric mean
+        assert!((map.tempo_at(8.0) as f64 - 100.0 * 2.0f64.sqrt()).abs() < 1e-3);
+
+        let steps = 160_000;
+        let step = 16.0 / steps as f64;
+        let numeric: f64 = (0..steps)
+            .map(|i| 60.0 / map.tempo_at((i as f64 + 0.5) * step) as f64 * step)
+            .sum();
+        assert!((map.beats_to_seconds(16.0) - numeric).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_meter_changes() {
+        let mut map = TempoMap::new(120.0, 44100.0);
+        // 4 bars of 4/4, 2 bars of 7/8, back to 4/4
+        map.add_meter_change(5, 7, 8);
+        map.add_meter_change(7, 4, 4);
+
+        let changes = map.meter_changes();
+        assert_eq!(changes.len(), 3);
+        assert!((changes[1].beat() - 16.0).abs() < 1e-9);
+        assert!((changes[2].beat() - 23.0).abs() < 1e-9);
+
+        assert_eq!(map.beats_to_bbt(16.0), BBT::new(5, 1, 0));
+        assert_eq!(map.beats_to_bbt(19.5), BBT::new(6, 1, 0));
+        assert_eq!(map.beats_to_bbt(23.0), BBT::new(7, 1, 0));
+        assert_eq!(map.time_signature_at(20.0), TimeSignature::new(7, 8));
+        assert_eq!(map.time_signature_at(23.0), TimeSignature::new(4, 4));
+
+        // Beats in 7/8 are eighth notes
+        assert_eq!(map.beats_to_bbt(17.0), BBT::new(5, 3, 0));
+        assert_eq!(map.beats_to_bbt(17.25), BBT::new(5, 3, 480));
+        assert!((map.bbt_to_beats(BBT::new(6, 7, 0)) - 22.5).abs() < 1e-9);
+
+        for beats in [0.0, 15.5, 16.0, 17.25, 19.5, 22.75, 23.0, 30.25] {
+            let back = map.bbt_to_beats(map.beats_to_bbt(beats));
+            assert!((back - beats).abs() < 1e-3, "{beats} -> {back}");
+        }
+
+        // Changing the opening meter moves later changes with it
+        map.set_time_signature(3, 4);
+        assert!((map.meter_changes()[1].beat() - 12.0).abs() < 1e-9);
+
+        map.remove_meter_change(5);
+        assert_eq!(map.meter_changes().len(), 2);
+        map.clear_meter_changes();
+        assert_eq!(map.meter_changes().len(), 1);
+        assert_eq!(map.time_signature(), TimeSignature::new(3, 4));
+    }
 }
@@ -1,19 +1,48 @@
-use crate::options::DitherType;
+use crate::options::{DitherType, NoiseShape};
+
+/// Largest error fed back, in LSBs. Rounding plus TPDF dither stays within
+/// 1.5; anything more means the quantizer clipped, and feeding that back
+/// would drive the higher-order filters unstable.
+const MAX_ERROR_LSB: f64 = 2.0;
+
+/// Frequency grid the noise-shaping filters are designed on.
+const DESIGN_POINTS: usize = 1024;
+
+/// Largest sample value at `bits`: the float-to-integer scale every writer
+/// uses.
+#[inline]
+pub(crate) fn full_scale(bits: u16) -> f32 {
+    ((1u32 << (bits - 1)) - 1) as f32
+}
+
+/// Float to integer PCM. This is the one place samples are rounded; dither
+/// and noise shaping predict its result instead of rounding themselves.
+#[inline]
+pub(crate) fn quantize(sample: f32, bits: u16) -> i32 {
+    let scale = full_scale(bits);
+    (sample * scale).round().clamp(-scale, scale) as i32
+}
 
 pub(crate) struct DitherState {
     random_state: u32,
-    error_l: f32,
-    error_r: f32,
     dither_type: DitherType,
+    /// Error-feedback filter; empty unless noise shaping
+    coefficients: Vec<f64>,
+    /// Recent quantization errors per channel, newest first
+    errors: Vec<Vec<f64>>,
 }
 
 impl DitherState {
-    pub fn new(dither_type: DitherType) -> Self {
+    pub fn new(dither_type: DitherType, sample_rate: u32) -> Self {
+        let coefficients = match dither_type {
+            DitherType::NoiseShaped(shape) => design_filter(shape, sample_rate),
+            _ => Vec::new(),
+        };
         Self {
             random_state: 0x12345678,
-            error_l: 0.0,
-            error_r: 0.0,
             dither_type,
+            coefficients,
+            errors: Vec::new(),
         }
     }
 
@@ -38,54 +67,124 @@ impl DitherState {
         let r2 = self.random() as f32 / u32::MAX as f32;
         r1 - r2
     }
+
+    /// Error-feedback quantizer: subtract the filtered past errors, add TPDF
+    /// dither, and record how far [`quantize`] will land from the target.
+    /// The output is left unrounded for the writer.
+    #[inline]
+    fn shape(&mut self, ch: usize, sample: f32, bits: u16) -> f32 {
+        let scale = full_scale(bits) as f64;
+        let dither = self.triangular_noise() as f64 / scale;
+        let history = &mut self.errors[ch];
+
+        let feedback: f64 = self
+            .coefficients
+            .iter()
+            .zip(history.iter())
+            .map(|(c, e)| c * e)
+            .sum();
+        let target = sample as f64 - feedback;
+        let output = (target + dither) as f32;
+        let error = quantize(output, bits) as f64 / scale - target;
+
+        let limit = MAX_ERROR_LSB / scale;
+        history.rotate_right(1);
+        history[0] = error.clamp(-limit, limit);
+        output
+    }
 }
 
-pub(crate) fn apply_dither(
-    left: &mut [f32],
-    right: &mut [f32],
-    target_bits: u16,
-    state: &mut DitherState,
-) {
+/// Add dither for a `target_bits` integer output. Samples are left for the
+/// writer to round with [`quantize`].
+pub(crate) fn apply_dither(channels: &mut [Vec<f32>], target_bits: u16, state: &mut DitherState) {
     if state.dither_type == DitherType::None {
         return;
     }
 
-    let max_value = (1 << (target_bits - 1)) as f32;
-    let lsb = 1.0 / max_value;
+    let lsb = 1.0 / full_scale(target_bits);
+    let frames = channels.iter().map(Vec::len).max().unwrap_or(0);
+    let order = state.coefficients.len();
+    state.errors.resize(channels.len(), vec![0.0; order]);
 
-    match state.dither_type {
-        DitherType::None => {}
-        DitherType::Rectangular => {
-            for i in 0..left.len() {
-                left[i] += state.rectangular_noise() * lsb;
-                right[i] += state.rectangular_noise() * lsb;
+    // Frame-major so the noise sequence interleaves across channels
+    for i in 0..frames {
+        for (ch, channel) in channels.iter_mut().enumerate() {
+            let Some(sample) = channel.get_mut(i) else {
+                continue;
+            };
+            match state.dither_type {
+                DitherType::None => {}
+                DitherType::Rectangular => *sample += state.rectangular_noise() * lsb,
+                DitherType::Triangular => *sample += state.triangular_noise() * lsb,
+                DitherType::NoiseShaped(_) => *sample = state.shape(ch, *sample, target_bits),
             }
         }
-        DitherType::Triangular => {
-            for i in 0..left.len() {
-                left[i] += state.triangular_noise() * lsb;
-                right[i] += state.triangular_noise() * lsb;
-            }
+    }
+}
+
+/// Absolute threshold of hearing in dB SPL (Terhardt's approximation).
+fn hearing_threshold_db(hz: f64) -> f64 {
+    let khz = hz / 1000.0;
+    3.64 * khz.powf(-0.8) - 6.5 * (-0.6 * (khz - 3.3).powi(2)).exp() + 1e-3 * khz.powi(4)
+}
+
+impl NoiseShape {
+    fn order(self) -> usize {
+        match self {
+            NoiseShape::FWeighted => 9,
+            NoiseShape::EWeighted => 5,
         }
-        DitherType::NoiseShaped => {
-            for i in 0..left.len() {
-                let dither_l = state.triangular_noise() * lsb;
-                let dither_r = state.triangular_noise() * lsb;
+    }
 
-                let shaped_l = left[i] + dither_l - state.error_l * 0.5;
-                let shaped_r = right[i] + dither_r - state.error_r * 0.5;
+    /// Relative noise level (dB) the filter aims for at `hz`: the hearing
+    /// threshold, held flat below a corner and capped above the most
+    /// sensitive point so the filter doesn't spend its gain on ultrasonics.
+    fn target_db(self, hz: f64) -> f64 {
+        let sensitive = hearing_threshold_db(3300.0);
+        match self {
+            NoiseShape::FWeighted => hearing_threshold_db(hz.max(1000.0)).min(sensitive + 40.0),
+            NoiseShape::EWeighted => hearing_threshold_db(hz.max(3300.0)).min(sensitive + 20.0),
+        }
+    }
+}
 
-                let quantized_l = (shaped_l * max_value).round() / max_value;
-                let quantized_r = (shaped_r * max_value).round() / max_value;
+/// Error-feedback coefficients `h` for `shape` at `sample_rate`, giving a
+/// noise transfer function `1 - sum(h[k] z^-(k+1))`.
+///
+/// The noise transfer function is the minimum-phase whitening filter of the
+/// inverse target curve (linear prediction on it), which puts the noise
+/// spectrum as close to the target's shape as the order allows.
+fn design_filter(shape: NoiseShape, sample_rate: u32) -> Vec<f64> {
+    let order = shape.order();
+    let nyquist = sample_rate as f64 / 2.0;
 
-                state.error_l = quantized_l - left[i];
-                state.error_r = quantized_r - right[i];
+    // Autocorrelation of the weighting spectrum
+    let mut r = vec![0.0; order + 1];
+    for i in 0..DESIGN_POINTS {
+        let w = std::f64::consts::PI * (i as f64 + 0.5) / DESIGN_POINTS as f64;
+        let weight = 10f64.powf(-shape.target_db(w / std::f64::consts::PI * nyquist) / 10.0);
+        for (k, r) in r.iter_mut().enumerate() {
+            *r += weight * (w * k as f64).cos() / DESIGN_POINTS as f64;
+        }
+    }
+    r[0] *= 1.0 + 1e-9;
 
-                left[i] = quantized_l;
-                right[i] = quantized_r;
-            }
+    // Levinson-Durbin for A(z) = 1 + sum(a[k] z^-k)
+    let mut a = vec![0.0; order + 1];
+    a[0] = 1.0;
+    let mut error = r[0];
+    for i in 1..=order {
+        let acc: f64 = r[i] + (1..i).map(|j| a[j] * r[i - j]).sum::<f64>();
+        let k = -acc / error;
+        let previous = a.clone();
+        for j in 1..i {
+            a[j] = previous[j] + k * previous[i - j];
         }
+        a[i] = k;
+        error *= 1.0 - k * k;
     }
+
+    a[1..].iter().map(|a| -a).collect()
 }
 
 #[cfg(test)]
@@ -94,31 +193,29 @@ mod tests {
 
     #[test]
     fn test_dither_state_creation() {
-        let state = DitherState::new(DitherType::Triangular);
+        let state = DitherState::new(DitherType::Triangular, 44100);
         assert_eq!(state.dither_type, DitherType::Triangular);
+        assert!(state.coefficients.is_empty());
     }
 
     #[test]
     fn test_no_dither() {
-        let mut left = vec![0.5, -0.5, 0.25];
-        let mut right = vec![0.5, -0.5, 0.25];
-        let original_left = left.clone();
-        let original_right = right.clone();
+        let mut channels = vec![vec![0.5, -0.5, 0.25], vec![0.5, -0.5, 0.25]];
+        let original = channels.clone();
 
-        let mut state = DitherState::new(DitherType::None);
-        apply_dither(&mut left, &mut right, 16, &mut state);
+        let mut state = DitherState::new(DitherType::None, 44100);
+        apply_dither(&mut channels, 16, &mut state);
 
-        assert_eq!(left, original_left);
-        assert_eq!(right, original_right);
+        assert_eq!(channels, original);
     }
 
     #[test]
     fn test_rectangular_dither() {
-        let mut left = vec![0.0; 1000];
-        let mut right = vec![0.0; 1000];
+        let mut channels = vec![vec![0.0; 1000]; 2];
 
-        let mut state = DitherState::new(DitherType::Rectangular);
-        apply_dither(&mut left, &mut right, 16, &mut state);
+        let mut state = DitherState::new(DitherType::Rectangular, 44100);
+        apply_dither(&mut channels, 16, &mut state);
+        let left = &channels[0];
 
         // Check that dither was applied (samples should be non-zero)
         let non_zero = left.iter().filter(|&&x| x != 0.0).count();
@@ -126,7 +223,7 @@ mod tests {
 
         // Check that noise is bounded
         let max_noise = 1.0 / 32768.0; // 16-bit LSB
-        for &sample in &left {
+        for &sample in left {
             assert!(
                 sample.abs() < max_noise * 2.0,
                 "Noise exceeds expected bounds"
@@ -136,11 +233,11 @@ mod tests {
 
     #[test]
     fn test_triangular_dither() {
-        let mut left = vec![0.0; 1000];
-        let mut right = vec![0.0; 1000];
+        let mut channels = vec![vec![0.0; 1000]; 6];
 
-        let mut state = DitherState::new(DitherType::Triangular);
-        apply_dither(&mut left, &mut right, 16, &mut state);
+        let mut state = DitherState::new(DitherType::Triangular, 44100);
+        apply_dither(&mut channels, 16, &mut state);
+        let left = &channels[0];
 
         // TPDF dither should have larger range than rectangular
         let max_noise = 1.0 / 32768.0;
@@ -149,4 +246,127 @@ mod tests {
         // TPDF can go up to 2 LSBs
         assert!(max_sample < max_noise * 3.0);
     }
+
+    #[test]
+    fn test_quantize() {
+        assert_eq!(quantize(0.5, 16), 16384);
+        assert_eq!(quantize(-2.0, 16), -32767);
+        // Already on the grid: rounding again changes nothing
+        let on_grid = 1234.0 / full_scale(24);
+        assert_eq!(quantize(on_grid, 24), 1234);
+    }
+
+    /// Response of the noise transfer function at `hz`, in dB.
+    fn ntf_db(coefficients: &[f64], sample_rate: u32, hz: f64) -> f64 {
+        let w = 2.0 * std::f64::consts::PI * hz / sample_rate as f64;
+        let (mut re, mut im) = (1.0, 0.0);
+        for (k, c) in coefficients.iter().enumerate() {
+            re -= c * (w * (k + 1) as f64).cos();
+            im += c * (w * (k + 1) as f64).sin();
+        }
+        10.0 * (re * re + im * im).log10()
+    }
+
+    /// Requantization noise of silence at 16 bits: what the writer would
+    /// write, minus the input.
+    fn noise(dither: DitherType, sample_rate: u32, len: usize) -> Vec<f64> {
+        let mut channels = vec![vec![0.0; len]];
+        let mut state = DitherState::new(dither, sample_rate);
+        apply_dither(&mut channels, 16, &mut state);
+        channels[0]
+            .iter()
+            .map(|&s| quantize(s, 16) as f64 / full_scale(16) as f64)
+            .collect()
+    }
+
+    /// Average power (dB) around `hz`: Hann-windowed blocks, three DFT bins.
+    fn power_db(signal: &[f64], sample_rate: u32, hz: f64) -> f64 {
+        const BLOCK: usize = 1024;
+        let center = (hz * BLOCK as f64 / sample_rate as f64).round() as usize;
+        let window: Vec<f64> = (0..BLOCK)
+            .map(|i| 0.5 - 0.5 * (2.0 * std::f64::consts::PI * i as f64 / BLOCK as f64).cos())
+            .collect();
+
+        let mut total = 0.0;
+        let mut count = 0;
+        for block in signal.chunks_exact(BLOCK) {
+            for bin in center - 1..=center + 1 {
+                let w = 2.0 * std::f64::consts::PI * bin as f64 / BLOCK as f64;
+                let (mut re, mut im) = (0.0, 0.0);
+                for (i, (s, win)) in block.iter().zip(&window).enumerate() {
+                    re += s * win * (w * i as f64).cos();
+                    im -= s * win * (w * i as f64).sin();
+                }
+                total += re * re + im * im;
+                count += 1;
+            }
+        }
+        10.0 * (total / count as f64).log10()
+    }
+
+    #[test]
+    fn test_noise_shaped_spectrum() {
+        const LEN: usize = 1024 * 128;
+
+        for sample_rate in [44100, 48000] {
+            let flat = noise(DitherType::Triangular, sample_rate, LEN);
+            for shape in [NoiseShape::FWeighted, NoiseShape::EWeighted] {
+                let dither = DitherType::NoiseShaped(shape);
+                let shaped = noise(dither, sample_rate, LEN);
+                let coefficients = design_filter(shape, sample_rate);
+
+                // Shaped noise is the flat TPDF noise through the filter
+                for hz in [1000.0, 3500.0, 8000.0, 16000.0] {
+                    let measured =
+                        power_db(&shaped, sample_rate, hz) - power_db(&flat, sample_rate, hz);
+                    let expected = ntf_db(&coefficients, sample_rate, hz);
+                    assert!(
+                        (measured - expected).abs() < 2.0,
+                        "{:?} at {} Hz ({} Hz): {:.1} dB, expected {:.1} dB",
+                        shape,
+                        hz,
+                        sample_rate,
+                        measured,
+                        expected
+                    );
+                }
+
+                // Quieter where the ear is most sensitive, louder up top
+                let relative = |hz| ntf_db(&coefficients, sample_rate, hz);
+                assert!(relative(3500.0) < -10.0, "{:?}", shape);
+                assert!(relative(16000.0) > 5.0, "{:?}", shape);
+            }
+        }
+
+        // F-weighting dips at 3-4 kHz; E-weighting stays flat below
+        let f = design_filter(NoiseShape::FWeighted, 44100);
+        assert!(ntf_db(&f, 44100, 3500.0) < ntf_db(&f, 44100, 1000.0) - 5.0);
+        let e = design_filter(NoiseShape::EWeighted, 44100);
+        assert!((ntf_db(&e, 44100, 1000.0) - ntf_db(&e, 44100, 100.0)).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_filters_follow_sample_rate() {
+        // The curve stays put in Hz, so the taps differ per rate
+        let at_44k = design_filter(NoiseShape::FWeighted, 44100);
+        let at_96k = design_filter(NoiseShape::FWeighted, 96000);
+        assert_eq!(at_44k.len(), 9);
+        assert!(at_44k.iter().zip(&at_96k).any(|(a, b)| (a - b).abs() > 0.1));
+
+        let noise_at = |c: &[f64], rate| ntf_db(c, rate, 3500.0);
+        assert!((noise_at(&at_44k, 44100) - noise_at(&at_96k, 96000)).abs() < 12.0);
+    }
+
+    #[test]
+    fn test_noise_shaping_survives_clipping() {
+        let mut channels = vec![vec![1.5; 4096]];
+        channels[0].extend(vec![0.0; 4096]);
+        let mut state = DitherState::new(DitherType::NoiseShaped(NoiseShape::FWeighted), 44100);
+        apply_dither(&mut channels, 16, &mut state);
+
+        // Back to a few LSBs of noise once the overload ends
+        let lsb = 1.0 / full_scale(16);
+        let tail = &channels[0][6000..];
+        assert!(tail.iter().all(|s| s.abs() < 64.0 * lsb));
+    }
 }
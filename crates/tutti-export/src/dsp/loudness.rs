@@ -1,9 +1,17 @@
-use tutti_core::analyze_true_peak;
+use super::limiter::{limit, LimiterStats};
+use crate::options::LimiterOptions;
+use tutti_core::analyze_true_peak_multichannel;
+
+/// Limiting lowers the loudness a little, so the gain is corrected and the
+/// limiter rerun until the result lands within this of the target.
+const LOUDNESS_TOLERANCE_LU: f64 = 0.05;
+
+/// Upper bound on limiter passes; two or three usually suffice.
+const MAX_LIMITER_PASSES: usize = 6;
 
 /// EBU R128 loudness normalization.
 pub(crate) fn normalize_loudness(
-    left: &mut [f32],
-    right: &mut [f32],
+    channels: &mut [Vec<f32>],
     current_lufs: f64,
     target_lufs: f64,
     true_peak_limit: f64,
@@ -11,7 +19,7 @@ pub(crate) fn normalize_loudness(
     let gain_db = target_lufs - current_lufs;
     let mut gain = 10.0_f64.powf(gain_db / 20.0) as f32;
 
-    let current_peak = analyze_true_peak(left, right);
+    let current_peak = true_peak(channels);
     let new_peak = current_peak + gain_db;
 
     if new_peak > true_peak_limit {
@@ -19,21 +27,79 @@ pub(crate) fn normalize_loudness(
         gain *= 10.0_f32.powf(-reduction_db as f32 / 20.0);
     }
 
-    left.iter_mut().zip(right.iter_mut()).for_each(|(l, r)| {
-        *l *= gain;
-        *r *= gain;
-    });
+    apply_gain(channels, gain);
+}
+
+/// Loudness normalization through the look-ahead limiter: the full gain to
+/// `target_lufs`, with anything over `true_peak_limit` limited rather than
+/// the whole file turned down. `measure` returns integrated loudness.
+pub(crate) fn normalize_loudness_limited(
+    channels: &mut Vec<Vec<f32>>,
+    measure: impl Fn(&[Vec<f32>]) -> f64,
+    target_lufs: f64,
+    true_peak_limit: f64,
+    limiter: &LimiterOptions,
+    sample_rate: u32,
+) -> LimiterStats {
+    let current = measure(channels);
+    // Nothing above the gate: there is no loudness to normalize
+    if !current.is_finite() || current <= -70.0 {
+        return LimiterStats::default();
+    }
+
+    let mut gain_db = target_lufs - current;
+    let mut previous: Option<(f64, f64)> = None;
+    let mut pass = 0;
+    loop {
+        let mut limited = channels.clone();
+        apply_gain(&mut limited, 10.0_f64.powf(gain_db / 20.0) as f32);
+        let stats = limit(&mut limited, true_peak_limit, limiter, sample_rate);
+
+        // The limiter's interpolator and the meter's can disagree by a
+        // fraction of a dB; the meter's reading is the one that counts
+        let peak = true_peak(&limited);
+        if peak > true_peak_limit {
+            apply_gain(
+                &mut limited,
+                10.0_f64.powf((true_peak_limit - peak) / 20.0) as f32,
+            );
+        }
+
+        pass += 1;
+        let error = target_lufs - measure(&limited);
+        if error.abs() < LOUDNESS_TOLERANCE_LU || pass == MAX_LIMITER_PASSES {
+            *channels = limited;
+            return stats;
+        }
+        // Secant step: limited peaks don't get louder with the gain, so the
+        // loudness rises by less than a dB per dB
+        let next = match previous {
+            Some((last_gain, last_error)) if (last_error - error).abs() > 1e-9 => {
+                gain_db + error * (gain_db - last_gain) / (last_error - error)
+            }
+            _ => gain_db + error,
+        };
+        previous = Some((gain_db, error));
+        gain_db = next;
+    }
 }
 
-pub(crate) fn normalize_peak(left: &mut [f32], right: &mut [f32], target_db: f64) {
-    let current_peak = analyze_true_peak(left, right);
+pub(crate) fn normalize_peak(channels: &mut [Vec<f32>], target_db: f64) {
+    let current_peak = true_peak(channels);
     let gain_db = target_db - current_peak;
-    let gain = 10.0_f64.powf(gain_db / 20.0) as f32;
+    apply_gain(channels, 10.0_f64.powf(gain_db / 20.0) as f32);
+}
+
+fn true_peak(channels: &[Vec<f32>]) -> f64 {
+    let refs: Vec<&[f32]> = channels.iter().map(Vec::as_slice).collect();
+    analyze_true_peak_multichannel(&refs)
+}
 
-    left.iter_mut().zip(right.iter_mut()).for_each(|(l, r)| {
-        *l *= gain;
-        *r *= gain;
-    });
+fn apply_gain(channels: &mut [Vec<f32>], gain: f32) {
+    channels
+        .iter_mut()
+        .flat_map(|c| c.iter_mut())
+        .for_each(|s| *s *= gain);
 }
 
 #[cfg(test)]
@@ -46,17 +112,17 @@ mod tests {
         let sample_rate = 44100;
         let duration_samples = sample_rate * 2;
 
-        let mut left: Vec<f32> = (0..duration_samples)
+        let left: Vec<f32> = (0..duration_samples)
             .map(|i| {
                 0.1 * (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / sample_rate as f32).sin()
             })
             .collect();
-        let mut right = left.clone();
+        let mut channels = vec![left.clone(), left];
 
-        let current = analyze_loudness(&left, &right, sample_rate as u32);
-        normalize_loudness(&mut left, &mut right, current.integrated_lufs, -14.0, -1.0);
+        let current = analyze_loudness(&channels[0], &channels[1], sample_rate as u32);
+        normalize_loudness(&mut channels, current.integrated_lufs, -14.0, -1.0);
 
-        let normalized = analyze_loudness(&left, &right, sample_rate as u32);
+        let normalized = analyze_loudness(&channels[0], &channels[1], sample_rate as u32);
 
         assert!(
             (normalized.integrated_lufs - (-14.0)).abs() < 2.0,
@@ -64,4 +130,73 @@ mod tests {
             normalized.integrated_lufs
         );
     }
+
+    /// Sine with a short burst 20 dB up: gain-only normalization has to stop
+    /// well short of -14 LUFS to keep the burst under -1 dBTP.
+    fn dynamic_material(sample_rate: usize) -> Vec<Vec<f32>> {
+        let signal: Vec<f32> = (0..sample_rate * 4)
+            .map(|i| {
+                let burst = if (sample_rate * 2..sample_rate * 2 + 2400).contains(&i) {
+                    10.0
+                } else {
+                    1.0
+                };
+                burst
+                    * 0.1
+                    * (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / sample_rate as f32).sin()
+            })
+            .collect();
+        vec![signal.clone(), signal]
+    }
+
+    #[test]
+    fn test_limiter_hits_loudness_target() {
+        let sample_rate = 48000;
+        let measure = |channels: &[Vec<f32>]| {
+            analyze_loudness(&channels[0], &channels[1], 48000).integrated_lufs
+        };
+
+        let mut gain_only = dynamic_material(sample_rate);
+        let current = measure(&gain_only);
+        normalize_loudness(&mut gain_only, current, -14.0, -1.0);
+        assert!(measure(&gain_only) < -16.0);
+
+        let mut limited = dynamic_material(sample_rate);
+        let stats = normalize_loudness_limited(
+            &mut limited,
+            measure,
+            -14.0,
+            -1.0,
+            &LimiterOptions::default(),
+            sample_rate as u32,
+        );
+
+        let result = analyze_loudness(&limited[0], &limited[1], sample_rate as u32);
+        assert!(
+            (result.integrated_lufs + 14.0).abs() < 0.1,
+            "Expected -14 LUFS, got {}",
+            result.integrated_lufs
+        );
+        assert!(
+            result.true_peak_dbtp <= -1.0 + 1e-3,
+            "{}",
+            result.true_peak_dbtp
+        );
+        assert!(stats.max_gain_reduction_db > 3.0);
+    }
+
+    #[test]
+    fn test_limiter_leaves_silence() {
+        let mut channels = vec![vec![0.0; 4800]; 2];
+        let stats = normalize_loudness_limited(
+            &mut channels,
+            |c| analyze_loudness(&c[0], &c[1], 48000).integrated_lufs,
+            -14.0,
+            -1.0,
+            &LimiterOptions::default(),
+            48000,
+        );
+        assert_eq!(stats, LimiterStats::default());
+        assert!(channels.iter().flatten().all(|&s| s == 0.0));
+    }
 }
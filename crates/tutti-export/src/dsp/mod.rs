@@ -1,85 +1,167 @@
-#[cfg(any(feature = "wav", feature = "flac"))]
+//! Processing shared by every encoder: resample, normalize, limit, dither.
+
+// Only the encoders call into this; without any of them it goes unused.
+#![cfg_attr(
+    not(any(
+        feature = "wav",
+        feature = "flac",
+        feature = "vorbis",
+        feature = "opus",
+        feature = "mp3"
+    )),
+    allow(dead_code, unused_imports)
+)]
+
 mod dither;
-#[cfg(any(feature = "wav", feature = "flac"))]
+mod limiter;
 mod loudness;
 mod resample;
 
-#[cfg(any(feature = "wav", feature = "flac"))]
-pub(crate) use dither::{apply_dither, DitherState};
-#[cfg(any(feature = "wav", feature = "flac"))]
-pub(crate) use loudness::{normalize_loudness, normalize_peak};
-#[cfg(any(feature = "wav", feature = "flac"))]
-pub(crate) use resample::resample_stereo;
+pub(crate) use dither::{apply_dither, quantize, DitherState};
+pub(crate) use limiter::LimiterStats;
+pub(crate) use loudness::{normalize_loudness, normalize_loudness_limited, normalize_peak};
+pub(crate) use resample::resample_channels;
 pub use resample::ResampleQuality;
 
-#[cfg(any(feature = "wav", feature = "flac"))]
-pub(crate) use tutti_core::analyze_loudness;
+pub(crate) use tutti_core::analyze_loudness_multichannel;
 
-#[cfg(any(feature = "wav", feature = "flac"))]
-use crate::options::{ExportOptions, NormalizationMode};
-#[cfg(any(feature = "wav", feature = "flac"))]
-use crate::Result;
+use crate::options::{BitDepth, DitherType, ExportOptions, NormalizationMode};
+use crate::{ExportError, LoudnessReport, Result, SpeakerLayout};
+use tutti_core::LoudnessResult;
 
-/// Pipeline order: resample -> normalize -> dither.
-#[cfg(any(feature = "wav", feature = "flac"))]
+/// Audio ready for an encoder.
+pub(crate) struct Processed {
+    pub channels: Vec<Vec<f32>>,
+    pub layout: SpeakerLayout,
+    /// Measured on `channels` after all processing, for the file's tags.
+    pub loudness: LoudnessResult,
+    pub report: LoudnessReport,
+}
+
+/// Pipeline order: resample -> mono downmix -> normalize (and limit) ->
+/// dither, then a verification pass measuring what will be encoded.
+///
+/// Dither is last: it computes the exact rounding the writer will apply
+/// (see [`quantize`]), so nothing may touch the samples in between.
 pub(crate) fn process_audio(
-    left: &[f32],
-    right: &[f32],
+    channels: &[&[f32]],
     options: &ExportOptions,
     output_sample_rate: u32,
-) -> Result<(Vec<f32>, Vec<f32>)> {
-    let mut left_proc = left.to_vec();
-    let mut right_proc = right.to_vec();
+) -> Result<Processed> {
+    if let Some(first) = channels.first() {
+        if channels.iter().any(|c| c.len() != first.len()) {
+            return Err(ExportError::InvalidData(
+                "Channels have different lengths".into(),
+            ));
+        }
+    }
+    let mut channels: Vec<Vec<f32>> = channels.iter().map(|c| c.to_vec()).collect();
 
     if let Some(target_rate) = options.sample_rate {
         if target_rate != options.source_sample_rate {
-            let (l, r) = resample_stereo(
-                &left_proc,
-                &right_proc,
+            channels = resample_channels(
+                &channels,
                 options.source_sample_rate,
                 target_rate,
                 options.resample_quality,
             )?;
-            left_proc = l;
-            right_proc = r;
         }
     }
 
+    let (mut channels, layout) = downmix(channels, options)?;
+
+    let mut limiting = LimiterStats::default();
     match options.normalization {
         NormalizationMode::None => {}
         NormalizationMode::Peak(target_db) => {
-            normalize_peak(&mut left_proc, &mut right_proc, target_db);
+            normalize_peak(&mut channels, target_db);
         }
         NormalizationMode::Loudness {
             target_lufs,
             true_peak_dbtp,
         } => {
-            let current = analyze_loudness(&left_proc, &right_proc, output_sample_rate);
-            normalize_loudness(
-                &mut left_proc,
-                &mut right_proc,
-                current.integrated_lufs,
-                target_lufs,
-                true_peak_dbtp,
-            );
+            if let Some(limiter) = options.limiter {
+                limiting = normalize_loudness_limited(
+                    &mut channels,
+                    |c| measure_loudness(c, layout, output_sample_rate).integrated_lufs,
+                    target_lufs,
+                    true_peak_dbtp,
+                    &limiter,
+                    output_sample_rate,
+                );
+            } else {
+                let current = measure_loudness(&channels, layout, output_sample_rate);
+                normalize_loudness(
+                    &mut channels,
+                    current.integrated_lufs,
+                    target_lufs,
+                    true_peak_dbtp,
+                );
+            }
         }
     }
 
-    if options.dither != crate::options::DitherType::None {
-        let mut state = DitherState::new(options.dither);
-        apply_dither(
-            &mut left_proc,
-            &mut right_proc,
-            options.bit_depth.bits(),
-            &mut state,
-        );
+    // Float output is never quantized, so there's nothing to dither
+    if options.dither != DitherType::None && options.bit_depth != BitDepth::Float32 {
+        let mut state = DitherState::new(options.dither, output_sample_rate);
+        apply_dither(&mut channels, options.bit_depth.bits(), &mut state);
     }
 
-    Ok((left_proc, right_proc))
+    let loudness = measure_loudness(&channels, layout, output_sample_rate);
+    Ok(Processed {
+        report: LoudnessReport {
+            integrated_lufs: loudness.integrated_lufs,
+            true_peak_dbtp: loudness.true_peak_dbtp,
+            loudness_range_lu: loudness.loudness_range_lu,
+            max_gain_reduction_db: limiting.max_gain_reduction_db,
+            limited_fraction: limiting.limited_fraction,
+        },
+        channels,
+        layout,
+        loudness,
+    })
+}
+
+/// BS.1770 loudness of `channels`, weighted for `layout`.
+pub(crate) fn measure_loudness(
+    channels: &[Vec<f32>],
+    layout: SpeakerLayout,
+    sample_rate: u32,
+) -> LoudnessResult {
+    let refs: Vec<&[f32]> = channels.iter().map(Vec::as_slice).collect();
+    analyze_loudness_multichannel(&refs, &layout.loudness_weights(), sample_rate)
+}
+
+/// Apply the mono downmix if requested.
+fn downmix(
+    channels: Vec<Vec<f32>>,
+    options: &ExportOptions,
+) -> Result<(Vec<Vec<f32>>, SpeakerLayout)> {
+    if !options.mono {
+        return Ok((channels, options.layout));
+    }
+    match channels.as_slice() {
+        [left, right] => Ok((vec![stereo_to_mono(left, right)], SpeakerLayout::Mono)),
+        _ => Err(ExportError::InvalidOptions(
+            "Mono downmix is only supported for stereo exports".into(),
+        )),
+    }
+}
+
+/// Fail early when the channel count doesn't match the layout.
+pub(crate) fn check_layout(channels: &[&[f32]], options: &ExportOptions) -> Result<()> {
+    if channels.len() != options.layout.channels() {
+        return Err(ExportError::InvalidOptions(format!(
+            "{:?} needs {} channels, got {}",
+            options.layout,
+            options.layout.channels(),
+            channels.len()
+        )));
+    }
+    Ok(())
 }
 
 /// Convert stereo to mono by averaging channels.
-#[cfg(any(feature = "wav", feature = "flac"))]
 pub(crate) fn stereo_to_mono(left: &[f32], right: &[f32]) -> Vec<f32> {
     left.iter()
         .zip(right.iter())
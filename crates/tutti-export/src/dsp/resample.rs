@@ -2,9 +2,7 @@
 //!
 //! Provides high-quality sample rate conversion with SIMD optimization.
 
-#[cfg(any(feature = "wav", feature = "flac"))]
 use crate::error::{ExportError, Result};
-#[cfg(any(feature = "wav", feature = "flac"))]
 use rubato::{FftFixedIn, Resampler};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -16,7 +14,6 @@ pub enum ResampleQuality {
     Best,
 }
 
-#[cfg(any(feature = "wav", feature = "flac"))]
 impl ResampleQuality {
     fn chunk_size(&self) -> usize {
         match self {
@@ -37,21 +34,20 @@ impl ResampleQuality {
     }
 }
 
-#[cfg(any(feature = "wav", feature = "flac"))]
-pub(crate) fn resample_stereo(
-    left: &[f32],
-    right: &[f32],
+pub(crate) fn resample_channels(
+    channels: &[Vec<f32>],
     source_rate: u32,
     target_rate: u32,
     quality: ResampleQuality,
-) -> Result<(Vec<f32>, Vec<f32>)> {
-    if source_rate == target_rate {
-        return Ok((left.to_vec(), right.to_vec()));
+) -> Result<Vec<Vec<f32>>> {
+    if source_rate == target_rate || channels.is_empty() {
+        return Ok(channels.to_vec());
     }
 
-    if left.len() != right.len() {
+    let input_frames = channels[0].len();
+    if channels.iter().any(|c| c.len() != input_frames) {
         return Err(ExportError::InvalidData(
-            "Left and right channels have different lengths".into(),
+            "Channels have different lengths".into(),
         ));
     }
 
@@ -63,15 +59,16 @@ pub(crate) fn resample_stereo(
         target_rate as usize,
         chunk_size,
         sub_chunks,
-        2,
+        channels.len(),
     )?;
 
-    let input_frames = left.len();
     let expected_output_frames =
         (input_frames as f64 * target_rate as f64 / source_rate as f64).ceil() as usize;
 
-    let mut output_left = Vec::with_capacity(expected_output_frames + chunk_size);
-    let mut output_right = Vec::with_capacity(expected_output_frames + chunk_size);
+    let mut output: Vec<Vec<f32>> = channels
+        .iter()
+        .map(|_| Vec::with_capacity(expected_output_frames + chunk_size))
+        .collect();
 
     let mut pos = 0;
     while pos < input_frames {
@@ -85,26 +82,30 @@ pub(crate) fn resample_stereo(
             frames_to_process.max(input_frames_needed)
         };
 
-        let mut chunk_left = vec![0.0f32; actual_frames];
-        let mut chunk_right = vec![0.0f32; actual_frames];
-
         let copy_frames = frames_to_process.min(remaining);
-        chunk_left[..copy_frames].copy_from_slice(&left[pos..pos + copy_frames]);
-        chunk_right[..copy_frames].copy_from_slice(&right[pos..pos + copy_frames]);
+        let input_chunk: Vec<Vec<f32>> = channels
+            .iter()
+            .map(|channel| {
+                let mut chunk = vec![0.0f32; actual_frames];
+                chunk[..copy_frames].copy_from_slice(&channel[pos..pos + copy_frames]);
+                chunk
+            })
+            .collect();
 
-        let input_channels = vec![chunk_left, chunk_right];
-        let output = resampler.process(&input_channels, None)?;
-        output_left.extend_from_slice(&output[0]);
-        output_right.extend_from_slice(&output[1]);
+        let resampled = resampler.process(&input_chunk, None)?;
+        for (out, chunk) in output.iter_mut().zip(&resampled) {
+            out.extend_from_slice(chunk);
+        }
 
         pos += actual_frames;
     }
 
-    let final_length = expected_output_frames.min(output_left.len());
-    output_left.truncate(final_length);
-    output_right.truncate(final_length);
+    for out in output.iter_mut() {
+        let final_length = expected_output_frames.min(out.len());
+        out.truncate(final_length);
+    }
 
-    Ok((output_left, output_right))
+    Ok(output)
 }
 
 #[cfg(test)]
@@ -113,14 +114,11 @@ mod tests {
 
     #[test]
     fn test_no_resample_needed() {
-        let left = vec![1.0, 2.0, 3.0];
-        let right = vec![4.0, 5.0, 6.0];
+        let channels = vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]];
 
-        let (out_l, out_r) =
-            resample_stereo(&left, &right, 44100, 44100, ResampleQuality::Fast).unwrap();
+        let out = resample_channels(&channels, 44100, 44100, ResampleQuality::Fast).unwrap();
 
-        assert_eq!(out_l, left);
-        assert_eq!(out_r, right);
+        assert_eq!(out, channels);
     }
 
     #[test]
@@ -133,16 +131,11 @@ mod tests {
         let left: Vec<f32> = (0..duration_samples)
             .map(|i| (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / sample_rate as f32).sin())
             .collect();
-        let right = left.clone();
+        let channels = vec![left; 6];
 
-        let (out_l, out_r) = resample_stereo(
-            &left,
-            &right,
-            sample_rate,
-            target_rate,
-            ResampleQuality::Medium,
-        )
-        .unwrap();
+        let out = resample_channels(&channels, sample_rate, target_rate, ResampleQuality::Medium)
+            .unwrap();
+        let out_l = &out[0];
 
         // Check output length is approximately correct
         let expected_length =
@@ -153,7 +146,7 @@ mod tests {
             out_l.len(),
             expected_length
         );
-        assert_eq!(out_l.len(), out_r.len());
+        assert!(out.iter().all(|c| c.len() == out_l.len()));
     }
 
     #[test]
@@ -165,16 +158,11 @@ mod tests {
         let left: Vec<f32> = (0..duration_samples)
             .map(|i| (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / sample_rate as f32).sin())
             .collect();
-        let right = left.clone();
+        let channels = vec![left.clone(), left];
 
-        let (out_l, _out_r) = resample_stereo(
-            &left,
-            &right,
-            sample_rate,
-            target_rate,
-            ResampleQuality::High,
-        )
-        .unwrap();
+        let out =
+            resample_channels(&channels, sample_rate, target_rate, ResampleQuality::High).unwrap();
+        let out_l = &out[0];
 
         // Check output length is approximately correct
         let expected_length =
@@ -189,10 +177,9 @@ mod tests {
 
     #[test]
     fn test_mismatched_channel_lengths() {
-        let left = vec![1.0, 2.0, 3.0];
-        let right = vec![4.0, 5.0];
+        let channels = vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0]];
 
-        let result = resample_stereo(&left, &right, 44100, 48000, ResampleQuality::Fast);
+        let result = resample_channels(&channels, 44100, 48000, ResampleQuality::Fast);
         assert!(result.is_err());
     }
 }
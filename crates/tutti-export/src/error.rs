@@ -23,17 +23,13 @@ pub enum ExportError {
 
     #[error("Invalid audio data: {0}")]
     InvalidData(String),
+
+    #[error("Export cancelled")]
+    Cancelled,
 }
 
 pub type Result<T> = core::result::Result<T, ExportError>;
 
-#[cfg(feature = "wav")]
-impl From<hound::Error> for ExportError {
-    fn from(e: hound::Error) -> Self {
-        ExportError::Io(io::Error::other(e))
-    }
-}
-
 impl From<rubato::ResamplerConstructionError> for ExportError {
     fn from(e: rubato::ResamplerConstructionError) -> Self {
         ExportError::Resample(e.to_string())
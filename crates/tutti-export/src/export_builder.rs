@@ -1,13 +1,35 @@
 use crate::handle::ExportHandle;
-use crate::{AudioFormat, ExportOptions, NormalizationMode, Result};
-use std::path::Path;
-use tutti_core::{AudioUnit, ExportContext};
+use crate::queue::CancelToken;
+use crate::range::{self, Position, TailDetector, TailOptions};
+use crate::stem::{self, Stem, StemAudio, StemTap};
+use crate::{
+    AudioFormat, ExportOptions, LimiterOptions, Metadata, Mp3Options, NormalizationMode,
+    OpusOptions, Result, SpeakerLayout, VorbisOptions,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tutti_core::{AudioUnit, ExportContext, TransportReader, BBT};
 
 #[derive(Debug, Clone, Copy)]
 pub struct ExportProgress {
     pub phase: ExportPhase,
     /// Progress within current phase (0.0 to 1.0).
     pub progress: f32,
+    /// Set on the update that ends the processing phase.
+    pub loudness: Option<LoudnessReport>,
+}
+
+/// Loudness of an export as written, re-measured after all processing, and
+/// how much the limiter had to do to get there.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoudnessReport {
+    pub integrated_lufs: f64,
+    pub true_peak_dbtp: f64,
+    pub loudness_range_lu: f64,
+    /// Deepest limiter gain reduction in dB (0 without a limiter).
+    pub max_gain_reduction_db: f64,
+    /// Fraction of the export (0.0 to 1.0) the limiter was reducing gain in.
+    pub limited_fraction: f64,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -26,14 +48,59 @@ pub enum ExportPhase {
 ///     .format(AudioFormat::Flac)
 ///     .normalize(NormalizationMode::lufs(-14.0))
 ///     .to_file("output.flac")?;
+///
+/// // Bars 5-12, the loop played twice, plus the reverb tail
+/// engine.export()
+///     .bbt_range(BBT::new(5, 1, 0), BBT::new(13, 1, 0))
+///     .loop_count(2)
+///     .tail(TailOptions::default())
+///     .to_file("chorus.wav")?;
 /// ```
 pub struct ExportBuilder {
     net: tutti_core::dsp::Net,
     sample_rate: f64,
     duration_seconds: Option<f64>,
+    /// Range boundaries, resolved through the context's tempo map
+    range_start: Option<Position>,
+    range_end: Option<Position>,
+    /// Range end when none is given (the end of the engine's content)
+    default_end_beat: Option<f64>,
+    loop_count: Option<u32>,
+    tail: Option<TailOptions>,
+    /// Threshold (dBFS) for trimming silence off both ends
+    trim_silence_db: Option<f64>,
     options: ExportOptions,
     compensate_latency: bool,
     context: Option<ExportContext>,
+    stems: Vec<Stem>,
+    cancel: Option<CancelToken>,
+    /// Set up while preparing the net; fails the render
+    setup_error: Option<String>,
+}
+
+/// Master and stem buffers from one render pass.
+struct Rendered {
+    channels: Vec<Vec<f32>>,
+    stems: Vec<StemAudio>,
+    /// Timeline position of the first frame, when rendered from a timeline
+    start_seconds: Option<f64>,
+}
+
+/// Where the render starts and how long the range runs.
+struct Plan {
+    /// Timeline start; `None` keeps the context's current position
+    start_beat: Option<f64>,
+    seconds: f64,
+    loop_count: Option<u32>,
+}
+
+impl Rendered {
+    fn into_stereo(self) -> (Vec<f32>, Vec<f32>) {
+        let mut channels = self.channels.into_iter();
+        let left = channels.next().unwrap_or_default();
+        let right = channels.next().unwrap_or_else(|| left.clone());
+        (left, right)
+    }
 }
 
 impl ExportBuilder {
@@ -46,9 +113,18 @@ impl ExportBuilder {
             net,
             sample_rate,
             duration_seconds: None,
+            range_start: None,
+            range_end: None,
+            default_end_beat: None,
+            loop_count: None,
+            tail: None,
+            trim_silence_db: None,
             options,
             compensate_latency: false,
             context: None,
+            stems: Vec::new(),
+            cancel: None,
+            setup_error: None,
         }
     }
 
@@ -61,11 +137,77 @@ impl ExportBuilder {
 
     pub fn duration_seconds(mut self, seconds: f64) -> Self {
         self.duration_seconds = Some(seconds);
+        self.range_end = None;
         self
     }
 
+    /// Duration of `beats` at a constant `tempo`. Use
+    /// [`beat_range`](Self::beat_range) to follow the project's tempo map.
     pub fn duration_beats(mut self, beats: f64, tempo: f64) -> Self {
         self.duration_seconds = Some((beats / tempo) * 60.0);
+        self.range_end = None;
+        self
+    }
+
+    /// Render from `start_beat` to `end_beat`. The timeline starts at
+    /// `start_beat` and the duration follows the context's tempo map, so
+    /// tempo ramps and changes land where they do live.
+    pub fn beat_range(mut self, start_beat: f64, end_beat: f64) -> Self {
+        self.range_start = Some(Position::Beat(start_beat));
+        self.range_end = Some(Position::Beat(end_beat));
+        self.duration_seconds = None;
+        self
+    }
+
+    /// [`beat_range`](Self::beat_range) in bars and beats, following the
+    /// tempo map's meter changes.
+    pub fn bbt_range(mut self, start: BBT, end: BBT) -> Self {
+        self.range_start = Some(Position::Bbt(start));
+        self.range_end = Some(Position::Bbt(end));
+        self.duration_seconds = None;
+        self
+    }
+
+    /// Start rendering at `beat`. Without a range end or duration, the
+    /// export runs to the [default end](Self::default_end_beat).
+    pub fn from_beat(mut self, beat: f64) -> Self {
+        self.range_start = Some(Position::Beat(beat));
+        self
+    }
+
+    /// [`from_beat`](Self::from_beat) in bars and beats.
+    pub fn from_bbt(mut self, start: BBT) -> Self {
+        self.range_start = Some(Position::Bbt(start));
+        self
+    }
+
+    /// Range end used when neither an end nor a duration is given.
+    /// `engine.export()` sets this to the end of the project's content.
+    pub fn default_end_beat(mut self, beat: f64) -> Self {
+        self.default_end_beat = Some(beat);
+        self
+    }
+
+    /// Play the transport's loop region `count` times before carrying on
+    /// to the range end; the duration grows to match. Ranges otherwise play
+    /// straight through the loop once.
+    pub fn loop_count(mut self, count: u32) -> Self {
+        self.loop_count = Some(count.max(1));
+        self
+    }
+
+    /// Keep rendering past the range end until the output stays silent, so
+    /// reverb and delay tails aren't cut off.
+    pub fn tail(mut self, tail: TailOptions) -> Self {
+        self.tail = Some(tail);
+        self
+    }
+
+    /// Trim leading and trailing audio below `threshold_db` (dBFS). Stems
+    /// are trimmed by the same amount so they stay aligned with the master,
+    /// and the Broadcast Wave time reference moves with the start.
+    pub fn trim_silence(mut self, threshold_db: f64) -> Self {
+        self.trim_silence_db = Some(threshold_db);
         self
     }
 
@@ -79,18 +221,97 @@ impl ExportBuilder {
         self
     }
 
+    /// Dither for integer bit depths, e.g.
+    /// `DitherType::NoiseShaped(NoiseShape::FWeighted)` for 16-bit masters.
+    pub fn dither(mut self, dither: crate::DitherType) -> Self {
+        self.options.dither = dither;
+        self
+    }
+
+    /// Bitrate for `.opus` files.
+    pub fn opus(mut self, opus: OpusOptions) -> Self {
+        self.options.opus = opus;
+        self
+    }
+
+    /// Quality for `.ogg` files.
+    pub fn vorbis(mut self, vorbis: VorbisOptions) -> Self {
+        self.options.vorbis = vorbis;
+        self
+    }
+
+    /// Bitrate for `.mp3` files.
+    pub fn mp3(mut self, mp3: Mp3Options) -> Self {
+        self.options.mp3 = mp3;
+        self
+    }
+
     pub fn normalize(mut self, mode: NormalizationMode) -> Self {
         self.options.normalization = mode;
         self
     }
 
+    /// Reach a loudness target by limiting peaks to its true-peak limit
+    /// instead of lowering the gain. The result is reported on the progress
+    /// update that ends processing.
+    pub fn limiter(mut self, limiter: LimiterOptions) -> Self {
+        self.options.limiter = Some(limiter);
+        self
+    }
+
+    /// Tags for the exported file. Loudness tags are added automatically.
+    pub fn metadata(mut self, metadata: Metadata) -> Self {
+        self.options.metadata = metadata;
+        self
+    }
+
+    /// Render this many of the net's outputs, laid out as `layout` says.
+    /// The net needs at least `layout.channels()` outputs, except that a
+    /// mono net may be exported as stereo.
+    pub fn layout(mut self, layout: SpeakerLayout) -> Self {
+        self.options.layout = layout;
+        self
+    }
+
     /// Trim initial latency (from look-ahead limiters, linear-phase filters, etc.)
-    /// from the rendered audio. Disable when exporting stems that need time-alignment.
+    /// from the rendered audio. Stems are shifted by the same amount, so they
+    /// stay sample-aligned with the master either way.
     pub fn compensate_latency(mut self, enabled: bool) -> Self {
         self.compensate_latency = enabled;
         self
     }
 
+    /// Also capture `stem` during the render. File exports write each stem
+    /// next to the master as `<name>.<ext>`, using the master's format and
+    /// options apart from normalization, and titled with the stem's name.
+    pub fn stem(mut self, stem: Stem) -> Self {
+        self.stems.push(stem);
+        self
+    }
+
+    pub fn stems(mut self, stems: impl IntoIterator<Item = Stem>) -> Self {
+        self.stems.extend(stems);
+        self
+    }
+
+    /// Stop the export when `token` is cancelled. It stops at the next
+    /// render block or before the next file, fails with
+    /// [`ExportError::Cancelled`](crate::ExportError::Cancelled) and removes
+    /// the files it wrote.
+    pub fn cancel_token(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Fail the export with [`ExportError::Render`](crate::ExportError::Render)
+    /// instead of rendering. `engine.export()` uses this when a node of the
+    /// net couldn't be prepared, so the bounce never silently differs from
+    /// playback.
+    pub fn setup_error(mut self, message: impl Into<String>) -> Self {
+        self.setup_error = Some(message.into());
+        self
+    }
+
     pub fn to_file(self, path: impl AsRef<Path>) -> Result<()> {
         self.to_file_with_progress(path, |_| {})
     }
@@ -100,18 +321,39 @@ impl ExportBuilder {
         path: impl AsRef<Path>,
         on_progress: impl Fn(ExportProgress),
     ) -> Result<()> {
-        let options = self.options.clone();
-        let (left, right) = self.render_impl(&on_progress)?;
-
+        let mut options = self.options.clone();
+        let normalizations: Vec<_> = self.stems.iter().map(|s| s.normalization).collect();
         let path = path.as_ref();
-        crate::export_to_file_with_progress(
-            path.to_str()
-                .ok_or_else(|| crate::ExportError::InvalidOptions("Invalid path".into()))?,
-            &left,
-            &right,
+        let stem_paths = self
+            .stems
+            .iter()
+            .map(|s| stem_path(path, &s.name, options.format))
+            .collect::<Result<Vec<_>>>()?;
+
+        let cancel = self.cancel.clone();
+        let rendered = self.render_impl(&on_progress)?;
+        if let Some(start_seconds) = rendered.start_seconds {
+            options.start_seconds = start_seconds;
+        }
+
+        let mut started = Vec::new();
+        let result = write_files(
+            &rendered,
+            path,
+            &stem_paths,
+            &normalizations,
             &options,
-            on_progress,
-        )
+            cancel.as_ref(),
+            &mut started,
+            &on_progress,
+        );
+        if result.is_err() {
+            // Don't leave half-written files or an incomplete set behind
+            for file in started {
+                let _ = std::fs::remove_file(file);
+            }
+        }
+        result
     }
 
     /// Start a non-blocking background export, returning a handle to poll progress.
@@ -130,13 +372,15 @@ impl ExportBuilder {
     ///         ExportStatus::Running(p) => println!("{:.0}%", p.progress * 100.0),
     ///         ExportStatus::Complete => break,
     ///         ExportStatus::Failed(e) => { eprintln!("{}", e); break; }
+    ///         ExportStatus::Cancelled => break,
     ///         ExportStatus::Pending => {}
     ///     }
     /// }
     /// ```
-    pub fn start(self, path: impl AsRef<Path>) -> ExportHandle {
+    pub fn start(mut self, path: impl AsRef<Path>) -> ExportHandle {
         let path = path.as_ref().to_path_buf();
         let (tx, rx) = crossbeam_channel::bounded(64);
+        let cancel = self.cancel.get_or_insert_with(CancelToken::new).clone();
 
         let thread = std::thread::Builder::new()
             .name("tutti-export".into())
@@ -147,12 +391,12 @@ impl ExportBuilder {
             })
             .expect("failed to spawn export thread");
 
-        ExportHandle::new(rx, thread)
+        ExportHandle::new(rx, thread, cancel)
     }
 
     pub fn render(self) -> Result<(Vec<f32>, Vec<f32>, f64)> {
         let sample_rate = self.sample_rate;
-        let (left, right) = self.render_impl(&|_| {})?;
+        let (left, right) = self.render_impl(&|_| {})?.into_stereo();
         Ok((left, right, sample_rate))
     }
 
@@ -161,22 +405,117 @@ impl ExportBuilder {
         on_progress: impl Fn(ExportProgress),
     ) -> Result<(Vec<f32>, Vec<f32>, f64)> {
         let sample_rate = self.sample_rate;
-        let (left, right) = self.render_impl(&on_progress)?;
+        let (left, right) = self.render_impl(&on_progress)?.into_stereo();
         Ok((left, right, sample_rate))
     }
 
+    /// Render every channel of the layout (see [`layout`](Self::layout)).
+    pub fn render_channels(self) -> Result<(Vec<Vec<f32>>, f64)> {
+        let sample_rate = self.sample_rate;
+        let rendered = self.render_impl(&|_| {})?;
+        Ok((rendered.channels, sample_rate))
+    }
+
+    /// Render the master and every stem in one pass, without normalization.
+    /// Stems come back in the order they were added.
+    pub fn render_stems(self) -> Result<(Vec<Vec<f32>>, Vec<StemAudio>, f64)> {
+        let sample_rate = self.sample_rate;
+        let rendered = self.render_impl(&|_| {})?;
+        Ok((rendered.channels, rendered.stems, sample_rate))
+    }
+
+    /// Resolve the range (or duration) against the context's timeline.
+    fn plan(&self) -> Result<Plan> {
+        if let Some(ref message) = self.setup_error {
+            return Err(crate::ExportError::Render(message.clone()));
+        }
+
+        let end = match self.range_end {
+            Some(end) => Some(end),
+            None if self.duration_seconds.is_some() => None,
+            None => self.default_end_beat.map(Position::Beat),
+        };
+        let timeline = self.context.as_ref().map(|c| &c.timeline);
+
+        let Some(end) = end else {
+            let seconds = self.duration_seconds.ok_or_else(|| {
+                crate::ExportError::InvalidOptions(
+                    "Duration not set. Use .duration_seconds(), .duration_beats() or .beat_range()"
+                        .into(),
+                )
+            })?;
+            let start_beat = match (self.range_start, timeline) {
+                (Some(start), Some(timeline)) => Some(start.to_beats(timeline)),
+                _ => None,
+            };
+            return Ok(Plan {
+                start_beat,
+                seconds,
+                loop_count: None,
+            });
+        };
+
+        let timeline = timeline.ok_or_else(|| {
+            crate::ExportError::InvalidOptions(
+                "A beat range needs an export context for its tempo map".into(),
+            )
+        })?;
+        let start_beat = self
+            .range_start
+            .map_or(0.0, |start| start.to_beats(timeline));
+        let end_beat = end.to_beats(timeline);
+        if end_beat <= start_beat {
+            return Err(crate::ExportError::InvalidOptions(format!(
+                "Empty beat range {}..{}",
+                start_beat, end_beat
+            )));
+        }
+
+        let loop_count = self.loop_count.unwrap_or(1);
+        let mut seconds = timeline.seconds_between(start_beat, end_beat);
+        match timeline.get_loop_range() {
+            // Each extra pass replays the loop region once more
+            Some((loop_start, loop_end))
+                if loop_start < loop_end && start_beat < loop_end && loop_end <= end_beat =>
+            {
+                seconds += (loop_count - 1) as f64 * timeline.seconds_between(loop_start, loop_end);
+            }
+            _ if loop_count > 1 => {
+                return Err(crate::ExportError::InvalidOptions(
+                    "Loop count needs a transport loop that ends inside the range".into(),
+                ))
+            }
+            _ => {}
+        }
+
+        Ok(Plan {
+            start_beat: Some(start_beat),
+            seconds,
+            loop_count: Some(loop_count),
+        })
+    }
+
     /// Block-based offline render. Processes audio in blocks of up to 64 samples
     /// (MAX_BUFFER_SIZE) for efficient SIMD processing. If an ExportContext is
     /// present, advances its timeline so transport-aware nodes (samplers, MIDI
     /// synths) produce correct output.
-    fn render_impl(self, on_progress: &impl Fn(ExportProgress)) -> Result<(Vec<f32>, Vec<f32>)> {
+    ///
+    /// Stems are recorded by taps pushed into the same net, then shifted by
+    /// the compensation their path would get on the way to the master.
+    fn render_impl(self, on_progress: &impl Fn(ExportProgress)) -> Result<Rendered> {
         use tutti_core::{BufferRef, BufferVec, MAX_BUFFER_SIZE};
 
-        let duration = self.duration_seconds.ok_or_else(|| {
-            crate::ExportError::InvalidOptions(
-                "Duration not set. Use .duration_seconds() or .duration_beats()".into(),
-            )
-        })?;
+        let plan = self.plan()?;
+        if let Some(ref context) = self.context {
+            if let Some(start_beat) = plan.start_beat {
+                context.timeline.reset(start_beat);
+            }
+            if plan.loop_count.is_some() {
+                context.timeline.set_loop_count(plan.loop_count);
+            }
+        }
+        let start_seconds = self.context.as_ref().map(|c| c.timeline.current_seconds());
+        let cancel = self.cancel;
 
         let mut net = self.net;
         net.set_sample_rate(self.sample_rate);
@@ -186,13 +525,67 @@ impl ExportBuilder {
         } else {
             0
         };
-        let extra_duration = latency_samples as f64 / self.sample_rate;
 
-        let total_samples = ((duration + extra_duration) * self.sample_rate).round() as usize;
-        let output_length = (duration * self.sample_rate).round() as usize;
+        // The range itself, then room for the tail until it goes quiet
+        let range_length = (plan.seconds * self.sample_rate).round() as usize;
+        let max_tail = self.tail.map_or(0, |tail| {
+            (tail.max_seconds * self.sample_rate).round() as usize
+        });
+        let mut output_length = range_length + max_tail;
+        let mut detector = self
+            .tail
+            .map(|tail| TailDetector::new(&tail, self.sample_rate));
+
+        // Where each stem starts in its raw capture, relative to the master
+        let mut memo = HashMap::new();
+        let master_latency = stem::master_path_latency(&mut net, &mut memo) as isize;
+        let mut stem_skips = Vec::with_capacity(self.stems.len());
+        for stem in &self.stems {
+            if !net.contains(stem.node) {
+                return Err(crate::ExportError::InvalidOptions(format!(
+                    "Stem '{}' refers to a node that is not in the graph",
+                    stem.name
+                )));
+            }
+            let offset =
+                master_latency - stem::path_latency(&mut net, stem.node, &mut memo) as isize;
+            stem_skips.push(latency_samples as isize - offset);
+        }
 
-        let mut left = Vec::with_capacity(output_length);
-        let mut right = Vec::with_capacity(output_length);
+        let stem_extra = (stem_skips.iter().copied().max().unwrap_or(0).max(0) as usize)
+            .saturating_sub(latency_samples);
+        let mut total_samples = latency_samples + output_length + stem_extra;
+
+        let taps: Vec<_> = self
+            .stems
+            .iter()
+            .map(|stem| {
+                let channels = net.outputs_in(stem.node);
+                let tap = net.push(Box::new(StemTap::new(channels, total_samples)));
+                for port in 0..channels {
+                    net.connect(stem.node, port, tap, port);
+                }
+                tap
+            })
+            .collect();
+
+        // A mono net fills both sides of a stereo export
+        let layout = self.options.layout;
+        let channel_count = layout.channels();
+        let sources: Vec<usize> = match (net.outputs(), layout) {
+            (1, SpeakerLayout::Stereo) => vec![0, 0],
+            (outputs, _) if outputs >= channel_count => (0..channel_count).collect(),
+            (outputs, layout) => {
+                return Err(crate::ExportError::InvalidOptions(format!(
+                    "{:?} needs {} outputs, the graph has {}",
+                    layout, channel_count, outputs
+                )))
+            }
+        };
+        let mut channels: Vec<Vec<f32>> = sources
+            .iter()
+            .map(|_| Vec::with_capacity(output_length))
+            .collect();
 
         let mut buffer = BufferVec::new(net.outputs().max(2));
         let progress_interval = (self.sample_rate * 0.5) as usize;
@@ -202,6 +595,7 @@ impl ExportBuilder {
         on_progress(ExportProgress {
             phase: ExportPhase::Rendering,
             progress: 0.0,
+            loudness: None,
         });
 
         // If context exists, advance by 1 sample so the first processed sample
@@ -212,6 +606,9 @@ impl ExportBuilder {
 
         let mut i = 0;
         while i < total_samples {
+            if cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+                return Err(crate::ExportError::Cancelled);
+            }
             let block_size = (total_samples - i).min(MAX_BUFFER_SIZE);
 
             let mut buffer_mut = buffer.buffer_mut();
@@ -224,13 +621,22 @@ impl ExportBuilder {
 
             for j in 0..block_size {
                 let sample_idx = i + j;
-                if sample_idx >= latency_samples && left.len() < output_length {
-                    left.push(buffer_mut.at_f32(0, j));
-                    right.push(if net.outputs() >= 2 {
-                        buffer_mut.at_f32(1, j)
-                    } else {
-                        buffer_mut.at_f32(0, j)
-                    });
+                if sample_idx >= latency_samples && channels[0].len() < output_length {
+                    for (channel, &source) in channels.iter_mut().zip(&sources) {
+                        channel.push(buffer_mut.at_f32(source, j));
+                    }
+                    let Some(ref mut detector) = detector else {
+                        continue;
+                    };
+                    let frame = sources.iter().map(|&source| buffer_mut.at_f32(source, j));
+                    if channels[0].len() > range_length && detector.push(frame) {
+                        // The tail has died away: end where the silence began
+                        output_length = channels[0].len() - detector.run;
+                        for channel in &mut channels {
+                            channel.truncate(output_length);
+                        }
+                        total_samples = latency_samples + output_length + stem_extra;
+                    }
                 }
             }
 
@@ -240,11 +646,228 @@ impl ExportBuilder {
                 on_progress(ExportProgress {
                     phase: ExportPhase::Rendering,
                     progress: i as f32 / total_samples as f32,
+                    loudness: None,
                 });
                 next_progress += progress_interval;
             }
         }
 
-        Ok((left, right))
+        let mut stems: Vec<StemAudio> = self
+            .stems
+            .into_iter()
+            .zip(taps)
+            .zip(stem_skips)
+            .map(|((stem, tap), skip)| {
+                let mut raw = <dyn AudioUnit>::as_any_mut(net.node_mut(tap))
+                    .downcast_mut::<StemTap>()
+                    .map(StemTap::take_channels)
+                    .unwrap_or_default();
+                if raw.len() == 1 && layout == SpeakerLayout::Stereo {
+                    raw.push(raw[0].clone());
+                }
+                StemAudio {
+                    name: stem.name,
+                    channels: raw
+                        .iter()
+                        .map(|channel| stem::align(channel, skip, output_length))
+                        .collect(),
+                }
+            })
+            .collect();
+
+        let mut start_seconds = start_seconds;
+        if let Some((start, end)) = self
+            .trim_silence_db
+            .and_then(|threshold_db| range::audible_bounds(&channels, threshold_db))
+        {
+            for channel in channels
+                .iter_mut()
+                .chain(stems.iter_mut().flat_map(|stem| stem.channels.iter_mut()))
+            {
+                channel.truncate(end);
+                channel.drain(..start);
+            }
+            start_seconds = start_seconds.map(|s| s + start as f64 / self.sample_rate);
+        }
+
+        Ok(Rendered {
+            channels,
+            stems,
+            start_seconds,
+        })
+    }
+}
+
+/// Write the master, then each stem, recording every file before it is
+/// started so a failure can remove them.
+#[allow(clippy::too_many_arguments)]
+fn write_files(
+    rendered: &Rendered,
+    path: &Path,
+    stem_paths: &[PathBuf],
+    normalizations: &[NormalizationMode],
+    options: &ExportOptions,
+    cancel: Option<&CancelToken>,
+    started: &mut Vec<PathBuf>,
+    on_progress: &impl Fn(ExportProgress),
+) -> Result<()> {
+    let check_cancelled = || match cancel {
+        Some(token) if token.is_cancelled() => Err(crate::ExportError::Cancelled),
+        _ => Ok(()),
+    };
+
+    check_cancelled()?;
+    started.push(path.to_path_buf());
+    crate::export_channels_to_file_with_progress(
+        path_str(path)?,
+        &as_slices(&rendered.channels),
+        options,
+        on_progress,
+    )?;
+
+    for ((audio, file), &normalization) in rendered.stems.iter().zip(stem_paths).zip(normalizations)
+    {
+        // Stems with the master's channel count share its layout
+        let layout = if audio.channels.len() == options.layout.channels() {
+            options.layout
+        } else {
+            SpeakerLayout::for_channels(audio.channels.len())
+        };
+        let options = ExportOptions {
+            normalization,
+            layout,
+            metadata: Metadata {
+                title: Some(audio.name.clone()),
+                ..options.metadata.clone()
+            },
+            ..options.clone()
+        };
+        check_cancelled()?;
+        started.push(file.clone());
+        crate::export_channels_to_file_with_progress(
+            path_str(file)?,
+            &as_slices(&audio.channels),
+            &options,
+            on_progress,
+        )?;
+    }
+    Ok(())
+}
+
+fn as_slices(channels: &[Vec<f32>]) -> Vec<&[f32]> {
+    channels.iter().map(Vec::as_slice).collect()
+}
+
+fn path_str(path: &Path) -> Result<&str> {
+    path.to_str()
+        .ok_or_else(|| crate::ExportError::InvalidOptions("Invalid path".into()))
+}
+
+/// `<master dir>/<name>.<ext>`. Names must be plain file names.
+fn stem_path(master: &Path, name: &str, format: AudioFormat) -> Result<std::path::PathBuf> {
+    if name.is_empty() || name.contains(['/', '\\']) || name == "." || name == ".." {
+        return Err(crate::ExportError::InvalidOptions(format!(
+            "Invalid stem name '{}'",
+            name
+        )));
+    }
+    let file = format!("{}.{}", name, format.extension());
+    Ok(master.with_file_name(file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fundsp::prelude::*;
+    use tutti_core::NodeId;
+
+    fn two_track_net() -> (Net, NodeId, NodeId) {
+        let mut net = Net::new(0, 1);
+        let a = net.push(Box::new(dc(0.25)));
+        let b = net.push(Box::new(dc(0.5)));
+        let mix = net.push(Box::new(pass() + pass()));
+        net.connect(a, 0, mix, 0);
+        net.connect(b, 0, mix, 1);
+        net.pipe_output(mix);
+        (net, a, b)
+    }
+
+    #[test]
+    fn test_stems_share_the_master_pass() {
+        let (net, a, b) = two_track_net();
+        let (master, stems, _) = ExportBuilder::new(net, 48000.0)
+            .duration_seconds(0.01)
+            .stem(Stem::new(a, "a"))
+            .stem(Stem::new(b, "b"))
+            .render_stems()
+            .unwrap();
+
+        let left = &master[0];
+        assert_eq!(left.len(), 480);
+        assert_eq!(master[1], *left);
+        assert!(left.iter().all(|&s| (s - 0.75).abs() < 1e-6));
+
+        assert_eq!(stems.len(), 2);
+        assert_eq!(stems[0].name, "a");
+        assert!(stems[0].channels[0]
+            .iter()
+            .all(|&s| (s - 0.25).abs() < 1e-6));
+        // Mono stems are exported as stereo, like the master
+        assert!(stems[1].channels[1].iter().all(|&s| (s - 0.5).abs() < 1e-6));
+        assert!(stems
+            .iter()
+            .flat_map(|s| &s.channels)
+            .all(|c| c.len() == left.len()));
+    }
+
+    #[test]
+    fn test_setup_error_fails_render() {
+        let (net, _, _) = two_track_net();
+        let result = ExportBuilder::new(net, 48000.0)
+            .duration_seconds(0.01)
+            .setup_error("reverb: offline instance failed")
+            .render();
+        assert!(matches!(result, Err(crate::ExportError::Render(ref m)) if m.contains("reverb")));
+    }
+
+    #[test]
+    fn test_layout_selects_outputs() {
+        let mut net = Net::new(0, 6);
+        for ch in 0..6 {
+            let source = net.push(Box::new(dc(ch as f32)));
+            net.connect_output(source, 0, ch);
+        }
+        let (channels, _) = ExportBuilder::new(net.clone(), 48000.0)
+            .duration_seconds(0.001)
+            .layout(SpeakerLayout::Surround5_1)
+            .render_channels()
+            .unwrap();
+        assert_eq!(channels.len(), 6);
+        assert!(channels[3].iter().all(|&s| s == 3.0));
+
+        let too_wide = ExportBuilder::new(net, 48000.0)
+            .duration_seconds(0.001)
+            .layout(SpeakerLayout::Surround7_1)
+            .render_channels();
+        assert!(too_wide.is_err());
+    }
+
+    #[test]
+    fn test_stem_paths() {
+        let master = Path::new("mixes/song.wav");
+        assert_eq!(
+            stem_path(master, "song - drums", AudioFormat::Flac).unwrap(),
+            Path::new("mixes/song - drums.flac")
+        );
+        assert!(stem_path(master, "../drums", AudioFormat::Wav).is_err());
+
+        // A node from a different graph
+        let (_, ghost, _) = two_track_net();
+        let (net, _, _) = two_track_net();
+        let result = ExportBuilder::new(net, 48000.0)
+            .duration_seconds(0.01)
+            .stem(Stem::new(ghost, "ghost"))
+            .render_stems();
+        assert!(result.is_err());
     }
 }
@@ -1,7 +1,9 @@
-use crate::dsp::{process_audio, stereo_to_mono};
+use crate::dsp::{check_layout, process_audio, quantize, Processed};
 use crate::error::{ExportError, Result};
 use crate::export_builder::{ExportPhase, ExportProgress};
+use crate::metadata::{replaygain_fields, vorbis_comment};
 use crate::options::{BitDepth, ExportOptions};
+use crate::SpeakerLayout;
 use flacenc::bitsink::ByteSink;
 use flacenc::component::BitRepr;
 use flacenc::config::Encoder as EncoderConfig;
@@ -12,114 +14,101 @@ use std::fs::File;
 use std::io::Write;
 use std::path::Path;
 
+/// FLAC's channel assignment only covers up to 7.1.
+const MAX_FLAC_CHANNELS: usize = 8;
+
+const BLOCK_TYPE_VORBIS_COMMENT: u8 = 4;
+
+/// Channel mask FLAC assigns by default to 1-8 channels.
+const FLAC_DEFAULT_MASKS: [u32; MAX_FLAC_CHANNELS] =
+    [0x4, 0x3, 0x7, 0x33, 0x37, 0x3F, 0x70F, 0x63F];
+
 #[derive(Debug, Clone)]
 struct FlacConfig {
     sample_rate: u32,
     bit_depth: BitDepth,
-    channels: u16,
     block_size: u32,
+    /// Source channel for each file channel
+    order: Vec<usize>,
+    /// Body of the VORBIS_COMMENT block
+    comment: Vec<u8>,
 }
 
-impl Default for FlacConfig {
-    fn default() -> Self {
-        Self {
-            sample_rate: 44100,
-            bit_depth: BitDepth::Int16,
-            channels: 2,
-            block_size: 4096,
-        }
-    }
-}
-
-pub(crate) fn export_flac(
-    path: &str,
-    left: &[f32],
-    right: &[f32],
-    options: &ExportOptions,
-) -> Result<()> {
-    let config = FlacConfig {
-        sample_rate: options.output_sample_rate(),
-        bit_depth: options.bit_depth,
-        channels: if options.mono { 1 } else { 2 },
-        ..Default::default()
-    };
-
-    let (left_proc, right_proc) = process_audio(left, right, options, config.sample_rate)?;
-
-    if options.mono {
-        encode_flac_mono_file(
-            &stereo_to_mono(&left_proc, &right_proc),
-            Path::new(path),
-            &config,
-        )
-    } else {
-        encode_flac_file(&left_proc, &right_proc, Path::new(path), &config)
-    }
+pub(crate) fn export_flac(path: &str, channels: &[&[f32]], options: &ExportOptions) -> Result<()> {
+    export_flac_with_progress(path, channels, options, |_| {})
 }
 
 pub(crate) fn export_flac_with_progress(
     path: &str,
-    left: &[f32],
-    right: &[f32],
+    channels: &[&[f32]],
     options: &ExportOptions,
     on_progress: impl Fn(ExportProgress),
 ) -> Result<()> {
+    check_layout(channels, options)?;
+    if channels.len() > MAX_FLAC_CHANNELS {
+        return Err(ExportError::UnsupportedFormat(format!(
+            "FLAC supports up to {} channels, got {}",
+            MAX_FLAC_CHANNELS,
+            channels.len()
+        )));
+    }
+
     on_progress(ExportProgress {
         phase: ExportPhase::Processing,
         progress: 0.0,
+        loudness: None,
     });
 
+    let sample_rate = options.output_sample_rate();
+    let Processed {
+        channels: processed,
+        layout,
+        loudness,
+        report,
+    } = process_audio(channels, options, sample_rate)?;
+    let mut fields = options.metadata.vorbis_fields();
+    fields.extend(replaygain_fields(&loudness));
+    fields.extend(channel_mask_field(&layout));
     let config = FlacConfig {
-        sample_rate: options.output_sample_rate(),
+        sample_rate,
         bit_depth: options.bit_depth,
-        channels: if options.mono { 1 } else { 2 },
-        ..Default::default()
+        block_size: 4096,
+        order: layout.file_order(),
+        comment: vorbis_comment(&fields),
     };
 
-    let (left_proc, right_proc) = process_audio(left, right, options, config.sample_rate)?;
-
     on_progress(ExportProgress {
         phase: ExportPhase::Processing,
         progress: 1.0,
+        loudness: Some(report),
     });
 
     on_progress(ExportProgress {
         phase: ExportPhase::Encoding,
         progress: 0.0,
+        loudness: None,
     });
 
-    let result = if options.mono {
-        encode_flac_mono_file(
-            &stereo_to_mono(&left_proc, &right_proc),
-            Path::new(path),
-            &config,
-        )
-    } else {
-        encode_flac_file(&left_proc, &right_proc, Path::new(path), &config)
-    };
+    let result = encode_flac_file(&processed, Path::new(path), &config);
 
     on_progress(ExportProgress {
         phase: ExportPhase::Encoding,
         progress: 1.0,
+        loudness: None,
     });
 
     result
 }
 
-fn encode_flac_file(left: &[f32], right: &[f32], path: &Path, config: &FlacConfig) -> Result<()> {
-    let flac_data = encode_flac_memory(left, right, config)?;
+fn encode_flac_file(channels: &[Vec<f32>], path: &Path, config: &FlacConfig) -> Result<()> {
+    let flac_data = encode_flac_memory(channels, config)?;
+    let flac_data = insert_metadata_block(&flac_data, BLOCK_TYPE_VORBIS_COMMENT, &config.comment)?;
     let mut file = File::create(path)?;
     file.write_all(&flac_data)?;
     Ok(())
 }
 
-fn encode_flac_memory(left: &[f32], right: &[f32], config: &FlacConfig) -> Result<Vec<u8>> {
-    if left.len() != right.len() {
-        return Err(ExportError::InvalidData(
-            "Left and right channels have different lengths".into(),
-        ));
-    }
-
+fn encode_flac_memory(channels: &[Vec<f32>], config: &FlacConfig) -> Result<Vec<u8>> {
     if config.bit_depth == BitDepth::Float32 {
         return Err(ExportError::UnsupportedFormat(
             "FLAC does not support 32-bit float".into(),
@@ -132,7 +121,7 @@ fn encode_flac_memory(left: &[f32], right: &[f32], config: &FlacConfig) -> Resul
         BitDepth::Float32 => unreachable!(),
     };
 
-    let interleaved = interleave_to_i32(left, right, config.bit_depth);
+    let interleaved = interleave_to_i32(channels, &config.order, config.bit_depth);
 
     let encoder_config = EncoderConfig::default()
         .into_verified()
@@ -140,7 +129,7 @@ fn encode_flac_memory(left: &[f32], right: &[f32], config: &FlacConfig) -> Resul
 
     let source = MemSource::from_samples(
         &interleaved,
-        config.channels as usize,
+        config.order.len(),
         bits_per_sample,
         config.sample_rate as usize,
     );
@@ -156,68 +145,66 @@ fn encode_flac_memory(left: &[f32], right: &[f32], config: &FlacConfig) -> Resul
     Ok(sink.into_inner())
 }
 
-fn encode_flac_mono_file(samples: &[f32], path: &Path, config: &FlacConfig) -> Result<()> {
-    let flac_data = encode_flac_mono_memory(samples, config)?;
-    let mut file = File::create(path)?;
-    file.write_all(&flac_data)?;
-    Ok(())
-}
-
-fn encode_flac_mono_memory(samples: &[f32], config: &FlacConfig) -> Result<Vec<u8>> {
-    if config.bit_depth == BitDepth::Float32 {
-        return Err(ExportError::UnsupportedFormat(
-            "FLAC does not support 32-bit float".into(),
-        ));
+/// `WAVEFORMATEXTENSIBLE_CHANNEL_MASK` tag for layouts that differ from
+/// FLAC's default assignment, so readers don't fall back to it. Ambisonic
+/// and discrete channels are tagged with an empty mask.
+fn channel_mask_field(layout: &SpeakerLayout) -> Option<(&'static str, String)> {
+    let mask = layout.channel_mask();
+    if FLAC_DEFAULT_MASKS.get(layout.channels().wrapping_sub(1)) == Some(&mask) {
+        return None;
     }
+    Some((
+        "WAVEFORMATEXTENSIBLE_CHANNEL_MASK",
+        format!("0x{:04X}", mask),
+    ))
+}
 
-    let bits_per_sample = match config.bit_depth {
-        BitDepth::Int16 => 16,
-        BitDepth::Int24 => 24,
-        BitDepth::Float32 => unreachable!(),
+/// Append a metadata block after the ones flacenc wrote, making it the last.
+fn insert_metadata_block(flac: &[u8], block_type: u8, body: &[u8]) -> Result<Vec<u8>> {
+    let malformed = || ExportError::Encoding("Malformed FLAC stream from encoder".into());
+
+    // Walk the block headers after "fLaC" to the end of the last one
+    let mut offset = 4;
+    let (last_header, end) = loop {
+        let header = flac.get(offset..offset + 4).ok_or_else(malformed)?;
+        let len = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+        if header[0] & 0x80 != 0 {
+            break (offset, offset + 4 + len);
+        }
+        offset += 4 + len;
     };
+    if end > flac.len() {
+        return Err(malformed());
+    }
 
-    let int_samples: Vec<i32> = samples
-        .iter()
-        .map(|&s| float_to_i32(s, config.bit_depth))
-        .collect();
-
-    let encoder_config = EncoderConfig::default()
-        .into_verified()
-        .map_err(|e| ExportError::Encoding(format!("Invalid FLAC config: {:?}", e)))?;
-
-    let source = MemSource::from_samples(
-        &int_samples,
-        1,
-        bits_per_sample,
-        config.sample_rate as usize,
-    );
-
-    let stream = encode_with_fixed_block_size(&encoder_config, source, config.block_size as usize)
-        .map_err(|e| ExportError::Encoding(format!("FLAC encoding failed: {:?}", e)))?;
-
-    let mut sink = ByteSink::new();
-    stream
-        .write(&mut sink)
-        .map_err(|e| ExportError::Encoding(format!("Failed to write FLAC stream: {:?}", e)))?;
-
-    Ok(sink.into_inner())
+    let mut out = Vec::with_capacity(flac.len() + 4 + body.len());
+    out.extend_from_slice(&flac[..end]);
+    out[last_header] &= 0x7F;
+    out.push(0x80 | block_type);
+    out.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+    out.extend_from_slice(body);
+    out.extend_from_slice(&flac[end..]);
+    Ok(out)
 }
 
-fn interleave_to_i32(left: &[f32], right: &[f32], bit_depth: BitDepth) -> Vec<i32> {
-    let mut interleaved = Vec::with_capacity(left.len() * 2);
-    for i in 0..left.len() {
-        interleaved.push(float_to_i32(left[i], bit_depth));
-        interleaved.push(float_to_i32(right[i], bit_depth));
+/// Interleave in file order (`order[i]` is the source of file channel `i`).
+fn interleave_to_i32(channels: &[Vec<f32>], order: &[usize], bit_depth: BitDepth) -> Vec<i32> {
+    let frames = channels.first().map_or(0, Vec::len);
+    let mut interleaved = Vec::with_capacity(frames * order.len());
+    for i in 0..frames {
+        interleaved.extend(
+            order
+                .iter()
+                .map(|&ch| float_to_i32(channels[ch][i], bit_depth)),
+        );
     }
     interleaved
 }
 
 #[inline]
 fn float_to_i32(sample: f32, bit_depth: BitDepth) -> i32 {
-    let clamped = sample.clamp(-1.0, 1.0);
     match bit_depth {
-        BitDepth::Int16 => (clamped * 32767.0) as i32,
-        BitDepth::Int24 => (clamped * 8388607.0) as i32,
+        BitDepth::Int16 | BitDepth::Int24 => quantize(sample, bit_depth.bits()),
         BitDepth::Float32 => unreachable!(),
     }
 }
@@ -228,15 +215,109 @@ mod tests {
 
     #[test]
     fn test_interleave_to_i32() {
-        let left = vec![0.0, 1.0];
-        let right = vec![0.5, -0.5];
-        let interleaved = interleave_to_i32(&left, &right, BitDepth::Int16);
+        let channels = vec![vec![0.0, 1.0], vec![0.5, -0.5]];
+        let interleaved = interleave_to_i32(&channels, &[0, 1], BitDepth::Int16);
 
         assert_eq!(interleaved.len(), 4);
         assert_eq!(interleaved[0], 0);
-        assert_eq!(interleaved[1], 16383);
+        // Rounded, not truncated
+        assert_eq!(interleaved[1], 16384);
         assert_eq!(interleaved[2], 32767);
-        assert_eq!(interleaved[3], -16383);
+        assert_eq!(interleaved[3], -16384);
+    }
+
+    #[test]
+    fn test_vorbis_comment_block() {
+        let tone: Vec<f32> = (0..44100).map(|i| (i as f32 * 0.06).sin() * 0.5).collect();
+        let options = ExportOptions {
+            format: crate::AudioFormat::Flac,
+            metadata: crate::Metadata::new().title("Intro").artist("Tutti"),
+            ..Default::default()
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tagged.flac");
+        export_flac(path.to_str().unwrap(), &[&tone[..], &tone[..]], &options).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+
+        // STREAMINFO is no longer last; the comment block after it is
+        assert_eq!(bytes[4], 0);
+        let comment = 4 + 4 + 34;
+        assert_eq!(bytes[comment], 0x80 | BLOCK_TYPE_VORBIS_COMMENT);
+        let body = &bytes[comment + 4..];
+        let contains = |text: &[u8]| body.windows(text.len()).any(|w| w == text);
+        assert!(contains(b"TITLE=Intro"));
+        assert!(contains(b"ARTIST=Tutti"));
+        assert!(contains(b"REPLAYGAIN_TRACK_GAIN="));
+    }
+
+    /// Export `channels` copies of a tone and read the comment fields back.
+    fn exported_comments(layout: crate::SpeakerLayout, channels: usize) -> Vec<String> {
+        let tone: Vec<f32> = (0..4800).map(|i| (i as f32 * 0.05).sin() * 0.5).collect();
+        let options = ExportOptions {
+            format: crate::AudioFormat::Flac,
+            layout,
+            ..Default::default()
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mask.flac");
+        export_flac(path.to_str().unwrap(), &vec![&tone[..]; channels], &options).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+
+        let read_u32 = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        let comment = 4 + 4 + 34;
+        assert_eq!(bytes[comment], 0x80 | BLOCK_TYPE_VORBIS_COMMENT);
+        let mut at = comment + 4;
+        at += 4 + read_u32(at) as usize;
+        let count = read_u32(at);
+        at += 4;
+        (0..count)
+            .map(|_| {
+                let len = read_u32(at) as usize;
+                let field = String::from_utf8(bytes[at + 4..at + 4 + len].to_vec()).unwrap();
+                at += 4 + len;
+                field
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_channel_mask_tag() {
+        let mask_of = |fields: Vec<String>| {
+            fields.into_iter().find_map(|f| {
+                f.strip_prefix("WAVEFORMATEXTENSIBLE_CHANNEL_MASK=")
+                    .map(str::to_owned)
+            })
+        };
+
+        // FLAC's own assignment needs no tag
+        assert_eq!(
+            mask_of(exported_comments(crate::SpeakerLayout::Stereo, 2)),
+            None
+        );
+        assert_eq!(
+            mask_of(exported_comments(crate::SpeakerLayout::Surround7_1, 8)),
+            None
+        );
+        // 5.1 with side surrounds differs from FLAC's back-surround default
+        assert_eq!(
+            mask_of(exported_comments(crate::SpeakerLayout::Surround5_1, 6)).as_deref(),
+            Some("0x060F")
+        );
+        // Four channels would otherwise read back as quad
+        assert_eq!(
+            mask_of(exported_comments(
+                crate::SpeakerLayout::Ambisonic { order: 1 },
+                4
+            ))
+            .as_deref(),
+            Some("0x0000")
+        );
+        assert_eq!(
+            mask_of(exported_comments(crate::SpeakerLayout::Discrete(4), 4)).as_deref(),
+            Some("0x0000")
+        );
     }
 
     #[test]
@@ -248,7 +329,35 @@ mod tests {
 
         let dir = tempfile::tempdir().unwrap();
         let path = dir.path().join("test.flac");
-        let result = export_flac(path.to_str().unwrap(), &left, &right, &options);
+        let result = export_flac(path.to_str().unwrap(), &[&left[..], &right[..]], &options);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_export_flac_surround() {
+        let tone: Vec<f32> = (0..4800).map(|i| (i as f32 * 0.05).sin() * 0.5).collect();
+        let channels: Vec<&[f32]> = vec![&tone[..]; 6];
+        let options = ExportOptions {
+            format: crate::AudioFormat::Flac,
+            layout: crate::SpeakerLayout::Surround5_1,
+            ..Default::default()
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("surround.flac");
+        export_flac(path.to_str().unwrap(), &channels, &options).unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(&bytes[0..4], b"fLaC");
+        // STREAMINFO: channels - 1 sits in bits 1-3 of byte 20
+        assert_eq!(((bytes[20] >> 1) & 0x07) + 1, 6);
+
+        let options = ExportOptions {
+            layout: crate::SpeakerLayout::Ambisonic { order: 2 },
+            ..options
+        };
+        let channels: Vec<&[f32]> = vec![&tone[..]; 9];
+        let result = export_flac(path.to_str().unwrap(), &channels, &options);
+        assert!(matches!(result, Err(ExportError::UnsupportedFormat(_))));
+    }
 }
@@ -1,11 +1,49 @@
 //! Audio format encoders
 //!
 //! Each encoder is feature-gated:
-//! - `wav`: WAV via hound (pure Rust)
+//! - `wav`: RIFF/WAVE, written directly
 //! - `flac`: FLAC via flacenc (pure Rust)
+//! - `vorbis`: Ogg Vorbis via libvorbis
+//! - `opus`: Ogg Opus via libopus
+//! - `mp3`: MP3 via LAME
 
 #[cfg(feature = "wav")]
 pub mod wav;
 
 #[cfg(feature = "flac")]
 pub mod flac;
+
+#[cfg(feature = "vorbis")]
+pub mod vorbis;
+
+#[cfg(feature = "opus")]
+pub mod opus;
+
+#[cfg(feature = "mp3")]
+pub mod mp3;
+
+#[cfg(any(feature = "vorbis", feature = "opus", feature = "mp3"))]
+use crate::{
+    dsp::{process_audio, Processed},
+    DitherType, ExportOptions, Result,
+};
+
+/// Frames handed to a lossy encoder per call; also the progress granularity.
+#[cfg(any(feature = "vorbis", feature = "mp3"))]
+const ENCODE_BLOCK_FRAMES: usize = 4096;
+
+/// Resample and normalize for a lossy encoder running at `sample_rate`.
+/// No dither: the codec requantizes anyway, and the noise only costs bits.
+#[cfg(any(feature = "vorbis", feature = "opus", feature = "mp3"))]
+fn process_lossy(
+    channels: &[&[f32]],
+    options: &ExportOptions,
+    sample_rate: u32,
+) -> Result<Processed> {
+    let options = ExportOptions {
+        sample_rate: Some(sample_rate),
+        dither: DitherType::None,
+        ..options.clone()
+    };
+    process_audio(channels, &options, sample_rate)
+}
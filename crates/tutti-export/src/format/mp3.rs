@@ -0,0 +1,245 @@
+//! MP3 encoder (LAME via `mp3lame-encoder`), constant bitrate, with an
+//! ID3v2.4 tag in front.
+
+use super::{process_lossy, ENCODE_BLOCK_FRAMES};
+use crate::dsp::{check_layout, Processed};
+use crate::error::{ExportError, Result};
+use crate::export_builder::{ExportPhase, ExportProgress};
+use crate::metadata::{id3_frame, id3_tag, replaygain_fields, ID3_UTF8, SOFTWARE};
+use crate::options::ExportOptions;
+use crate::Metadata;
+use mp3lame_encoder::{Bitrate, Builder, DualPcm, FlushNoGap, MonoPcm, Quality};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// Sample rates MPEG-1, 2 and 2.5 Layer III can carry.
+const MP3_SAMPLE_RATES: [u32; 9] = [8000, 11025, 12000, 16000, 22050, 24000, 32000, 44100, 48000];
+
+const MAX_MP3_CHANNELS: usize = 2;
+
+pub(crate) fn export_mp3(path: &str, channels: &[&[f32]], options: &ExportOptions) -> Result<()> {
+    export_mp3_with_progress(path, channels, options, |_| {})
+}
+
+pub(crate) fn export_mp3_with_progress(
+    path: &str,
+    channels: &[&[f32]],
+    options: &ExportOptions,
+    on_progress: impl Fn(ExportProgress),
+) -> Result<()> {
+    check_layout(channels, options)?;
+    if channels.len() > MAX_MP3_CHANNELS {
+        return Err(ExportError::UnsupportedFormat(format!(
+            "MP3 supports up to {} channels, got {}",
+            MAX_MP3_CHANNELS,
+            channels.len()
+        )));
+    }
+    let bitrate = lame_bitrate(options.mp3.bitrate_kbps).ok_or_else(|| {
+        ExportError::InvalidOptions(format!(
+            "Unsupported MP3 bitrate: {} kbit/s",
+            options.mp3.bitrate_kbps
+        ))
+    })?;
+    let sample_rate = mp3_sample_rate(options.output_sample_rate())?;
+
+    on_progress(ExportProgress {
+        phase: ExportPhase::Processing,
+        progress: 0.0,
+        loudness: None,
+    });
+
+    let Processed {
+        channels: processed,
+        layout: _,
+        loudness,
+        report,
+    } = process_lossy(channels, options, sample_rate)?;
+
+    on_progress(ExportProgress {
+        phase: ExportPhase::Processing,
+        progress: 1.0,
+        loudness: Some(report),
+    });
+
+    on_progress(ExportProgress {
+        phase: ExportPhase::Encoding,
+        progress: 0.0,
+        loudness: None,
+    });
+
+    let mut builder =
+        Builder::new().ok_or_else(|| ExportError::Encoding("Failed to create LAME".into()))?;
+    builder
+        .set_num_channels(processed.len() as u8)
+        .map_err(build_error)?;
+    builder.set_sample_rate(sample_rate).map_err(build_error)?;
+    builder.set_brate(bitrate).map_err(build_error)?;
+    builder
+        .set_quality(Quality::NearBest)
+        .map_err(build_error)?;
+    let mut encoder = builder.build().map_err(build_error)?;
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(&id3v2_tag(&options.metadata, &replaygain_fields(&loudness)))?;
+    let mut mp3 = Vec::new();
+    let frames = processed[0].len();
+    let mut start = 0;
+    while start < frames {
+        let end = (start + ENCODE_BLOCK_FRAMES).min(frames);
+        mp3.clear();
+        match processed.as_slice() {
+            [mono] => encoder.encode_to_vec(MonoPcm(&mono[start..end]), &mut mp3),
+            [left, right] => encoder.encode_to_vec(
+                DualPcm {
+                    left: &left[start..end],
+                    right: &right[start..end],
+                },
+                &mut mp3,
+            ),
+            _ => unreachable!("channel count checked above"),
+        }
+        .map_err(encode_error)?;
+        writer.write_all(&mp3)?;
+
+        start = end;
+        on_progress(ExportProgress {
+            phase: ExportPhase::Encoding,
+            progress: start as f32 / frames as f32,
+            loudness: None,
+        });
+    }
+
+    mp3.clear();
+    encoder
+        .flush_to_vec::<FlushNoGap>(&mut mp3)
+        .map_err(encode_error)?;
+    writer.write_all(&mp3)?;
+    writer.flush()?;
+
+    on_progress(ExportProgress {
+        phase: ExportPhase::Encoding,
+        progress: 1.0,
+        loudness: None,
+    });
+
+    Ok(())
+}
+
+/// ID3v2.4 tag with the text tags, plus the loudness tags as `TXXX` frames.
+fn id3v2_tag(metadata: &Metadata, loudness_fields: &[(&str, String)]) -> Vec<u8> {
+    let text = |value: &str| [&[ID3_UTF8][..], value.as_bytes()].concat();
+    let mut frames = Vec::new();
+    for (id, value) in [
+        (b"TIT2", &metadata.title),
+        (b"TPE1", &metadata.artist),
+        (b"TALB", &metadata.album),
+        (b"TSRC", &metadata.isrc),
+    ] {
+        if let Some(value) = value {
+            frames.extend(id3_frame(id, &text(value.as_str())));
+        }
+    }
+    if let Some(comment) = &metadata.comment {
+        // Language, then an empty description
+        let body = [&[ID3_UTF8][..], &b"eng\0"[..], comment.as_bytes()].concat();
+        frames.extend(id3_frame(b"COMM", &body));
+    }
+    frames.extend(id3_frame(b"TSSE", &text(SOFTWARE)));
+    for (key, value) in loudness_fields {
+        let body = [&[ID3_UTF8][..], key.as_bytes(), &[0][..], value.as_bytes()].concat();
+        frames.extend(id3_frame(b"TXXX", &body));
+    }
+    id3_tag(&frames)
+}
+
+/// The output rate, or 48 kHz for anything higher. Lower rates MP3 can't
+/// carry are an error rather than a silent conversion.
+fn mp3_sample_rate(rate: u32) -> Result<u32> {
+    if rate > 48000 {
+        Ok(48000)
+    } else if MP3_SAMPLE_RATES.contains(&rate) {
+        Ok(rate)
+    } else {
+        Err(ExportError::UnsupportedFormat(format!(
+            "MP3 does not support {} Hz",
+            rate
+        )))
+    }
+}
+
+fn lame_bitrate(kbps: u32) -> Option<Bitrate> {
+    Some(match kbps {
+        8 => Bitrate::Kbps8,
+        16 => Bitrate::Kbps16,
+        24 => Bitrate::Kbps24,
+        32 => Bitrate::Kbps32,
+        40 => Bitrate::Kbps40,
+        48 => Bitrate::Kbps48,
+        64 => Bitrate::Kbps64,
+        80 => Bitrate::Kbps80,
+        96 => Bitrate::Kbps96,
+        112 => Bitrate::Kbps112,
+        128 => Bitrate::Kbps128,
+        160 => Bitrate::Kbps160,
+        192 => Bitrate::Kbps192,
+        224 => Bitrate::Kbps224,
+        256 => Bitrate::Kbps256,
+        320 => Bitrate::Kbps320,
+        _ => return None,
+    })
+}
+
+fn build_error(e: mp3lame_encoder::BuildError) -> ExportError {
+    ExportError::Encoding(format!("Invalid MP3 config: {:?}", e))
+}
+
+fn encode_error(e: mp3lame_encoder::EncodeError) -> ExportError {
+    ExportError::Encoding(format!("MP3 encoding failed: {:?}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mp3_sample_rate() {
+        assert_eq!(mp3_sample_rate(44100).unwrap(), 44100);
+        assert_eq!(mp3_sample_rate(96000).unwrap(), 48000);
+        assert!(mp3_sample_rate(40000).is_err());
+    }
+
+    #[test]
+    fn test_export_mp3() {
+        let tone: Vec<f32> = (0..44100).map(|i| (i as f32 * 0.06).sin() * 0.5).collect();
+        let options = ExportOptions {
+            format: crate::AudioFormat::Mp3,
+            metadata: Metadata::new().title("Intro"),
+            ..Default::default()
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tone.mp3");
+        export_mp3(path.to_str().unwrap(), &[&tone[..], &tone[..]], &options).unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(&bytes[0..4], b"ID3\x04");
+        assert_eq!(&bytes[10..14], b"TIT2");
+        // Audio starts on an MPEG frame sync right after the tag
+        let size = bytes[6..10]
+            .iter()
+            .fold(0usize, |size, &b| (size << 7) | b as usize);
+        let audio = &bytes[10 + size..];
+        assert_eq!(audio[0], 0xFF);
+        assert_eq!(audio[1] & 0xE0, 0xE0);
+        // 192 kbit/s for a second is 24 kB
+        assert!(audio.len() > 20_000 && audio.len() < 30_000);
+
+        let options = ExportOptions {
+            mp3: crate::Mp3Options { bitrate_kbps: 100 },
+            ..options
+        };
+        let result = export_mp3(path.to_str().unwrap(), &[&tone[..], &tone[..]], &options);
+        assert!(matches!(result, Err(ExportError::InvalidOptions(_))));
+    }
+}
@@ -0,0 +1,231 @@
+//! Ogg Opus encoder (libopus via `opus`, framed per RFC 7845).
+//!
+//! Opus always runs at 48 kHz, so other rates are resampled first. The
+//! original rate is kept in the header for decoders that want it back.
+
+use super::process_lossy;
+use crate::dsp::{check_layout, Processed};
+use crate::error::{ExportError, Result};
+use crate::export_builder::{ExportPhase, ExportProgress};
+use crate::metadata::{r128_fields, vorbis_comment};
+use crate::options::ExportOptions;
+use ogg::{PacketWriteEndInfo, PacketWriter};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+const OPUS_SAMPLE_RATE: u32 = 48_000;
+
+/// 20 ms, the usual frame size for music.
+const FRAME_SIZE: usize = 960;
+
+/// Report encoding progress once per second of audio.
+const PROGRESS_PACKETS: usize = 50;
+
+/// Largest packet libopus produces (RFC 6716, section 3.4).
+const MAX_PACKET_SIZE: usize = 1275 * 3 + 7;
+
+/// Mapping family 0 covers mono and stereo only.
+const MAX_OPUS_CHANNELS: usize = 2;
+
+const STREAM_SERIAL: u32 = 0x5455_5454; // "TUTT"
+
+pub(crate) fn export_opus(path: &str, channels: &[&[f32]], options: &ExportOptions) -> Result<()> {
+    export_opus_with_progress(path, channels, options, |_| {})
+}
+
+pub(crate) fn export_opus_with_progress(
+    path: &str,
+    channels: &[&[f32]],
+    options: &ExportOptions,
+    on_progress: impl Fn(ExportProgress),
+) -> Result<()> {
+    check_layout(channels, options)?;
+    let bitrate = options.opus.bitrate_kbps;
+    if !(6..=510).contains(&bitrate) {
+        return Err(ExportError::InvalidOptions(format!(
+            "Opus bitrate must be between 6 and 510 kbit/s, got {}",
+            bitrate
+        )));
+    }
+    if channels.len() > MAX_OPUS_CHANNELS {
+        return Err(ExportError::UnsupportedFormat(format!(
+            "Opus export supports up to {} channels, got {}",
+            MAX_OPUS_CHANNELS,
+            channels.len()
+        )));
+    }
+
+    on_progress(ExportProgress {
+        phase: ExportPhase::Processing,
+        progress: 0.0,
+        loudness: None,
+    });
+
+    let Processed {
+        channels: processed,
+        layout: _,
+        loudness,
+        report,
+    } = process_lossy(channels, options, OPUS_SAMPLE_RATE)?;
+    let mut fields = options.metadata.vorbis_fields();
+    fields.extend(r128_fields(&loudness));
+    let opus_channels = if processed.len() == 1 {
+        opus::Channels::Mono
+    } else {
+        opus::Channels::Stereo
+    };
+
+    on_progress(ExportProgress {
+        phase: ExportPhase::Processing,
+        progress: 1.0,
+        loudness: Some(report),
+    });
+
+    on_progress(ExportProgress {
+        phase: ExportPhase::Encoding,
+        progress: 0.0,
+        loudness: None,
+    });
+
+    let mut encoder = opus::Encoder::new(OPUS_SAMPLE_RATE, opus_channels, opus::Application::Audio)
+        .map_err(encoding)?;
+    encoder
+        .set_bitrate(opus::Bitrate::Bits(bitrate as i32 * 1000))
+        .map_err(encoding)?;
+    let pre_skip = encoder.get_lookahead().map_err(encoding)? as usize;
+
+    let mut writer = PacketWriter::new(BufWriter::new(File::create(path)?));
+    let head = opus_head(
+        processed.len() as u8,
+        pre_skip as u16,
+        options.output_sample_rate(),
+    );
+    writer.write_packet(head, STREAM_SERIAL, PacketWriteEndInfo::EndPage, 0)?;
+    writer.write_packet(
+        opus_tags(&fields),
+        STREAM_SERIAL,
+        PacketWriteEndInfo::EndPage,
+        0,
+    )?;
+
+    // Keep encoding past the end until the encoder's look-ahead has been
+    // flushed; the final granule position trims the padding off again.
+    let frames = processed[0].len();
+    let end = frames + pre_skip;
+    let packets = end.div_ceil(FRAME_SIZE).max(1);
+    let mut pcm = vec![0.0f32; FRAME_SIZE * processed.len()];
+    let mut packet = vec![0u8; MAX_PACKET_SIZE];
+
+    for n in 0..packets {
+        let start = n * FRAME_SIZE;
+        for (i, frame) in pcm.chunks_exact_mut(processed.len()).enumerate() {
+            for (sample, channel) in frame.iter_mut().zip(&processed) {
+                *sample = channel.get(start + i).copied().unwrap_or(0.0);
+            }
+        }
+
+        let len = encoder.encode_float(&pcm, &mut packet).map_err(encoding)?;
+        let last = n + 1 == packets;
+        let granule = ((n + 1) * FRAME_SIZE).min(end) as u64;
+        let end_info = if last {
+            PacketWriteEndInfo::EndStream
+        } else {
+            PacketWriteEndInfo::NormalPacket
+        };
+        writer.write_packet(packet[..len].to_vec(), STREAM_SERIAL, end_info, granule)?;
+
+        if (n + 1) % PROGRESS_PACKETS == 0 {
+            on_progress(ExportProgress {
+                phase: ExportPhase::Encoding,
+                progress: (n + 1) as f32 / packets as f32,
+                loudness: None,
+            });
+        }
+    }
+    writer.into_inner().flush()?;
+
+    on_progress(ExportProgress {
+        phase: ExportPhase::Encoding,
+        progress: 1.0,
+        loudness: None,
+    });
+
+    Ok(())
+}
+
+/// Identification header (RFC 7845, section 5.1).
+fn opus_head(channels: u8, pre_skip: u16, input_sample_rate: u32) -> Vec<u8> {
+    let mut head = Vec::with_capacity(19);
+    head.extend_from_slice(b"OpusHead");
+    head.push(1); // version
+    head.push(channels);
+    head.extend_from_slice(&pre_skip.to_le_bytes());
+    head.extend_from_slice(&input_sample_rate.to_le_bytes());
+    head.extend_from_slice(&0i16.to_le_bytes()); // output gain
+    head.push(0); // channel mapping family
+    head
+}
+
+/// Comment header (RFC 7845, section 5.2).
+fn opus_tags(fields: &[(&str, String)]) -> Vec<u8> {
+    let mut tags = b"OpusTags".to_vec();
+    tags.extend(vorbis_comment(fields));
+    tags
+}
+
+fn encoding(e: opus::Error) -> ExportError {
+    ExportError::Encoding(format!("Opus encoding failed: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Granule position of the last Ogg page.
+    fn final_granule(bytes: &[u8]) -> u64 {
+        let page = bytes.windows(4).rposition(|w| w == b"OggS").unwrap();
+        u64::from_le_bytes(bytes[page + 6..page + 14].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_export_opus() {
+        let tone: Vec<f32> = (0..48000).map(|i| (i as f32 * 0.06).sin() * 0.5).collect();
+        let options = ExportOptions {
+            format: crate::AudioFormat::Opus,
+            source_sample_rate: 48000,
+            metadata: crate::Metadata::new().artist("Tutti"),
+            ..Default::default()
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tone.opus");
+        export_opus(path.to_str().unwrap(), &[&tone[..], &tone[..]], &options).unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(&bytes[0..4], b"OggS");
+        assert_eq!(&bytes[28..36], b"OpusHead");
+        assert_eq!(bytes[37], 2);
+        let pre_skip = u16::from_le_bytes([bytes[38], bytes[39]]) as u64;
+        // The last granule marks exactly where the input ended
+        assert_eq!(final_granule(&bytes), pre_skip + 48000);
+        // 128 kbit/s for a second, plus headers and framing
+        assert!(bytes.len() < 20_000);
+        let contains = |text: &[u8]| bytes.windows(text.len()).any(|w| w == text);
+        assert!(contains(b"ARTIST=Tutti"));
+        assert!(contains(b"R128_TRACK_GAIN="));
+    }
+
+    #[test]
+    fn test_export_opus_rejects_surround() {
+        let tone = vec![0.0f32; 4800];
+        let channels: Vec<&[f32]> = vec![&tone[..]; 6];
+        let options = ExportOptions {
+            layout: crate::SpeakerLayout::Surround5_1,
+            ..Default::default()
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("surround.opus");
+        let result = export_opus(path.to_str().unwrap(), &channels, &options);
+        assert!(matches!(result, Err(ExportError::UnsupportedFormat(_))));
+    }
+}
@@ -0,0 +1,174 @@
+//! Ogg Vorbis encoder (libvorbis via `vorbis_rs`).
+
+use super::{process_lossy, ENCODE_BLOCK_FRAMES};
+use crate::dsp::{check_layout, Processed};
+use crate::error::{ExportError, Result};
+use crate::export_builder::{ExportPhase, ExportProgress};
+use crate::metadata::replaygain_fields;
+use crate::options::ExportOptions;
+use std::fs::File;
+use std::io::BufWriter;
+use std::num::{NonZeroU32, NonZeroU8};
+use vorbis_rs::{VorbisBitrateManagementStrategy, VorbisEncoderBuilder};
+
+pub(crate) fn export_vorbis(
+    path: &str,
+    channels: &[&[f32]],
+    options: &ExportOptions,
+) -> Result<()> {
+    export_vorbis_with_progress(path, channels, options, |_| {})
+}
+
+pub(crate) fn export_vorbis_with_progress(
+    path: &str,
+    channels: &[&[f32]],
+    options: &ExportOptions,
+    on_progress: impl Fn(ExportProgress),
+) -> Result<()> {
+    check_layout(channels, options)?;
+    let quality = options.vorbis.quality;
+    if !(-0.1..=1.0).contains(&quality) {
+        return Err(ExportError::InvalidOptions(format!(
+            "Vorbis quality must be between -0.1 and 1.0, got {}",
+            quality
+        )));
+    }
+
+    on_progress(ExportProgress {
+        phase: ExportPhase::Processing,
+        progress: 0.0,
+        loudness: None,
+    });
+
+    let sample_rate = options.output_sample_rate();
+    let Processed {
+        channels: processed,
+        layout,
+        loudness,
+        report,
+    } = process_lossy(channels, options, sample_rate)?;
+    let planar: Vec<&[f32]> = layout
+        .vorbis_order()
+        .into_iter()
+        .map(|ch| processed[ch].as_slice())
+        .collect();
+
+    on_progress(ExportProgress {
+        phase: ExportPhase::Processing,
+        progress: 1.0,
+        loudness: Some(report),
+    });
+
+    on_progress(ExportProgress {
+        phase: ExportPhase::Encoding,
+        progress: 0.0,
+        loudness: None,
+    });
+
+    let rate = NonZeroU32::new(sample_rate)
+        .ok_or_else(|| ExportError::InvalidOptions("Sample rate must not be zero".into()))?;
+    let channel_count = u8::try_from(planar.len())
+        .ok()
+        .and_then(NonZeroU8::new)
+        .ok_or_else(|| {
+            ExportError::UnsupportedFormat(format!(
+                "Vorbis supports 1 to 255 channels, got {}",
+                planar.len()
+            ))
+        })?;
+
+    let file = BufWriter::new(File::create(path)?);
+    let mut builder = VorbisEncoderBuilder::new(rate, channel_count, file).map_err(encoding)?;
+    builder.bitrate_management_strategy(VorbisBitrateManagementStrategy::QualityVbr {
+        target_quality: quality,
+    });
+    for (key, value) in options
+        .metadata
+        .vorbis_fields()
+        .into_iter()
+        .chain(replaygain_fields(&loudness))
+    {
+        builder.comment_tag(key, value).map_err(encoding)?;
+    }
+    let mut encoder = builder.build().map_err(encoding)?;
+
+    let frames = planar.first().map_or(0, |c| c.len());
+    let mut start = 0;
+    while start < frames {
+        let end = (start + ENCODE_BLOCK_FRAMES).min(frames);
+        let block: Vec<&[f32]> = planar.iter().map(|c| &c[start..end]).collect();
+        encoder.encode_audio_block(&block).map_err(encoding)?;
+        start = end;
+        on_progress(ExportProgress {
+            phase: ExportPhase::Encoding,
+            progress: start as f32 / frames as f32,
+            loudness: None,
+        });
+    }
+    encoder.finish().map_err(encoding)?;
+
+    on_progress(ExportProgress {
+        phase: ExportPhase::Encoding,
+        progress: 1.0,
+        loudness: None,
+    });
+
+    Ok(())
+}
+
+fn encoding(e: vorbis_rs::VorbisError) -> ExportError {
+    ExportError::Encoding(format!("Vorbis encoding failed: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SpeakerLayout;
+
+    #[test]
+    fn test_export_vorbis() {
+        let tone: Vec<f32> = (0..44100).map(|i| (i as f32 * 0.06).sin() * 0.5).collect();
+        let options = ExportOptions {
+            format: crate::AudioFormat::Vorbis,
+            metadata: crate::Metadata::new().title("Intro"),
+            ..Default::default()
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tone.ogg");
+        export_vorbis(path.to_str().unwrap(), &[&tone[..], &tone[..]], &options).unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(&bytes[0..4], b"OggS");
+        // Identification header: packet type 1, "vorbis", version, channels
+        assert_eq!(&bytes[28..35], b"\x01vorbis");
+        assert_eq!(bytes[39], 2);
+        // A second of audio, well under the 176 kB it takes as 16-bit PCM
+        assert!(bytes.len() < 44100);
+        let contains = |text: &[u8]| bytes.windows(text.len()).any(|w| w == text);
+        assert!(contains(b"TITLE=Intro"));
+        assert!(contains(b"REPLAYGAIN_TRACK_GAIN="));
+    }
+
+    #[test]
+    fn test_export_vorbis_surround_channels() {
+        let tone: Vec<f32> = (0..4800).map(|i| (i as f32 * 0.05).sin() * 0.5).collect();
+        let channels: Vec<&[f32]> = vec![&tone[..]; 6];
+        let options = ExportOptions {
+            layout: SpeakerLayout::Surround5_1,
+            ..Default::default()
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("surround.ogg");
+        export_vorbis(path.to_str().unwrap(), &channels, &options).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap()[39], 6);
+
+        let options = ExportOptions {
+            vorbis: crate::VorbisOptions { quality: 2.0 },
+            ..options
+        };
+        let result = export_vorbis(path.to_str().unwrap(), &channels, &options);
+        assert!(matches!(result, Err(ExportError::InvalidOptions(_))));
+    }
+}
@@ -1,242 +1,457 @@
-use crate::dsp::{process_audio, stereo_to_mono};
+//! RIFF/WAVE encoder.
+//!
+//! Written by hand rather than through `hound` so multichannel files get the
+//! layout's real WAVE_FORMAT_EXTENSIBLE channel mask, and so the Broadcast
+//! Wave `bext`, `iXML` and LIST/INFO chunks can go in ahead of the audio.
+
+use crate::dsp::{check_layout, process_audio, quantize, Processed};
 use crate::error::{ExportError, Result};
 use crate::export_builder::{ExportPhase, ExportProgress};
+use crate::metadata::{id3_frame, id3_tag, is_measured, ID3_UTF8, SOFTWARE};
 use crate::options::{BitDepth, ExportOptions};
-use hound::{SampleFormat, WavSpec, WavWriter};
-use std::io::{Seek, Write};
+use crate::Metadata;
+use std::fs::File;
+use std::io::{BufWriter, Write};
 use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tutti_core::{LoudnessResult, SmpteFrameRate};
+
+const WAVE_FORMAT_PCM: u16 = 0x0001;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 0x0003;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// Tail of the KSDATAFORMAT_SUBTYPE_* GUIDs, after the 2-byte format tag.
+const SUBTYPE_GUID_TAIL: [u8; 14] = [
+    0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71,
+];
+
+/// Fixed part of a version 2 `bext` chunk, before the coding history.
+const BEXT_SIZE: usize = 602;
+
+/// bext loudness fields that weren't measured.
+const BEXT_LOUDNESS_UNSET: i16 = 0x7FFF;
 
 #[derive(Debug, Clone)]
 struct WavConfig {
     sample_rate: u32,
     bit_depth: BitDepth,
-    channels: u16,
+    channel_mask: u32,
+    /// Source channel for each file channel
+    order: Vec<usize>,
+    /// Complete chunks written between `fmt ` and `data`
+    metadata_chunks: Vec<u8>,
 }
 
-pub(crate) fn export_wav(
-    path: &str,
-    left: &[f32],
-    right: &[f32],
-    options: &ExportOptions,
-) -> Result<()> {
-    let config = WavConfig {
-        sample_rate: options.output_sample_rate(),
-        bit_depth: options.bit_depth,
-        channels: if options.mono { 1 } else { 2 },
-    };
-
-    let (left_proc, right_proc) = process_audio(left, right, options, config.sample_rate)?;
-
-    if options.mono {
-        encode_wav_mono_file(
-            &stereo_to_mono(&left_proc, &right_proc),
-            Path::new(path),
-            &config,
-        )
-    } else {
-        encode_wav_file(&left_proc, &right_proc, Path::new(path), &config)
-    }
+pub(crate) fn export_wav(path: &str, channels: &[&[f32]], options: &ExportOptions) -> Result<()> {
+    export_wav_with_progress(path, channels, options, |_| {})
 }
 
 pub(crate) fn export_wav_with_progress(
     path: &str,
-    left: &[f32],
-    right: &[f32],
+    channels: &[&[f32]],
     options: &ExportOptions,
     on_progress: impl Fn(ExportProgress),
 ) -> Result<()> {
+    check_layout(channels, options)?;
+
     on_progress(ExportProgress {
         phase: ExportPhase::Processing,
         progress: 0.0,
+        loudness: None,
     });
 
+    let sample_rate = options.output_sample_rate();
+    let Processed {
+        channels: processed,
+        layout,
+        loudness,
+        report,
+    } = process_audio(channels, options, sample_rate)?;
+    let time_reference = (options.start_seconds.max(0.0) * sample_rate as f64).round() as u64;
+    let mut metadata_chunks = bext_chunk(
+        &options.metadata,
+        time_reference,
+        &loudness,
+        &coding_history(sample_rate, options.bit_depth),
+    );
+    metadata_chunks.extend(ixml_chunk(
+        &options.metadata,
+        time_reference,
+        sample_rate,
+        options.bit_depth,
+    ));
+    metadata_chunks.extend(info_chunk(&options.metadata));
+    metadata_chunks.extend(id3_chunk(&options.metadata));
     let config = WavConfig {
-        sample_rate: options.output_sample_rate(),
+        sample_rate,
         bit_depth: options.bit_depth,
-        channels: if options.mono { 1 } else { 2 },
+        channel_mask: layout.channel_mask(),
+        order: layout.file_order(),
+        metadata_chunks,
     };
 
-    let (left_proc, right_proc) = process_audio(left, right, options, config.sample_rate)?;
-
     on_progress(ExportProgress {
         phase: ExportPhase::Processing,
         progress: 1.0,
+        loudness: Some(report),
     });
 
     on_progress(ExportProgress {
         phase: ExportPhase::Encoding,
         progress: 0.0,
+        loudness: None,
     });
 
-    let result = if options.mono {
-        encode_wav_mono_file(
-            &stereo_to_mono(&left_proc, &right_proc),
-            Path::new(path),
-            &config,
-        )
-    } else {
-        encode_wav_file(&left_proc, &right_proc, Path::new(path), &config)
-    };
+    let result = encode_wav_file(&processed, Path::new(path), &config);
 
     on_progress(ExportProgress {
         phase: ExportPhase::Encoding,
         progress: 1.0,
+        loudness: None,
     });
 
     result
 }
 
-fn encode_wav_file(left: &[f32], right: &[f32], path: &Path, config: &WavConfig) -> Result<()> {
-    if left.len() != right.len() {
+fn encode_wav_file(channels: &[Vec<f32>], path: &Path, config: &WavConfig) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    let frames = channels.first().map_or(0, Vec::len);
+    write_header(&mut writer, config, frames)?;
+    write_samples(&mut writer, channels, config)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// RIFF header, `fmt ` chunk, metadata chunks and `data` chunk header. Uses
+/// WAVE_FORMAT_EXTENSIBLE above two channels or 16 bits, like `hound`.
+fn write_header<W: Write>(writer: &mut W, config: &WavConfig, frames: usize) -> Result<()> {
+    let channels = config.order.len() as u16;
+    let bits = config.bit_depth.bits();
+    let block_align = channels as u32 * (bits as u32 / 8);
+    let data_size = frames as u64 * block_align as u64;
+    let pad = data_size % 2;
+    let float = config.bit_depth == BitDepth::Float32;
+    // Float is always 32-bit, so it always takes the extensible form
+    let extensible = channels > 2 || bits > 16;
+    let fmt_size: u32 = if extensible { 40 } else { 16 };
+    let riff_size =
+        4 + (8 + fmt_size as u64) + config.metadata_chunks.len() as u64 + (8 + data_size + pad);
+    if riff_size > u32::MAX as u64 {
         return Err(ExportError::InvalidData(
-            "Left and right channels have different lengths".into(),
+            "Audio too long for a RIFF WAV file (4 GB limit)".into(),
         ));
     }
 
-    let spec = create_wav_spec(config);
-    let mut writer =
-        WavWriter::create(path, spec).map_err(|e| ExportError::Io(std::io::Error::other(e)))?;
+    let format_tag = if float {
+        WAVE_FORMAT_IEEE_FLOAT
+    } else {
+        WAVE_FORMAT_PCM
+    };
 
-    write_samples(&mut writer, left, right, config)?;
+    let mut header = Vec::with_capacity(68 + config.metadata_chunks.len());
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&(riff_size as u32).to_le_bytes());
+    header.extend_from_slice(b"WAVE");
+
+    header.extend_from_slice(b"fmt ");
+    header.extend_from_slice(&fmt_size.to_le_bytes());
+    header.extend_from_slice(
+        &if extensible {
+            WAVE_FORMAT_EXTENSIBLE
+        } else {
+            format_tag
+        }
+        .to_le_bytes(),
+    );
+    header.extend_from_slice(&channels.to_le_bytes());
+    header.extend_from_slice(&config.sample_rate.to_le_bytes());
+    header.extend_from_slice(&(config.sample_rate * block_align).to_le_bytes());
+    header.extend_from_slice(&(block_align as u16).to_le_bytes());
+    header.extend_from_slice(&bits.to_le_bytes());
+    if extensible {
+        header.extend_from_slice(&22u16.to_le_bytes());
+        header.extend_from_slice(&bits.to_le_bytes()); // valid bits
+        header.extend_from_slice(&config.channel_mask.to_le_bytes());
+        header.extend_from_slice(&format_tag.to_le_bytes());
+        header.extend_from_slice(&SUBTYPE_GUID_TAIL);
+    }
+    header.extend_from_slice(&config.metadata_chunks);
 
-    writer
-        .finalize()
-        .map_err(|e| ExportError::Io(std::io::Error::other(e)))?;
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&(data_size as u32).to_le_bytes());
 
+    writer.write_all(&header)?;
     Ok(())
 }
 
-fn encode_wav_mono_file(samples: &[f32], path: &Path, config: &WavConfig) -> Result<()> {
-    let spec = WavSpec {
-        channels: 1,
-        sample_rate: config.sample_rate,
-        bits_per_sample: config.bit_depth.bits(),
-        sample_format: match config.bit_depth {
-            BitDepth::Float32 => SampleFormat::Float,
-            _ => SampleFormat::Int,
-        },
-    };
+/// Interleaved samples in file order, plus the pad byte an odd-sized chunk needs.
+fn write_samples<W: Write>(
+    writer: &mut W,
+    channels: &[Vec<f32>],
+    config: &WavConfig,
+) -> Result<()> {
+    let frames = channels.first().map_or(0, Vec::len);
+    let mut written = 0usize;
+
+    for i in 0..frames {
+        for &ch in &config.order {
+            let sample = channels[ch][i];
+            match config.bit_depth {
+                BitDepth::Int16 => writer.write_all(&float_to_i16(sample).to_le_bytes())?,
+                BitDepth::Int24 => {
+                    writer.write_all(&float_to_i24(sample).to_le_bytes()[..3])?;
+                }
+                BitDepth::Float32 => writer.write_all(&sample.to_le_bytes())?,
+            }
+        }
+        written += config.order.len() * (config.bit_depth.bits() as usize / 8);
+    }
 
-    let mut writer =
-        WavWriter::create(path, spec).map_err(|e| ExportError::Io(std::io::Error::other(e)))?;
+    if written % 2 == 1 {
+        writer.write_all(&[0])?;
+    }
+    Ok(())
+}
 
-    write_mono_samples(&mut writer, samples, config)?;
+/// Broadcast Wave `bext` chunk, version 2 (EBU Tech 3285), with the
+/// loudness fields filled in from the measurement.
+fn bext_chunk(
+    metadata: &Metadata,
+    time_reference: u64,
+    loudness: &LoudnessResult,
+    coding_history: &str,
+) -> Vec<u8> {
+    let mut bext = Vec::with_capacity(BEXT_SIZE + coding_history.len());
+    let description = metadata.comment.as_ref().or(metadata.title.as_ref());
+    put_text(&mut bext, description.map_or("", String::as_str), 256);
+    put_text(&mut bext, SOFTWARE, 32); // originator
+    put_text(&mut bext, "", 32); // originator reference
+    let (date, time) = origination_date_time();
+    put_text(&mut bext, &date, 10);
+    put_text(&mut bext, &time, 8);
+    bext.extend_from_slice(&time_reference.to_le_bytes()); // low word, then high
+    bext.extend_from_slice(&2u16.to_le_bytes()); // version
+    bext.extend_from_slice(&[0; 64]); // UMID
+
+    let hundredths = |value: f64| (value * 100.0).round() as i16;
+    let (integrated, range, true_peak) = if is_measured(loudness) {
+        (
+            hundredths(loudness.integrated_lufs),
+            hundredths(loudness.loudness_range_lu),
+            hundredths(loudness.true_peak_dbtp),
+        )
+    } else {
+        (
+            BEXT_LOUDNESS_UNSET,
+            BEXT_LOUDNESS_UNSET,
+            BEXT_LOUDNESS_UNSET,
+        )
+    };
+    // Max momentary and short-term loudness aren't measured
+    for value in [
+        integrated,
+        range,
+        true_peak,
+        BEXT_LOUDNESS_UNSET,
+        BEXT_LOUDNESS_UNSET,
+    ] {
+        bext.extend_from_slice(&value.to_le_bytes());
+    }
+    bext.extend_from_slice(&[0; 180]); // reserved
+    bext.extend_from_slice(coding_history.as_bytes());
 
-    writer
-        .finalize()
-        .map_err(|e| ExportError::Io(std::io::Error::other(e)))?;
+    chunk(b"bext", &bext)
+}
 
-    Ok(())
+/// EBU R98 coding history line for the file as written.
+fn coding_history(sample_rate: u32, bit_depth: BitDepth) -> String {
+    format!(
+        "A=PCM,F={},W={},T={}\r\n",
+        sample_rate,
+        bit_depth.bits(),
+        SOFTWARE
+    )
 }
 
-fn create_wav_spec(config: &WavConfig) -> WavSpec {
-    let (bits_per_sample, sample_format) = match config.bit_depth {
-        BitDepth::Int16 => (16, SampleFormat::Int),
-        BitDepth::Int24 => (24, SampleFormat::Int),
-        BitDepth::Float32 => (32, SampleFormat::Float),
-    };
+/// `iXML` chunk: production fields plus the same time reference as `bext`,
+/// as samples since midnight split into 32-bit halves.
+fn ixml_chunk(
+    metadata: &Metadata,
+    time_reference: u64,
+    sample_rate: u32,
+    bit_depth: BitDepth,
+) -> Vec<u8> {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<BWFXML>\n<IXML_VERSION>2.10</IXML_VERSION>\n",
+    );
+    let tags = [
+        ("PROJECT", &metadata.project),
+        ("SCENE", &metadata.scene),
+        ("TAKE", &metadata.take),
+        ("NOTE", &metadata.comment),
+    ];
+    for (tag, value) in tags {
+        if let Some(value) = value {
+            xml.push_str(&format!("<{tag}>{}</{tag}>\n", xml_escape(value)));
+        }
+    }
 
-    WavSpec {
-        channels: config.channels,
-        sample_rate: config.sample_rate,
-        bits_per_sample,
-        sample_format,
+    xml.push_str("<SPEED>\n");
+    if let Some(rate) = metadata.timecode_rate {
+        let (rate, flag) = timecode_rate_fields(rate);
+        xml.push_str(&format!(
+            "<TIMECODE_RATE>{rate}</TIMECODE_RATE>\n<TIMECODE_FLAG>{flag}</TIMECODE_FLAG>\n"
+        ));
     }
+    xml.push_str(&format!(
+        "<FILE_SAMPLE_RATE>{sample_rate}</FILE_SAMPLE_RATE>\n\
+         <AUDIO_BIT_DEPTH>{}</AUDIO_BIT_DEPTH>\n\
+         <TIMESTAMP_SAMPLE_RATE>{sample_rate}</TIMESTAMP_SAMPLE_RATE>\n\
+         <TIMESTAMP_SAMPLES_SINCE_MIDNIGHT_HI>{}</TIMESTAMP_SAMPLES_SINCE_MIDNIGHT_HI>\n\
+         <TIMESTAMP_SAMPLES_SINCE_MIDNIGHT_LO>{}</TIMESTAMP_SAMPLES_SINCE_MIDNIGHT_LO>\n",
+        bit_depth.bits(),
+        time_reference >> 32,
+        time_reference & 0xFFFF_FFFF,
+    ));
+    xml.push_str("</SPEED>\n</BWFXML>\n");
+
+    chunk(b"iXML", xml.as_bytes())
 }
 
-fn write_samples<W: Write + Seek>(
-    writer: &mut WavWriter<W>,
-    left: &[f32],
-    right: &[f32],
-    config: &WavConfig,
-) -> Result<()> {
-    match config.bit_depth {
-        BitDepth::Int16 => {
-            for i in 0..left.len() {
-                let l = float_to_i16(left[i]);
-                let r = float_to_i16(right[i]);
-                writer
-                    .write_sample(l)
-                    .map_err(|e| ExportError::Io(std::io::Error::other(e)))?;
-                writer
-                    .write_sample(r)
-                    .map_err(|e| ExportError::Io(std::io::Error::other(e)))?;
-            }
-        }
-        BitDepth::Int24 => {
-            for i in 0..left.len() {
-                let l = float_to_i24(left[i]);
-                let r = float_to_i24(right[i]);
-                writer
-                    .write_sample(l)
-                    .map_err(|e| ExportError::Io(std::io::Error::other(e)))?;
-                writer
-                    .write_sample(r)
-                    .map_err(|e| ExportError::Io(std::io::Error::other(e)))?;
-            }
+/// iXML `TIMECODE_RATE` fraction and `TIMECODE_FLAG`.
+fn timecode_rate_fields(rate: SmpteFrameRate) -> (&'static str, &'static str) {
+    match rate {
+        SmpteFrameRate::Fps24 => ("24/1", "NDF"),
+        SmpteFrameRate::Fps25 => ("25/1", "NDF"),
+        SmpteFrameRate::Fps2997Df => ("30000/1001", "DF"),
+        SmpteFrameRate::Fps2997Ndf => ("30000/1001", "NDF"),
+        SmpteFrameRate::Fps30 => ("30/1", "NDF"),
+    }
+}
+
+fn xml_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            _ => out.push(c),
         }
-        BitDepth::Float32 => {
-            for i in 0..left.len() {
-                writer
-                    .write_sample(left[i])
-                    .map_err(|e| ExportError::Io(std::io::Error::other(e)))?;
-                writer
-                    .write_sample(right[i])
-                    .map_err(|e| ExportError::Io(std::io::Error::other(e)))?;
-            }
+    }
+    out
+}
+
+/// `LIST`/`INFO` chunk with the set tags; none when nothing is set. INFO's
+/// `ISRC` id means "source", so the recording code goes in [`id3_chunk`].
+fn info_chunk(metadata: &Metadata) -> Option<Vec<u8>> {
+    let tags = [
+        (b"INAM", &metadata.title),
+        (b"IART", &metadata.artist),
+        (b"IPRD", &metadata.album),
+        (b"ICMT", &metadata.comment),
+    ];
+    if tags.iter().all(|(_, value)| value.is_none()) {
+        return None;
+    }
+
+    let mut info = b"INFO".to_vec();
+    let software = Some(SOFTWARE.to_string());
+    for (id, value) in tags.into_iter().chain([(b"ISFT", &software)]) {
+        if let Some(value) = value {
+            let mut text = value.as_bytes().to_vec();
+            text.push(0);
+            info.extend(chunk(id, &text));
         }
     }
+    Some(chunk(b"LIST", &info))
+}
 
-    Ok(())
+/// `id3 ` chunk with the ISRC as an ID3v2 `TSRC` frame; none without one.
+fn id3_chunk(metadata: &Metadata) -> Option<Vec<u8>> {
+    let isrc = metadata.isrc.as_ref()?;
+    let frame = id3_frame(b"TSRC", &[&[ID3_UTF8][..], isrc.as_bytes()].concat());
+    Some(chunk(b"id3 ", &id3_tag(&frame)))
 }
 
-fn write_mono_samples<W: Write + Seek>(
-    writer: &mut WavWriter<W>,
-    samples: &[f32],
-    config: &WavConfig,
-) -> Result<()> {
-    match config.bit_depth {
-        BitDepth::Int16 => {
-            for &sample in samples {
-                let s = float_to_i16(sample);
-                writer
-                    .write_sample(s)
-                    .map_err(|e| ExportError::Io(std::io::Error::other(e)))?;
-            }
-        }
-        BitDepth::Int24 => {
-            for &sample in samples {
-                let s = float_to_i24(sample);
-                writer
-                    .write_sample(s)
-                    .map_err(|e| ExportError::Io(std::io::Error::other(e)))?;
-            }
-        }
-        BitDepth::Float32 => {
-            for &sample in samples {
-                writer
-                    .write_sample(sample)
-                    .map_err(|e| ExportError::Io(std::io::Error::other(e)))?;
-            }
-        }
+/// A RIFF chunk around `body`, padded to an even size.
+fn chunk(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(9 + body.len());
+    out.extend_from_slice(id);
+    out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    out.extend_from_slice(body);
+    if body.len() % 2 == 1 {
+        out.push(0);
     }
+    out
+}
 
-    Ok(())
+/// `text` as a fixed-size, NUL-padded ASCII field.
+fn put_text(out: &mut Vec<u8>, text: &str, size: usize) {
+    let start = out.len();
+    out.extend(text.bytes().filter(u8::is_ascii).take(size));
+    out.resize(start + size, 0);
+}
+
+/// The current UTC date and time as bext's `yyyy-mm-dd` and `hh:mm:ss`.
+fn origination_date_time() -> (String, String) {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let secs = secs % 86_400;
+    (
+        format!("{:04}-{:02}-{:02}", year, month, day),
+        format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60),
+    )
+}
+
+/// Gregorian date for a day count since 1970-01-01 (Howard Hinnant's
+/// `civil_from_days`).
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
 }
 
 #[inline]
 fn float_to_i16(sample: f32) -> i16 {
-    (sample.clamp(-1.0, 1.0) * 32767.0) as i16
+    quantize(sample, 16) as i16
 }
 
 #[inline]
 fn float_to_i24(sample: f32) -> i32 {
-    (sample.clamp(-1.0, 1.0) * 8388607.0) as i32
+    quantize(sample, 24)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::SpeakerLayout;
+
+    fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+    }
+
+    fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    /// Offset of the body of the top-level chunk `id`.
+    fn find_chunk(bytes: &[u8], id: &[u8; 4]) -> usize {
+        let mut offset = 12;
+        while &bytes[offset..offset + 4] != id {
+            offset += 8 + (u32_at(bytes, offset + 4) as usize).next_multiple_of(2);
+        }
+        offset + 8
+    }
 
     #[test]
     fn test_float_to_i16() {
@@ -258,14 +473,162 @@ mod tests {
     fn test_export_wav_file() {
         let left = vec![0.0, 0.5, -0.5];
         let right = vec![0.1, -0.1, 0.0];
-        let options = ExportOptions::default();
+        let options = ExportOptions {
+            bit_depth: BitDepth::Int16,
+            dither: crate::DitherType::None,
+            ..Default::default()
+        };
 
         let dir = tempfile::tempdir().unwrap();
         let path = dir.path().join("test.wav");
-        export_wav(path.to_str().unwrap(), &left, &right, &options).unwrap();
+        export_wav(path.to_str().unwrap(), &[&left[..], &right[..]], &options).unwrap();
 
         let bytes = std::fs::read(&path).unwrap();
         assert_eq!(&bytes[0..4], b"RIFF");
         assert_eq!(&bytes[8..12], b"WAVE");
+        assert_eq!(u16_at(&bytes, 20), WAVE_FORMAT_PCM);
+        assert_eq!(u32_at(&bytes, 4) as usize, bytes.len() - 8);
+        assert_eq!(&bytes[12..16], b"fmt ");
+        let data = find_chunk(&bytes, b"data");
+        assert_eq!(u32_at(&bytes, data - 4), 12);
+        // Second frame, left then right
+        assert_eq!(u16_at(&bytes, data + 4) as i16, float_to_i16(0.5));
+        assert_eq!(u16_at(&bytes, data + 6) as i16, float_to_i16(-0.1));
+    }
+
+    #[test]
+    fn test_surround_mask_and_order() {
+        // One frame of 7.1 where each channel holds its own index
+        let values: Vec<Vec<f32>> = (0..8).map(|ch| vec![ch as f32 / 10.0]).collect();
+        let channels: Vec<&[f32]> = values.iter().map(Vec::as_slice).collect();
+        let options = ExportOptions {
+            bit_depth: BitDepth::Float32,
+            layout: SpeakerLayout::Surround7_1,
+            dither: crate::DitherType::None,
+            ..Default::default()
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("surround.wav");
+        export_wav(path.to_str().unwrap(), &channels, &options).unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(u16_at(&bytes, 20), WAVE_FORMAT_EXTENSIBLE);
+        assert_eq!(u16_at(&bytes, 22), 8);
+        assert_eq!(u32_at(&bytes, 40), 0x63F);
+        assert_eq!(u16_at(&bytes, 44), WAVE_FORMAT_IEEE_FLOAT);
+
+        let samples: Vec<f32> = bytes[find_chunk(&bytes, b"data")..]
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        // Back surrounds (Tutti channels 6, 7) come before the sides in WAV
+        let expected: Vec<f32> = [0, 1, 2, 3, 6, 7, 4, 5]
+            .iter()
+            .map(|&ch| ch as f32 / 10.0)
+            .collect();
+        assert_eq!(samples, expected);
+    }
+
+    #[test]
+    fn test_broadcast_wave_and_info_chunks() {
+        let tone: Vec<f32> = (0..48000).map(|i| (i as f32 * 0.06).sin() * 0.5).collect();
+        let options = ExportOptions {
+            source_sample_rate: 48000,
+            metadata: Metadata::new().title("Intro").isrc("USRC17607839"),
+            start_seconds: 10.0,
+            ..Default::default()
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("broadcast.wav");
+        export_wav(path.to_str().unwrap(), &[&tone[..], &tone[..]], &options).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+
+        let bext = find_chunk(&bytes, b"bext");
+        assert_eq!(&bytes[bext..bext + 5], b"Intro");
+        assert_eq!(&bytes[bext + 256..bext + 261], b"Tutti");
+        let time_reference = u64::from_le_bytes(bytes[bext + 338..bext + 346].try_into().unwrap());
+        assert_eq!(time_reference, 480_000);
+        assert_eq!(u16_at(&bytes, bext + 346), 2);
+        // A -6 dBFS sine on both channels measures about -6 LUFS
+        let integrated = u16_at(&bytes, bext + 412) as i16;
+        assert!((-800..-500).contains(&integrated), "{}", integrated);
+        assert!(bytes[bext + BEXT_SIZE..].starts_with(b"A=PCM,F=48000,W=24"));
+
+        let list = find_chunk(&bytes, b"LIST");
+        assert_eq!(&bytes[list..list + 4], b"INFO");
+        assert_eq!(&bytes[list + 4..list + 8], b"INAM");
+        assert_eq!(&bytes[list + 12..list + 18], b"Intro\0");
+        let info_size = u32_at(&bytes, list - 4) as usize;
+        assert!(!bytes[list..list + info_size]
+            .windows(4)
+            .any(|w| w == b"ISRC"));
+
+        let id3 = find_chunk(&bytes, b"id3 ");
+        assert_eq!(&bytes[id3..id3 + 6], b"ID3\x04\0\0");
+        assert_eq!(&bytes[id3 + 6..id3 + 10], &[0, 0, 0, 23]);
+        assert_eq!(&bytes[id3 + 10..id3 + 14], b"TSRC");
+        assert_eq!(&bytes[id3 + 20..id3 + 33], b"\x03USRC17607839");
+    }
+
+    #[test]
+    fn test_ixml_chunk() {
+        let silence = vec![0.0f32; 4800];
+        let options = ExportOptions {
+            source_sample_rate: 48000,
+            metadata: Metadata::new()
+                .project("Feature & Co")
+                .scene("12A")
+                .take("3")
+                .timecode_rate(SmpteFrameRate::Fps2997Df),
+            start_seconds: 10.0,
+            ..Default::default()
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ixml.wav");
+        export_wav(
+            path.to_str().unwrap(),
+            &[&silence[..], &silence[..]],
+            &options,
+        )
+        .unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+
+        let ixml = find_chunk(&bytes, b"iXML");
+        let size = u32_at(&bytes, ixml - 4) as usize;
+        let xml = std::str::from_utf8(&bytes[ixml..ixml + size]).unwrap();
+        assert!(xml.contains("<PROJECT>Feature &amp; Co</PROJECT>"));
+        assert!(xml.contains("<SCENE>12A</SCENE>"));
+        assert!(xml.contains("<TAKE>3</TAKE>"));
+        assert!(xml.contains("<TIMECODE_RATE>30000/1001</TIMECODE_RATE>"));
+        assert!(xml.contains("<TIMECODE_FLAG>DF</TIMECODE_FLAG>"));
+        // Same time reference as bext
+        assert!(xml.contains("<TIMESTAMP_SAMPLES_SINCE_MIDNIGHT_HI>0</"));
+        assert!(xml.contains("<TIMESTAMP_SAMPLES_SINCE_MIDNIGHT_LO>480000</"));
+        let bext = find_chunk(&bytes, b"bext");
+        let time_reference = u64::from_le_bytes(bytes[bext + 338..bext + 346].try_into().unwrap());
+        assert_eq!(time_reference, 480_000);
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19_723), (2024, 1, 1));
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+    }
+
+    #[test]
+    fn test_layout_mismatch() {
+        let options = ExportOptions {
+            layout: SpeakerLayout::Surround5_1,
+            ..Default::default()
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bad.wav");
+        let left = vec![0.0; 4];
+        let result = export_wav(path.to_str().unwrap(), &[&left[..], &left[..]], &options);
+        assert!(matches!(result, Err(ExportError::InvalidOptions(_))));
     }
 }
@@ -1,4 +1,5 @@
 use crate::export_builder::ExportProgress;
+use crate::queue::CancelToken;
 use crossbeam_channel::Receiver;
 use std::thread::JoinHandle;
 
@@ -7,6 +8,8 @@ pub enum ExportStatus {
     Running(ExportProgress),
     Complete,
     Failed(String),
+    /// Stopped by [`ExportHandle::cancel()`]; partial files were removed.
+    Cancelled,
     Pending,
 }
 
@@ -27,6 +30,7 @@ pub enum ExportStatus {
 ///         ExportStatus::Running(p) => println!("{:?} {:.0}%", p.phase, p.progress * 100.0),
 ///         ExportStatus::Complete => { println!("Done!"); break; }
 ///         ExportStatus::Failed(e) => { eprintln!("Error: {}", e); break; }
+///         ExportStatus::Cancelled => break,
 ///         ExportStatus::Pending => {}
 ///     }
 /// }
@@ -35,20 +39,29 @@ pub struct ExportHandle {
     progress_rx: Receiver<ExportProgress>,
     thread: Option<JoinHandle<crate::Result<()>>>,
     last_progress: Option<ExportProgress>,
+    cancel: CancelToken,
 }
 
 impl ExportHandle {
     pub(crate) fn new(
         progress_rx: Receiver<ExportProgress>,
         thread: JoinHandle<crate::Result<()>>,
+        cancel: CancelToken,
     ) -> Self {
         Self {
             progress_rx,
             thread: Some(thread),
             last_progress: None,
+            cancel,
         }
     }
 
+    /// Stop the export at the next render block or before the next file.
+    /// Files it already wrote are removed.
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
     /// Drains all pending progress messages and returns the latest status.
     pub fn progress(&mut self) -> ExportStatus {
         while let Ok(p) = self.progress_rx.try_recv() {
@@ -60,6 +73,7 @@ impl ExportHandle {
                 let thread = self.thread.take().unwrap();
                 return match thread.join() {
                     Ok(Ok(())) => ExportStatus::Complete,
+                    Ok(Err(crate::ExportError::Cancelled)) => ExportStatus::Cancelled,
                     Ok(Err(e)) => ExportStatus::Failed(e.to_string()),
                     Err(_) => ExportStatus::Failed("Export thread panicked".to_string()),
                 };
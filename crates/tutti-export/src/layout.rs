@@ -0,0 +1,218 @@
+//! Speaker layouts for multichannel export.
+//!
+//! Channels are given in Tutti's order, which matches the VBAP layouts in
+//! `tutti-dsp` (L R C LFE Ls Rs Lrs Rrs, then heights). Encoders reorder them
+//! as their container requires.
+
+use tutti_core::ChannelWeight;
+
+/// A speaker position, with its WAVE_FORMAT_EXTENSIBLE channel mask bit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Speaker {
+    FrontLeft = 0x1,
+    FrontRight = 0x2,
+    FrontCenter = 0x4,
+    Lfe = 0x8,
+    BackLeft = 0x10,
+    BackRight = 0x20,
+    SideLeft = 0x200,
+    SideRight = 0x400,
+    TopFrontLeft = 0x1000,
+    TopFrontRight = 0x4000,
+    TopBackLeft = 0x8000,
+    TopBackRight = 0x20000,
+}
+
+impl Speaker {
+    pub fn mask(self) -> u32 {
+        self as u32
+    }
+
+    /// BS.1770 weighting for this position.
+    pub fn weight(self) -> ChannelWeight {
+        match self {
+            Speaker::Lfe => ChannelWeight::Excluded,
+            Speaker::SideLeft | Speaker::SideRight => ChannelWeight::Surround,
+            _ => ChannelWeight::Unity,
+        }
+    }
+}
+
+use Speaker::*;
+
+const MONO: &[Speaker] = &[FrontCenter];
+const STEREO: &[Speaker] = &[FrontLeft, FrontRight];
+const SURROUND_5_1: &[Speaker] = &[FrontLeft, FrontRight, FrontCenter, Lfe, SideLeft, SideRight];
+const SURROUND_7_1: &[Speaker] = &[
+    FrontLeft,
+    FrontRight,
+    FrontCenter,
+    Lfe,
+    SideLeft,
+    SideRight,
+    BackLeft,
+    BackRight,
+];
+const SURROUND_7_1_4: &[Speaker] = &[
+    FrontLeft,
+    FrontRight,
+    FrontCenter,
+    Lfe,
+    SideLeft,
+    SideRight,
+    BackLeft,
+    BackRight,
+    TopFrontLeft,
+    TopFrontRight,
+    TopBackLeft,
+    TopBackRight,
+];
+
+/// Channel layout of an export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpeakerLayout {
+    Mono,
+    #[default]
+    Stereo,
+    /// L R C LFE Ls Rs
+    Surround5_1,
+    /// L R C LFE Ls Rs Lrs Rrs
+    Surround7_1,
+    /// 7.1 plus Ltf Rtf Ltr Rtr
+    Surround7_1_4,
+    /// Ambisonic B-format in AmbiX convention (ACN order, SN3D), `(order + 1)²`
+    /// channels. Written without a speaker mask.
+    Ambisonic {
+        order: u8,
+    },
+    /// Unassigned channels (no speaker mask, all weighted equally).
+    Discrete(u16),
+}
+
+impl SpeakerLayout {
+    pub fn channels(&self) -> usize {
+        match self {
+            SpeakerLayout::Ambisonic { order } => (*order as usize + 1).pow(2),
+            SpeakerLayout::Discrete(channels) => *channels as usize,
+            _ => self.speakers().len(),
+        }
+    }
+
+    /// Speaker of each channel, in Tutti order. Empty for ambisonic and
+    /// discrete layouts.
+    pub fn speakers(&self) -> &'static [Speaker] {
+        match self {
+            SpeakerLayout::Mono => MONO,
+            SpeakerLayout::Stereo => STEREO,
+            SpeakerLayout::Surround5_1 => SURROUND_5_1,
+            SpeakerLayout::Surround7_1 => SURROUND_7_1,
+            SpeakerLayout::Surround7_1_4 => SURROUND_7_1_4,
+            SpeakerLayout::Ambisonic { .. } | SpeakerLayout::Discrete(_) => &[],
+        }
+    }
+
+    /// WAVE_FORMAT_EXTENSIBLE `dwChannelMask`; 0 means no speaker assignment.
+    pub fn channel_mask(&self) -> u32 {
+        self.speakers().iter().fold(0, |mask, s| mask | s.mask())
+    }
+
+    /// Source channel for each file channel. WAV and FLAC both store
+    /// channels in ascending mask-bit order.
+    pub(crate) fn file_order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.channels()).collect();
+        let speakers = self.speakers();
+        if !speakers.is_empty() {
+            order.sort_by_key(|&ch| speakers[ch].mask());
+        }
+        order
+    }
+
+    /// Source channel for each Vorbis channel. Vorbis puts the center
+    /// second and the LFE last; layouts it doesn't define keep Tutti order.
+    #[cfg(feature = "vorbis")]
+    pub(crate) fn vorbis_order(&self) -> Vec<usize> {
+        match self {
+            SpeakerLayout::Surround5_1 => vec![0, 2, 1, 4, 5, 3],
+            SpeakerLayout::Surround7_1 => vec![0, 2, 1, 4, 5, 6, 7, 3],
+            _ => (0..self.channels()).collect(),
+        }
+    }
+
+    /// BS.1770 weight of each channel. Ambisonic loudness is measured on the
+    /// omnidirectional W channel alone.
+    pub(crate) fn loudness_weights(&self) -> Vec<ChannelWeight> {
+        match self {
+            SpeakerLayout::Ambisonic { .. } => (0..self.channels())
+                .map(|ch| {
+                    if ch == 0 {
+                        ChannelWeight::Unity
+                    } else {
+                        ChannelWeight::Excluded
+                    }
+                })
+                .collect(),
+            SpeakerLayout::Discrete(channels) => vec![ChannelWeight::Unity; *channels as usize],
+            _ => self.speakers().iter().map(|s| s.weight()).collect(),
+        }
+    }
+
+    /// The default layout for a channel count: mono, stereo, or discrete.
+    pub fn for_channels(channels: usize) -> Self {
+        match channels {
+            1 => SpeakerLayout::Mono,
+            2 => SpeakerLayout::Stereo,
+            n => SpeakerLayout::Discrete(n as u16),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_masks() {
+        assert_eq!(SpeakerLayout::Stereo.channel_mask(), 0x3);
+        assert_eq!(SpeakerLayout::Surround5_1.channel_mask(), 0x60F);
+        assert_eq!(SpeakerLayout::Surround7_1.channel_mask(), 0x63F);
+        assert_eq!(SpeakerLayout::Surround7_1_4.channel_mask(), 0x2D63F);
+        assert_eq!(SpeakerLayout::Ambisonic { order: 1 }.channel_mask(), 0);
+        assert_eq!(SpeakerLayout::Ambisonic { order: 3 }.channels(), 16);
+    }
+
+    #[test]
+    fn test_file_order_puts_back_before_side() {
+        assert_eq!(
+            SpeakerLayout::Surround5_1.file_order(),
+            vec![0, 1, 2, 3, 4, 5]
+        );
+        assert_eq!(
+            SpeakerLayout::Surround7_1.file_order(),
+            vec![0, 1, 2, 3, 6, 7, 4, 5]
+        );
+    }
+
+    #[cfg(feature = "vorbis")]
+    #[test]
+    fn test_vorbis_order() {
+        assert_eq!(SpeakerLayout::Stereo.vorbis_order(), vec![0, 1]);
+        // L C R Ls Rs LFE
+        assert_eq!(
+            SpeakerLayout::Surround5_1.vorbis_order(),
+            vec![0, 2, 1, 4, 5, 3]
+        );
+    }
+
+    #[test]
+    fn test_loudness_weights() {
+        use ChannelWeight::*;
+        assert_eq!(
+            SpeakerLayout::Surround5_1.loudness_weights(),
+            vec![Unity, Unity, Unity, Excluded, Surround, Surround]
+        );
+        assert_eq!(
+            SpeakerLayout::Ambisonic { order: 1 }.loudness_weights(),
+            vec![Unity, Excluded, Excluded, Excluded]
+        );
+    }
+}
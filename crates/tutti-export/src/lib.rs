@@ -5,9 +5,13 @@
 //! ## When to Use
 //!
 //! Use this crate for **offline rendering** with full DSP processing:
-//! - Bounce/export a mix to WAV or FLAC
+//! - Bounce/export a mix to WAV or FLAC, or Opus/Vorbis/MP3 for previews
+//! - Stereo, surround (5.1, 7.1, 7.1.4) and ambisonic B-format
 //! - Sample rate conversion, bit depth reduction
-//! - LUFS normalization, dithering
+//! - LUFS normalization with an optional true-peak limiter, dithering
+//! - Tags (title, artist, ISRC, ...), Broadcast Wave `bext`, ReplayGain
+//! - Beat/bar ranges, repeated loops, decay tails and silence trimming
+//! - Cancellable background jobs and batch queues with ETA
 //!
 //! For **real-time recording** (capturing live audio input), use
 //! `tutti-sampler`'s recording API instead.
@@ -22,12 +26,52 @@
 //!     .normalize(NormalizationMode::lufs(-14.0))
 //!     .to_file("output.flac")?;
 //!
+//! // Hit -14 LUFS by limiting peaks to -1 dBTP instead of turning down
+//! engine.export()
+//!     .duration_seconds(180.0)
+//!     .normalize(NormalizationMode::lufs(-14.0))
+//!     .limiter(LimiterOptions::default())
+//!     .to_file_with_progress("master.wav", |p| {
+//!         if let Some(report) = p.loudness {
+//!             println!("{:.1} LUFS, {:.1} dB limited", report.integrated_lufs, report.max_gain_reduction_db);
+//!         }
+//!     })?;
+//!
+//! // Bars 9-16 with the loop played four times, until the reverb dies away
+//! engine.export()
+//!     .bbt_range(BBT::new(9, 1, 0), BBT::new(17, 1, 0))
+//!     .loop_count(4)
+//!     .tail(TailOptions::default())
+//!     .trim_silence(-80.0)
+//!     .to_file("loop.wav")?;
+//!
+//! // Stems from the same render pass, written next to the master
+//! engine.export()
+//!     .duration_seconds(180.0)
+//!     .stem(Stem::new(drums, "Song - Drums"))
+//!     .stem(Stem::new(bass, "Song - Bass"))
+//!     .to_file("mixes/Song.wav")?;
+//!
 //! // With progress callback
 //! engine.export()
 //!     .duration_seconds(3600.0)
 //!     .to_file_with_progress("output.wav", |p| {
 //!         println!("{:?}: {:.0}%", p.phase, p.progress * 100.0);
 //!     })?;
+//!
+//! // Several renders, two at a time; each can be cancelled on its own
+//! let mut queue = ExportQueue::new().max_parallel(2);
+//! let master = queue.push(engine.export().beat_range(0.0, 64.0), "Song.wav");
+//! let preview = queue.push(
+//!     engine.export().beat_range(0.0, 64.0).format(AudioFormat::Mp3),
+//!     "Song.mp3",
+//! );
+//! preview.cancel();
+//! for status in queue.wait() {
+//!     if let JobStatus::Complete(Some(report)) = status {
+//!         println!("{:.1} LUFS", report.integrated_lufs);
+//!     }
+//! }
 //! ```
 //!
 //! Or standalone:
@@ -40,8 +84,11 @@
 //!
 //! ## Features
 //!
-//! - `wav` (default): WAV encoding
+//! - `wav` (default): WAV encoding (WAVE_FORMAT_EXTENSIBLE for multichannel)
 //! - `flac` (default): FLAC encoding
+//! - `vorbis`: Ogg Vorbis encoding (`.ogg`, builds libvorbis)
+//! - `opus`: Ogg Opus encoding (`.opus`, builds libopus)
+//! - `mp3`: MP3 encoding (`.mp3`, builds LAME)
 
 mod error;
 pub use error::{ExportError, Result};
@@ -49,14 +96,30 @@ pub use error::{ExportError, Result};
 mod handle;
 pub use handle::{ExportHandle, ExportStatus};
 
+mod queue;
+pub use queue::{CancelToken, ExportJob, ExportQueue, JobProgress, JobStatus};
+
 mod export_builder;
-pub use export_builder::{ExportBuilder, ExportPhase, ExportProgress};
+pub use export_builder::{ExportBuilder, ExportPhase, ExportProgress, LoudnessReport};
 
 pub use tutti_core::{ExportConfig, ExportContext};
 
+mod layout;
+pub use layout::{Speaker, SpeakerLayout};
+
+mod metadata;
+pub use metadata::Metadata;
+
+mod range;
+pub use range::TailOptions;
+
+mod stem;
+pub use stem::{Stem, StemAudio};
+
 mod options;
 pub use options::{
-    AudioFormat, BitDepth, DitherType, ExportOptions, FlacOptions, NormalizationMode,
+    AudioFormat, BitDepth, DitherType, ExportOptions, FlacOptions, LimiterOptions, Mp3Options,
+    NoiseShape, NormalizationMode, OpusOptions, VorbisOptions,
 };
 
 pub(crate) mod dsp;
@@ -65,54 +128,77 @@ pub use dsp::ResampleQuality;
 pub(crate) mod format;
 
 /// Export audio to a file (format detected from extension).
-#[allow(unused_variables)]
 pub fn export_to_file(
     path: &str,
     left: &[f32],
     right: &[f32],
     options: &ExportOptions,
 ) -> Result<()> {
-    let ext = path.rsplit('.').next().unwrap_or("").to_lowercase();
-
-    match ext.as_str() {
-        #[cfg(feature = "wav")]
-        "wav" => format::wav::export_wav(path, left, right, options),
-        #[cfg(not(feature = "wav"))]
-        "wav" => Err(ExportError::UnsupportedFormat("WAV not enabled".into())),
-
-        #[cfg(feature = "flac")]
-        "flac" => format::flac::export_flac(path, left, right, options),
-        #[cfg(not(feature = "flac"))]
-        "flac" => Err(ExportError::UnsupportedFormat("FLAC not enabled".into())),
-
-        _ => Err(ExportError::UnsupportedFormat(format!(
-            "Unknown extension: .{}",
-            ext
-        ))),
-    }
+    export_channels_to_file(path, &[left, right], options)
 }
 
-#[allow(unused_variables)]
 pub fn export_to_file_with_progress(
     path: &str,
     left: &[f32],
     right: &[f32],
     options: &ExportOptions,
     on_progress: impl Fn(ExportProgress),
+) -> Result<()> {
+    export_channels_to_file_with_progress(path, &[left, right], options, on_progress)
+}
+
+/// Export any number of channels, laid out as `options.layout` says.
+///
+/// ```ignore
+/// let options = ExportOptions {
+///     layout: SpeakerLayout::Surround5_1,
+///     ..Default::default()
+/// };
+/// export_channels_to_file("mix.wav", &[&l, &r, &c, &lfe, &ls, &rs], &options)?;
+/// ```
+pub fn export_channels_to_file(
+    path: &str,
+    channels: &[&[f32]],
+    options: &ExportOptions,
+) -> Result<()> {
+    export_channels_to_file_with_progress(path, channels, options, |_| {})
+}
+
+#[allow(unused_variables)]
+pub fn export_channels_to_file_with_progress(
+    path: &str,
+    channels: &[&[f32]],
+    options: &ExportOptions,
+    on_progress: impl Fn(ExportProgress),
 ) -> Result<()> {
     let ext = path.rsplit('.').next().unwrap_or("").to_lowercase();
 
     match ext.as_str() {
         #[cfg(feature = "wav")]
-        "wav" => format::wav::export_wav_with_progress(path, left, right, options, on_progress),
+        "wav" => format::wav::export_wav_with_progress(path, channels, options, on_progress),
         #[cfg(not(feature = "wav"))]
         "wav" => Err(ExportError::UnsupportedFormat("WAV not enabled".into())),
 
         #[cfg(feature = "flac")]
-        "flac" => format::flac::export_flac_with_progress(path, left, right, options, on_progress),
+        "flac" => format::flac::export_flac_with_progress(path, channels, options, on_progress),
         #[cfg(not(feature = "flac"))]
         "flac" => Err(ExportError::UnsupportedFormat("FLAC not enabled".into())),
 
+        #[cfg(feature = "vorbis")]
+        "ogg" => format::vorbis::export_vorbis_with_progress(path, channels, options, on_progress),
+        #[cfg(not(feature = "vorbis"))]
+        "ogg" => Err(ExportError::UnsupportedFormat("Vorbis not enabled".into())),
+
+        #[cfg(feature = "opus")]
+        "opus" => format::opus::export_opus_with_progress(path, channels, options, on_progress),
+        #[cfg(not(feature = "opus"))]
+        "opus" => Err(ExportError::UnsupportedFormat("Opus not enabled".into())),
+
+        #[cfg(feature = "mp3")]
+        "mp3" => format::mp3::export_mp3_with_progress(path, channels, options, on_progress),
+        #[cfg(not(feature = "mp3"))]
+        "mp3" => Err(ExportError::UnsupportedFormat("MP3 not enabled".into())),
+
         _ => Err(ExportError::UnsupportedFormat(format!(
             "Unknown extension: .{}",
             ext
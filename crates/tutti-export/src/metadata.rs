@@ -0,0 +1,260 @@
+//! Descriptive and loudness tags for exported files.
+//!
+//! Every container gets the same tags in its own form: Vorbis comments in
+//! FLAC and Ogg, ID3v2 frames in MP3, and LIST/INFO, an `id3 ` chunk (for
+//! the ISRC) plus Broadcast Wave `bext` and `iXML` chunks in WAV. Loudness tags are measured on the encoded
+//! audio, after normalization.
+
+use tutti_core::{LoudnessResult, SmpteFrameRate};
+
+/// Written as the encoder/software tag.
+pub(crate) const SOFTWARE: &str = "Tutti";
+
+/// ID3v2 text encoding byte for UTF-8.
+#[cfg_attr(not(any(feature = "wav", feature = "mp3")), allow(dead_code))]
+pub(crate) const ID3_UTF8: u8 = 3;
+
+/// ReplayGain 2.0 reference level.
+const REPLAYGAIN_REFERENCE_LUFS: f64 = -18.0;
+
+/// Opus' R128 gain tags are relative to EBU R128's target (RFC 7845, 5.2.1).
+const R128_REFERENCE_LUFS: f64 = -23.0;
+
+/// Descriptive tags for an export.
+///
+/// ```ignore
+/// engine.export()
+///     .duration_seconds(180.0)
+///     .metadata(Metadata::new().title("Intro").artist("Tutti").isrc("USRC17607839"))
+///     .to_file("intro.flac")?;
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    /// International Standard Recording Code, e.g. `USRC17607839`.
+    pub isrc: Option<String>,
+    pub comment: Option<String>,
+    /// Production fields, written to WAV's `iXML` chunk.
+    pub project: Option<String>,
+    pub scene: Option<String>,
+    pub take: Option<String>,
+    pub timecode_rate: Option<SmpteFrameRate>,
+}
+
+impl Metadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn artist(mut self, artist: impl Into<String>) -> Self {
+        self.artist = Some(artist.into());
+        self
+    }
+
+    pub fn album(mut self, album: impl Into<String>) -> Self {
+        self.album = Some(album.into());
+        self
+    }
+
+    pub fn isrc(mut self, isrc: impl Into<String>) -> Self {
+        self.isrc = Some(isrc.into());
+        self
+    }
+
+    pub fn comment(mut self, comment: impl Into<String>) -> Self {
+        self.comment = Some(comment.into());
+        self
+    }
+
+    pub fn project(mut self, project: impl Into<String>) -> Self {
+        self.project = Some(project.into());
+        self
+    }
+
+    pub fn scene(mut self, scene: impl Into<String>) -> Self {
+        self.scene = Some(scene.into());
+        self
+    }
+
+    pub fn take(mut self, take: impl Into<String>) -> Self {
+        self.take = Some(take.into());
+        self
+    }
+
+    /// Timecode rate of the production, for iXML's `TIMECODE_RATE`.
+    pub fn timecode_rate(mut self, rate: SmpteFrameRate) -> Self {
+        self.timecode_rate = Some(rate);
+        self
+    }
+
+    /// Set tags as Vorbis comment field names and values.
+    #[cfg_attr(
+        not(any(feature = "flac", feature = "vorbis", feature = "opus")),
+        allow(dead_code)
+    )]
+    pub(crate) fn vorbis_fields(&self) -> Vec<(&'static str, String)> {
+        [
+            ("TITLE", &self.title),
+            ("ARTIST", &self.artist),
+            ("ALBUM", &self.album),
+            ("ISRC", &self.isrc),
+            ("COMMENT", &self.comment),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| value.clone().map(|value| (key, value)))
+        .collect()
+    }
+}
+
+/// Whether the measurement found anything above the gate. Silence gets no
+/// loudness tags rather than a +52 dB gain.
+pub(crate) fn is_measured(loudness: &LoudnessResult) -> bool {
+    loudness.integrated_lufs.is_finite() && loudness.integrated_lufs > -70.0
+}
+
+/// ReplayGain 2.0 track tags (gain, true peak, loudness range).
+#[cfg_attr(
+    not(any(feature = "flac", feature = "vorbis", feature = "mp3")),
+    allow(dead_code)
+)]
+pub(crate) fn replaygain_fields(loudness: &LoudnessResult) -> Vec<(&'static str, String)> {
+    if !is_measured(loudness) {
+        return Vec::new();
+    }
+    vec![
+        (
+            "REPLAYGAIN_TRACK_GAIN",
+            format!(
+                "{:.2} dB",
+                REPLAYGAIN_REFERENCE_LUFS - loudness.integrated_lufs
+            ),
+        ),
+        (
+            "REPLAYGAIN_TRACK_PEAK",
+            format!("{:.6}", 10f64.powf(loudness.true_peak_dbtp / 20.0)),
+        ),
+        (
+            "REPLAYGAIN_TRACK_RANGE",
+            format!("{:.2} dB", loudness.loudness_range_lu),
+        ),
+        (
+            "REPLAYGAIN_REFERENCE_LOUDNESS",
+            format!("{:.2} LUFS", REPLAYGAIN_REFERENCE_LUFS),
+        ),
+    ]
+}
+
+/// Opus `R128_TRACK_GAIN`: Q7.8 dB to reach -23 LUFS. Opus players ignore
+/// ReplayGain tags, so this replaces them.
+#[cfg_attr(not(feature = "opus"), allow(dead_code))]
+pub(crate) fn r128_fields(loudness: &LoudnessResult) -> Vec<(&'static str, String)> {
+    if !is_measured(loudness) {
+        return Vec::new();
+    }
+    let gain = ((R128_REFERENCE_LUFS - loudness.integrated_lufs) * 256.0)
+        .round()
+        .clamp(i16::MIN as f64, i16::MAX as f64) as i16;
+    vec![("R128_TRACK_GAIN", gain.to_string())]
+}
+
+/// A Vorbis comment structure: vendor string, then `KEY=value` fields.
+/// FLAC's VORBIS_COMMENT block and Opus' OpusTags packet both carry this
+/// (without Vorbis' trailing framing bit).
+#[cfg_attr(not(any(feature = "flac", feature = "opus")), allow(dead_code))]
+pub(crate) fn vorbis_comment(fields: &[(&str, String)]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&(SOFTWARE.len() as u32).to_le_bytes());
+    out.extend_from_slice(SOFTWARE.as_bytes());
+    out.extend_from_slice(&(fields.len() as u32).to_le_bytes());
+    for (key, value) in fields {
+        let field = format!("{}={}", key, value);
+        out.extend_from_slice(&(field.len() as u32).to_le_bytes());
+        out.extend_from_slice(field.as_bytes());
+    }
+    out
+}
+
+/// ID3v2.4 tag around already-built frames.
+#[cfg_attr(not(any(feature = "wav", feature = "mp3")), allow(dead_code))]
+pub(crate) fn id3_tag(frames: &[u8]) -> Vec<u8> {
+    let mut tag = b"ID3".to_vec();
+    tag.extend_from_slice(&[4, 0, 0]); // v2.4.0, no flags
+    tag.extend_from_slice(&synchsafe(frames.len() as u32));
+    tag.extend_from_slice(frames);
+    tag
+}
+
+#[cfg_attr(not(any(feature = "wav", feature = "mp3")), allow(dead_code))]
+pub(crate) fn id3_frame(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(10 + body.len());
+    frame.extend_from_slice(id);
+    frame.extend_from_slice(&synchsafe(body.len() as u32));
+    frame.extend_from_slice(&[0, 0]); // flags
+    frame.extend_from_slice(body);
+    frame
+}
+
+/// 28-bit size in four 7-bit bytes, as ID3v2.4 sizes are stored.
+#[cfg_attr(not(any(feature = "wav", feature = "mp3")), allow(dead_code))]
+fn synchsafe(size: u32) -> [u8; 4] {
+    [
+        (size >> 21) as u8 & 0x7F,
+        (size >> 14) as u8 & 0x7F,
+        (size >> 7) as u8 & 0x7F,
+        size as u8 & 0x7F,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loudness(integrated_lufs: f64) -> LoudnessResult {
+        LoudnessResult {
+            integrated_lufs,
+            true_peak_dbtp: -1.0,
+            loudness_range_lu: 4.5,
+        }
+    }
+
+    #[test]
+    fn test_synchsafe() {
+        assert_eq!(synchsafe(127), [0, 0, 0, 127]);
+        assert_eq!(synchsafe(128), [0, 0, 1, 0]);
+        assert_eq!(synchsafe(0x0FFF_FFFF), [0x7F; 4]);
+    }
+
+    #[test]
+    fn test_loudness_tags() {
+        let fields = replaygain_fields(&loudness(-14.0));
+        assert_eq!(fields[0], ("REPLAYGAIN_TRACK_GAIN", "-4.00 dB".into()));
+        assert_eq!(fields[1], ("REPLAYGAIN_TRACK_PEAK", "0.891251".into()));
+        assert_eq!(fields[2], ("REPLAYGAIN_TRACK_RANGE", "4.50 dB".into()));
+        assert_eq!(
+            r128_fields(&loudness(-14.0)),
+            vec![("R128_TRACK_GAIN", "-2304".into())]
+        );
+
+        assert!(replaygain_fields(&loudness(f64::NEG_INFINITY)).is_empty());
+    }
+
+    #[test]
+    fn test_vorbis_comment() {
+        let fields = Metadata::new()
+            .title("Intro")
+            .isrc("USRC17607839")
+            .vorbis_fields();
+        let bytes = vorbis_comment(&fields);
+        assert_eq!(&bytes[4..9], b"Tutti");
+        assert_eq!(u32::from_le_bytes(bytes[9..13].try_into().unwrap()), 2);
+        assert_eq!(u32::from_le_bytes(bytes[13..17].try_into().unwrap()), 11);
+        assert_eq!(&bytes[17..28], b"TITLE=Intro");
+    }
+}
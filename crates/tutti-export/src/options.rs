@@ -1,10 +1,16 @@
 use crate::dsp::ResampleQuality;
+use crate::{Metadata, SpeakerLayout};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum AudioFormat {
     #[default]
     Wav,
     Flac,
+    /// Ogg Opus, always encoded at 48 kHz.
+    Opus,
+    /// Ogg Vorbis
+    Vorbis,
+    Mp3,
 }
 
 impl AudioFormat {
@@ -12,6 +18,9 @@ impl AudioFormat {
         match self {
             AudioFormat::Wav => "wav",
             AudioFormat::Flac => "flac",
+            AudioFormat::Opus => "opus",
+            AudioFormat::Vorbis => "ogg",
+            AudioFormat::Mp3 => "mp3",
         }
     }
 }
@@ -40,7 +49,23 @@ pub enum DitherType {
     Rectangular,
     #[default]
     Triangular,
-    NoiseShaped,
+    /// Triangular dither with the requantization noise moved away from the
+    /// frequencies the ear is most sensitive to.
+    NoiseShaped(NoiseShape),
+}
+
+/// Noise-shaping curve. Filters are designed for the output sample rate, so
+/// the curve lands on the same frequencies at 44.1, 48 or 96 kHz.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NoiseShape {
+    /// 9th-order curve following the threshold of hearing: noise drops
+    /// ~15 dB through the midrange and ~23 dB around 3.5 kHz, and rises
+    /// above 12 kHz. The usual choice for 16-bit masters.
+    #[default]
+    FWeighted,
+    /// Gentler 5th-order high-pass curve: noise drops ~13 dB below 5 kHz
+    /// and rises ~7 dB at the top of the band.
+    EWeighted,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
@@ -66,6 +91,29 @@ impl NormalizationMode {
     }
 }
 
+/// Look-ahead true-peak limiter for [`NormalizationMode::Loudness`].
+///
+/// Without it, loudness normalization turns the whole file down when the
+/// target would push peaks past the true-peak limit, and the file ends up
+/// quieter than asked. With it, the full gain is applied and the peaks are
+/// limited to the true-peak limit instead.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LimiterOptions {
+    /// How far ahead of a peak gain reduction starts (ms).
+    pub lookahead_ms: f64,
+    /// Time constant of the recovery after a peak (ms).
+    pub release_ms: f64,
+}
+
+impl Default for LimiterOptions {
+    fn default() -> Self {
+        Self {
+            lookahead_ms: 1.5,
+            release_ms: 50.0,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct FlacOptions {
     pub compression_level: u8,
@@ -79,6 +127,43 @@ impl Default for FlacOptions {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OpusOptions {
+    /// Target bitrate in kbit/s (6-510).
+    pub bitrate_kbps: u32,
+}
+
+impl Default for OpusOptions {
+    fn default() -> Self {
+        Self { bitrate_kbps: 128 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VorbisOptions {
+    /// VBR quality from -0.1 to 1.0 (0.5 is roughly 160 kbit/s stereo).
+    pub quality: f32,
+}
+
+impl Default for VorbisOptions {
+    fn default() -> Self {
+        Self { quality: 0.5 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mp3Options {
+    /// Constant bitrate in kbit/s: 8, 16, 24, 32, 40, 48, 64, 80, 96, 112,
+    /// 128, 160, 192, 224, 256 or 320.
+    pub bitrate_kbps: u32,
+}
+
+impl Default for Mp3Options {
+    fn default() -> Self {
+        Self { bitrate_kbps: 192 }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct ExportOptions {
     pub format: AudioFormat,
@@ -87,10 +172,23 @@ pub struct ExportOptions {
     pub sample_rate: Option<u32>,
     pub source_sample_rate: u32,
     pub normalization: NormalizationMode,
+    /// Limit peaks instead of lowering the loudness target (None = off).
+    pub limiter: Option<LimiterOptions>,
     pub dither: DitherType,
     pub resample_quality: ResampleQuality,
+    /// Layout of the channels being exported. Drives the WAV channel mask,
+    /// channel order in the file and loudness weighting.
+    pub layout: SpeakerLayout,
+    /// Downmix a stereo export to mono.
     pub mono: bool,
     pub flac: FlacOptions,
+    pub opus: OpusOptions,
+    pub vorbis: VorbisOptions,
+    pub mp3: Mp3Options,
+    pub metadata: Metadata,
+    /// Timeline position of the first sample, in seconds. WAV files record
+    /// it as the Broadcast Wave time reference.
+    pub start_seconds: f64,
 }
 
 impl Default for ExportOptions {
@@ -101,10 +199,17 @@ impl Default for ExportOptions {
             sample_rate: None,
             source_sample_rate: 44100,
             normalization: NormalizationMode::None,
+            limiter: None,
             dither: DitherType::Triangular,
             resample_quality: ResampleQuality::Medium,
+            layout: SpeakerLayout::Stereo,
             mono: false,
             flac: FlacOptions::default(),
+            opus: OpusOptions::default(),
+            vorbis: VorbisOptions::default(),
+            mp3: Mp3Options::default(),
+            metadata: Metadata::default(),
+            start_seconds: 0.0,
         }
     }
 }
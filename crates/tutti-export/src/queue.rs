@@ -0,0 +1,412 @@
+//! Batches of exports: album tracks, format variants, stems per mix.
+//!
+//! An [`ExportQueue`] runs its jobs one at a time by default, or several at
+//! once up to [`max_parallel`](ExportQueue::max_parallel). Each job can be
+//! cancelled on its own and reports its phase, an ETA and, once done, the
+//! loudness of what it wrote.
+
+use crate::export_builder::{ExportBuilder, ExportPhase, ExportProgress, LoudnessReport};
+use crate::ExportError;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Stops an export from another thread. Clones share the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Release);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct JobProgress {
+    pub phase: ExportPhase,
+    /// Progress within current phase (0.0 to 1.0).
+    pub progress: f32,
+    /// Estimated time until the whole job is done, once there is enough to
+    /// go on.
+    pub eta: Option<Duration>,
+}
+
+#[derive(Debug, Clone)]
+pub enum JobStatus {
+    Queued,
+    Running(JobProgress),
+    /// Written; carries the master file's loudness report.
+    Complete(Option<LoudnessReport>),
+    Failed(String),
+    /// Cancelled before or while running; partial files were removed.
+    Cancelled,
+}
+
+impl JobStatus {
+    /// Complete, failed or cancelled.
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Complete(_) | JobStatus::Failed(_) | JobStatus::Cancelled
+        )
+    }
+}
+
+struct JobShared {
+    cancel: CancelToken,
+    status: Mutex<JobStatus>,
+}
+
+/// One job in an [`ExportQueue`]. Clones refer to the same job.
+#[derive(Clone)]
+pub struct ExportJob {
+    path: PathBuf,
+    shared: Arc<JobShared>,
+}
+
+impl ExportJob {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn status(&self) -> JobStatus {
+        self.status_lock().clone()
+    }
+
+    /// A queued job never starts; a running one stops at the next render
+    /// block or before the next file and removes the files it wrote.
+    pub fn cancel(&self) {
+        self.shared.cancel.cancel();
+        let mut status = self.status_lock();
+        if matches!(*status, JobStatus::Queued) {
+            *status = JobStatus::Cancelled;
+        }
+    }
+
+    fn status_lock(&self) -> MutexGuard<'_, JobStatus> {
+        // A panicking job is reported as failed; its status is still usable
+        self.shared
+            .status
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn run(&self, builder: ExportBuilder) {
+        {
+            let mut status = self.status_lock();
+            if self.shared.cancel.is_cancelled() {
+                *status = JobStatus::Cancelled;
+                return;
+            }
+            *status = JobStatus::Running(JobProgress {
+                phase: ExportPhase::Rendering,
+                progress: 0.0,
+                eta: None,
+            });
+        }
+
+        let started = Instant::now();
+        let done = Cell::new(0.0);
+        let loudness = Cell::new(None);
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            builder
+                .cancel_token(self.shared.cancel.clone())
+                .to_file_with_progress(&self.path, |p| {
+                    // The master is written first; stems report their own
+                    if loudness.get().is_none() {
+                        loudness.set(p.loudness);
+                    }
+                    // Stems restart the encoding phase, so never go backwards
+                    done.set(job_fraction(&p).max(done.get()));
+                    *self.status_lock() = JobStatus::Running(JobProgress {
+                        phase: p.phase,
+                        progress: p.progress,
+                        eta: eta(started.elapsed(), done.get()),
+                    });
+                })
+        }));
+
+        *self.status_lock() = match result {
+            Ok(Ok(())) => JobStatus::Complete(loudness.get()),
+            Ok(Err(ExportError::Cancelled)) => JobStatus::Cancelled,
+            Ok(Err(e)) => JobStatus::Failed(e.to_string()),
+            Err(_) => JobStatus::Failed("Export job panicked".to_string()),
+        };
+    }
+}
+
+/// Share of the whole job done at `progress`. Rendering runs the graph and
+/// takes most of the time; processing and encoding split the rest.
+fn job_fraction(progress: &ExportProgress) -> f64 {
+    let p = progress.progress.clamp(0.0, 1.0) as f64;
+    match progress.phase {
+        ExportPhase::Rendering => 0.8 * p,
+        ExportPhase::Processing => 0.8 + 0.1 * p,
+        ExportPhase::Encoding => 0.9 + 0.1 * p,
+    }
+}
+
+/// Time left if the rest goes at the rate so far.
+fn eta(elapsed: Duration, done: f64) -> Option<Duration> {
+    (done > 0.0).then(|| elapsed.mul_f64((1.0 - done).max(0.0) / done))
+}
+
+struct QueueState {
+    pending: VecDeque<(ExportBuilder, ExportJob)>,
+    /// Workers currently taking jobs
+    workers: usize,
+}
+
+/// Runs export jobs in the background, in the order they were pushed.
+///
+/// Each job renders its own graph snapshot, so jobs running in parallel
+/// don't interfere; each also holds its rendered audio until written, which
+/// is what [`max_parallel`](Self::max_parallel) bounds.
+///
+/// # Example
+/// ```ignore
+/// let mut queue = ExportQueue::new().max_parallel(2);
+/// for format in [AudioFormat::Wav, AudioFormat::Flac, AudioFormat::Mp3] {
+///     let path = format!("album/01 - Intro.{}", format.extension());
+///     queue.push(engine.export().beat_range(0.0, 64.0).format(format), path);
+/// }
+///
+/// while !queue.is_done() {
+///     for job in queue.jobs() {
+///         if let JobStatus::Running(p) = job.status() {
+///             println!("{}: {:?} {:.0}%, {:?} left", job.path().display(), p.phase, p.progress * 100.0, p.eta);
+///         }
+///     }
+///     std::thread::sleep(Duration::from_millis(100));
+/// }
+/// ```
+///
+/// Dropping the queue lets its jobs finish in the background; call
+/// [`cancel_all`](Self::cancel_all) first to stop them.
+pub struct ExportQueue {
+    max_parallel: usize,
+    state: Arc<Mutex<QueueState>>,
+    workers: Vec<JoinHandle<()>>,
+    jobs: Vec<ExportJob>,
+}
+
+impl Default for ExportQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExportQueue {
+    /// A queue that runs one job at a time.
+    pub fn new() -> Self {
+        Self {
+            max_parallel: 1,
+            state: Arc::new(Mutex::new(QueueState {
+                pending: VecDeque::new(),
+                workers: 0,
+            })),
+            workers: Vec::new(),
+            jobs: Vec::new(),
+        }
+    }
+
+    /// Run up to `jobs` exports at once (at least one).
+    pub fn max_parallel(mut self, jobs: usize) -> Self {
+        self.max_parallel = jobs.max(1);
+        self
+    }
+
+    /// Queue `builder` to be written to `path`. It starts as soon as a
+    /// worker is free.
+    pub fn push(&mut self, builder: ExportBuilder, path: impl AsRef<Path>) -> ExportJob {
+        let job = ExportJob {
+            path: path.as_ref().to_path_buf(),
+            shared: Arc::new(JobShared {
+                cancel: CancelToken::new(),
+                status: Mutex::new(JobStatus::Queued),
+            }),
+        };
+        self.jobs.push(job.clone());
+
+        let mut state = lock(&self.state);
+        state.pending.push_back((builder, job.clone()));
+        if state.workers < self.max_parallel {
+            state.workers += 1;
+            let shared = Arc::clone(&self.state);
+            self.workers.retain(|worker| !worker.is_finished());
+            self.workers.push(
+                std::thread::Builder::new()
+                    .name("tutti-export-queue".into())
+                    .spawn(move || work(&shared))
+                    .expect("failed to spawn export thread"),
+            );
+        }
+        job
+    }
+
+    /// Every job pushed so far, in order.
+    pub fn jobs(&self) -> &[ExportJob] {
+        &self.jobs
+    }
+
+    pub fn cancel_all(&self) {
+        for job in &self.jobs {
+            job.cancel();
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.jobs.iter().all(|job| job.status().is_finished())
+    }
+
+    /// Block until every job has finished; returns their final status in
+    /// the order they were pushed.
+    pub fn wait(mut self) -> Vec<JobStatus> {
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+        self.jobs.iter().map(ExportJob::status).collect()
+    }
+}
+
+/// Take jobs until the queue is empty.
+fn work(state: &Mutex<QueueState>) {
+    loop {
+        let next = {
+            let mut state = lock(state);
+            match state.pending.pop_front() {
+                Some(next) => next,
+                None => {
+                    // Decided under the lock, so a concurrent push either
+                    // sees this worker gone or its job gets taken here
+                    state.workers -= 1;
+                    return;
+                }
+            }
+        };
+        let (builder, job) = next;
+        job.run(builder);
+    }
+}
+
+fn lock(state: &Mutex<QueueState>) -> MutexGuard<'_, QueueState> {
+    state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fundsp::prelude::*;
+
+    fn dc_export(level: f32, seconds: f64) -> ExportBuilder {
+        let mut net = Net::new(0, 1);
+        let dc_id = net.push(Box::new(dc(level)));
+        net.pipe_output(dc_id);
+        ExportBuilder::new(net, 48000.0).duration_seconds(seconds)
+    }
+
+    #[test]
+    fn test_queue_runs_every_job() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut queue = ExportQueue::new().max_parallel(2);
+        for (i, level) in [0.1, 0.2, 0.4].into_iter().enumerate() {
+            queue.push(dc_export(level, 0.25), dir.path().join(format!("{i}.wav")));
+        }
+        let state = Arc::clone(&queue.state);
+
+        let statuses = queue.wait();
+        assert_eq!(statuses.len(), 3);
+        for (i, status) in statuses.iter().enumerate() {
+            assert!(
+                matches!(status, JobStatus::Complete(Some(_))),
+                "{i}: {status:?}"
+            );
+            assert!(dir.path().join(format!("{i}.wav")).exists());
+        }
+        let state = lock(&state);
+        assert!(state.pending.is_empty());
+        assert_eq!(state.workers, 0);
+    }
+
+    #[test]
+    fn test_cancelled_jobs_leave_no_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut queue = ExportQueue::new();
+        let first = queue.push(dc_export(0.5, 0.25), dir.path().join("first.wav"));
+        // Long enough that it can't finish before the cancel lands
+        let long = queue.push(dc_export(0.5, 600.0), dir.path().join("long.wav"));
+        let last = queue.push(dc_export(0.5, 0.25), dir.path().join("last.wav"));
+        long.cancel();
+
+        let statuses = queue.wait();
+        assert!(matches!(statuses[0], JobStatus::Complete(_)));
+        assert!(matches!(statuses[1], JobStatus::Cancelled));
+        assert!(matches!(statuses[2], JobStatus::Complete(_)));
+        assert!(first.path().exists());
+        assert!(!long.path().exists());
+        assert!(last.path().exists());
+    }
+
+    #[test]
+    fn test_cancel_between_files_removes_the_master() {
+        use crate::Stem;
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut net = Net::new(0, 1);
+        let dc_id = net.push(Box::new(dc(0.5)));
+        net.pipe_output(dc_id);
+        let master = dir.path().join("mix.wav");
+
+        let token = CancelToken::new();
+        let result = ExportBuilder::new(net, 48000.0)
+            .duration_seconds(0.25)
+            .stem(Stem::new(dc_id, "mix - dc"))
+            .cancel_token(token.clone())
+            .to_file_with_progress(&master, |p| {
+                // Cancel once the master is written, before the stem
+                if p.phase == ExportPhase::Encoding && p.progress == 1.0 {
+                    token.cancel();
+                }
+            });
+
+        assert!(matches!(result, Err(ExportError::Cancelled)));
+        assert!(!master.exists());
+        assert!(!dir.path().join("mix - dc.wav").exists());
+    }
+
+    #[test]
+    fn test_eta_follows_the_phases() {
+        let at = |phase, progress| {
+            job_fraction(&ExportProgress {
+                phase,
+                progress,
+                loudness: None,
+            })
+        };
+        assert_eq!(at(ExportPhase::Rendering, 0.5), 0.4);
+        assert_eq!(at(ExportPhase::Processing, 1.0), 0.9);
+        assert_eq!(at(ExportPhase::Encoding, 1.0), 1.0);
+
+        assert_eq!(eta(Duration::from_secs(4), 0.0), None);
+        assert_eq!(
+            eta(Duration::from_secs(4), 0.5),
+            Some(Duration::from_secs(4))
+        );
+        assert_eq!(eta(Duration::from_secs(4), 1.0), Some(Duration::ZERO));
+    }
+}
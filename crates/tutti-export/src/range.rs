@@ -0,0 +1,124 @@
+//! What part of the timeline to render and where the file ends.
+//!
+//! A range is given in beats or bars and resolved through the export
+//! timeline's tempo map. Past the range end, an optional tail keeps
+//! rendering until reverbs and delays have died away, and silence at either
+//! end can be trimmed off.
+
+use tutti_core::{ExportTimeline, BBT};
+
+/// A range boundary, resolved to beats once the timeline is known.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Position {
+    Beat(f64),
+    Bbt(BBT),
+}
+
+impl Position {
+    pub(crate) fn to_beats(self, timeline: &ExportTimeline) -> f64 {
+        match self {
+            Position::Beat(beat) => beat,
+            Position::Bbt(bbt) => timeline.bbt_to_beats(bbt),
+        }
+    }
+}
+
+/// Keep rendering past the range end until the output dies away.
+///
+/// The file ends where the output dropped below `threshold_db` for good, so
+/// the held silence itself is not written.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TailOptions {
+    /// Level (dBFS) below which the output counts as silent.
+    pub threshold_db: f64,
+    /// How long the output must stay below the threshold (seconds).
+    pub hold_seconds: f64,
+    /// Longest tail to render (seconds), for feedback that never decays.
+    pub max_seconds: f64,
+}
+
+impl Default for TailOptions {
+    fn default() -> Self {
+        Self {
+            threshold_db: -90.0,
+            hold_seconds: 0.5,
+            max_seconds: 30.0,
+        }
+    }
+}
+
+/// Counts how long the output has stayed below the tail threshold.
+pub(crate) struct TailDetector {
+    threshold: f32,
+    hold: usize,
+    /// Consecutive silent frames so far
+    pub(crate) run: usize,
+}
+
+impl TailDetector {
+    pub(crate) fn new(options: &TailOptions, sample_rate: f64) -> Self {
+        Self {
+            threshold: db_to_amplitude(options.threshold_db),
+            hold: ((options.hold_seconds * sample_rate).round() as usize).max(1),
+            run: 0,
+        }
+    }
+
+    /// Feed one frame; true once the output has been silent long enough.
+    pub(crate) fn push(&mut self, frame: impl IntoIterator<Item = f32>) -> bool {
+        if frame.into_iter().any(|s| s.abs() > self.threshold) {
+            self.run = 0;
+        } else {
+            self.run += 1;
+        }
+        self.run >= self.hold
+    }
+}
+
+/// First audible frame and one past the last, or `None` if the whole
+/// export is below `threshold_db`.
+pub(crate) fn audible_bounds(channels: &[Vec<f32>], threshold_db: f64) -> Option<(usize, usize)> {
+    let threshold = db_to_amplitude(threshold_db);
+    let frames = channels.iter().map(Vec::len).min().unwrap_or(0);
+    let audible = |i: usize| channels.iter().any(|c| c[i].abs() > threshold);
+    let start = (0..frames).find(|&i| audible(i))?;
+    let end = (start..frames).rev().find(|&i| audible(i))? + 1;
+    Some((start, end))
+}
+
+fn db_to_amplitude(db: f64) -> f32 {
+    10f64.powf(db / 20.0) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tail_detector_needs_a_full_hold() {
+        let options = TailOptions {
+            threshold_db: -60.0,
+            hold_seconds: 0.003,
+            max_seconds: 1.0,
+        };
+        let mut detector = TailDetector::new(&options, 1000.0);
+        assert!(!detector.push([0.0, 0.0]));
+        assert!(!detector.push([0.0, 0.0]));
+        // A blip above -60 dB restarts the count
+        assert!(!detector.push([0.0, 0.01]));
+        assert!(!detector.push([0.0005, 0.0]));
+        assert!(!detector.push([0.0, 0.0]));
+        assert!(detector.push([0.0, 0.0]));
+        assert_eq!(detector.run, 3);
+    }
+
+    #[test]
+    fn test_audible_bounds() {
+        let channels = vec![
+            vec![0.0, 0.0, 0.5, 0.0, 0.0, 0.0],
+            vec![0.0, 0.0, 0.0, 0.0, 0.2, 0.0],
+        ];
+        assert_eq!(audible_bounds(&channels, -60.0), Some((2, 5)));
+        assert_eq!(audible_bounds(&channels, 0.0), None);
+    }
+}
@@ -0,0 +1,204 @@
+//! Stem taps for multi-output offline renders.
+//!
+//! A stem is captured by pushing a [`StemTap`] into the export net and wiring
+//! the stem node's outputs into it. The tap has no outputs, so the mix is
+//! untouched; it just records what flows past while the master renders.
+
+use crate::NormalizationMode;
+use std::collections::HashMap;
+use tutti_core::dsp::Net;
+use tutti_core::{AudioUnit, BufferMut, BufferRef, NodeId, SignalFrame, Source};
+
+/// A node (track, bus, instrument) to write to its own file alongside the
+/// master mix.
+///
+/// ```ignore
+/// engine.export()
+///     .duration_seconds(180.0)
+///     .stem(Stem::new(drums_bus, "Song - Drums"))
+///     .stem(Stem::new(vocal_bus, "Song - Vox").normalize(NormalizationMode::Peak(-1.0)))
+///     .to_file("mixes/Song.wav")?; // also writes mixes/Song - Drums.wav, ...
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stem {
+    pub node: NodeId,
+    /// File name without extension; written next to the master file.
+    pub name: String,
+    pub normalization: NormalizationMode,
+}
+
+impl Stem {
+    /// Stems are not normalized by default, so they still sum to the master.
+    pub fn new(node: NodeId, name: impl Into<String>) -> Self {
+        Self {
+            node,
+            name: name.into(),
+            normalization: NormalizationMode::None,
+        }
+    }
+
+    pub fn normalize(mut self, mode: NormalizationMode) -> Self {
+        self.normalization = mode;
+        self
+    }
+}
+
+/// Rendered audio for one stem, one buffer per output of the stem node (a
+/// mono node is doubled up when the master is stereo).
+#[derive(Debug, Clone)]
+pub struct StemAudio {
+    pub name: String,
+    pub channels: Vec<Vec<f32>>,
+}
+
+/// Records its inputs. No outputs, so adding one never changes the mix.
+#[derive(Clone)]
+pub(crate) struct StemTap {
+    channels: Vec<Vec<f32>>,
+}
+
+impl StemTap {
+    pub(crate) fn new(inputs: usize, capacity: usize) -> Self {
+        Self {
+            channels: (0..inputs).map(|_| Vec::with_capacity(capacity)).collect(),
+        }
+    }
+
+    pub(crate) fn take_channels(&mut self) -> Vec<Vec<f32>> {
+        std::mem::take(&mut self.channels)
+    }
+}
+
+impl AudioUnit for StemTap {
+    fn inputs(&self) -> usize {
+        self.channels.len()
+    }
+
+    fn outputs(&self) -> usize {
+        0
+    }
+
+    fn reset(&mut self) {
+        self.channels.iter_mut().for_each(Vec::clear);
+    }
+
+    fn set_sample_rate(&mut self, _sample_rate: f64) {}
+
+    fn tick(&mut self, input: &[f32], _output: &mut [f32]) {
+        for (channel, &sample) in self.channels.iter_mut().zip(input) {
+            channel.push(sample);
+        }
+    }
+
+    fn process(&mut self, size: usize, input: &BufferRef, _output: &mut BufferMut) {
+        for (ch, channel) in self.channels.iter_mut().enumerate() {
+            channel.extend((0..size).map(|i| input.at_f32(ch, i)));
+        }
+    }
+
+    fn get_id(&self) -> u64 {
+        0x5354454D_u64 // "STEM"
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn route(&mut self, _input: &SignalFrame, _frequency: f64) -> SignalFrame {
+        SignalFrame::new(0)
+    }
+
+    fn footprint(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self
+                .channels
+                .iter()
+                .map(|c| c.capacity() * std::mem::size_of::<f32>())
+                .sum::<usize>()
+    }
+}
+
+/// Latency (samples) along the slowest path up to and including `node`.
+///
+/// PDC delay nodes report no latency, so this is the uncompensated arrival
+/// time; the difference to [`master_path_latency`] is the compensation the
+/// node's signal picks up on its way to the master.
+pub(crate) fn path_latency(
+    net: &mut Net,
+    node: NodeId,
+    memo: &mut HashMap<NodeId, usize>,
+) -> usize {
+    if let Some(&latency) = memo.get(&node) {
+        return latency;
+    }
+
+    let upstream = (0..net.inputs_in(node))
+        .filter_map(|port| match net.source(node, port) {
+            Source::Local(src, _) => Some(src),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .map(|src| path_latency(net, src, memo))
+        .max()
+        .unwrap_or(0);
+    let own = net.node_mut(node).latency().unwrap_or(0.0).round() as usize;
+
+    memo.insert(node, upstream + own);
+    upstream + own
+}
+
+/// Latency along the slowest path to any of the net's outputs.
+pub(crate) fn master_path_latency(net: &mut Net, memo: &mut HashMap<NodeId, usize>) -> usize {
+    (0..net.outputs())
+        .filter_map(|ch| match net.output_source(ch) {
+            Source::Local(src, _) => Some(src),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .map(|src| path_latency(net, src, memo))
+        .max()
+        .unwrap_or(0)
+}
+
+/// `len` samples of `raw` starting at `skip`. A negative skip pads the front
+/// with silence, a short buffer is padded at the end.
+pub(crate) fn align(raw: &[f32], skip: isize, len: usize) -> Vec<f32> {
+    let mut out = Vec::with_capacity(len);
+    let pad = (-skip).max(0) as usize;
+    out.resize(pad.min(len), 0.0);
+    let start = skip.max(0) as usize;
+    if start < raw.len() {
+        let take = (len - out.len()).min(raw.len() - start);
+        out.extend_from_slice(&raw[start..start + take]);
+    }
+    out.resize(len, 0.0);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_align() {
+        let raw = [1.0, 2.0, 3.0, 4.0];
+        assert_eq!(align(&raw, 1, 2), vec![2.0, 3.0]);
+        assert_eq!(align(&raw, -2, 4), vec![0.0, 0.0, 1.0, 2.0]);
+        assert_eq!(align(&raw, 3, 3), vec![4.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_tap_records_without_output() {
+        let mut tap = StemTap::new(1, 8);
+        let mut out: [f32; 0] = [];
+        tap.tick(&[0.5], &mut out);
+        tap.tick(&[0.25], &mut out);
+        assert_eq!(tap.take_channels(), vec![vec![0.5, 0.25]]);
+    }
+}
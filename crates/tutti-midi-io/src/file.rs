@@ -1,18 +1,38 @@
 //! Standard MIDI File (SMF) parsing via `midly`, converting to `TimedMidiEvent` for playback.
+//!
+//! Besides the flattened channel events, a parse keeps the original track
+//! structure (names, text, sysex) and the complete tempo/meter map, which can
+//! be applied to a [`TransportManager`] in one call.
 
 use crate::error::{Error, Result};
-use midly::{MetaMessage, MidiMessage, Smf, Timing, Track, TrackEventKind};
+use midly::{Format, MetaMessage, MidiMessage, Smf, Timing, Track, TrackEventKind};
 use std::path::Path;
 use tracing::debug;
+use tutti_core::{TempoCurve, TempoMap, TempoMapSnapshot, TransportManager};
+
+/// Spacing of the tempo events that approximate a tempo ramp.
+const RAMP_STEP_BEATS: f64 = 1.0 / 16.0;
 
 /// A parsed MIDI file ready for streaming playback.
 #[derive(Debug, Clone)]
 pub struct ParsedMidiFile {
+    /// Channel events from all tracks, merged in time order. Format 2
+    /// patterns are laid end to end, each starting where the previous
+    /// track ends.
     pub events: Vec<TimedMidiEvent>,
     pub ticks_per_beat: u16,
-    /// Default tempo in BPM (from first tempo event, or 120 if none).
+    /// Initial tempo in BPM (from the first tempo event, or 120 if none).
     pub tempo_bpm: f64,
     pub duration_beats: f64,
+    pub format: MidiFileFormat,
+    /// Tracks as stored in the file, each timed from its own start.
+    pub tracks: Vec<MidiFileTrack>,
+    /// Tempo, time signature and key signature changes from all tracks, on
+    /// the same timeline as `events`.
+    pub tempo_map: MidiTempoMap,
+    /// Marker meta events from all tracks, in time order, on the same
+    /// timeline as `events`.
+    pub markers: Vec<TimedText>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -21,10 +41,12 @@ pub struct TimedMidiEvent {
     pub time_beats: f64,
     /// MIDI channel (0-15).
     pub channel: u8,
+    /// Index of the track this event came from.
+    pub track: usize,
     pub event: MidiEventType,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum MidiEventType {
     NoteOn {
         note: u8,
@@ -45,6 +67,199 @@ pub enum MidiEventType {
     PitchBend {
         value: i16,
     },
+    /// Polyphonic key pressure.
+    PolyAftertouch {
+        note: u8,
+        pressure: u8,
+    },
+    /// Channel pressure.
+    ChannelAftertouch {
+        pressure: u8,
+    },
+}
+
+/// SMF header format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MidiFileFormat {
+    /// Format 0: one multi-channel track.
+    SingleTrack,
+    /// Format 1: simultaneous tracks sharing one tempo map.
+    Parallel,
+    /// Format 2: independent sequences, each starting at beat 0 with its
+    /// own tempo map. Parsing plays them one after another.
+    Sequential,
+}
+
+/// One `MTrk` chunk.
+#[derive(Debug, Clone, Default)]
+pub struct MidiFileTrack {
+    pub name: Option<String>,
+    pub instrument: Option<String>,
+    /// Channel events in this track, in time order.
+    pub events: Vec<TimedMidiEvent>,
+    pub sysex: Vec<TimedSysEx>,
+    /// Text meta events other than the track and instrument name.
+    pub text: Vec<TimedText>,
+}
+
+/// A system exclusive message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimedSysEx {
+    pub time_beats: f64,
+    /// Bytes after the leading `F0`, including the closing `F7` when the
+    /// file stores one.
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextKind {
+    Text,
+    Copyright,
+    Lyric,
+    Marker,
+    CuePoint,
+    ProgramName,
+    DeviceName,
+}
+
+/// A text meta event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimedText {
+    pub time_beats: f64,
+    pub kind: TextKind,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MidiTempoChange {
+    pub time_beats: f64,
+    pub bpm: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MidiTimeSignature {
+    pub time_beats: f64,
+    pub numerator: u8,
+    /// Actual denominator (4 for x/4), not the SMF power of two.
+    pub denominator: u8,
+    /// MIDI clocks per metronome click.
+    pub clocks_per_click: u8,
+    pub thirty_seconds_per_quarter: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MidiKeySignature {
+    pub time_beats: f64,
+    /// Negative for flats, positive for sharps.
+    pub sharps: i8,
+    pub minor: bool,
+}
+
+/// Tempo and meter map of a MIDI file.
+#[derive(Debug, Clone, Default)]
+pub struct MidiTempoMap {
+    /// Tempo changes in time order; empty means the SMF default of 120 BPM.
+    pub tempos: Vec<MidiTempoChange>,
+    pub time_signatures: Vec<MidiTimeSignature>,
+    pub key_signatures: Vec<MidiKeySignature>,
+}
+
+impl MidiTempoMap {
+    pub fn initial_tempo(&self) -> f64 {
+        self.tempos
+            .first()
+            .filter(|t| t.time_beats <= 0.0)
+            .map(|t| t.bpm)
+            .unwrap_or(120.0)
+    }
+
+    /// Build a transport tempo map. Time signature changes that fall inside a
+    /// bar move to the next bar line.
+    pub fn to_tempo_map(&self, sample_rate: f64) -> TempoMap {
+        let mut map = TempoMap::new(self.initial_tempo() as f32, sample_rate);
+
+        for tempo in &self.tempos {
+            map.add_tempo_point(tempo.time_beats, tempo.bpm as f32);
+        }
+
+        let mut bar = 1u32;
+        let mut bar_beat = 0.0;
+        let mut beats_per_bar = 4.0;
+        for ts in &self.time_signatures {
+            if ts.numerator == 0 || ts.denominator == 0 {
+                continue;
+            }
+            let bars = ((ts.time_beats - bar_beat) / beats_per_bar - 1e-6)
+                .ceil()
+                .max(0.0);
+            bar += bars as u32;
+            bar_beat += bars * beats_per_bar;
+
+            map.add_meter_change(bar, ts.numerator as u32, ts.denominator as u32);
+            beats_per_bar = ts.numerator as f64 * 4.0 / ts.denominator as f64;
+        }
+
+        map
+    }
+
+    /// Convert a transport tempo map. SMF tempo events are steps, so ramps
+    /// become a staircase of 1/16-beat steps, each holding the average tempo
+    /// of its span so the staircase keeps the ramp's timing.
+    pub fn from_tempo_map(map: &TempoMapSnapshot) -> Self {
+        let points: Vec<_> = map.tempo_points().collect();
+        let mut tempos = Vec::with_capacity(points.len());
+
+        for (i, &(beat, bpm, curve)) in points.iter().enumerate() {
+            let ramp_end = points
+                .get(i + 1)
+                .filter(|next| curve != TempoCurve::Step && (next.1 - bpm).abs() > 1e-6)
+                .map(|next| next.0);
+            let Some(end) = ramp_end else {
+                tempos.push(MidiTempoChange {
+                    time_beats: beat,
+                    bpm: bpm as f64,
+                });
+                continue;
+            };
+
+            let steps = ((end - beat) / RAMP_STEP_BEATS).ceil().max(1.0) as usize;
+            for step in 0..steps {
+                let from = beat + step as f64 * RAMP_STEP_BEATS;
+                let to = (from + RAMP_STEP_BEATS).min(end);
+                let seconds = map.beats_to_seconds(to) - map.beats_to_seconds(from);
+                tempos.push(MidiTempoChange {
+                    time_beats: from,
+                    bpm: 60.0 * (to - from) / seconds,
+                });
+            }
+        }
+
+        let time_signatures = map
+            .meter_changes()
+            .iter()
+            .map(|meter| {
+                let ts = meter.time_signature();
+                MidiTimeSignature {
+                    time_beats: meter.beat(),
+                    numerator: ts.numerator.min(255) as u8,
+                    denominator: ts.denominator.min(128) as u8,
+                    clocks_per_click: (96 / ts.denominator.max(1)).clamp(1, 255) as u8,
+                    thirty_seconds_per_quarter: 8,
+                }
+            })
+            .collect();
+
+        Self {
+            tempos,
+            time_signatures,
+            key_signatures: Vec::new(),
+        }
+    }
+
+    /// Replace the transport's tempo and meter map with this one.
+    pub fn apply_to(&self, transport: &TransportManager) {
+        transport.set_tempo_map(self.to_tempo_map(transport.sample_rate()));
+    }
 }
 
 impl ParsedMidiFile {
@@ -63,6 +278,12 @@ impl ParsedMidiFile {
             }
         };
 
+        let format = match smf.header.format {
+            Format::SingleTrack => MidiFileFormat::SingleTrack,
+            Format::Parallel => MidiFileFormat::Parallel,
+            Format::Sequential => MidiFileFormat::Sequential,
+        };
+
         debug!(
             "Parsing MIDI file: {} tracks, {} ticks per beat",
             smf.tracks.len(),
@@ -70,21 +291,40 @@ impl ParsedMidiFile {
         );
 
         let mut all_events = Vec::new();
-        let mut tempo_bpm = 120.0; // Default tempo
-        let mut found_tempo = false;
-
-        for track in smf.tracks.iter() {
-            let track_events = Self::parse_track(track, ticks_per_beat);
-
-            if !found_tempo {
-                if let Some(tempo) = Self::extract_tempo(track) {
-                    tempo_bpm = tempo;
-                    found_tempo = true;
-                    debug!("Found tempo: {} BPM", tempo_bpm);
-                }
+        let mut tracks = Vec::with_capacity(smf.tracks.len());
+        let mut tempo_map = MidiTempoMap::default();
+        let mut markers = Vec::new();
+        // Where the current track starts on the merged timeline
+        let mut offset = 0.0;
+
+        for (index, track) in smf.tracks.iter().enumerate() {
+            let mut track_tempo_map = MidiTempoMap::default();
+            let (parsed, length_beats) =
+                Self::parse_track(track, index, ticks_per_beat, &mut track_tempo_map);
+            if format == MidiFileFormat::Sequential {
+                // Each pattern starts from the SMF defaults, not from the
+                // tempo and meter the previous one ended on
+                track_tempo_map.insert_defaults();
             }
-
-            all_events.extend(track_events);
+            tempo_map.append(track_tempo_map, offset);
+            all_events.extend(parsed.events.iter().map(|e| TimedMidiEvent {
+                time_beats: e.time_beats + offset,
+                ..*e
+            }));
+            markers.extend(
+                parsed
+                    .text
+                    .iter()
+                    .filter(|t| t.kind == TextKind::Marker)
+                    .map(|t| TimedText {
+                        time_beats: t.time_beats + offset,
+                        ..t.clone()
+                    }),
+            );
+            if format == MidiFileFormat::Sequential {
+                offset += length_beats;
+            }
+            tracks.push(parsed);
         }
 
         all_events.sort_by(|a, b| {
@@ -92,12 +332,16 @@ impl ParsedMidiFile {
                 .partial_cmp(&b.time_beats)
                 .expect("MIDI event time_beats should never be NaN")
         });
+        markers.sort_by(|a, b| a.time_beats.total_cmp(&b.time_beats));
+        tempo_map.sort();
 
+        let tempo_bpm = tempo_map.initial_tempo();
         let duration_beats = all_events.last().map(|e| e.time_beats).unwrap_or(0.0);
 
         debug!(
-            "Parsed {} MIDI events, duration: {:.2} beats",
+            "Parsed {} MIDI events, {} tempo changes, duration: {:.2} beats",
             all_events.len(),
+            tempo_map.tempos.len(),
             duration_beats
         );
 
@@ -106,23 +350,95 @@ impl ParsedMidiFile {
             ticks_per_beat,
             tempo_bpm,
             duration_beats,
+            format,
+            tracks,
+            tempo_map,
+            markers,
         })
     }
 
-    fn parse_track(track: &Track, ticks_per_beat: u16) -> Vec<TimedMidiEvent> {
-        let mut events = Vec::new();
+    /// Parse one track, returning it with its length (up to end of track).
+    fn parse_track(
+        track: &Track,
+        index: usize,
+        ticks_per_beat: u16,
+        tempo_map: &mut MidiTempoMap,
+    ) -> (MidiFileTrack, f64) {
+        let mut parsed = MidiFileTrack::default();
         let mut current_tick = 0u64;
 
         for event in track.iter() {
             current_tick += event.delta.as_int() as u64;
             let time_beats = current_tick as f64 / ticks_per_beat as f64;
 
-            if let Some(midi_event) = Self::convert_event(&event.kind, time_beats) {
-                events.push(midi_event);
+            match &event.kind {
+                TrackEventKind::Midi { .. } => {
+                    if let Some(mut midi_event) = Self::convert_event(&event.kind, time_beats) {
+                        midi_event.track = index;
+                        parsed.events.push(midi_event);
+                    }
+                }
+                TrackEventKind::SysEx(data) => parsed.sysex.push(TimedSysEx {
+                    time_beats,
+                    data: data.to_vec(),
+                }),
+                TrackEventKind::Escape(_) => {}
+                TrackEventKind::Meta(meta) => {
+                    Self::convert_meta(meta, time_beats, &mut parsed, tempo_map)
+                }
             }
         }
 
-        events
+        (parsed, current_tick as f64 / ticks_per_beat as f64)
+    }
+
+    fn convert_meta(
+        meta: &MetaMessage,
+        time_beats: f64,
+        track: &mut MidiFileTrack,
+        tempo_map: &mut MidiTempoMap,
+    ) {
+        let text = |bytes: &[u8]| String::from_utf8_lossy(bytes).into_owned();
+        let mut push_text = |kind, bytes: &[u8]| {
+            track.text.push(TimedText {
+                time_beats,
+                kind,
+                text: text(bytes),
+            })
+        };
+
+        match meta {
+            MetaMessage::Tempo(tempo) => tempo_map.tempos.push(MidiTempoChange {
+                time_beats,
+                bpm: 60_000_000.0 / tempo.as_int().max(1) as f64,
+            }),
+            MetaMessage::TimeSignature(numerator, denominator_pow, clocks, thirty_seconds) => {
+                tempo_map.time_signatures.push(MidiTimeSignature {
+                    time_beats,
+                    numerator: *numerator,
+                    denominator: 1u8.checked_shl(*denominator_pow as u32).unwrap_or(0),
+                    clocks_per_click: *clocks,
+                    thirty_seconds_per_quarter: *thirty_seconds,
+                })
+            }
+            MetaMessage::KeySignature(sharps, minor) => {
+                tempo_map.key_signatures.push(MidiKeySignature {
+                    time_beats,
+                    sharps: *sharps,
+                    minor: *minor,
+                })
+            }
+            MetaMessage::Text(bytes) => push_text(TextKind::Text, bytes),
+            MetaMessage::Copyright(bytes) => push_text(TextKind::Copyright, bytes),
+            MetaMessage::Lyric(bytes) => push_text(TextKind::Lyric, bytes),
+            MetaMessage::Marker(bytes) => push_text(TextKind::Marker, bytes),
+            MetaMessage::CuePoint(bytes) => push_text(TextKind::CuePoint, bytes),
+            MetaMessage::ProgramName(bytes) => push_text(TextKind::ProgramName, bytes),
+            MetaMessage::DeviceName(bytes) => push_text(TextKind::DeviceName, bytes),
+            MetaMessage::TrackName(bytes) => track.name = Some(text(bytes)),
+            MetaMessage::InstrumentName(bytes) => track.instrument = Some(text(bytes)),
+            _ => {}
+        }
     }
 
     fn convert_event(kind: &TrackEventKind, time_beats: f64) -> Option<TimedMidiEvent> {
@@ -157,12 +473,19 @@ impl ParsedMidiFile {
                     MidiMessage::PitchBend { bend } => MidiEventType::PitchBend {
                         value: bend.as_int(),
                     },
-                    _ => return None,
+                    MidiMessage::Aftertouch { key, vel } => MidiEventType::PolyAftertouch {
+                        note: key.as_int(),
+                        pressure: vel.as_int(),
+                    },
+                    MidiMessage::ChannelAftertouch { vel } => MidiEventType::ChannelAftertouch {
+                        pressure: vel.as_int(),
+                    },
                 };
 
                 Some(TimedMidiEvent {
                     time_beats,
                     channel: channel.as_int(),
+                    track: 0,
                     event: event_type,
                 })
             }
@@ -170,15 +493,9 @@ impl ParsedMidiFile {
         }
     }
 
-    fn extract_tempo(track: &Track) -> Option<f64> {
-        for event in track.iter() {
-            if let TrackEventKind::Meta(MetaMessage::Tempo(tempo)) = &event.kind {
-                let us_per_qn = tempo.as_int();
-                let bpm = 60_000_000.0 / us_per_qn as f64;
-                return Some(bpm);
-            }
-        }
-        None
+    /// Track by name (first match).
+    pub fn track_named(&self, name: &str) -> Option<&MidiFileTrack> {
+        self.tracks.iter().find(|t| t.name.as_deref() == Some(name))
     }
 
     pub fn get_events_in_range(&self, start_beats: f64, end_beats: f64) -> &[TimedMidiEvent] {
@@ -200,6 +517,75 @@ impl ParsedMidiFile {
     }
 }
 
+impl MidiTempoMap {
+    /// Add `other`'s events, moved `offset` beats later.
+    fn append(&mut self, other: MidiTempoMap, offset: f64) {
+        self.tempos
+            .extend(other.tempos.into_iter().map(|t| MidiTempoChange {
+                time_beats: t.time_beats + offset,
+                ..t
+            }));
+        self.time_signatures.extend(
+            other
+                .time_signatures
+                .into_iter()
+                .map(|t| MidiTimeSignature {
+                    time_beats: t.time_beats + offset,
+                    ..t
+                }),
+        );
+        self.key_signatures
+            .extend(other.key_signatures.into_iter().map(|k| MidiKeySignature {
+                time_beats: k.time_beats + offset,
+                ..k
+            }));
+    }
+
+    /// Start at 120 BPM in 4/4 unless the map sets its own tempo or meter at
+    /// beat 0.
+    fn insert_defaults(&mut self) {
+        if !self.tempos.iter().any(|t| t.time_beats <= 0.0) {
+            self.tempos.push(MidiTempoChange {
+                time_beats: 0.0,
+                bpm: 120.0,
+            });
+        }
+        if !self.time_signatures.iter().any(|t| t.time_beats <= 0.0) {
+            self.time_signatures.push(MidiTimeSignature {
+                time_beats: 0.0,
+                numerator: 4,
+                denominator: 4,
+                clocks_per_click: 24,
+                thirty_seconds_per_quarter: 8,
+            });
+        }
+    }
+
+    /// Order by time; at equal times the event stored last wins.
+    fn sort(&mut self) {
+        self.tempos
+            .sort_by(|a, b| a.time_beats.total_cmp(&b.time_beats));
+        self.tempos.dedup_by(|later, earlier| {
+            let same = later.time_beats == earlier.time_beats;
+            if same {
+                *earlier = *later;
+            }
+            same
+        });
+        self.time_signatures
+            .sort_by(|a, b| a.time_beats.total_cmp(&b.time_beats));
+        self.time_signatures.dedup_by(|later, earlier| {
+            let same = later.time_beats == earlier.time_beats;
+            if same {
+                *earlier = *later;
+            }
+            same
+        });
+        self.key_signatures
+            .sort_by(|a, b| a.time_beats.total_cmp(&b.time_beats));
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -248,4 +634,136 @@ mod tests {
             _ => panic!("Expected NoteOff"),
         }
     }
+
+    /// Format 1 file, 480 ticks per beat.
+    fn format1(tracks: &[&[u8]]) -> Vec<u8> {
+        smf(1, tracks)
+    }
+
+    fn smf(format: u16, tracks: &[&[u8]]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(b"MThd");
+        data.extend_from_slice(&6u32.to_be_bytes());
+        data.extend_from_slice(&format.to_be_bytes());
+        data.extend_from_slice(&(tracks.len() as u16).to_be_bytes());
+        data.extend_from_slice(&480u16.to_be_bytes());
+        for track in tracks {
+            data.extend_from_slice(b"MTrk");
+            data.extend_from_slice(&(track.len() as u32).to_be_bytes());
+            data.extend_from_slice(track);
+        }
+        data
+    }
+
+    fn film_cue() -> Vec<u8> {
+        let conductor: &[u8] = &[
+            0x00, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20, // 120 BPM
+            0x00, 0xFF, 0x58, 0x04, 0x04, 0x02, 0x18, 0x08, // 4/4
+            0x00, 0xFF, 0x59, 0x02, 0xFE, 0x00, // Bb major
+            0x8F, 0x00, 0xFF, 0x51, 0x03, 0x0F, 0x42, 0x40, // beat 4: 60 BPM
+            0x8F, 0x00, 0xFF, 0x58, 0x04, 0x07, 0x03, 0x0C, 0x08, // beat 8: 7/8
+            0x00, 0xFF, 0x06, 0x06, b'B', b'r', b'i', b'd', b'g', b'e', // marker
+            0x00, 0xFF, 0x2F, 0x00,
+        ];
+        let piano: &[u8] = &[
+            0x00, 0xFF, 0x03, 0x05, b'P', b'i', b'a', b'n', b'o', // track name
+            0x00, 0xF0, 0x05, 0x7E, 0x7F, 0x09, 0x01, 0xF7, // GM on
+            0x00, 0x91, 0x3C, 0x64, // note on
+            0x00, 0xA1, 0x3C, 0x50, // poly aftertouch
+            0x60, 0xD1, 0x40, // channel aftertouch
+            0x83, 0x60, 0x81, 0x3C, 0x00, // note off
+            0x00, 0xFF, 0x2F, 0x00,
+        ];
+        format1(&[conductor, piano])
+    }
+
+    #[test]
+    fn test_parse_keeps_tracks_and_meta() {
+        let file = ParsedMidiFile::parse(&film_cue()).unwrap();
+
+        assert_eq!(file.format, MidiFileFormat::Parallel);
+        assert_eq!(file.tracks.len(), 2);
+        assert!((file.tempo_bpm - 120.0).abs() < 1e-9);
+
+        let piano = file.track_named("Piano").unwrap();
+        assert_eq!(piano.events.len(), 4);
+        assert!(piano.events.iter().all(|e| e.track == 1 && e.channel == 1));
+        assert!(matches!(
+            piano.events[1].event,
+            MidiEventType::PolyAftertouch {
+                note: 60,
+                pressure: 80
+            }
+        ));
+        assert!(matches!(
+            piano.events[2].event,
+            MidiEventType::ChannelAftertouch { pressure: 64 }
+        ));
+        assert!(piano.sysex[0].data.starts_with(&[0x7E, 0x7F, 0x09, 0x01]));
+        assert_eq!(file.events.len(), 4);
+
+        assert_eq!(file.markers.len(), 1);
+        assert_eq!(file.markers[0].text, "Bridge");
+        assert!((file.markers[0].time_beats - 8.0).abs() < 1e-9);
+
+        let map = &file.tempo_map;
+        assert_eq!(map.tempos.len(), 2);
+        assert!((map.tempos[1].time_beats - 4.0).abs() < 1e-9);
+        assert!((map.tempos[1].bpm - 60.0).abs() < 1e-9);
+        assert_eq!(map.time_signatures.len(), 2);
+        assert_eq!(map.time_signatures[1].numerator, 7);
+        assert_eq!(map.time_signatures[1].denominator, 8);
+        assert_eq!(map.key_signatures[0].sharps, -2);
+        assert!(!map.key_signatures[0].minor);
+    }
+
+    #[test]
+    fn test_tempo_map_applies_to_transport() {
+        let file = ParsedMidiFile::parse(&film_cue()).unwrap();
+        let transport = TransportManager::default();
+        file.tempo_map.apply_to(&transport);
+
+        // 4 beats at 120 + 4 beats at 60
+        assert!((transport.beats_to_seconds(8.0) - 6.0).abs() < 1e-9);
+        assert!((transport.get_tempo() - 120.0).abs() < 1e-3);
+
+        let bbt = transport.beats_to_bbt(8.0);
+        assert_eq!((bbt.bar, bbt.beat), (3, 1));
+        let ts = transport.time_signature_at(8.5);
+        assert_eq!((ts.numerator, ts.denominator), (7, 8));
+    }
+
+    #[test]
+    fn test_format2_patterns_play_in_sequence() {
+        let verse: &[u8] = &[
+            0x00, 0xFF, 0x51, 0x03, 0x0F, 0x42, 0x40, // 60 BPM
+            0x00, 0x90, 0x3C, 0x64, // note on
+            0x87, 0x40, 0x80, 0x3C, 0x00, // beat 2: note off
+            0x87, 0x40, 0xFF, 0x2F, 0x00, // beat 4: end of track
+        ];
+        let chorus: &[u8] = &[
+            0x00, 0xFF, 0x06, 0x06, b'C', b'h', b'o', b'r', b'u', b's', // marker
+            0x00, 0x90, 0x40, 0x64, // note on
+            0x83, 0x60, 0x80, 0x40, 0x00, // beat 1: note off
+            0x00, 0xFF, 0x2F, 0x00,
+        ];
+        let file = ParsedMidiFile::parse(&smf(2, &[verse, chorus])).unwrap();
+
+        assert_eq!(file.format, MidiFileFormat::Sequential);
+        // Tracks keep their own timing
+        assert_eq!(file.tracks[1].events[0].time_beats, 0.0);
+        // The chorus follows the verse's end of track
+        let times: Vec<f64> = file.events.iter().map(|e| e.time_beats).collect();
+        assert_eq!(times, [0.0, 2.0, 4.0, 5.0]);
+        assert_eq!(file.events[2].track, 1);
+        assert!((file.markers[0].time_beats - 4.0).abs() < 1e-9);
+
+        // The chorus has no tempo of its own, so it's back at 120 BPM
+        let map = &file.tempo_map;
+        assert_eq!(map.tempos.len(), 2);
+        assert!((map.tempos[0].bpm - 60.0).abs() < 1e-9);
+        assert!((map.tempos[1].time_beats - 4.0).abs() < 1e-9);
+        assert!((map.tempos[1].bpm - 120.0).abs() < 1e-9);
+        assert_eq!(map.time_signatures.len(), 2);
+    }
 }
@@ -0,0 +1,547 @@
+//! Transport-driven playback of parsed MIDI files.
+//!
+//! [`MidiFilePlayer`] is installed on the system as a
+//! [`TransportMidiSource`]. The audio callback hands it every frame's
+//! transport position before rendering that frame, and the player queues the
+//! file's events for their target nodes exactly on the frame they fall on.
+//!
+//! Discontinuities are handled by position, not by transport state: a jump
+//! (locate, loop wrap, sync chase) releases sounding notes, resets
+//! controllers and continues from the new position; moving backwards
+//! (reverse, scrubbing) releases notes and plays nothing until the position
+//! moves forward again.
+//!
+//! ```ignore
+//! let file = ParsedMidiFile::load("song.mid")?;
+//! let player = MidiFilePlayer::builder(&file)
+//!     .channel(9, drums_id)
+//!     .track(1, piano_id)
+//!     .fallback(strings_id)
+//!     .build();
+//! system.set_transport_midi_source(Some(Arc::new(player)));
+//! ```
+
+use crate::file::{MidiEventType, ParsedMidiFile, TimedMidiEvent};
+use std::cell::UnsafeCell;
+use tutti_core::{MidiEvent, MidiRegistry, MidiSnapshot, TransportMidiSource};
+
+/// Largest per-frame advance treated as continuous playback. Bigger steps
+/// are locates.
+const MAX_STEP_BEATS: f64 = 1.0 / 64.0;
+
+const CC_SUSTAIN: u8 = 64;
+const CC_RESET_ALL_CONTROLLERS: u8 = 121;
+const PITCH_BEND_CENTER: u16 = 8192;
+
+/// Which file events go to a target node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RouteKey {
+    Channel(u8),
+    Track(usize),
+}
+
+/// Builder for [`MidiFilePlayer`].
+///
+/// Track routes take precedence over channel routes; events matching neither
+/// go to the fallback target, or are dropped without one.
+#[derive(Debug, Clone)]
+pub struct MidiFilePlayerBuilder {
+    events: Vec<TimedMidiEvent>,
+    routes: Vec<(RouteKey, u64)>,
+    fallback: Option<u64>,
+    start_beat: f64,
+}
+
+impl MidiFilePlayerBuilder {
+    /// Send events on `channel` (0-15) to `unit_id`.
+    pub fn channel(mut self, channel: u8, unit_id: u64) -> Self {
+        self.routes
+            .push((RouteKey::Channel(channel & 0x0F), unit_id));
+        self
+    }
+
+    /// Send events from track `track` (index into `ParsedMidiFile::tracks`)
+    /// to `unit_id`.
+    pub fn track(mut self, track: usize, unit_id: u64) -> Self {
+        self.routes.push((RouteKey::Track(track), unit_id));
+        self
+    }
+
+    pub fn fallback(mut self, unit_id: u64) -> Self {
+        self.fallback = Some(unit_id);
+        self
+    }
+
+    /// Transport beat at which the file's beat 0 plays. Default 0.
+    pub fn start_beat(mut self, beat: f64) -> Self {
+        self.start_beat = beat;
+        self
+    }
+
+    pub fn build(self) -> MidiFilePlayer {
+        let mut targets: Vec<u64> = Vec::new();
+        let mut target_index = |unit_id: u64| match targets.iter().position(|&t| t == unit_id) {
+            Some(index) => index,
+            None => {
+                targets.push(unit_id);
+                targets.len() - 1
+            }
+        };
+
+        let mut events = Vec::with_capacity(self.events.len());
+        for event in &self.events {
+            let routed_to = |key: RouteKey| {
+                self.routes
+                    .iter()
+                    .filter(move |(k, _)| *k == key)
+                    .map(|&(_, unit_id)| unit_id)
+            };
+            let mut units: Vec<u64> = routed_to(RouteKey::Track(event.track)).collect();
+            if units.is_empty() {
+                units.extend(routed_to(RouteKey::Channel(event.channel)));
+            }
+            if units.is_empty() {
+                units.extend(self.fallback);
+            }
+
+            for unit_id in units {
+                events.push(ScheduledEvent {
+                    beat: event.time_beats,
+                    target: target_index(unit_id),
+                    event: to_midi_event(event),
+                });
+            }
+        }
+
+        let state = PlayerState {
+            cursor: 0,
+            last_beat: None,
+            step: 0.0,
+            was_playing: false,
+            sounding: vec![[0; 16]; targets.len()],
+            touched: vec![0; targets.len()],
+        };
+
+        MidiFilePlayer {
+            events,
+            targets,
+            start_beat: self.start_beat,
+            state: UnsafeCell::new(state),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ScheduledEvent {
+    /// File beat.
+    beat: f64,
+    /// Index into `MidiFilePlayer::targets`.
+    target: usize,
+    event: MidiEvent,
+}
+
+/// Audio-thread state.
+struct PlayerState {
+    /// Next event to play.
+    cursor: usize,
+    /// File beat of the previous frame.
+    last_beat: Option<f64>,
+    /// Advance per frame during continuous playback.
+    step: f64,
+    was_playing: bool,
+    /// Sounding notes per target, one bit per note per channel.
+    sounding: Vec<[u128; 16]>,
+    /// Channels per target whose controllers moved since the last reset.
+    touched: Vec<u16>,
+}
+
+/// Plays a [`ParsedMidiFile`] into graph nodes, following the transport.
+///
+/// Targets are graph node IDs; nodes register with the MIDI registry when
+/// they are added, so the player never allocates on the audio thread.
+pub struct MidiFilePlayer {
+    /// Routed events in file-beat order.
+    events: Vec<ScheduledEvent>,
+    targets: Vec<u64>,
+    start_beat: f64,
+    state: UnsafeCell<PlayerState>,
+}
+
+// SAFETY: `state` is only touched from `process_frame`, which the audio
+// callback calls from a single thread.
+unsafe impl Sync for MidiFilePlayer {}
+
+impl MidiFilePlayer {
+    pub fn builder(file: &ParsedMidiFile) -> MidiFilePlayerBuilder {
+        MidiFilePlayerBuilder {
+            events: file.events.clone(),
+            routes: Vec::new(),
+            fallback: None,
+            start_beat: 0.0,
+        }
+    }
+
+    /// Target units, in first-use order.
+    pub fn targets(&self) -> &[u64] {
+        &self.targets
+    }
+
+    pub fn start_beat(&self) -> f64 {
+        self.start_beat
+    }
+
+    /// Number of routed events (an event routed to two targets counts twice).
+    pub fn event_count(&self) -> usize {
+        self.events.len()
+    }
+
+    fn send(&self, state: &mut PlayerState, scheduled: &ScheduledEvent, registry: &MidiRegistry) {
+        let event = scheduled.event;
+        let channel = event.channel_num() as usize;
+        let notes = &mut state.sounding[scheduled.target][channel];
+
+        if let Some(note) = event.note() {
+            let bit = 1u128 << (note & 0x7F);
+            if event.is_note_on() {
+                *notes |= bit;
+            } else if event.is_note_off() {
+                *notes &= !bit;
+            }
+        } else if !matches!(event.msg, tutti_midi::ChannelVoiceMsg::ProgramChange { .. }) {
+            state.touched[scheduled.target] |= 1 << channel;
+        }
+
+        registry.queue(self.targets[scheduled.target], &[event]);
+    }
+
+    /// Release sounding notes and reset controllers that moved.
+    fn silence(&self, state: &mut PlayerState, registry: &MidiRegistry) {
+        for (target, &unit_id) in self.targets.iter().enumerate() {
+            for channel in 0..16u8 {
+                let notes = &mut state.sounding[target][channel as usize];
+                while *notes != 0 {
+                    let note = notes.trailing_zeros() as u8;
+                    *notes &= !(1u128 << note);
+                    registry.queue(unit_id, &[MidiEvent::note_off(0, channel, note, 0)]);
+                }
+
+                if state.touched[target] & (1 << channel) != 0 {
+                    registry.queue(
+                        unit_id,
+                        &[
+                            MidiEvent::control_change(0, channel, CC_SUSTAIN, 0),
+                            MidiEvent::pitch_bend(0, channel, PITCH_BEND_CENTER),
+                            MidiEvent::control_change(0, channel, CC_RESET_ALL_CONTROLLERS, 0),
+                        ],
+                    );
+                }
+            }
+            state.touched[target] = 0;
+        }
+    }
+
+    /// Index of the first event at or after `beat`.
+    fn seek_index(&self, beat: f64) -> usize {
+        self.events.partition_point(|e| e.beat < beat)
+    }
+}
+
+impl TransportMidiSource for MidiFilePlayer {
+    fn process_frame(&self, beat: f64, playing: bool, registry: &MidiRegistry) {
+        // SAFETY: single caller (audio thread), see `unsafe impl Sync`.
+        let state = unsafe { &mut *self.state.get() };
+        let beat = beat - self.start_beat;
+
+        if !playing {
+            if state.was_playing {
+                self.silence(state, registry);
+                state.was_playing = false;
+            }
+            return;
+        }
+        state.was_playing = true;
+
+        match state.last_beat {
+            None => state.cursor = self.seek_index(beat),
+            Some(last) => {
+                let delta = beat - last;
+                if (0.0..=MAX_STEP_BEATS).contains(&delta) {
+                    if delta > 0.0 {
+                        state.step = delta;
+                    }
+                } else if delta < 0.0 && delta >= -MAX_STEP_BEATS {
+                    // Reverse or scrub: nothing plays backwards, and events at
+                    // this position count as already passed
+                    self.silence(state, registry);
+                    state.cursor = self.events.partition_point(|e| e.beat <= beat);
+                } else {
+                    // Locate or loop wrap. The frame landed up to one step past
+                    // the target, so play what that step skipped
+                    self.silence(state, registry);
+                    state.cursor = self.seek_index(beat - state.step);
+                }
+            }
+        }
+        state.last_beat = Some(beat);
+
+        while let Some(scheduled) = self.events.get(state.cursor) {
+            if scheduled.beat > beat {
+                break;
+            }
+            self.send(state, scheduled, registry);
+            state.cursor += 1;
+        }
+    }
+
+    fn export_to(&self, snapshot: &mut MidiSnapshot) {
+        for scheduled in &self.events {
+            snapshot.add_event(
+                self.targets[scheduled.target],
+                scheduled.beat + self.start_beat,
+                scheduled.event,
+            );
+        }
+    }
+}
+
+fn to_midi_event(event: &TimedMidiEvent) -> MidiEvent {
+    let channel = event.channel;
+    match event.event {
+        MidiEventType::NoteOn { note, velocity } => MidiEvent::note_on(0, channel, note, velocity),
+        MidiEventType::NoteOff { note, velocity } => {
+            MidiEvent::note_off(0, channel, note, velocity)
+        }
+        MidiEventType::ControlChange { controller, value } => {
+            MidiEvent::control_change(0, channel, controller, value)
+        }
+        MidiEventType::ProgramChange { program } => {
+            MidiEvent::program_builder(program).channel(channel).build()
+        }
+        MidiEventType::PitchBend { value } => {
+            MidiEvent::pitch_bend(0, channel, (value as i32 + 8192).clamp(0, 16383) as u16)
+        }
+        MidiEventType::PolyAftertouch { note, pressure } => {
+            MidiEvent::poly_aftertouch(0, channel, note, pressure)
+        }
+        MidiEventType::ChannelAftertouch { pressure } => {
+            MidiEvent::aftertouch(0, channel, pressure)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_writer::MidiFileWriter;
+    use tutti_midi::ChannelVoiceMsg;
+
+    const PIANO: u64 = 10;
+    const DRUMS: u64 = 20;
+    const PAD: u64 = 30;
+
+    /// Beats per frame; a power of two keeps frame positions exact.
+    const STEP: f64 = 1.0 / 32768.0;
+
+    fn note(time_beats: f64, channel: u8, note: u8, on: bool) -> TimedMidiEvent {
+        let event = if on {
+            MidiEventType::NoteOn {
+                note,
+                velocity: 100,
+            }
+        } else {
+            MidiEventType::NoteOff { note, velocity: 0 }
+        };
+        TimedMidiEvent {
+            time_beats,
+            channel,
+            track: 0,
+            event,
+        }
+    }
+
+    /// Track 1: piano on channel 0. Track 2: drums on channel 9 plus a pad
+    /// note on channel 3.
+    fn song() -> ParsedMidiFile {
+        let piano = [
+            note(0.0, 0, 60, true),
+            note(1.0, 0, 60, false),
+            note(1.0, 0, 64, true),
+            note(3.0, 0, 64, false),
+        ];
+        let mut drums = vec![note(0.5, 9, 36, true), note(0.75, 9, 36, false)];
+        drums.push(note(0.5, 3, 48, true));
+        drums.push(TimedMidiEvent {
+            time_beats: 0.5,
+            channel: 3,
+            track: 0,
+            event: MidiEventType::ControlChange {
+                controller: 1,
+                value: 90,
+            },
+        });
+        let data = MidiFileWriter::new()
+            .events("Piano", &piano)
+            .events("Drums", &drums)
+            .to_bytes()
+            .unwrap();
+        ParsedMidiFile::parse(&data).unwrap()
+    }
+
+    fn registry() -> MidiRegistry {
+        let registry = MidiRegistry::new();
+        for unit in [PIANO, DRUMS, PAD] {
+            registry.register_unit(unit);
+        }
+        registry
+    }
+
+    fn drain(registry: &MidiRegistry, unit: u64) -> Vec<MidiEvent> {
+        let mut buffer = [MidiEvent::note_on(0, 0, 0, 0); 64];
+        let count = registry.poll_into(unit, &mut buffer);
+        buffer[..count].to_vec()
+    }
+
+    /// Play frames from `from` for `frames` frames; returns the frame index at
+    /// which each event reached `unit`.
+    fn play(
+        player: &MidiFilePlayer,
+        registry: &MidiRegistry,
+        unit: u64,
+        from: f64,
+        frames: usize,
+    ) -> Vec<(usize, MidiEvent)> {
+        let mut received = Vec::new();
+        for frame in 0..frames {
+            player.process_frame(from + frame as f64 * STEP, true, registry);
+            received.extend(drain(registry, unit).into_iter().map(|e| (frame, e)));
+        }
+        received
+    }
+
+    fn player() -> MidiFilePlayer {
+        MidiFilePlayer::builder(&song())
+            .track(1, PIANO)
+            .channel(9, DRUMS)
+            .fallback(PAD)
+            .build()
+    }
+
+    #[test]
+    fn test_events_land_on_their_frame() {
+        let player = player();
+        let registry = registry();
+
+        let piano = play(&player, &registry, PIANO, 0.0, 40000);
+        assert_eq!(piano.len(), 3);
+        assert_eq!(piano[0].0, 0);
+        assert_eq!(piano[1].0, 32768);
+        assert!(piano[1].1.is_note_off() && piano[2].1.is_note_on());
+        assert_eq!(piano[2].0, 32768);
+
+        assert_eq!(drain(&registry, DRUMS).len(), 2);
+        assert_eq!(player.targets(), &[PIANO, DRUMS, PAD]);
+    }
+
+    #[test]
+    fn test_routing_precedence() {
+        let player = player();
+        let registry = registry();
+
+        let mut per_unit = [Vec::new(), Vec::new(), Vec::new()];
+        for frame in 0..30000 {
+            player.process_frame(frame as f64 * STEP, true, &registry);
+            for (i, unit) in [PIANO, DRUMS, PAD].into_iter().enumerate() {
+                per_unit[i].extend(drain(&registry, unit));
+            }
+        }
+
+        // Track 1 wins over the fallback; channel 9 goes to the drums; the
+        // channel 3 events on track 2 have no route and fall back
+        assert!(per_unit[0].iter().all(|e| e.channel_num() == 0));
+        assert_eq!(per_unit[1].len(), 2);
+        assert!(per_unit[1].iter().all(|e| e.channel_num() == 9));
+        assert_eq!(per_unit[2].len(), 2);
+        assert!(per_unit[2].iter().all(|e| e.channel_num() == 3));
+    }
+
+    #[test]
+    fn test_stop_releases_notes_and_resets_controllers() {
+        let player = player();
+        let registry = registry();
+
+        play(&player, &registry, PAD, 0.0, 25000);
+        player.process_frame(25000.0 * STEP, false, &registry);
+        assert!(drain(&registry, PIANO).iter().any(|e| e.is_note_off()));
+
+        let released = drain(&registry, PAD);
+        assert!(released
+            .iter()
+            .any(|e| e.is_note_off() && e.note() == Some(48)));
+        assert!(released.iter().any(|e| matches!(
+            e.msg,
+            ChannelVoiceMsg::ControlChange {
+                control: tutti_midi::ControlChange::CC { control: 121, .. }
+            }
+        )));
+        assert!(released.iter().all(|e| e.channel_num() == 3));
+
+        // Stopping again sends nothing new
+        player.process_frame(25000.0 * STEP, false, &registry);
+        assert!(drain(&registry, PAD).is_empty());
+    }
+
+    #[test]
+    fn test_loop_wrap_retriggers_from_loop_start() {
+        let player = player();
+        let registry = registry();
+
+        // Play into the second piano note, then wrap back to beat 0
+        play(&player, &registry, PIANO, 0.0, 36000);
+        assert_eq!(player.start_beat(), 0.0);
+        let wrapped = play(&player, &registry, PIANO, 0.5 * STEP, 10);
+
+        assert_eq!(wrapped.len(), 2);
+        assert_eq!(wrapped[0].0, 0);
+        assert!(wrapped[0].1.is_note_off() && wrapped[0].1.note() == Some(64));
+        assert!(wrapped[1].1.is_note_on() && wrapped[1].1.note() == Some(60));
+    }
+
+    #[test]
+    fn test_reverse_plays_nothing() {
+        let player = player();
+        let registry = registry();
+
+        play(&player, &registry, PIANO, 0.99, 1000);
+        let end = 0.99 + 999.0 * STEP;
+
+        // Moving back over beat 1.0 only releases the note that was playing
+        let mut received = Vec::new();
+        for frame in 1..=1000 {
+            player.process_frame(end - frame as f64 * 2.0 * STEP, true, &registry);
+            received.extend(drain(&registry, PIANO));
+        }
+        assert_eq!(received.len(), 1);
+        assert!(received[0].is_note_off() && received[0].note() == Some(64));
+
+        // Forward again from just before beat 1.0 plays it once
+        let forward = play(&player, &registry, PIANO, 1.0 - 10.0 * STEP, 20);
+        assert_eq!(forward.len(), 2);
+        assert_eq!(forward[0].0, 10);
+    }
+
+    #[test]
+    fn test_export_offsets_by_start_beat() {
+        let player = MidiFilePlayer::builder(&song())
+            .track(1, PIANO)
+            .start_beat(8.0)
+            .build();
+        let mut snapshot = MidiSnapshot::new();
+        player.export_to(&mut snapshot);
+
+        let events = snapshot.events(PIANO);
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[0].beat, 8.0);
+        assert_eq!(events[3].beat, 11.0);
+        assert!(!snapshot.has_events(PAD));
+    }
+}
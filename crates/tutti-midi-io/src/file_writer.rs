@@ -0,0 +1,597 @@
+//! Standard MIDI File (SMF) writing.
+//!
+//! [`MidiFileWriter`] serialises tracks of [`TimedMidiEvent`]s, the contents of
+//! a [`MidiSnapshot`] or recorded takes (via their beat-timed events) into a
+//! format 0 or format 1 file. The tempo and meter map goes into a conductor
+//! track, so the file plays back at the tempo it was recorded at.
+//!
+//! ```ignore
+//! let writer = MidiFileWriter::new()
+//!     .ppq(960)
+//!     .transport_tempo_map(&transport.tempo_map_snapshot())
+//!     .beat_events("Take 1", &take.to_timed_midi_events());
+//! writer.save("take1.mid")?;
+//! ```
+
+use crate::error::{Error, Result};
+use crate::file::{
+    MidiEventType, MidiFileFormat, MidiFileTrack, MidiTempoMap, TextKind, TimedMidiEvent, TimedText,
+};
+use std::io::Write;
+use std::path::Path;
+use tutti_core::{MidiSnapshot, TempoMapSnapshot};
+
+/// Builder for Standard MIDI Files.
+///
+/// Defaults to format 1 at 480 ticks per quarter note with no tempo map,
+/// which readers take as 120 BPM in 4/4.
+#[derive(Debug, Clone)]
+pub struct MidiFileWriter {
+    format: MidiFileFormat,
+    ppq: u16,
+    tempo_map: MidiTempoMap,
+    markers: Vec<TimedText>,
+    tracks: Vec<MidiFileTrack>,
+}
+
+impl Default for MidiFileWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MidiFileWriter {
+    pub fn new() -> Self {
+        Self {
+            format: MidiFileFormat::Parallel,
+            ppq: 480,
+            tempo_map: MidiTempoMap::default(),
+            markers: Vec::new(),
+            tracks: Vec::new(),
+        }
+    }
+
+    /// Format 0 merges every track into one; format 1 writes a conductor
+    /// track followed by one track per added track. Format 2 is rejected
+    /// when writing.
+    pub fn format(mut self, format: MidiFileFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Ticks per quarter note (1-32767). Event times are rounded to the
+    /// nearest tick.
+    pub fn ppq(mut self, ppq: u16) -> Self {
+        self.ppq = ppq;
+        self
+    }
+
+    pub fn tempo_map(mut self, tempo_map: MidiTempoMap) -> Self {
+        self.tempo_map = tempo_map;
+        self
+    }
+
+    /// Use the transport's tempo and meter map. See
+    /// [`MidiTempoMap::from_tempo_map`] for how ramps are written.
+    pub fn transport_tempo_map(self, tempo_map: &TempoMapSnapshot) -> Self {
+        self.tempo_map(MidiTempoMap::from_tempo_map(tempo_map))
+    }
+
+    pub fn marker(mut self, time_beats: f64, text: impl Into<String>) -> Self {
+        self.markers.push(TimedText {
+            time_beats,
+            kind: TextKind::Marker,
+            text: text.into(),
+        });
+        self
+    }
+
+    pub fn track(mut self, track: MidiFileTrack) -> Self {
+        self.tracks.push(track);
+        self
+    }
+
+    /// Add a named track holding `events`. The events' `track` field is
+    /// ignored; the track's position in the file decides it.
+    pub fn events(self, name: impl Into<String>, events: &[TimedMidiEvent]) -> Self {
+        self.track(MidiFileTrack {
+            name: Some(name.into()),
+            events: events.to_vec(),
+            ..Default::default()
+        })
+    }
+
+    /// Add a named track from beat-timed channel messages, such as a
+    /// recorded take or one unit of a [`MidiSnapshot`].
+    pub fn beat_events(
+        self,
+        name: impl Into<String>,
+        events: &[tutti_core::TimedMidiEvent],
+    ) -> Self {
+        self.track(MidiFileTrack {
+            name: Some(name.into()),
+            events: events.iter().flat_map(convert_beat_event).collect(),
+            ..Default::default()
+        })
+    }
+
+    /// Add one track per unit in `snapshot`, in ascending unit ID order,
+    /// named after the unit ID.
+    pub fn snapshot(mut self, snapshot: &MidiSnapshot) -> Self {
+        let mut unit_ids: Vec<u64> = snapshot.unit_ids().collect();
+        unit_ids.sort_unstable();
+        for unit_id in unit_ids {
+            self = self.beat_events(format!("Unit {unit_id}"), snapshot.events(unit_id));
+        }
+        self
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        self.write(&mut data)?;
+        Ok(data)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let file = std::fs::File::create(path.as_ref())?;
+        let mut writer = std::io::BufWriter::new(file);
+        self.write(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn write(&self, mut writer: impl Write) -> Result<()> {
+        if self.ppq == 0 || self.ppq > 0x7FFF {
+            return Err(Error::InvalidConfig(format!(
+                "PPQ must be between 1 and 32767, got {}",
+                self.ppq
+            )));
+        }
+
+        let chunks = match self.format {
+            MidiFileFormat::SingleTrack => {
+                let mut chunk = TrackChunk::default();
+                if let Some(name) = self.tracks.iter().find_map(|t| t.name.as_deref()) {
+                    chunk.meta(0.0, 0x03, name.as_bytes());
+                }
+                self.write_conductor(&mut chunk);
+                for track in &self.tracks {
+                    self.write_track(&mut chunk, track, false);
+                }
+                vec![chunk]
+            }
+            MidiFileFormat::Parallel => {
+                let mut conductor = TrackChunk::default();
+                self.write_conductor(&mut conductor);
+                let mut chunks = vec![conductor];
+                for track in &self.tracks {
+                    let mut chunk = TrackChunk::default();
+                    self.write_track(&mut chunk, track, true);
+                    chunks.push(chunk);
+                }
+                chunks
+            }
+            MidiFileFormat::Sequential => {
+                return Err(Error::InvalidConfig(
+                    "writing format 2 MIDI files is not supported".into(),
+                ));
+            }
+        };
+
+        let format: u16 = match self.format {
+            MidiFileFormat::SingleTrack => 0,
+            _ => 1,
+        };
+        writer.write_all(b"MThd")?;
+        writer.write_all(&6u32.to_be_bytes())?;
+        writer.write_all(&format.to_be_bytes())?;
+        writer.write_all(&(chunks.len() as u16).to_be_bytes())?;
+        writer.write_all(&self.ppq.to_be_bytes())?;
+
+        for chunk in chunks {
+            let data = chunk.finish(self.ppq);
+            writer.write_all(b"MTrk")?;
+            writer.write_all(&(data.len() as u32).to_be_bytes())?;
+            writer.write_all(&data)?;
+        }
+        Ok(())
+    }
+
+    fn write_conductor(&self, chunk: &mut TrackChunk) {
+        for tempo in &self.tempo_map.tempos {
+            let micros = (60_000_000.0 / tempo.bpm.max(1e-3))
+                .round()
+                .clamp(1.0, 16_777_215.0) as u32;
+            chunk.meta(tempo.time_beats, 0x51, &micros.to_be_bytes()[1..]);
+        }
+        for ts in &self.tempo_map.time_signatures {
+            // SMF stores the denominator as a power of two
+            if !ts.denominator.is_power_of_two() {
+                continue;
+            }
+            chunk.meta(
+                ts.time_beats,
+                0x58,
+                &[
+                    ts.numerator,
+                    ts.denominator.trailing_zeros() as u8,
+                    ts.clocks_per_click,
+                    ts.thirty_seconds_per_quarter,
+                ],
+            );
+        }
+        for key in &self.tempo_map.key_signatures {
+            chunk.meta(key.time_beats, 0x59, &[key.sharps as u8, key.minor as u8]);
+        }
+        for marker in &self.markers {
+            chunk.meta(marker.time_beats, 0x06, marker.text.as_bytes());
+        }
+    }
+
+    fn write_track(&self, chunk: &mut TrackChunk, track: &MidiFileTrack, with_name: bool) {
+        if with_name {
+            if let Some(name) = &track.name {
+                chunk.meta(0.0, 0x03, name.as_bytes());
+            }
+        }
+        if let Some(instrument) = &track.instrument {
+            chunk.meta(0.0, 0x04, instrument.as_bytes());
+        }
+        for text in &track.text {
+            chunk.meta(
+                text.time_beats,
+                text_meta_type(text.kind),
+                text.text.as_bytes(),
+            );
+        }
+        for sysex in &track.sysex {
+            let mut bytes = vec![0xF0];
+            write_vlq(&mut bytes, sysex.data.len() as u32);
+            bytes.extend_from_slice(&sysex.data);
+            chunk.push(sysex.time_beats, bytes);
+        }
+        for event in &track.events {
+            chunk.push(
+                event.time_beats,
+                channel_message(event.channel, &event.event),
+            );
+        }
+    }
+}
+
+/// Events of one `MTrk` chunk before delta-time encoding.
+#[derive(Default)]
+struct TrackChunk {
+    events: Vec<(f64, Vec<u8>)>,
+}
+
+impl TrackChunk {
+    fn push(&mut self, time_beats: f64, bytes: Vec<u8>) {
+        self.events.push((time_beats, bytes));
+    }
+
+    fn meta(&mut self, time_beats: f64, kind: u8, payload: &[u8]) {
+        let mut bytes = vec![0xFF, kind];
+        write_vlq(&mut bytes, payload.len() as u32);
+        bytes.extend_from_slice(payload);
+        self.push(time_beats, bytes);
+    }
+
+    /// Encode with delta times. Events are ordered by tick; at equal ticks
+    /// they keep the order they were added in (meta events first).
+    fn finish(self, ppq: u16) -> Vec<u8> {
+        let mut events: Vec<(u64, Vec<u8>)> = self
+            .events
+            .into_iter()
+            .map(|(beats, bytes)| ((beats.max(0.0) * ppq as f64).round() as u64, bytes))
+            .collect();
+        events.sort_by_key(|(tick, _)| *tick);
+
+        let mut data = Vec::new();
+        let mut last_tick = 0;
+        for (tick, bytes) in events {
+            write_vlq(&mut data, (tick - last_tick).min(0x0FFF_FFFF) as u32);
+            data.extend_from_slice(&bytes);
+            last_tick = tick;
+        }
+        data.extend_from_slice(&[0x00, 0xFF, 0x2F, 0x00]);
+        data
+    }
+}
+
+fn write_vlq(out: &mut Vec<u8>, mut value: u32) {
+    let mut buf = [0u8; 5];
+    let mut i = buf.len() - 1;
+    buf[i] = (value & 0x7F) as u8;
+    value >>= 7;
+    while value > 0 {
+        i -= 1;
+        buf[i] = (value & 0x7F) as u8 | 0x80;
+        value >>= 7;
+    }
+    out.extend_from_slice(&buf[i..]);
+}
+
+fn text_meta_type(kind: TextKind) -> u8 {
+    match kind {
+        TextKind::Text => 0x01,
+        TextKind::Copyright => 0x02,
+        TextKind::Lyric => 0x05,
+        TextKind::Marker => 0x06,
+        TextKind::CuePoint => 0x07,
+        TextKind::ProgramName => 0x08,
+        TextKind::DeviceName => 0x09,
+    }
+}
+
+fn channel_message(channel: u8, event: &MidiEventType) -> Vec<u8> {
+    let ch = channel & 0x0F;
+    match *event {
+        MidiEventType::NoteOn { note, velocity } => vec![0x90 | ch, note & 0x7F, velocity & 0x7F],
+        MidiEventType::NoteOff { note, velocity } => {
+            vec![0x80 | ch, note & 0x7F, velocity & 0x7F]
+        }
+        MidiEventType::PolyAftertouch { note, pressure } => {
+            vec![0xA0 | ch, note & 0x7F, pressure & 0x7F]
+        }
+        MidiEventType::ControlChange { controller, value } => {
+            vec![0xB0 | ch, controller & 0x7F, value & 0x7F]
+        }
+        MidiEventType::ProgramChange { program } => vec![0xC0 | ch, program & 0x7F],
+        MidiEventType::ChannelAftertouch { pressure } => vec![0xD0 | ch, pressure & 0x7F],
+        MidiEventType::PitchBend { value } => {
+            let raw = (value as i32 + 8192).clamp(0, 16383) as u16;
+            vec![0xE0 | ch, (raw & 0x7F) as u8, (raw >> 7) as u8]
+        }
+    }
+}
+
+/// Split a channel voice message into its MIDI 1.0 messages (a 14-bit
+/// controller, for example, becomes two).
+fn convert_beat_event(event: &tutti_core::TimedMidiEvent) -> Vec<TimedMidiEvent> {
+    let bytes = event.event.to_bytes();
+    let mut events = Vec::new();
+    let mut status = 0u8;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] & 0x80 != 0 {
+            status = bytes[i];
+            i += 1;
+        }
+        let len = match status & 0xF0 {
+            0xC0 | 0xD0 => 1,
+            0x80..=0xE0 => 2,
+            _ => break,
+        };
+        if i + len > bytes.len() {
+            break;
+        }
+        let (d1, d2) = (bytes[i], if len == 2 { bytes[i + 1] } else { 0 });
+        i += len;
+
+        let kind = match status & 0xF0 {
+            0x90 if d2 > 0 => MidiEventType::NoteOn {
+                note: d1,
+                velocity: d2,
+            },
+            0x80 | 0x90 => MidiEventType::NoteOff {
+                note: d1,
+                velocity: d2,
+            },
+            0xA0 => MidiEventType::PolyAftertouch {
+                note: d1,
+                pressure: d2,
+            },
+            0xB0 => MidiEventType::ControlChange {
+                controller: d1,
+                value: d2,
+            },
+            0xC0 => MidiEventType::ProgramChange { program: d1 },
+            0xD0 => MidiEventType::ChannelAftertouch { pressure: d1 },
+            _ => MidiEventType::PitchBend {
+                value: ((((d2 as u16) << 7) | d1 as u16) as i32 - 8192) as i16,
+            },
+        };
+        events.push(TimedMidiEvent {
+            time_beats: event.beat,
+            channel: status & 0x0F,
+            track: 0,
+            event: kind,
+        });
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::{MidiTempoChange, MidiTimeSignature, ParsedMidiFile};
+    use tutti_core::{MidiEvent, TempoCurve, TempoMap};
+
+    fn event(time_beats: f64, channel: u8, event: MidiEventType) -> TimedMidiEvent {
+        TimedMidiEvent {
+            time_beats,
+            channel,
+            track: 0,
+            event,
+        }
+    }
+
+    fn bass_line() -> Vec<TimedMidiEvent> {
+        vec![
+            event(0.0, 1, MidiEventType::ProgramChange { program: 33 }),
+            event(
+                0.0,
+                1,
+                MidiEventType::NoteOn {
+                    note: 36,
+                    velocity: 110,
+                },
+            ),
+            event(0.25, 1, MidiEventType::PitchBend { value: -4096 }),
+            event(
+                0.5,
+                1,
+                MidiEventType::ControlChange {
+                    controller: 64,
+                    value: 127,
+                },
+            ),
+            event(
+                0.75,
+                1,
+                MidiEventType::PolyAftertouch {
+                    note: 36,
+                    pressure: 40,
+                },
+            ),
+            event(1.0, 1, MidiEventType::ChannelAftertouch { pressure: 90 }),
+            event(
+                1.5,
+                1,
+                MidiEventType::NoteOff {
+                    note: 36,
+                    velocity: 64,
+                },
+            ),
+        ]
+    }
+
+    fn same_events(a: &[TimedMidiEvent], b: &[TimedMidiEvent]) -> bool {
+        a.len() == b.len()
+            && a.iter().zip(b).all(|(a, b)| {
+                (a.time_beats - b.time_beats).abs() < 1e-9
+                    && a.channel == b.channel
+                    && a.event == b.event
+            })
+    }
+
+    #[test]
+    fn test_format1_roundtrip() {
+        let mut tempo_map = MidiTempoMap::default();
+        tempo_map.tempos.push(MidiTempoChange {
+            time_beats: 0.0,
+            bpm: 100.0,
+        });
+        tempo_map.time_signatures.push(MidiTimeSignature {
+            time_beats: 0.0,
+            numerator: 6,
+            denominator: 8,
+            clocks_per_click: 36,
+            thirty_seconds_per_quarter: 8,
+        });
+
+        let data = MidiFileWriter::new()
+            .ppq(960)
+            .tempo_map(tempo_map)
+            .marker(1.0, "Fill")
+            .events("Bass", &bass_line())
+            .events("Empty", &[])
+            .to_bytes()
+            .unwrap();
+        let file = ParsedMidiFile::parse(&data).unwrap();
+
+        assert_eq!(file.format, MidiFileFormat::Parallel);
+        assert_eq!(file.ticks_per_beat, 960);
+        assert_eq!(file.tracks.len(), 3);
+        assert!((file.tempo_bpm - 100.0).abs() < 1e-3);
+        assert_eq!(file.tempo_map.time_signatures[0].numerator, 6);
+        assert_eq!(file.tempo_map.time_signatures[0].denominator, 8);
+        assert_eq!(file.markers[0].text, "Fill");
+
+        let bass = file.track_named("Bass").unwrap();
+        assert!(same_events(&bass.events, &bass_line()));
+        assert!(bass.events.iter().all(|e| e.track == 1));
+        assert!(file.track_named("Empty").unwrap().events.is_empty());
+    }
+
+    #[test]
+    fn test_format0_merges_tracks() {
+        let lead = [event(
+            0.5,
+            0,
+            MidiEventType::NoteOn {
+                note: 72,
+                velocity: 90,
+            },
+        )];
+        let data = MidiFileWriter::new()
+            .format(MidiFileFormat::SingleTrack)
+            .ppq(96)
+            .events("Bass", &bass_line())
+            .events("Lead", &lead)
+            .to_bytes()
+            .unwrap();
+        let file = ParsedMidiFile::parse(&data).unwrap();
+
+        assert_eq!(file.format, MidiFileFormat::SingleTrack);
+        assert_eq!(file.ticks_per_beat, 96);
+        assert_eq!(file.tracks.len(), 1);
+        assert_eq!(file.tracks[0].name.as_deref(), Some("Bass"));
+        assert_eq!(file.events.len(), bass_line().len() + 1);
+        assert!(file
+            .events
+            .iter()
+            .any(|e| e.channel == 0 && (e.time_beats - 0.5).abs() < 1e-9));
+    }
+
+    #[test]
+    fn test_conductor_keeps_ramp_timing() {
+        let mut map = TempoMap::new(90.0, 48000.0);
+        map.add_tempo_point_with_curve(4.0, 90.0, TempoCurve::Linear);
+        map.add_tempo_point(8.0, 150.0);
+        map.add_meter_change(3, 3, 4);
+        let snapshot = map.snapshot();
+
+        let data = MidiFileWriter::new()
+            .transport_tempo_map(&snapshot)
+            .to_bytes()
+            .unwrap();
+        let file = ParsedMidiFile::parse(&data).unwrap();
+        let restored = file.tempo_map.to_tempo_map(48000.0).snapshot();
+
+        for beats in [2.0, 4.0, 5.3, 8.0, 12.0] {
+            let error = restored.beats_to_seconds(beats) - snapshot.beats_to_seconds(beats);
+            assert!(error.abs() < 1e-3, "beat {beats}: off by {error}s");
+        }
+        let ts = restored.time_signature_at(8.5);
+        assert_eq!((ts.numerator, ts.denominator), (3, 4));
+    }
+
+    #[test]
+    fn test_snapshot_tracks() {
+        let mut snapshot = MidiSnapshot::new();
+        snapshot.add_event(7, 1.0, MidiEvent::note_off(0, 2, 60, 0));
+        snapshot.add_event(7, 0.0, MidiEvent::note_on(0, 2, 60, 100));
+        snapshot.add_event(3, 0.5, MidiEvent::pitch_bend(0, 0, 8192 + 100));
+
+        let data = MidiFileWriter::new()
+            .snapshot(&snapshot)
+            .to_bytes()
+            .unwrap();
+        let file = ParsedMidiFile::parse(&data).unwrap();
+
+        assert!(matches!(
+            file.track_named("Unit 3").unwrap().events[0].event,
+            MidiEventType::PitchBend { value: 100 }
+        ));
+        let synth = file.track_named("Unit 7").unwrap();
+        assert_eq!(synth.events.len(), 2);
+        assert!(synth.events.iter().all(|e| e.channel == 2));
+        assert!(matches!(
+            synth.events[1].event,
+            MidiEventType::NoteOff { note: 60, .. }
+        ));
+    }
+
+    #[test]
+    fn test_rejects_invalid_settings() {
+        assert!(MidiFileWriter::new().ppq(0).to_bytes().is_err());
+        assert!(MidiFileWriter::new()
+            .format(MidiFileFormat::Sequential)
+            .to_bytes()
+            .is_err());
+    }
+}
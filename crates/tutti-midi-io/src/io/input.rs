@@ -2,8 +2,9 @@
 
 #![cfg_attr(not(feature = "midi-io"), allow(unused_imports, dead_code))]
 
-use crate::port::async_port::InputProducerHandle;
+use crate::port::async_port::{InputProducerHandle, SyncInputProducerHandle};
 use crate::port::MidiPortManager;
+use crate::sync::SyncMessage;
 use crate::MidiEvent;
 use crossbeam_channel::{bounded, Receiver, Sender};
 #[cfg(feature = "midi-io")]
@@ -26,7 +27,7 @@ pub struct MidiInputDevice {
 }
 
 enum MidiCommand {
-    Connect(usize, usize, InputProducerHandle), // (device_index, port_index, producer_handle)
+    Connect(usize, usize, InputProducerHandle, SyncInputProducerHandle), // (device_index, port_index, producer_handle, sync_handle)
     Disconnect,
     SetObserver(Sender<MidiEvent>),
     Shutdown,
@@ -105,7 +106,12 @@ impl MidiInputManager {
 
         loop {
             match command_receiver.recv_timeout(std::time::Duration::from_millis(100)) {
-                Ok(MidiCommand::Connect(device_index, port_index, producer_handle)) => {
+                Ok(MidiCommand::Connect(
+                    device_index,
+                    port_index,
+                    producer_handle,
+                    sync_handle,
+                )) => {
                     if let Some(conn) = connection.take() {
                         drop(conn);
                         is_connected.store(false, Ordering::SeqCst);
@@ -116,6 +122,7 @@ impl MidiInputManager {
                     match Self::connect_to_device(
                         device_index,
                         producer_handle,
+                        sync_handle,
                         ui_observer.clone(),
                         #[cfg(feature = "mpe")]
                         mpe_processor.clone(),
@@ -159,6 +166,7 @@ impl MidiInputManager {
     fn connect_to_device(
         device_index: usize,
         producer_handle: InputProducerHandle,
+        sync_handle: SyncInputProducerHandle,
         ui_observer: Option<Sender<MidiEvent>>,
         #[cfg(feature = "mpe")] mpe_processor: Option<MpeProcessorRef>,
     ) -> Result<(MidiInputConnection<()>, String), crate::error::Error> {
@@ -178,6 +186,15 @@ impl MidiInputManager {
             "dawai-input",
             move |_timestamp, message, _| {
                 let now = Instant::now();
+
+                // Clock, SPP and MTC go to the sync queue (MidiEvent can't hold them)
+                if let Some(sync) = SyncMessage::from_bytes(message) {
+                    if !sync_handle.push(sync, now) {
+                        debug!("MIDI sync ring buffer full, dropping message");
+                    }
+                    return;
+                }
+
                 match MidiEvent::from_bytes(message) {
                     Ok(event) => {
                         // Process through MPE if enabled.
@@ -239,12 +256,19 @@ impl MidiInputManager {
             .ok_or_else(|| {
                 crate::error::Error::MidiDevice("Failed to get producer handle".to_string())
             })?;
+        let sync_handle = self
+            .port_manager
+            .get_sync_input_producer_handle(port_index)
+            .ok_or_else(|| {
+                crate::error::Error::MidiDevice("Failed to get sync producer handle".to_string())
+            })?;
 
         self.command_sender
             .send(MidiCommand::Connect(
                 device_index,
                 port_index,
                 producer_handle,
+                sync_handle,
             ))
             .map_err(|_| crate::error::Error::MidiDevice("MIDI thread not running".to_string()))
     }
@@ -1,6 +1,7 @@
 //! MIDI I/O subsystem for Tutti audio engine.
 //!
-//! Provides port management, hardware I/O, MPE, MIDI 2.0, CC mapping, and output collection.
+//! Provides port management, hardware I/O, MPE, MIDI 2.0, CC mapping, output collection,
+//! and Standard MIDI File reading and writing.
 //!
 //! Feature gates: `midi-io` (hardware I/O), `mpe` (polyphonic expression), `midi2` (high-res messages).
 
@@ -31,6 +32,14 @@ pub use tutti_midi::{
 pub(crate) mod port;
 pub use port::{PortInfo, PortType};
 
+pub(crate) mod sync;
+#[cfg(feature = "midi-io")]
+pub use sync::SyncOutputSender;
+pub use sync::{
+    MidiSyncFollower, MidiSyncGenerator, SyncFollowerConfig, SyncFollowerHandle,
+    SyncGeneratorConfig, SyncMessage,
+};
+
 #[cfg(feature = "midi-io")]
 pub(crate) mod io;
 
@@ -49,7 +58,16 @@ pub use output_collector::{
 };
 
 pub(crate) mod file;
-pub use file::{MidiEventType, ParsedMidiFile, TimedMidiEvent};
+pub use file::{
+    MidiEventType, MidiFileFormat, MidiFileTrack, MidiKeySignature, MidiTempoChange, MidiTempoMap,
+    MidiTimeSignature, ParsedMidiFile, TextKind, TimedMidiEvent, TimedSysEx, TimedText,
+};
+
+pub(crate) mod file_writer;
+pub use file_writer::MidiFileWriter;
+
+pub(crate) mod file_player;
+pub use file_player::{MidiFilePlayer, MidiFilePlayerBuilder};
 
 pub use tutti_midi::note;
 pub use tutti_midi::Note;
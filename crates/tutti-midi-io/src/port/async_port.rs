@@ -10,6 +10,7 @@ use ringbuf::{
 use std::cell::UnsafeCell;
 use std::time::Instant;
 
+use crate::sync::SyncMessage;
 pub use crate::MidiEvent;
 
 /// # Safety
@@ -34,6 +35,52 @@ impl InputProducerHandle {
     }
 }
 
+/// Producer for clock/SPP/MTC messages, which `MidiEvent` cannot carry.
+///
+/// # Safety
+/// Must only be used from a single thread (the midir callback thread).
+pub struct SyncInputProducerHandle {
+    producer: *mut ringbuf::HeapProd<(Instant, SyncMessage)>,
+}
+
+// SAFETY: HeapProd is Send. Ownership is transferred to the midir callback thread.
+unsafe impl Send for SyncInputProducerHandle {}
+
+// SAFETY: Only one thread calls push() (SPSC invariant).
+unsafe impl Sync for SyncInputProducerHandle {}
+
+impl SyncInputProducerHandle {
+    #[inline]
+    pub fn push(&self, message: SyncMessage, timestamp: Instant) -> bool {
+        // SAFETY: Exclusive access as single producer (SPSC invariant).
+        let prod = unsafe { &mut *self.producer };
+        prod.try_push((timestamp, message)).is_ok()
+    }
+}
+
+/// Producer for generated clock/SPP/MTC, stamped with their send time.
+///
+/// # Safety
+/// Must only be used from a single thread (the audio thread).
+pub struct SyncOutputProducerHandle {
+    producer: *mut ringbuf::HeapProd<(Instant, SyncMessage)>,
+}
+
+// SAFETY: HeapProd is Send. Ownership is transferred to the audio thread.
+unsafe impl Send for SyncOutputProducerHandle {}
+
+// SAFETY: Only one thread calls push() (SPSC invariant).
+unsafe impl Sync for SyncOutputProducerHandle {}
+
+impl SyncOutputProducerHandle {
+    #[inline]
+    pub fn push(&self, message: SyncMessage, send_at: Instant) -> bool {
+        // SAFETY: Exclusive access as single producer (SPSC invariant).
+        let prod = unsafe { &mut *self.producer };
+        prod.try_push((send_at, message)).is_ok()
+    }
+}
+
 /// # Safety
 /// Must only be used from a single thread (SPSC invariant).
 #[cfg(feature = "midi2")]
@@ -89,6 +136,10 @@ pub struct AsyncMidiPort {
     input_producer: UnsafeCell<ringbuf::HeapProd<(Instant, MidiEvent)>>,
     output_producer: UnsafeCell<ringbuf::HeapProd<MidiEvent>>,
     output_consumer: UnsafeCell<ringbuf::HeapCons<MidiEvent>>,
+    sync_input_consumer: UnsafeCell<ringbuf::HeapCons<(Instant, SyncMessage)>>,
+    sync_input_producer: UnsafeCell<ringbuf::HeapProd<(Instant, SyncMessage)>>,
+    sync_output_producer: UnsafeCell<ringbuf::HeapProd<(Instant, SyncMessage)>>,
+    sync_output_consumer: UnsafeCell<ringbuf::HeapCons<(Instant, SyncMessage)>>,
     #[cfg(feature = "midi2")]
     unified_input_consumer: UnsafeCell<ringbuf::HeapCons<crate::event::UnifiedMidiEvent>>,
     #[cfg(feature = "midi2")]
@@ -102,6 +153,10 @@ impl AsyncMidiPort {
         let (input_producer, input_consumer) = input_rb.split();
         let output_rb = HeapRb::<MidiEvent>::new(fifo_size);
         let (output_producer, output_consumer) = output_rb.split();
+        let sync_rb = HeapRb::<(Instant, SyncMessage)>::new(fifo_size);
+        let (sync_input_producer, sync_input_consumer) = sync_rb.split();
+        let sync_output_rb = HeapRb::<(Instant, SyncMessage)>::new(fifo_size);
+        let (sync_output_producer, sync_output_consumer) = sync_output_rb.split();
 
         #[cfg(feature = "midi2")]
         let unified_rb = HeapRb::<crate::event::UnifiedMidiEvent>::new(fifo_size);
@@ -115,6 +170,10 @@ impl AsyncMidiPort {
             input_producer: UnsafeCell::new(input_producer),
             output_producer: UnsafeCell::new(output_producer),
             output_consumer: UnsafeCell::new(output_consumer),
+            sync_input_consumer: UnsafeCell::new(sync_input_consumer),
+            sync_input_producer: UnsafeCell::new(sync_input_producer),
+            sync_output_producer: UnsafeCell::new(sync_output_producer),
+            sync_output_consumer: UnsafeCell::new(sync_output_consumer),
             #[cfg(feature = "midi2")]
             unified_input_consumer: UnsafeCell::new(unified_input_consumer),
             #[cfg(feature = "midi2")]
@@ -143,6 +202,18 @@ impl AsyncMidiPort {
         }
     }
 
+    pub fn sync_input_producer_handle(&self) -> SyncInputProducerHandle {
+        SyncInputProducerHandle {
+            producer: self.sync_input_producer.get(),
+        }
+    }
+
+    pub fn sync_output_producer_handle(&self) -> SyncOutputProducerHandle {
+        SyncOutputProducerHandle {
+            producer: self.sync_output_producer.get(),
+        }
+    }
+
     #[cfg(feature = "midi2")]
     pub fn unified_input_producer_handle(&self) -> UnifiedInputProducerHandle {
         UnifiedInputProducerHandle {
@@ -168,6 +239,26 @@ impl AsyncMidiPort {
         }
     }
 
+    /// Drain queued sync messages. Only the sync follower may call this
+    /// (single consumer).
+    #[inline]
+    pub fn read_sync_input_into(&self, buf: &mut Vec<(Instant, SyncMessage)>) {
+        let consumer = unsafe { &mut *self.sync_input_consumer.get() };
+        while let Some(entry) = consumer.try_pop() {
+            buf.push(entry);
+        }
+    }
+
+    /// Drain generated sync messages. Only the sync sender may call this
+    /// (single consumer).
+    #[inline]
+    pub fn read_sync_output_into(&self, buf: &mut Vec<(Instant, SyncMessage)>) {
+        let consumer = unsafe { &mut *self.sync_output_consumer.get() };
+        while let Some(entry) = consumer.try_pop() {
+            buf.push(entry);
+        }
+    }
+
     #[inline]
     #[cfg(feature = "midi2")]
     pub fn cycle_start_read_unified_input_into(
@@ -253,6 +344,25 @@ mod tests {
         assert_eq!(events[0].note(), Some(0x3C));
     }
 
+    #[test]
+    fn test_sync_input_separate_from_events() {
+        let port = AsyncMidiPort::new("Clock", 256);
+        let sync_handle = port.sync_input_producer_handle();
+
+        assert!(sync_handle.push(SyncMessage::Clock, Instant::now()));
+        assert!(sync_handle.push(SyncMessage::SongPosition(16), Instant::now()));
+
+        assert!(read_input(&port).is_empty());
+
+        let mut buf = Vec::new();
+        port.read_sync_input_into(&mut buf);
+        let messages: Vec<_> = buf.into_iter().map(|(_, m)| m).collect();
+        assert_eq!(
+            messages,
+            vec![SyncMessage::Clock, SyncMessage::SongPosition(16)]
+        );
+    }
+
     #[test]
     fn test_fifo_full() {
         let port = AsyncMidiPort::new("Full", 4);
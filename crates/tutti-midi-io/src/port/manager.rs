@@ -1,6 +1,7 @@
 //! Multi-port MIDI manager with lock-free access for the audio thread.
 
 use super::async_port::{AsyncMidiPort, OutputProducerHandle};
+use crate::sync::SyncMessage;
 use crate::MidiEvent;
 use arc_swap::ArcSwap;
 use parking_lot::RwLock;
@@ -284,6 +285,64 @@ impl MidiPortManager {
             .map(|port| port.input_producer_handle())
     }
 
+    pub fn get_sync_input_producer_handle(
+        &self,
+        port_index: usize,
+    ) -> Option<super::async_port::SyncInputProducerHandle> {
+        let input_ports = self.input_ports.load();
+        input_ports
+            .get(port_index)
+            .map(|port| port.sync_input_producer_handle())
+    }
+
+    /// Drain clock/SPP/MTC messages received on an input port.
+    ///
+    /// Single consumer: only one sync follower per port.
+    pub fn read_sync_input_into(
+        &self,
+        port_index: usize,
+        buf: &mut Vec<(Instant, SyncMessage)>,
+    ) -> bool {
+        let input_ports = self.input_ports.load();
+        match input_ports.get(port_index) {
+            Some(port) if port.is_active() => {
+                port.read_sync_input_into(buf);
+                true
+            }
+            Some(_) => true,
+            None => false,
+        }
+    }
+
+    pub fn get_sync_output_producer_handle(
+        &self,
+        port_index: usize,
+    ) -> Option<super::async_port::SyncOutputProducerHandle> {
+        let output_ports = self.output_ports.load();
+        output_ports
+            .get(port_index)
+            .map(|port| port.sync_output_producer_handle())
+    }
+
+    /// Drain clock/SPP/MTC generated for an output port, with send times.
+    ///
+    /// Single consumer: only one sync sender per port.
+    pub fn read_sync_output_into(
+        &self,
+        port_index: usize,
+        buf: &mut Vec<(Instant, SyncMessage)>,
+    ) -> bool {
+        let output_ports = self.output_ports.load();
+        match output_ports.get(port_index) {
+            Some(port) if port.is_active() => {
+                port.read_sync_output_into(buf);
+                true
+            }
+            Some(_) => true,
+            None => false,
+        }
+    }
+
     #[cfg(feature = "midi2")]
     pub fn get_unified_input_producer_handle(
         &self,
@@ -326,6 +385,38 @@ impl MidiPortManager {
         }
     }
 
+    /// RT-safe (lock-free). Uses `Instant::now()` as the timestamp.
+    pub fn push_input_sync(&self, port_index: usize, message: SyncMessage) -> bool {
+        self.push_input_sync_at(port_index, message, Instant::now())
+    }
+
+    /// Like [`push_input_sync`](Self::push_input_sync) with an explicit arrival time.
+    pub fn push_input_sync_at(
+        &self,
+        port_index: usize,
+        message: SyncMessage,
+        timestamp: Instant,
+    ) -> bool {
+        let input_ports = self.input_ports.load();
+        if let Some(port) = input_ports.get(port_index) {
+            port.sync_input_producer_handle().push(message, timestamp)
+        } else {
+            false
+        }
+    }
+
+    /// Push raw bytes as received from a device, routing sync messages and
+    /// channel voice messages to their respective queues.
+    pub fn push_input_bytes(&self, port_index: usize, bytes: &[u8]) -> bool {
+        if let Some(message) = SyncMessage::from_bytes(bytes) {
+            return self.push_input_sync(port_index, message);
+        }
+        match MidiEvent::from_bytes(bytes) {
+            Ok(event) => self.push_input_event(port_index, event),
+            Err(_) => false,
+        }
+    }
+
     #[cfg(feature = "midi2")]
     pub fn push_unified_event(
         &self,
@@ -519,6 +610,29 @@ mod tests {
         assert!(port_ids.contains(&id2));
     }
 
+    #[test]
+    fn test_push_input_bytes_splits_sync_and_events() {
+        let manager = MidiPortManager::new(256);
+        let input_id = manager.create_input_port("Input");
+
+        assert!(manager.push_input_bytes(input_id, &[0xF8]));
+        assert!(manager.push_input_bytes(input_id, &[0x90, 60, 100]));
+        assert!(manager.push_input_bytes(input_id, &[0xF2, 0x08, 0x00]));
+        assert!(!manager.push_input_bytes(input_id, &[0xFE]));
+
+        let events = manager.cycle_start_read_all_inputs(512, now(), 44100.0);
+        assert_eq!(events.len(), 1);
+        assert!(events[0].1.is_note_on());
+
+        let mut sync = Vec::new();
+        assert!(manager.read_sync_input_into(input_id, &mut sync));
+        let messages: Vec<_> = sync.into_iter().map(|(_, m)| m).collect();
+        assert_eq!(
+            messages,
+            vec![SyncMessage::Clock, SyncMessage::SongPosition(8)]
+        );
+    }
+
     #[test]
     fn test_timestamp_to_frame_offset_conversion() {
         let manager = MidiPortManager::new(256);
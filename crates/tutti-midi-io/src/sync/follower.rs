@@ -0,0 +1,741 @@
+//! Slave the transport to incoming MIDI Clock or MIDI Time Code.
+//!
+//! The follower drains the sync queue of one input port, estimates the
+//! master's tempo with a second-order delay-locked loop, and drives
+//! `TransportManager`'s `SyncState` through Locking → Locked ↔ Drifting.
+//! Only messages matching the transport's selected [`SyncSource`] are used.
+
+use super::message::SyncMessage;
+use crate::port::MidiPortManager;
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tutti_core::{SmpteFrameRate, SyncSource, SyncStatus, Timecode, TransportManager};
+
+/// MIDI Clock pulses per quarter note.
+const CLOCKS_PER_BEAT: f64 = 24.0;
+
+/// Jitter smoothing factor (one-pole, per pulse).
+const JITTER_SMOOTHING: f64 = 0.1;
+
+/// Tuning for [`MidiSyncFollower`].
+#[derive(Debug, Clone)]
+pub struct SyncFollowerConfig {
+    /// DLL bandwidth in Hz. Lower values reject more jitter but track tempo
+    /// changes more slowly. Default: 1.0.
+    pub bandwidth_hz: f64,
+    /// Pulses (clock ticks or MTC quarter frames) required before locking.
+    /// Default: 24.
+    pub lock_pulses: u32,
+    /// Filtered timing error, as a fraction of the pulse period, below which
+    /// the follower reports `Locked`. Default: 0.1.
+    pub lock_tolerance: f64,
+    /// Filtered timing error above which a locked follower reports `Drifting`.
+    /// Default: 0.3.
+    pub drift_tolerance: f64,
+    /// Position error (beats) tolerated before the transport is re-located.
+    /// Default: 1/12 beat (two clock pulses).
+    pub chase_tolerance_beats: f64,
+    /// Silence after which the master is considered gone. Default: 300 ms.
+    pub timeout: Duration,
+}
+
+impl Default for SyncFollowerConfig {
+    fn default() -> Self {
+        Self {
+            bandwidth_hz: 1.0,
+            lock_pulses: 24,
+            lock_tolerance: 0.1,
+            drift_tolerance: 0.3,
+            chase_tolerance_beats: 2.0 / CLOCKS_PER_BEAT,
+            timeout: Duration::from_millis(300),
+        }
+    }
+}
+
+/// Second-order DLL over pulse arrival times (seconds).
+///
+/// Tracks the pulse period and predicts the next arrival; the filtered
+/// prediction error doubles as the jitter estimate used for lock status.
+#[derive(Debug, Clone, Default)]
+struct PulsePll {
+    last_pulse: Option<f64>,
+    next_pulse: f64,
+    period: f64,
+    jitter: f64,
+    pulses: u32,
+}
+
+impl PulsePll {
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    fn pulse(&mut self, t: f64, bandwidth_hz: f64) {
+        let Some(last) = self.last_pulse else {
+            self.last_pulse = Some(t);
+            return;
+        };
+
+        if self.pulses == 0 {
+            // Second pulse: seed the period from the raw interval
+            self.period = (t - last).max(1e-6);
+            self.next_pulse = t + self.period;
+            self.jitter = 0.0;
+        } else {
+            let error = t - self.next_pulse;
+
+            if error.abs() > self.period {
+                // Missed pulses or a tempo jump: re-seed instead of slewing
+                self.period = (t - last).max(1e-6);
+                self.next_pulse = t + self.period;
+                self.jitter = 1.0;
+            } else {
+                let omega = 2.0 * PI * bandwidth_hz * self.period;
+                let b = std::f64::consts::SQRT_2 * omega;
+                let c = omega * omega;
+                self.next_pulse += b * error + self.period;
+                self.period += c * error;
+                self.jitter += JITTER_SMOOTHING * (error.abs() / self.period - self.jitter);
+            }
+        }
+
+        self.last_pulse = Some(t);
+        self.pulses = self.pulses.saturating_add(1);
+    }
+
+    fn period(&self) -> Option<f64> {
+        (self.pulses > 0).then_some(self.period)
+    }
+}
+
+/// Assembles MTC quarter frames into full timecodes.
+#[derive(Debug, Clone, Default)]
+struct MtcAssembler {
+    nibbles: [u8; 8],
+    received: u8,
+    last_piece: Option<u8>,
+    last_frame_count: Option<u64>,
+    consistent_cycles: u32,
+}
+
+impl MtcAssembler {
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Returns the decoded timecode when piece 7 completes a forward cycle.
+    fn push(&mut self, piece: u8, value: u8) -> Option<(Timecode, SmpteFrameRate)> {
+        let in_sequence = match self.last_piece {
+            Some(last) => piece == (last + 1) % 8,
+            None => piece == 0,
+        };
+        if !in_sequence {
+            self.received = 0;
+            self.last_piece = None;
+            if piece != 0 {
+                return None;
+            }
+        }
+
+        self.nibbles[piece as usize] = value & 0x0F;
+        self.received |= 1 << piece;
+        self.last_piece = Some(piece);
+
+        if piece != 7 || self.received != 0xFF {
+            return None;
+        }
+        self.received = 0;
+
+        let n = &self.nibbles;
+        let frames = n[0] | (n[1] << 4);
+        let seconds = n[2] | (n[3] << 4);
+        let minutes = n[4] | (n[5] << 4);
+        let hours = n[6] | ((n[7] & 0x01) << 4);
+        let rate = SmpteFrameRate::from_mtc_code(n[7] >> 1);
+        let timecode = Timecode::new(hours, minutes, seconds, frames);
+
+        // A running master advances two frames per cycle
+        let count = timecode.to_frame_count(rate);
+        if self.last_frame_count.map(|last| last + 2) == Some(count) {
+            self.consistent_cycles = self.consistent_cycles.saturating_add(1);
+        } else {
+            self.consistent_cycles = 0;
+        }
+        self.last_frame_count = Some(count);
+
+        Some((timecode, rate))
+    }
+}
+
+/// Follows MIDI Clock / MTC arriving on one input port.
+///
+/// Call [`poll`](Self::poll) regularly (a few ms apart), or hand the follower
+/// to a background thread with [`spawn`](Self::spawn).
+///
+/// # Example
+/// ```ignore
+/// let transport = engine.transport_manager().clone();
+/// transport.set_sync_source(SyncSource::MidiClock);
+/// transport.set_following(true);
+///
+/// let _sync = midi.sync_follower(clock_port, transport).spawn(Duration::from_millis(2));
+/// ```
+pub struct MidiSyncFollower {
+    port_manager: Arc<MidiPortManager>,
+    port_index: usize,
+    transport: Arc<TransportManager>,
+    config: SyncFollowerConfig,
+    epoch: Instant,
+    buffer: Vec<(Instant, SyncMessage)>,
+
+    pll: PulsePll,
+    mtc: MtcAssembler,
+    last_activity: Option<Instant>,
+
+    /// MIDI Clock: position (beats) of the next clock pulse.
+    clock_position: f64,
+    clock_running: bool,
+    start_pending: bool,
+
+    /// Most recent (arrival time, external beat) reference for chasing.
+    reference: Option<(f64, f64)>,
+    last_tempo: f32,
+}
+
+impl MidiSyncFollower {
+    pub fn new(
+        port_manager: Arc<MidiPortManager>,
+        port_index: usize,
+        transport: Arc<TransportManager>,
+    ) -> Self {
+        Self {
+            port_manager,
+            port_index,
+            transport,
+            config: SyncFollowerConfig::default(),
+            epoch: Instant::now(),
+            buffer: Vec::with_capacity(256),
+            pll: PulsePll::default(),
+            mtc: MtcAssembler::default(),
+            last_activity: None,
+            clock_position: 0.0,
+            clock_running: false,
+            start_pending: false,
+            reference: None,
+            last_tempo: 0.0,
+        }
+    }
+
+    pub fn with_config(mut self, config: SyncFollowerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn port_index(&self) -> usize {
+        self.port_index
+    }
+
+    /// Tempo estimated from the incoming clock, if enough pulses have arrived.
+    pub fn estimated_tempo(&self) -> Option<f32> {
+        if self.transport.get_sync_source() != SyncSource::MidiClock {
+            return None;
+        }
+        self.pll
+            .period()
+            .map(|period| (60.0 / (period * CLOCKS_PER_BEAT)) as f32)
+    }
+
+    /// Drain pending messages and update the transport. Returns the number of
+    /// messages consumed.
+    pub fn poll(&mut self) -> usize {
+        self.poll_at(Instant::now())
+    }
+
+    /// Like [`poll`](Self::poll), treating `now` as the current time.
+    pub fn poll_at(&mut self, now: Instant) -> usize {
+        let mut buffer = std::mem::take(&mut self.buffer);
+        buffer.clear();
+        self.port_manager
+            .read_sync_input_into(self.port_index, &mut buffer);
+
+        let source = self.transport.get_sync_source();
+        for &(timestamp, message) in &buffer {
+            match source {
+                SyncSource::MidiClock => self.handle_clock(message, timestamp),
+                SyncSource::MidiTimecode => self.handle_mtc(message, timestamp),
+                // Drain so the queue doesn't back up while another source is active
+                SyncSource::Internal | SyncSource::Ltc => {}
+            }
+        }
+
+        let consumed = buffer.len();
+        self.buffer = buffer;
+
+        if matches!(source, SyncSource::MidiClock | SyncSource::MidiTimecode) {
+            self.check_timeout(now);
+            self.chase(now);
+        }
+        consumed
+    }
+
+    /// Run [`poll`](Self::poll) on a background thread every `interval`.
+    pub fn spawn(mut self, interval: Duration) -> SyncFollowerHandle {
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_running = running.clone();
+        let thread = std::thread::Builder::new()
+            .name("tutti-midi-sync".into())
+            .spawn(move || {
+                while thread_running.load(Ordering::Acquire) {
+                    self.poll();
+                    std::thread::sleep(interval);
+                }
+            })
+            .expect("failed to spawn MIDI sync thread");
+
+        SyncFollowerHandle {
+            running,
+            thread: Some(thread),
+        }
+    }
+
+    #[inline]
+    fn seconds(&self, instant: Instant) -> f64 {
+        instant.saturating_duration_since(self.epoch).as_secs_f64()
+    }
+
+    fn handle_clock(&mut self, message: SyncMessage, timestamp: Instant) {
+        let t = self.seconds(timestamp);
+        self.last_activity = Some(timestamp);
+
+        match message {
+            SyncMessage::Clock => {
+                self.pll.pulse(t, self.config.bandwidth_hz);
+                self.update_status();
+
+                if self.start_pending {
+                    self.start_pending = false;
+                    self.clock_running = true;
+                    if self.transport.sync_state().is_following() {
+                        self.locate(self.clock_position);
+                        self.transport.play();
+                    }
+                }
+
+                if self.clock_running {
+                    self.reference = Some((t, self.clock_position));
+                    self.transport
+                        .sync_state()
+                        .set_external_position(self.clock_position);
+                    self.clock_position += 1.0 / CLOCKS_PER_BEAT;
+                }
+
+                if let Some(tempo) = self.estimated_tempo() {
+                    if (tempo - self.last_tempo).abs() > 0.01 {
+                        self.last_tempo = tempo;
+                        self.transport.receive_external_tempo(tempo);
+                    }
+                }
+            }
+            SyncMessage::Start => {
+                self.clock_position = 0.0;
+                self.start_pending = true;
+            }
+            SyncMessage::Continue => {
+                self.start_pending = true;
+            }
+            SyncMessage::Stop => {
+                self.clock_running = false;
+                self.start_pending = false;
+                self.reference = None;
+                if self.transport.sync_state().is_following() {
+                    self.transport.stop();
+                }
+            }
+            SyncMessage::SongPosition(sixteenths) => {
+                self.clock_position = sixteenths as f64 / 4.0;
+                self.transport
+                    .sync_state()
+                    .set_external_position(self.clock_position);
+                if !self.clock_running && self.transport.sync_state().is_following() {
+                    self.locate(self.clock_position);
+                }
+            }
+            SyncMessage::MtcQuarterFrame { .. } | SyncMessage::MtcFullFrame { .. } => {}
+        }
+    }
+
+    fn handle_mtc(&mut self, message: SyncMessage, timestamp: Instant) {
+        let t = self.seconds(timestamp);
+
+        match message {
+            SyncMessage::MtcQuarterFrame { piece, value } => {
+                let was_running = self.reference.is_some();
+                self.last_activity = Some(timestamp);
+                self.pll.pulse(t, self.config.bandwidth_hz);
+
+                if let Some((timecode, rate)) = self.mtc.push(piece, value) {
+                    self.transport.set_smpte_frame_rate(rate);
+
+                    // Piece 7 arrives 1.75 frames after the encoded frame started
+                    let seconds = timecode.to_seconds(rate) + 1.75 / rate.fps();
+                    let beats = self.transport.seconds_to_beats(seconds);
+                    self.reference = Some((t, beats));
+                    self.transport.sync_state().set_external_position(beats);
+
+                    if !was_running && self.transport.sync_state().is_following() {
+                        self.locate(beats);
+                        self.transport.play();
+                    }
+                }
+                self.update_status();
+            }
+            SyncMessage::MtcFullFrame { timecode, rate } => {
+                // Sent while shuttling or on locate: jump, don't start
+                self.transport.set_smpte_frame_rate(rate);
+                self.mtc.reset();
+                self.pll.reset();
+                self.reference = None;
+
+                let beats = self.transport.seconds_to_beats(timecode.to_seconds(rate));
+                self.transport.sync_state().set_external_position(beats);
+                if self.transport.sync_state().is_following() {
+                    self.locate(beats);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn update_status(&self) {
+        let state = self.transport.sync_state();
+        let current = state.status();
+        let jitter = self.pll.jitter;
+
+        let enough_pulses = self.pll.pulses >= self.config.lock_pulses;
+        let mtc_consistent = self.transport.get_sync_source() != SyncSource::MidiTimecode
+            || self.mtc.consistent_cycles >= 1;
+
+        let next = match current {
+            SyncStatus::Locked if jitter > self.config.drift_tolerance => SyncStatus::Drifting,
+            SyncStatus::Locked => SyncStatus::Locked,
+            SyncStatus::Drifting if jitter < self.config.lock_tolerance => SyncStatus::Locked,
+            SyncStatus::Drifting => SyncStatus::Drifting,
+            _ if enough_pulses && mtc_consistent && jitter < self.config.lock_tolerance => {
+                SyncStatus::Locked
+            }
+            _ => SyncStatus::Locking,
+        };
+
+        if next != current {
+            tracing::debug!("MIDI sync status {:?} -> {:?}", current, next);
+            state.set_status(next);
+        }
+    }
+
+    fn check_timeout(&mut self, now: Instant) {
+        let Some(last) = self.last_activity else {
+            return;
+        };
+        if now.saturating_duration_since(last) < self.config.timeout {
+            return;
+        }
+
+        tracing::debug!("MIDI sync source timed out on port {}", self.port_index);
+        self.last_activity = None;
+        self.pll.reset();
+        self.mtc.reset();
+        self.reference = None;
+        self.start_pending = false;
+        self.last_tempo = 0.0;
+
+        // MTC has no Stop message; silence means the master stopped
+        let was_running =
+            self.clock_running || self.transport.get_sync_source() == SyncSource::MidiTimecode;
+        self.clock_running = false;
+
+        let state = self.transport.sync_state();
+        if state.is_following() && was_running {
+            self.transport.stop();
+        }
+        state.set_status(SyncStatus::Locking);
+    }
+
+    /// Re-locate the transport if it has wandered from the master's position.
+    fn chase(&self, now: Instant) {
+        let Some((t, beats)) = self.reference else {
+            return;
+        };
+        if !self.transport.is_slaved() || self.transport.is_paused() {
+            return;
+        }
+
+        let tempo = self.transport.get_tempo() as f64;
+        let elapsed = (self.seconds(now) - t).max(0.0);
+        let external_now = beats + elapsed * tempo / 60.0;
+        let target = external_now + self.offset_beats();
+
+        if (self.transport.get_current_beat() - target).abs() > self.config.chase_tolerance_beats {
+            self.transport.receive_external_position(external_now);
+        }
+    }
+
+    fn offset_beats(&self) -> f64 {
+        let offset = self.transport.sync_state().offset_samples();
+        if offset != 0.0 {
+            offset / self.transport.samples_per_beat()
+        } else {
+            0.0
+        }
+    }
+
+    /// Explicit locate (SPP, full frame, start), applied whatever the lock status.
+    fn locate(&self, beats: f64) {
+        self.transport.locate(beats + self.offset_beats());
+    }
+}
+
+/// Keeps a spawned [`MidiSyncFollower`] running; stops it on drop.
+pub struct SyncFollowerHandle {
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SyncFollowerHandle {
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.running.store(false, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for SyncFollowerHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SR: f64 = 48000.0;
+
+    struct Fixture {
+        ports: Arc<MidiPortManager>,
+        port: usize,
+        transport: Arc<TransportManager>,
+        follower: MidiSyncFollower,
+        start: Instant,
+    }
+
+    fn fixture(source: SyncSource) -> Fixture {
+        let ports = Arc::new(MidiPortManager::new(4096));
+        let port = ports.create_input_port("Sync In");
+        let transport = Arc::new(TransportManager::default());
+        transport.set_sync_source(source);
+        transport.set_following(true);
+        let follower = MidiSyncFollower::new(ports.clone(), port, transport.clone());
+        let start = follower.epoch + Duration::from_millis(10);
+        Fixture {
+            ports,
+            port,
+            transport,
+            follower,
+            start,
+        }
+    }
+
+    /// Deterministic jitter in [-amount, amount] seconds.
+    fn jitter(i: usize, amount: f64) -> f64 {
+        let x = (i as u64)
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((x >> 33) as f64 / (1u64 << 31) as f64 * 2.0 - 1.0) * amount
+    }
+
+    fn at(f: &Fixture, seconds: f64) -> Instant {
+        f.start + Duration::from_secs_f64(seconds)
+    }
+
+    fn send_clocks(f: &Fixture, from: usize, count: usize, bpm: f64, jitter_s: f64) -> f64 {
+        let period = 60.0 / bpm / CLOCKS_PER_BEAT;
+        let mut t = 0.0;
+        for i in from..from + count {
+            t = i as f64 * period + jitter(i, jitter_s);
+            assert!(f
+                .ports
+                .push_input_sync_at(f.port, SyncMessage::Clock, at(f, t)));
+        }
+        t
+    }
+
+    #[test]
+    fn test_pll_converges_on_jittery_clock() {
+        let mut f = fixture(SyncSource::MidiClock);
+
+        let last = send_clocks(&f, 0, 24 * 16, 125.0, 0.001);
+        f.follower.poll_at(at(&f, last));
+
+        let tempo = f.follower.estimated_tempo().unwrap();
+        assert!((tempo - 125.0).abs() < 0.5, "estimated {tempo}");
+        assert_eq!(f.transport.sync_state().status(), SyncStatus::Locked);
+        assert!((f.transport.get_tempo() - tempo).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_locking_before_enough_pulses() {
+        let mut f = fixture(SyncSource::MidiClock);
+
+        let last = send_clocks(&f, 0, 6, 120.0, 0.0);
+        f.follower.poll_at(at(&f, last));
+        assert_eq!(f.transport.sync_state().status(), SyncStatus::Locking);
+    }
+
+    #[test]
+    fn test_drifting_then_relock() {
+        let mut f = fixture(SyncSource::MidiClock);
+
+        let last = send_clocks(&f, 0, 48, 120.0, 0.0);
+        f.follower.poll_at(at(&f, last));
+        assert_eq!(f.transport.sync_state().status(), SyncStatus::Locked);
+
+        // Erratic pulses: alternate early/late by 40% of the period
+        let period = 0.5 / CLOCKS_PER_BEAT;
+        let mut t = last;
+        for i in 0..24 {
+            t += period * if i % 2 == 0 { 0.6 } else { 1.4 };
+            f.ports
+                .push_input_sync_at(f.port, SyncMessage::Clock, at(&f, t));
+        }
+        f.follower.poll_at(at(&f, t));
+        assert_eq!(f.transport.sync_state().status(), SyncStatus::Drifting);
+
+        // Steady again
+        for i in 1..=96 {
+            f.ports
+                .push_input_sync_at(f.port, SyncMessage::Clock, at(&f, t + i as f64 * period));
+        }
+        f.follower.poll_at(at(&f, t + 96.0 * period));
+        assert_eq!(f.transport.sync_state().status(), SyncStatus::Locked);
+    }
+
+    #[test]
+    fn test_start_spp_and_stop() {
+        let mut f = fixture(SyncSource::MidiClock);
+
+        // SPP to bar 3 (32 sixteenths = 8 beats) while stopped
+        f.ports
+            .push_input_sync_at(f.port, SyncMessage::SongPosition(32), at(&f, 0.0));
+        f.follower.poll_at(at(&f, 0.0));
+        f.transport.process_commands();
+        assert!((f.transport.get_current_beat() - 8.0).abs() < 1e-9);
+
+        f.ports
+            .push_input_sync_at(f.port, SyncMessage::Continue, at(&f, 0.001));
+        let last = send_clocks(&f, 1, 48, 120.0, 0.0);
+        f.follower.poll_at(at(&f, last));
+        f.transport.process_commands();
+
+        assert!(!f.transport.is_paused());
+        // 48 pulses from beat 8: the last pulse was at 8 + 47/24
+        let external = f.transport.sync_state().external_position();
+        assert!((external - (8.0 + 47.0 / 24.0)).abs() < 1e-9);
+
+        f.ports
+            .push_input_sync_at(f.port, SyncMessage::Stop, at(&f, last + 0.001));
+        f.follower.poll_at(at(&f, last + 0.001));
+        f.transport.process_commands();
+        assert!(f.transport.is_paused());
+    }
+
+    #[test]
+    fn test_timeout_returns_to_locking() {
+        let mut f = fixture(SyncSource::MidiClock);
+
+        let last = send_clocks(&f, 0, 48, 120.0, 0.0);
+        f.follower.poll_at(at(&f, last));
+        assert_eq!(f.transport.sync_state().status(), SyncStatus::Locked);
+
+        f.follower.poll_at(at(&f, last + 1.0));
+        assert_eq!(f.transport.sync_state().status(), SyncStatus::Locking);
+        assert!(f.follower.estimated_tempo().is_none());
+    }
+
+    #[test]
+    fn test_ignores_clock_when_source_is_mtc() {
+        let mut f = fixture(SyncSource::MidiTimecode);
+
+        let last = send_clocks(&f, 0, 48, 90.0, 0.0);
+        assert_eq!(f.follower.poll_at(at(&f, last)), 48);
+        assert!((f.transport.get_tempo() - 120.0).abs() < 1e-3);
+    }
+
+    fn send_mtc(f: &Fixture, start: Timecode, rate: SmpteFrameRate, cycles: u64) -> f64 {
+        let quarter = 1.0 / (rate.fps() * 4.0);
+        let base = start.to_frame_count(rate);
+        let mut t = 0.0;
+        for cycle in 0..cycles {
+            let tc = Timecode::from_frame_count(base + cycle * 2, rate);
+            for (i, msg) in SyncMessage::quarter_frames(tc, rate)
+                .into_iter()
+                .enumerate()
+            {
+                t = (cycle * 8 + i as u64) as f64 * quarter;
+                f.ports.push_input_sync_at(f.port, msg, at(f, t));
+            }
+        }
+        t
+    }
+
+    #[test]
+    fn test_mtc_quarter_frames_lock_and_position() {
+        let mut f = fixture(SyncSource::MidiTimecode);
+        let rate = SmpteFrameRate::Fps25;
+        let start = Timecode::new(0, 0, 10, 0);
+
+        let last = send_mtc(&f, start, rate, 8);
+        f.follower.poll_at(at(&f, last));
+        f.transport.process_commands();
+
+        let state = f.transport.sync_state();
+        assert_eq!(state.status(), SyncStatus::Locked);
+        assert_eq!(state.smpte_frame_rate(), SmpteFrameRate::Fps25);
+        assert!(!f.transport.is_paused());
+
+        // Last cycle encodes 00:00:10:14, completed 1.75 frames later
+        let seconds = 10.0 + (14.0 + 1.75) / 25.0;
+        let expected = f.transport.seconds_to_beats(seconds);
+        assert!((state.external_position() - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_mtc_full_frame_locates() {
+        let mut f = fixture(SyncSource::MidiTimecode);
+        f.transport.set_sync_offset(SR / 2.0);
+
+        let timecode = Timecode::new(0, 0, 4, 0);
+        f.ports.push_input_sync_at(
+            f.port,
+            SyncMessage::MtcFullFrame {
+                timecode,
+                rate: SmpteFrameRate::Fps30,
+            },
+            at(&f, 0.0),
+        );
+        f.follower.poll_at(at(&f, 0.0));
+        f.transport.process_commands();
+
+        // 4 s at 120 BPM = 8 beats, plus the offset converted at the transport's rate
+        let offset_beats = (SR / 2.0) / f.transport.samples_per_beat();
+        assert!((f.transport.get_current_beat() - (8.0 + offset_beats)).abs() < 1e-6);
+        assert!(f.transport.is_paused());
+    }
+}
@@ -0,0 +1,675 @@
+//! Lead external gear from the transport: MIDI Clock, SPP and MTC master.
+//!
+//! [`MidiSyncGenerator`] is installed as the core's `TransportSyncOutput`.
+//! Each audio block it walks the per-frame transport positions and places
+//! clock pulses, Start/Stop/Continue, Song Position Pointer and MTC quarter
+//! frames on the frame they fall on. Messages are queued on an output port
+//! stamped with their send time (frame time plus output latency);
+//! [`SyncOutputSender`] delivers them to the hardware output at that time.
+
+use super::message::SyncMessage;
+use crate::port::async_port::SyncOutputProducerHandle;
+use crate::port::MidiPortManager;
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tutti_core::{SmpteFrameRate, SyncBlock, Timecode, TransportManager, TransportSyncOutput};
+
+/// MIDI Clock pulses per quarter note.
+const CLOCKS_PER_BEAT: f64 = 24.0;
+
+/// Clock pulses per MIDI beat (sixteenth note), the unit of SPP.
+const CLOCKS_PER_SIXTEENTH: i64 = 6;
+
+/// Per-frame position change above which the transport has jumped
+/// (far beyond any real tempo at any sample rate).
+const JUMP_THRESHOLD_BEATS: f64 = 1.0 / 48.0;
+
+const EPSILON: f64 = 1e-9;
+
+/// What [`MidiSyncGenerator`] sends and how it is timed.
+#[derive(Debug, Clone)]
+pub struct SyncGeneratorConfig {
+    /// 24-PPQN clock with Start/Stop/Continue and Song Position Pointer.
+    /// Default: true.
+    pub clock: bool,
+    /// Keep clocking at the current tempo while stopped so followers stay
+    /// locked. Default: true.
+    pub clock_while_stopped: bool,
+    /// MTC quarter frames at the transport's SMPTE rate, plus a full frame on
+    /// every locate. Default: false.
+    pub mtc: bool,
+    /// Audio output latency. Messages are delayed by this much so they line
+    /// up with what is heard rather than with what is rendered. Default: 0.
+    pub output_latency: Duration,
+    /// Latency of the MIDI path (driver + interface), subtracted from
+    /// `output_latency`. Default: 0.
+    pub midi_latency: Duration,
+}
+
+impl Default for SyncGeneratorConfig {
+    fn default() -> Self {
+        Self {
+            clock: true,
+            clock_while_stopped: true,
+            mtc: false,
+            output_latency: Duration::ZERO,
+            midi_latency: Duration::ZERO,
+        }
+    }
+}
+
+impl SyncGeneratorConfig {
+    pub fn clock(mut self, enabled: bool) -> Self {
+        self.clock = enabled;
+        self
+    }
+
+    pub fn clock_while_stopped(mut self, enabled: bool) -> Self {
+        self.clock_while_stopped = enabled;
+        self
+    }
+
+    pub fn mtc(mut self, enabled: bool) -> Self {
+        self.mtc = enabled;
+        self
+    }
+
+    pub fn output_latency(mut self, latency: Duration) -> Self {
+        self.output_latency = latency;
+        self
+    }
+
+    pub fn midi_latency(mut self, latency: Duration) -> Self {
+        self.midi_latency = latency;
+        self
+    }
+
+    /// Delay between a frame being rendered and its sync message being sent.
+    pub fn send_delay(&self) -> Duration {
+        self.output_latency.saturating_sub(self.midi_latency)
+    }
+}
+
+/// Audio-thread state, carried between blocks.
+#[derive(Debug, Default)]
+struct GeneratorState {
+    was_playing: bool,
+    last_beat: Option<f64>,
+    /// Next pulse to send while rolling, as an index on the 1/24-beat grid.
+    next_pulse: i64,
+    /// Fractional pulses accumulated while stopped.
+    idle_phase: f64,
+    /// Timeline position (seconds) of the current frame, for MTC.
+    mtc_seconds: f64,
+    /// Next quarter frame to send, as an index on the timeline's 1/4-frame grid.
+    next_quarter: i64,
+}
+
+/// Generates MIDI Clock / SPP / MTC from the transport onto an output port.
+///
+/// # Example
+/// ```ignore
+/// let generator = midi.sync_generator(
+///     clock_out,
+///     engine.transport_manager().clone(),
+///     SyncGeneratorConfig::default().mtc(true).output_latency(Duration::from_millis(10)),
+/// )?;
+/// system.set_sync_output(Some(generator));
+/// let _sender = midi.sync_sender(clock_out)?;
+/// ```
+pub struct MidiSyncGenerator {
+    transport: Arc<TransportManager>,
+    config: SyncGeneratorConfig,
+    port_index: usize,
+    producer: SyncOutputProducerHandle,
+    /// Keeps the port's ring alive for `producer`.
+    _port_manager: Arc<MidiPortManager>,
+    state: UnsafeCell<GeneratorState>,
+    dropped: AtomicU64,
+}
+
+// SAFETY: `state` and `producer` are only touched from `process_block`, which
+// the audio callback calls from a single thread.
+unsafe impl Sync for MidiSyncGenerator {}
+
+impl MidiSyncGenerator {
+    /// Returns `None` if `port_index` is not an output port.
+    pub fn new(
+        port_manager: Arc<MidiPortManager>,
+        port_index: usize,
+        transport: Arc<TransportManager>,
+        config: SyncGeneratorConfig,
+    ) -> Option<Self> {
+        let producer = port_manager.get_sync_output_producer_handle(port_index)?;
+        Some(Self {
+            transport,
+            config,
+            port_index,
+            producer,
+            _port_manager: port_manager,
+            state: UnsafeCell::new(GeneratorState::default()),
+            dropped: AtomicU64::new(0),
+        })
+    }
+
+    pub fn port_index(&self) -> usize {
+        self.port_index
+    }
+
+    pub fn config(&self) -> &SyncGeneratorConfig {
+        &self.config
+    }
+
+    /// Messages lost because the port's sync queue was full.
+    pub fn dropped_messages(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    fn emit(&self, message: SyncMessage, send_at: Instant) {
+        if !self.producer.push(message, send_at) {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Transport started rolling at `beat`.
+    fn start(&self, state: &mut GeneratorState, beat: f64, at: Instant) {
+        if self.config.clock {
+            if beat.abs() <= EPSILON {
+                self.emit(SyncMessage::Start, at);
+                state.next_pulse = 0;
+            } else {
+                self.resume(state, beat, at);
+            }
+        }
+        if self.config.mtc {
+            self.resync_mtc(state, beat);
+        }
+    }
+
+    /// SPP + Continue. Followers resume on the first clock, so clocking
+    /// restarts exactly at the SPP position (the next sixteenth).
+    fn resume(&self, state: &mut GeneratorState, beat: f64, at: Instant) {
+        let position = song_position(beat);
+        self.emit(SyncMessage::SongPosition(position), at);
+        self.emit(SyncMessage::Continue, at);
+        state.next_pulse = position as i64 * CLOCKS_PER_SIXTEENTH;
+    }
+
+    /// Transport jumped while rolling (locate or loop wrap).
+    fn jump(&self, state: &mut GeneratorState, beat: f64, at: Instant) {
+        if self.config.clock {
+            // SPP is only honoured while stopped
+            self.emit(SyncMessage::Stop, at);
+            self.resume(state, beat, at);
+        }
+        if self.config.mtc {
+            self.send_full_frame(beat, at);
+            self.resync_mtc(state, beat);
+        }
+    }
+
+    /// Transport moved while stopped.
+    fn locate(&self, beat: f64, at: Instant) {
+        if self.config.clock {
+            self.emit(SyncMessage::SongPosition(song_position(beat)), at);
+        }
+        if self.config.mtc {
+            self.send_full_frame(beat, at);
+        }
+    }
+
+    fn send_full_frame(&self, beat: f64, at: Instant) {
+        let rate = self.transport.sync_state().smpte_frame_rate();
+        let seconds = self
+            .transport
+            .tempo_map_shared()
+            .load()
+            .beats_to_seconds(beat);
+        let timecode = Timecode::from_seconds(seconds, rate);
+        self.emit(SyncMessage::MtcFullFrame { timecode, rate }, at);
+    }
+
+    fn resync_mtc(&self, state: &mut GeneratorState, beat: f64) {
+        let rate = self.transport.sync_state().smpte_frame_rate();
+        state.mtc_seconds = self
+            .transport
+            .tempo_map_shared()
+            .load()
+            .beats_to_seconds(beat)
+            .max(0.0);
+        state.next_quarter = (state.mtc_seconds * quarter_frame_rate(rate) - EPSILON).ceil() as i64;
+    }
+
+    fn process_stopped(&self, state: &mut GeneratorState, block: &SyncBlock<'_>, base: Instant) {
+        let beat = block.beats[0];
+
+        if state.was_playing && self.config.clock {
+            self.emit(SyncMessage::Stop, base);
+        }
+
+        // Right after stopping, the position has advanced one frame past
+        // `last_beat`; only a real jump counts as a locate.
+        let tolerance = if state.was_playing {
+            JUMP_THRESHOLD_BEATS
+        } else {
+            EPSILON
+        };
+        let moved = state
+            .last_beat
+            .is_none_or(|last| (last - beat).abs() > tolerance);
+        if moved {
+            self.locate(beat, base);
+        }
+
+        if self.config.clock && self.config.clock_while_stopped {
+            let pulses_per_frame =
+                self.transport.get_tempo() as f64 / 60.0 * CLOCKS_PER_BEAT / block.sample_rate;
+            for i in 0..block.frames() {
+                state.idle_phase += pulses_per_frame;
+                if state.idle_phase >= 1.0 {
+                    state.idle_phase -= 1.0;
+                    self.emit(SyncMessage::Clock, frame_time(base, i, block.sample_rate));
+                }
+            }
+        }
+
+        state.last_beat = Some(block.beats[block.frames() - 1]);
+    }
+
+    fn process_rolling(&self, state: &mut GeneratorState, block: &SyncBlock<'_>, base: Instant) {
+        let rate = self.transport.sync_state().smpte_frame_rate();
+        let quarters_per_second = quarter_frame_rate(rate);
+        let seconds_per_frame = 1.0 / block.sample_rate;
+
+        let mut prev = if state.was_playing {
+            state.last_beat
+        } else {
+            self.start(state, block.beats[0], base);
+            None
+        };
+
+        for (i, &beat) in block.beats.iter().enumerate() {
+            let at = frame_time(base, i, block.sample_rate);
+
+            if let Some(prev) = prev {
+                let step = beat - prev;
+                if !(-EPSILON..=JUMP_THRESHOLD_BEATS).contains(&step) {
+                    self.jump(state, beat, at);
+                }
+            }
+
+            if self.config.clock {
+                let pulse_position = beat * CLOCKS_PER_BEAT;
+                if pulse_position >= state.next_pulse as f64 - EPSILON {
+                    self.emit(SyncMessage::Clock, at);
+                    // One pulse per frame; never fall behind the grid
+                    state.next_pulse =
+                        (state.next_pulse + 1).max((pulse_position - EPSILON).floor() as i64 + 1);
+                }
+            }
+
+            if self.config.mtc {
+                if state.mtc_seconds * quarters_per_second >= state.next_quarter as f64 - EPSILON {
+                    let quarter = state.next_quarter;
+                    let piece = quarter.rem_euclid(8);
+                    // Each 8-piece cycle spans two frames and encodes the first
+                    let frame = ((quarter - piece) / 4).max(0) as u64;
+                    let timecode = Timecode::from_frame_count(frame, rate);
+                    let message = SyncMessage::quarter_frames(timecode, rate)[piece as usize];
+                    self.emit(message, at);
+                    state.next_quarter += 1;
+                }
+                state.mtc_seconds += seconds_per_frame;
+            }
+
+            prev = Some(beat);
+        }
+
+        state.last_beat = prev;
+    }
+}
+
+impl TransportSyncOutput for MidiSyncGenerator {
+    fn process_block(&self, block: &SyncBlock<'_>) {
+        if block.beats.is_empty() {
+            return;
+        }
+
+        // SAFETY: single caller (audio thread), see `unsafe impl Sync`.
+        let state = unsafe { &mut *self.state.get() };
+        let base = block.buffer_start + self.config.send_delay();
+
+        if block.playing {
+            self.process_rolling(state, block, base);
+        } else {
+            self.process_stopped(state, block, base);
+        }
+        state.was_playing = block.playing;
+    }
+}
+
+/// SPP value (sixteenths) of the first sixteenth at or after `beat`.
+#[inline]
+fn song_position(beat: f64) -> u16 {
+    (beat * 4.0 - EPSILON).ceil().clamp(0.0, 16383.0) as u16
+}
+
+#[inline]
+fn quarter_frame_rate(rate: SmpteFrameRate) -> f64 {
+    rate.fps() * 4.0
+}
+
+#[inline]
+fn frame_time(base: Instant, frame: usize, sample_rate: f64) -> Instant {
+    base + Duration::from_secs_f64(frame as f64 / sample_rate)
+}
+
+/// Sends an output port's generated sync messages to the connected MIDI
+/// output device at their scheduled times. Stops on drop.
+#[cfg(feature = "midi-io")]
+pub struct SyncOutputSender {
+    running: Arc<std::sync::atomic::AtomicBool>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+#[cfg(feature = "midi-io")]
+impl SyncOutputSender {
+    /// Longest the sender sleeps before checking for new messages.
+    const MAX_SLEEP: Duration = Duration::from_millis(1);
+
+    pub fn spawn(
+        port_manager: Arc<MidiPortManager>,
+        port_index: usize,
+        output: Arc<crate::io::MidiOutputManager>,
+    ) -> Self {
+        let running = Arc::new(std::sync::atomic::AtomicBool::new(true));
+        let thread_running = running.clone();
+
+        let thread = std::thread::Builder::new()
+            .name("tutti-midi-sync-out".into())
+            .spawn(move || {
+                let mut incoming = Vec::with_capacity(256);
+                let mut pending = std::collections::VecDeque::with_capacity(256);
+                let mut bytes = [0u8; 10];
+
+                while thread_running.load(Ordering::Acquire) {
+                    incoming.clear();
+                    port_manager.read_sync_output_into(port_index, &mut incoming);
+                    pending.extend(incoming.drain(..));
+
+                    let now = Instant::now();
+                    while let Some(&(send_at, message)) = pending.front() {
+                        if send_at > now {
+                            break;
+                        }
+                        pending.pop_front();
+                        let len = message.to_bytes(&mut bytes);
+                        output.send_message(crate::MidiOutputMessage {
+                            bytes: bytes[..len].to_vec(),
+                        });
+                    }
+
+                    let sleep = pending
+                        .front()
+                        .map(|&(send_at, _)| send_at.saturating_duration_since(now))
+                        .unwrap_or(Self::MAX_SLEEP)
+                        .min(Self::MAX_SLEEP);
+                    std::thread::sleep(sleep);
+                }
+            })
+            .expect("failed to spawn MIDI sync output thread");
+
+        Self {
+            running,
+            thread: Some(thread),
+        }
+    }
+
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.running.store(false, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(feature = "midi-io")]
+impl Drop for SyncOutputSender {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::MidiSyncFollower;
+    use tutti_core::{SyncSource, SyncStatus};
+
+    const SR: f64 = 48000.0;
+    const BLOCK: usize = 480;
+
+    struct Fixture {
+        ports: Arc<MidiPortManager>,
+        port: usize,
+        transport: Arc<TransportManager>,
+        generator: MidiSyncGenerator,
+        start: Instant,
+    }
+
+    fn fixture(config: SyncGeneratorConfig) -> Fixture {
+        let ports = Arc::new(MidiPortManager::new(8192));
+        let port = ports.create_output_port("Sync Out");
+        let transport = Arc::new(TransportManager::default());
+        let generator =
+            MidiSyncGenerator::new(ports.clone(), port, transport.clone(), config).unwrap();
+        Fixture {
+            ports,
+            port,
+            transport,
+            generator,
+            start: Instant::now(),
+        }
+    }
+
+    /// Render `blocks` blocks starting at `first_frame`, with `beat_at(frame)`.
+    fn run(
+        f: &Fixture,
+        first_frame: usize,
+        blocks: usize,
+        playing: bool,
+        beat_at: impl Fn(usize) -> f64,
+    ) {
+        let mut beats = vec![0.0; BLOCK];
+        for b in 0..blocks {
+            let offset = first_frame + b * BLOCK;
+            for (i, beat) in beats.iter_mut().enumerate() {
+                *beat = beat_at(offset + i);
+            }
+            f.generator.process_block(&SyncBlock {
+                beats: &beats,
+                playing,
+                sample_rate: SR,
+                buffer_start: frame_time(f.start, offset, SR),
+            });
+        }
+    }
+
+    fn drain(f: &Fixture) -> Vec<(usize, SyncMessage)> {
+        let mut out = Vec::new();
+        f.ports.read_sync_output_into(f.port, &mut out);
+        out.into_iter()
+            .map(|(at, msg)| {
+                let frame = (at.duration_since(f.start).as_secs_f64() * SR).round() as usize;
+                (frame, msg)
+            })
+            .collect()
+    }
+
+    /// 120 BPM: one beat every 24000 frames, one clock every 1000.
+    fn beat_120(frame: usize) -> f64 {
+        frame as f64 / 24000.0
+    }
+
+    fn clocks(messages: &[(usize, SyncMessage)]) -> Vec<usize> {
+        messages
+            .iter()
+            .filter(|(_, m)| *m == SyncMessage::Clock)
+            .map(|(frame, _)| *frame)
+            .collect()
+    }
+
+    #[test]
+    fn test_start_and_clock_frames() {
+        let f = fixture(SyncGeneratorConfig::default());
+        run(&f, 0, 100, true, beat_120);
+
+        let messages = drain(&f);
+        assert_eq!(messages[0], (0, SyncMessage::Start));
+        let clocks = clocks(&messages);
+        assert_eq!(clocks.len(), 48);
+        for (k, frame) in clocks.iter().enumerate() {
+            assert_eq!(*frame, k * 1000);
+        }
+    }
+
+    #[test]
+    fn test_continue_from_mid_bar_waits_for_spp_position() {
+        let f = fixture(SyncGeneratorConfig::default());
+        // Start rolling at beat 8.1 (frame 194400)
+        run(&f, 194_400, 10, true, beat_120);
+
+        let messages = drain(&f);
+        assert_eq!(messages[0], (194_400, SyncMessage::SongPosition(33)));
+        assert_eq!(messages[1], (194_400, SyncMessage::Continue));
+        // First clock at beat 8.25
+        assert_eq!(clocks(&messages)[0], 198_000);
+    }
+
+    #[test]
+    fn test_loop_wrap_sends_stop_spp_continue() {
+        let f = fixture(SyncGeneratorConfig::default().clock_while_stopped(false));
+        // 4-beat loop at 120 BPM: wraps at frame 96000
+        run(&f, 0, 220, true, |frame| beat_120(frame % 96_000));
+
+        let messages = drain(&f);
+        let wrap: Vec<_> = messages
+            .iter()
+            .filter(|(frame, _)| *frame == 96_000)
+            .map(|(_, m)| *m)
+            .collect();
+        assert_eq!(
+            wrap,
+            vec![
+                SyncMessage::Stop,
+                SyncMessage::SongPosition(0),
+                SyncMessage::Continue,
+                SyncMessage::Clock,
+            ]
+        );
+        // No doubled or missing pulses across the wrap
+        let clocks = clocks(&messages);
+        assert!(clocks.windows(2).all(|w| w[1] - w[0] == 1000));
+    }
+
+    #[test]
+    fn test_stop_locate_and_idle_clock() {
+        let f = fixture(SyncGeneratorConfig::default());
+        run(&f, 0, 10, true, beat_120);
+        drain(&f);
+
+        // Stop at frame 4800, then locate to beat 16 while stopped
+        run(&f, 4800, 1, false, |_| 0.2);
+        run(&f, 5280, 100, false, |_| 16.0);
+
+        let messages = drain(&f);
+        assert_eq!(messages[0], (4800, SyncMessage::Stop));
+        assert!(messages.contains(&(5280, SyncMessage::SongPosition(64))));
+        let spp_count = messages
+            .iter()
+            .filter(|(_, m)| matches!(m, SyncMessage::SongPosition(_)))
+            .count();
+        assert_eq!(spp_count, 1);
+
+        // Tempo keeps flowing while stopped (120 BPM -> 48 pulses/s)
+        let clocks = clocks(&messages);
+        assert!((47..=49).contains(&clocks.len()), "{}", clocks.len());
+    }
+
+    #[test]
+    fn test_output_latency_delays_messages() {
+        let f = fixture(
+            SyncGeneratorConfig::default()
+                .output_latency(Duration::from_millis(15))
+                .midi_latency(Duration::from_millis(5)),
+        );
+        run(&f, 0, 4, true, beat_120);
+
+        let messages = drain(&f);
+        // 10 ms = 480 frames
+        assert_eq!(messages[0], (480, SyncMessage::Start));
+        assert_eq!(clocks(&messages)[..2], [480, 1480]);
+    }
+
+    #[test]
+    fn test_mtc_quarter_frames_encode_timeline() {
+        let f = fixture(SyncGeneratorConfig::default().clock(false).mtc(true));
+        f.transport.set_smpte_frame_rate(SmpteFrameRate::Fps25);
+        run(&f, 0, 100, true, beat_120);
+
+        let messages = drain(&f);
+        // 25 fps -> 100 quarter frames per second, every 480 frames
+        assert_eq!(messages.len(), 100);
+        for (q, (frame, _)) in messages.iter().enumerate() {
+            assert_eq!(*frame, q * 480);
+        }
+
+        // Second cycle encodes frame 2
+        let mut nibbles = [0u8; 8];
+        for (_, message) in &messages[8..16] {
+            let SyncMessage::MtcQuarterFrame { piece, value } = *message else {
+                panic!("expected quarter frame, got {message:?}");
+            };
+            nibbles[piece as usize] = value;
+        }
+        assert_eq!(nibbles[0] | (nibbles[1] << 4), 2);
+        assert_eq!(nibbles[7] >> 1, SmpteFrameRate::Fps25.mtc_code());
+    }
+
+    #[test]
+    fn test_loopback_into_follower() {
+        let input_ports = Arc::new(MidiPortManager::new(8192));
+        let input = input_ports.create_input_port("Loopback");
+        let slave = Arc::new(TransportManager::default());
+        slave.set_sync_source(SyncSource::MidiClock);
+        slave.set_following(true);
+        // Created first: the follower measures time from its construction
+        let mut follower = MidiSyncFollower::new(input_ports.clone(), input, slave.clone());
+
+        let f = fixture(SyncGeneratorConfig::default());
+        // 125 BPM at 48 kHz: 23040 frames per beat, one clock every 960
+        run(&f, 0, 200, true, |frame| frame as f64 / 23_040.0);
+
+        let mut sent = Vec::new();
+        f.ports.read_sync_output_into(f.port, &mut sent);
+        let last = sent.last().unwrap().0;
+        for (at, message) in sent {
+            input_ports.push_input_sync_at(input, message, at);
+        }
+
+        follower.poll_at(last);
+
+        assert_eq!(slave.sync_state().status(), SyncStatus::Locked);
+        assert!((follower.estimated_tempo().unwrap() - 125.0).abs() < 0.1);
+        // 200 blocks = 96000 frames; the last pulse (frame 95040) is beat 4 + 1/8
+        let expected = 95_040.0 / 23_040.0;
+        assert!((slave.sync_state().external_position() - expected).abs() < 1e-9);
+    }
+}
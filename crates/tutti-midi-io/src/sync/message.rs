@@ -0,0 +1,189 @@
+//! MIDI timing messages: clock, transport, Song Position Pointer and MTC.
+
+use tutti_core::{SmpteFrameRate, Timecode};
+
+/// A system real-time or system common message used for synchronization.
+///
+/// [`MidiEvent`](crate::MidiEvent) only carries channel voice messages, so these
+/// travel on a separate per-port queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncMessage {
+    /// 0xF8 — 24 per quarter note.
+    Clock,
+    /// 0xFA — play from the top.
+    Start,
+    /// 0xFB — resume from the last Song Position Pointer.
+    Continue,
+    /// 0xFC
+    Stop,
+    /// 0xF2 — position in MIDI beats (sixteenth notes).
+    SongPosition(u16),
+    /// 0xF1 — one nibble of the running timecode.
+    MtcQuarterFrame { piece: u8, value: u8 },
+    /// Universal real-time SysEx `F0 7F 7F 01 01 hh mm ss ff F7`.
+    MtcFullFrame {
+        timecode: Timecode,
+        rate: SmpteFrameRate,
+    },
+}
+
+impl SyncMessage {
+    /// Parse a raw message. Returns `None` for anything that is not a sync message.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        match *bytes.first()? {
+            0xF8 => Some(Self::Clock),
+            0xFA => Some(Self::Start),
+            0xFB => Some(Self::Continue),
+            0xFC => Some(Self::Stop),
+            0xF2 if bytes.len() >= 3 => Some(Self::SongPosition(
+                (bytes[1] as u16 & 0x7F) | ((bytes[2] as u16 & 0x7F) << 7),
+            )),
+            0xF1 if bytes.len() >= 2 => Some(Self::MtcQuarterFrame {
+                piece: (bytes[1] >> 4) & 0x07,
+                value: bytes[1] & 0x0F,
+            }),
+            0xF0 if bytes.len() >= 10 && bytes[1..5] == [0x7F, 0x7F, 0x01, 0x01] => {
+                Some(Self::MtcFullFrame {
+                    timecode: Timecode::new(bytes[5] & 0x1F, bytes[6], bytes[7], bytes[8]),
+                    rate: SmpteFrameRate::from_mtc_code(bytes[5] >> 5),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Encode into `buf`, returning the number of bytes written (at most 10).
+    pub fn to_bytes(&self, buf: &mut [u8; 10]) -> usize {
+        match *self {
+            Self::Clock => {
+                buf[0] = 0xF8;
+                1
+            }
+            Self::Start => {
+                buf[0] = 0xFA;
+                1
+            }
+            Self::Continue => {
+                buf[0] = 0xFB;
+                1
+            }
+            Self::Stop => {
+                buf[0] = 0xFC;
+                1
+            }
+            Self::SongPosition(position) => {
+                buf[0] = 0xF2;
+                buf[1] = (position & 0x7F) as u8;
+                buf[2] = ((position >> 7) & 0x7F) as u8;
+                3
+            }
+            Self::MtcQuarterFrame { piece, value } => {
+                buf[0] = 0xF1;
+                buf[1] = ((piece & 0x07) << 4) | (value & 0x0F);
+                2
+            }
+            Self::MtcFullFrame { timecode, rate } => {
+                buf[..5].copy_from_slice(&[0xF0, 0x7F, 0x7F, 0x01, 0x01]);
+                buf[5] = (rate.mtc_code() << 5) | (timecode.hours & 0x1F);
+                buf[6] = timecode.minutes;
+                buf[7] = timecode.seconds;
+                buf[8] = timecode.frames;
+                buf[9] = 0xF7;
+                10
+            }
+        }
+    }
+
+    /// The eight quarter-frame messages that spell out `timecode`, in send order.
+    pub fn quarter_frames(timecode: Timecode, rate: SmpteFrameRate) -> [Self; 8] {
+        let hours = (timecode.hours & 0x1F) | (rate.mtc_code() << 5);
+        let fields = [timecode.frames, timecode.seconds, timecode.minutes, hours];
+        core::array::from_fn(|i| {
+            let field = fields[i / 2];
+            let value = if i % 2 == 0 { field & 0x0F } else { field >> 4 };
+            Self::MtcQuarterFrame {
+                piece: i as u8,
+                value,
+            }
+        })
+    }
+
+    pub fn is_real_time(&self) -> bool {
+        matches!(
+            self,
+            Self::Clock | Self::Start | Self::Continue | Self::Stop
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_real_time() {
+        assert_eq!(SyncMessage::from_bytes(&[0xF8]), Some(SyncMessage::Clock));
+        assert_eq!(SyncMessage::from_bytes(&[0xFA]), Some(SyncMessage::Start));
+        assert_eq!(
+            SyncMessage::from_bytes(&[0xFB]),
+            Some(SyncMessage::Continue)
+        );
+        assert_eq!(SyncMessage::from_bytes(&[0xFC]), Some(SyncMessage::Stop));
+        assert_eq!(SyncMessage::from_bytes(&[0xFE]), None);
+        assert_eq!(SyncMessage::from_bytes(&[0x90, 60, 100]), None);
+        assert_eq!(SyncMessage::from_bytes(&[]), None);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let messages = [
+            SyncMessage::Clock,
+            SyncMessage::Continue,
+            SyncMessage::SongPosition(0x1234),
+            SyncMessage::MtcQuarterFrame { piece: 5, value: 9 },
+            SyncMessage::MtcFullFrame {
+                timecode: Timecode::new(1, 2, 3, 4),
+                rate: SmpteFrameRate::Fps25,
+            },
+        ];
+
+        for msg in messages {
+            let mut buf = [0u8; 10];
+            let len = msg.to_bytes(&mut buf);
+            assert_eq!(SyncMessage::from_bytes(&buf[..len]), Some(msg));
+        }
+    }
+
+    #[test]
+    fn test_song_position_is_14_bit() {
+        // 0xF2 lsb=0x10 msb=0x01 → 0x90 sixteenths
+        assert_eq!(
+            SyncMessage::from_bytes(&[0xF2, 0x10, 0x01]),
+            Some(SyncMessage::SongPosition(0x90))
+        );
+    }
+
+    #[test]
+    fn test_quarter_frames_encode_rate() {
+        let pieces = SyncMessage::quarter_frames(
+            Timecode::new(0x11, 0x22, 0x33, 0x14),
+            SmpteFrameRate::Fps2997Df,
+        );
+        assert_eq!(
+            pieces[0],
+            SyncMessage::MtcQuarterFrame { piece: 0, value: 4 }
+        );
+        assert_eq!(
+            pieces[1],
+            SyncMessage::MtcQuarterFrame { piece: 1, value: 1 }
+        );
+        // Piece 7 carries the hour's high bit and the rate code in bits 1-2
+        assert_eq!(
+            pieces[7],
+            SyncMessage::MtcQuarterFrame {
+                piece: 7,
+                value: (2 << 1) | 1
+            }
+        );
+    }
+}
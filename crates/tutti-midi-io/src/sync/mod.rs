@@ -0,0 +1,11 @@
+//! MIDI Clock, Song Position Pointer and MIDI Time Code sync.
+
+mod follower;
+mod generator;
+mod message;
+
+pub use follower::{MidiSyncFollower, SyncFollowerConfig, SyncFollowerHandle};
+#[cfg(feature = "midi-io")]
+pub use generator::SyncOutputSender;
+pub use generator::{MidiSyncGenerator, SyncGeneratorConfig};
+pub use message::SyncMessage;
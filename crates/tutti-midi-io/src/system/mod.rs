@@ -17,13 +17,11 @@ pub use mpe_handle::MpeHandle;
 pub use midi2_handle::Midi2Handle;
 
 #[cfg(feature = "midi-io")]
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::event::MidiEvent;
 use crate::port::{MidiPortManager, PortInfo, PortType};
 use std::sync::Arc;
 
-#[cfg(feature = "midi-io")]
-use crate::error::Error;
 #[cfg(feature = "midi-io")]
 use crate::io::{MidiInputDevice, MidiInputManager, MidiOutputManager, MidiOutputMessage};
 
@@ -334,6 +332,49 @@ impl MidiSystem {
         self.inner.port_manager.clone()
     }
 
+    /// Follow MIDI Clock / MTC arriving on `port_index` into `transport`.
+    pub fn sync_follower(
+        &self,
+        port_index: usize,
+        transport: Arc<tutti_core::TransportManager>,
+    ) -> crate::MidiSyncFollower {
+        crate::MidiSyncFollower::new(self.inner.port_manager.clone(), port_index, transport)
+    }
+
+    /// Generate MIDI Clock / SPP / MTC from `transport` onto output port
+    /// `port_index`. Install the result with `TuttiSystem::set_sync_output`.
+    pub fn sync_generator(
+        &self,
+        port_index: usize,
+        transport: Arc<tutti_core::TransportManager>,
+        config: crate::SyncGeneratorConfig,
+    ) -> Result<Arc<crate::MidiSyncGenerator>> {
+        crate::MidiSyncGenerator::new(
+            self.inner.port_manager.clone(),
+            port_index,
+            transport,
+            config,
+        )
+        .map(Arc::new)
+        .ok_or_else(|| Error::MidiPort(format!("No MIDI output port {}", port_index)))
+    }
+
+    /// Deliver the sync messages generated for `port_index` to the connected
+    /// hardware output.
+    #[cfg(feature = "midi-io")]
+    pub fn sync_sender(&self, port_index: usize) -> Result<crate::SyncOutputSender> {
+        let output = self
+            .inner
+            .output_manager
+            .clone()
+            .ok_or_else(|| Error::InvalidConfig("MIDI output not enabled".to_string()))?;
+        Ok(crate::SyncOutputSender::spawn(
+            self.inner.port_manager.clone(),
+            port_index,
+            output,
+        ))
+    }
+
     #[cfg(feature = "midi-io")]
     pub fn output_manager(&self) -> Option<Arc<MidiOutputManager>> {
         self.inner.output_manager.clone()
@@ -489,6 +489,8 @@ fn test_midi_file_to_port_pipeline() {
         0x00, 0x90, 60, 100, // beat 0: Note On
         0x83, 0x60, 0x80, 60, 0, // beat 1: Note Off
         0x00, 0x90, 64, 80, // beat 1: Note On
+        0x00, 0xA0, 64, 50, // beat 1: Poly Aftertouch
+        0x00, 0xD0, 70, // beat 1: Channel Aftertouch
         0x83, 0x60, 0x80, 64, 0, // beat 2: Note Off
     ];
 
@@ -517,19 +519,36 @@ fn test_midi_file_to_port_pipeline() {
                 MidiEvent::pitch_bend(0, timed_event.channel, unsigned)
             }
             tutti_midi_io::MidiEventType::ProgramChange { .. } => continue,
+            tutti_midi_io::MidiEventType::PolyAftertouch { note, pressure } => {
+                MidiEvent::poly_aftertouch(0, timed_event.channel, note, pressure)
+            }
+            tutti_midi_io::MidiEventType::ChannelAftertouch { pressure } => {
+                MidiEvent::aftertouch(0, timed_event.channel, pressure)
+            }
         };
         assert!(handle.push(event, Instant::now()));
     }
 
     // Read all
     let events = pm.cycle_start_read_all_inputs(512, Instant::now(), 44100.0);
-    assert_eq!(events.len(), 4);
+    assert_eq!(events.len(), 6);
 
-    // Verify ordering: on, off, on, off
+    // Verify ordering: on, off, on, pressure, pressure, off
     assert!(events[0].1.is_note_on());
     assert!(events[1].1.is_note_off());
     assert!(events[2].1.is_note_on());
-    assert!(events[3].1.is_note_off());
+    assert!(matches!(
+        events[3].1.msg,
+        tutti_midi_io::ChannelVoiceMsg::PolyPressure {
+            note: 64,
+            pressure: 50
+        }
+    ));
+    assert!(matches!(
+        events[4].1.msg,
+        tutti_midi_io::ChannelVoiceMsg::ChannelPressure { pressure: 70 }
+    ));
+    assert!(events[5].1.is_note_off());
 }
 
 // ---------------------------------------------------------------------------
@@ -6,7 +6,7 @@ use crate::instance::{PluginInstance, ProcessContext, ProcessOutput};
 use std::path::Path;
 use tutti_plugin::protocol::{
     AudioBuffer, AudioBuffer64, NoteExpressionChanges, ParameterChanges, ParameterFlags,
-    ParameterInfo,
+    ParameterInfo, ProcessMode,
 };
 use tutti_plugin::{BridgeError, LoadStage, PluginMetadata, Result};
 
@@ -174,6 +174,11 @@ impl PluginInstance for ClapInstance {
         self.inner.set_sample_rate(rate);
     }
 
+    fn set_process_mode(&mut self, mode: ProcessMode) {
+        // Plugins without the render extension just keep running as usual
+        let _ = self.inner.set_render_mode(mode == ProcessMode::Offline);
+    }
+
     fn get_parameter_count(&self) -> usize {
         self.inner.parameter_count()
     }
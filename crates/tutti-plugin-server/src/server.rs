@@ -4,7 +4,7 @@ use crate::instance::{PluginInstance, ProcessContext};
 use crate::transport::{MessageTransport, TransportListener};
 use std::path::PathBuf;
 
-use tutti_plugin::protocol::{BridgeMessage, HostMessage, IpcMidiEvent};
+use tutti_plugin::protocol::{BridgeMessage, HostMessage, IpcMidiEvent, ProcessMode};
 use tutti_plugin::shared_memory::SharedAudioBuffer;
 use tutti_plugin::{BridgeConfig, BridgeError, LoadStage, PluginMetadata, Result, SampleFormat};
 
@@ -33,6 +33,9 @@ pub struct PluginServer {
     /// Current sample rate (f64 for precision, matches VST3/CLAP)
     sample_rate: f64,
 
+    /// Realtime or offline; applied to plugins loaded later as well
+    process_mode: ProcessMode,
+
     // Pre-allocated audio buffers (RT-safe: resize only on config change)
     input_buffers_f32: Vec<Vec<f32>>,
     output_buffers_f32: Vec<Vec<f32>>,
@@ -147,6 +150,7 @@ impl PluginServer {
             editor_open: false,
             negotiated_format: SampleFormat::Float32,
             sample_rate: 44100.0, // Default, updated when plugin is loaded
+            process_mode: ProcessMode::Realtime,
             input_buffers_f32: Vec::new(),
             output_buffers_f32: Vec::new(),
             input_buffers_f64: Vec::new(),
@@ -361,6 +365,15 @@ impl PluginServer {
                 Ok(None)
             }
 
+            HostMessage::SetProcessMode { mode } => {
+                self.process_mode = mode;
+                if let Some(ref mut plugin) = self.plugin {
+                    plugin.as_instance_mut().set_process_mode(mode);
+                }
+
+                Ok(None)
+            }
+
             HostMessage::Reset => Ok(None),
 
             HostMessage::SaveState => {
@@ -604,7 +617,7 @@ impl PluginServer {
 
         let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
 
-        let (plugin, metadata): (LoadedPlugin, PluginMetadata) = match extension
+        let (mut plugin, metadata): (LoadedPlugin, PluginMetadata) = match extension
             .to_lowercase()
             .as_str()
         {
@@ -644,6 +657,10 @@ impl PluginServer {
             }
         };
 
+        if self.process_mode != ProcessMode::Realtime {
+            plugin.as_instance_mut().set_process_mode(self.process_mode);
+        }
+
         let negotiated_format =
             if preferred_format == SampleFormat::Float64 && metadata.supports_f64 {
                 SampleFormat::Float64
@@ -868,6 +885,19 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_set_process_mode_no_plugin() {
+        let mut server = test_server("set_mode").await;
+        let result = server
+            .handle_message(HostMessage::SetProcessMode {
+                mode: ProcessMode::Offline,
+            })
+            .await
+            .unwrap();
+        assert!(result.is_none());
+        assert_eq!(server.process_mode, ProcessMode::Offline);
+    }
+
     #[tokio::test]
     async fn test_save_state_no_plugin() {
         let mut server = test_server("save_state").await;
@@ -3,7 +3,7 @@
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 use tutti_midi_io::{ChannelVoiceMsg, ControlChange};
-use tutti_plugin::protocol::{AudioBuffer, MidiEvent};
+use tutti_plugin::protocol::{AudioBuffer, MidiEvent, ProcessMode};
 use tutti_plugin::{BridgeError, LoadStage, PluginMetadata, Result};
 
 #[cfg(feature = "vst2")]
@@ -52,6 +52,8 @@ pub struct Vst2Instance {
     params: SendParams,
     metadata: PluginMetadata,
     sample_rate: f64,
+    /// Last mode the host asked for; the plugin itself stays realtime.
+    requested_mode: ProcessMode,
     #[cfg(feature = "vst2")]
     param_rx: crossbeam_channel::Receiver<ParameterChange>,
 }
@@ -129,6 +131,7 @@ impl Vst2Instance {
                 params,
                 metadata,
                 sample_rate,
+                requested_mode: ProcessMode::Realtime,
                 param_rx,
             })
         }
@@ -431,6 +434,33 @@ impl Vst2Instance {
         }
     }
 
+    /// Prepare for realtime or offline rendering.
+    ///
+    /// VST2 plugins only learn about offline rendering by asking for
+    /// `kVstProcessLevelOffline` through `audioMasterGetCurrentProcessLevel`,
+    /// which the `vst` crate's host dispatcher answers itself. The plugin
+    /// can't be told, so it keeps rendering at the realtime level and
+    /// [`process_mode`](Self::process_mode) says so. A switch still suspends
+    /// and resumes it, so a bounce starts from a clean processing state.
+    pub fn set_process_mode(&mut self, mode: ProcessMode) {
+        if mode == self.requested_mode {
+            return;
+        }
+        self.requested_mode = mode;
+
+        #[cfg(feature = "vst2")]
+        {
+            self.instance.suspend();
+            self.instance.resume();
+        }
+    }
+
+    /// The mode the plugin renders in: always [`ProcessMode::Realtime`], see
+    /// [`set_process_mode`](Self::set_process_mode).
+    pub fn process_mode(&self) -> ProcessMode {
+        ProcessMode::Realtime
+    }
+
     pub fn has_editor(&self) -> bool {
         self.metadata.has_editor
     }
@@ -873,6 +903,10 @@ impl crate::instance::PluginInstance for Vst2Instance {
         Vst2Instance::set_sample_rate(self, rate);
     }
 
+    fn set_process_mode(&mut self, mode: ProcessMode) {
+        Vst2Instance::set_process_mode(self, mode);
+    }
+
     fn get_parameter_count(&self) -> usize {
         Vst2Instance::get_parameter_count(self) as usize
     }
@@ -1567,6 +1601,23 @@ mod tests {
         PluginInstance::stop_processing(&mut instance);
     }
 
+    #[test]
+    fn test_vst2_process_mode() {
+        let _lock = crate::test_utils::PLUGIN_LOAD_LOCK.lock().unwrap();
+        let path = Path::new(VST2_PLUGIN);
+        let mut instance =
+            Vst2Instance::load(path, 44100.0, 512).expect("Failed to load VST2 plugin");
+        assert_eq!(instance.process_mode(), ProcessMode::Realtime);
+
+        // Through the trait, as the server's SetProcessMode handler does. The
+        // plugin is never told, so it isn't reported as offline either
+        PluginInstance::set_process_mode(&mut instance, ProcessMode::Offline);
+        assert_eq!(instance.process_mode(), ProcessMode::Realtime);
+
+        // Resumed after the switch, so it keeps processing
+        load_and_process_block(&mut instance, 512, &[]);
+    }
+
     #[test]
     fn test_vst2_many_midi_events() {
         let _lock = crate::test_utils::PLUGIN_LOAD_LOCK.lock().unwrap();
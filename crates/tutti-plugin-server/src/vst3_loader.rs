@@ -4,7 +4,7 @@ use std::path::Path;
 
 use tutti_plugin::protocol::{
     AudioBuffer, AudioBuffer64, MidiEvent, MidiEventVec, NoteExpressionChanges, NoteExpressionType,
-    ParameterChanges, ParameterPoint, ParameterQueue, TransportInfo,
+    ParameterChanges, ParameterPoint, ParameterQueue, ProcessMode, TransportInfo,
 };
 use tutti_plugin::{BridgeError, LoadStage, PluginMetadata, Result};
 
@@ -287,6 +287,20 @@ impl Vst3Instance {
         self.inner.set_sample_rate(rate);
     }
 
+    /// Prepare for realtime or offline rendering.
+    ///
+    /// TODO: pass `kOffline` through `IAudioProcessor::setupProcessing` once
+    /// `vst3_host` can set up an inactive component again. Until then the
+    /// plugin keeps rendering in `kRealtime` and
+    /// [`process_mode`](Self::process_mode) says so.
+    pub fn set_process_mode(&mut self, _mode: ProcessMode) {}
+
+    /// The mode the plugin renders in: always [`ProcessMode::Realtime`], see
+    /// [`set_process_mode`](Self::set_process_mode).
+    pub fn process_mode(&self) -> ProcessMode {
+        ProcessMode::Realtime
+    }
+
     pub fn get_parameter_count(&self) -> i32 {
         self.inner.get_parameter_count()
     }
@@ -752,6 +766,10 @@ impl crate::instance::PluginInstance for Vst3Instance {
         self.inner.set_sample_rate(rate);
     }
 
+    fn set_process_mode(&mut self, mode: ProcessMode) {
+        Vst3Instance::set_process_mode(self, mode);
+    }
+
     fn get_parameter_count(&self) -> usize {
         self.inner.get_parameter_count() as usize
     }
@@ -1185,4 +1203,41 @@ mod tests {
             output_data[0][0], has_nonzero
         );
     }
+
+    #[test]
+    fn test_voxengo_span_offline_process_mode() {
+        let _lock = crate::test_utils::PLUGIN_LOAD_LOCK.lock().unwrap();
+        let path = fixture_path(SPAN_VST3);
+        if !path.exists() {
+            eprintln!("Skipping: SPAN.vst3 not found at {:?}", path);
+            return;
+        }
+        let mut instance = Vst3Instance::load(&path, 44100.0, 512).expect("Failed to load SPAN");
+        assert_eq!(instance.process_mode(), ProcessMode::Realtime);
+
+        // Through the trait, as the server's SetProcessMode handler does. The
+        // plugin isn't set up for offline yet, so it isn't reported as such
+        PluginInstance::set_process_mode(&mut instance, ProcessMode::Offline);
+        assert_eq!(instance.process_mode(), ProcessMode::Realtime);
+
+        // Still processes after the request
+        let num_samples = 512;
+        let input_data = vec![vec![0.25f32; num_samples]; 2];
+        let mut output_data = vec![vec![0.0f32; num_samples]; 2];
+        let input_slices: Vec<&[f32]> = input_data.iter().map(|v| v.as_slice()).collect();
+        let mut output_slices: Vec<&mut [f32]> =
+            output_data.iter_mut().map(|v| v.as_mut_slice()).collect();
+        let mut buffer = AudioBuffer {
+            inputs: &input_slices,
+            outputs: &mut output_slices,
+            num_samples,
+            sample_rate: 44100.0,
+        };
+        let ctx = crate::instance::ProcessContext::new();
+        let _output = PluginInstance::process_f32(&mut instance, &mut buffer, &ctx);
+        assert!(output_data.iter().flatten().all(|s| s.is_finite()));
+
+        PluginInstance::set_process_mode(&mut instance, ProcessMode::Realtime);
+        assert_eq!(instance.process_mode(), ProcessMode::Realtime);
+    }
 }
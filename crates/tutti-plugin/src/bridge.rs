@@ -2,7 +2,8 @@
 
 use crate::error::Result;
 use crate::protocol::{
-    MidiEventVec, NoteExpressionChanges, ParameterChanges, ParameterInfo, TransportInfo,
+    MidiEventVec, NoteExpressionChanges, ParameterChanges, ParameterInfo, ProcessMode,
+    TransportInfo,
 };
 
 /// Trait abstracting the communication bridge between audio/control threads and a plugin.
@@ -43,6 +44,10 @@ pub trait PluginBridge: Send + Sync {
 
     fn is_crashed(&self) -> bool;
 
+    /// In [`ProcessMode::Offline`], `process` waits for every block however
+    /// long the plugin takes, instead of giving up to stay real-time.
+    fn set_process_mode(&self, mode: ProcessMode) -> bool;
+
     /// Returns (width, height) on success.
     fn open_editor(&self, parent_handle: u64) -> Option<(u32, u32)>;
     fn close_editor(&self) -> bool;
@@ -1,15 +1,19 @@
 use crate::bridge::PluginBridge;
 use crate::error::{BridgeError, LoadStage, Result};
 use crate::lockfree_bridge::{BridgeThreadHandle, LockFreeBridge};
-use crate::protocol::{BridgeConfig, BridgeMessage, HostMessage, PluginMetadata, SampleFormat};
+use crate::protocol::{
+    BridgeConfig, BridgeMessage, HostMessage, PluginMetadata, ProcessMode, SampleFormat,
+    TransportInfo,
+};
 use crate::shared_memory::SharedAudioBuffer;
 use crate::transport::MessageTransport;
 use ringbuf::traits::{Consumer, Producer, Split};
+use std::any::Any;
 use std::cell::UnsafeCell;
 use std::path::{Path, PathBuf};
 use std::process::{Child, Command};
 use std::sync::Arc;
-use tutti_core::{AudioUnit, BufferMut, BufferRef, SignalFrame, F64};
+use tutti_core::{AudioUnit, BufferMut, BufferRef, SignalFrame, TransportReader, F64};
 use tutti_midi_io::MidiEvent;
 
 /// Batch size for tick() accumulation (matches fundsp MAX_BUFFER_SIZE).
@@ -18,6 +22,20 @@ const TICK_BATCH_SIZE: usize = 64;
 /// Monotonic counter for stable PluginClient IDs that survive cloning.
 static NEXT_CLIENT_ID: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(1);
 
+/// Numbers server instances so their shared memory and sockets don't collide.
+static NEXT_INSTANCE_ID: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(1);
+
+/// Loads another instance of the same plugin.
+type InstanceLoader = Arc<dyn Fn() -> Result<LoadedInstance> + Send + Sync>;
+
+/// A freshly loaded plugin instance, used for offline renders.
+pub struct LoadedInstance {
+    pub bridge: Arc<dyn PluginBridge>,
+    /// Keeps the instance running (server process, bridge thread); dropped
+    /// with the last node using it.
+    pub owner: Box<dyn Any + Send + Sync>,
+}
+
 #[derive(Clone)]
 struct ScratchBuffer {
     f32_buf: Vec<f32>,
@@ -95,7 +113,15 @@ pub struct PluginClient {
     midi_drain_buffer: crate::protocol::MidiEventVec,
     /// Set by engine for graph-routed MIDI via `engine.note_on()` / `engine.note_off()`.
     midi_registry: Option<tutti_core::MidiRegistry>,
+    /// Set by engine on export clones; replays the export's MIDI snapshot.
+    midi_source: Option<Arc<dyn tutti_core::MidiSource>>,
     midi_poll_buffer: Vec<MidiEvent>,
+    /// Tempo, position and loop passed to the plugin with every block.
+    transport: Option<Arc<dyn TransportReader>>,
+    sample_rate: f64,
+    instance_loader: Option<InstanceLoader>,
+    /// Set when this node owns its own instance (offline renders).
+    instance_owner: Option<Arc<dyn Any + Send + Sync>>,
 }
 
 // Safety: SPSC queues - producer and consumer never accessed concurrently
@@ -138,7 +164,11 @@ impl PluginClient {
         // Create shared memory BEFORE sending LoadPlugin so the server can open it.
         // We use conservative defaults (2ch stereo, max buffer size) since we don't
         // know the plugin's channel count yet. The buffer is oversized but safe.
-        let shm_name = format!("dawai_plugin_{}", std::process::id());
+        let shm_name = format!(
+            "dawai_plugin_{}_{}",
+            std::process::id(),
+            NEXT_INSTANCE_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+        );
         let pre_channels = 2; // Will be validated after metadata arrives
         let pre_audio_buffer = SharedAudioBuffer::create_with_format(
             shm_name.clone(),
@@ -180,7 +210,12 @@ impl PluginClient {
         let (bridge, bridge_thread) = LockFreeBridge::new(transport, audio_buffer)?;
         let bridge_arc: Arc<dyn PluginBridge> = Arc::new(bridge);
 
-        let client = Self::create_client(bridge_arc, *metadata, &config, negotiated_format);
+        let mut client = Self::create_client(bridge_arc, *metadata, &config, negotiated_format);
+        client.sample_rate = sample_rate;
+        let loader_config = config.clone();
+        client.instance_loader = Some(Arc::new(move || {
+            Self::load_offline_instance(&loader_config, &plugin_path, sample_rate)
+        }));
         let handle = PluginClientHandle {
             process: Some(process),
             bridge_thread: Some(bridge_thread),
@@ -256,6 +291,78 @@ impl PluginClient {
         self.midi_registry = Some(registry);
     }
 
+    /// MIDI source polled alongside the queue and registry, e.g. the
+    /// export's snapshot reader on a node prepared for an offline render.
+    pub fn set_midi_source(&mut self, source: Box<dyn tutti_core::MidiSource>) {
+        self.midi_source = Some(Arc::from(source));
+    }
+
+    /// Transport the plugin reads tempo, position and loop from. Without one
+    /// it sees a stopped transport at 120 BPM.
+    pub fn set_transport(&mut self, transport: Arc<dyn TransportReader>) {
+        self.transport = Some(transport);
+    }
+
+    /// How to load another instance of this plugin, for
+    /// [`prepare_offline_render`](Self::prepare_offline_render). Set by
+    /// [`load`](Self::load); in-process hosts provide their own.
+    pub fn set_instance_loader(
+        &mut self,
+        loader: impl Fn() -> Result<LoadedInstance> + Send + Sync + 'static,
+    ) {
+        self.instance_loader = Some(Arc::new(loader));
+    }
+
+    /// Switch this node to its own instance of the plugin for an offline
+    /// render.
+    ///
+    /// The new instance gets the current instance's state, renders in
+    /// [`ProcessMode::Offline`] and follows `transport`. The instance playing
+    /// live is left untouched, so call this on a cloned node.
+    pub fn prepare_offline_render(&mut self, transport: Arc<dyn TransportReader>) -> Result<()> {
+        let loader = self.instance_loader.clone().ok_or_else(|| {
+            BridgeError::ProtocolError("No way to load another instance of this plugin".into())
+        })?;
+        let LoadedInstance { bridge, owner } = loader()?;
+
+        if let Some(state) = self.save_state().filter(|state| !state.is_empty()) {
+            if !bridge.load_state(&state) {
+                return Err(BridgeError::StateRestoreError(format!(
+                    "{} rejected the live instance's state",
+                    self.metadata.name
+                )));
+            }
+        }
+        if !bridge.set_process_mode(ProcessMode::Offline) {
+            return Err(BridgeError::ProcessCrashed);
+        }
+
+        // The MIDI queue and registry stay with the live node; the export's
+        // snapshot comes in through `set_midi_source`
+        let (midi_prod, midi_cons) = ringbuf::HeapRb::<MidiEvent>::new(512).split();
+        #[allow(clippy::arc_with_non_send_sync)]
+        let midi_producer = Arc::new(UnsafeCell::new(midi_prod));
+        #[allow(clippy::arc_with_non_send_sync)]
+        let midi_consumer = Arc::new(UnsafeCell::new(midi_cons));
+        self.midi_producer = midi_producer;
+        self.midi_consumer = midi_consumer;
+        self.midi_registry = None;
+
+        self.bridge = Some(bridge);
+        self.instance_owner = Some(Arc::from(owner));
+        self.transport = Some(transport);
+        self.tick_f32.reset();
+        self.tick_f64.reset();
+        Ok(())
+    }
+
+    fn transport_info(&self) -> TransportInfo {
+        match &self.transport {
+            Some(transport) => TransportInfo::from_transport(transport.as_ref(), self.sample_rate),
+            None => TransportInfo::default(),
+        }
+    }
+
     pub(crate) fn bridge_arc(&self) -> Option<Arc<dyn PluginBridge>> {
         self.bridge.as_ref().map(Arc::clone)
     }
@@ -290,7 +397,12 @@ impl PluginClient {
             midi_consumer,
             midi_drain_buffer: smallvec::SmallVec::new(),
             midi_registry: None,
+            midi_source: None,
             midi_poll_buffer: vec![MidiEvent::note_on_builder(0, 0).build(); 256],
+            transport: None,
+            sample_rate: 44100.0,
+            instance_loader: None,
+            instance_owner: None,
         }
     }
 }
@@ -360,6 +472,49 @@ impl PluginClient {
         }
     }
 
+    /// Start a second server for the same plugin, for offline renders.
+    fn load_offline_instance(
+        config: &BridgeConfig,
+        plugin_path: &Path,
+        sample_rate: f64,
+    ) -> Result<LoadedInstance> {
+        let mut config = config.clone();
+        let stem = config
+            .socket_path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("dawai-bridge")
+            .to_string();
+        config.socket_path.set_file_name(format!(
+            "{stem}-offline-{}.sock",
+            NEXT_INSTANCE_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+        ));
+        let plugin_path = plugin_path.to_path_buf();
+
+        // The connection stays registered with the runtime it was opened on,
+        // so that runtime lives as long as the instance. Loading runs on its
+        // own thread in case the caller is already inside a runtime.
+        std::thread::spawn(move || -> Result<LoadedInstance> {
+            let runtime = tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(1)
+                .enable_all()
+                .build()?;
+            let (client, handle) =
+                runtime.block_on(Self::load(config, plugin_path, sample_rate))?;
+            Ok(LoadedInstance {
+                bridge: client
+                    .bridge
+                    .expect("BUG: loaded client always has a bridge"),
+                owner: Box::new(OfflineInstance {
+                    handle: Some(handle),
+                    runtime: Some(runtime),
+                }),
+            })
+        })
+        .join()
+        .map_err(|_| BridgeError::ConnectionFailed("offline instance loader panicked".into()))?
+    }
+
     pub(crate) fn negotiate_format(
         preferred: SampleFormat,
         metadata: &PluginMetadata,
@@ -401,7 +556,12 @@ impl PluginClient {
             midi_consumer,
             midi_drain_buffer: smallvec::SmallVec::new(),
             midi_registry: None,
+            midi_source: None,
             midi_poll_buffer: vec![MidiEvent::note_on_builder(0, 0).build(); 256],
+            transport: None,
+            sample_rate: 44100.0,
+            instance_loader: None,
+            instance_owner: None,
         }
     }
 }
@@ -436,7 +596,12 @@ impl PluginClient {
             midi_consumer,
             midi_drain_buffer: smallvec::SmallVec::new(),
             midi_registry: None,
+            midi_source: None,
             midi_poll_buffer: vec![MidiEvent::note_on_builder(0, 0).build(); 256],
+            transport: None,
+            sample_rate: 44100.0,
+            instance_loader: None,
+            instance_owner: None,
         }
     }
 }
@@ -457,6 +622,14 @@ impl PluginClient {
                 self.midi_drain_buffer.push(self.midi_poll_buffer[i]);
             }
         }
+
+        // Offline renders replay the export snapshot instead
+        if let Some(ref source) = self.midi_source {
+            let count = source.poll_into(self.stable_id, &mut self.midi_poll_buffer);
+            for i in 0..count {
+                self.midi_drain_buffer.push(self.midi_poll_buffer[i]);
+            }
+        }
     }
 
     fn fill_silence<F>(size: usize, outputs: usize, mut set_output: F)
@@ -532,7 +705,7 @@ impl PluginClient {
             midi_events,
             crate::protocol::ParameterChanges::new(),
             crate::protocol::NoteExpressionChanges::new(),
-            crate::protocol::TransportInfo::default(),
+            self.transport_info(),
         ) {
             Self::fill_silence(size, self.outputs, set_output);
             return;
@@ -652,7 +825,7 @@ impl PluginClient {
             midi_events,
             crate::protocol::ParameterChanges::new(),
             crate::protocol::NoteExpressionChanges::new(),
-            crate::protocol::TransportInfo::default(),
+            self.transport_info(),
         ) {
             self.tick_f32.fill_output_silence(size);
             return;
@@ -764,7 +937,7 @@ impl PluginClient {
             midi_events,
             crate::protocol::ParameterChanges::new(),
             crate::protocol::NoteExpressionChanges::new(),
-            crate::protocol::TransportInfo::default(),
+            self.transport_info(),
         ) {
             self.tick_f64.fill_output_silence(size);
             return;
@@ -831,6 +1004,7 @@ impl AudioUnit for PluginClient {
     fn set_sample_rate(&mut self, sample_rate: f64) {
         self.tick_f32.reset();
         self.tick_f64.reset();
+        self.sample_rate = sample_rate;
         if let Some(bridge) = &self.bridge {
             let _ = bridge.set_sample_rate_rt(sample_rate);
         }
@@ -908,6 +1082,7 @@ impl AudioUnit<F64> for PluginClient {
     fn set_sample_rate(&mut self, sample_rate: f64) {
         self.tick_f32.reset();
         self.tick_f64.reset();
+        self.sample_rate = sample_rate;
         if let Some(bridge) = &self.bridge {
             let _ = bridge.set_sample_rate_rt(sample_rate);
         }
@@ -985,6 +1160,24 @@ impl PluginClient {
     }
 }
 
+/// A server started by [`PluginClient::load_offline_instance`], with the
+/// runtime its connection lives on.
+struct OfflineInstance {
+    handle: Option<PluginClientHandle>,
+    runtime: Option<tokio::runtime::Runtime>,
+}
+
+impl Drop for OfflineInstance {
+    fn drop(&mut self) {
+        self.handle.take();
+        // May be dropped from inside another runtime, where a blocking
+        // shutdown would panic
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
 impl Drop for PluginClientHandle {
     fn drop(&mut self) {
         if let Some(mut process) = self.process.take() {
@@ -1628,4 +1821,226 @@ mod tests {
         let (handle, _bridge_handle, _server_thread) = setup_handle_with_mock_server(|_| None);
         assert!(!handle.is_crashed());
     }
+
+    /// Mock plugin whose whole state is one gain value.
+    struct GainPlugin {
+        gain: f32,
+        mode: std::sync::Arc<parking_lot::Mutex<ProcessMode>>,
+        /// Note-ons received, in order.
+        notes: std::sync::Arc<parking_lot::Mutex<Vec<u8>>>,
+    }
+
+    impl crate::instance::PluginInstance for GainPlugin {
+        fn metadata(&self) -> &PluginMetadata {
+            static META: std::sync::OnceLock<PluginMetadata> = std::sync::OnceLock::new();
+            META.get_or_init(|| PluginMetadata::new("mock.gain", "Mock Gain").audio_io(2, 2))
+        }
+
+        fn supports_f64(&self) -> bool {
+            false
+        }
+
+        fn process_f32<'a>(
+            &mut self,
+            buffer: &'a mut crate::protocol::AudioBuffer<'a>,
+            ctx: &crate::instance::ProcessContext,
+        ) -> crate::instance::ProcessOutput {
+            for event in ctx.midi_events.iter().filter(|event| event.is_note_on()) {
+                if let tutti_midi_io::ChannelVoiceMsg::NoteOn { note, .. } = event.msg {
+                    self.notes.lock().push(note);
+                }
+            }
+            for output in buffer.outputs.iter_mut() {
+                output.fill(self.gain);
+            }
+            crate::instance::ProcessOutput::default()
+        }
+
+        fn process_f64<'a>(
+            &mut self,
+            _buffer: &'a mut crate::protocol::AudioBuffer64<'a>,
+            _ctx: &crate::instance::ProcessContext,
+        ) -> crate::instance::ProcessOutput {
+            crate::instance::ProcessOutput::default()
+        }
+
+        fn set_sample_rate(&mut self, _rate: f64) {}
+        fn set_process_mode(&mut self, mode: ProcessMode) {
+            *self.mode.lock() = mode;
+        }
+        fn get_parameter_count(&self) -> usize {
+            0
+        }
+        fn get_parameter(&self, _id: u32) -> f64 {
+            0.0
+        }
+        fn set_parameter(&mut self, _id: u32, _value: f64) {}
+        fn get_parameter_list(&mut self) -> Vec<crate::protocol::ParameterInfo> {
+            vec![]
+        }
+        fn get_parameter_info(&mut self, _id: u32) -> Option<crate::protocol::ParameterInfo> {
+            None
+        }
+        fn has_editor(&mut self) -> bool {
+            false
+        }
+        unsafe fn open_editor(&mut self, _parent: *mut std::ffi::c_void) -> Result<(u32, u32)> {
+            Err(BridgeError::EditorError("no editor".into()))
+        }
+        fn close_editor(&mut self) {}
+        fn editor_idle(&mut self) {}
+        fn get_state(&mut self) -> Result<Vec<u8>> {
+            Ok(self.gain.to_le_bytes().to_vec())
+        }
+        fn set_state(&mut self, data: &[u8]) -> Result<()> {
+            let bytes = data
+                .try_into()
+                .map_err(|_| BridgeError::StateRestoreError("bad state".into()))?;
+            self.gain = f32::from_le_bytes(bytes);
+            Ok(())
+        }
+    }
+
+    fn gain_bridge(
+        gain: f32,
+        mode: &std::sync::Arc<parking_lot::Mutex<ProcessMode>>,
+    ) -> (
+        Arc<dyn PluginBridge>,
+        crate::inprocess_bridge::InProcessThreadHandle,
+    ) {
+        gain_bridge_with_notes(gain, mode, &Default::default())
+    }
+
+    fn gain_bridge_with_notes(
+        gain: f32,
+        mode: &std::sync::Arc<parking_lot::Mutex<ProcessMode>>,
+        notes: &std::sync::Arc<parking_lot::Mutex<Vec<u8>>>,
+    ) -> (
+        Arc<dyn PluginBridge>,
+        crate::inprocess_bridge::InProcessThreadHandle,
+    ) {
+        let plugin = Box::new(GainPlugin {
+            gain,
+            mode: Arc::clone(mode),
+            notes: Arc::clone(notes),
+        });
+        let (bridge, handle) = crate::inprocess_bridge::InProcessBridge::new(plugin, 2, 512);
+        (Arc::new(bridge), handle)
+    }
+
+    #[test]
+    fn test_prepare_offline_render_uses_own_instance() {
+        use tutti_core::{ExportConfig, ExportTimeline};
+
+        let live_mode = Arc::new(parking_lot::Mutex::new(ProcessMode::Realtime));
+        let (live_bridge, _live_handle) = gain_bridge(0.5, &live_mode);
+        let metadata = PluginMetadata::new("mock.gain", "Mock Gain").audio_io(2, 2);
+        let mut live = PluginClient::from_bridge(Arc::clone(&live_bridge), metadata, 512);
+
+        // Without a loader there is nothing to render offline with
+        assert!(live
+            .clone()
+            .prepare_offline_render(Arc::new(ExportTimeline::new(&ExportConfig::default())))
+            .is_err());
+
+        let offline_mode = Arc::new(parking_lot::Mutex::new(ProcessMode::Realtime));
+        let loader_mode = Arc::clone(&offline_mode);
+        live.set_instance_loader(move || {
+            let (bridge, handle) = gain_bridge(1.0, &loader_mode);
+            Ok(LoadedInstance {
+                bridge,
+                owner: Box::new(handle),
+            })
+        });
+
+        let timeline = Arc::new(ExportTimeline::new(&ExportConfig::default()));
+        timeline.reset(4.0);
+        let mut offline = live.clone();
+        offline.prepare_offline_render(timeline).unwrap();
+
+        // The new instance carries the live state and renders offline
+        assert!(!Arc::ptr_eq(
+            &offline.bridge_arc().unwrap(),
+            &live.bridge_arc().unwrap()
+        ));
+        assert_eq!(offline.save_state().unwrap(), 0.5f32.to_le_bytes());
+        assert_eq!(*offline_mode.lock(), ProcessMode::Offline);
+        assert_eq!(offline.transport_info().position_quarters, 4.0);
+
+        // The live instance is untouched
+        assert_eq!(*live_mode.lock(), ProcessMode::Realtime);
+        assert_eq!(live.transport_info().position_quarters, 0.0);
+    }
+
+    #[test]
+    fn test_offline_render_plays_export_midi() {
+        use tutti_core::{
+            BufferVec, ExportConfig, ExportTimeline, MidiSnapshot, MidiSnapshotReader,
+        };
+
+        let mode = Arc::new(parking_lot::Mutex::new(ProcessMode::Realtime));
+        let (live_bridge, _live_handle) = gain_bridge(1.0, &mode);
+        let metadata = PluginMetadata::new("mock.gain", "Mock Gain").audio_io(2, 2);
+        let mut live = PluginClient::from_bridge(live_bridge, metadata, 512);
+
+        let notes = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let loader_notes = Arc::clone(&notes);
+        live.set_instance_loader(move || {
+            let mode = Arc::new(parking_lot::Mutex::new(ProcessMode::Realtime));
+            let (bridge, handle) = gain_bridge_with_notes(1.0, &mode, &loader_notes);
+            Ok(LoadedInstance {
+                bridge,
+                owner: Box::new(handle),
+            })
+        });
+
+        let config = ExportConfig::default();
+        let timeline = Arc::new(ExportTimeline::new(&config));
+        let mut offline = live.clone();
+        offline.prepare_offline_render(timeline.clone()).unwrap();
+
+        // A note half a beat in, queued for this node as the engine does
+        let mut snapshot = MidiSnapshot::new();
+        snapshot.add_event(
+            <PluginClient as AudioUnit>::get_id(&offline),
+            0.5,
+            MidiEvent::note_on_builder(60, 100).build(),
+        );
+        offline.set_midi_source(Box::new(MidiSnapshotReader::new(
+            snapshot,
+            Arc::clone(&timeline),
+        )));
+
+        let input = BufferVec::new(2);
+        let mut output = BufferVec::new(2);
+        let samples_per_beat = config.sample_rate * 60.0 / config.tempo as f64;
+
+        // Before the note's beat the plugin hears nothing
+        <PluginClient as AudioUnit>::process(
+            &mut offline,
+            64,
+            &input.buffer_ref(),
+            &mut output.buffer_mut(),
+        );
+        assert!(notes.lock().is_empty());
+
+        timeline.advance(samples_per_beat as usize);
+        <PluginClient as AudioUnit>::process(
+            &mut offline,
+            64,
+            &input.buffer_ref(),
+            &mut output.buffer_mut(),
+        );
+        assert_eq!(*notes.lock(), [60]);
+
+        // The note is delivered once
+        timeline.advance(samples_per_beat as usize);
+        <PluginClient as AudioUnit>::process(
+            &mut offline,
+            64,
+            &input.buffer_ref(),
+            &mut output.buffer_mut(),
+        );
+        assert_eq!(*notes.lock(), [60]);
+    }
 }
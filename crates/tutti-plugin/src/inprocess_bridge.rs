@@ -9,7 +9,8 @@ use crate::bridge::PluginBridge;
 use crate::error::{BridgeError, Result};
 use crate::instance::{PluginInstance, ProcessContext, ProcessOutput};
 use crate::protocol::{
-    MidiEventVec, NoteExpressionChanges, ParameterChanges, ParameterInfo, TransportInfo,
+    MidiEventVec, NoteExpressionChanges, ParameterChanges, ParameterInfo, ProcessMode,
+    TransportInfo,
 };
 use crossbeam::queue::ArrayQueue;
 use parking_lot::Mutex;
@@ -181,6 +182,7 @@ enum BridgeCommand {
     Process(Box<ProcessCommandData>),
     SetParameter { param_id: u32, value: f32 },
     SetSampleRate { rate: f64 },
+    SetProcessMode { mode: ProcessMode },
     Reset,
     Shutdown,
 }
@@ -202,6 +204,8 @@ pub struct InProcessBridge {
     audio_buffer: Arc<InProcessAudioBuffer>,
     running: Arc<AtomicBool>,
     crashed: Arc<AtomicBool>,
+    /// Offline rendering: `process` waits for each block without a deadline.
+    offline: Arc<AtomicBool>,
     /// RT-safe recycling: bridge thread returns used Box here, audio thread reuses it.
     recycle_queue: Arc<ArrayQueue<Box<ProcessCommandData>>>,
 }
@@ -234,6 +238,7 @@ impl InProcessBridge {
             audio_buffer: Arc::clone(&audio_buffer),
             running: Arc::clone(&running),
             crashed: Arc::clone(&crashed),
+            offline: Arc::new(AtomicBool::new(false)),
             recycle_queue: Arc::clone(&recycle_queue),
         };
 
@@ -298,6 +303,9 @@ impl InProcessBridge {
                     BridgeCommand::SetSampleRate { rate } => {
                         plugin.lock().set_sample_rate(rate);
                     }
+                    BridgeCommand::SetProcessMode { mode } => {
+                        plugin.lock().set_process_mode(mode);
+                    }
                     BridgeCommand::Reset => {}
                     BridgeCommand::Shutdown => {
                         break;
@@ -350,6 +358,19 @@ impl PluginBridge for InProcessBridge {
             return false;
         }
 
+        if self.offline.load(Ordering::Acquire) {
+            // Wait for this block, however long the plugin takes
+            loop {
+                if let Some(resp) = self.response_queue.pop() {
+                    return matches!(resp, BridgeResponse::AudioProcessed);
+                }
+                if self.crashed.load(Ordering::Acquire) {
+                    return false;
+                }
+                thread::yield_now();
+            }
+        }
+
         // RT-safe busy-spin: no syscalls, no sleep, stays on-CPU.
         // In-process plugins typically complete in <1ms, so this spins briefly.
         // Bounded to ~5ms worst case (50_000 iterations × ~100ns per spin_loop hint).
@@ -407,6 +428,24 @@ impl PluginBridge for InProcessBridge {
         self.crashed.load(Ordering::Acquire)
     }
 
+    fn set_process_mode(&self, mode: ProcessMode) -> bool {
+        if self.crashed.load(Ordering::Acquire) {
+            return false;
+        }
+        if self
+            .command_queue
+            .push(BridgeCommand::SetProcessMode { mode })
+            .is_err()
+        {
+            return false;
+        }
+        // Late replies to timed-out real-time blocks would be taken for later ones
+        while self.response_queue.pop().is_some() {}
+        self.offline
+            .store(mode == ProcessMode::Offline, Ordering::Release);
+        true
+    }
+
     fn open_editor(&self, parent_handle: u64) -> Option<(u32, u32)> {
         if self.crashed.load(Ordering::Acquire) {
             return None;
@@ -1168,4 +1207,117 @@ mod tests {
             output[0]
         );
     }
+
+    /// Mock plugin that takes longer per block than the real-time spin allows
+    /// and records how it was driven.
+    struct SlowPlugin {
+        mode: Arc<Mutex<ProcessMode>>,
+        position: Arc<Mutex<f64>>,
+    }
+
+    impl PluginInstance for SlowPlugin {
+        fn metadata(&self) -> &PluginMetadata {
+            static META: std::sync::OnceLock<PluginMetadata> = std::sync::OnceLock::new();
+            META.get_or_init(|| PluginMetadata::new("mock.slow", "Mock Slow").audio_io(2, 2))
+        }
+
+        fn supports_f64(&self) -> bool {
+            false
+        }
+
+        fn process_f32<'a>(
+            &mut self,
+            buffer: &'a mut AudioBuffer<'a>,
+            ctx: &ProcessContext,
+        ) -> ProcessOutput {
+            thread::sleep(Duration::from_millis(20));
+            for output in buffer.outputs.iter_mut() {
+                output.fill(1.0);
+            }
+            if let Some(transport) = ctx.transport {
+                *self.position.lock() = transport.position_quarters;
+            }
+            ProcessOutput::default()
+        }
+
+        fn process_f64<'a>(
+            &mut self,
+            _buffer: &'a mut AudioBuffer64<'a>,
+            _ctx: &ProcessContext,
+        ) -> ProcessOutput {
+            ProcessOutput::default()
+        }
+
+        fn set_sample_rate(&mut self, _rate: f64) {}
+        fn set_process_mode(&mut self, mode: ProcessMode) {
+            *self.mode.lock() = mode;
+        }
+        fn get_parameter_count(&self) -> usize {
+            0
+        }
+        fn get_parameter(&self, _id: u32) -> f64 {
+            0.0
+        }
+        fn set_parameter(&mut self, _id: u32, _value: f64) {}
+        fn get_parameter_list(&mut self) -> Vec<ParameterInfo> {
+            vec![]
+        }
+        fn get_parameter_info(&mut self, _id: u32) -> Option<ParameterInfo> {
+            None
+        }
+        fn has_editor(&mut self) -> bool {
+            false
+        }
+        unsafe fn open_editor(&mut self, _parent: *mut std::ffi::c_void) -> Result<(u32, u32)> {
+            Err(crate::error::BridgeError::EditorError("no editor".into()))
+        }
+        fn close_editor(&mut self) {}
+        fn editor_idle(&mut self) {}
+        fn get_state(&mut self) -> Result<Vec<u8>> {
+            Ok(vec![])
+        }
+        fn set_state(&mut self, _data: &[u8]) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_offline_process_waits_for_slow_plugin() {
+        let mode = Arc::new(Mutex::new(ProcessMode::Realtime));
+        let position = Arc::new(Mutex::new(0.0));
+        let plugin = Box::new(SlowPlugin {
+            mode: Arc::clone(&mode),
+            position: Arc::clone(&position),
+        });
+        let (bridge, _handle) = InProcessBridge::new(plugin, 2, 512);
+
+        let transport = TransportInfo {
+            position_quarters: 12.5,
+            ..Default::default()
+        };
+        let process = || {
+            bridge.process(
+                64,
+                smallvec::SmallVec::new(),
+                ParameterChanges::new(),
+                NoteExpressionChanges::new(),
+                transport,
+            )
+        };
+
+        // Real time gives up on a 20 ms block
+        assert!(!process());
+        // Let the abandoned block finish so its reply is drained below
+        thread::sleep(Duration::from_millis(50));
+
+        assert!(bridge.set_process_mode(ProcessMode::Offline));
+        for _ in 0..3 {
+            assert!(process());
+            let mut output = vec![0.0f32; 64];
+            bridge.read_output_channel_into(0, &mut output).unwrap();
+            assert_eq!(output[63], 1.0);
+        }
+        assert_eq!(*mode.lock(), ProcessMode::Offline);
+        assert_eq!(*position.lock(), 12.5);
+    }
 }
@@ -4,7 +4,7 @@
 
 use crate::protocol::{
     AudioBuffer, AudioBuffer64, MidiEvent, MidiEventVec, NoteExpressionChanges, ParameterChanges,
-    ParameterInfo, TransportInfo,
+    ParameterInfo, ProcessMode, TransportInfo,
 };
 use crate::PluginMetadata;
 use crate::Result;
@@ -72,6 +72,10 @@ pub trait PluginInstance: Send {
 
     fn set_sample_rate(&mut self, rate: f64);
 
+    /// Tell the plugin whether it is rendering offline. Formats without a
+    /// way to pass this on ignore it.
+    fn set_process_mode(&mut self, _mode: ProcessMode) {}
+
     fn get_parameter_count(&self) -> usize;
 
     /// Normalized 0..1.
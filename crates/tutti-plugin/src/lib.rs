@@ -7,7 +7,7 @@ pub mod error;
 pub use error::{BridgeError, LoadStage, Result};
 
 mod client;
-pub use client::{PluginClient, PluginClientHandle};
+pub use client::{LoadedInstance, PluginClient, PluginClientHandle};
 
 mod handle;
 pub use handle::PluginHandle;
@@ -26,8 +26,8 @@ pub mod protocol;
 
 pub use protocol::{
     BridgeConfig, MidiEventVec, NoteExpressionChanges, NoteExpressionType, NoteExpressionValue,
-    ParameterChanges, ParameterFlags, ParameterInfo, ParameterPoint, ParameterQueue, SampleFormat,
-    TransportInfo,
+    ParameterChanges, ParameterFlags, ParameterInfo, ParameterPoint, ParameterQueue, ProcessMode,
+    SampleFormat, TransportInfo,
 };
 
 pub use tutti_midi_io::MidiEvent;
@@ -1,7 +1,7 @@
 //! Lock-free bridge for RT-safe plugin communication.
 
 use crate::error::Result;
-use crate::protocol::{BridgeMessage, HostMessage, IpcMidiEvent, ParameterInfo};
+use crate::protocol::{BridgeMessage, HostMessage, IpcMidiEvent, ParameterInfo, ProcessMode};
 use crate::shared_memory::SharedAudioBuffer;
 use crate::transport::MessageTransport;
 use crossbeam::queue::ArrayQueue;
@@ -33,6 +33,7 @@ enum BridgeCommand {
     Reset,
     Shutdown,
     // Control commands (main thread, non-RT)
+    SetProcessMode { mode: ProcessMode },
     OpenEditor { parent_handle: u64 },
     CloseEditor,
     EditorIdle,
@@ -69,6 +70,8 @@ pub struct LockFreeBridge {
     buffer_id_counter: Arc<AtomicU32>,
     running: Arc<AtomicBool>,
     crashed: Arc<AtomicBool>,
+    /// Offline rendering: `process` waits for each block without a deadline.
+    offline: Arc<AtomicBool>,
     /// RT-safe recycling: bridge thread returns used Box here, audio thread reuses it.
     recycle_queue: Arc<ArrayQueue<Box<ProcessCommandData>>>,
 }
@@ -99,6 +102,7 @@ impl LockFreeBridge {
             buffer_id_counter: Arc::new(AtomicU32::new(0)),
             running: Arc::clone(&running),
             crashed: Arc::clone(&crashed),
+            offline: Arc::new(AtomicBool::new(false)),
             recycle_queue: Arc::clone(&recycle_queue),
         };
 
@@ -172,6 +176,7 @@ impl LockFreeBridge {
             buffer_id_counter: Arc::new(AtomicU32::new(0)),
             running: Arc::clone(&running),
             crashed: Arc::clone(&crashed),
+            offline: Arc::new(AtomicBool::new(false)),
             recycle_queue: Arc::clone(&recycle_queue),
         };
 
@@ -220,11 +225,19 @@ impl LockFreeBridge {
         crashed: Arc<AtomicBool>,
         transport: &mut MessageTransport,
     ) {
+        let mut mode = ProcessMode::Realtime;
         while running.load(Ordering::Relaxed) {
             if let Some(cmd) = commands.pop() {
-                if Self::handle(cmd, transport, &responses, &control_responses, &recycle)
-                    .await
-                    .is_err()
+                if Self::handle(
+                    cmd,
+                    transport,
+                    &responses,
+                    &control_responses,
+                    &recycle,
+                    &mut mode,
+                )
+                .await
+                .is_err()
                 {
                     // Transport error = server crashed or disconnected
                     crashed.store(true, Ordering::Release);
@@ -236,6 +249,10 @@ impl LockFreeBridge {
                     }
                     break;
                 }
+            } else if mode == ProcessMode::Offline {
+                // The timer rounds up to a millisecond, which would dominate
+                // an offline render made of short blocks
+                tokio::task::yield_now().await;
             } else {
                 tokio::time::sleep(Duration::from_micros(100)).await;
             }
@@ -248,6 +265,7 @@ impl LockFreeBridge {
         responses: &Arc<ArrayQueue<BridgeResponse>>,
         control_responses: &Arc<ArrayQueue<ControlResponse>>,
         recycle: &Arc<ArrayQueue<Box<ProcessCommandData>>>,
+        mode: &mut ProcessMode,
     ) -> Result<()> {
         match cmd {
             BridgeCommand::Process(mut data) => {
@@ -267,16 +285,30 @@ impl LockFreeBridge {
 
                 transport.send_host_message(&msg).await?;
 
-                match transport.recv_with_timeout(Duration::from_secs(30)).await? {
-                    BridgeMessage::AudioProcessedFull { .. }
-                    | BridgeMessage::AudioProcessedMidi { .. }
-                    | BridgeMessage::AudioProcessed { .. } => {
-                        let _ = responses.push(BridgeResponse::AudioProcessed);
-                    }
-                    BridgeMessage::Error { .. } => {
-                        let _ = responses.push(BridgeResponse::Error);
+                loop {
+                    // Offline blocks may take as long as the plugin needs
+                    let response = match mode {
+                        ProcessMode::Offline => transport.recv_bridge_message().await?,
+                        ProcessMode::Realtime => {
+                            transport.recv_with_timeout(Duration::from_secs(30)).await?
+                        }
+                    };
+                    match response {
+                        BridgeMessage::AudioProcessedFull { .. }
+                        | BridgeMessage::AudioProcessedMidi { .. }
+                        | BridgeMessage::AudioProcessed { .. } => {
+                            let _ = responses.push(BridgeResponse::AudioProcessed);
+                        }
+                        BridgeMessage::Error { .. } => {
+                            let _ = responses.push(BridgeResponse::Error);
+                        }
+                        // Notifications (e.g. parameter changes) can arrive
+                        // ahead of the block's reply; an offline caller is
+                        // still waiting for it
+                        _ if *mode == ProcessMode::Offline => continue,
+                        _ => {}
                     }
-                    _ => {}
+                    break;
                 }
             }
             BridgeCommand::SetParameter { param_id, value } => {
@@ -295,6 +327,12 @@ impl LockFreeBridge {
             BridgeCommand::Shutdown => {
                 transport.send_host_message(&HostMessage::Shutdown).await?;
             }
+            BridgeCommand::SetProcessMode { mode: new_mode } => {
+                transport
+                    .send_host_message(&HostMessage::SetProcessMode { mode: new_mode })
+                    .await?;
+                *mode = new_mode;
+            }
             // Control commands — send and wait for response, route to control queue
             BridgeCommand::OpenEditor { parent_handle } => {
                 transport
@@ -422,6 +460,25 @@ impl LockFreeBridge {
         self.command_queue.push(BridgeCommand::Reset).is_ok()
     }
 
+    /// Non-RT. Call before rendering, not while blocks are in flight.
+    pub fn set_process_mode(&self, mode: ProcessMode) -> bool {
+        if self.crashed.load(Ordering::Acquire) {
+            return false;
+        }
+        if self
+            .command_queue
+            .push(BridgeCommand::SetProcessMode { mode })
+            .is_err()
+        {
+            return false;
+        }
+        // Responses to earlier real-time blocks would be taken for later ones
+        while self.response_queue.pop().is_some() {}
+        self.offline
+            .store(mode == ProcessMode::Offline, Ordering::Release);
+        true
+    }
+
     pub fn write_input_channel(&self, channel: usize, data: &[f32]) -> Result<()> {
         self.audio_buffer.write_channel(channel, data)
     }
@@ -482,6 +539,19 @@ impl LockFreeBridge {
             return false;
         }
 
+        if self.offline.load(Ordering::Acquire) {
+            // Wait for this block, however long the plugin takes
+            loop {
+                if let Some(response) = self.response_queue.pop() {
+                    return matches!(response, BridgeResponse::AudioProcessed);
+                }
+                if self.crashed.load(Ordering::Acquire) {
+                    return false;
+                }
+                thread::yield_now();
+            }
+        }
+
         matches!(
             self.response_queue.pop(),
             Some(BridgeResponse::AudioProcessed)
@@ -648,6 +718,10 @@ impl crate::bridge::PluginBridge for LockFreeBridge {
         self.is_crashed()
     }
 
+    fn set_process_mode(&self, mode: ProcessMode) -> bool {
+        self.set_process_mode(mode)
+    }
+
     fn open_editor(&self, parent_handle: u64) -> Option<(u32, u32)> {
         self.open_editor(parent_handle)
     }
@@ -80,6 +80,24 @@ impl Default for SampleFormat {
     }
 }
 
+/// Whether a plugin is running live or rendering offline.
+///
+/// Offline maps to VST3's `kOffline` process mode and CLAP's render extension
+/// (`CLAP_RENDER_OFFLINE`): plugins may switch to higher-quality algorithms
+/// and the host waits for every block instead of timing out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProcessMode {
+    Realtime,
+    Offline,
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for ProcessMode {
+    fn default() -> Self {
+        ProcessMode::Realtime
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct ParameterPoint {
     pub sample_offset: i32,
@@ -255,6 +273,53 @@ pub struct TransportInfo {
     pub cycle_end_quarters: f64,
 }
 
+impl TransportInfo {
+    /// Transport state at `transport`'s current position, for the block
+    /// starting there. Works with both the live transport and an export
+    /// timeline, so offline renders see the same tempo, meter and loop.
+    pub fn from_transport(transport: &dyn tutti_core::TransportReader, sample_rate: f64) -> Self {
+        let beat = transport.current_beat();
+        let map = transport.tempo_map();
+
+        // The map's tempo only applies once it has changes; otherwise the
+        // transport's own tempo is authoritative
+        let (tempo, seconds) = match map.as_ref().filter(|m| m.has_tempo_changes()) {
+            Some(map) => (map.tempo_at(beat) as f64, map.beats_to_seconds(beat)),
+            None => {
+                let tempo = transport.tempo() as f64;
+                (tempo, beat * 60.0 / tempo)
+            }
+        };
+        let (time_signature, bar_start) = match map {
+            Some(map) => {
+                let meter = map.meter_at(beat);
+                let beats_per_bar = meter.time_signature().beats_per_bar();
+                let bars = ((beat - meter.beat()) / beats_per_bar).floor();
+                (meter.time_signature(), meter.beat() + bars * beats_per_bar)
+            }
+            None => (
+                tutti_core::TimeSignature::default(),
+                (beat / 4.0).floor() * 4.0,
+            ),
+        };
+        let (cycle_start, cycle_end) = transport.get_loop_range().unwrap_or((0.0, 0.0));
+
+        Self {
+            playing: transport.is_playing(),
+            recording: transport.is_recording(),
+            cycle_active: transport.is_loop_enabled(),
+            tempo,
+            time_sig_numerator: time_signature.numerator as i32,
+            time_sig_denominator: time_signature.denominator as i32,
+            position_samples: (seconds * sample_rate).round() as i64,
+            position_quarters: beat,
+            bar_position_quarters: bar_start,
+            cycle_start_quarters: cycle_start,
+            cycle_end_quarters: cycle_end,
+        }
+    }
+}
+
 impl Default for TransportInfo {
     fn default() -> Self {
         Self {
@@ -333,6 +398,9 @@ pub enum HostMessage {
     SetSampleRate {
         rate: f64,
     },
+    SetProcessMode {
+        mode: ProcessMode,
+    },
     Reset,
     SaveState,
     LoadState {
@@ -684,6 +752,39 @@ mod tests {
         assert_eq!(expr.changes[0].expression_type, NoteExpressionType::Tuning);
     }
 
+    #[test]
+    fn test_process_mode_serialization() {
+        let msg = HostMessage::SetProcessMode {
+            mode: ProcessMode::Offline,
+        };
+        let encoded = bincode::serialize(&msg).unwrap();
+        match bincode::deserialize(&encoded).unwrap() {
+            HostMessage::SetProcessMode { mode } => assert_eq!(mode, ProcessMode::Offline),
+            _ => panic!("Wrong message type"),
+        }
+        assert_eq!(ProcessMode::default(), ProcessMode::Realtime);
+    }
+
+    #[test]
+    fn test_transport_info_from_timeline() {
+        use tutti_core::{ExportConfig, ExportTimeline, TempoMap};
+
+        let mut map = TempoMap::new(120.0, 48000.0);
+        map.add_tempo_point(8.0, 60.0);
+        map.add_meter_change(3, 3, 4);
+        let timeline = ExportTimeline::new(&ExportConfig::default()).with_tempo_map(map.snapshot());
+
+        // Bar 3 in 3/4 starts at beat 8; beat 10 is 4 s + 2 s in
+        timeline.reset(10.0);
+        let info = TransportInfo::from_transport(&timeline, 48000.0);
+        assert_eq!(info.tempo, 60.0);
+        assert_eq!(info.time_sig_numerator, 3);
+        assert_eq!(info.time_sig_denominator, 4);
+        assert_eq!(info.bar_position_quarters, 8.0);
+        assert_eq!(info.position_quarters, 10.0);
+        assert_eq!(info.position_samples, 6 * 48000);
+    }
+
     #[test]
     fn test_transport_info_default() {
         let info = TransportInfo::default();
@@ -60,6 +60,7 @@ impl Auditioner {
         let cache = self.sampler.cache();
 
         let cached_wave = cache.as_ref().and_then(|c| c.get(&path));
+        self.sampler.record_cache_lookup(cached_wave.is_some());
 
         if let Some(wave) = cached_wave {
             self.start_in_memory(wave, &path);
@@ -0,0 +1,779 @@
+//! Capture file writer with crash-safe headers.
+//!
+//! WAV captures reserve a `JUNK` chunk ahead of `fmt `, so a file that grows
+//! past the 4 GB RIFF limit is promoted in place to RF64 (EBU Tech 3306) by
+//! turning it into `ds64`. Wave64 is available as an alternative container.
+//!
+//! The header is rewritten every second of audio, so a file left behind by a
+//! crash is readable up to the last update; [`recover_capture`] repairs it to
+//! cover everything that reached the disk.
+
+use crate::error::{Error, Result};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::Path;
+
+const WAVE_FORMAT_PCM: u16 = 0x0001;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 0x0003;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// Largest size a RIFF header can express.
+const RIFF_LIMIT: u64 = u32::MAX as u64;
+
+/// Body size of a `ds64` chunk without a table, reserved as `JUNK` up front.
+const DS64_SIZE: u32 = 28;
+
+/// RIFF header, `JUNK`/`ds64`, 16-byte `fmt ` and the `data` chunk header.
+const WAV_DATA_START: u64 = 12 + 8 + DS64_SIZE as u64 + 8 + 16 + 8;
+
+/// Wave64 `riff` header with `wave` GUID, 16-byte `fmt ` and the `data` chunk header.
+const W64_DATA_START: u64 = 40 + 24 + 16 + 24;
+
+const W64_RIFF: [u8; 16] = [
+    0x72, 0x69, 0x66, 0x66, 0x2E, 0x91, 0xCF, 0x11, 0xA5, 0xD6, 0x28, 0xDB, 0x04, 0xC1, 0x00, 0x00,
+];
+
+/// Shared tail of the Wave64 `wave`, `fmt ` and `data` GUIDs.
+const W64_GUID_TAIL: [u8; 12] = [
+    0xF3, 0xAC, 0xD3, 0x11, 0x8C, 0xD1, 0x00, 0xC0, 0x4F, 0x8E, 0xDB, 0x8A,
+];
+
+/// Seconds of audio between header updates.
+const HEADER_UPDATE_SECONDS: f64 = 1.0;
+
+/// Sample encoding for capture files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CaptureSampleFormat {
+    Int16,
+    Int24,
+    #[default]
+    Float32,
+}
+
+impl CaptureSampleFormat {
+    pub fn bits(&self) -> u16 {
+        match self {
+            CaptureSampleFormat::Int16 => 16,
+            CaptureSampleFormat::Int24 => 24,
+            CaptureSampleFormat::Float32 => 32,
+        }
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.bits() as usize / 8
+    }
+
+    fn format_tag(&self) -> u16 {
+        match self {
+            CaptureSampleFormat::Float32 => WAVE_FORMAT_IEEE_FLOAT,
+            _ => WAVE_FORMAT_PCM,
+        }
+    }
+
+    #[inline]
+    fn encode(&self, sample: f32, out: &mut Vec<u8>) {
+        match self {
+            CaptureSampleFormat::Int16 => {
+                let value = (sample.clamp(-1.0, 1.0) * 32767.0).round() as i16;
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+            CaptureSampleFormat::Int24 => {
+                let value = (sample.clamp(-1.0, 1.0) * 8_388_607.0).round() as i32;
+                out.extend_from_slice(&value.to_le_bytes()[..3]);
+            }
+            CaptureSampleFormat::Float32 => out.extend_from_slice(&sample.to_le_bytes()),
+        }
+    }
+
+    #[inline]
+    fn decode(&self, bytes: &[u8]) -> f32 {
+        match self {
+            CaptureSampleFormat::Int16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32767.0,
+            CaptureSampleFormat::Int24 => {
+                (i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8) as f32 / 8_388_607.0
+            }
+            CaptureSampleFormat::Float32 => {
+                f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+            }
+        }
+    }
+}
+
+/// Container for capture files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CaptureContainer {
+    /// RIFF/WAVE, switched to RF64 once the file passes 4 GB.
+    #[default]
+    Wav,
+    /// Sony Wave64, with 64-bit sizes from the start. Fewer readers support it.
+    W64,
+}
+
+impl CaptureContainer {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            CaptureContainer::Wav => "wav",
+            CaptureContainer::W64 => "w64",
+        }
+    }
+}
+
+/// File format for audio captures (default: 32-bit float WAV).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CaptureFormat {
+    pub container: CaptureContainer,
+    pub sample_format: CaptureSampleFormat,
+}
+
+impl CaptureFormat {
+    pub fn new(container: CaptureContainer, sample_format: CaptureSampleFormat) -> Self {
+        Self {
+            container,
+            sample_format,
+        }
+    }
+
+    pub fn wav(sample_format: CaptureSampleFormat) -> Self {
+        Self::new(CaptureContainer::Wav, sample_format)
+    }
+
+    pub fn w64(sample_format: CaptureSampleFormat) -> Self {
+        Self::new(CaptureContainer::W64, sample_format)
+    }
+
+    pub fn extension(&self) -> &'static str {
+        self.container.extension()
+    }
+}
+
+fn w64_guid(id: &[u8; 4]) -> [u8; 16] {
+    let mut guid = [0u8; 16];
+    guid[..4].copy_from_slice(id);
+    guid[4..].copy_from_slice(&W64_GUID_TAIL);
+    guid
+}
+
+fn fmt_body(sample_format: CaptureSampleFormat, channels: u16, sample_rate: u32) -> [u8; 16] {
+    let block_align = channels * sample_format.bytes() as u16;
+    let mut body = [0u8; 16];
+    body[0..2].copy_from_slice(&sample_format.format_tag().to_le_bytes());
+    body[2..4].copy_from_slice(&channels.to_le_bytes());
+    body[4..8].copy_from_slice(&sample_rate.to_le_bytes());
+    body[8..12].copy_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    body[12..14].copy_from_slice(&block_align.to_le_bytes());
+    body[14..16].copy_from_slice(&sample_format.bits().to_le_bytes());
+    body
+}
+
+/// Padding after the audio data: RIFF chunks are word-aligned, Wave64 chunks 8-byte aligned.
+fn data_padding(container: CaptureContainer, data_bytes: u64) -> u64 {
+    match container {
+        CaptureContainer::Wav => data_bytes & 1,
+        CaptureContainer::W64 => (8 - data_bytes % 8) % 8,
+    }
+}
+
+/// Write the size fields for `data_bytes` of audio, promoting WAV to RF64 past `riff_limit`.
+///
+/// Returns whether the file is RF64.
+fn write_sizes<F: Write + Seek>(
+    file: &mut F,
+    container: CaptureContainer,
+    data_start: u64,
+    data_bytes: u64,
+    frames: u64,
+    riff_limit: u64,
+) -> io::Result<bool> {
+    let padded = data_bytes + data_padding(container, data_bytes);
+    match container {
+        CaptureContainer::Wav => {
+            let riff_size = data_start - 8 + padded;
+            if riff_size <= riff_limit {
+                file.seek(SeekFrom::Start(0))?;
+                file.write_all(b"RIFF")?;
+                file.write_all(&(riff_size as u32).to_le_bytes())?;
+                file.seek(SeekFrom::Start(data_start - 4))?;
+                file.write_all(&(data_bytes as u32).to_le_bytes())?;
+                Ok(false)
+            } else {
+                file.seek(SeekFrom::Start(0))?;
+                file.write_all(b"RF64")?;
+                file.write_all(&u32::MAX.to_le_bytes())?;
+                file.seek(SeekFrom::Start(12))?;
+                file.write_all(b"ds64")?;
+                file.write_all(&DS64_SIZE.to_le_bytes())?;
+                file.write_all(&riff_size.to_le_bytes())?;
+                file.write_all(&data_bytes.to_le_bytes())?;
+                file.write_all(&frames.to_le_bytes())?;
+                file.write_all(&0u32.to_le_bytes())?;
+                file.seek(SeekFrom::Start(data_start - 4))?;
+                file.write_all(&u32::MAX.to_le_bytes())?;
+                Ok(true)
+            }
+        }
+        CaptureContainer::W64 => {
+            file.seek(SeekFrom::Start(16))?;
+            file.write_all(&(data_start + padded).to_le_bytes())?;
+            file.seek(SeekFrom::Start(data_start - 8))?;
+            file.write_all(&(24 + data_bytes).to_le_bytes())?;
+            Ok(false)
+        }
+    }
+}
+
+/// Streaming capture file writer.
+///
+/// Header sizes are brought up to date every [`HEADER_UPDATE_SECONDS`];
+/// dropping the writer finalizes the file.
+pub(crate) struct CaptureWriter {
+    file: BufWriter<File>,
+    format: CaptureFormat,
+    channels: usize,
+    data_start: u64,
+    data_bytes: u64,
+    update_interval: u64,
+    frames_since_update: u64,
+    riff_limit: u64,
+    /// Encoded samples for the current write
+    scratch: Vec<u8>,
+    finalized: bool,
+}
+
+impl CaptureWriter {
+    /// Create a capture file. Only mono and stereo are written.
+    pub(crate) fn create(
+        path: &Path,
+        sample_rate: f64,
+        channels: usize,
+        format: CaptureFormat,
+    ) -> io::Result<Self> {
+        let channels = channels.clamp(1, 2);
+        let fmt = fmt_body(format.sample_format, channels as u16, sample_rate as u32);
+        let mut file = BufWriter::new(File::create(path)?);
+
+        let data_start = match format.container {
+            CaptureContainer::Wav => {
+                file.write_all(b"RIFF")?;
+                file.write_all(&0u32.to_le_bytes())?;
+                file.write_all(b"WAVE")?;
+                file.write_all(b"JUNK")?;
+                file.write_all(&DS64_SIZE.to_le_bytes())?;
+                file.write_all(&[0u8; DS64_SIZE as usize])?;
+                file.write_all(b"fmt ")?;
+                file.write_all(&16u32.to_le_bytes())?;
+                file.write_all(&fmt)?;
+                file.write_all(b"data")?;
+                file.write_all(&0u32.to_le_bytes())?;
+                WAV_DATA_START
+            }
+            CaptureContainer::W64 => {
+                file.write_all(&W64_RIFF)?;
+                file.write_all(&0u64.to_le_bytes())?;
+                file.write_all(&w64_guid(b"wave"))?;
+                file.write_all(&w64_guid(b"fmt "))?;
+                file.write_all(&(24u64 + 16).to_le_bytes())?;
+                file.write_all(&fmt)?;
+                file.write_all(&w64_guid(b"data"))?;
+                file.write_all(&24u64.to_le_bytes())?;
+                W64_DATA_START
+            }
+        };
+
+        let mut writer = Self {
+            file,
+            format,
+            channels,
+            data_start,
+            data_bytes: 0,
+            update_interval: (sample_rate * HEADER_UPDATE_SECONDS).max(1.0) as u64,
+            frames_since_update: 0,
+            riff_limit: RIFF_LIMIT,
+            scratch: Vec::new(),
+            finalized: false,
+        };
+        writer.update_header()?;
+        Ok(writer)
+    }
+
+    pub(crate) fn bytes_per_frame(&self) -> usize {
+        self.channels * self.format.sample_format.bytes()
+    }
+
+    pub(crate) fn frames(&self) -> u64 {
+        self.data_bytes / self.bytes_per_frame() as u64
+    }
+
+    pub(crate) fn write_frames(&mut self, frames: &[(f32, f32)]) -> io::Result<()> {
+        let sample_format = self.format.sample_format;
+        self.scratch.clear();
+        for &(left, right) in frames {
+            sample_format.encode(left, &mut self.scratch);
+            if self.channels > 1 {
+                sample_format.encode(right, &mut self.scratch);
+            }
+        }
+        self.file.write_all(&self.scratch)?;
+        self.data_bytes += self.scratch.len() as u64;
+
+        self.frames_since_update += frames.len() as u64;
+        if self.frames_since_update >= self.update_interval {
+            self.update_header()?;
+        }
+        Ok(())
+    }
+
+    /// Write the current sizes into the header and hand everything to the OS.
+    pub(crate) fn update_header(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let frames = self.frames();
+        let file = self.file.get_mut();
+        write_sizes(
+            file,
+            self.format.container,
+            self.data_start,
+            self.data_bytes,
+            frames,
+            self.riff_limit,
+        )?;
+        file.seek(SeekFrom::End(0))?;
+        self.frames_since_update = 0;
+        Ok(())
+    }
+
+    pub(crate) fn finalize(mut self) -> io::Result<()> {
+        self.finish()
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        if std::mem::replace(&mut self.finalized, true) {
+            return Ok(());
+        }
+        let padding = data_padding(self.format.container, self.data_bytes) as usize;
+        self.file.write_all(&[0u8; 8][..padding])?;
+        self.update_header()?;
+        self.file.get_ref().sync_all()
+    }
+}
+
+impl Drop for CaptureWriter {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+/// Format and length of a capture file, read from its header.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CaptureFileInfo {
+    pub format: CaptureFormat,
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub frames: u64,
+    /// Whether sizes are stored RF64-style in a `ds64` chunk
+    pub rf64: bool,
+}
+
+impl CaptureFileInfo {
+    /// Read the header of a WAV, RF64 or Wave64 capture.
+    ///
+    /// For a file that was never finalized, `frames` is the length at the last
+    /// header update.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut file = File::open(path)?;
+        Ok(parse_header(&mut file, path)?.info)
+    }
+
+    pub fn duration_seconds(&self) -> f64 {
+        if self.sample_rate == 0 {
+            0.0
+        } else {
+            self.frames as f64 / self.sample_rate as f64
+        }
+    }
+}
+
+struct Header {
+    info: CaptureFileInfo,
+    data_start: u64,
+    block_align: u64,
+    /// First chunk is a 28-byte `JUNK`/`ds64`, so the file can become RF64
+    ds64_reserved: bool,
+}
+
+fn read_array<const N: usize>(file: &mut File) -> io::Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    file.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn parse_header(file: &mut File, path: &Path) -> Result<Header> {
+    let invalid = |reason: &str| Error::Recording(format!("{}: {}", path.display(), reason));
+    let head: [u8; 16] = read_array(file).map_err(|_| invalid("not a capture file"))?;
+
+    let container = if head == W64_RIFF {
+        CaptureContainer::W64
+    } else if (&head[..4] == b"RIFF" || &head[..4] == b"RF64") && &head[8..12] == b"WAVE" {
+        CaptureContainer::Wav
+    } else {
+        return Err(invalid("not a WAV, RF64 or Wave64 file"));
+    };
+
+    let mut fmt: Option<Vec<u8>> = None;
+    let mut ds64_data: Option<u64> = None;
+    let mut ds64_reserved = false;
+    let mut position = match container {
+        CaptureContainer::Wav => 12,
+        CaptureContainer::W64 => 40,
+    };
+
+    let (data_start, header_data_bytes) = loop {
+        file.seek(SeekFrom::Start(position))?;
+        let (id, body_start, body_size, next) = match container {
+            CaptureContainer::Wav => {
+                let Ok(chunk) = read_array::<8>(file) else {
+                    return Err(invalid("no data chunk"));
+                };
+                let size = u32::from_le_bytes(chunk[4..8].try_into().unwrap()) as u64;
+                let id: [u8; 4] = chunk[..4].try_into().unwrap();
+                (id, position + 8, size, position + 8 + size + (size & 1))
+            }
+            CaptureContainer::W64 => {
+                let Ok(chunk) = read_array::<24>(file) else {
+                    return Err(invalid("no data chunk"));
+                };
+                if chunk[4..16] != W64_GUID_TAIL {
+                    return Err(invalid("unknown Wave64 chunk"));
+                }
+                let size = u64::from_le_bytes(chunk[16..24].try_into().unwrap());
+                let id: [u8; 4] = chunk[..4].try_into().unwrap();
+                let size = size.max(24);
+                (id, position + 24, size - 24, position + ((size + 7) & !7))
+            }
+        };
+
+        match &id {
+            b"data" => break (body_start, body_size),
+            b"fmt " => {
+                let mut body = vec![0u8; body_size.min(64) as usize];
+                file.read_exact(&mut body)?;
+                fmt = Some(body);
+            }
+            b"ds64" | b"JUNK" if position == 12 && body_size >= DS64_SIZE as u64 => {
+                ds64_reserved = true;
+                if &id == b"ds64" {
+                    let body: [u8; 16] = read_array(file)?;
+                    ds64_data = Some(u64::from_le_bytes(body[8..16].try_into().unwrap()));
+                }
+            }
+            _ => {}
+        }
+        position = next;
+    };
+
+    let fmt = fmt
+        .filter(|f| f.len() >= 16)
+        .ok_or_else(|| invalid("no fmt chunk"))?;
+    let mut tag = u16::from_le_bytes([fmt[0], fmt[1]]);
+    if tag == WAVE_FORMAT_EXTENSIBLE && fmt.len() >= 26 {
+        tag = u16::from_le_bytes([fmt[24], fmt[25]]);
+    }
+    let channels = u16::from_le_bytes([fmt[2], fmt[3]]);
+    let sample_rate = u32::from_le_bytes([fmt[4], fmt[5], fmt[6], fmt[7]]);
+    let bits = u16::from_le_bytes([fmt[14], fmt[15]]);
+    let sample_format = match (tag, bits) {
+        (WAVE_FORMAT_PCM, 16) => CaptureSampleFormat::Int16,
+        (WAVE_FORMAT_PCM, 24) => CaptureSampleFormat::Int24,
+        (WAVE_FORMAT_IEEE_FLOAT, 32) => CaptureSampleFormat::Float32,
+        _ => return Err(invalid("unsupported sample format")),
+    };
+    let block_align = channels as u64 * sample_format.bytes() as u64;
+    if block_align == 0 {
+        return Err(invalid("no channels"));
+    }
+
+    let rf64 = container == CaptureContainer::Wav && &head[..4] == b"RF64";
+    let data_bytes = match ds64_data {
+        Some(size) if rf64 => size,
+        _ => header_data_bytes,
+    };
+
+    Ok(Header {
+        info: CaptureFileInfo {
+            format: CaptureFormat::new(container, sample_format),
+            channels,
+            sample_rate,
+            frames: data_bytes / block_align,
+            rf64,
+        },
+        data_start,
+        block_align,
+        ds64_reserved,
+    })
+}
+
+/// Read frames `range` of a WAV, RF64 or Wave64 capture as stereo; mono
+/// captures play on both sides. The range is clipped to the file's length.
+pub(crate) fn read_capture_frames(path: &Path, range: Range<u64>) -> Result<Vec<(f32, f32)>> {
+    let mut file = File::open(path)?;
+    let header = parse_header(&mut file, path)?;
+    let end = range.end.min(header.info.frames);
+    let start = range.start.min(end);
+    file.seek(SeekFrom::Start(
+        header.data_start + start * header.block_align,
+    ))?;
+
+    let sample_format = header.info.format.sample_format;
+    let bytes = sample_format.bytes();
+    let right = if header.info.channels > 1 { bytes } else { 0 };
+    let mut reader = BufReader::new(file);
+    let mut block = vec![0u8; header.block_align as usize];
+    let mut frames = Vec::with_capacity((end - start) as usize);
+    for _ in start..end {
+        reader.read_exact(&mut block)?;
+        frames.push((
+            sample_format.decode(&block[..bytes]),
+            sample_format.decode(&block[right..right + bytes]),
+        ));
+    }
+    Ok(frames)
+}
+
+/// Repair a capture file that was never finalized, e.g. after a crash.
+///
+/// Sizes are recomputed from the file length, so everything that reached the
+/// disk becomes readable; a trailing partial frame is dropped. Only files
+/// written by capture are supported, since the audio must be the last chunk.
+pub fn recover_capture(path: impl AsRef<Path>) -> Result<CaptureFileInfo> {
+    let path = path.as_ref();
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let header = parse_header(&mut file, path)?;
+    let container = header.info.format.container;
+
+    let length = file.metadata()?.len();
+    let data_bytes =
+        length.saturating_sub(header.data_start) / header.block_align * header.block_align;
+    let frames = data_bytes / header.block_align;
+
+    let riff_size = header.data_start - 8 + data_bytes + data_padding(container, data_bytes);
+    if container == CaptureContainer::Wav && riff_size > RIFF_LIMIT && !header.ds64_reserved {
+        return Err(Error::Recording(format!(
+            "{}: larger than 4 GB without room for an RF64 header",
+            path.display()
+        )));
+    }
+
+    file.set_len(header.data_start + data_bytes)?;
+    file.seek(SeekFrom::End(0))?;
+    let padding = data_padding(container, data_bytes) as usize;
+    file.write_all(&[0u8; 8][..padding])?;
+    let rf64 = write_sizes(
+        &mut file,
+        container,
+        header.data_start,
+        data_bytes,
+        frames,
+        RIFF_LIMIT,
+    )?;
+    file.sync_all()?;
+
+    Ok(CaptureFileInfo {
+        frames,
+        rf64,
+        ..header.info
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp(frames: usize) -> Vec<(f32, f32)> {
+        (0..frames)
+            .map(|i| {
+                let x = i as f32 / frames as f32;
+                (x, -x)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_wav_int24_readable_by_hound() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("take.wav");
+
+        let mut writer = CaptureWriter::create(
+            &path,
+            48000.0,
+            2,
+            CaptureFormat::wav(CaptureSampleFormat::Int24),
+        )
+        .unwrap();
+        writer.write_frames(&ramp(1001)).unwrap();
+        writer.finalize().unwrap();
+
+        let mut reader = hound::WavReader::open(&path).unwrap();
+        let spec = reader.spec();
+        assert_eq!(spec.bits_per_sample, 24);
+        assert_eq!(spec.channels, 2);
+        assert_eq!(reader.duration(), 1001);
+
+        let samples: Vec<i32> = reader.samples::<i32>().map(|s| s.unwrap()).collect();
+        assert_eq!(samples[0], 0);
+        let expected = (1000.0f32 / 1001.0 * 8_388_607.0).round() as i32;
+        assert_eq!(samples[2000], expected);
+        assert_eq!(samples[2001], -expected);
+
+        let info = CaptureFileInfo::read(&path).unwrap();
+        assert_eq!(info.frames, 1001);
+        assert_eq!(info.format, CaptureFormat::wav(CaptureSampleFormat::Int24));
+        assert!(!info.rf64);
+    }
+
+    #[test]
+    fn test_header_updated_while_writing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("long.wav");
+
+        let mut writer = CaptureWriter::create(
+            &path,
+            1000.0,
+            1,
+            CaptureFormat::wav(CaptureSampleFormat::Int16),
+        )
+        .unwrap();
+        writer.write_frames(&ramp(1500)).unwrap();
+        writer.write_frames(&ramp(100)).unwrap();
+
+        // The 1 s update covered the first write; the second is still buffered
+        let info = CaptureFileInfo::read(&path).unwrap();
+        assert_eq!(info.frames, 1500);
+
+        // Simulate a crash: nothing after this point is flushed or finalized
+        std::mem::forget(writer);
+        assert!(hound::WavReader::open(&path).is_ok());
+    }
+
+    #[test]
+    fn test_switches_to_rf64_past_riff_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("huge.wav");
+
+        let mut writer =
+            CaptureWriter::create(&path, 48000.0, 2, CaptureFormat::default()).unwrap();
+        writer.riff_limit = 4096;
+        writer.write_frames(&ramp(100)).unwrap();
+        writer.update_header().unwrap();
+        assert!(!CaptureFileInfo::read(&path).unwrap().rf64);
+
+        writer.write_frames(&ramp(900)).unwrap();
+        writer.finalize().unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(&bytes[..4], b"RF64");
+        assert_eq!(&bytes[12..16], b"ds64");
+        assert_eq!(bytes.len() as u64, WAV_DATA_START + 1000 * 8);
+
+        let info = CaptureFileInfo::read(&path).unwrap();
+        assert!(info.rf64);
+        assert_eq!(info.frames, 1000);
+        assert_eq!(info.format.sample_format, CaptureSampleFormat::Float32);
+    }
+
+    #[test]
+    fn test_w64_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("take.w64");
+
+        let mut writer = CaptureWriter::create(
+            &path,
+            44100.0,
+            1,
+            CaptureFormat::w64(CaptureSampleFormat::Int24),
+        )
+        .unwrap();
+        writer.write_frames(&ramp(333)).unwrap();
+        writer.finalize().unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(bytes.len() % 8, 0);
+        assert_eq!(
+            u64::from_le_bytes(bytes[16..24].try_into().unwrap()),
+            bytes.len() as u64
+        );
+
+        let info = CaptureFileInfo::read(&path).unwrap();
+        assert_eq!(info.frames, 333);
+        assert_eq!(info.channels, 1);
+        assert_eq!(info.sample_rate, 44100);
+        assert_eq!(info.format.container, CaptureContainer::W64);
+
+        // Mono plays on both sides, within 24-bit precision
+        let frames = read_capture_frames(&path, 100..400).unwrap();
+        assert_eq!(frames.len(), 233);
+        for (frame, (expected, _)) in frames.iter().zip(&ramp(333)[100..]) {
+            assert!((frame.0 - expected).abs() < 1e-6);
+            assert_eq!(frame.0, frame.1);
+        }
+    }
+
+    #[test]
+    fn test_read_frames_of_each_format() {
+        let dir = tempfile::tempdir().unwrap();
+        for (format, tolerance) in [
+            (CaptureFormat::wav(CaptureSampleFormat::Float32), 0.0),
+            (CaptureFormat::wav(CaptureSampleFormat::Int16), 1e-4),
+            (CaptureFormat::w64(CaptureSampleFormat::Float32), 0.0),
+        ] {
+            let path = dir.path().join(format!("take.{}", format.extension()));
+            let mut writer = CaptureWriter::create(&path, 48000.0, 2, format).unwrap();
+            writer.write_frames(&ramp(500)).unwrap();
+            writer.finalize().unwrap();
+
+            let frames = read_capture_frames(&path, 0..500).unwrap();
+            assert_eq!(frames.len(), 500);
+            for (frame, expected) in frames.iter().zip(&ramp(500)) {
+                assert!((frame.0 - expected.0).abs() <= tolerance, "{format:?}");
+                assert!((frame.1 - expected.1).abs() <= tolerance, "{format:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_recover_unfinalized_capture() {
+        let dir = tempfile::tempdir().unwrap();
+        for format in [
+            CaptureFormat::wav(CaptureSampleFormat::Float32),
+            CaptureFormat::w64(CaptureSampleFormat::Int16),
+        ] {
+            let path = dir.path().join(format!("crashed.{}", format.extension()));
+            let writer = CaptureWriter::create(&path, 48000.0, 2, format).unwrap();
+            std::mem::forget(writer);
+
+            // Audio that reached the disk after the last header update, plus half a frame
+            let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+            let frame_bytes = 2 * format.sample_format.bytes();
+            file.write_all(&vec![0u8; 250 * frame_bytes + frame_bytes / 2])
+                .unwrap();
+            drop(file);
+            assert_eq!(CaptureFileInfo::read(&path).unwrap().frames, 0);
+
+            let info = recover_capture(&path).unwrap();
+            assert_eq!(info.frames, 250);
+            assert_eq!(CaptureFileInfo::read(&path).unwrap(), info);
+        }
+
+        let path = dir.path().join("crashed.wav");
+        let reader = hound::WavReader::open(&path).unwrap();
+        assert_eq!(reader.duration(), 250);
+    }
+
+    #[test]
+    fn test_recover_rejects_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        std::fs::write(&path, b"not audio at all").unwrap();
+        assert!(recover_capture(&path).is_err());
+        assert!(CaptureFileInfo::read(&path).is_err());
+    }
+}
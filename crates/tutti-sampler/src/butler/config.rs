@@ -1,5 +1,7 @@
 //! Butler thread configuration.
 
+use crate::resample::ResampleQuality;
+
 #[derive(Debug, Clone, Copy)]
 pub struct BufferConfig {
     /// Default: 10.0
@@ -18,6 +20,9 @@ pub struct BufferConfig {
     pub speed_ramp_samples: u32,
     /// When true, multiple streams are refilled concurrently via rayon.
     pub parallel_io: bool,
+    /// Interpolation for streams whose file rate differs from the session rate.
+    /// Default: [`ResampleQuality::Sinc`]
+    pub resample_quality: ResampleQuality,
 }
 
 impl Default for BufferConfig {
@@ -31,6 +36,7 @@ impl Default for BufferConfig {
             seek_crossfade_samples: 512,
             speed_ramp_samples: 1024,
             parallel_io: true,
+            resample_quality: ResampleQuality::Sinc,
         }
     }
 }
@@ -63,6 +69,7 @@ mod tests {
         assert_eq!(config.seek_crossfade_samples, 512);
         assert_eq!(config.speed_ramp_samples, 1024);
         assert!(config.parallel_io);
+        assert_eq!(config.resample_quality, ResampleQuality::Sinc);
     }
 
     #[test]
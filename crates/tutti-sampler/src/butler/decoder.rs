@@ -0,0 +1,376 @@
+//! Seekable per-stream decoding for disk streaming.
+//!
+//! Each streamed region owns a [`StreamDecoder`] that decodes only the frames
+//! the butler asks for, so memory use scales with the number of streams
+//! rather than with file length.
+
+use super::metrics::IOMetrics;
+use crate::error::{Error, Result};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Arc;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{Decoder, DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+use symphonia::core::io::{MediaSource, MediaSourceStream};
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use tutti_core::Wave;
+
+/// Random-access source of stereo frames for the refill paths.
+pub(super) trait FrameSource {
+    /// Append `count` frames starting at frame `start` to `out`.
+    ///
+    /// Frames past the end of the source are silent. Mono sources are
+    /// duplicated to both channels.
+    fn read_frames(&mut self, start: u64, count: usize, out: &mut Vec<(f32, f32)>);
+}
+
+impl FrameSource for Wave {
+    fn read_frames(&mut self, start: u64, count: usize, out: &mut Vec<(f32, f32)>) {
+        let channels = self.channels();
+        for i in 0..count {
+            let idx = start as usize + i;
+            if idx < self.len() {
+                let left = self.at(0, idx);
+                let right = if channels > 1 { self.at(1, idx) } else { left };
+                out.push((left, right));
+            } else {
+                out.push((0.0, 0.0));
+            }
+        }
+    }
+}
+
+/// Incremental decoder for one streamed file (WAV, AIFF, FLAC, Ogg Vorbis, MP3).
+///
+/// Sequential reads continue from the last decoded packet; a read at any
+/// other position seeks and discards the leading frames of the landing
+/// packet, so positions are sample-accurate.
+pub(crate) struct StreamDecoder {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    sample_rate: f64,
+    n_frames: Option<u64>,
+    sample_buf: Option<SampleBuffer<f32>>,
+    /// Decoded frames not yet handed out; `pending[cursor]` is frame `position`.
+    pending: Vec<(f32, f32)>,
+    cursor: usize,
+    position: u64,
+    at_end: bool,
+}
+
+impl StreamDecoder {
+    /// Open `path` for streaming. Every byte read from disk is recorded in `metrics`.
+    pub fn open(path: &Path, metrics: Arc<IOMetrics>) -> Result<Self> {
+        let file = File::open(path)?;
+        let byte_len = file.metadata().ok().map(|m| m.len());
+        let source = MeteredFile {
+            file,
+            byte_len,
+            metrics,
+        };
+        let stream = MediaSourceStream::new(Box::new(source), Default::default());
+
+        let mut hint = Hint::new();
+        if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+            hint.with_extension(ext);
+        }
+
+        let probed = symphonia::default::get_probe()
+            .format(
+                &hint,
+                stream,
+                &FormatOptions::default(),
+                &MetadataOptions::default(),
+            )
+            .map_err(|e| open_error(path, e))?;
+        let format = probed.format;
+
+        let track = format
+            .tracks()
+            .iter()
+            .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or_else(|| Error::SampleNotFound(format!("{}: no audio track", path.display())))?;
+        let track_id = track.id;
+        let sample_rate = track.codec_params.sample_rate.ok_or_else(|| {
+            Error::SampleNotFound(format!("{}: unknown sample rate", path.display()))
+        })? as f64;
+        let n_frames = track.codec_params.n_frames;
+        let decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &DecoderOptions::default())
+            .map_err(|e| open_error(path, e))?;
+
+        Ok(Self {
+            format,
+            decoder,
+            track_id,
+            sample_rate,
+            n_frames,
+            sample_buf: None,
+            pending: Vec::new(),
+            cursor: 0,
+            position: 0,
+            at_end: false,
+        })
+    }
+
+    pub fn sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+
+    /// Length in frames, if the container declares it.
+    pub fn n_frames(&self) -> Option<u64> {
+        self.n_frames
+    }
+
+    fn seek(&mut self, frame: u64) {
+        self.pending.clear();
+        self.cursor = 0;
+        self.position = frame;
+        self.at_end = self.n_frames.is_some_and(|len| frame >= len);
+        if self.at_end {
+            return;
+        }
+
+        let seeked = self.format.seek(
+            SeekMode::Accurate,
+            SeekTo::TimeStamp {
+                ts: frame,
+                track_id: self.track_id,
+            },
+        );
+        self.decoder.reset();
+        if seeked.is_err() {
+            self.at_end = true;
+        }
+    }
+
+    /// Decode packets until frames at `position` are pending.
+    ///
+    /// Returns `false` at end of stream or on an unrecoverable error.
+    fn decode_next(&mut self) -> bool {
+        self.pending.clear();
+        self.cursor = 0;
+
+        while !self.at_end {
+            if self.n_frames.is_some_and(|len| self.position >= len) {
+                self.at_end = true;
+                break;
+            }
+
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(_) => {
+                    self.at_end = true;
+                    break;
+                }
+            };
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                // Corrupt packet: skip it and keep streaming
+                Err(SymphoniaError::DecodeError(_)) => continue,
+                Err(_) => {
+                    self.at_end = true;
+                    break;
+                }
+            };
+
+            let frames = decoded.frames() as u64;
+            if frames == 0 {
+                continue;
+            }
+
+            let spec = *decoded.spec();
+            let channels = spec.channels.count();
+            let needed = decoded.capacity() * channels;
+            if self
+                .sample_buf
+                .as_ref()
+                .is_none_or(|b| b.capacity() < needed)
+            {
+                self.sample_buf = Some(SampleBuffer::new(decoded.capacity() as u64, spec));
+            }
+            let Some(buf) = self.sample_buf.as_mut() else {
+                continue;
+            };
+            buf.copy_interleaved_ref(decoded);
+
+            // Trim to [position, end): leading frames land before a seek
+            // target, trailing frames may be codec padding past the end.
+            let packet_ts = packet.ts();
+            let mut end = packet_ts + frames;
+            if let Some(len) = self.n_frames {
+                end = end.min(len);
+            }
+            let first = self.position.saturating_sub(packet_ts);
+            if packet_ts + first >= end {
+                continue;
+            }
+
+            let frames_iter = buf
+                .samples()
+                .chunks_exact(channels)
+                .take((end - packet_ts) as usize)
+                .skip(first as usize);
+            for frame in frames_iter {
+                let left = frame[0];
+                let right = if channels > 1 { frame[1] } else { left };
+                self.pending.push((left, right));
+            }
+            return true;
+        }
+
+        false
+    }
+}
+
+impl FrameSource for StreamDecoder {
+    fn read_frames(&mut self, start: u64, count: usize, out: &mut Vec<(f32, f32)>) {
+        if start != self.position {
+            self.seek(start);
+        }
+
+        let mut remaining = count;
+        while remaining > 0 {
+            if self.cursor == self.pending.len() && !self.decode_next() {
+                break;
+            }
+            let n = remaining.min(self.pending.len() - self.cursor);
+            out.extend_from_slice(&self.pending[self.cursor..self.cursor + n]);
+            self.cursor += n;
+            self.position += n as u64;
+            remaining -= n;
+        }
+
+        out.resize(out.len() + remaining, (0.0, 0.0));
+        self.position += remaining as u64;
+    }
+}
+
+fn open_error(path: &Path, err: SymphoniaError) -> Error {
+    Error::SampleNotFound(format!("{}: {}", path.display(), err))
+}
+
+/// File handle that reports every read to the butler's I/O metrics.
+struct MeteredFile {
+    file: File,
+    byte_len: Option<u64>,
+    metrics: Arc<IOMetrics>,
+}
+
+impl Read for MeteredFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.file.read(buf)?;
+        if n > 0 {
+            self.metrics.record_read(n as u64);
+        }
+        Ok(n)
+    }
+}
+
+impl Seek for MeteredFile {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+impl MediaSource for MeteredFile {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        self.byte_len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write a stereo 16-bit WAV whose left channel encodes the frame index.
+    fn write_ramp_wav(frames: u32) -> tempfile::NamedTempFile {
+        let file = tempfile::Builder::new().suffix(".wav").tempfile().unwrap();
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 48000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(file.path(), spec).unwrap();
+        for i in 0..frames {
+            writer.write_sample((i % 16384) as i16).unwrap();
+            writer.write_sample(-((i % 16384) as i16)).unwrap();
+        }
+        writer.finalize().unwrap();
+        file
+    }
+
+    fn frame_value(i: u64) -> f32 {
+        (i % 16384) as f32 / 32768.0
+    }
+
+    #[test]
+    fn test_sequential_and_seek_reads_are_sample_accurate() {
+        let file = write_ramp_wav(20_000);
+        let metrics = Arc::new(IOMetrics::new());
+        let mut decoder = StreamDecoder::open(file.path(), metrics.clone()).unwrap();
+
+        assert_eq!(decoder.n_frames(), Some(20_000));
+        assert_eq!(decoder.sample_rate(), 48000.0);
+
+        let mut out = Vec::new();
+        decoder.read_frames(0, 1000, &mut out);
+        decoder.read_frames(1000, 1000, &mut out);
+        assert_eq!(out.len(), 2000);
+        for (i, &(l, r)) in out.iter().enumerate() {
+            assert_eq!(l, frame_value(i as u64));
+            assert_eq!(r, -frame_value(i as u64));
+        }
+
+        // Backwards seek into the middle of a packet
+        out.clear();
+        decoder.read_frames(12_345, 10, &mut out);
+        for (i, &(l, _)) in out.iter().enumerate() {
+            assert_eq!(l, frame_value(12_345 + i as u64));
+        }
+
+        // Reads past the end are padded with silence
+        out.clear();
+        decoder.read_frames(19_998, 4, &mut out);
+        assert_eq!(out[1].0, frame_value(19_999));
+        assert_eq!(&out[2..], &[(0.0, 0.0), (0.0, 0.0)]);
+    }
+
+    #[test]
+    fn test_reads_only_what_is_requested() {
+        let file = write_ramp_wav(480_000);
+        let file_bytes = std::fs::metadata(file.path()).unwrap().len();
+        let metrics = Arc::new(IOMetrics::new());
+        let mut decoder = StreamDecoder::open(file.path(), metrics.clone()).unwrap();
+
+        let mut out = Vec::new();
+        decoder.read_frames(0, 4096, &mut out);
+
+        let read = metrics.snapshot().bytes_read;
+        assert!(read > 0);
+        assert!(
+            read < file_bytes / 4,
+            "read {read} of {file_bytes} bytes for a 4096-frame chunk"
+        );
+    }
+
+    #[test]
+    fn test_open_missing_file_fails() {
+        let metrics = Arc::new(IOMetrics::new());
+        assert!(StreamDecoder::open(Path::new("/nonexistent/file.wav"), metrics).is_err());
+    }
+}
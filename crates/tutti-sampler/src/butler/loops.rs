@@ -1,14 +1,10 @@
 //! Loop handling and crossfade capture for butler thread.
 
-use super::cache::LruCache;
-use super::metrics::IOMetrics;
+use super::decoder::FrameSource;
 use super::prefetch::RegionBufferProducer;
-use super::refill::get_wave_from_cache;
 use super::request::RegionId;
 use super::stream_state::{ChannelStreamState, LoopStatus};
 use dashmap::DashMap;
-use std::path::PathBuf;
-use tutti_core::Wave;
 
 /// Check and handle stream loop conditions with crossfade support.
 ///
@@ -18,8 +14,6 @@ pub(super) fn check_and_handle_loops(
     stream_states: &DashMap<usize, ChannelStreamState>,
     producers: &mut [RegionBufferProducer],
     producer_index: &std::collections::HashMap<RegionId, usize>,
-    sample_cache: &LruCache,
-    metrics: &IOMetrics,
 ) {
     for stream_entry in stream_states.iter() {
         let stream_state = stream_entry.value();
@@ -45,15 +39,15 @@ pub(super) fn check_and_handle_loops(
                 };
 
                 if let Some(&idx) = producer_index.get(&region_id) {
-                    let file_path = producers[idx].file_path();
-                    if let Some(wave) = get_wave_from_cache(sample_cache, metrics, file_path) {
-                        let fadeout_start = (loop_end as usize).saturating_sub(fade_len);
-                        let fadeout = capture_samples(&wave, fadeout_start, fade_len);
+                    let producer = &mut producers[idx];
+                    if producer.decoder_mut().is_some() {
+                        let fadeout_start = loop_end.saturating_sub(producer.file_frames(fade_len));
+                        let fadeout = producer.capture(fadeout_start, fade_len);
 
                         let fadein = if let Some(preloop) = stream_state.preloop_buffer() {
                             preloop.to_vec()
                         } else {
-                            capture_samples(&wave, loop_start as usize, fade_len)
+                            producer.capture(loop_start, fade_len)
                         };
 
                         stream_state
@@ -67,36 +61,36 @@ pub(super) fn check_and_handle_loops(
 
                 if let Some(region_id) = stream_state.region_id() {
                     if let Some(&idx) = producer_index.get(&region_id) {
+                        let producer = &mut producers[idx];
+
+                        // Flush old content and seek to loop start
+                        stream_state.flush_buffer();
+                        producer.set_file_position(loop_start);
+
                         // Get samples from loop start to pre-fill the buffer
                         // This prevents underruns while the butler refills asynchronously
-                        let file_path = producers[idx].file_path();
-                        let prefill_samples = if let Some(wave) =
-                            get_wave_from_cache(sample_cache, metrics, file_path)
-                        {
+                        let decode_space = producer.decode_space();
+                        let prefill_samples = if let Some(decoder) = producer.decoder_mut() {
                             // Capture the ENTIRE loop region to prevent underruns
                             // This ensures the audio thread always has valid content
                             let loop_end = stream_state
                                 .loop_range()
-                                .map(|(_, end)| end as usize)
-                                .unwrap_or(wave.len());
-                            let loop_len = loop_end - loop_start as usize;
+                                .map(|(_, end)| end)
+                                .or(decoder.n_frames())
+                                .unwrap_or(loop_start);
+                            let loop_len = loop_end.saturating_sub(loop_start) as usize;
                             // Fill buffer with at least one full loop iteration
                             // Limited by ring buffer capacity
-                            let prefill_len = loop_len.min(producers[idx].write_space());
-                            capture_samples(&wave, loop_start as usize, prefill_len)
+                            let prefill_len = loop_len.min(decode_space);
+                            capture_samples(decoder, loop_start, prefill_len)
                         } else {
                             Vec::new()
                         };
 
-                        // Flush old content and seek to loop start
-                        stream_state.flush_buffer();
-                        producers[idx].set_file_position(loop_start);
-
                         // Immediately write pre-captured samples to the buffer
                         if !prefill_samples.is_empty() {
-                            let written = producers[idx].write(&prefill_samples);
-                            // Advance file position to account for pre-filled samples
-                            producers[idx].set_file_position(loop_start + written as u64);
+                            let next_position = loop_start + prefill_samples.len() as u64;
+                            producer.write_decoded(&prefill_samples, next_position);
                         }
                     }
                 }
@@ -105,20 +99,14 @@ pub(super) fn check_and_handle_loops(
     }
 }
 
-/// Capture samples from a wave file into a Vec for crossfade.
-pub(super) fn capture_samples(wave: &Wave, start: usize, count: usize) -> Vec<(f32, f32)> {
+/// Capture samples from a stream source into a Vec for crossfade.
+pub(super) fn capture_samples<S: FrameSource + ?Sized>(
+    source: &mut S,
+    start: u64,
+    count: usize,
+) -> Vec<(f32, f32)> {
     let mut samples = Vec::with_capacity(count);
-    let channels = wave.channels();
-    for i in 0..count {
-        let idx = start + i;
-        if idx < wave.len() {
-            let left = wave.at(0, idx);
-            let right = if channels > 1 { wave.at(1, idx) } else { left };
-            samples.push((left, right));
-        } else {
-            samples.push((0.0, 0.0));
-        }
-    }
+    source.read_frames(start, count, &mut samples);
     samples
 }
 
@@ -165,11 +153,9 @@ pub(super) fn capture_fadeout_samples(
     samples
 }
 
-/// Capture samples from the Wave file at the new seek position for fadein.
+/// Capture samples from the region's decoder at the new seek position for fadein.
 pub(super) fn capture_fadein_samples(
-    sample_cache: &LruCache,
-    metrics: &IOMetrics,
-    file_path: &PathBuf,
+    producer: &mut RegionBufferProducer,
     position_samples: u64,
     count: usize,
 ) -> Vec<(f32, f32)> {
@@ -177,25 +163,7 @@ pub(super) fn capture_fadein_samples(
         return Vec::new();
     }
 
-    let Some(wave) = get_wave_from_cache(sample_cache, metrics, file_path) else {
-        return Vec::new();
-    };
-
-    let mut samples = Vec::with_capacity(count);
-    let channels = wave.channels();
-
-    for i in 0..count {
-        let idx = position_samples as usize + i;
-        if idx >= wave.len() {
-            samples.push((0.0, 0.0));
-        } else {
-            let left = wave.at(0, idx);
-            let right = if channels > 1 { wave.at(1, idx) } else { left };
-            samples.push((left, right));
-        }
-    }
-
-    samples
+    producer.capture(position_samples, count)
 }
 
 pub(super) fn calculate_buffer_size(file_length_samples: u64, sample_rate: f64) -> usize {
@@ -219,6 +187,9 @@ pub(super) fn calculate_buffer_size(file_length_samples: u64, sample_rate: f64)
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::butler::prefetch::RegionBuffer;
+    use std::path::PathBuf;
+    use tutti_core::Wave;
 
     fn make_test_wave(samples: &[(f32, f32)]) -> Wave {
         let mut wave = Wave::new(2, 48000.0);
@@ -238,9 +209,9 @@ mod tests {
 
     #[test]
     fn test_capture_samples_basic() {
-        let wave = make_test_wave(&[(1.0, 2.0), (3.0, 4.0), (5.0, 6.0), (7.0, 8.0)]);
+        let mut wave = make_test_wave(&[(1.0, 2.0), (3.0, 4.0), (5.0, 6.0), (7.0, 8.0)]);
 
-        let captured = capture_samples(&wave, 0, 3);
+        let captured = capture_samples(&mut wave, 0, 3);
 
         assert_eq!(captured.len(), 3);
         assert_eq!(captured[0], (1.0, 2.0));
@@ -250,9 +221,9 @@ mod tests {
 
     #[test]
     fn test_capture_samples_with_offset() {
-        let wave = make_test_wave(&[(1.0, 2.0), (3.0, 4.0), (5.0, 6.0), (7.0, 8.0)]);
+        let mut wave = make_test_wave(&[(1.0, 2.0), (3.0, 4.0), (5.0, 6.0), (7.0, 8.0)]);
 
-        let captured = capture_samples(&wave, 2, 2);
+        let captured = capture_samples(&mut wave, 2, 2);
 
         assert_eq!(captured.len(), 2);
         assert_eq!(captured[0], (5.0, 6.0));
@@ -261,9 +232,9 @@ mod tests {
 
     #[test]
     fn test_capture_samples_past_end_pads_zeros() {
-        let wave = make_test_wave(&[(1.0, 2.0), (3.0, 4.0)]);
+        let mut wave = make_test_wave(&[(1.0, 2.0), (3.0, 4.0)]);
 
-        let captured = capture_samples(&wave, 1, 4);
+        let captured = capture_samples(&mut wave, 1, 4);
 
         assert_eq!(captured.len(), 4);
         assert_eq!(captured[0], (3.0, 4.0)); // Valid sample
@@ -274,18 +245,18 @@ mod tests {
 
     #[test]
     fn test_capture_samples_empty_request() {
-        let wave = make_test_wave(&[(1.0, 2.0)]);
+        let mut wave = make_test_wave(&[(1.0, 2.0)]);
 
-        let captured = capture_samples(&wave, 0, 0);
+        let captured = capture_samples(&mut wave, 0, 0);
 
         assert!(captured.is_empty());
     }
 
     #[test]
     fn test_capture_samples_mono_duplicates_to_stereo() {
-        let wave = make_mono_wave(&[1.0, 2.0, 3.0]);
+        let mut wave = make_mono_wave(&[1.0, 2.0, 3.0]);
 
-        let captured = capture_samples(&wave, 0, 3);
+        let captured = capture_samples(&mut wave, 0, 3);
 
         assert_eq!(captured.len(), 3);
         assert_eq!(captured[0], (1.0, 1.0)); // Mono duplicated to stereo
@@ -380,34 +351,32 @@ mod tests {
 
     #[test]
     fn test_capture_fadein_zero_count() {
-        let cache = LruCache::new(10, 1024 * 1024);
-        let metrics = IOMetrics::new();
-        let path = PathBuf::from("nonexistent.wav");
+        let (mut producer, _) =
+            RegionBuffer::with_capacity(RegionId::generate(), PathBuf::from("test.wav"), 4096);
 
-        let samples = capture_fadein_samples(&cache, &metrics, &path, 0, 0);
+        let samples = capture_fadein_samples(&mut producer, 0, 0);
 
         assert!(samples.is_empty());
     }
 
     #[test]
-    fn test_capture_fadein_file_not_in_cache() {
-        let cache = LruCache::new(10, 1024 * 1024);
-        let metrics = IOMetrics::new();
-        let path = PathBuf::from("nonexistent.wav");
+    fn test_capture_fadein_without_decoder() {
+        let (mut producer, _) =
+            RegionBuffer::with_capacity(RegionId::generate(), PathBuf::from("test.wav"), 4096);
 
-        let samples = capture_fadein_samples(&cache, &metrics, &path, 0, 100);
+        let samples = capture_fadein_samples(&mut producer, 0, 100);
 
-        // File not in cache and doesn't exist, so returns empty
+        // No decoder attached (file never opened), so returns empty
         assert!(samples.is_empty());
     }
 
     #[test]
     fn test_capture_samples_empty_wave() {
         // Empty wave with 0 samples - should return zeros
-        let wave = Wave::new(2, 48000.0);
+        let mut wave = Wave::new(2, 48000.0);
         assert_eq!(wave.len(), 0);
 
-        let captured = capture_samples(&wave, 0, 3);
+        let captured = capture_samples(&mut wave, 0, 3);
 
         // Should pad with zeros since wave is empty
         assert_eq!(captured.len(), 3);
@@ -418,10 +387,10 @@ mod tests {
 
     #[test]
     fn test_capture_samples_large_start_no_panic() {
-        let wave = make_test_wave(&[(1.0, 2.0), (3.0, 4.0)]);
+        let mut wave = make_test_wave(&[(1.0, 2.0), (3.0, 4.0)]);
 
         // Start way past wave length - should not panic, just return zeros
-        let captured = capture_samples(&wave, 1_000_000, 5);
+        let captured = capture_samples(&mut wave, 1_000_000, 5);
 
         // All indices are way past wave.len(), so all zeros
         assert_eq!(captured.len(), 5);
@@ -2,8 +2,10 @@
 
 mod cache;
 mod capture;
+mod capture_file;
 mod config;
 mod crossfade;
+mod decoder;
 mod loops;
 mod metrics;
 mod pdc;
@@ -17,6 +19,12 @@ mod transport;
 mod varispeed;
 
 pub(crate) use cache::{CacheStats, LruCache};
+pub(crate) use capture_file::read_capture_frames;
+#[cfg(test)]
+pub(crate) use capture_file::CaptureWriter;
+pub use capture_file::{
+    recover_capture, CaptureContainer, CaptureFileInfo, CaptureFormat, CaptureSampleFormat,
+};
 pub(crate) use config::BufferConfig;
 pub(crate) use metrics::IOMetricsSnapshot;
 pub(crate) use prefetch::{CaptureBufferProducer, RegionBufferConsumer};
@@ -30,3 +38,8 @@ pub(crate) use crossfade::LoopCrossfade;
 pub(crate) use prefetch::{CaptureBuffer, CaptureBufferConsumer};
 pub(crate) use request::{ButlerCommand, FlushRequest};
 pub(crate) use thread::ButlerThread;
+
+#[cfg(test)]
+pub(crate) use prefetch::RegionBuffer;
+#[cfg(test)]
+pub(crate) use request::RegionId;
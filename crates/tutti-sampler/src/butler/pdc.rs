@@ -1,9 +1,7 @@
 //! Plugin delay compensation (PDC) integration for butler thread.
 
-use super::cache::LruCache;
 use super::config::BufferConfig;
 use super::loops::{capture_fadein_samples, capture_fadeout_samples};
-use super::metrics::IOMetrics;
 use super::prefetch::RegionBufferProducer;
 use super::request::RegionId;
 use super::stream_state::ChannelStreamState;
@@ -13,14 +11,11 @@ use tutti_core::PdcManager;
 
 /// Called each refill cycle. Detects plugin latency changes and
 /// adjusts stream positions with smooth crossfades.
-#[allow(clippy::too_many_arguments)]
 pub(super) fn check_pdc_updates(
     pdc_manager: &Option<Arc<PdcManager>>,
     stream_states: &DashMap<usize, ChannelStreamState>,
     producers: &mut [RegionBufferProducer],
     producer_index: &std::collections::HashMap<RegionId, usize>,
-    sample_cache: &LruCache,
-    metrics: &IOMetrics,
     config: &BufferConfig,
 ) {
     let Some(pdc) = pdc_manager.as_ref() else {
@@ -71,13 +66,7 @@ pub(super) fn check_pdc_updates(
         stream_state.flush_buffer();
         producer.set_file_position(new_pos);
 
-        let fadein = capture_fadein_samples(
-            sample_cache,
-            metrics,
-            producer.file_path(),
-            new_pos,
-            crossfade_len,
-        );
+        let fadein = capture_fadein_samples(producer, new_pos, crossfade_len);
 
         if !fadeout.is_empty() && !fadein.is_empty() {
             stream_state
@@ -102,8 +91,6 @@ mod tests {
         DashMap<usize, ChannelStreamState>,
         Vec<RegionBufferProducer>,
         std::collections::HashMap<RegionId, usize>,
-        LruCache,
-        IOMetrics,
         BufferConfig,
     ) {
         let stream_states = DashMap::new();
@@ -123,24 +110,14 @@ mod tests {
             stream_states.insert(i, state);
         }
 
-        let cache = LruCache::new(10, 1024 * 1024);
-        let metrics = IOMetrics::new();
         let config = BufferConfig::default();
 
-        (
-            stream_states,
-            producers,
-            producer_index,
-            cache,
-            metrics,
-            config,
-        )
+        (stream_states, producers, producer_index, config)
     }
 
     #[test]
     fn test_no_pdc_manager_is_noop() {
-        let (stream_states, mut producers, producer_index, cache, metrics, config) =
-            create_test_fixtures(1);
+        let (stream_states, mut producers, producer_index, config) = create_test_fixtures(1);
 
         // Set initial file position
         producers[0].set_file_position(1000);
@@ -151,8 +128,6 @@ mod tests {
             &stream_states,
             &mut producers,
             &producer_index,
-            &cache,
-            &metrics,
             &config,
         );
 
@@ -162,8 +137,7 @@ mod tests {
 
     #[test]
     fn test_pdc_disabled_is_noop() {
-        let (stream_states, mut producers, producer_index, cache, metrics, config) =
-            create_test_fixtures(1);
+        let (stream_states, mut producers, producer_index, config) = create_test_fixtures(1);
 
         let pdc = Arc::new(PdcManager::new(4, 0));
         pdc.set_enabled(false);
@@ -176,8 +150,6 @@ mod tests {
             &stream_states,
             &mut producers,
             &producer_index,
-            &cache,
-            &metrics,
             &config,
         );
 
@@ -187,8 +159,7 @@ mod tests {
 
     #[test]
     fn test_preroll_unchanged_no_seek() {
-        let (stream_states, mut producers, producer_index, cache, metrics, config) =
-            create_test_fixtures(1);
+        let (stream_states, mut producers, producer_index, config) = create_test_fixtures(1);
 
         let pdc = Arc::new(PdcManager::new(4, 0));
         // No latency set, so compensation is 0
@@ -201,8 +172,6 @@ mod tests {
             &stream_states,
             &mut producers,
             &producer_index,
-            &cache,
-            &metrics,
             &config,
         );
 
@@ -212,8 +181,7 @@ mod tests {
 
     #[test]
     fn test_preroll_increased_seeks_backward() {
-        let (stream_states, mut producers, producer_index, cache, metrics, config) =
-            create_test_fixtures(2);
+        let (stream_states, mut producers, producer_index, config) = create_test_fixtures(2);
 
         let pdc = Arc::new(PdcManager::new(4, 0));
 
@@ -231,8 +199,6 @@ mod tests {
             &stream_states,
             &mut producers,
             &producer_index,
-            &cache,
-            &metrics,
             &config,
         );
 
@@ -252,8 +218,7 @@ mod tests {
 
     #[test]
     fn test_preroll_decreased_seeks_forward() {
-        let (stream_states, mut producers, producer_index, cache, metrics, config) =
-            create_test_fixtures(1);
+        let (stream_states, mut producers, producer_index, config) = create_test_fixtures(1);
 
         let pdc = Arc::new(PdcManager::new(4, 0));
 
@@ -267,8 +232,6 @@ mod tests {
             &stream_states,
             &mut producers,
             &producer_index,
-            &cache,
-            &metrics,
             &config,
         );
 
@@ -285,8 +248,6 @@ mod tests {
             &stream_states,
             &mut producers,
             &producer_index,
-            &cache,
-            &metrics,
             &config,
         );
 
@@ -299,8 +260,7 @@ mod tests {
 
     #[test]
     fn test_seeking_flag_set_during_update() {
-        let (stream_states, mut producers, producer_index, cache, metrics, config) =
-            create_test_fixtures(1);
+        let (stream_states, mut producers, producer_index, config) = create_test_fixtures(1);
 
         let pdc = Arc::new(PdcManager::new(4, 0));
         pdc.set_channel_latency(1, 500); // Causes channel 0 to need compensation
@@ -315,8 +275,6 @@ mod tests {
             &stream_states,
             &mut producers,
             &producer_index,
-            &cache,
-            &metrics,
             &config,
         );
 
@@ -326,8 +284,7 @@ mod tests {
 
     #[test]
     fn test_multiple_channels_independent_compensation() {
-        let (stream_states, mut producers, producer_index, cache, metrics, config) =
-            create_test_fixtures(3);
+        let (stream_states, mut producers, producer_index, config) = create_test_fixtures(3);
 
         let pdc = Arc::new(PdcManager::new(4, 0));
 
@@ -347,8 +304,6 @@ mod tests {
             &stream_states,
             &mut producers,
             &producer_index,
-            &cache,
-            &metrics,
             &config,
         );
 
@@ -367,8 +322,7 @@ mod tests {
 
     #[test]
     fn test_channel_not_in_pdc_snapshot() {
-        let (stream_states, mut producers, producer_index, cache, metrics, config) =
-            create_test_fixtures(1);
+        let (stream_states, mut producers, producer_index, config) = create_test_fixtures(1);
 
         // Create PDC with only 0 channels (empty)
         let pdc = Arc::new(PdcManager::new(0, 0));
@@ -380,8 +334,6 @@ mod tests {
             &stream_states,
             &mut producers,
             &producer_index,
-            &cache,
-            &metrics,
             &config,
         );
 
@@ -8,7 +8,9 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use tutti_core::{AtomicU64, Ordering};
 
+use super::decoder::{FrameSource, StreamDecoder};
 use super::request::{CaptureId, RegionId};
+use crate::resample::{ResampleQuality, StreamResampler};
 
 #[derive(Debug)]
 pub(crate) struct RegionBufferMeta {
@@ -29,6 +31,12 @@ impl RegionBufferMeta {
 pub(crate) struct RegionBufferProducer {
     prod: HeapProd<(f32, f32)>,
     meta: Arc<RegionBufferMeta>,
+    decoder: Option<StreamDecoder>,
+    /// Converts decoded frames to the session rate; `None` when the rates match.
+    resampler: Option<StreamResampler>,
+    /// File position the resampler's last block ended at.
+    resampled_until: Option<u64>,
+    resample_scratch: Vec<(f32, f32)>,
 }
 
 impl RegionBufferProducer {
@@ -60,23 +68,95 @@ impl RegionBufferProducer {
         written
     }
 
-    /// Write samples in reverse order (for reverse playback).
-    /// Samples are taken from the end of the slice first.
-    pub fn write_reversed(&mut self, samples: &[(f32, f32)]) -> usize {
-        let mut written = 0;
-        for &sample in samples.iter().rev() {
-            if self.prod.try_push(sample).is_ok() {
-                written += 1;
-            } else {
-                break;
+    /// Decoded frames whose session-rate output fits in the free space.
+    pub fn decode_space(&self) -> usize {
+        match self.resampler {
+            Some(ref resampler) => resampler.input_for(self.write_space()),
+            None => self.write_space(),
+        }
+    }
+
+    /// File frames spanned by `frames` session-rate frames.
+    pub fn file_frames(&self, frames: usize) -> u64 {
+        match self.resampler {
+            Some(ref resampler) => (frames as f64 * resampler.step()).round() as u64,
+            None => frames as u64,
+        }
+    }
+
+    /// Write frames decoded from the current file position in playback order,
+    /// converted to the session rate, then move the file position on.
+    ///
+    /// `frames` must fit in [`decode_space`](Self::decode_space). The
+    /// resampler restarts whenever the file position jumped since its last block.
+    pub fn write_decoded(&mut self, frames: &[(f32, f32)], next_position: u64) {
+        match self.resampler {
+            Some(ref mut resampler) => {
+                if self.resampled_until != Some(self.meta.file_position()) {
+                    resampler.reset();
+                }
+                self.resample_scratch.clear();
+                resampler.process(frames, &mut self.resample_scratch);
+                for &frame in &self.resample_scratch {
+                    if self.prod.try_push(frame).is_err() {
+                        break;
+                    }
+                }
+                self.resampled_until = Some(next_position);
+            }
+            None => {
+                self.write(frames);
             }
         }
-        written
+        self.set_file_position(next_position);
+    }
+
+    /// Read `count` session-rate frames starting at file frame `start`, for
+    /// crossfades. Leaves the streaming position alone.
+    pub fn capture(&mut self, start: u64, count: usize) -> Vec<(f32, f32)> {
+        let Some(decoder) = self.decoder.as_mut() else {
+            return Vec::new();
+        };
+        let mut frames = Vec::with_capacity(count);
+        let Some(ref resampler) = self.resampler else {
+            decoder.read_frames(start, count, &mut frames);
+            return frames;
+        };
+
+        let mut converter = resampler.clone();
+        converter.reset();
+        decoder.read_frames(start, converter.input_len(count), &mut frames);
+        let mut converted = Vec::with_capacity(count + 1);
+        converter.process(&frames, &mut converted);
+        converted.resize(count, (0.0, 0.0));
+        converted
     }
 
     pub fn file_path(&self) -> &PathBuf {
         &self.meta.file_path
     }
+
+    /// Attach the decoder this region is streamed from.
+    pub fn set_decoder(&mut self, decoder: StreamDecoder) {
+        self.decoder = Some(decoder);
+    }
+
+    pub fn decoder_mut(&mut self) -> Option<&mut StreamDecoder> {
+        self.decoder.as_mut()
+    }
+
+    /// Convert this region from `ratio` file frames per session frame.
+    pub fn set_src_ratio(&mut self, ratio: f64, quality: ResampleQuality) {
+        self.resampler = (ratio != 1.0).then(|| StreamResampler::new(ratio, quality));
+        self.resampled_until = None;
+    }
+
+    /// Pick up a quality change published through the shared stream state.
+    pub fn set_resample_quality(&mut self, quality: ResampleQuality) {
+        if let Some(ref mut resampler) = self.resampler {
+            resampler.set_quality(quality);
+        }
+    }
 }
 
 unsafe impl Send for RegionBufferProducer {}
@@ -146,6 +226,10 @@ impl RegionBuffer {
         let producer = RegionBufferProducer {
             prod,
             meta: meta.clone(),
+            decoder: None,
+            resampler: None,
+            resampled_until: None,
+            resample_scratch: Vec::new(),
         };
 
         let consumer = RegionBufferConsumer {
@@ -222,6 +306,11 @@ impl CaptureBufferProducer {
     pub fn file_path(&self) -> &PathBuf {
         &self.meta.file_path
     }
+
+    /// Frames accepted into the buffer so far.
+    pub fn frames_captured(&self) -> u64 {
+        self.meta.frames_captured.load(Ordering::Relaxed)
+    }
 }
 
 pub(crate) struct CaptureBufferConsumer {
@@ -321,6 +410,31 @@ mod tests {
         assert_eq!(sample, (0.0, 0.0));
     }
 
+    #[test]
+    fn test_write_decoded_converts_to_session_rate() {
+        let (mut prod, mut cons) =
+            RegionBuffer::with_capacity(RegionId::generate(), PathBuf::from("test.wav"), 4096);
+        prod.set_src_ratio(2.0, ResampleQuality::Linear);
+
+        // Two blocks of a 2:1 file convert as one run
+        let ramp: Vec<_> = (0..400).map(|i| (i as f32, i as f32)).collect();
+        prod.write_decoded(&ramp[..150], 150);
+        prod.write_decoded(&ramp[150..], 400);
+        assert_eq!(prod.file_position(), 400);
+
+        let played: Vec<f32> = std::iter::from_fn(|| cons.read()).map(|f| f.0).collect();
+        assert_eq!(played.len(), 200);
+        for (j, &value) in played.iter().enumerate() {
+            assert_eq!(value, (2 * j) as f32);
+        }
+
+        // A seek restarts the conversion at the new position
+        prod.set_file_position(100);
+        prod.write_decoded(&ramp[100..110], 110);
+        assert_eq!(cons.read(), Some((100.0, 100.0)));
+        assert_eq!(cons.read(), Some((102.0, 102.0)));
+    }
+
     #[test]
     fn test_buffer_full() {
         let region_id = RegionId::generate();
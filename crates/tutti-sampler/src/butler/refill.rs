@@ -1,15 +1,13 @@
 //! Ring buffer refill logic for butler thread.
 
-use super::cache::LruCache;
+use super::decoder::FrameSource;
 use super::metrics::IOMetrics;
 use super::prefetch::RegionBufferProducer;
 use super::request::RegionId;
 use super::stream_state::ChannelStreamState;
 use dashmap::DashMap;
 use rayon::prelude::*;
-use std::path::PathBuf;
 use std::sync::Arc;
-use tutti_core::Wave;
 
 /// Calculate optimal chunk size using varifill strategy.
 ///
@@ -51,18 +49,18 @@ pub(super) fn calculate_varifill_chunk(
 /// Refill ring buffers from disk with varifill strategy.
 ///
 /// Uses a pre-allocated buffer to avoid allocation in the hot path.
-/// Loop crossfade is handled by the audio thread via SharedStreamState.
+/// Files at another sample rate are converted to the session rate here, so
+/// the audio thread only interpolates for varispeed. Loop crossfade is
+/// handled by the audio thread via SharedStreamState.
 ///
 /// Chunk size is dynamically adjusted (varifill) based on:
 /// - Buffer urgency (how empty the buffer is)
 /// - Disk throughput (recent read rate)
 /// - Playback speed (varispeed)
-#[allow(clippy::too_many_arguments)]
 pub(super) fn refill_all_streams(
     stream_states: &DashMap<usize, ChannelStreamState>,
     producers: &mut [RegionBufferProducer],
     producer_index: &std::collections::HashMap<RegionId, usize>,
-    sample_cache: &LruCache,
     metrics: &IOMetrics,
     base_chunk_size: usize,
     buffer_margin: f64,
@@ -106,39 +104,25 @@ pub(super) fn refill_all_streams(
         let speed = stream_state.speed();
         let src_ratio = stream_state.shared_state().src_ratio();
 
-        // SRC ratio > 1.0 means audio thread consumes ring buffer faster
+        // SRC ratio > 1.0 means more file frames are decoded per frame played
         let adjusted_speed = speed * src_ratio * buffer_margin as f32;
         let chunk_size =
             calculate_varifill_chunk(fill_pct, base_chunk_size, read_rate, adjusted_speed);
 
-        let file_path = producer.file_path();
-        let wave = match get_wave_from_cache(sample_cache, metrics, file_path) {
-            Some(w) => w,
-            None => continue,
-        };
-
-        let file_position = producer.file_position() as usize;
-        let channels = wave.channels();
+        let file_position = producer.file_position();
 
         // Get loop range to respect loop boundaries during refill
         let loop_range = stream_state.loop_range();
 
+        producer.set_resample_quality(stream_state.shared_state().resample_quality());
+
         if is_reverse {
-            refill_reverse(
-                producer,
-                &wave,
-                file_position,
-                chunk_size,
-                channels,
-                interleave_buffer,
-            );
+            refill_reverse(producer, file_position, chunk_size, interleave_buffer);
         } else {
             refill_forward(
                 producer,
-                &wave,
                 file_position,
                 chunk_size,
-                channels,
                 interleave_buffer,
                 loop_range,
             );
@@ -150,7 +134,7 @@ struct RefillWorkItem {
     producer_idx: usize,
     chunk_size: usize,
     is_reverse: bool,
-    file_path: PathBuf,
+    loop_range: Option<(u64, u64)>,
     fill_pct: f32,
     shared: Arc<super::shared_state::SharedStreamState>,
 }
@@ -158,13 +142,12 @@ struct RefillWorkItem {
 /// Parallel refill using rayon's par_iter_mut with varifill strategy.
 ///
 /// Uses Vec<RegionBufferProducer> with par_iter_mut which only requires Send, not Sync.
-/// Each rayon worker gets exclusive &mut access to a different producer.
+/// Each rayon worker gets exclusive &mut access to a different producer and its decoder.
 /// Only used when parallel_io is enabled and there are 3+ streams.
 pub(super) fn refill_all_streams_parallel(
     stream_states: &DashMap<usize, ChannelStreamState>,
     producers: &mut [RegionBufferProducer],
     producer_index: &std::collections::HashMap<RegionId, usize>,
-    sample_cache: &LruCache,
     metrics: &IOMetrics,
     base_chunk_size: usize,
     buffer_margin: f64,
@@ -201,7 +184,7 @@ pub(super) fn refill_all_streams_parallel(
                 producer_idx: idx,
                 chunk_size,
                 is_reverse: stream_state.is_reverse(),
-                file_path: producer.file_path().to_path_buf(),
+                loop_range: stream_state.loop_range(),
                 fill_pct,
                 shared,
             })
@@ -234,238 +217,149 @@ pub(super) fn refill_all_streams_parallel(
 
             LOCAL_BUF.with(|buf| {
                 let mut buf = buf.borrow_mut();
-                refill_single_stream_with_producer(
-                    producer,
-                    sample_cache,
-                    item.chunk_size,
-                    item.is_reverse,
-                    &item.file_path,
-                    item.fill_pct,
-                    &item.shared,
-                    &mut buf,
-                );
+                item.shared.set_buffer_fill(item.fill_pct);
+                producer.set_resample_quality(item.shared.resample_quality());
+
+                let file_position = producer.file_position();
+                if item.is_reverse {
+                    refill_reverse(producer, file_position, item.chunk_size, &mut buf);
+                } else {
+                    refill_forward(
+                        producer,
+                        file_position,
+                        item.chunk_size,
+                        &mut buf,
+                        item.loop_range,
+                    );
+                }
             });
         });
 }
 
-/// Refill a single stream with the provided producer and buffer (used by parallel path).
-///
-/// Takes a direct mutable reference to the producer (from par_iter_mut).
-#[allow(clippy::too_many_arguments)]
-fn refill_single_stream_with_producer(
-    producer: &mut RegionBufferProducer,
-    sample_cache: &LruCache,
-    chunk_size: usize,
-    is_reverse: bool,
-    file_path: &PathBuf,
-    fill_pct: f32,
-    shared: &super::shared_state::SharedStreamState,
-    buffer: &mut Vec<(f32, f32)>,
-) {
-    let wave = match sample_cache.get(file_path) {
-        Some(w) => w,
-        None => return,
-    };
-
-    shared.set_buffer_fill(fill_pct);
-
-    let file_position = producer.file_position() as usize;
-    let channels = wave.channels();
-
-    buffer.clear();
-
-    if is_reverse {
-        fill_buffer_reverse(&wave, file_position, chunk_size, channels, buffer);
-        let written = producer.write(buffer);
-        producer.set_file_position(file_position.saturating_sub(written) as u64);
-    } else {
-        fill_buffer_forward(&wave, file_position, chunk_size, channels, buffer);
-        let written = producer.write(buffer);
-        producer.set_file_position((file_position + written) as u64);
-    }
+/// Loop bounds as `(start, end)`, or `None` if the range is empty.
+#[inline]
+fn loop_bounds(loop_range: Option<(u64, u64)>) -> Option<(u64, u64)> {
+    loop_range.filter(|&(start, end)| end > start)
 }
 
-/// Fill buffer with forward samples (no ring buffer write).
-#[inline]
-fn fill_buffer_forward(
-    wave: &Wave,
-    file_position: usize,
+/// Fill buffer with forward samples, wrapping at the loop end if set (no ring buffer write).
+///
+/// Reads contiguous runs so a decoder only seeks at the loop wrap.
+fn fill_buffer_forward<S: FrameSource + ?Sized>(
+    source: &mut S,
+    file_position: u64,
     chunk_size: usize,
-    channels: usize,
+    loop_bounds: Option<(u64, u64)>,
     buffer: &mut Vec<(f32, f32)>,
 ) {
-    for i in 0..chunk_size {
-        let sample_idx = file_position + i;
-        let sample = if sample_idx >= wave.len() {
-            (0.0, 0.0)
-        } else {
-            let left = wave.at(0, sample_idx);
-            let right = if channels > 1 {
-                wave.at(1, sample_idx)
-            } else {
-                left
-            };
-            (left, right)
+    let mut pos = file_position;
+    let mut remaining = chunk_size;
+
+    while remaining > 0 {
+        let run = match loop_bounds {
+            Some((loop_start, loop_end)) => {
+                if pos >= loop_end {
+                    pos = loop_start + ((pos - loop_start) % (loop_end - loop_start));
+                }
+                remaining.min((loop_end - pos) as usize)
+            }
+            None => remaining,
         };
-        buffer.push(sample);
+
+        source.read_frames(pos, run, buffer);
+        pos += run as u64;
+        remaining -= run;
     }
 }
 
-/// Fill buffer with reversed samples (no ring buffer write).
-#[inline]
-fn fill_buffer_reverse(
-    wave: &Wave,
-    file_position: usize,
+/// Fill buffer with the frames preceding `file_position`, in file order (no ring buffer write).
+///
+/// Pads a full chunk of silence once playback has reached the start of the file.
+fn fill_buffer_reverse<S: FrameSource + ?Sized>(
+    source: &mut S,
+    file_position: u64,
     chunk_size: usize,
-    channels: usize,
     buffer: &mut Vec<(f32, f32)>,
 ) {
-    let read_start = file_position.saturating_sub(chunk_size);
-    let actual_chunk = file_position - read_start;
+    let read_start = file_position.saturating_sub(chunk_size as u64);
+    let actual_chunk = (file_position - read_start) as usize;
 
     if actual_chunk == 0 {
-        for _ in 0..chunk_size {
-            buffer.push((0.0, 0.0));
-        }
+        buffer.resize(buffer.len() + chunk_size, (0.0, 0.0));
         return;
     }
 
-    let mut temp = Vec::with_capacity(actual_chunk);
-    for i in 0..actual_chunk {
-        let sample_idx = read_start + i;
-        let left = wave.at(0, sample_idx);
-        let right = if channels > 1 {
-            wave.at(1, sample_idx)
-        } else {
-            left
-        };
-        temp.push((left, right));
-    }
-
-    for sample in temp.into_iter().rev() {
-        buffer.push(sample);
-    }
+    source.read_frames(read_start, actual_chunk, buffer);
 }
 
 /// Refill buffer for forward playback, respecting loop boundaries if set.
-#[allow(clippy::too_many_arguments)]
+///
+/// Decodes at most one ring buffer's worth of free space, so the decoder's
+/// read position stays in step with the producer's file position.
 pub(super) fn refill_forward(
     producer: &mut RegionBufferProducer,
-    wave: &Wave,
-    file_position: usize,
+    file_position: u64,
     chunk_size: usize,
-    channels: usize,
     interleave_buffer: &mut Vec<(f32, f32)>,
     loop_range: Option<(u64, u64)>,
 ) {
-    interleave_buffer.clear();
-
-    // Extract loop bounds if set and valid
-    let loop_bounds = loop_range.and_then(|(start, end)| {
-        let start = start as usize;
-        let end = end as usize;
-        let len = end.saturating_sub(start);
-        (len > 0).then_some((start, end, len))
-    });
-
-    let mut pos = file_position;
-
-    for _ in 0..chunk_size {
-        // Wrap position if looping
-        if let Some((loop_start, loop_end, loop_len)) = loop_bounds {
-            if pos >= loop_end {
-                pos = loop_start + ((pos - loop_start) % loop_len);
-            }
-        }
-
-        let sample = if pos >= wave.len() {
-            (0.0, 0.0)
-        } else {
-            let left = wave.at(0, pos);
-            let right = if channels > 1 { wave.at(1, pos) } else { left };
-            (left, right)
-        };
-
-        interleave_buffer.push(sample);
-        pos += 1;
+    let chunk_size = chunk_size.min(producer.decode_space());
+    let Some(decoder) = producer.decoder_mut() else {
+        return;
+    };
+    if chunk_size == 0 {
+        return;
     }
 
-    let written = producer.write(interleave_buffer);
+    let loop_bounds = loop_bounds(loop_range);
+
+    interleave_buffer.clear();
+    fill_buffer_forward(
+        decoder,
+        file_position,
+        chunk_size,
+        loop_bounds,
+        interleave_buffer,
+    );
 
     // Calculate new file position, wrapping if looping
-    let mut new_pos = file_position + written;
-    if let Some((loop_start, loop_end, loop_len)) = loop_bounds {
+    let mut new_pos = file_position + interleave_buffer.len() as u64;
+    if let Some((loop_start, loop_end)) = loop_bounds {
         if new_pos >= loop_end {
-            new_pos = loop_start + ((new_pos - loop_start) % loop_len);
+            new_pos = loop_start + ((new_pos - loop_start) % (loop_end - loop_start));
         }
     }
-    producer.set_file_position(new_pos as u64);
+    producer.write_decoded(interleave_buffer, new_pos);
 }
 
 /// Refill buffer for reverse playback.
 /// Reads samples forward from disk, then writes them reversed to the ring buffer.
 pub(super) fn refill_reverse(
     producer: &mut RegionBufferProducer,
-    wave: &Wave,
-    file_position: usize,
+    file_position: u64,
     chunk_size: usize,
-    channels: usize,
     interleave_buffer: &mut Vec<(f32, f32)>,
 ) {
-    let read_start = file_position.saturating_sub(chunk_size);
-    let actual_chunk = file_position - read_start;
-
-    if actual_chunk == 0 {
-        interleave_buffer.clear();
-        for _ in 0..chunk_size {
-            interleave_buffer.push((0.0, 0.0));
-        }
-        producer.write(interleave_buffer);
+    let chunk_size = chunk_size.min(producer.decode_space());
+    let Some(decoder) = producer.decoder_mut() else {
+        return;
+    };
+    if chunk_size == 0 {
         return;
     }
 
     interleave_buffer.clear();
-    for i in 0..actual_chunk {
-        let sample_idx = read_start + i;
-        let left = wave.at(0, sample_idx);
-        let right = if channels > 1 {
-            wave.at(1, sample_idx)
-        } else {
-            left
-        };
-        interleave_buffer.push((left, right));
-    }
+    fill_buffer_reverse(decoder, file_position, chunk_size, interleave_buffer);
 
-    let written = producer.write_reversed(interleave_buffer);
-    producer.set_file_position(file_position.saturating_sub(written) as u64);
-}
-
-pub(super) fn get_wave_from_cache(
-    sample_cache: &LruCache,
-    metrics: &IOMetrics,
-    file_path: &PathBuf,
-) -> Option<Arc<Wave>> {
-    if let Some(cached) = sample_cache.get(file_path) {
-        metrics.record_cache_hit();
-        Some(cached)
-    } else {
-        metrics.record_cache_miss();
-        match Wave::load(file_path) {
-            Ok(w) => {
-                let arc_wave = Arc::new(w);
-                let bytes = arc_wave.len() as u64 * arc_wave.channels() as u64 * 4;
-                metrics.record_read(bytes);
-                sample_cache.insert(file_path.clone(), arc_wave.clone());
-                Some(arc_wave)
-            }
-            Err(_) => None,
-        }
-    }
+    let new_pos = file_position.saturating_sub(interleave_buffer.len() as u64);
+    interleave_buffer.reverse();
+    producer.write_decoded(interleave_buffer, new_pos);
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tutti_core::Wave;
 
     #[test]
     fn test_varifill_empty_buffer_increases_chunk() {
@@ -660,10 +554,10 @@ mod tests {
     #[test]
     fn test_fill_buffer_forward_basic() {
         // Create a simple wave: 0.1, 0.2, 0.3, 0.4
-        let wave = make_test_wave(&[(0.1, 0.1), (0.2, 0.2), (0.3, 0.3), (0.4, 0.4)]);
+        let mut wave = make_test_wave(&[(0.1, 0.1), (0.2, 0.2), (0.3, 0.3), (0.4, 0.4)]);
         let mut buffer = Vec::new();
 
-        fill_buffer_forward(&wave, 0, 3, 2, &mut buffer);
+        fill_buffer_forward(&mut wave, 0, 3, None, &mut buffer);
 
         assert_eq!(buffer.len(), 3);
         assert_eq!(buffer[0], (0.1, 0.1));
@@ -673,10 +567,10 @@ mod tests {
 
     #[test]
     fn test_fill_buffer_forward_past_end_pads_zeros() {
-        let wave = make_test_wave(&[(0.1, 0.1), (0.2, 0.2)]);
+        let mut wave = make_test_wave(&[(0.1, 0.1), (0.2, 0.2)]);
         let mut buffer = Vec::new();
 
-        fill_buffer_forward(&wave, 1, 4, 2, &mut buffer);
+        fill_buffer_forward(&mut wave, 1, 4, None, &mut buffer);
 
         assert_eq!(buffer.len(), 4);
         assert_eq!(buffer[0], (0.2, 0.2)); // Last valid sample
@@ -685,6 +579,30 @@ mod tests {
         assert_eq!(buffer[3], (0.0, 0.0));
     }
 
+    #[test]
+    fn test_fill_buffer_forward_wraps_at_loop_end() {
+        let mut wave = make_test_wave(&[(0.0, 0.0), (0.1, 0.1), (0.2, 0.2), (0.3, 0.3)]);
+        let mut buffer = Vec::new();
+
+        fill_buffer_forward(&mut wave, 2, 5, Some((1, 3)), &mut buffer);
+
+        let left: Vec<f32> = buffer.iter().map(|s| s.0).collect();
+        assert_eq!(left, vec![0.2, 0.1, 0.2, 0.1, 0.2]);
+    }
+
+    #[test]
+    fn test_fill_buffer_reverse_reads_preceding_frames() {
+        let mut wave = make_test_wave(&[(0.0, 0.0), (0.1, 0.1), (0.2, 0.2), (0.3, 0.3)]);
+        let mut buffer = Vec::new();
+
+        fill_buffer_reverse(&mut wave, 3, 2, &mut buffer);
+        assert_eq!(buffer, vec![(0.1, 0.1), (0.2, 0.2)]);
+
+        buffer.clear();
+        fill_buffer_reverse(&mut wave, 0, 2, &mut buffer);
+        assert_eq!(buffer, vec![(0.0, 0.0), (0.0, 0.0)]);
+    }
+
     #[test]
     fn test_loop_wrap_position_calculation() {
         // Test the wrapping logic used in refill_forward_loop_aware
@@ -24,6 +24,7 @@ impl CaptureId {
     }
 }
 
+use super::capture_file::CaptureFormat;
 use super::prefetch::CaptureBufferConsumer;
 use super::varispeed::PlayDirection;
 
@@ -127,6 +128,8 @@ pub(crate) enum ButlerCommand {
         sample_rate: f64,
         /// Number of audio channels
         channels: usize,
+        /// Container and sample format of the file
+        format: CaptureFormat,
     },
     /// Remove a capture buffer (finalize and close file)
     RemoveCapture(CaptureId),
@@ -212,11 +215,13 @@ impl std::fmt::Debug for ButlerCommand {
             ButlerCommand::RegisterCapture {
                 capture_id,
                 file_path,
+                format,
                 ..
             } => f
                 .debug_struct("RegisterCapture")
                 .field("capture_id", capture_id)
                 .field("file_path", file_path)
+                .field("format", format)
                 .finish(),
             ButlerCommand::RemoveCapture(id) => f.debug_tuple("RemoveCapture").field(id).finish(),
             ButlerCommand::Flush(req) => f.debug_tuple("Flush").field(req).finish(),
@@ -3,6 +3,7 @@
 //! All fields are atomic or lock-free for RT-safe cross-thread access.
 //! The audio thread must never block, so we use ArcSwap for buffer access.
 
+use crate::resample::ResampleQuality;
 use arc_swap::ArcSwap;
 use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicU8, Ordering};
 use std::sync::Arc;
@@ -18,6 +19,8 @@ pub struct SharedStreamState {
     /// 0 = forward, 1 = reverse
     direction: AtomicU8,
     seeking: AtomicBool,
+    /// Bumped on every seek so the audio thread notices ones it never saw in progress
+    seek_generation: AtomicU64,
     underrun_count: AtomicU64,
     /// 0-1000 representing 0.0-1.0
     buffer_fill_level: AtomicU32,
@@ -34,8 +37,11 @@ pub struct SharedStreamState {
     /// 0 = not active
     loop_crossfade_len: AtomicU32,
 
-    /// file_sample_rate / session_sample_rate. 1.0 = no conversion.
+    /// file_sample_rate / session_sample_rate, converted by the butler. 1.0 = no conversion.
     src_ratio: AtomicFloat,
+    /// [`ResampleQuality`] of the butler's rate conversion and the audio
+    /// thread's varispeed interpolation.
+    resample_quality: AtomicU8,
 }
 
 impl Default for SharedStreamState {
@@ -53,6 +59,7 @@ impl SharedStreamState {
             speed_ramp_samples: AtomicU32::new(0),
             direction: AtomicU8::new(0),
             seeking: AtomicBool::new(false),
+            seek_generation: AtomicU64::new(0),
             underrun_count: AtomicU64::new(0),
             buffer_fill_level: AtomicU32::new(0),
             seek_fadeout: ArcSwap::from_pointee(Vec::new()),
@@ -64,6 +71,11 @@ impl SharedStreamState {
             loop_crossfade_pos: AtomicU32::new(0),
             loop_crossfade_len: AtomicU32::new(0),
             src_ratio: AtomicFloat::new(1.0),
+            resample_quality: AtomicU8::new({
+                let quality = ResampleQuality::default();
+                quality.prepare();
+                quality.as_u8()
+            }),
         }
     }
 
@@ -144,9 +156,17 @@ impl SharedStreamState {
     }
 
     pub fn set_seeking(&self, seeking: bool) {
+        if seeking {
+            self.seek_generation.fetch_add(1, Ordering::AcqRel);
+        }
         self.seeking.store(seeking, Ordering::Release);
     }
 
+    #[inline]
+    pub fn seek_generation(&self) -> u64 {
+        self.seek_generation.load(Ordering::Acquire)
+    }
+
     #[inline]
     pub fn src_ratio(&self) -> f32 {
         self.src_ratio.get()
@@ -156,6 +176,19 @@ impl SharedStreamState {
         self.src_ratio.set(ratio);
     }
 
+    #[inline]
+    pub fn resample_quality(&self) -> ResampleQuality {
+        ResampleQuality::from_u8(self.resample_quality.load(Ordering::Acquire))
+    }
+
+    /// Builds the kernel table (if needed) before publishing, so the audio
+    /// thread never allocates when it switches.
+    pub fn set_resample_quality(&self, quality: ResampleQuality) {
+        quality.prepare();
+        self.resample_quality
+            .store(quality.as_u8(), Ordering::Release);
+    }
+
     #[inline]
     pub fn report_underrun(&self) {
         self.underrun_count.fetch_add(1, Ordering::Relaxed);
@@ -361,6 +394,7 @@ mod tests {
 
         state.set_seeking(false);
         assert!(!state.is_seeking());
+        assert_eq!(state.seek_generation(), 1);
     }
 
     #[test]
@@ -136,7 +136,9 @@ impl ChannelStreamState {
             return LoopStatus::Normal;
         };
 
-        let read_pos = pos.load(Ordering::Relaxed);
+        // The ring holds session-rate frames; loop points are file frames
+        let read_pos =
+            (pos.load(Ordering::Relaxed) as f64 * self.shared_state.src_ratio() as f64) as u64;
 
         if read_pos >= loop_end {
             return LoopStatus::AtEnd(loop_start);
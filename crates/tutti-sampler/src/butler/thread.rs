@@ -1,11 +1,13 @@
 //! Butler thread for asynchronous disk I/O operations.
 
 use super::cache::LruCache;
-use super::capture::{create_wav_writer, flush_all_captures, flush_capture, CaptureConsumerState};
+use super::capture::{
+    create_capture_writer, flush_all_captures, flush_capture, CaptureConsumerState,
+};
 use super::config::BufferConfig;
+use super::decoder::StreamDecoder;
 use super::loops::{
-    calculate_buffer_size, capture_fadein_samples, capture_fadeout_samples, capture_samples,
-    check_and_handle_loops,
+    calculate_buffer_size, capture_fadein_samples, capture_fadeout_samples, check_and_handle_loops,
 };
 use super::metrics::IOMetrics;
 use super::pdc::check_pdc_updates;
@@ -29,7 +31,6 @@ use tutti_core::PdcManager;
 /// butler function. Extracted to avoid 8-13 parameter function signatures.
 pub(super) struct ButlerResources {
     pub stream_states: Arc<DashMap<usize, ChannelStreamState>>,
-    pub sample_cache: Arc<LruCache>,
     pub metrics: Arc<IOMetrics>,
     pub pdc_manager: Option<Arc<PdcManager>>,
     pub config: BufferConfig,
@@ -112,7 +113,6 @@ impl ButlerThread {
 
         let res = ButlerResources {
             stream_states: Arc::clone(&self.stream_states),
-            sample_cache: Arc::clone(&self.sample_cache),
             metrics: Arc::clone(&self.metrics),
             pdc_manager: self.pdc_manager.clone(),
             config: self.config,
@@ -208,25 +208,16 @@ fn butler_loop(
             &res.stream_states,
             &mut ms.producers,
             &ms.producer_index,
-            &res.sample_cache,
-            &res.metrics,
             &res.config,
         );
 
-        check_and_handle_loops(
-            &res.stream_states,
-            &mut ms.producers,
-            &ms.producer_index,
-            &res.sample_cache,
-            &res.metrics,
-        );
+        check_and_handle_loops(&res.stream_states, &mut ms.producers, &ms.producer_index);
 
         if parallel_io && res.stream_states.len() >= 3 {
             refill_all_streams_parallel(
                 &res.stream_states,
                 &mut ms.producers,
                 &ms.producer_index,
-                &res.sample_cache,
                 &res.metrics,
                 base_chunk_size,
                 ms.buffer_margin,
@@ -236,7 +227,6 @@ fn butler_loop(
                 &res.stream_states,
                 &mut ms.producers,
                 &ms.producer_index,
-                &res.sample_cache,
                 &res.metrics,
                 base_chunk_size,
                 ms.buffer_margin,
@@ -369,16 +359,11 @@ fn handle_command(
             file_path,
             sample_rate,
             channels,
+            format,
         } => {
-            let writer = create_wav_writer(&file_path, sample_rate, channels);
-            ms.capture_consumers.insert(
-                capture_id,
-                CaptureConsumerState {
-                    consumer,
-                    writer,
-                    channels,
-                },
-            );
+            let writer = create_capture_writer(&file_path, sample_rate, channels, format);
+            ms.capture_consumers
+                .insert(capture_id, CaptureConsumerState { consumer, writer });
         }
         ButlerCommand::RemoveCapture(capture_id) => {
             if let Some(mut cap_state) = ms.capture_consumers.remove(&capture_id) {
@@ -426,34 +411,24 @@ fn handle_stream_audio_file(
 ) {
     use super::prefetch::RegionBuffer;
     use parking_lot::Mutex;
-    use tutti_core::Wave;
 
-    let wave = if let Some(cached) = res.sample_cache.get(&file_path) {
-        res.metrics.record_cache_hit();
-        cached
-    } else {
-        res.metrics.record_cache_miss();
-        match Wave::load(&file_path) {
-            Ok(w) => {
-                let arc_wave = Arc::new(w);
-                let bytes = arc_wave.len() as u64 * arc_wave.channels() as u64 * 4;
-                res.metrics.record_read(bytes);
-                res.sample_cache.insert(file_path.clone(), arc_wave.clone());
-                arc_wave
-            }
-            Err(_) => {
-                return;
-            }
-        }
+    // Only the container header is read here; audio is decoded chunk by chunk on refill
+    let Ok(decoder) = StreamDecoder::open(&file_path, Arc::clone(&res.metrics)) else {
+        return;
     };
 
-    let file_length = wave.len() as u64;
+    // Unknown-length streams (some Ogg files) get the 30s cap used for short files
+    let file_length = decoder
+        .n_frames()
+        .unwrap_or((res.sample_rate * 30.0) as u64);
+    let file_sr = decoder.sample_rate();
 
     let buffer_capacity = calculate_buffer_size(file_length, res.sample_rate);
     let region_id = RegionId::generate();
 
-    let (producer, consumer) =
+    let (mut producer, consumer) =
         RegionBuffer::with_capacity(region_id, file_path.clone(), buffer_capacity);
+    producer.set_decoder(decoder);
 
     let pdc_preroll = res
         .pdc_manager
@@ -465,23 +440,25 @@ fn handle_stream_audio_file(
     let adjusted_offset = (offset_samples as u64).saturating_sub(pdc_preroll);
     producer.set_file_position(adjusted_offset);
 
-    let idx = ms.producers.len();
-    ms.producers.push(producer);
-    ms.producer_index.insert(region_id, idx);
-
-    res.stream_states.entry(channel_index).or_default();
-
-    let file_sr = wave.sample_rate();
     let src_ratio = if (file_sr - res.sample_rate).abs() < 0.01 {
         1.0
     } else {
         (file_sr / res.sample_rate) as f32
     };
+    producer.set_src_ratio(src_ratio as f64, res.config.resample_quality);
+
+    let idx = ms.producers.len();
+    ms.producers.push(producer);
+    ms.producer_index.insert(region_id, idx);
+
+    res.stream_states.entry(channel_index).or_default();
 
     if let Some(mut stream_state) = res.stream_states.get_mut(&channel_index) {
         stream_state.start_streaming(Arc::new(Mutex::new(consumer)));
         stream_state.set_pdc_preroll(pdc_preroll);
-        stream_state.shared_state().set_src_ratio(src_ratio);
+        let shared = stream_state.shared_state();
+        shared.set_src_ratio(src_ratio);
+        shared.set_resample_quality(res.config.resample_quality);
     }
 }
 
@@ -507,13 +484,7 @@ fn handle_seek_stream(
                 stream_state.flush_buffer();
                 producer.set_file_position(adjusted_position);
 
-                let fadein = capture_fadein_samples(
-                    &res.sample_cache,
-                    &res.metrics,
-                    producer.file_path(),
-                    adjusted_position,
-                    crossfade_len,
-                );
+                let fadein = capture_fadein_samples(producer, adjusted_position, crossfade_len);
 
                 if !fadeout.is_empty() && !fadein.is_empty() {
                     stream_state
@@ -546,13 +517,9 @@ fn handle_set_loop_range(
                     ms.producers[idx].set_file_position(start_samples);
                 }
 
-                if crossfade_samples > 0 {
-                    let file_path = ms.producers[idx].file_path();
-                    if let Some(wave) = res.sample_cache.get(file_path) {
-                        let preloop =
-                            capture_samples(&wave, start_samples as usize, crossfade_samples);
-                        stream_state.set_preloop_buffer(preloop);
-                    }
+                if crossfade_samples > 0 && ms.producers[idx].decoder_mut().is_some() {
+                    let preloop = ms.producers[idx].capture(start_samples, crossfade_samples);
+                    stream_state.set_preloop_buffer(preloop);
                 }
             }
         }
@@ -586,13 +553,7 @@ fn handle_update_pdc_preroll(
                     stream_state.flush_buffer();
                     producer.set_file_position(new_pos);
 
-                    let fadein = capture_fadein_samples(
-                        &res.sample_cache,
-                        &res.metrics,
-                        producer.file_path(),
-                        new_pos,
-                        crossfade_len,
-                    );
+                    let fadein = capture_fadein_samples(producer, new_pos, crossfade_len);
 
                     if !fadeout.is_empty() && !fadein.is_empty() {
                         stream_state
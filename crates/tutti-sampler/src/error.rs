@@ -22,6 +22,9 @@ pub enum Error {
     #[error("SoundFont error: {0}")]
     SoundFont(String),
 
+    #[error("Instrument error: {0}")]
+    Instrument(String),
+
     #[error("Time stretch error: {0}")]
     TimeStretch(String),
 
@@ -92,6 +92,20 @@ impl SamplerHandle {
             .start_recording(channel_index, source, mode, current_beat)
     }
 
+    /// Start recording with full settings, e.g. a loop range for cycle recording.
+    pub fn start_recording_with_config(
+        &self,
+        config: crate::RecordingConfig,
+        current_beat: f64,
+    ) -> crate::error::Result<()> {
+        let sampler = self.sampler.as_ref().ok_or_else(|| {
+            crate::error::Error::Recording("Sampler subsystem is disabled".to_string())
+        })?;
+        sampler
+            .recording()
+            .start_recording_with_config(config, current_beat)
+    }
+
     pub fn stop_recording(
         &self,
         channel_index: usize,
@@ -0,0 +1,319 @@
+//! Decent Sampler `.dspreset` import.
+//!
+//! Reads the `<groups>`/`<group>`/`<sample>` mapping (attributes inherit
+//! downwards) and ignores UI, effects and modulation sections.
+
+use super::sfz::parse_note;
+use super::zone::{Zone, ZoneLoopMode};
+use super::WaveCache;
+use crate::error::{Error, Result};
+use std::collections::HashMap;
+use std::path::Path;
+
+type Attributes = HashMap<String, String>;
+
+/// Load a `.dspreset` file and the samples it maps.
+pub(super) fn load(path: &Path) -> Result<Vec<Zone>> {
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+    let text = std::fs::read_to_string(path)?;
+
+    let mut cache = WaveCache::default();
+    let mut zones = Vec::new();
+    for attributes in sample_elements(&text)? {
+        // Release-triggered samples need note-off triggering, which zones don't model
+        if attributes.get("trigger").is_some_and(|t| t != "attack") {
+            continue;
+        }
+        let Some(sample) = attributes.get("path") else {
+            continue;
+        };
+        let wave = cache.load(&base_dir.join(sample.replace('\\', "/")))?;
+        zones.push(zone_from_attributes(Zone::new(wave), &attributes)?);
+    }
+
+    if zones.is_empty() {
+        return Err(Error::Instrument(format!("{}: no samples", path.display())));
+    }
+    Ok(zones)
+}
+
+/// Attributes of every `<sample>` inside `<groups>`, merged with its ancestors'.
+fn sample_elements(text: &str) -> Result<Vec<Attributes>> {
+    let mut samples = Vec::new();
+    // Inherited attributes of open <groups>/<group> elements
+    let mut stack: Vec<(String, Attributes)> = Vec::new();
+
+    for tag in tags(text) {
+        let tag = tag?;
+        match tag {
+            Tag::Open {
+                name,
+                attributes,
+                self_closing,
+            } => {
+                let in_groups = stack.iter().any(|(n, _)| n == "groups");
+                if name == "sample" && in_groups {
+                    let mut merged = inherited(&stack);
+                    merged.extend(attributes);
+                    samples.push(merged);
+                } else if !self_closing && (name == "groups" || (name == "group" && in_groups)) {
+                    stack.push((name, attributes));
+                }
+            }
+            Tag::Close(name) => {
+                if stack.last().is_some_and(|(n, _)| *n == name) {
+                    stack.pop();
+                }
+            }
+        }
+    }
+    Ok(samples)
+}
+
+fn inherited(stack: &[(String, Attributes)]) -> Attributes {
+    let mut merged = Attributes::new();
+    for (_, attributes) in stack {
+        merged.extend(attributes.iter().map(|(k, v)| (k.clone(), v.clone())));
+    }
+    merged
+}
+
+#[derive(Debug, PartialEq)]
+enum Tag {
+    Open {
+        name: String,
+        attributes: Attributes,
+        self_closing: bool,
+    },
+    Close(String),
+}
+
+/// Minimal XML tag scanner: element names and attributes, no text content.
+fn tags(text: &str) -> impl Iterator<Item = Result<Tag>> + '_ {
+    let mut rest = text;
+    std::iter::from_fn(move || loop {
+        let start = rest.find('<')?;
+        rest = &rest[start + 1..];
+
+        if let Some(comment) = rest.strip_prefix("!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        if rest.starts_with('?') || rest.starts_with('!') {
+            rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+            continue;
+        }
+
+        let Some(end) = tag_end(rest) else {
+            rest = "";
+            return Some(Err(Error::Instrument("unterminated XML tag".into())));
+        };
+        let body = &rest[..end];
+        rest = &rest[end + 1..];
+
+        if let Some(name) = body.strip_prefix('/') {
+            return Some(Ok(Tag::Close(name.trim().to_string())));
+        }
+        let self_closing = body.ends_with('/');
+        let body = body.trim_end_matches('/');
+        let name_end = body.find(|c: char| c.is_whitespace()).unwrap_or(body.len());
+        return Some(
+            parse_attributes(&body[name_end..]).map(|attributes| Tag::Open {
+                name: body[..name_end].to_string(),
+                attributes,
+                self_closing,
+            }),
+        );
+    })
+}
+
+/// Index of the `>` closing a tag, skipping any inside quoted values.
+fn tag_end(s: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_attributes(mut s: &str) -> Result<Attributes> {
+    let mut attributes = Attributes::new();
+    loop {
+        s = s.trim_start();
+        if s.is_empty() {
+            return Ok(attributes);
+        }
+        let eq = s
+            .find('=')
+            .ok_or_else(|| Error::Instrument(format!("malformed attribute: {s}")))?;
+        let name = s[..eq].trim().to_string();
+        let value = s[eq + 1..].trim_start();
+        let quote = value
+            .chars()
+            .next()
+            .filter(|c| *c == '"' || *c == '\'')
+            .ok_or_else(|| Error::Instrument(format!("unquoted attribute: {name}")))?;
+        let close = value[1..]
+            .find(quote)
+            .ok_or_else(|| Error::Instrument(format!("unterminated attribute: {name}")))?;
+        attributes.insert(name, unescape(&value[1..1 + close]));
+        s = &value[close + 2..];
+    }
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn number(attributes: &Attributes, name: &str) -> Result<Option<f32>> {
+    attributes
+        .get(name)
+        .filter(|value| !value.is_empty())
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| Error::Instrument(format!("invalid {name}=\"{value}\"")))
+        })
+        .transpose()
+}
+
+fn note(attributes: &Attributes, name: &str) -> Result<Option<u8>> {
+    attributes
+        .get(name)
+        .map(|value| {
+            parse_note(value)
+                .ok_or_else(|| Error::Instrument(format!("invalid {name}=\"{value}\"")))
+        })
+        .transpose()
+}
+
+/// Volume is either linear (`0.5`) or decibels (`-6dB`).
+fn gain(attributes: &Attributes) -> Result<f32> {
+    let Some(value) = attributes.get("volume") else {
+        return Ok(1.0);
+    };
+    let invalid = || Error::Instrument(format!("invalid volume=\"{value}\""));
+    let lower = value.trim().to_ascii_lowercase();
+    match lower.strip_suffix("db") {
+        Some(db) => Ok(10.0f32.powf(db.trim().parse::<f32>().map_err(|_| invalid())? / 20.0)),
+        None => lower.parse().map_err(|_| invalid()),
+    }
+}
+
+fn zone_from_attributes(mut zone: Zone, attributes: &Attributes) -> Result<Zone> {
+    let root = note(attributes, "rootNote")?.unwrap_or(60);
+    zone = zone
+        .root_key(root)
+        .keys(
+            note(attributes, "loNote")?.unwrap_or(0),
+            note(attributes, "hiNote")?.unwrap_or(127),
+        )
+        .velocities(
+            number(attributes, "loVel")?.map_or(1, |v| v as u8),
+            number(attributes, "hiVel")?.map_or(127, |v| v as u8),
+        );
+
+    if attributes.get("seqMode").map(String::as_str) == Some("round_robin") {
+        if let Some(length) = number(attributes, "seqLength")? {
+            let position = number(attributes, "seqPosition")?.unwrap_or(1.0);
+            zone = zone.round_robin(position as u32, length as u32);
+        }
+    }
+
+    if let Some(start) = number(attributes, "start")? {
+        zone = zone.offset(start as u64);
+    }
+    if attributes.get("loopEnabled").map(String::as_str) == Some("true") {
+        zone = zone.loop_mode(ZoneLoopMode::Continuous);
+        let start = number(attributes, "loopStart")?.map_or(0, |v| v as u64);
+        // loopEnd is the last looped frame
+        let end = number(attributes, "loopEnd")?.map_or(zone.wave.len() as u64, |v| v as u64 + 1);
+        zone = zone.loop_range(start, end);
+    }
+
+    zone = zone.envelope(
+        number(attributes, "attack")?.unwrap_or(0.0),
+        number(attributes, "decay")?.unwrap_or(0.0),
+        number(attributes, "sustain")?.unwrap_or(1.0),
+        number(attributes, "release")?.unwrap_or(0.1),
+    );
+
+    zone = zone.gain(gain(attributes)?);
+    if let Some(pan) = number(attributes, "pan")? {
+        zone = zone.pan(pan / 100.0);
+    }
+    if let Some(semitones) = number(attributes, "tuning")? {
+        zone = zone.tune(semitones * 100.0);
+    }
+    if let Some(pitch_tracking) = number(attributes, "pitchKeyTrack")? {
+        zone = zone.key_tracking(pitch_tracking * 100.0);
+    }
+    if let Some(amp_velocity_tracking) = number(attributes, "ampVelTrack")? {
+        zone = zone.velocity_tracking(amp_velocity_tracking);
+    }
+
+    Ok(zone)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRESET: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<DecentSampler minVersion="1.0.0">
+  <ui><tab><labeled-knob label="Tone" /></tab></ui>
+  <!-- <sample path="commented.wav" /> -->
+  <groups attack="0.01" release="0.8" volume="-6dB">
+    <group seqMode="round_robin" seqLength="2" tuning="0.5">
+      <sample path="Samples/A &amp; B.wav" rootNote="60" loNote="55" hiNote="64" seqPosition="1"/>
+      <sample path="Samples/A2.wav" rootNote="60" loNote="55" hiNote="64" seqPosition="2" release="2"/>
+    </group>
+    <group>
+      <sample path="Samples/rel.wav" trigger="release" />
+    </group>
+  </groups>
+  <effects><effect type="reverb" /></effects>
+</DecentSampler>"#;
+
+    #[test]
+    fn test_sample_elements_inherit_group_attributes() {
+        let samples = sample_elements(PRESET).unwrap();
+        assert_eq!(samples.len(), 3);
+
+        assert_eq!(samples[0]["path"], "Samples/A & B.wav");
+        assert_eq!(samples[0]["seqLength"], "2");
+        assert_eq!(samples[0]["release"], "0.8");
+        assert_eq!(samples[0]["volume"], "-6dB");
+        assert_eq!(samples[1]["release"], "2");
+        assert!(!samples[2].contains_key("seqLength"));
+    }
+
+    #[test]
+    fn test_zone_from_attributes() {
+        let wave = std::sync::Arc::new(tutti_core::Wave::from_samples(44100.0, &[0.0; 100]));
+        let samples = sample_elements(PRESET).unwrap();
+        let zone = zone_from_attributes(Zone::new(wave), &samples[1]).unwrap();
+
+        assert_eq!(zone.key_range(), (55, 64));
+        assert_eq!(zone.round_robin, Some((2, 2)));
+        assert_eq!(zone.envelope.release, 2.0);
+        assert_eq!(zone.tune_cents, 50.0);
+        assert!((zone.gain - 0.501).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_malformed_xml_errors() {
+        assert!(sample_elements("<groups><sample path=unquoted.wav/></groups>").is_err());
+        assert!(sample_elements("<groups><sample path=\"a.wav\"").is_err());
+    }
+}
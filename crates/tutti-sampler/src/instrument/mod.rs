@@ -0,0 +1,49 @@
+//! Multisample instruments: MIDI-driven zone mapping with SFZ and Decent Sampler import.
+//!
+//! ```ignore
+//! use tutti_sampler::{SamplerInstrument, Zone};
+//!
+//! // From an SFZ file
+//! let piano = SamplerInstrument::builder(48000.0).sfz("piano.sfz")?.build()?;
+//!
+//! // Or by hand
+//! let kit = SamplerInstrument::builder(48000.0)
+//!     .zone(Zone::new(kick).keys(36, 36).root_key(36).key_tracking(0.0))
+//!     .zone(Zone::new(snare_a).keys(38, 38).round_robin(1, 2))
+//!     .zone(Zone::new(snare_b).keys(38, 38).round_robin(2, 2))
+//!     .build()?;
+//! ```
+
+mod dspreset;
+mod sfz;
+mod unit;
+mod voice;
+mod zone;
+
+pub use unit::{SamplerInstrument, SamplerInstrumentBuilder};
+pub use zone::{Zone, ZoneEnvelope, ZoneFilter, ZoneFilterType, ZoneLoopMode};
+
+use crate::error::{Error, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tutti_core::Wave;
+
+/// Loads each sample file once per import, however many zones share it.
+#[derive(Default)]
+struct WaveCache {
+    waves: HashMap<PathBuf, Arc<Wave>>,
+}
+
+impl WaveCache {
+    fn load(&mut self, path: &Path) -> Result<Arc<Wave>> {
+        if let Some(wave) = self.waves.get(path) {
+            return Ok(Arc::clone(wave));
+        }
+        let wave = Wave::load(path)
+            .map_err(|e| Error::SampleNotFound(format!("{}: {e}", path.display())))?;
+        let wave = Arc::new(wave);
+        self.waves.insert(path.to_path_buf(), Arc::clone(&wave));
+        Ok(wave)
+    }
+}
@@ -0,0 +1,566 @@
+//! SFZ import.
+//!
+//! Supports the `<control>`, `<global>`, `<master>`, `<group>` and `<region>`
+//! headers with opcode inheritance, `#define` and `#include`, and the opcodes
+//! that map onto [`Zone`]: key/velocity ranges, round robin (`seq_*`),
+//! loops, `ampeg_*`, `fil_type`/`cutoff`/`resonance`, and level/pitch
+//! offsets. Unknown headers and opcodes are ignored.
+
+use super::zone::{Zone, ZoneFilter, ZoneFilterType, ZoneLoopMode};
+use super::WaveCache;
+use crate::error::{Error, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Nested `#include` depth before giving up.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+type Opcodes = HashMap<String, String>;
+
+/// Regions of a parsed SFZ file with inherited opcodes merged in.
+#[derive(Debug, Default)]
+struct SfzFile {
+    default_path: String,
+    regions: Vec<Opcodes>,
+}
+
+/// Load an SFZ file and the samples its regions reference.
+pub(super) fn load(path: &Path) -> Result<Vec<Zone>> {
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+    let text = std::fs::read_to_string(path)?;
+    let file = parse(&text, base_dir)?;
+
+    let mut cache = WaveCache::default();
+    let mut zones = Vec::with_capacity(file.regions.len());
+    for opcodes in &file.regions {
+        let Some(sample) = opcodes.get("sample") else {
+            continue;
+        };
+        // Built-in generators (*sine, *silence, ...) have no file to play
+        if sample.starts_with('*') {
+            continue;
+        }
+        let sample_path = base_dir.join(sample_path(&file.default_path, sample));
+        let wave = cache.load(&sample_path)?;
+        zones.push(zone_from_opcodes(Zone::new(wave), opcodes)?);
+    }
+
+    if zones.is_empty() {
+        return Err(Error::Instrument(format!(
+            "{}: no playable regions",
+            path.display()
+        )));
+    }
+    Ok(zones)
+}
+
+/// SFZ paths use `\` on every platform.
+fn sample_path(default_path: &str, sample: &str) -> PathBuf {
+    PathBuf::from(format!("{default_path}{sample}").replace('\\', "/"))
+}
+
+fn parse(text: &str, base_dir: &Path) -> Result<SfzFile> {
+    let mut defines = Vec::new();
+    let source = preprocess(text, base_dir, &mut defines, 0)?;
+
+    #[derive(PartialEq)]
+    enum Scope {
+        None,
+        Control,
+        Global,
+        Master,
+        Group,
+        Region,
+        Ignored,
+    }
+
+    let mut file = SfzFile::default();
+    let (mut control, mut global, mut master, mut group) = (
+        Opcodes::new(),
+        Opcodes::new(),
+        Opcodes::new(),
+        Opcodes::new(),
+    );
+    let mut region: Option<Opcodes> = None;
+    let mut scope = Scope::None;
+
+    for token in tokenize(&source) {
+        match token {
+            Token::Header(name) => {
+                if let Some(done) = region.take() {
+                    file.regions.push(done);
+                }
+                scope = match name {
+                    "control" => Scope::Control,
+                    "global" => {
+                        global.clear();
+                        master.clear();
+                        group.clear();
+                        Scope::Global
+                    }
+                    "master" => {
+                        master.clear();
+                        group.clear();
+                        Scope::Master
+                    }
+                    "group" => {
+                        group.clear();
+                        Scope::Group
+                    }
+                    "region" => {
+                        let mut inherited = global.clone();
+                        inherited.extend(master.clone());
+                        inherited.extend(group.clone());
+                        region = Some(inherited);
+                        Scope::Region
+                    }
+                    _ => Scope::Ignored,
+                };
+            }
+            Token::Opcode(name, value) => {
+                let target = match scope {
+                    Scope::Control => &mut control,
+                    Scope::Global => &mut global,
+                    Scope::Master => &mut master,
+                    Scope::Group => &mut group,
+                    Scope::Region => region.get_or_insert_with(Opcodes::new),
+                    Scope::None | Scope::Ignored => continue,
+                };
+                target.insert(name.to_string(), value.to_string());
+            }
+        }
+    }
+    if let Some(done) = region.take() {
+        file.regions.push(done);
+    }
+
+    file.default_path = control.remove("default_path").unwrap_or_default();
+    Ok(file)
+}
+
+/// Strip comments, expand `#include` and substitute `#define`d variables.
+fn preprocess(
+    text: &str,
+    base_dir: &Path,
+    defines: &mut Vec<(String, String)>,
+    depth: usize,
+) -> Result<String> {
+    if depth > MAX_INCLUDE_DEPTH {
+        return Err(Error::Instrument("#include nested too deeply".into()));
+    }
+
+    let text = strip_comments(text);
+    let mut out = String::with_capacity(text.len());
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if let Some(rest) = trimmed.strip_prefix("#define") {
+            let mut parts = rest.split_whitespace();
+            if let (Some(name), Some(value)) = (parts.next(), parts.next()) {
+                // Longest names first so `$A` doesn't clobber `$AB`
+                defines.push((name.to_string(), value.to_string()));
+                defines.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
+            }
+            continue;
+        }
+        if let Some(rest) = trimmed.strip_prefix("#include") {
+            let include = rest.trim().trim_matches('"');
+            let include_path = base_dir.join(include.replace('\\', "/"));
+            let included = std::fs::read_to_string(&include_path).map_err(|e| {
+                Error::Instrument(format!("#include {}: {e}", include_path.display()))
+            })?;
+            out.push_str(&preprocess(&included, base_dir, defines, depth + 1)?);
+            out.push('\n');
+            continue;
+        }
+
+        let mut line = line.to_string();
+        for (name, value) in defines.iter() {
+            if line.contains(name.as_str()) {
+                line = line.replace(name.as_str(), value);
+            }
+        }
+        out.push_str(&line);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+fn strip_comments(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while !rest.is_empty() {
+        let line_comment = rest.find("//");
+        let block_comment = rest.find("/*");
+        match (line_comment, block_comment) {
+            (Some(l), b) if b.is_none_or(|b| l < b) => {
+                out.push_str(&rest[..l]);
+                rest = rest[l..].find('\n').map_or("", |end| &rest[l + end..]);
+            }
+            (_, Some(b)) => {
+                out.push_str(&rest[..b]);
+                out.push(' ');
+                rest = rest[b + 2..]
+                    .find("*/")
+                    .map_or("", |end| &rest[b + 2 + end + 2..]);
+            }
+            _ => {
+                out.push_str(rest);
+                rest = "";
+            }
+        }
+    }
+    out
+}
+
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    Header(&'a str),
+    Opcode(&'a str, &'a str),
+}
+
+/// Split source into headers and `name=value` opcodes.
+///
+/// Values run until the next opcode or header on the same line, so sample
+/// paths may contain spaces.
+fn tokenize(source: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    for line in source.lines() {
+        let mut rest = line;
+        while !rest.trim().is_empty() {
+            let trimmed = rest.trim_start();
+            if let Some(header) = trimmed.strip_prefix('<') {
+                let Some(end) = header.find('>') else {
+                    break;
+                };
+                tokens.push(Token::Header(header[..end].trim()));
+                rest = &header[end + 1..];
+                continue;
+            }
+
+            let Some(eq) = trimmed.find('=') else {
+                break;
+            };
+            let name = trimmed[..eq].trim();
+            let after = &trimmed[eq + 1..];
+            let value_end = next_token_start(after).unwrap_or(after.len());
+            tokens.push(Token::Opcode(name, after[..value_end].trim()));
+            rest = &after[value_end..];
+        }
+    }
+    tokens
+}
+
+/// Byte offset in `s` where the next `name=` opcode or `<header>` begins.
+fn next_token_start(s: &str) -> Option<usize> {
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'<' {
+            return Some(i);
+        }
+        if bytes[i].is_ascii_whitespace() {
+            let start = i + 1;
+            let mut j = start;
+            while j < bytes.len() && (bytes[j].is_ascii_alphanumeric() || bytes[j] == b'_') {
+                j += 1;
+            }
+            if j > start && j < bytes.len() && bytes[j] == b'=' {
+                return Some(start);
+            }
+        }
+        i += 1;
+    }
+    None
+}
+
+/// MIDI note from a number or a note name (`c4` = 60, `f#3`, `eb5`).
+pub(super) fn parse_note(value: &str) -> Option<u8> {
+    if let Ok(number) = value.parse::<i32>() {
+        return u8::try_from(number).ok().filter(|&n| n <= 127);
+    }
+
+    let lower = value.to_ascii_lowercase();
+    let mut chars = lower.chars();
+    let semitone = match chars.next()? {
+        'c' => 0,
+        'd' => 2,
+        'e' => 4,
+        'f' => 5,
+        'g' => 7,
+        'a' => 9,
+        'b' => 11,
+        _ => return None,
+    };
+    let rest = chars.as_str();
+    let (accidental, octave) = match rest.chars().next()? {
+        '#' => (1, &rest[1..]),
+        'b' if rest.len() > 1 => (-1, &rest[1..]),
+        _ => (0, rest),
+    };
+    let octave: i32 = octave.parse().ok()?;
+    let note = (octave + 1) * 12 + semitone + accidental;
+    u8::try_from(note).ok().filter(|&n| n <= 127)
+}
+
+fn number<T: std::str::FromStr>(opcodes: &Opcodes, name: &str) -> Result<Option<T>> {
+    opcodes
+        .get(name)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| Error::Instrument(format!("invalid {name}={value}")))
+        })
+        .transpose()
+}
+
+fn note(opcodes: &Opcodes, name: &str) -> Result<Option<u8>> {
+    opcodes
+        .get(name)
+        .map(|value| {
+            parse_note(value).ok_or_else(|| Error::Instrument(format!("invalid {name}={value}")))
+        })
+        .transpose()
+}
+
+fn zone_from_opcodes(mut zone: Zone, opcodes: &Opcodes) -> Result<Zone> {
+    // Keys
+    let key = note(opcodes, "key")?;
+    let lo_key = note(opcodes, "lokey")?.or(key).unwrap_or(0);
+    let hi_key = note(opcodes, "hikey")?.or(key).unwrap_or(127);
+    zone = zone.keys(lo_key, hi_key);
+    zone = zone.root_key(note(opcodes, "pitch_keycenter")?.or(key).unwrap_or(60));
+
+    let lo_vel = number::<u8>(opcodes, "lovel")?.unwrap_or(1);
+    let hi_vel = number::<u8>(opcodes, "hivel")?.unwrap_or(127);
+    zone = zone.velocities(lo_vel, hi_vel);
+
+    // Round robin
+    if let Some(length) = number::<u32>(opcodes, "seq_length")? {
+        let position = number::<u32>(opcodes, "seq_position")?.unwrap_or(1);
+        zone = zone.round_robin(position, length);
+    }
+
+    // Playback
+    if let Some(offset) = number::<u64>(opcodes, "offset")? {
+        zone = zone.offset(offset);
+    }
+    let loop_start = number::<u64>(opcodes, "loop_start")?.or(number::<u64>(opcodes, "loopstart")?);
+    let loop_end = number::<u64>(opcodes, "loop_end")?.or(number::<u64>(opcodes, "loopend")?);
+    let loop_mode = match opcodes
+        .get("loop_mode")
+        .or(opcodes.get("loopmode"))
+        .map(String::as_str)
+    {
+        Some("one_shot") => ZoneLoopMode::OneShot,
+        Some("loop_continuous") => ZoneLoopMode::Continuous,
+        Some("loop_sustain") => ZoneLoopMode::Sustain,
+        Some("no_loop") => ZoneLoopMode::NoLoop,
+        Some(other) => return Err(Error::Instrument(format!("invalid loop_mode={other}"))),
+        None if loop_start.is_some() || loop_end.is_some() => ZoneLoopMode::Continuous,
+        None => ZoneLoopMode::NoLoop,
+    };
+    zone = zone.loop_mode(loop_mode);
+    if loop_start.is_some() || loop_end.is_some() {
+        let start = loop_start.unwrap_or(0);
+        // `loop_end` is the last looped frame
+        let end = loop_end.map_or(zone.wave.len() as u64, |end| end + 1);
+        zone = zone.loop_range(start, end);
+    }
+
+    // Amplitude envelope
+    let attack = number::<f32>(opcodes, "ampeg_attack")?.unwrap_or(0.0);
+    let decay = number::<f32>(opcodes, "ampeg_decay")?.unwrap_or(0.0);
+    let sustain = number::<f32>(opcodes, "ampeg_sustain")?.unwrap_or(100.0) / 100.0;
+    let release = number::<f32>(opcodes, "ampeg_release")?.unwrap_or(0.005);
+    zone = zone.envelope(attack, decay, sustain, release);
+
+    // Filter
+    if let Some(cutoff) = number::<f32>(opcodes, "cutoff")? {
+        let filter_type = match opcodes.get("fil_type").map(String::as_str) {
+            None => ZoneFilterType::Lowpass,
+            Some(t) if t.starts_with("lpf") => ZoneFilterType::Lowpass,
+            Some(t) if t.starts_with("hpf") => ZoneFilterType::Highpass,
+            Some(t) if t.starts_with("bpf") => ZoneFilterType::Bandpass,
+            Some(other) => return Err(Error::Instrument(format!("invalid fil_type={other}"))),
+        };
+        // Resonance is the peak in dB above a Butterworth response
+        let resonance_db = number::<f32>(opcodes, "resonance")?.unwrap_or(0.0);
+        let q = std::f32::consts::FRAC_1_SQRT_2 * 10.0f32.powf(resonance_db / 20.0);
+        zone = zone.filter(ZoneFilter::new(filter_type, cutoff, q));
+    }
+
+    // Level and pitch
+    let volume_db = number::<f32>(opcodes, "volume")?.unwrap_or(0.0);
+    let amplitude = number::<f32>(opcodes, "amplitude")?.unwrap_or(100.0) / 100.0;
+    zone = zone.gain(10.0f32.powf(volume_db / 20.0) * amplitude);
+    if let Some(pan) = number::<f32>(opcodes, "pan")? {
+        zone = zone.pan(pan / 100.0);
+    }
+    let tune = number::<f32>(opcodes, "tune")?.unwrap_or(0.0);
+    let transpose = number::<f32>(opcodes, "transpose")?.unwrap_or(0.0);
+    zone = zone.tune(tune + transpose * 100.0);
+    if let Some(keytrack) = number::<f32>(opcodes, "pitch_keytrack")? {
+        zone = zone.key_tracking(keytrack);
+    }
+    if let Some(veltrack) = number::<f32>(opcodes, "amp_veltrack")? {
+        zone = zone.velocity_tracking(veltrack / 100.0);
+    }
+
+    Ok(zone)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tutti_core::Wave;
+
+    fn write_wav(path: &Path, frames: usize) {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 44100,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(path, spec).unwrap();
+        for i in 0..frames {
+            writer.write_sample((i % 100) as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    #[test]
+    fn test_note_names() {
+        assert_eq!(parse_note("60"), Some(60));
+        assert_eq!(parse_note("c4"), Some(60));
+        assert_eq!(parse_note("C#4"), Some(61));
+        assert_eq!(parse_note("eb4"), Some(63));
+        assert_eq!(parse_note("a0"), Some(21));
+        assert_eq!(parse_note("c-1"), Some(0));
+        assert_eq!(parse_note("g9"), Some(127));
+        assert_eq!(parse_note("h4"), None);
+        assert_eq!(parse_note("200"), None);
+    }
+
+    #[test]
+    fn test_parse_inheritance_comments_and_defines() {
+        let text = r#"
+            // Piano
+            #define $VEL 64
+            <control> default_path=Samples\Piano\
+            <global> ampeg_release=0.5
+            <group> lovel=1 hivel=$VEL /* soft layer */
+            <region> sample=C4 soft.wav key=c4
+            <region> sample=D4 soft.wav key=62 ampeg_release=1
+            <group> lovel=65
+            <region> sample=C4 loud.wav lokey=0 hikey=61 pitch_keycenter=60
+        "#;
+        let file = parse(text, Path::new(".")).unwrap();
+
+        assert_eq!(file.default_path, r"Samples\Piano\");
+        assert_eq!(file.regions.len(), 3);
+
+        let first = &file.regions[0];
+        assert_eq!(first["sample"], "C4 soft.wav");
+        assert_eq!(first["key"], "c4");
+        assert_eq!(first["hivel"], "64");
+        assert_eq!(first["ampeg_release"], "0.5");
+
+        assert_eq!(file.regions[1]["ampeg_release"], "1");
+
+        let loud = &file.regions[2];
+        assert_eq!(loud["lovel"], "65");
+        assert!(
+            !loud.contains_key("hivel"),
+            "new <group> resets group opcodes"
+        );
+        assert_eq!(loud["ampeg_release"], "0.5");
+
+        assert_eq!(
+            sample_path(&file.default_path, &first["sample"]),
+            PathBuf::from("Samples/Piano/C4 soft.wav")
+        );
+    }
+
+    #[test]
+    fn test_zone_from_opcodes() {
+        let wave = Arc::new(Wave::from_samples(44100.0, &[0.0; 1000]));
+        let opcodes: Opcodes = [
+            ("lokey", "c3"),
+            ("hikey", "b3"),
+            ("pitch_keycenter", "f3"),
+            ("lovel", "10"),
+            ("hivel", "90"),
+            ("seq_length", "3"),
+            ("seq_position", "2"),
+            ("loop_mode", "loop_sustain"),
+            ("loop_start", "100"),
+            ("loop_end", "899"),
+            ("ampeg_attack", "0.01"),
+            ("ampeg_sustain", "50"),
+            ("fil_type", "hpf_2p"),
+            ("cutoff", "200"),
+            ("volume", "-6"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+        let zone = zone_from_opcodes(Zone::new(wave), &opcodes).unwrap();
+        assert_eq!(zone.key_range(), (48, 59));
+        assert_eq!(zone.root(), 53);
+        assert_eq!(zone.velocity_range(), (10, 90));
+        assert_eq!(zone.round_robin, Some((2, 3)));
+        assert_eq!(zone.loop_mode, ZoneLoopMode::Sustain);
+        assert_eq!(zone.loop_range, Some((100, 900)));
+        assert_eq!(zone.envelope.sustain, 0.5);
+        assert_eq!(
+            zone.filter.map(|f| f.filter_type),
+            Some(ZoneFilterType::Highpass)
+        );
+        assert!((zone.gain - 0.501).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_invalid_opcode_value_errors() {
+        let wave = Arc::new(Wave::from_samples(44100.0, &[0.0; 10]));
+        let opcodes: Opcodes = [("lovel".to_string(), "loud".to_string())].into();
+        assert!(zone_from_opcodes(Zone::new(wave), &opcodes).is_err());
+    }
+
+    #[test]
+    fn test_load_resolves_samples_and_includes() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("samples")).unwrap();
+        write_wav(&dir.path().join("samples/low.wav"), 500);
+        write_wav(&dir.path().join("samples/high.wav"), 700);
+        std::fs::write(
+            dir.path().join("high.sfzh"),
+            "<region> sample=high.wav lokey=60 hikey=127\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("kit.sfz"),
+            "<control> default_path=samples/\n\
+             <region> sample=low.wav hikey=59\n\
+             <region> sample=*sine\n\
+             #include \"high.sfzh\"\n",
+        )
+        .unwrap();
+
+        let zones = load(&dir.path().join("kit.sfz")).unwrap();
+        assert_eq!(zones.len(), 2);
+        assert_eq!(zones[0].key_range(), (0, 59));
+        assert_eq!(zones[0].wave().len(), 500);
+        assert_eq!(zones[1].key_range(), (60, 127));
+        assert_eq!(zones[1].wave().len(), 700);
+    }
+
+    #[test]
+    fn test_load_missing_sample_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("broken.sfz");
+        std::fs::write(&path, "<region> sample=missing.wav\n").unwrap();
+        assert!(load(&path).is_err());
+    }
+}
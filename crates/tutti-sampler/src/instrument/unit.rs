@@ -0,0 +1,613 @@
+//! MIDI-driven multisample instrument implementing [`AudioUnit`].
+
+use super::voice::{SamplerVoice, MAX_LAYERS};
+use super::zone::Zone;
+use crate::error::{Error, Result};
+use crate::resample::{Interpolator, ResampleQuality};
+use smallvec::SmallVec;
+use std::path::Path;
+use tutti_core::midi::{ChannelVoiceMsg, ControlChange, MidiEvent, MidiRegistry, MidiSource};
+use tutti_core::{AudioUnit, BufferMut, BufferRef, SignalFrame};
+use tutti_synth::{AllocationResult, AllocationStrategy, VoiceAllocator, VoiceAllocatorConfig};
+
+/// Polyphonic multisample instrument.
+///
+/// Note-ons pick every [`Zone`] whose key and velocity range match (up to
+/// four layers), honoring round-robin positions. Voices come from
+/// `tutti-synth`'s [`VoiceAllocator`], so stealing and sustain/sostenuto
+/// pedals behave like [`PolySynth`](tutti_synth::PolySynth).
+///
+/// MIDI is pulled from a [`MidiSource`] during `tick()`/`process()`: a live
+/// `MidiRegistry`, or a `MidiSnapshotReader` for export renders.
+///
+/// # Example
+///
+/// ```ignore
+/// let piano = SamplerInstrument::builder(48000.0)
+///     .sfz("piano/piano.sfz")?
+///     .polyphony(64)
+///     .build()?;
+/// ```
+pub struct SamplerInstrument {
+    zones: Vec<Zone>,
+    /// Hits per zone, for round-robin selection.
+    round_robin_counters: Vec<u32>,
+    allocator: VoiceAllocator,
+    voices: Vec<SamplerVoice>,
+    interpolator: Interpolator,
+    sample_rate: f64,
+    /// Pitch bend as a rate ratio per MIDI channel, updated on bend messages.
+    bend_ratios: [f64; 16],
+    pitch_bend_range: f32,
+    gain: f32,
+    id: u64,
+    midi_source: Option<Box<dyn MidiSource>>,
+    midi_buffer: Vec<MidiEvent>,
+}
+
+impl SamplerInstrument {
+    pub fn builder(sample_rate: f64) -> SamplerInstrumentBuilder {
+        SamplerInstrumentBuilder {
+            sample_rate,
+            zones: Vec::new(),
+            polyphony: 32,
+            strategy: AllocationStrategy::Oldest,
+            resample_quality: ResampleQuality::default(),
+            pitch_bend_range: 2.0,
+            gain: 1.0,
+        }
+    }
+
+    /// Convenience: set a live `MidiRegistry` as the MIDI source.
+    pub fn with_midi_registry(mut self, registry: MidiRegistry) -> Self {
+        self.midi_source = Some(Box::new(registry));
+        self
+    }
+
+    /// Set the MIDI source (live registry or export snapshot reader).
+    pub fn set_midi_source(&mut self, source: Box<dyn MidiSource>) {
+        self.midi_source = Some(source);
+    }
+
+    pub fn zones(&self) -> &[Zone] {
+        &self.zones
+    }
+
+    pub fn active_voice_count(&self) -> usize {
+        self.voices.iter().filter(|v| v.active).count()
+    }
+
+    pub fn set_gain(&mut self, gain: f32) {
+        self.gain = gain.max(0.0);
+    }
+
+    pub fn note_on(&mut self, channel: u8, note: u8, velocity: u8) {
+        if velocity == 0 {
+            self.note_off(channel, note);
+            return;
+        }
+        let note = note.min(127);
+
+        let mut matched: SmallVec<[usize; MAX_LAYERS]> = SmallVec::new();
+        for (index, zone) in self.zones.iter().enumerate() {
+            if !zone.contains(note, velocity) {
+                continue;
+            }
+            let counter = &mut self.round_robin_counters[index];
+            let selected = match zone.round_robin {
+                Some((position, length)) => *counter % length == position - 1,
+                None => true,
+            };
+            *counter = counter.wrapping_add(1);
+            if selected && matched.len() < MAX_LAYERS {
+                matched.push(index);
+            }
+        }
+        if matched.is_empty() {
+            return;
+        }
+
+        let slot_index = match self
+            .allocator
+            .allocate(note, channel, velocity as f32 / 127.0)
+        {
+            AllocationResult::Allocated { slot_index }
+            | AllocationResult::Stolen { slot_index }
+            | AllocationResult::LegatoRetrigger { slot_index } => slot_index,
+            AllocationResult::Unavailable => return,
+        };
+
+        // A retriggered note releases its previous voice in the allocator;
+        // let that voice ring out unless it is the slot being reused.
+        for (i, voice) in self.voices.iter_mut().enumerate() {
+            if i != slot_index && voice.active && voice.note == note && voice.channel == channel {
+                voice.release();
+            }
+        }
+
+        self.voices[slot_index].start(
+            &self.zones,
+            &matched,
+            note,
+            channel,
+            velocity,
+            self.sample_rate,
+        );
+    }
+
+    pub fn note_off(&mut self, channel: u8, note: u8) {
+        self.allocator.release(note, channel);
+        self.sync_releases();
+    }
+
+    fn poll_midi_events(&mut self) {
+        let Some(ref source) = self.midi_source else {
+            return;
+        };
+        let count = source.poll_into(self.id, &mut self.midi_buffer);
+        for i in 0..count {
+            let event = self.midi_buffer[i];
+            self.process_midi_event(&event);
+        }
+    }
+
+    fn process_midi_event(&mut self, event: &MidiEvent) {
+        let channel = event.channel_num();
+        match event.msg {
+            ChannelVoiceMsg::NoteOn { note, velocity } => self.note_on(channel, note, velocity),
+            ChannelVoiceMsg::NoteOff { note, .. } => self.note_off(channel, note),
+            ChannelVoiceMsg::PitchBend { bend } => {
+                self.set_pitch_bend(channel, (bend as f32 - 8192.0) / 8192.0);
+            }
+            ChannelVoiceMsg::ControlChange {
+                control: ControlChange::CC { control, value },
+            } => match control {
+                64 => {
+                    self.allocator.sustain_pedal(channel, value >= 64);
+                    self.sync_releases();
+                }
+                66 => {
+                    self.allocator.sostenuto_pedal(channel, value >= 64);
+                    self.sync_releases();
+                }
+                120 => {
+                    self.voices
+                        .iter_mut()
+                        .filter(|v| v.channel == channel)
+                        .for_each(|v| v.kill());
+                    self.allocator.all_sound_off(channel);
+                }
+                123 => {
+                    self.allocator.all_notes_off(channel);
+                    self.sync_releases();
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    /// Bend `channel`'s voices by `bend` (-1..1) times the bend range.
+    fn set_pitch_bend(&mut self, channel: u8, bend: f32) {
+        let semitones = (bend * self.pitch_bend_range) as f64;
+        self.bend_ratios[channel as usize & 15] = 2.0f64.powf(semitones / 12.0);
+    }
+
+    /// Release voices whose allocator slot is no longer held by key or pedal.
+    fn sync_releases(&mut self) {
+        for (i, voice) in self.voices.iter_mut().enumerate() {
+            if voice.active && !self.allocator.is_slot_active(i) {
+                voice.release();
+            }
+        }
+    }
+
+    #[inline]
+    fn render_frame(&mut self) -> (f32, f32) {
+        let (mut left, mut right) = (0.0f32, 0.0f32);
+
+        for (i, voice) in self.voices.iter_mut().enumerate() {
+            if !voice.active {
+                continue;
+            }
+            let bend = self.bend_ratios[voice.channel as usize & 15];
+            let (l, r) = voice.tick(&self.zones, &self.interpolator, bend);
+            left += l;
+            right += r;
+
+            if voice.active {
+                self.allocator.update_envelope_level(i, voice.level());
+            } else if let Some(voice_id) = self.allocator.slot_voice_id(i) {
+                self.allocator.voice_finished(voice_id);
+            }
+        }
+
+        self.allocator.advance_time(1);
+        (left * self.gain, right * self.gain)
+    }
+}
+
+impl AudioUnit for SamplerInstrument {
+    fn reset(&mut self) {
+        for voice in &mut self.voices {
+            voice.kill();
+        }
+        self.allocator.reset();
+        self.round_robin_counters.fill(0);
+        self.bend_ratios = [1.0; 16];
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+    }
+
+    fn tick(&mut self, _input: &[f32], output: &mut [f32]) {
+        self.poll_midi_events();
+        let (left, right) = self.render_frame();
+        output[0] = left;
+        if output.len() > 1 {
+            output[1] = right;
+        }
+    }
+
+    fn process(&mut self, size: usize, _input: &BufferRef, output: &mut BufferMut) {
+        self.poll_midi_events();
+        for i in 0..size {
+            let (left, right) = self.render_frame();
+            output.set_f32(0, i, left);
+            output.set_f32(1, i, right);
+        }
+    }
+
+    fn inputs(&self) -> usize {
+        0
+    }
+
+    fn outputs(&self) -> usize {
+        2
+    }
+
+    fn route(&mut self, _input: &SignalFrame, _frequency: f64) -> SignalFrame {
+        SignalFrame::new(self.outputs())
+    }
+
+    fn set(&mut self, _setting: tutti_core::Setting) {}
+
+    fn get_id(&self) -> u64 {
+        self.id
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn core::any::Any {
+        self
+    }
+
+    fn footprint(&self) -> usize {
+        core::mem::size_of::<Self>()
+            + self.voices.capacity() * core::mem::size_of::<SamplerVoice>()
+            + self.midi_buffer.capacity() * core::mem::size_of::<MidiEvent>()
+    }
+
+    fn allocate(&mut self) {}
+}
+
+impl Clone for SamplerInstrument {
+    fn clone(&self) -> Self {
+        Self {
+            zones: self.zones.clone(),
+            round_robin_counters: self.round_robin_counters.clone(),
+            allocator: self.allocator.clone(),
+            voices: self.voices.clone(),
+            interpolator: self.interpolator,
+            sample_rate: self.sample_rate,
+            bend_ratios: self.bend_ratios,
+            pitch_bend_range: self.pitch_bend_range,
+            gain: self.gain,
+            id: self.id,
+            // Cloned instruments need explicit MIDI source setup
+            midi_source: None,
+            midi_buffer: vec![MidiEvent::note_on(0, 0, 0, 0); 256],
+        }
+    }
+}
+
+/// Builder for [`SamplerInstrument`].
+pub struct SamplerInstrumentBuilder {
+    sample_rate: f64,
+    zones: Vec<Zone>,
+    polyphony: usize,
+    strategy: AllocationStrategy,
+    resample_quality: ResampleQuality,
+    pitch_bend_range: f32,
+    gain: f32,
+}
+
+impl SamplerInstrumentBuilder {
+    pub fn zone(mut self, zone: Zone) -> Self {
+        self.zones.push(zone);
+        self
+    }
+
+    pub fn zones(mut self, zones: impl IntoIterator<Item = Zone>) -> Self {
+        self.zones.extend(zones);
+        self
+    }
+
+    /// Add the regions of an SFZ file. Samples are resolved relative to the file.
+    pub fn sfz(self, path: impl AsRef<Path>) -> Result<Self> {
+        let zones = super::sfz::load(path.as_ref())?;
+        Ok(self.zones(zones))
+    }
+
+    /// Add the samples of a Decent Sampler `.dspreset` file.
+    pub fn dspreset(self, path: impl AsRef<Path>) -> Result<Self> {
+        let zones = super::dspreset::load(path.as_ref())?;
+        Ok(self.zones(zones))
+    }
+
+    /// Maximum simultaneous notes (default: 32).
+    pub fn polyphony(mut self, voices: usize) -> Self {
+        self.polyphony = voices;
+        self
+    }
+
+    /// Which voice to steal when polyphony is exhausted (default: oldest).
+    pub fn allocation_strategy(mut self, strategy: AllocationStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Interpolation for pitch shifting and rate conversion (default: [`ResampleQuality::Sinc`]).
+    pub fn resample_quality(mut self, quality: ResampleQuality) -> Self {
+        self.resample_quality = quality;
+        self
+    }
+
+    /// Pitch bend range in semitones (default: 2.0).
+    pub fn pitch_bend_range(mut self, semitones: f32) -> Self {
+        self.pitch_bend_range = semitones;
+        self
+    }
+
+    /// Output gain (default: 1.0).
+    pub fn gain(mut self, gain: f32) -> Self {
+        self.gain = gain.max(0.0);
+        self
+    }
+
+    pub fn build(self) -> Result<SamplerInstrument> {
+        if self.polyphony == 0 {
+            return Err(Error::Instrument("polyphony must be at least 1".into()));
+        }
+        if self.zones.is_empty() {
+            return Err(Error::Instrument("instrument has no zones".into()));
+        }
+
+        use std::sync::atomic::{AtomicU64, Ordering};
+        static ID_COUNTER: AtomicU64 = AtomicU64::new(1);
+        let id = 0x5346_5A00_0000_0000 | ID_COUNTER.fetch_add(1, Ordering::Relaxed);
+
+        let allocator = VoiceAllocator::new(VoiceAllocatorConfig {
+            max_voices: self.polyphony,
+            strategy: self.strategy,
+            ..Default::default()
+        });
+
+        Ok(SamplerInstrument {
+            round_robin_counters: vec![0; self.zones.len()],
+            zones: self.zones,
+            allocator,
+            voices: vec![SamplerVoice::default(); self.polyphony],
+            interpolator: Interpolator::new(self.resample_quality),
+            sample_rate: self.sample_rate,
+            bend_ratios: [1.0; 16],
+            pitch_bend_range: self.pitch_bend_range,
+            gain: self.gain,
+            id,
+            midi_source: None,
+            midi_buffer: vec![MidiEvent::note_on(0, 0, 0, 0); 256],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tutti_core::Wave;
+
+    fn constant_wave(value: f32, frames: usize) -> Arc<Wave> {
+        Arc::new(Wave::from_samples(48000.0, &vec![value; frames]))
+    }
+
+    fn render(instrument: &mut SamplerInstrument, frames: usize) -> Vec<f32> {
+        let mut output = [0.0f32; 2];
+        (0..frames)
+            .map(|_| {
+                instrument.tick(&[], &mut output);
+                output[0]
+            })
+            .collect()
+    }
+
+    fn instrument(zones: Vec<Zone>) -> SamplerInstrument {
+        SamplerInstrument::builder(48000.0)
+            .zones(zones)
+            .polyphony(4)
+            .resample_quality(ResampleQuality::Linear)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_key_and_velocity_mapping() {
+        let mut inst = instrument(vec![
+            Zone::new(constant_wave(0.25, 48000))
+                .keys(0, 59)
+                .velocity_tracking(0.0),
+            Zone::new(constant_wave(0.5, 48000))
+                .keys(60, 127)
+                .velocities(1, 63)
+                .velocity_tracking(0.0),
+            Zone::new(constant_wave(0.75, 48000))
+                .keys(60, 127)
+                .velocities(64, 127)
+                .velocity_tracking(0.0),
+        ]);
+
+        inst.note_on(0, 48, 100);
+        assert!((render(&mut inst, 10)[5] - 0.25).abs() < 1e-4);
+        inst.reset();
+
+        inst.note_on(0, 72, 30);
+        assert!((render(&mut inst, 10)[5] - 0.5).abs() < 1e-4);
+        inst.reset();
+
+        inst.note_on(0, 72, 100);
+        assert!((render(&mut inst, 10)[5] - 0.75).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_round_robin_alternates() {
+        let mut inst = instrument(vec![
+            Zone::new(constant_wave(0.25, 48000))
+                .round_robin(1, 2)
+                .velocity_tracking(0.0),
+            Zone::new(constant_wave(0.5, 48000))
+                .round_robin(2, 2)
+                .velocity_tracking(0.0),
+        ]);
+
+        let mut heard = Vec::new();
+        for _ in 0..4 {
+            inst.note_on(0, 60, 100);
+            heard.push(render(&mut inst, 10)[5]);
+            inst.reset_voices_for_test();
+        }
+        for (got, expected) in heard.iter().zip([0.25, 0.5, 0.25, 0.5]) {
+            assert!((got - expected).abs() < 1e-4, "{heard:?}");
+        }
+    }
+
+    #[test]
+    fn test_root_key_sets_pitch() {
+        // One cycle every 48 frames = 1 kHz at the root key
+        let samples: Vec<f32> = (0..48000)
+            .map(|i| (2.0 * std::f32::consts::PI * i as f32 / 48.0).sin())
+            .collect();
+        let wave = Arc::new(Wave::from_samples(48000.0, &samples));
+        let mut inst = instrument(vec![Zone::new(wave).root_key(60)]);
+
+        // An octave up halves the period
+        inst.note_on(0, 72, 127);
+        let out = render(&mut inst, 2400);
+        let crossings = out.windows(2).filter(|w| w[0] < 0.0 && w[1] >= 0.0).count();
+        assert!((99..=101).contains(&crossings), "{crossings} cycles");
+    }
+
+    #[test]
+    fn test_pitch_bend_is_per_channel() {
+        // One cycle every 48 frames at the root key
+        let samples: Vec<f32> = (0..48000)
+            .map(|i| (2.0 * std::f32::consts::PI * i as f32 / 48.0).sin())
+            .collect();
+        let wave = Arc::new(Wave::from_samples(48000.0, &samples));
+        let mut inst = instrument(vec![Zone::new(wave).root_key(60)]);
+        let cycles = |inst: &mut SamplerInstrument| {
+            let out = render(inst, 4800);
+            out.windows(2).filter(|w| w[0] < 0.0 && w[1] >= 0.0).count()
+        };
+
+        // Bending another channel leaves this voice alone
+        inst.note_on(0, 60, 127);
+        inst.process_midi_event(&MidiEvent::pitch_bend(0, 1, 16383));
+        assert!((99..=101).contains(&cycles(&mut inst)));
+
+        // A full bend up on its own channel raises it two semitones
+        inst.process_midi_event(&MidiEvent::pitch_bend(0, 0, 16383));
+        assert!((111..=113).contains(&cycles(&mut inst)));
+    }
+
+    #[test]
+    fn test_release_and_sustain_pedal() {
+        let mut inst = instrument(vec![
+            Zone::new(constant_wave(0.5, 48000)).envelope(0.0, 0.0, 1.0, 0.001)
+        ]);
+
+        inst.note_on(0, 60, 127);
+        render(&mut inst, 10);
+        inst.note_off(0, 60);
+        render(&mut inst, 100);
+        assert_eq!(inst.active_voice_count(), 0);
+
+        inst.process_midi_event(&MidiEvent::control_change(0, 0, 64, 127));
+        inst.note_on(0, 60, 127);
+        inst.note_off(0, 60);
+        render(&mut inst, 100);
+        assert_eq!(inst.active_voice_count(), 1, "pedal holds the note");
+
+        inst.process_midi_event(&MidiEvent::control_change(0, 0, 64, 0));
+        render(&mut inst, 100);
+        assert_eq!(inst.active_voice_count(), 0);
+    }
+
+    #[test]
+    fn test_loop_sustains_past_sample_end() {
+        let mut inst = instrument(vec![Zone::new(constant_wave(0.5, 100))
+            .loop_mode(crate::instrument::ZoneLoopMode::Continuous)
+            .loop_range(20, 80)
+            .velocity_tracking(0.0)]);
+
+        inst.note_on(0, 60, 127);
+        let out = render(&mut inst, 1000);
+        assert!(out[900..].iter().all(|&s| (s - 0.5).abs() < 1e-4));
+    }
+
+    #[test]
+    fn test_polyphony_steals_when_full() {
+        let mut inst = instrument(vec![Zone::new(constant_wave(0.1, 48000))]);
+        for note in 60..70 {
+            inst.note_on(0, note, 100);
+        }
+        render(&mut inst, 10);
+        assert_eq!(inst.active_voice_count(), 4);
+    }
+
+    #[test]
+    fn test_plays_from_export_snapshot() {
+        use tutti_core::{ExportConfig, ExportTimeline, MidiSnapshot, MidiSnapshotReader};
+
+        let mut inst = instrument(vec![
+            Zone::new(constant_wave(0.5, 48000)).velocity_tracking(0.0)
+        ]);
+        let timeline = Arc::new(ExportTimeline::new(&ExportConfig {
+            sample_rate: 48000.0,
+            tempo: 120.0,
+            ..Default::default()
+        }));
+        let mut snapshot = MidiSnapshot::new();
+        snapshot.add_event(inst.get_id(), 0.0, MidiEvent::note_on(0, 0, 60, 100));
+        inst.set_midi_source(Box::new(MidiSnapshotReader::new(
+            snapshot,
+            timeline.clone(),
+        )));
+
+        timeline.advance(64);
+        let mut buffer = tutti_core::BufferVec::new(2);
+        let mut output = buffer.buffer_mut();
+        inst.process(64, &BufferRef::new(&[]), &mut output);
+        assert!((output.at_f32(0, 32) - 0.5).abs() < 1e-4);
+    }
+
+    impl SamplerInstrument {
+        fn reset_voices_for_test(&mut self) {
+            for voice in &mut self.voices {
+                voice.kill();
+            }
+            self.allocator.reset();
+        }
+    }
+}
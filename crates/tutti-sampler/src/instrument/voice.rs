@@ -0,0 +1,350 @@
+//! Per-note playback state for [`SamplerInstrument`](super::SamplerInstrument).
+
+use super::zone::{Zone, ZoneEnvelope, ZoneFilter, ZoneFilterType, ZoneLoopMode};
+use crate::resample::Interpolator;
+use smallvec::SmallVec;
+
+/// Zones that can sound together for one note (layers, velocity crossfades).
+pub(super) const MAX_LAYERS: usize = 4;
+
+/// Below this level a released layer is considered silent.
+const SILENCE: f32 = 1.0e-4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+    Done,
+}
+
+/// Linear-segment ADSR.
+#[derive(Debug, Clone, Copy)]
+struct Envelope {
+    stage: Stage,
+    level: f32,
+    attack_step: f32,
+    decay_step: f32,
+    sustain: f32,
+    release_samples: f32,
+    release_step: f32,
+}
+
+impl Envelope {
+    fn new(config: &ZoneEnvelope, sample_rate: f32) -> Self {
+        let per_sample = |seconds: f32| 1.0 / (seconds * sample_rate).max(1.0);
+        let mut envelope = Self {
+            stage: Stage::Attack,
+            level: 0.0,
+            attack_step: per_sample(config.attack),
+            decay_step: (1.0 - config.sustain) * per_sample(config.decay),
+            sustain: config.sustain,
+            release_samples: (config.release * sample_rate).max(1.0),
+            release_step: 0.0,
+        };
+        if config.attack <= 0.0 {
+            envelope.level = 1.0;
+            envelope.stage = Stage::Decay;
+        }
+        envelope
+    }
+
+    fn release(&mut self) {
+        if self.stage != Stage::Done {
+            self.stage = Stage::Release;
+            self.release_step = self.level / self.release_samples;
+        }
+    }
+
+    #[inline]
+    fn tick(&mut self) -> f32 {
+        match self.stage {
+            Stage::Attack => {
+                self.level += self.attack_step;
+                if self.level >= 1.0 {
+                    self.level = 1.0;
+                    self.stage = Stage::Decay;
+                }
+            }
+            Stage::Decay => {
+                self.level -= self.decay_step;
+                if self.level <= self.sustain {
+                    self.level = self.sustain;
+                    self.stage = Stage::Sustain;
+                }
+            }
+            Stage::Sustain => {}
+            Stage::Release => {
+                self.level -= self.release_step;
+                if self.level <= SILENCE {
+                    self.level = 0.0;
+                    self.stage = Stage::Done;
+                }
+            }
+            Stage::Done => {}
+        }
+        self.level
+    }
+}
+
+/// Topology-preserving state-variable filter (one per channel).
+#[derive(Debug, Clone, Copy)]
+struct Svf {
+    filter_type: ZoneFilterType,
+    k: f32,
+    a1: f32,
+    a2: f32,
+    a3: f32,
+    state: [(f32, f32); 2],
+}
+
+impl Svf {
+    fn new(filter: &ZoneFilter, sample_rate: f32) -> Self {
+        let cutoff = filter.cutoff.clamp(10.0, sample_rate * 0.49);
+        let g = (std::f32::consts::PI * cutoff / sample_rate).tan();
+        let k = 1.0 / filter.q.max(0.05);
+        let a1 = 1.0 / (1.0 + g * (g + k));
+        let a2 = g * a1;
+        Self {
+            filter_type: filter.filter_type,
+            k,
+            a1,
+            a2,
+            a3: g * a2,
+            state: [(0.0, 0.0); 2],
+        }
+    }
+
+    #[inline]
+    fn process(&mut self, channel: usize, input: f32) -> f32 {
+        let (ic1, ic2) = self.state[channel];
+        let v3 = input - ic2;
+        let v1 = self.a1 * ic1 + self.a2 * v3;
+        let v2 = ic2 + self.a2 * ic1 + self.a3 * v3;
+        self.state[channel] = (2.0 * v1 - ic1, 2.0 * v2 - ic2);
+        match self.filter_type {
+            ZoneFilterType::Lowpass => v2,
+            ZoneFilterType::Bandpass => v1,
+            ZoneFilterType::Highpass => input - self.k * v1 - v2,
+        }
+    }
+}
+
+/// One zone sounding for a note.
+#[derive(Debug, Clone)]
+struct Layer {
+    zone: usize,
+    position: f64,
+    /// Pitch ratio times file/session rate ratio, before pitch bend.
+    step: f64,
+    gains: (f32, f32),
+    loop_mode: ZoneLoopMode,
+    loop_bounds: Option<(u64, u64)>,
+    envelope: Envelope,
+    filter: Option<Svf>,
+    done: bool,
+}
+
+impl Layer {
+    /// Whether reads should wrap at the loop end right now.
+    #[inline]
+    fn looping(&self) -> Option<(u64, u64)> {
+        match self.loop_mode {
+            ZoneLoopMode::Continuous => self.loop_bounds,
+            ZoneLoopMode::Sustain if self.envelope.stage != Stage::Release => self.loop_bounds,
+            _ => None,
+        }
+    }
+}
+
+/// Playback state for one allocator slot.
+#[derive(Debug, Clone, Default)]
+pub(super) struct SamplerVoice {
+    pub active: bool,
+    pub note: u8,
+    pub channel: u8,
+    released: bool,
+    layers: SmallVec<[Layer; MAX_LAYERS]>,
+}
+
+impl SamplerVoice {
+    /// Start `zone_indices` for a note, replacing whatever this slot was playing.
+    pub fn start(
+        &mut self,
+        zones: &[Zone],
+        zone_indices: &[usize],
+        note: u8,
+        channel: u8,
+        velocity: u8,
+        sample_rate: f64,
+    ) {
+        self.layers.clear();
+        for &zone_index in zone_indices.iter().take(MAX_LAYERS) {
+            let zone = &zones[zone_index];
+            let velocity_gain = zone.velocity_gain(velocity);
+            let (pan_l, pan_r) = zone.pan_gains();
+            self.layers.push(Layer {
+                zone: zone_index,
+                position: zone.offset as f64,
+                step: zone.pitch_ratio(note) * zone.wave.sample_rate() / sample_rate,
+                gains: (velocity_gain * pan_l, velocity_gain * pan_r),
+                loop_mode: zone.loop_mode,
+                loop_bounds: zone.effective_loop(),
+                envelope: Envelope::new(&zone.envelope, sample_rate as f32),
+                filter: zone
+                    .filter
+                    .as_ref()
+                    .map(|f| Svf::new(f, sample_rate as f32)),
+                done: false,
+            });
+        }
+        self.active = !self.layers.is_empty();
+        self.released = false;
+        self.note = note;
+        self.channel = channel;
+    }
+
+    /// Enter the release stage once (one-shot layers keep playing).
+    pub fn release(&mut self) {
+        if std::mem::replace(&mut self.released, true) {
+            return;
+        }
+        for layer in &mut self.layers {
+            if layer.loop_mode != ZoneLoopMode::OneShot {
+                layer.envelope.release();
+            }
+        }
+    }
+
+    /// Silence immediately.
+    pub fn kill(&mut self) {
+        self.layers.clear();
+        self.active = false;
+    }
+
+    /// Render one stereo frame. Deactivates the voice when every layer is done.
+    #[inline]
+    pub fn tick(&mut self, zones: &[Zone], interpolator: &Interpolator, bend: f64) -> (f32, f32) {
+        let (mut left, mut right) = (0.0f32, 0.0f32);
+        let mut any_playing = false;
+
+        for layer in self.layers.iter_mut().filter(|l| !l.done) {
+            let wave = &zones[layer.zone].wave;
+            let len = wave.len() as u64;
+            let looping = layer.looping();
+            let step = layer.step * bend;
+
+            let base = layer.position.floor() as isize;
+            let frac = (layer.position - base as f64) as f32;
+            let frame = |k: isize| {
+                let Ok(mut idx) = u64::try_from(base + k) else {
+                    return (0.0, 0.0);
+                };
+                if let Some((start, end)) = looping {
+                    if idx >= end {
+                        idx = start + (idx - end) % (end - start);
+                    }
+                }
+                if idx >= len {
+                    return (0.0, 0.0);
+                }
+                let idx = idx as usize;
+                let l = wave.at(0, idx);
+                let r = if wave.channels() > 1 {
+                    wave.at(1, idx)
+                } else {
+                    l
+                };
+                (l, r)
+            };
+            let (mut l, mut r) = interpolator.interpolate(frame, frac, step);
+
+            if let Some(ref mut filter) = layer.filter {
+                l = filter.process(0, l);
+                r = filter.process(1, r);
+            }
+
+            let amp = layer.envelope.tick();
+            left += l * amp * layer.gains.0;
+            right += r * amp * layer.gains.1;
+
+            layer.position += step;
+            if let Some((start, end)) = looping {
+                while layer.position >= end as f64 {
+                    layer.position -= (end - start) as f64;
+                }
+            }
+
+            layer.done = layer.envelope.stage == Stage::Done || layer.position >= len as f64;
+            any_playing |= !layer.done;
+        }
+
+        if !any_playing {
+            self.kill();
+        }
+        (left, right)
+    }
+
+    /// Peak envelope level across layers, for quietest-voice stealing.
+    pub fn level(&self) -> f32 {
+        self.layers
+            .iter()
+            .filter(|l| !l.done)
+            .map(|l| l.envelope.level)
+            .fold(0.0, f32::max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelope_stages() {
+        let config = ZoneEnvelope {
+            attack: 0.01,
+            decay: 0.01,
+            sustain: 0.5,
+            release: 0.01,
+        };
+        let mut env = Envelope::new(&config, 1000.0);
+
+        for _ in 0..10 {
+            env.tick();
+        }
+        assert!((env.level - 1.0).abs() < 1e-4);
+        for _ in 0..10 {
+            env.tick();
+        }
+        assert_eq!(env.stage, Stage::Sustain);
+        assert_eq!(env.level, 0.5);
+
+        env.release();
+        for _ in 0..11 {
+            env.tick();
+        }
+        assert_eq!(env.stage, Stage::Done);
+    }
+
+    #[test]
+    fn test_svf_lowpass_passes_dc_and_blocks_nyquist() {
+        let filter = ZoneFilter::new(ZoneFilterType::Lowpass, 1000.0, 0.707);
+        let mut svf = Svf::new(&filter, 48000.0);
+
+        let mut dc = 0.0;
+        for _ in 0..2000 {
+            dc = svf.process(0, 1.0);
+        }
+        assert!((dc - 1.0).abs() < 1e-3);
+
+        let mut peak = 0.0f32;
+        for i in 0..2000 {
+            let y = svf.process(1, if i % 2 == 0 { 1.0 } else { -1.0 });
+            if i > 1000 {
+                peak = peak.max(y.abs());
+            }
+        }
+        assert!(peak < 1e-3, "nyquist leaked: {peak}");
+    }
+}
@@ -0,0 +1,291 @@
+//! Key/velocity zones of a multisample instrument.
+
+use std::sync::Arc;
+use tutti_core::Wave;
+
+/// How a zone's sample repeats while the note is held.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ZoneLoopMode {
+    /// Play to the end of the sample, or until the release finishes.
+    #[default]
+    NoLoop,
+    /// Play the whole sample regardless of note-off.
+    OneShot,
+    /// Loop the loop range until the release finishes.
+    Continuous,
+    /// Loop while held, then play through past the loop end on release.
+    Sustain,
+}
+
+/// Amplitude envelope, in seconds (sustain is a 0.0-1.0 level).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ZoneEnvelope {
+    pub attack: f32,
+    pub decay: f32,
+    pub sustain: f32,
+    pub release: f32,
+}
+
+impl Default for ZoneEnvelope {
+    fn default() -> Self {
+        Self {
+            attack: 0.0,
+            decay: 0.0,
+            sustain: 1.0,
+            release: 0.005,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ZoneFilterType {
+    #[default]
+    Lowpass,
+    Highpass,
+    Bandpass,
+}
+
+/// Per-zone state-variable filter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ZoneFilter {
+    pub filter_type: ZoneFilterType,
+    /// Cutoff in Hz.
+    pub cutoff: f32,
+    /// Resonance as Q (0.707 = no peak).
+    pub q: f32,
+}
+
+impl ZoneFilter {
+    pub fn new(filter_type: ZoneFilterType, cutoff: f32, q: f32) -> Self {
+        Self {
+            filter_type,
+            cutoff,
+            q,
+        }
+    }
+}
+
+/// One sample mapped to a key and velocity range.
+///
+/// # Example
+///
+/// ```ignore
+/// let zone = Zone::new(wave)
+///     .keys(48, 59)
+///     .root_key(53)
+///     .velocities(0, 90)
+///     .round_robin(1, 2)
+///     .envelope(0.002, 0.3, 0.6, 0.4);
+/// ```
+#[derive(Clone)]
+pub struct Zone {
+    pub(crate) wave: Arc<Wave>,
+    pub(crate) lo_key: u8,
+    pub(crate) hi_key: u8,
+    pub(crate) root_key: u8,
+    pub(crate) lo_vel: u8,
+    pub(crate) hi_vel: u8,
+    /// (1-based position, length)
+    pub(crate) round_robin: Option<(u32, u32)>,
+    pub(crate) loop_mode: ZoneLoopMode,
+    pub(crate) loop_range: Option<(u64, u64)>,
+    pub(crate) offset: u64,
+    pub(crate) envelope: ZoneEnvelope,
+    pub(crate) filter: Option<ZoneFilter>,
+    pub(crate) gain: f32,
+    pub(crate) pan: f32,
+    pub(crate) tune_cents: f32,
+    pub(crate) key_tracking: f32,
+    pub(crate) velocity_tracking: f32,
+}
+
+impl Zone {
+    /// Zone covering every key and velocity, rooted at middle C.
+    pub fn new(wave: Arc<Wave>) -> Self {
+        Self {
+            wave,
+            lo_key: 0,
+            hi_key: 127,
+            root_key: 60,
+            lo_vel: 1,
+            hi_vel: 127,
+            round_robin: None,
+            loop_mode: ZoneLoopMode::NoLoop,
+            loop_range: None,
+            offset: 0,
+            envelope: ZoneEnvelope::default(),
+            filter: None,
+            gain: 1.0,
+            pan: 0.0,
+            tune_cents: 0.0,
+            key_tracking: 100.0,
+            velocity_tracking: 1.0,
+        }
+    }
+
+    /// Inclusive key range.
+    pub fn keys(mut self, lo: u8, hi: u8) -> Self {
+        self.lo_key = lo.min(127);
+        self.hi_key = hi.min(127);
+        self
+    }
+
+    /// Key at which the sample plays at its recorded pitch.
+    pub fn root_key(mut self, key: u8) -> Self {
+        self.root_key = key.min(127);
+        self
+    }
+
+    /// Inclusive velocity range.
+    pub fn velocities(mut self, lo: u8, hi: u8) -> Self {
+        self.lo_vel = lo.min(127);
+        self.hi_vel = hi.min(127);
+        self
+    }
+
+    /// Play only on every `length`-th hit of this zone's range, at `position` (1-based).
+    ///
+    /// Zones sharing a range with positions 1..=length alternate round-robin.
+    pub fn round_robin(mut self, position: u32, length: u32) -> Self {
+        self.round_robin = (length > 1).then_some((position.clamp(1, length), length));
+        self
+    }
+
+    pub fn loop_mode(mut self, mode: ZoneLoopMode) -> Self {
+        self.loop_mode = mode;
+        self
+    }
+
+    /// Loop points in sample frames (end exclusive). Defaults to the whole sample.
+    pub fn loop_range(mut self, start: u64, end: u64) -> Self {
+        self.loop_range = (end > start).then_some((start, end));
+        self
+    }
+
+    /// Start playback this many frames into the sample.
+    pub fn offset(mut self, frames: u64) -> Self {
+        self.offset = frames;
+        self
+    }
+
+    pub fn envelope(mut self, attack: f32, decay: f32, sustain: f32, release: f32) -> Self {
+        self.envelope = ZoneEnvelope {
+            attack: attack.max(0.0),
+            decay: decay.max(0.0),
+            sustain: sustain.clamp(0.0, 1.0),
+            release: release.max(0.0),
+        };
+        self
+    }
+
+    pub fn filter(mut self, filter: ZoneFilter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Linear gain.
+    pub fn gain(mut self, gain: f32) -> Self {
+        self.gain = gain.max(0.0);
+        self
+    }
+
+    /// Gain in decibels.
+    pub fn volume_db(mut self, db: f32) -> Self {
+        self.gain = 10.0f32.powf(db / 20.0);
+        self
+    }
+
+    /// -1.0 (left) to 1.0 (right).
+    pub fn pan(mut self, pan: f32) -> Self {
+        self.pan = pan.clamp(-1.0, 1.0);
+        self
+    }
+
+    /// Fine tuning in cents.
+    pub fn tune(mut self, cents: f32) -> Self {
+        self.tune_cents = cents;
+        self
+    }
+
+    /// Pitch change per key away from the root, in cents (default 100; 0 for drums).
+    pub fn key_tracking(mut self, cents_per_key: f32) -> Self {
+        self.key_tracking = cents_per_key;
+        self
+    }
+
+    /// How much velocity scales amplitude, 0.0-1.0 (default 1.0).
+    pub fn velocity_tracking(mut self, amount: f32) -> Self {
+        self.velocity_tracking = amount.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn wave(&self) -> &Arc<Wave> {
+        &self.wave
+    }
+
+    pub fn key_range(&self) -> (u8, u8) {
+        (self.lo_key, self.hi_key)
+    }
+
+    pub fn velocity_range(&self) -> (u8, u8) {
+        (self.lo_vel, self.hi_vel)
+    }
+
+    pub fn root(&self) -> u8 {
+        self.root_key
+    }
+
+    pub(crate) fn contains(&self, note: u8, velocity: u8) -> bool {
+        (self.lo_key..=self.hi_key).contains(&note)
+            && (self.lo_vel..=self.hi_vel).contains(&velocity)
+    }
+
+    /// Loop bounds clamped to the sample, if this zone loops at all.
+    pub(crate) fn effective_loop(&self) -> Option<(u64, u64)> {
+        if !matches!(
+            self.loop_mode,
+            ZoneLoopMode::Continuous | ZoneLoopMode::Sustain
+        ) {
+            return None;
+        }
+        let len = self.wave.len() as u64;
+        let (start, end) = self.loop_range.unwrap_or((0, len));
+        let end = end.min(len);
+        (end > start).then_some((start, end))
+    }
+
+    /// Playback rate relative to the sample's own rate for `note`, before SRC.
+    pub(crate) fn pitch_ratio(&self, note: u8) -> f64 {
+        let cents = (note as f32 - self.root_key as f32) * self.key_tracking + self.tune_cents;
+        2.0f64.powf(cents as f64 / 1200.0)
+    }
+
+    /// Amplitude for `velocity`, following a squared velocity curve.
+    pub(crate) fn velocity_gain(&self, velocity: u8) -> f32 {
+        let v = velocity as f32 / 127.0;
+        self.gain * (1.0 - self.velocity_tracking + self.velocity_tracking * v * v)
+    }
+
+    /// Constant-power (left, right) gains.
+    pub(crate) fn pan_gains(&self) -> (f32, f32) {
+        let angle = (self.pan + 1.0) * std::f32::consts::FRAC_PI_4;
+        (
+            angle.cos() * std::f32::consts::SQRT_2,
+            angle.sin() * std::f32::consts::SQRT_2,
+        )
+    }
+}
+
+impl std::fmt::Debug for Zone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Zone")
+            .field("keys", &(self.lo_key, self.hi_key))
+            .field("root_key", &self.root_key)
+            .field("velocities", &(self.lo_vel, self.hi_vel))
+            .field("round_robin", &self.round_robin)
+            .field("loop_mode", &self.loop_mode)
+            .field("loop_range", &self.loop_range)
+            .field("frames", &self.wave.len())
+            .finish_non_exhaustive()
+    }
+}
@@ -6,10 +6,13 @@
 //! # Features
 //!
 //! - **Disk streaming**: Butler thread for asynchronous I/O with ring buffers
-//! - **Audio input**: Hardware capture with lock-free MPMC channels
-//! - **Recording**: MIDI, audio, and pattern recording with quantization
+//! - **Audio input**: Hardware capture with lock-free MPMC channels, written as WAV/RF64 or W64
+//!   with crash-safe headers
+//! - **Recording**: MIDI, audio, and pattern recording with quantization, plus loop
+//!   recording into take lanes with crossfaded comping
 //! - **Time-stretching**: Real-time pitch and tempo manipulation via phase vocoder
 //! - **Automation**: Parameter automation recording and playback
+//! - **Instruments**: MIDI multisample instruments with SFZ/Decent Sampler import (`instrument` feature)
 //!
 //! # Example
 //!
@@ -51,17 +54,30 @@ pub use time_stretch::{
 
 pub use import::{ImportHandle, ImportStatus};
 
-pub use butler::{PlayDirection, Varispeed};
+pub use butler::{
+    recover_capture, CaptureContainer, CaptureFileInfo, CaptureFormat, CaptureSampleFormat,
+    PlayDirection, Varispeed,
+};
+pub use resample::ResampleQuality;
+
+#[cfg(feature = "instrument")]
+pub use instrument::{
+    SamplerInstrument, SamplerInstrumentBuilder, Zone, ZoneEnvelope, ZoneFilter, ZoneFilterType,
+    ZoneLoopMode,
+};
 
 pub use recording::{
-    PunchEvent, QuantizeSettings, QuantizeSettingsBuilder, RecordedData, RecordingBuffer,
-    RecordingConfig, RecordingConfigBuilder, RecordingMode, RecordingSession, RecordingSource,
-    RecordingState, XRunEvent, XRunType,
+    Comp, CompSegment, PunchEvent, QuantizeSettings, QuantizeSettingsBuilder, RecordedData,
+    RecordingBuffer, RecordingConfig, RecordingConfigBuilder, RecordingMode, RecordingSession,
+    RecordingSource, RecordingState, Take, TakeAudio, TakeLane, XRunEvent, XRunType,
 };
 
 mod audio_input;
 pub(crate) mod butler;
 pub mod import;
+#[cfg(feature = "instrument")]
+mod instrument;
 pub(crate) mod recording;
+mod resample;
 mod sampler;
 mod time_stretch;
@@ -1,5 +1,7 @@
 //! Recording configuration types.
 
+use crate::butler::CaptureFormat;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RecordingSource {
     MidiInput,
@@ -12,6 +14,7 @@ pub enum RecordingSource {
 pub enum RecordingMode {
     Replace,
     Overdub,
+    /// Cycle recording: each pass through the transport loop becomes a separate take.
     Loop,
 }
 
@@ -25,6 +28,10 @@ pub struct RecordingConfig {
     pub preroll_beats: f64,
     pub punch_in: Option<f64>,
     pub punch_out: Option<f64>,
+    /// Transport loop in beats (start, end), used to bound takes in [`RecordingMode::Loop`].
+    pub loop_range: Option<(f64, f64)>,
+    /// File format for [`RecordingSource::AudioInput`] captures.
+    pub capture_format: CaptureFormat,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -45,6 +52,8 @@ impl Default for RecordingConfig {
             preroll_beats: 0.0,
             punch_in: None,
             punch_out: None,
+            loop_range: None,
+            capture_format: CaptureFormat::default(),
         }
     }
 }
@@ -79,6 +88,8 @@ pub struct RecordingConfigBuilder {
     preroll_beats: f64,
     punch_in: Option<f64>,
     punch_out: Option<f64>,
+    loop_range: Option<(f64, f64)>,
+    capture_format: CaptureFormat,
 }
 
 impl Default for RecordingConfigBuilder {
@@ -92,6 +103,8 @@ impl Default for RecordingConfigBuilder {
             preroll_beats: 0.0,
             punch_in: None,
             punch_out: None,
+            loop_range: None,
+            capture_format: CaptureFormat::default(),
         }
     }
 }
@@ -149,6 +162,20 @@ impl RecordingConfigBuilder {
         self
     }
 
+    /// Set the transport loop in beats for cycle recording
+    ///
+    /// Without it, takes end at the last beat seen before the wrap.
+    pub fn loop_range(mut self, start_beat: f64, end_beat: f64) -> Self {
+        self.loop_range = (end_beat > start_beat).then_some((start_beat, end_beat));
+        self
+    }
+
+    /// Set the container and sample format of audio input captures
+    pub fn capture_format(mut self, format: CaptureFormat) -> Self {
+        self.capture_format = format;
+        self
+    }
+
     pub fn build(self) -> RecordingConfig {
         RecordingConfig {
             channel_index: self.channel_index,
@@ -159,6 +186,8 @@ impl RecordingConfigBuilder {
             preroll_beats: self.preroll_beats,
             punch_in: self.punch_in,
             punch_out: self.punch_out,
+            loop_range: self.loop_range,
+            capture_format: self.capture_format,
         }
     }
 }
@@ -358,4 +387,29 @@ mod tests {
         assert_eq!(config.punch_in, Some(4.0));
         assert_eq!(config.punch_out, Some(8.0));
     }
+
+    #[test]
+    fn test_recording_config_builder_loop_range() {
+        let config = RecordingConfig::builder()
+            .mode(RecordingMode::Loop)
+            .loop_range(4.0, 12.0)
+            .build();
+        assert_eq!(config.loop_range, Some((4.0, 12.0)));
+
+        let config = RecordingConfig::builder().loop_range(8.0, 8.0).build();
+        assert_eq!(config.loop_range, None);
+    }
+
+    #[test]
+    fn test_recording_config_builder_capture_format() {
+        use crate::butler::CaptureSampleFormat;
+
+        let config = RecordingConfig::builder().build();
+        assert_eq!(config.capture_format, CaptureFormat::default());
+
+        let format = CaptureFormat::w64(CaptureSampleFormat::Int24);
+        let config = RecordingConfig::builder().capture_format(format).build();
+        assert_eq!(config.capture_format, format);
+        assert_eq!(config.capture_format.extension(), "w64");
+    }
 }
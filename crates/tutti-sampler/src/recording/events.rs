@@ -621,6 +621,15 @@ impl RecordingBuffer {
         self.current_beat = self.start_beat;
     }
 
+    /// End every held note at `beat`, e.g. when a loop pass is cut into a take.
+    pub fn release_held_notes(&mut self, beat: f64) {
+        let mut held: Vec<u8> = self.active_notes.keys().copied().collect();
+        held.sort_unstable();
+        for note in held {
+            self.record_midi_note_off(note, beat, 0);
+        }
+    }
+
     pub fn active_note_count(&self) -> usize {
         self.active_notes.len()
     }
@@ -628,6 +637,75 @@ impl RecordingBuffer {
     pub fn has_active_notes(&self) -> bool {
         !self.active_notes.is_empty()
     }
+
+    /// Completed notes and controller data as MIDI 1.0 messages at their
+    /// transport beat, sorted by beat with note-offs first at equal beats.
+    ///
+    /// Notes still held and MIDI 2.0 per-note data are not included.
+    #[cfg(feature = "midi")]
+    pub fn to_timed_midi_events(&self) -> Vec<tutti_core::TimedMidiEvent> {
+        use tutti_core::{MidiEvent, TimedMidiEvent};
+
+        let mut events = Vec::new();
+        let mut push = |beat: f64, event: MidiEvent| events.push(TimedMidiEvent { event, beat });
+
+        for note in &self.midi_events {
+            push(
+                note.start_beat,
+                MidiEvent::note_on(0, note.channel, note.note, note.velocity),
+            );
+            push(
+                note.start_beat + note.duration,
+                MidiEvent::note_off(0, note.channel, note.note, 0),
+            );
+        }
+        for cc in &self.cc_events {
+            push(
+                cc.beat,
+                MidiEvent::control_change(0, cc.channel, cc.controller, cc.value),
+            );
+        }
+        for bend in &self.pitch_bend_events {
+            let raw = (bend.value as i32 + 8192).clamp(0, 16383) as u16;
+            push(bend.beat, MidiEvent::pitch_bend(0, bend.channel, raw));
+        }
+        for pressure in &self.pressure_events {
+            let event = match pressure.note {
+                Some(note) => {
+                    MidiEvent::poly_aftertouch(0, pressure.channel, note, pressure.pressure)
+                }
+                None => MidiEvent::aftertouch(0, pressure.channel, pressure.pressure),
+            };
+            push(pressure.beat, event);
+        }
+        for program in &self.program_change_events {
+            push(
+                program.beat,
+                MidiEvent::program_builder(program.program)
+                    .channel(program.channel)
+                    .build(),
+            );
+        }
+
+        // At equal beats: note-offs, then controllers and programs, then
+        // note-ons, so a re-struck note is released first and sounds with
+        // the controller state recorded alongside it
+        let rank = |e: &MidiEvent| {
+            if e.is_note_off() {
+                0
+            } else if e.is_note_on() {
+                2
+            } else {
+                1
+            }
+        };
+        events.sort_by(|a, b| {
+            a.beat
+                .total_cmp(&b.beat)
+                .then_with(|| rank(&a.event).cmp(&rank(&b.event)))
+        });
+        events
+    }
 }
 
 #[cfg(test)]
@@ -650,6 +728,20 @@ mod tests {
         assert_eq!(buffer.midi_events[0].duration, 1.0);
     }
 
+    #[test]
+    fn test_release_held_notes() {
+        let mut buffer = RecordingBuffer::new(0.0, 44100.0);
+        buffer.record_midi_note_on(64, 90, 1.0, 0);
+        buffer.record_midi_note_on(60, 100, 0.5, 0);
+
+        buffer.release_held_notes(4.0);
+        assert!(!buffer.has_active_notes());
+        assert_eq!(buffer.midi_events.len(), 2);
+        assert_eq!(buffer.midi_events[0].note, 60);
+        assert_eq!(buffer.midi_events[0].duration, 3.5);
+        assert_eq!(buffer.midi_events[1].duration, 3.0);
+    }
+
     #[test]
     fn test_audio_chunking() {
         let mut buffer = RecordingBuffer::new(0.0, 44100.0);
@@ -676,4 +768,34 @@ mod tests {
         assert_eq!(buffer.pattern_events[0].symbol, "bd");
         assert_eq!(buffer.pattern_events[1].step, 4);
     }
+
+    #[cfg(feature = "midi")]
+    #[test]
+    fn test_timed_midi_events() {
+        let mut buffer = RecordingBuffer::new(0.0, 44100.0);
+        buffer.record_midi_program_change(3, 12, 0.0);
+        buffer.record_midi_note_on(60, 100, 0.0, 3);
+        buffer.record_midi_note_off(60, 1.0, 3);
+        buffer.record_midi_note_on(60, 90, 1.0, 3);
+        buffer.record_midi_note_off(60, 2.0, 3);
+        buffer.record_midi_pitch_bend(3, -8192, 1.5);
+
+        let events = buffer.to_timed_midi_events();
+        assert_eq!(events.len(), 6);
+        assert!(events.iter().all(|e| e.event.channel_num() == 3));
+        assert!(events.windows(2).all(|w| w[0].beat <= w[1].beat));
+
+        // Program first, and the first note ends before the second starts
+        assert_eq!(
+            events[0].event.msg,
+            tutti_core::ChannelVoiceMsg::ProgramChange { program: 12 }
+        );
+        assert_eq!(events[2].beat, 1.0);
+        assert!(events[2].event.is_note_off());
+        assert!(events[3].event.is_note_on());
+        assert_eq!(
+            events[4].event.msg,
+            tutti_core::ChannelVoiceMsg::PitchBend { bend: 0 }
+        );
+    }
 }
@@ -1,14 +1,27 @@
 //! Recording session management for MIDI and audio input.
 //! - Audio callback: Minimal interaction (only for audio input via Butler)
 
-use crate::butler::{ButlerCommand, CaptureBuffer, CaptureId, FlushRequest};
+use crate::butler::{
+    ButlerCommand, CaptureBuffer, CaptureBufferConsumer, CaptureBufferProducer, CaptureFileInfo,
+    CaptureId, FlushRequest,
+};
+use crate::recording::session::FinishedPass;
 use crate::recording::{
     PunchEvent, RecordedData, RecordingBuffer, RecordingConfig, RecordingMode, RecordingSession,
-    RecordingSource, RecordingState, XRunEvent,
+    RecordingSource, RecordingState, Take, TakeAudio, TakeLane, XRunEvent,
 };
+use crossbeam_channel::Sender;
 use dashmap::DashMap;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+type Sessions = Arc<DashMap<usize, Arc<RecordingSession>>>;
+
+/// How often finished loop passes are collected into takes.
+const COLLECT_INTERVAL: Duration = Duration::from_millis(5);
 
 /// Manages audio and MIDI recording sessions
 ///
@@ -23,6 +36,9 @@ pub struct RecordingManager {
 
     /// Sample rate
     sample_rate: f64,
+
+    /// Collects loop passes off the audio thread; runs while any loop recording does
+    collector: parking_lot::Mutex<Option<TakeCollector>>,
 }
 
 impl RecordingManager {
@@ -36,6 +52,7 @@ impl RecordingManager {
             sessions: Arc::new(DashMap::new()),
             butler_tx,
             sample_rate,
+            collector: parking_lot::Mutex::new(None),
         }
     }
 
@@ -55,18 +72,63 @@ impl RecordingManager {
             mode,
             ..Default::default()
         };
+        self.start_recording_with_config(config, current_beat)
+    }
 
+    /// Start recording with full settings (punch range, preroll, loop range).
+    pub fn start_recording_with_config(
+        &self,
+        config: RecordingConfig,
+        current_beat: f64,
+    ) -> crate::error::Result<()> {
+        let channel_index = config.channel_index;
         let session = RecordingSession::new(config, self.sample_rate, current_beat);
 
-        if source == RecordingSource::AudioInput {
-            self.setup_audio_input_capture(&session)?;
+        let looping = session.mode() == RecordingMode::Loop;
+        if session.source() == RecordingSource::AudioInput {
+            self.setup_audio_input_capture(&session, looping.then_some(0))?;
+        }
+        if looping {
+            prepare_next_pass(&session, self.sample_rate);
         }
 
         self.sessions.insert(channel_index, Arc::new(session));
 
+        // After the insert, so a concurrent stop can't see no loop sessions
+        // and shut down the collector this one needs
+        if looping {
+            self.collector.lock().get_or_insert_with(|| {
+                TakeCollector::start(
+                    Arc::clone(&self.sessions),
+                    self.butler_tx.clone(),
+                    self.sample_rate,
+                )
+            });
+        } else {
+            self.stop_idle_collector();
+        }
+
         Ok(())
     }
 
+    /// Stop the take collector once no loop recording is left for it.
+    fn stop_idle_collector(&self) {
+        let idle = {
+            let mut collector = self.collector.lock();
+            let looping = self
+                .sessions
+                .iter()
+                .any(|entry| entry.mode() == RecordingMode::Loop);
+            if looping {
+                None
+            } else {
+                collector.take()
+            }
+        };
+        // Joins the thread
+        drop(idle);
+    }
+
     pub fn stop_recording(&self, channel_index: usize) -> crate::error::Result<RecordedData> {
         // Get session and clone Arc to avoid holding DashMap lock
         let session = self
@@ -88,6 +150,36 @@ impl RecordingManager {
         let punch_events = session.get_punch_events();
         let xrun_events = session.get_xrun_events();
 
+        if session.mode() == RecordingMode::Loop {
+            collect_passes(&session, &self.butler_tx, self.sample_rate)?;
+
+            let end_beat = session.last_beat().max(session.get_buffer().current_beat);
+            let mut takes = session.lock_takes();
+            let last = FinishedPass {
+                pass: session.pass_index(),
+                start_beat: session.pass_start_beat(),
+                end_beat,
+                buffer: session.swap_buffer(RecordingBuffer::new(end_beat, self.sample_rate)),
+                capture: session.get_capture_producer(),
+                lead_in: session.pass_lead_in(),
+                trailing: 0,
+            };
+            finish_pass(&mut takes, &self.butler_tx, last)?;
+
+            let mut lane = TakeLane::new(channel_index, self.sample_rate);
+            for take in takes.drain(..) {
+                lane.push(take);
+            }
+            drop(takes);
+            self.sessions.remove(&channel_index);
+            self.stop_idle_collector();
+            return Ok(RecordedData::Takes {
+                lane,
+                punch_events,
+                xrun_events,
+            });
+        }
+
         // Extract recorded data based on source
         let data = match session.source() {
             RecordingSource::MidiInput => {
@@ -194,6 +286,43 @@ impl RecordingManager {
         transitions
     }
 
+    /// Split [`RecordingMode::Loop`] sessions into takes when the transport wraps.
+    ///
+    /// Call each audio callback with the beat after any loop jump, before
+    /// capturing that callback's audio. When the transport jumped during the
+    /// previous callback, `frames_after_wrap` is how many of its frames came
+    /// after the jump (its length minus the in-block offset of the wrap); they
+    /// are already in the finished pass's capture and become the start of the
+    /// next take. Returns number of sessions that started a new pass.
+    ///
+    /// Real-time safe: a wrap only swaps in the buffer and capture prepared
+    /// for the next pass. A background thread turns finished passes into
+    /// takes and prepares the pass after.
+    pub fn process_loop_all(&self, current_beat: f64, frames_after_wrap: usize) -> usize {
+        let mut wrapped = 0;
+        for session in self.sessions.iter() {
+            let Some(previous_beat) = session.update_loop_position(current_beat) else {
+                continue;
+            };
+            let (end_beat, next_start_beat) = match session.config().loop_range {
+                Some((start, end)) => (end, start),
+                None => (previous_beat, current_beat),
+            };
+            if session.wrap_pass(end_beat, next_start_beat, frames_after_wrap as u64) {
+                wrapped += 1;
+            }
+        }
+        wrapped
+    }
+
+    /// Number of completed passes for a channel's loop recording.
+    pub fn take_count(&self, channel_index: usize) -> usize {
+        self.sessions
+            .get(&channel_index)
+            .map(|s| s.pass_index())
+            .unwrap_or(0)
+    }
+
     /// Set record safe mode for a channel.
     ///
     /// When record safe is enabled, the session will not record any data
@@ -460,39 +589,21 @@ impl RecordingManager {
         self.sessions.get(&channel_index)?.get_capture_producer()
     }
 
-    /// Setup audio input capture for a session
-    fn setup_audio_input_capture(&self, session: &RecordingSession) -> crate::error::Result<()> {
-        let capture_id = CaptureId::generate();
-
-        let file_path = PathBuf::from(format!(
-            "recordings/track_{}_{}.wav",
-            session.channel_index(),
-            capture_id.0
-        ));
-
-        let (producer, consumer) = CaptureBuffer::new(
-            capture_id,
-            file_path.clone(),
+    /// Setup audio input capture for a session, one file per pass when looping
+    fn setup_audio_input_capture(
+        &self,
+        session: &RecordingSession,
+        pass: Option<usize>,
+    ) -> crate::error::Result<()> {
+        let (producer, consumer) = create_capture(session, pass, self.sample_rate);
+        register_capture(
+            &self.butler_tx,
+            session,
+            &producer,
+            consumer,
             self.sample_rate,
-            1000.0, // 1 second buffer
-        );
-
-        self.butler_tx
-            .send(ButlerCommand::RegisterCapture {
-                capture_id,
-                consumer,
-                file_path: file_path.clone(),
-                sample_rate: self.sample_rate,
-                channels: 2,
-            })
-            .map_err(|e| {
-                crate::error::Error::Recording(format!("Failed to send RegisterCapture: {}", e))
-            })?;
-
-        session.set_capture_id(capture_id);
-        session.set_recording_file(file_path);
+        )?;
         session.set_capture_producer(producer);
-
         Ok(())
     }
 
@@ -521,14 +632,9 @@ impl RecordingManager {
                 crate::error::Error::Recording(format!("Failed to send RemoveCapture: {}", e))
             })?;
 
-        let duration_seconds = match hound::WavReader::open(&file_path) {
-            Ok(reader) => {
-                let spec = reader.spec();
-                let sample_count = reader.len();
-                sample_count as f64 / (spec.sample_rate as f64 * spec.channels as f64)
-            }
-            Err(_) => 0.0,
-        };
+        let duration_seconds = CaptureFileInfo::read(&file_path)
+            .map(|info| info.duration_seconds())
+            .unwrap_or(0.0);
 
         Ok(RecordedData::Audio {
             file_path,
@@ -539,6 +645,213 @@ impl RecordingManager {
     }
 }
 
+/// Capture buffer and file for a session, one file per pass when looping.
+fn create_capture(
+    session: &RecordingSession,
+    pass: Option<usize>,
+    sample_rate: f64,
+) -> (CaptureBufferProducer, CaptureBufferConsumer) {
+    let capture_id = CaptureId::generate();
+    let format = session.config().capture_format;
+
+    let file_path = PathBuf::from(match pass {
+        Some(pass) => format!(
+            "recordings/track_{}_{}_pass{}.{}",
+            session.channel_index(),
+            capture_id.0,
+            pass,
+            format.extension()
+        ),
+        None => format!(
+            "recordings/track_{}_{}.{}",
+            session.channel_index(),
+            capture_id.0,
+            format.extension()
+        ),
+    });
+
+    CaptureBuffer::new(
+        capture_id,
+        file_path,
+        sample_rate,
+        1000.0, // 1 second buffer
+    )
+}
+
+/// Hand a capture to the butler and make it the session's current one.
+fn register_capture(
+    butler_tx: &Sender<ButlerCommand>,
+    session: &RecordingSession,
+    producer: &CaptureBufferProducer,
+    consumer: CaptureBufferConsumer,
+    sample_rate: f64,
+) -> crate::error::Result<()> {
+    let capture_id = producer.capture_id();
+    let file_path = producer.file_path().clone();
+
+    butler_tx
+        .send(ButlerCommand::RegisterCapture {
+            capture_id,
+            consumer,
+            file_path: file_path.clone(),
+            sample_rate,
+            channels: 2,
+            format: session.config().capture_format,
+        })
+        .map_err(|e| {
+            crate::error::Error::Recording(format!("Failed to send RegisterCapture: {}", e))
+        })?;
+
+    session.set_capture_id(capture_id);
+    session.set_recording_file(file_path);
+    Ok(())
+}
+
+/// Prepare the buffer and capture the audio thread swaps in at the next wrap.
+fn prepare_next_pass(session: &RecordingSession, sample_rate: f64) {
+    if !session.has_next_buffer() {
+        let start_beat = session.config().loop_range.map_or(0.0, |(start, _)| start);
+        session.set_next_buffer(RecordingBuffer::new(start_beat, sample_rate));
+    }
+    if session.source() == RecordingSource::AudioInput && !session.has_next_capture() {
+        let (producer, consumer) =
+            create_capture(session, Some(session.pass_index() + 1), sample_rate);
+        session.set_next_capture(producer, consumer);
+    }
+}
+
+/// Turn passes closed on the audio thread into takes and prepare the next pass.
+fn collect_passes(
+    session: &RecordingSession,
+    butler_tx: &Sender<ButlerCommand>,
+    sample_rate: f64,
+) -> crate::error::Result<()> {
+    let mut takes = session.lock_takes();
+    for finished in session.finished_passes() {
+        if finished.capture.is_some() {
+            // The audio thread is writing to the swapped-in capture
+            if let (Some(consumer), Some(producer)) = (
+                session.take_next_capture_consumer(),
+                session.get_capture_producer(),
+            ) {
+                register_capture(butler_tx, session, &producer, consumer, sample_rate)?;
+            }
+        }
+        finish_pass(&mut takes, butler_tx, finished)?;
+    }
+    if session.is_active() {
+        prepare_next_pass(session, sample_rate);
+    }
+    Ok(())
+}
+
+/// Close a pass as a take, releasing its capture file.
+fn finish_pass(
+    takes: &mut Vec<Take>,
+    butler_tx: &Sender<ButlerCommand>,
+    finished: FinishedPass,
+) -> crate::error::Result<()> {
+    let FinishedPass {
+        pass,
+        start_beat,
+        end_beat,
+        buffer,
+        capture,
+        lead_in,
+        trailing,
+    } = finished;
+
+    let mut buffer = Arc::unwrap_or_clone(buffer);
+    buffer.start_beat = start_beat;
+    buffer.release_held_notes(end_beat);
+
+    let audio = match capture {
+        Some(producer) => {
+            butler_tx
+                .send(ButlerCommand::RemoveCapture(producer.capture_id()))
+                .map_err(|e| {
+                    crate::error::Error::Recording(format!("Failed to send RemoveCapture: {}", e))
+                })?;
+            // The previous pass's file holds this pass's first frames
+            let lead_in = takes
+                .iter()
+                .rev()
+                .find(|t| t.pass + 1 == pass)
+                .and_then(|t| t.audio.as_ref())
+                .filter(|_| lead_in > 0)
+                .map(|a| (a.file_path.clone(), lead_in));
+            Some(TakeAudio {
+                file_path: producer.file_path().clone(),
+                frames: producer.frames_captured().saturating_sub(trailing),
+                lead_in,
+            })
+        }
+        None => None,
+    };
+
+    // Stopping right at a wrap leaves an empty pass
+    let empty = end_beat <= start_beat
+        && buffer.is_empty()
+        && audio
+            .as_ref()
+            .is_none_or(|a| a.frames == 0 && a.lead_in.is_none());
+    if !empty {
+        takes.push(Take {
+            pass,
+            start_beat,
+            end_beat,
+            audio,
+            buffer,
+        });
+    }
+    Ok(())
+}
+
+/// Background thread collecting finished loop passes, like the butler's
+/// transport bridge polls the transport.
+struct TakeCollector {
+    running: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl TakeCollector {
+    fn start(sessions: Sessions, butler_tx: Sender<ButlerCommand>, sample_rate: f64) -> Self {
+        let running = Arc::new(AtomicBool::new(true));
+        let running_clone = Arc::clone(&running);
+
+        let thread = thread::Builder::new()
+            .name("tutti-takes".into())
+            .spawn(move || {
+                while running_clone.load(Ordering::Acquire) {
+                    let looping: Vec<_> = sessions
+                        .iter()
+                        .filter(|entry| entry.mode() == RecordingMode::Loop)
+                        .map(|entry| Arc::clone(entry.value()))
+                        .collect();
+                    for session in looping {
+                        let _ = collect_passes(&session, &butler_tx, sample_rate);
+                    }
+                    thread::sleep(COLLECT_INTERVAL);
+                }
+            })
+            .expect("failed to spawn take collector thread");
+
+        Self {
+            running,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for TakeCollector {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
 impl Default for RecordingManager {
     fn default() -> Self {
         let (tx, _rx) = crossbeam_channel::unbounded();
@@ -550,6 +863,13 @@ impl Default for RecordingManager {
 mod tests {
     use super::*;
 
+    /// Collect finished passes now rather than waiting for the background thread.
+    fn collect(manager: &RecordingManager) {
+        for entry in manager.sessions.iter() {
+            collect_passes(entry.value(), &manager.butler_tx, manager.sample_rate).unwrap();
+        }
+    }
+
     fn create_test_manager() -> RecordingManager {
         let (tx, _rx) = crossbeam_channel::unbounded();
         RecordingManager::new(8, tx, 44100.0)
@@ -728,6 +1048,152 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_loop_recording_splits_takes() {
+        let manager = create_test_manager();
+
+        let config = RecordingConfig::builder()
+            .channel(0)
+            .source(RecordingSource::MidiInput)
+            .mode(RecordingMode::Loop)
+            .loop_range(0.0, 4.0)
+            .build();
+        manager.start_recording_with_config(config, 0.0).unwrap();
+        manager
+            .get_session(0)
+            .unwrap()
+            .set_state(RecordingState::Recording);
+
+        // Pass 0: note held across the wrap is cut at the loop end
+        manager.process_loop_all(0.0, 0);
+        manager.record_midi_note_on(0, 60, 100, 1.0, 0).unwrap();
+        manager.record_midi_note_off(0, 60, 2.0, 0).unwrap();
+        manager.record_midi_note_on(0, 62, 100, 3.0, 0).unwrap();
+        assert_eq!(manager.process_loop_all(3.9, 0), 0);
+        assert_eq!(manager.process_loop_all(0.1, 0), 1);
+        assert_eq!(manager.take_count(0), 1);
+
+        // Pass 1, stopped halfway
+        manager.record_midi_note_on(0, 64, 90, 0.5, 0).unwrap();
+        manager.record_midi_note_off(0, 64, 1.5, 0).unwrap();
+        manager.process_loop_all(2.0, 0);
+
+        let data = manager.stop_recording(0).unwrap();
+        let RecordedData::Takes { lane, .. } = data else {
+            panic!("Expected takes");
+        };
+        assert_eq!(lane.len(), 2);
+
+        let first = lane.get(0).unwrap();
+        assert_eq!((first.start_beat, first.end_beat), (0.0, 4.0));
+        assert_eq!(first.buffer.midi_events.len(), 2);
+        assert_eq!(first.buffer.midi_events[1].note, 62);
+        assert_eq!(first.buffer.midi_events[1].duration, 1.0);
+
+        let second = lane.get(1).unwrap();
+        assert_eq!((second.start_beat, second.end_beat), (0.0, 2.0));
+        assert_eq!(second.buffer.midi_events.len(), 1);
+        assert_eq!(second.buffer.midi_events[0].note, 64);
+    }
+
+    #[test]
+    fn test_take_collector_runs_only_while_looping() {
+        let manager = create_test_manager();
+        let loop_config = |channel| {
+            RecordingConfig::builder()
+                .channel(channel)
+                .mode(RecordingMode::Loop)
+                .loop_range(0.0, 4.0)
+                .build()
+        };
+
+        manager
+            .start_recording_with_config(loop_config(0), 0.0)
+            .unwrap();
+        manager
+            .start_recording_with_config(loop_config(1), 0.0)
+            .unwrap();
+        manager
+            .start_recording(2, RecordingSource::MidiInput, RecordingMode::Replace, 0.0)
+            .unwrap();
+        assert!(manager.collector.lock().is_some());
+
+        manager.stop_recording(0).unwrap();
+        assert!(manager.collector.lock().is_some());
+
+        // The last loop recording stops the thread; others don't need it
+        manager.stop_recording(1).unwrap();
+        assert!(manager.collector.lock().is_none());
+
+        // A new loop recording starts it again
+        manager
+            .start_recording_with_config(loop_config(1), 0.0)
+            .unwrap();
+        assert!(manager.collector.lock().is_some());
+        manager.stop_recording(1).unwrap();
+        assert!(manager.collector.lock().is_none());
+    }
+
+    #[test]
+    fn test_loop_audio_input_rotates_capture_files() {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let manager = RecordingManager::new(8, tx, 44100.0);
+
+        let config = RecordingConfig::builder()
+            .channel(3)
+            .source(RecordingSource::AudioInput)
+            .mode(RecordingMode::Loop)
+            .build();
+        manager.start_recording_with_config(config, 0.0).unwrap();
+        let session = manager.get_session(3).unwrap();
+        session.set_state(RecordingState::Recording);
+
+        manager.process_loop_all(7.5, 0);
+        assert_eq!(manager.process_loop_all(0.0, 0), 1);
+        // The next pass isn't prepared until the wrap has been collected
+        manager.process_loop_all(7.5, 0);
+        collect(&manager);
+        // Wrap seen one 64-frame callback late
+        assert_eq!(manager.process_loop_all(0.0, 64), 1);
+        manager.process_loop_all(1.0, 0);
+        drop(session);
+
+        let RecordedData::Takes { lane, .. } = manager.stop_recording(3).unwrap() else {
+            panic!("Expected takes");
+        };
+        assert_eq!(lane.len(), 3);
+        assert_eq!(lane.get(1).unwrap().end_beat, 7.5);
+        for (pass, take) in lane.takes().iter().enumerate() {
+            let path = take.audio.as_ref().unwrap().file_path.to_string_lossy();
+            assert!(path.ends_with(&format!("_pass{}.wav", pass)), "{path}");
+        }
+        assert!(lane
+            .get(1)
+            .unwrap()
+            .audio
+            .as_ref()
+            .unwrap()
+            .lead_in
+            .is_none());
+        let (lead_in_path, lead_in) = lane.get(2).unwrap().audio.clone().unwrap().lead_in.unwrap();
+        assert_eq!(lead_in, 64);
+        assert_eq!(
+            lead_in_path,
+            lane.get(1).unwrap().audio.as_ref().unwrap().file_path
+        );
+
+        let commands: Vec<_> = rx.try_iter().collect();
+        let registered = commands
+            .iter()
+            .filter(|c| matches!(c, ButlerCommand::RegisterCapture { .. }))
+            .count();
+        let removed = commands
+            .iter()
+            .filter(|c| matches!(c, ButlerCommand::RemoveCapture(_)))
+            .count();
+        assert_eq!((registered, removed), (3, 3));
+    }
+
     #[test]
     fn test_punch_events_in_recorded_data() {
         let manager = create_test_manager();
@@ -7,6 +7,7 @@ pub(crate) mod config;
 pub(crate) mod events;
 pub(crate) mod manager;
 pub(crate) mod session;
+pub(crate) mod takes;
 
 mod automation_lane;
 pub(crate) mod automation_manager;
@@ -20,6 +21,7 @@ pub use events::RecordingBuffer;
 pub use session::{
     PunchEvent, RecordedData, RecordingSession, RecordingState, XRunEvent, XRunType,
 };
+pub use takes::{Comp, CompSegment, Take, TakeAudio, TakeLane};
 
 pub(crate) use automation_lane::{AutomationLane, AutomationRecordingConfig};
 pub(crate) use automation_target::AutomationTarget;
@@ -2,10 +2,13 @@
 
 use super::config::{RecordingConfig, RecordingMode, RecordingSource};
 use super::events::RecordingBuffer;
-use crate::butler::{CaptureBufferProducer, CaptureId};
-use arc_swap::ArcSwap;
+use super::takes::{Take, TakeLane};
+use crate::butler::{CaptureBufferConsumer, CaptureBufferProducer, CaptureId};
+use arc_swap::{ArcSwap, ArcSwapOption};
+use crossbeam_channel::{Receiver, Sender};
+use parking_lot::{Mutex, MutexGuard};
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, AtomicUsize, Ordering};
 use std::sync::Arc;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -48,6 +51,21 @@ impl From<u8> for RecordingState {
     }
 }
 
+/// A [`RecordingMode::Loop`] pass closed on the audio thread, waiting to
+/// become a [`Take`].
+pub(crate) struct FinishedPass {
+    pub pass: usize,
+    pub start_beat: f64,
+    pub end_beat: f64,
+    pub buffer: Arc<RecordingBuffer>,
+    /// Capture swapped out at the wrap, when recording audio input
+    pub capture: Option<Arc<CaptureBufferProducer>>,
+    /// Frames at the start of the pass left at the end of the previous pass's capture
+    pub lead_in: u64,
+    /// Frames at the end of `capture` that belong to the next pass
+    pub trailing: u64,
+}
+
 pub struct RecordingSession {
     state: AtomicU8,
     config: RecordingConfig,
@@ -55,17 +73,35 @@ pub struct RecordingSession {
     preroll_remaining: AtomicU64,
     capture_id: AtomicU64,
     recording_file: ArcSwap<Option<PathBuf>>,
-    capture_producer: ArcSwap<Option<Arc<CaptureBufferProducer>>>,
+    capture_producer: ArcSwapOption<CaptureBufferProducer>,
     is_active: AtomicBool,
     record_safe: AtomicBool,
     punch_events: ArcSwap<Vec<PunchEvent>>,
     xrun_events: ArcSwap<Vec<XRunEvent>>,
+    /// Last transport beat seen, for detecting loop wraps
+    last_beat: AtomicU64,
+    /// Index and start beat of the pass being recorded in [`RecordingMode::Loop`]
+    pass: AtomicUsize,
+    pass_start_beat: AtomicU64,
+    /// Frames of the current pass captured before its wrap reached the audio thread
+    pass_lead_in: AtomicU64,
+    /// Buffer and capture for the next pass, prepared off the audio thread
+    next_buffer: ArcSwapOption<RecordingBuffer>,
+    next_capture: ArcSwapOption<CaptureBufferProducer>,
+    /// Registered with the butler once the audio thread starts writing
+    next_capture_consumer: Mutex<Option<CaptureBufferConsumer>>,
+    finished_tx: Sender<FinishedPass>,
+    finished_rx: Receiver<FinishedPass>,
+    /// Completed passes; never touched by the audio thread
+    takes: Mutex<Vec<Take>>,
 }
 
 impl RecordingSession {
     pub fn new(config: RecordingConfig, sample_rate: f64, start_beat: f64) -> Self {
         let buffer = RecordingBuffer::new(start_beat, sample_rate);
         let preroll_beats = config.preroll_beats;
+        // Each collection drains the queue and prepares one pass, so at most two wait
+        let (finished_tx, finished_rx) = crossbeam_channel::bounded(2);
 
         Self {
             state: AtomicU8::new(RecordingState::Armed as u8),
@@ -74,11 +110,21 @@ impl RecordingSession {
             preroll_remaining: AtomicU64::new(preroll_beats.to_bits()),
             capture_id: AtomicU64::new(0), // 0 = None
             recording_file: ArcSwap::new(Arc::new(None)),
-            capture_producer: ArcSwap::new(Arc::new(None)),
+            capture_producer: ArcSwapOption::empty(),
             is_active: AtomicBool::new(true),
             record_safe: AtomicBool::new(false),
             punch_events: ArcSwap::new(Arc::new(Vec::new())),
             xrun_events: ArcSwap::new(Arc::new(Vec::new())),
+            last_beat: AtomicU64::new(start_beat.to_bits()),
+            pass: AtomicUsize::new(0),
+            pass_start_beat: AtomicU64::new(start_beat.to_bits()),
+            pass_lead_in: AtomicU64::new(0),
+            next_buffer: ArcSwapOption::empty(),
+            next_capture: ArcSwapOption::empty(),
+            next_capture_consumer: Mutex::new(None),
+            finished_tx,
+            finished_rx,
+            takes: Mutex::new(Vec::new()),
         }
     }
 
@@ -204,13 +250,11 @@ impl RecordingSession {
     }
 
     pub fn set_capture_producer(&self, producer: CaptureBufferProducer) {
-        self.capture_producer
-            .store(Arc::new(Some(Arc::new(producer))));
+        self.capture_producer.store(Some(Arc::new(producer)));
     }
 
     pub fn get_capture_producer(&self) -> Option<Arc<CaptureBufferProducer>> {
-        let guard = self.capture_producer.load();
-        guard.as_ref().clone()
+        self.capture_producer.load_full()
     }
 
     pub fn check_punch_in(&self, current_beat: f64) -> bool {
@@ -329,6 +373,126 @@ impl RecordingSession {
     pub fn has_xruns(&self) -> bool {
         !self.xrun_events.load().is_empty()
     }
+
+    /// Call from audio callback in [`RecordingMode::Loop`].
+    ///
+    /// Returns the previous beat when the transport jumped back while recording,
+    /// i.e. the current pass just ended.
+    pub fn update_loop_position(&self, current_beat: f64) -> Option<f64> {
+        let previous = f64::from_bits(
+            self.last_beat
+                .swap(current_beat.to_bits(), Ordering::AcqRel),
+        );
+        let recording = matches!(
+            self.get_state(),
+            RecordingState::Recording | RecordingState::Overdubbing
+        );
+        (self.config.mode == RecordingMode::Loop
+            && recording
+            && !self.is_record_safe()
+            && current_beat < previous)
+            .then_some(previous)
+    }
+
+    pub fn last_beat(&self) -> f64 {
+        f64::from_bits(self.last_beat.load(Ordering::Acquire))
+    }
+
+    /// Index of the pass currently being recorded (0 for the first).
+    pub fn pass_index(&self) -> usize {
+        self.pass.load(Ordering::Acquire)
+    }
+
+    pub fn pass_start_beat(&self) -> f64 {
+        f64::from_bits(self.pass_start_beat.load(Ordering::Acquire))
+    }
+
+    /// Frames of the current pass left at the end of the previous pass's capture.
+    pub fn pass_lead_in(&self) -> u64 {
+        self.pass_lead_in.load(Ordering::Acquire)
+    }
+
+    /// Call from audio callback when [`update_loop_position`](Self::update_loop_position)
+    /// reports a wrap: close the current pass at `end_beat` and start the next
+    /// one at `next_start_beat`. `frames_after_wrap` frames were already
+    /// captured past the loop jump and open the next pass.
+    ///
+    /// Swaps in the buffer and capture prepared for the next pass and queues
+    /// the finished one; nothing is allocated or freed. Returns false, and
+    /// keeps recording into the current pass, while the next pass isn't
+    /// prepared yet.
+    pub fn wrap_pass(&self, end_beat: f64, next_start_beat: f64, frames_after_wrap: u64) -> bool {
+        let needs_capture = self.config.source == RecordingSource::AudioInput;
+        if self.next_buffer.load().is_none()
+            || (needs_capture && self.next_capture.load().is_none())
+        {
+            return false;
+        }
+        let Some(buffer) = self.next_buffer.swap(None) else {
+            return false;
+        };
+        let capture = if needs_capture {
+            self.capture_producer.swap(self.next_capture.swap(None))
+        } else {
+            None
+        };
+
+        let finished = FinishedPass {
+            pass: self.pass.fetch_add(1, Ordering::AcqRel),
+            start_beat: f64::from_bits(
+                self.pass_start_beat
+                    .swap(next_start_beat.to_bits(), Ordering::AcqRel),
+            ),
+            end_beat,
+            buffer: self.buffer.swap(buffer),
+            capture,
+            lead_in: self.pass_lead_in.swap(frames_after_wrap, Ordering::AcqRel),
+            trailing: frames_after_wrap,
+        };
+        let _ = self.finished_tx.try_send(finished);
+        true
+    }
+
+    pub(crate) fn has_next_buffer(&self) -> bool {
+        self.next_buffer.load().is_some()
+    }
+
+    pub(crate) fn set_next_buffer(&self, buffer: RecordingBuffer) {
+        self.next_buffer.store(Some(Arc::new(buffer)));
+    }
+
+    /// True while a prepared capture waits to be swapped in or registered.
+    pub(crate) fn has_next_capture(&self) -> bool {
+        self.next_capture.load().is_some() || self.next_capture_consumer.lock().is_some()
+    }
+
+    pub(crate) fn set_next_capture(
+        &self,
+        producer: CaptureBufferProducer,
+        consumer: CaptureBufferConsumer,
+    ) {
+        *self.next_capture_consumer.lock() = Some(consumer);
+        self.next_capture.store(Some(Arc::new(producer)));
+    }
+
+    /// Consumer of the capture the last wrap swapped in.
+    pub(crate) fn take_next_capture_consumer(&self) -> Option<CaptureBufferConsumer> {
+        self.next_capture_consumer.lock().take()
+    }
+
+    /// Passes closed by [`wrap_pass`](Self::wrap_pass) and not yet collected.
+    pub(crate) fn finished_passes(&self) -> impl Iterator<Item = FinishedPass> + '_ {
+        self.finished_rx.try_iter()
+    }
+
+    /// Completed takes, locked while finished passes are collected.
+    pub(crate) fn lock_takes(&self) -> MutexGuard<'_, Vec<Take>> {
+        self.takes.lock()
+    }
+
+    pub fn get_takes(&self) -> Vec<Take> {
+        self.takes.lock().clone()
+    }
 }
 
 impl std::fmt::Debug for RecordingSession {
@@ -367,6 +531,12 @@ pub enum RecordedData {
         punch_events: Vec<PunchEvent>,
         xrun_events: Vec<XRunEvent>,
     },
+    /// One take per pass of a [`RecordingMode::Loop`] recording.
+    Takes {
+        lane: TakeLane,
+        punch_events: Vec<PunchEvent>,
+        xrun_events: Vec<XRunEvent>,
+    },
 }
 
 impl RecordedData {
@@ -376,6 +546,7 @@ impl RecordedData {
             RecordedData::Audio { punch_events, .. } => punch_events,
             RecordedData::InternalAudio { punch_events, .. } => punch_events,
             RecordedData::Pattern { punch_events, .. } => punch_events,
+            RecordedData::Takes { punch_events, .. } => punch_events,
         }
     }
 
@@ -385,6 +556,7 @@ impl RecordedData {
             RecordedData::Audio { xrun_events, .. } => xrun_events,
             RecordedData::InternalAudio { xrun_events, .. } => xrun_events,
             RecordedData::Pattern { xrun_events, .. } => xrun_events,
+            RecordedData::Takes { xrun_events, .. } => xrun_events,
         }
     }
 
@@ -548,4 +720,57 @@ mod tests {
         assert_eq!(events[1].xrun_type, XRunType::Overrun);
         assert_eq!(events[0].sample_position, 44100);
     }
+
+    #[test]
+    fn test_loop_wrap_detection() {
+        let config = RecordingConfig {
+            mode: RecordingMode::Loop,
+            ..Default::default()
+        };
+        let session = RecordingSession::new(config, 44100.0, 0.0);
+
+        // Armed sessions don't split
+        assert_eq!(session.update_loop_position(3.0), None);
+        assert_eq!(session.update_loop_position(0.0), None);
+
+        session.set_state(RecordingState::Recording);
+        assert_eq!(session.update_loop_position(2.0), None);
+        assert_eq!(session.update_loop_position(3.9), None);
+        assert_eq!(session.update_loop_position(0.0), Some(3.9));
+        assert_eq!(session.last_beat(), 0.0);
+
+        let replace = RecordingSession::new(RecordingConfig::default(), 44100.0, 0.0);
+        replace.set_state(RecordingState::Recording);
+        replace.update_loop_position(4.0);
+        assert_eq!(replace.update_loop_position(0.0), None);
+    }
+
+    #[test]
+    fn test_wrap_pass_needs_prepared_pass() {
+        let config = RecordingConfig {
+            mode: RecordingMode::Loop,
+            ..Default::default()
+        };
+        let session = RecordingSession::new(config, 44100.0, 1.0);
+        session.with_buffer(|b| b.record_midi_note_on(60, 100, 2.0, 0));
+
+        assert!(!session.wrap_pass(4.0, 0.0, 0));
+        assert_eq!(session.pass_index(), 0);
+
+        session.set_next_buffer(RecordingBuffer::new(0.0, 44100.0));
+        assert!(session.wrap_pass(4.0, 0.0, 32));
+        assert_eq!(session.pass_index(), 1);
+        assert_eq!(session.pass_lead_in(), 32);
+        assert_eq!(session.pass_start_beat(), 0.0);
+        assert!(session.get_buffer().is_empty());
+        assert!(!session.has_next_buffer());
+
+        let finished: Vec<_> = session.finished_passes().collect();
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].pass, 0);
+        assert_eq!((finished[0].start_beat, finished[0].end_beat), (1.0, 4.0));
+        assert_eq!(finished[0].buffer.active_note_count(), 1);
+        assert!(finished[0].capture.is_none());
+        assert_eq!((finished[0].lead_in, finished[0].trailing), (0, 32));
+    }
 }
@@ -0,0 +1,672 @@
+//! Take lanes and comping for loop (cycle) recordings.
+//!
+//! In [`RecordingMode::Loop`](super::RecordingMode::Loop) every pass through the
+//! transport loop becomes a [`Take`]. A [`Comp`] then picks ranges from
+//! different takes and joins them with equal-power crossfades.
+//!
+//! # Example
+//!
+//! ```ignore
+//! if let RecordedData::Takes { lane, .. } = sampler.recording().stop_recording(0)? {
+//!     let mut comp = Comp::new().crossfade_ms(15.0);
+//!     comp.select(2, 0.0, 4.0).select(7, 4.0, 6.5).select(3, 6.5, 8.0);
+//!
+//!     comp.render_to_file(&lane, 120.0, "vocal_comp.wav")?;
+//!     sampler.stream_file(0, "vocal_comp.wav").start();
+//! }
+//! ```
+
+use super::events::RecordingBuffer;
+use crate::butler::{read_capture_frames, CaptureFileInfo};
+use crate::error::{Error, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tutti_core::Wave;
+
+/// Audio input captured to disk for one pass.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TakeAudio {
+    pub file_path: PathBuf,
+    /// Frames of the pass at the start of `file_path`
+    pub frames: u64,
+    /// The pass's opening frames, captured into the end of the previous
+    /// pass's file before the loop wrap was seen: that file and the frame count
+    pub lead_in: Option<(PathBuf, u64)>,
+}
+
+/// One pass through the loop.
+#[derive(Debug, Clone)]
+pub struct Take {
+    /// 0-based pass index
+    pub pass: usize,
+    pub start_beat: f64,
+    pub end_beat: f64,
+    /// Audio input file, when recording [`RecordingSource::AudioInput`](super::RecordingSource::AudioInput)
+    pub audio: Option<TakeAudio>,
+    /// MIDI events (and internal audio) recorded during the pass
+    pub buffer: RecordingBuffer,
+}
+
+impl Take {
+    pub fn duration_beats(&self) -> f64 {
+        (self.end_beat - self.start_beat).max(0.0)
+    }
+
+    /// Stereo frames of the pass: the captured file, or the buffer's internal audio.
+    ///
+    /// Captures are read with the capture reader, which handles every
+    /// container capture writes, RF64 and Wave64 included.
+    fn load_frames(&self) -> Result<Vec<(f32, f32)>> {
+        if let Some(audio) = &self.audio {
+            let mut frames = Vec::new();
+            if let Some((path, lead_in)) = &audio.lead_in {
+                let length = CaptureFileInfo::read(path)?.frames;
+                frames.extend(read_capture_frames(
+                    path,
+                    length.saturating_sub(*lead_in)..length,
+                )?);
+            }
+            frames.extend(read_capture_frames(&audio.file_path, 0..audio.frames)?);
+            return Ok(frames);
+        }
+        Ok(self
+            .buffer
+            .audio_chunks
+            .iter()
+            .flat_map(|chunk| chunk.samples.chunks_exact(2).map(|f| (f[0], f[1])))
+            .collect())
+    }
+}
+
+/// Takes recorded on one channel, in pass order.
+#[derive(Debug, Clone)]
+pub struct TakeLane {
+    channel_index: usize,
+    sample_rate: f64,
+    takes: Vec<Take>,
+}
+
+impl TakeLane {
+    pub fn new(channel_index: usize, sample_rate: f64) -> Self {
+        Self {
+            channel_index,
+            sample_rate,
+            takes: Vec::new(),
+        }
+    }
+
+    pub fn channel_index(&self) -> usize {
+        self.channel_index
+    }
+
+    pub fn sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+
+    pub fn takes(&self) -> &[Take] {
+        &self.takes
+    }
+
+    /// Take recorded on pass `pass`.
+    pub fn get(&self, pass: usize) -> Option<&Take> {
+        self.takes.iter().find(|t| t.pass == pass)
+    }
+
+    /// Remove a take. Remaining takes keep their pass index.
+    pub fn remove(&mut self, pass: usize) -> Option<Take> {
+        let index = self.takes.iter().position(|t| t.pass == pass)?;
+        Some(self.takes.remove(index))
+    }
+
+    pub fn len(&self) -> usize {
+        self.takes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.takes.is_empty()
+    }
+
+    pub(crate) fn push(&mut self, take: Take) {
+        self.takes.push(take);
+    }
+}
+
+/// A beat range of one take used in a [`Comp`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CompSegment {
+    pub pass: usize,
+    pub start_beat: f64,
+    pub end_beat: f64,
+}
+
+/// Ranges picked from different takes of a [`TakeLane`].
+///
+/// Later selections replace earlier ones where they overlap, like swiping
+/// across take lanes. Touching segments from different takes are joined with
+/// an equal-power crossfade centred on the edit point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comp {
+    segments: Vec<CompSegment>,
+    crossfade_ms: f32,
+}
+
+impl Default for Comp {
+    fn default() -> Self {
+        Self {
+            segments: Vec::new(),
+            crossfade_ms: 10.0,
+        }
+    }
+}
+
+impl Comp {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Crossfade length at each edit point (default 10 ms).
+    pub fn crossfade_ms(mut self, ms: f32) -> Self {
+        self.crossfade_ms = ms.max(0.0);
+        self
+    }
+
+    /// Use take `pass` between `start_beat` and `end_beat`.
+    pub fn select(&mut self, pass: usize, start_beat: f64, end_beat: f64) -> &mut Self {
+        if end_beat > start_beat {
+            self.cut(start_beat, end_beat);
+            self.segments.push(CompSegment {
+                pass,
+                start_beat,
+                end_beat,
+            });
+            self.normalize();
+        }
+        self
+    }
+
+    /// Leave a range silent.
+    pub fn clear_range(&mut self, start_beat: f64, end_beat: f64) -> &mut Self {
+        if end_beat > start_beat {
+            self.cut(start_beat, end_beat);
+        }
+        self
+    }
+
+    /// Segments in beat order, without overlaps.
+    pub fn segments(&self) -> &[CompSegment] {
+        &self.segments
+    }
+
+    /// Take selected at `beat`.
+    pub fn pass_at(&self, beat: f64) -> Option<usize> {
+        self.segments
+            .iter()
+            .find(|s| (s.start_beat..s.end_beat).contains(&beat))
+            .map(|s| s.pass)
+    }
+
+    /// MIDI of the selected ranges, taking each event from the take selected at its beat.
+    pub fn midi(&self, lane: &TakeLane) -> Result<RecordingBuffer> {
+        let start = self.segments.first().map_or(0.0, |s| s.start_beat);
+        let mut buffer = RecordingBuffer::new(start, lane.sample_rate);
+
+        for segment in &self.segments {
+            let take = Self::take(lane, segment.pass)?;
+            let range = segment.start_beat..segment.end_beat;
+            let source = &take.buffer;
+
+            buffer.midi_events.extend(
+                source
+                    .midi_events
+                    .iter()
+                    .filter(|e| range.contains(&e.start_beat))
+                    .cloned(),
+            );
+            buffer.cc_events.extend(
+                source
+                    .cc_events
+                    .iter()
+                    .filter(|e| range.contains(&e.beat))
+                    .cloned(),
+            );
+            buffer.pitch_bend_events.extend(
+                source
+                    .pitch_bend_events
+                    .iter()
+                    .filter(|e| range.contains(&e.beat))
+                    .cloned(),
+            );
+            buffer.pressure_events.extend(
+                source
+                    .pressure_events
+                    .iter()
+                    .filter(|e| range.contains(&e.beat))
+                    .cloned(),
+            );
+            buffer.program_change_events.extend(
+                source
+                    .program_change_events
+                    .iter()
+                    .filter(|e| range.contains(&e.beat))
+                    .cloned(),
+            );
+            buffer.current_beat = segment.end_beat;
+        }
+
+        buffer
+            .midi_events
+            .sort_by(|a, b| a.start_beat.total_cmp(&b.start_beat));
+        Ok(buffer)
+    }
+
+    /// Render the comp's audio as stereo, starting at the first selected beat.
+    ///
+    /// `tempo` is the BPM the takes were recorded at, which must not change
+    /// inside the loop. Samples are copied from each take at whole-frame
+    /// offsets, so the takes are not resampled.
+    pub fn render(&self, lane: &TakeLane, tempo: f64) -> Result<Wave> {
+        let (Some(first), Some(last)) = (self.segments.first(), self.segments.last()) else {
+            return Err(Error::Recording("Comp has no selected ranges".to_string()));
+        };
+        if !tempo.is_finite() || tempo <= 0.0 {
+            return Err(Error::Recording(format!("Invalid comp tempo {tempo}")));
+        }
+        let comp_start = first.start_beat;
+
+        let mut sources: HashMap<usize, (&Take, Vec<(f32, f32)>)> = HashMap::new();
+        for segment in &self.segments {
+            if !sources.contains_key(&segment.pass) {
+                let take = Self::take(lane, segment.pass)?;
+                sources.insert(segment.pass, (take, take.load_frames()?));
+            }
+        }
+
+        let frames_per_beat = lane.sample_rate * 60.0 / tempo;
+        let to_frame = |beat: f64| (beat * frames_per_beat).round() as i64;
+
+        let total = to_frame(last.end_beat - comp_start).max(0) as usize;
+        let fade = (self.crossfade_ms as f64 * lane.sample_rate / 1000.0).round() as i64;
+        let half = fade / 2;
+        let mut output = vec![(0.0f32, 0.0f32); total];
+
+        for (i, segment) in self.segments.iter().enumerate() {
+            let joined_prev = i > 0 && self.segments[i - 1].end_beat >= segment.start_beat;
+            let joined_next = self
+                .segments
+                .get(i + 1)
+                .is_some_and(|next| next.start_beat <= segment.end_beat);
+
+            let start = to_frame(segment.start_beat - comp_start);
+            let end = to_frame(segment.end_beat - comp_start);
+            let from = if joined_prev { start - half } else { start };
+            let to = if joined_next { end + half } else { end };
+
+            let (take, frames) = &sources[&segment.pass];
+            let offset = to_frame(comp_start - take.start_beat);
+
+            for t in from.max(0)..to.min(total as i64) {
+                let mut gain = 1.0;
+                if joined_prev && fade > 0 && t < start + half {
+                    gain *= fade_in((t - (start - half)) as f64 / fade as f64);
+                }
+                if joined_next && fade > 0 && t >= end - half {
+                    gain *= fade_out((t - (end - half)) as f64 / fade as f64);
+                }
+                let (l, r) = frame_at(frames, t + offset);
+                let out = &mut output[t as usize];
+                out.0 += l * gain;
+                out.1 += r * gain;
+            }
+        }
+
+        let mut wave = Wave::new(2, lane.sample_rate);
+        for frame in output {
+            wave.push(frame);
+        }
+        Ok(wave)
+    }
+
+    /// Render to a 32-bit float WAV, e.g. for streaming with
+    /// [`SamplerSystem::stream_file`](crate::SamplerSystem::stream_file).
+    pub fn render_to_file(
+        &self,
+        lane: &TakeLane,
+        tempo: f64,
+        path: impl AsRef<Path>,
+    ) -> Result<()> {
+        let wave = self.render(lane, tempo)?;
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: lane.sample_rate as u32,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let mut writer = hound::WavWriter::create(path, spec)?;
+        for i in 0..wave.len() {
+            writer.write_sample(wave.at(0, i))?;
+            writer.write_sample(wave.at(1, i))?;
+        }
+        writer.finalize()?;
+        Ok(())
+    }
+
+    fn take(lane: &TakeLane, pass: usize) -> Result<&Take> {
+        lane.get(pass)
+            .ok_or_else(|| Error::Recording(format!("No take for pass {}", pass)))
+    }
+
+    /// Trim or split existing segments out of `start_beat..end_beat`.
+    fn cut(&mut self, start_beat: f64, end_beat: f64) {
+        let mut kept = Vec::with_capacity(self.segments.len() + 1);
+        for segment in self.segments.drain(..) {
+            if segment.end_beat <= start_beat || segment.start_beat >= end_beat {
+                kept.push(segment);
+                continue;
+            }
+            if segment.start_beat < start_beat {
+                kept.push(CompSegment {
+                    end_beat: start_beat,
+                    ..segment
+                });
+            }
+            if segment.end_beat > end_beat {
+                kept.push(CompSegment {
+                    start_beat: end_beat,
+                    ..segment
+                });
+            }
+        }
+        self.segments = kept;
+    }
+
+    /// Sort and merge touching segments of the same take.
+    fn normalize(&mut self) {
+        self.segments
+            .sort_by(|a, b| a.start_beat.total_cmp(&b.start_beat));
+        let mut merged: Vec<CompSegment> = Vec::with_capacity(self.segments.len());
+        for segment in self.segments.drain(..) {
+            match merged.last_mut() {
+                Some(prev) if prev.pass == segment.pass && prev.end_beat >= segment.start_beat => {
+                    prev.end_beat = prev.end_beat.max(segment.end_beat);
+                }
+                _ => merged.push(segment),
+            }
+        }
+        self.segments = merged;
+    }
+}
+
+#[inline]
+fn fade_in(x: f64) -> f32 {
+    (x.clamp(0.0, 1.0) * std::f64::consts::FRAC_PI_2).sin() as f32
+}
+
+#[inline]
+fn fade_out(x: f64) -> f32 {
+    (x.clamp(0.0, 1.0) * std::f64::consts::FRAC_PI_2).cos() as f32
+}
+
+/// Frame `index` of a take, silent outside it.
+#[inline]
+fn frame_at(frames: &[(f32, f32)], index: i64) -> (f32, f32) {
+    usize::try_from(index)
+        .ok()
+        .and_then(|index| frames.get(index).copied())
+        .unwrap_or((0.0, 0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Internal-audio take of constant `value`, 100 frames per beat.
+    fn take(pass: usize, start_beat: f64, end_beat: f64, value: f32) -> Take {
+        let mut buffer = RecordingBuffer::new(start_beat, 100.0);
+        let frames = ((end_beat - start_beat) * 100.0) as usize;
+        for i in 0..frames {
+            buffer.record_audio(value, -value, start_beat + i as f64 / 100.0);
+        }
+        Take {
+            pass,
+            start_beat,
+            end_beat,
+            audio: None,
+            buffer,
+        }
+    }
+
+    fn lane() -> TakeLane {
+        let mut lane = TakeLane::new(0, 100.0);
+        lane.push(take(0, 0.0, 4.0, 0.1));
+        lane.push(take(1, 0.0, 4.0, 0.2));
+        lane.push(take(2, 0.0, 4.0, 0.3));
+        lane
+    }
+
+    #[test]
+    fn test_select_replaces_overlapping_ranges() {
+        let mut comp = Comp::new();
+        comp.select(0, 0.0, 4.0)
+            .select(1, 1.0, 2.0)
+            .select(2, 1.5, 3.0);
+
+        let ranges: Vec<_> = comp
+            .segments()
+            .iter()
+            .map(|s| (s.pass, s.start_beat, s.end_beat))
+            .collect();
+        assert_eq!(
+            ranges,
+            vec![(0, 0.0, 1.0), (1, 1.0, 1.5), (2, 1.5, 3.0), (0, 3.0, 4.0)]
+        );
+        assert_eq!(comp.pass_at(1.2), Some(1));
+        assert_eq!(comp.pass_at(3.5), Some(0));
+
+        // Re-selecting the neighbouring take merges the pieces back together
+        comp.select(0, 1.0, 3.0);
+        assert_eq!(comp.segments().len(), 1);
+
+        comp.clear_range(2.0, 3.0);
+        assert_eq!(comp.segments().len(), 2);
+        assert_eq!(comp.pass_at(2.5), None);
+    }
+
+    #[test]
+    fn test_render_crossfades_between_takes() {
+        let lane = lane();
+        let mut comp = Comp::new().crossfade_ms(200.0);
+        comp.select(0, 0.0, 2.0).select(2, 2.0, 4.0);
+
+        let wave = comp.render(&lane, 60.0).unwrap();
+        assert_eq!(wave.len(), 400);
+        assert_eq!(wave.channels(), 2);
+
+        // Away from the edit each take plays at unity
+        assert!((wave.at(0, 50) - 0.1).abs() < 1e-6);
+        assert!((wave.at(1, 50) + 0.1).abs() < 1e-6);
+        assert!((wave.at(0, 350) - 0.3).abs() < 1e-6);
+
+        // 20-frame equal-power fade centred on frame 200
+        let mid = wave.at(0, 200);
+        let expected = (0.1 + 0.3) * std::f32::consts::FRAC_1_SQRT_2;
+        assert!((mid - expected).abs() < 1e-3, "{mid} vs {expected}");
+        assert!((wave.at(0, 189) - 0.1).abs() < 1e-6);
+        assert!((wave.at(0, 211) - 0.3).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_render_gaps_and_partial_takes_are_silent() {
+        let mut lane = lane();
+        // Final pass stopped halfway through the loop
+        lane.push(take(3, 0.0, 1.0, 0.4));
+
+        let mut comp = Comp::new().crossfade_ms(0.0);
+        comp.select(1, 0.0, 1.0).select(3, 2.0, 3.0);
+
+        let wave = comp.render(&lane, 60.0).unwrap();
+        assert_eq!(wave.len(), 300);
+        assert!((wave.at(0, 50) - 0.2).abs() < 1e-6);
+        assert_eq!(wave.at(0, 150), 0.0);
+        assert_eq!(wave.at(0, 250), 0.0);
+    }
+
+    #[test]
+    fn test_render_errors() {
+        let lane = lane();
+        assert!(Comp::new().render(&lane, 60.0).is_err());
+
+        let mut comp = Comp::new();
+        comp.select(9, 0.0, 1.0);
+        assert!(comp.render(&lane, 60.0).is_err());
+
+        let mut comp = Comp::new();
+        comp.select(0, 0.0, 1.0);
+        assert!(comp.render(&lane, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_render_copies_samples_exactly() {
+        // Nyquist-rate signal: any interpolation between frames would dull it
+        let mut buffer = RecordingBuffer::new(0.0, 100.0);
+        for i in 0..400 {
+            let value = if i % 2 == 0 { 0.5 } else { -0.5 };
+            buffer.record_audio(value, value, i as f64 / 100.0);
+        }
+        let mut lane = TakeLane::new(0, 100.0);
+        lane.push(Take {
+            pass: 0,
+            start_beat: 0.0,
+            end_beat: 4.0,
+            audio: None,
+            buffer,
+        });
+
+        // 70 BPM at 100 Hz is 85.71 frames per beat, so beat 1 falls between frames
+        let mut comp = Comp::new().crossfade_ms(0.0);
+        comp.select(0, 1.0, 3.0);
+        let wave = comp.render(&lane, 70.0).unwrap();
+        assert_eq!(wave.len(), 171);
+        for i in 0..wave.len() {
+            let expected = if (i + 86) % 2 == 0 { 0.5 } else { -0.5 };
+            assert_eq!(wave.at(0, i), expected, "frame {i}");
+        }
+    }
+
+    #[test]
+    fn test_comp_midi() {
+        let mut lane = TakeLane::new(0, 44100.0);
+        for pass in 0..2 {
+            let mut t = take(pass, 0.0, 4.0, 0.0);
+            t.buffer.record_midi_note_on(60 + pass as u8, 100, 0.5, 0);
+            t.buffer.record_midi_note_off(60 + pass as u8, 1.0, 0);
+            t.buffer.record_midi_note_on(70 + pass as u8, 100, 2.5, 0);
+            t.buffer.record_midi_note_off(70 + pass as u8, 3.0, 0);
+            t.buffer.record_midi_cc(0, 1, 64, 2.0);
+            lane.push(t);
+        }
+
+        let mut comp = Comp::new();
+        comp.select(0, 0.0, 2.0).select(1, 2.0, 4.0);
+        let midi = comp.midi(&lane).unwrap();
+
+        let notes: Vec<u8> = midi.midi_events.iter().map(|e| e.note).collect();
+        assert_eq!(notes, vec![60, 71]);
+        assert_eq!(midi.cc_events.len(), 1);
+        assert_eq!(midi.duration_beats(), 4.0);
+    }
+
+    #[test]
+    fn test_render_splices_lead_in_from_previous_file() {
+        let dir = tempfile::tempdir().unwrap();
+        // One continuous ramp, 400 frames per pass
+        let write = |name: &str, range: std::ops::Range<u32>| {
+            let path = dir.path().join(name);
+            let spec = hound::WavSpec {
+                channels: 2,
+                sample_rate: 100,
+                bits_per_sample: 32,
+                sample_format: hound::SampleFormat::Float,
+            };
+            let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+            for n in range {
+                writer.write_sample(n as f32 / 1000.0).unwrap();
+                writer.write_sample(0.0f32).unwrap();
+            }
+            writer.finalize().unwrap();
+            path
+        };
+        // The wrap was seen 30 frames late, so pass 0's file ends with pass 1's start
+        let pass0 = write("pass0.wav", 0..430);
+        let pass1 = write("pass1.wav", 430..800);
+
+        let mut lane = TakeLane::new(0, 100.0);
+        for (pass, file_path, frames, lead_in) in [
+            (0, pass0.clone(), 400, None),
+            (1, pass1, 370, Some((pass0, 30))),
+        ] {
+            let mut take = take(pass, 0.0, 4.0, 0.0);
+            take.audio = Some(TakeAudio {
+                file_path,
+                frames,
+                lead_in,
+            });
+            lane.push(take);
+        }
+
+        let mut comp = Comp::new().crossfade_ms(0.0);
+        comp.select(0, 0.0, 2.0).select(1, 2.0, 4.0);
+        let wave = comp.render(&lane, 60.0).unwrap();
+        assert_eq!(wave.len(), 400);
+        for (frame, expected) in [(0, 0.0), (199, 0.199), (200, 0.6), (399, 0.799)] {
+            assert!(
+                (wave.at(0, frame) - expected).abs() < 1e-6,
+                "frame {frame}: {}",
+                wave.at(0, frame)
+            );
+        }
+    }
+
+    #[test]
+    fn test_render_reads_wave64_takes() {
+        use crate::butler::{CaptureFormat, CaptureSampleFormat, CaptureWriter};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pass0.w64");
+        let mut writer = CaptureWriter::create(
+            &path,
+            100.0,
+            2,
+            CaptureFormat::w64(CaptureSampleFormat::Float32),
+        )
+        .unwrap();
+        let frames: Vec<_> = (0..400).map(|n| (n as f32 / 1000.0, 0.0)).collect();
+        writer.write_frames(&frames).unwrap();
+        writer.finalize().unwrap();
+
+        let mut lane = TakeLane::new(0, 100.0);
+        let mut take = take(0, 0.0, 4.0, 0.0);
+        take.audio = Some(TakeAudio {
+            file_path: path,
+            frames: 400,
+            lead_in: None,
+        });
+        lane.push(take);
+
+        let mut comp = Comp::new();
+        comp.select(0, 1.0, 3.0);
+        let wave = comp.render(&lane, 60.0).unwrap();
+        assert_eq!(wave.len(), 200);
+        assert_eq!(wave.at(0, 0), 0.1);
+        assert_eq!(wave.at(0, 199), 0.299);
+    }
+
+    #[test]
+    fn test_lane_lookup_by_pass() {
+        let mut lane = lane();
+        assert_eq!(lane.len(), 3);
+        assert!(lane.remove(1).is_some());
+        assert!(lane.get(1).is_none());
+        assert_eq!(lane.get(2).unwrap().pass, 2);
+        assert!(lane.remove(1).is_none());
+    }
+}
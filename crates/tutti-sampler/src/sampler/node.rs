@@ -8,6 +8,7 @@ use std::sync::Arc;
 use tutti_core::{AudioUnit, BufferMut, BufferRef, SignalFrame, TransportReader, Wave};
 
 use crate::butler::LoopCrossfade;
+use crate::resample::{Interpolator, ResampleQuality};
 
 /// In-memory sample playback with optional loop crossfade.
 ///
@@ -32,6 +33,9 @@ pub struct SamplerUnit {
     /// SRC ratio: file_sample_rate / session_sample_rate. 1.0 = no conversion.
     src_ratio: f32,
 
+    /// Single interpolation stage for both rate conversion and speed.
+    interpolator: Interpolator,
+
     /// Loop range (start, end) in samples. If None, loops entire sample.
     loop_range: Option<(u64, u64)>,
 
@@ -61,6 +65,7 @@ impl Clone for SamplerUnit {
             speed: self.speed,
             sample_rate: self.sample_rate,
             src_ratio: self.src_ratio,
+            interpolator: self.interpolator,
             loop_range: self.loop_range,
             crossfade: self.crossfade.clone(),
             transport: self.transport.clone(),
@@ -82,6 +87,7 @@ impl SamplerUnit {
             speed: 1.0,
             sample_rate,
             src_ratio: 1.0,
+            interpolator: Interpolator::new(ResampleQuality::default()),
             loop_range: None,
             crossfade: None,
             transport: None,
@@ -101,6 +107,7 @@ impl SamplerUnit {
             speed,
             sample_rate,
             src_ratio: 1.0,
+            interpolator: Interpolator::new(ResampleQuality::default()),
             loop_range: None,
             crossfade: None,
             transport: None,
@@ -125,6 +132,7 @@ impl SamplerUnit {
             speed: 1.0,
             sample_rate,
             src_ratio: 1.0,
+            interpolator: Interpolator::new(ResampleQuality::default()),
             loop_range: None,
             crossfade: None,
             transport: Some(transport),
@@ -209,6 +217,9 @@ impl SamplerUnit {
     }
 
     /// Computes SRC ratio from file vs session sample rate.
+    ///
+    /// Playback then steps through the file at `speed * ratio`, interpolated
+    /// with the selected [`ResampleQuality`].
     pub fn set_session_sample_rate(&mut self, session_rate: f64) {
         let file_rate = self.wave.sample_rate();
         self.src_ratio = if (file_rate - session_rate).abs() < 0.01 {
@@ -218,6 +229,15 @@ impl SamplerUnit {
         };
     }
 
+    /// Set the interpolation used for rate conversion and speed (default: [`ResampleQuality::Sinc`]).
+    pub fn set_resample_quality(&mut self, quality: ResampleQuality) {
+        self.interpolator = Interpolator::new(quality);
+    }
+
+    pub fn resample_quality(&self) -> ResampleQuality {
+        self.interpolator.quality()
+    }
+
     pub fn set_loop_range(&mut self, loop_start: u64, loop_end: u64, crossfade_samples: usize) {
         self.loop_range = Some((loop_start, loop_end));
         self.looping.store(true, Ordering::Relaxed);
@@ -246,34 +266,36 @@ impl SamplerUnit {
     }
 
     #[inline]
-    fn get_sample_raw(&self, position: f64) -> (f32, f32) {
-        let len = self.wave.len() as f64;
-        if position >= len {
+    fn frame(&self, index: isize) -> (f32, f32) {
+        let Ok(idx) = usize::try_from(index) else {
+            return (0.0, 0.0);
+        };
+        if idx >= self.wave.len() {
             return (0.0, 0.0);
         }
-
-        let idx = position.floor() as usize;
-        let frac = position.fract() as f32;
-
-        let (l0, r0) = if self.wave.channels() >= 2 {
+        if self.wave.channels() >= 2 {
             (self.wave.at(0, idx), self.wave.at(1, idx))
         } else {
             let mono = self.wave.at(0, idx);
             (mono, mono)
-        };
-
-        let next_idx = (idx + 1).min(self.wave.len().saturating_sub(1));
-        let (l1, r1) = if self.wave.channels() >= 2 {
-            (self.wave.at(0, next_idx), self.wave.at(1, next_idx))
-        } else {
-            let mono = self.wave.at(0, next_idx);
-            (mono, mono)
-        };
+        }
+    }
 
-        let left = l0 + (l1 - l0) * frac;
-        let right = r0 + (r1 - r0) * frac;
+    #[inline]
+    fn get_sample_raw(&self, position: f64) -> (f32, f32) {
+        let len = self.wave.len() as f64;
+        if position >= len {
+            return (0.0, 0.0);
+        }
 
-        (left, right)
+        let base = position.floor() as isize;
+        let step = (self.speed * self.src_ratio).abs() as f64;
+        // Neither rate conversion nor varispeed: play the frames as they are
+        if step == 1.0 {
+            return self.frame(base);
+        }
+        self.interpolator
+            .interpolate(|k| self.frame(base + k), position.fract() as f32, step)
     }
 
     #[inline]
@@ -296,11 +318,20 @@ impl SamplerUnit {
         if self.duration_beats > 0.0 && beat_offset >= self.duration_beats {
             return None;
         }
-        let tempo = transport.tempo() as f64;
-        if tempo <= 0.0 {
-            return None;
-        }
-        let seconds_offset = beat_offset * 60.0 / tempo;
+        // Integrate tempo automation when there is any; otherwise the live
+        // tempo (which `set_tempo` changes without touching the map) rules.
+        let seconds_offset = match transport.tempo_map() {
+            Some(map) if map.has_tempo_changes() => {
+                map.beats_to_seconds(current_beat) - map.beats_to_seconds(self.start_beat)
+            }
+            _ => {
+                let tempo = transport.tempo() as f64;
+                if tempo <= 0.0 {
+                    return None;
+                }
+                beat_offset * 60.0 / tempo
+            }
+        };
         Some(seconds_offset * self.wave.sample_rate())
     }
 }
@@ -515,22 +546,16 @@ impl AudioUnit for SamplerUnit {
 mod streaming {
     use super::*;
     use crate::butler::{RegionBufferConsumer, SharedStreamState};
+    use crate::resample::InterpolationHistory;
     use parking_lot::Mutex;
 
-    /// Cubic Hermite interpolation for smooth varispeed playback.
-    #[inline]
-    pub(super) fn cubic_hermite(y0: f32, y1: f32, y2: f32, y3: f32, t: f32) -> f32 {
-        let c0 = y1;
-        let c1 = 0.5 * (y2 - y0);
-        let c2 = y0 - 2.5 * y1 + 2.0 * y2 - 0.5 * y3;
-        let c3 = 0.5 * (y3 - y0) + 1.5 * (y1 - y2);
-        ((c3 * t + c2) * t + c1) * t + c0
-    }
-
     /// 8192 frames at 4x speed with interpolation padding.
     const MAX_FETCH_SAMPLES: usize = 8192 * 4 + 8;
 
     /// Disk streaming sampler with varispeed, seeking, and crossfade support.
+    ///
+    /// The butler delivers frames already at the session rate, so this only
+    /// interpolates for varispeed; unity speed skips interpolation entirely.
     pub struct StreamingSamplerUnit {
         consumer: Arc<Mutex<RegionBufferConsumer>>,
         playing: AtomicBool,
@@ -544,8 +569,20 @@ mod streaming {
         /// Fractional position for sub-sample interpolation.
         fractional_pos: f64,
 
-        /// History buffer for cubic Hermite interpolation (last 4 samples).
-        history: [(f32, f32); 4],
+        /// Recent ring buffer frames; the interpolation point trails the newest by `lookahead`.
+        history: InterpolationHistory,
+
+        /// Varispeed interpolation.
+        interpolator: Interpolator,
+        /// Frames the history runs ahead of the interpolation point. Never
+        /// less than the interpolator's lookahead; switching to a shorter
+        /// kernel keeps the lead so the position doesn't jump.
+        lookahead: usize,
+        /// Frames still to pull into the history before output resumes, so
+        /// the interpolation point lands on the stream's first frame.
+        preroll: usize,
+        /// Last seek seen in the shared state.
+        seek_generation: u64,
 
         /// Pre-allocated scratch buffer for fetched samples (RT-safe).
         fetch_scratch: Vec<(f32, f32)>,
@@ -560,7 +597,11 @@ mod streaming {
                 sample_rate: self.sample_rate,
                 shared_state: self.shared_state.clone(),
                 fractional_pos: self.fractional_pos,
-                history: self.history,
+                history: self.history.clone(),
+                interpolator: self.interpolator,
+                lookahead: self.lookahead,
+                preroll: self.preroll,
+                seek_generation: self.seek_generation,
                 fetch_scratch: Vec::with_capacity(MAX_FETCH_SAMPLES),
             }
         }
@@ -571,6 +612,8 @@ mod streaming {
             consumer: Arc<Mutex<RegionBufferConsumer>>,
             shared_state: Arc<SharedStreamState>,
         ) -> Self {
+            let interpolator = Interpolator::new(shared_state.resample_quality());
+            let seek_generation = shared_state.seek_generation();
             Self {
                 consumer,
                 playing: AtomicBool::new(true),
@@ -578,7 +621,11 @@ mod streaming {
                 sample_rate: 44100.0,
                 shared_state: Some(shared_state),
                 fractional_pos: 0.0,
-                history: [(0.0, 0.0); 4],
+                history: InterpolationHistory::new(),
+                interpolator,
+                lookahead: interpolator.lookahead(),
+                preroll: interpolator.lookahead(),
+                seek_generation,
                 fetch_scratch: Vec::with_capacity(MAX_FETCH_SAMPLES),
             }
         }
@@ -599,17 +646,82 @@ mod streaming {
             self.gain = gain;
         }
 
+        /// Restart interpolation at the next frame the stream delivers.
+        ///
+        /// The history is pre-rolled by the kernel's lookahead before output
+        /// resumes, so playback isn't delayed. Seeks through the shared state
+        /// do this on their own.
+        pub fn reset_interpolation(&mut self) {
+            self.fractional_pos = 0.0;
+            self.history.reset();
+            self.lookahead = self.interpolator.lookahead();
+            self.preroll = self.lookahead;
+        }
+
+        /// Restart interpolation after a seek published through the shared state.
         #[inline]
-        fn shift_history(&mut self) {
-            self.history[0] = self.history[1];
-            self.history[1] = self.history[2];
-            self.history[2] = self.history[3];
+        fn sync_seek(&mut self) {
+            let generation = match self.shared_state {
+                Some(ref state) => state.seek_generation(),
+                None => return,
+            };
+            if generation != self.seek_generation {
+                self.seek_generation = generation;
+                self.reset_interpolation();
+            }
         }
 
-        /// Call after seek to reset interpolation state.
-        pub fn reset_interpolation(&mut self) {
-            self.fractional_pos = 0.0;
-            self.history = [(0.0, 0.0); 4];
+        /// Pick up quality changes published through the shared stream state.
+        ///
+        /// Kernel tables are built when the quality is set, never here.
+        #[inline]
+        fn sync_resample_quality(&mut self) {
+            if let Some(ref state) = self.shared_state {
+                let quality = state.resample_quality();
+                if quality != self.interpolator.quality() {
+                    self.interpolator = Interpolator::new(quality);
+                    // A longer kernel reads further ahead of the same point
+                    let lookahead = self.interpolator.lookahead();
+                    if lookahead > self.lookahead {
+                        self.preroll += lookahead - self.lookahead;
+                        self.lookahead = lookahead;
+                    }
+                }
+            }
+        }
+
+        /// Pull the frames the history still needs ahead of the interpolation
+        /// point. Returns false while the stream can't supply them yet.
+        #[inline]
+        fn fill_preroll(
+            history: &mut InterpolationHistory,
+            preroll: &mut usize,
+            gain: f32,
+            consumer: &mut RegionBufferConsumer,
+        ) -> bool {
+            while *preroll > 0 {
+                let Some((left, right)) = consumer.read() else {
+                    return false;
+                };
+                history.push((left * gain, right * gain));
+                *preroll -= 1;
+            }
+            true
+        }
+
+        /// Interpolate at the current fractional position for a combined step.
+        #[inline]
+        fn interpolate(&self, step: f64) -> (f32, f32) {
+            let lookahead = self.lookahead;
+            // No varispeed: play the frames as they are
+            if step == 1.0 {
+                return self.history.at(lookahead, 0);
+            }
+            self.interpolator.interpolate(
+                |k| self.history.at(lookahead, k),
+                self.fractional_pos as f32,
+                step,
+            )
         }
 
         fn process_normal_samples(&mut self, size: usize, offset: usize, output: &mut BufferMut) {
@@ -617,25 +729,27 @@ mod streaming {
                 return;
             }
 
-            let src_ratio = self
-                .shared_state
-                .as_ref()
-                .map(|s| s.src_ratio())
-                .unwrap_or(1.0) as f64;
+            self.sync_seek();
+            self.sync_resample_quality();
+
             let base_speed = self
                 .shared_state
                 .as_ref()
                 .map(|s| s.effective_speed())
-                .unwrap_or(1.0) as f64
-                * src_ratio;
+                .unwrap_or(1.0) as f64;
 
             let samples_needed = (size as f64 * base_speed).ceil() as usize + 4;
 
             // RT-safe: clear and reuse pre-allocated scratch buffer
             self.fetch_scratch.clear();
 
+            let mut primed = self.preroll == 0;
             if let Some(mut guard) = self.consumer.try_lock() {
-                for _ in 0..samples_needed {
+                primed =
+                    Self::fill_preroll(&mut self.history, &mut self.preroll, self.gain, &mut guard);
+                // Frames stay in the stream until the pre-roll completes
+                let fetch = if primed { samples_needed } else { 0 };
+                for _ in 0..fetch {
                     if let Some((left, right)) = guard.read() {
                         self.fetch_scratch
                             .push((left * self.gain, right * self.gain));
@@ -645,6 +759,14 @@ mod streaming {
                 }
             }
 
+            if !primed {
+                for i in offset..offset + size {
+                    output.set_f32(0, i, 0.0);
+                    output.set_f32(1, i, 0.0);
+                }
+                return;
+            }
+
             let mut fetch_idx = 0;
             for i in 0..size {
                 let speed = self
@@ -652,7 +774,7 @@ mod streaming {
                     .as_ref()
                     .map(|s| {
                         s.advance_speed_ramp();
-                        s.effective_speed() as f64 * s.src_ratio() as f64
+                        s.effective_speed() as f64
                     })
                     .unwrap_or(1.0);
 
@@ -660,34 +782,20 @@ mod streaming {
 
                 while self.fractional_pos >= 1.0 {
                     self.fractional_pos -= 1.0;
-                    self.shift_history();
 
-                    if fetch_idx < self.fetch_scratch.len() {
-                        self.history[3] = self.fetch_scratch[fetch_idx];
+                    let next = if fetch_idx < self.fetch_scratch.len() {
                         fetch_idx += 1;
+                        self.fetch_scratch[fetch_idx - 1]
                     } else {
                         if let Some(ref state) = self.shared_state {
                             state.report_underrun();
                         }
-                        self.history[3] = self.history[2];
-                    }
+                        self.history.newest()
+                    };
+                    self.history.push(next);
                 }
 
-                let t = self.fractional_pos as f32;
-                let left = cubic_hermite(
-                    self.history[0].0,
-                    self.history[1].0,
-                    self.history[2].0,
-                    self.history[3].0,
-                    t,
-                );
-                let right = cubic_hermite(
-                    self.history[0].1,
-                    self.history[1].1,
-                    self.history[2].1,
-                    self.history[3].1,
-                    t,
-                );
+                let (left, right) = self.interpolate(speed);
 
                 output.set_f32(0, offset + i, left);
                 output.set_f32(1, offset + i, right);
@@ -740,12 +848,34 @@ mod streaming {
                 }
             }
 
+            self.sync_seek();
+            self.sync_resample_quality();
+
+            if self.preroll > 0 {
+                let primed = match self.consumer.try_lock() {
+                    Some(mut guard) => Self::fill_preroll(
+                        &mut self.history,
+                        &mut self.preroll,
+                        self.gain,
+                        &mut guard,
+                    ),
+                    None => false,
+                };
+                if !primed {
+                    if output.len() >= 2 {
+                        output[0] = 0.0;
+                        output[1] = 0.0;
+                    }
+                    return;
+                }
+            }
+
             let speed = self
                 .shared_state
                 .as_ref()
                 .map(|s| {
                     s.advance_speed_ramp();
-                    s.effective_speed() as f64 * s.src_ratio() as f64
+                    s.effective_speed() as f64
                 })
                 .unwrap_or(1.0);
 
@@ -753,40 +883,22 @@ mod streaming {
 
             while self.fractional_pos >= 1.0 {
                 self.fractional_pos -= 1.0;
-                self.shift_history();
 
-                if let Some(mut guard) = self.consumer.try_lock() {
-                    if let Some((left, right)) = guard.read() {
-                        self.history[3] = (left * self.gain, right * self.gain);
-                    } else {
-                        if let Some(ref state) = self.shared_state {
-                            state.report_underrun();
-                        }
-                        self.history[3] = self.history[2];
-                    }
-                } else {
+                let next = self
+                    .consumer
+                    .try_lock()
+                    .and_then(|mut guard| guard.read())
+                    .map(|(left, right)| (left * self.gain, right * self.gain));
+                let next = next.unwrap_or_else(|| {
                     if let Some(ref state) = self.shared_state {
                         state.report_underrun();
                     }
-                    self.history[3] = self.history[2];
-                }
+                    self.history.newest()
+                });
+                self.history.push(next);
             }
 
-            let t = self.fractional_pos as f32;
-            let left = cubic_hermite(
-                self.history[0].0,
-                self.history[1].0,
-                self.history[2].0,
-                self.history[3].0,
-                t,
-            );
-            let right = cubic_hermite(
-                self.history[0].1,
-                self.history[1].1,
-                self.history[2].1,
-                self.history[3].1,
-                t,
-            );
+            let (left, right) = self.interpolate(speed);
 
             if output.len() >= 2 {
                 output[0] = left;
@@ -946,10 +1058,134 @@ mod tests {
         assert!(sampler.is_playing());
     }
 
+    #[test]
+    fn test_transport_position_follows_tempo_map() {
+        use tutti_core::{ExportConfig, ExportTimeline, TempoMap};
+
+        // One sample per millisecond, value = index
+        let samples: Vec<f32> = (0..10_000).map(|i| i as f32).collect();
+        let wave = Wave::from_samples(1000.0, &samples);
+
+        let mut map = TempoMap::new(60.0, 1000.0);
+        map.add_tempo_point(4.0, 120.0);
+        let timeline = Arc::new(
+            ExportTimeline::new(&ExportConfig {
+                sample_rate: 1000.0,
+                tempo: 60.0,
+                ..Default::default()
+            })
+            .with_tempo_map(map.snapshot()),
+        );
+        let mut sampler = SamplerUnit::with_transport(Arc::new(wave), timeline.clone(), 0.0, 0.0);
+
+        // Beat 6 = 4 s at 60 BPM + 1 s at 120 BPM
+        timeline.reset(6.0);
+        let mut output = [0.0f32; 2];
+        sampler.tick(&[], &mut output);
+        assert!((output[0] - 5000.0).abs() < 0.5, "got {}", output[0]);
+    }
+
+    #[test]
+    fn test_resample_quality_suppresses_aliasing_on_load() {
+        // 30 kHz tone in a 96 kHz file folds to 18 kHz at a 48 kHz session
+        let samples: Vec<f32> = (0..9600)
+            .map(|i| (2.0 * std::f64::consts::PI * 30_000.0 * i as f64 / 96_000.0).sin() as f32)
+            .collect();
+        let wave = Arc::new(Wave::from_samples(96_000.0, &samples));
+
+        let rms_for = |quality: ResampleQuality| {
+            let mut sampler = SamplerUnit::new(wave.clone());
+            sampler.set_sample_rate(48_000.0);
+            sampler.set_resample_quality(quality);
+            assert_eq!(sampler.resample_quality(), quality);
+
+            let mut sum = 0.0f64;
+            let mut output = [0.0f32; 2];
+            for i in 0..4800 {
+                sampler.tick(&[], &mut output);
+                if (100..4700).contains(&i) {
+                    sum += (output[0] as f64).powi(2);
+                }
+            }
+            (sum / 4600.0).sqrt()
+        };
+
+        assert!(rms_for(ResampleQuality::Linear) > 0.1);
+        assert!(rms_for(ResampleQuality::Sinc) < 0.001);
+    }
+
+    #[test]
+    fn test_unity_rate_plays_frames_unchanged() {
+        // Alternating full-scale frames: any kernel would smear them
+        let samples: Vec<f32> = (0..256)
+            .map(|i| if i % 2 == 0 { 1.0 } else { -1.0 })
+            .collect();
+        let mut sampler = SamplerUnit::new(Arc::new(Wave::from_samples(44_100.0, &samples)));
+        sampler.set_sample_rate(44_100.0);
+        sampler.set_resample_quality(ResampleQuality::SincHigh);
+
+        let mut output = [0.0f32; 2];
+        for &expected in &samples[..200] {
+            sampler.tick(&[], &mut output);
+            assert_eq!(output, [expected, expected]);
+        }
+    }
+
+    #[test]
+    fn test_streamed_impulse_aligns_with_in_memory() {
+        use crate::butler::{RegionBuffer, RegionId, SharedStreamState};
+        use parking_lot::Mutex;
+        use std::path::PathBuf;
+
+        let mut samples = vec![0.0f32; 1024];
+        samples[200] = 1.0;
+        let peak = |output: &[f32]| {
+            (0..output.len())
+                .max_by(|&a, &b| output[a].total_cmp(&output[b]))
+                .unwrap()
+        };
+
+        let mut sampler = SamplerUnit::new(Arc::new(Wave::from_samples(48_000.0, &samples)));
+        sampler.set_sample_rate(48_000.0);
+        let mut output = [0.0f32; 2];
+        let in_memory: Vec<f32> = (0..512)
+            .map(|_| {
+                sampler.tick(&[], &mut output);
+                output[0]
+            })
+            .collect();
+        assert_eq!(peak(&in_memory), 200);
+
+        for quality in [ResampleQuality::Sinc, ResampleQuality::SincHigh] {
+            let (mut producer, consumer) =
+                RegionBuffer::with_capacity(RegionId::generate(), PathBuf::from("test.wav"), 4096);
+            let frames: Vec<_> = samples.iter().map(|&s| (s, s)).collect();
+            producer.write(&frames);
+
+            // Start on a short kernel and switch before the impulse arrives
+            let state = Arc::new(SharedStreamState::new());
+            state.set_resample_quality(ResampleQuality::Linear);
+            let mut streaming =
+                StreamingSamplerUnit::new(Arc::new(Mutex::new(consumer)), state.clone());
+            let streamed: Vec<f32> = (0..512)
+                .map(|i| {
+                    if i == 100 {
+                        state.set_resample_quality(quality);
+                    }
+                    streaming.tick(&[], &mut output);
+                    output[0]
+                })
+                .collect();
+
+            assert_eq!(peak(&streamed), 200);
+            assert!((streamed[200] - 1.0).abs() < 1e-4);
+        }
+    }
+
     mod streaming_tests {
         #[test]
         fn test_cubic_hermite_interpolation() {
-            use super::streaming::cubic_hermite;
+            use crate::resample::cubic_hermite;
             let result = cubic_hermite(0.0, 1.0, 2.0, 3.0, 0.0);
             assert!((result - 1.0).abs() < 0.001);
 
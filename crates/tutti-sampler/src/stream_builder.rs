@@ -1,6 +1,6 @@
 //! Stream and record builders for fluent API
 
-use crate::butler::PlayDirection;
+use crate::butler::{CaptureFormat, PlayDirection};
 use crate::system::{CaptureSession, SamplerSystem};
 use std::path::PathBuf;
 
@@ -148,6 +148,7 @@ pub struct RecordBuilder<'a> {
     channels: usize,
     buffer_seconds: Option<f64>,
     sample_rate: Option<f64>,
+    format: CaptureFormat,
 }
 
 impl<'a> RecordBuilder<'a> {
@@ -158,6 +159,7 @@ impl<'a> RecordBuilder<'a> {
             channels: 2,
             buffer_seconds: None,
             sample_rate: None,
+            format: CaptureFormat::default(),
         }
     }
 
@@ -179,6 +181,12 @@ impl<'a> RecordBuilder<'a> {
         self
     }
 
+    /// Set container and sample format (default: 32-bit float WAV, RF64 past 4 GB).
+    pub fn format(mut self, format: CaptureFormat) -> Self {
+        self.format = format;
+        self
+    }
+
     /// Start recording and return a capture session.
     ///
     /// The returned session contains a producer that the audio callback
@@ -196,7 +204,7 @@ impl<'a> RecordBuilder<'a> {
             self.buffer_seconds,
         );
 
-        self.sampler.start_capture(session)
+        self.sampler.start_capture(session.with_format(self.format))
     }
 }
 
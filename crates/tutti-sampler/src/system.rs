@@ -1,10 +1,12 @@
 //! Sampler system - unified API for streaming, recording, and butler operations.
 
 use crate::butler::{
-    BufferConfig, ButlerCommand, CacheStats, CaptureBuffer, CaptureBufferProducer, CaptureId,
-    FlushRequest, IOMetricsSnapshot, LruCache, PlayDirection, TransportBridge, Varispeed,
+    BufferConfig, ButlerCommand, CacheStats, CaptureBuffer, CaptureBufferProducer, CaptureFormat,
+    CaptureId, FlushRequest, IOMetricsSnapshot, LruCache, PlayDirection, TransportBridge,
+    Varispeed,
 };
 use crate::error::Result;
+use crate::resample::ResampleQuality;
 use crossbeam_channel::Sender;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -78,6 +80,7 @@ impl SamplerSystem {
             file_path,
             sample_rate,
             channels,
+            format: CaptureFormat::default(),
         }
     }
 
@@ -94,6 +97,7 @@ impl SamplerSystem {
             file_path: session.file_path.clone(),
             sample_rate: session.sample_rate,
             channels: session.channels,
+            format: session.format,
         });
 
         session
@@ -225,14 +229,41 @@ impl SamplerSystem {
         self
     }
 
+    /// Change the interpolation used by the channel's current stream.
+    ///
+    /// Varispeed picks it up on the next audio block; rate conversion on the
+    /// butler's next refill, behind the audio already buffered. New streams
+    /// start with the quality from [`SamplerSystemBuilder::resample_quality`].
+    pub fn set_resample_quality(&self, channel_index: usize, quality: ResampleQuality) -> &Self {
+        if let Some(butler) = self.butler.as_ref() {
+            if let Some(state) = butler.stream_states().get(&channel_index) {
+                state.shared_state().set_resample_quality(quality);
+            }
+        }
+        self
+    }
+
     /// Get LRU cache for audio files.
     ///
     /// The cache automatically evicts least-recently-used entries when
-    /// limits are exceeded.
+    /// limits are exceeded. It holds fully loaded files (e.g. auditioner
+    /// previews); disk streams decode incrementally and never populate it.
     pub fn cache(&self) -> Option<Arc<LruCache>> {
         self.butler.as_ref().map(|b| b.cache())
     }
 
+    /// Count an LRU cache lookup in the I/O metrics.
+    pub(crate) fn record_cache_lookup(&self, hit: bool) {
+        if let Some(butler) = &self.butler {
+            let metrics = butler.metrics();
+            if hit {
+                metrics.record_cache_hit();
+            } else {
+                metrics.record_cache_miss();
+            }
+        }
+    }
+
     pub fn cache_stats(&self) -> CacheStats {
         self.butler
             .as_ref()
@@ -512,6 +543,15 @@ impl SamplerSystemBuilder {
         self
     }
 
+    /// Set the interpolation for streams whose file rate differs from the
+    /// session rate (default: [`ResampleQuality::Sinc`]).
+    ///
+    /// The same stage applies varispeed, so this also sets speed-change quality.
+    pub fn resample_quality(mut self, quality: ResampleQuality) -> Self {
+        self.buffer_config.resample_quality = quality;
+        self
+    }
+
     /// Set PDC manager for automatic delay compensation.
     ///
     /// When set, the butler thread will automatically apply preroll
@@ -572,9 +612,16 @@ pub struct CaptureSession {
     file_path: PathBuf,
     sample_rate: f64,
     channels: usize,
+    format: CaptureFormat,
 }
 
 impl CaptureSession {
+    /// File format to write (default: 32-bit float WAV). Set before starting.
+    pub fn with_format(mut self, format: CaptureFormat) -> Self {
+        self.format = format;
+        self
+    }
+
     pub fn file_path(&self) -> &PathBuf {
         &self.file_path
     }
@@ -587,6 +634,10 @@ impl CaptureSession {
         self.channels
     }
 
+    pub fn format(&self) -> CaptureFormat {
+        self.format
+    }
+
     pub fn is_started(&self) -> bool {
         self.consumer.is_none()
     }
@@ -19,8 +19,8 @@ pub mod error;
 pub use error::{Error, Result};
 
 mod voice;
-pub(crate) use voice::{
-    AllocationResult, AllocationStrategy, VoiceAllocator, VoiceAllocatorConfig, VoiceMode,
+pub use voice::{
+    AllocationResult, AllocationStrategy, VoiceAllocator, VoiceAllocatorConfig, VoiceId, VoiceMode,
 };
 
 mod unison;
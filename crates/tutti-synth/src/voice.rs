@@ -255,6 +255,21 @@ impl VoiceAllocator {
         &self.slots
     }
 
+    /// Whether the slot's note is still held (by key or pedal) rather than releasing.
+    pub fn is_slot_active(&self, slot_index: usize) -> bool {
+        self.slots
+            .get(slot_index)
+            .is_some_and(|s| s.state == VoiceState::Active)
+    }
+
+    /// Voice currently assigned to a slot, for [`voice_finished`](Self::voice_finished).
+    pub fn slot_voice_id(&self, slot_index: usize) -> Option<VoiceId> {
+        self.slots
+            .get(slot_index)
+            .filter(|s| s.state != VoiceState::Idle)
+            .map(|s| s.voice_id)
+    }
+
     pub fn reset(&mut self) {
         for slot in &mut self.slots {
             *slot = VoiceSlot::default();
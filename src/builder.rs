@@ -45,6 +45,8 @@ use crate::neural::NeuralSystem;
 /// ```
 pub struct TuttiEngineBuilder {
     output_device: Option<usize>,
+    #[cfg(feature = "std")]
+    backend: tutti_core::AudioBackend,
     inputs: usize,
     outputs: usize,
 
@@ -62,6 +64,8 @@ impl Default for TuttiEngineBuilder {
     fn default() -> Self {
         Self {
             output_device: None,
+            #[cfg(feature = "std")]
+            backend: tutti_core::AudioBackend::Cpal,
             inputs: 0,
             outputs: 2,
 
@@ -83,6 +87,24 @@ impl TuttiEngineBuilder {
         self
     }
 
+    /// Select the audio output backend. Default: CPAL device output.
+    ///
+    /// Use [`AudioBackend::Null`](tutti_core::AudioBackend::Null) to run without
+    /// a sound card (CI, render servers); the sample rate then comes from the
+    /// backend config.
+    ///
+    /// ```ignore
+    /// let engine = TuttiEngine::builder()
+    ///     .backend(AudioBackend::Null(NullBackendConfig::default().tap(48000)))
+    ///     .build()?;
+    /// let tap = engine.take_output_tap().unwrap();
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn backend(mut self, backend: tutti_core::AudioBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+
     /// Default: 0
     pub fn inputs(mut self, count: usize) -> Self {
         self.inputs = count;
@@ -143,8 +165,11 @@ impl TuttiEngineBuilder {
             .outputs(self.outputs);
 
         #[cfg(feature = "std")]
-        if let Some(device) = self.output_device {
-            core_builder = core_builder.output_device(device);
+        {
+            core_builder = core_builder.backend(self.backend);
+            if let Some(device) = self.output_device {
+                core_builder = core_builder.output_device(device);
+            }
         }
 
         // Wire MIDI port manager into the audio callback for hardware → graph routing.
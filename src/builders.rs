@@ -155,17 +155,89 @@ impl<'a> SampleBuilder<'a> {
     }
 }
 
-/// Load a plugin in-process. The plugin runs on a dedicated thread
-/// in the same process, enabling GUI editor support.
-#[cfg(feature = "plugin")]
-fn load_plugin(
-    engine: &crate::TuttiEngine,
+/// File format an [`InstrumentBuilder`] parses.
+#[cfg(feature = "instrument")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum InstrumentFormat {
+    Sfz,
+    DecentSampler,
+}
+
+/// Fluent builder for multisample instruments (SFZ, Decent Sampler).
+///
+/// Created via `engine.sfz(path)` or `engine.dspreset(path)`, which set the
+/// format regardless of file extension; samples are loaded relative to the
+/// file.
+#[cfg(feature = "instrument")]
+pub struct InstrumentBuilder<'a> {
+    engine: &'a crate::TuttiEngine,
     path: PathBuf,
-    params: &std::collections::HashMap<String, f32>,
-) -> Result<(Box<dyn crate::AudioUnit>, crate::plugin::PluginHandle)> {
-    let sample_rate = engine.sample_rate();
-    let block_size = 512;
+    format: InstrumentFormat,
+    polyphony: usize,
+    resample_quality: crate::sampler::ResampleQuality,
+    gain: f32,
+}
+
+#[cfg(feature = "instrument")]
+impl<'a> InstrumentBuilder<'a> {
+    pub(crate) fn new(
+        engine: &'a crate::TuttiEngine,
+        path: PathBuf,
+        format: InstrumentFormat,
+    ) -> Self {
+        Self {
+            engine,
+            path,
+            format,
+            polyphony: 32,
+            resample_quality: crate::sampler::ResampleQuality::default(),
+            gain: 1.0,
+        }
+    }
+
+    /// Maximum simultaneous notes. Default: 32.
+    pub fn polyphony(mut self, voices: usize) -> Self {
+        self.polyphony = voices;
+        self
+    }
+
+    /// Interpolation for pitch shifting. Default: windowed sinc.
+    pub fn resample_quality(mut self, quality: crate::sampler::ResampleQuality) -> Self {
+        self.resample_quality = quality;
+        self
+    }
+
+    /// Output gain. Default: 1.0.
+    pub fn gain(mut self, gain: f32) -> Self {
+        self.gain = gain;
+        self
+    }
+
+    /// Loads the instrument and its samples, wired to the graph's MIDI registry.
+    pub fn build(self) -> Result<crate::sampler::SamplerInstrument> {
+        let builder = crate::sampler::SamplerInstrument::builder(self.engine.sample_rate());
+        let builder = match self.format {
+            InstrumentFormat::Sfz => builder.sfz(&self.path)?,
+            InstrumentFormat::DecentSampler => builder.dspreset(&self.path)?,
+        };
 
+        let midi_registry = self.engine.graph_mut(|net| net.midi_registry().clone());
+        Ok(builder
+            .polyphony(self.polyphony)
+            .resample_quality(self.resample_quality)
+            .gain(self.gain)
+            .build()?
+            .with_midi_registry(midi_registry))
+    }
+}
+
+/// Load and activate a plugin instance for in-process hosting.
+#[cfg(feature = "plugin")]
+fn load_plugin_instance(
+    path: &std::path::Path,
+    sample_rate: f64,
+    block_size: usize,
+) -> crate::plugin::Result<Box<dyn crate::plugin::PluginInstance>> {
     let ext = path
         .extension()
         .and_then(|e| e.to_str())
@@ -176,7 +248,7 @@ fn load_plugin(
         #[cfg(feature = "clap")]
         "clap" => {
             let mut inst = tutti_plugin_server::clap_loader::ClapInstance::load(
-                &path,
+                path,
                 sample_rate,
                 block_size,
             )?;
@@ -189,7 +261,7 @@ fn load_plugin(
         #[cfg(feature = "vst3")]
         "vst3" => {
             let inst = tutti_plugin_server::vst3_loader::Vst3Instance::load(
-                &path,
+                path,
                 sample_rate,
                 block_size,
             )?;
@@ -198,20 +270,36 @@ fn load_plugin(
         #[cfg(feature = "vst2")]
         "dll" | "so" | "vst" => {
             let inst = tutti_plugin_server::vst2_loader::Vst2Instance::load(
-                &path,
+                path,
                 sample_rate,
                 block_size,
             )?;
             Box::new(inst)
         }
         _ => {
-            return Err(tutti_core::Error::InvalidConfig(format!(
-                "Unsupported plugin format: .{ext}"
-            ))
-            .into());
+            return Err(crate::plugin::BridgeError::LoadFailed {
+                path: path.to_path_buf(),
+                stage: crate::plugin::LoadStage::Opening,
+                reason: format!("Unsupported plugin format: .{ext}"),
+            });
         }
     };
 
+    Ok(instance)
+}
+
+/// Load a plugin in-process. The plugin runs on a dedicated thread
+/// in the same process, enabling GUI editor support.
+#[cfg(feature = "plugin")]
+fn load_plugin(
+    engine: &crate::TuttiEngine,
+    path: PathBuf,
+    params: &std::collections::HashMap<String, f32>,
+) -> Result<(Box<dyn crate::AudioUnit>, crate::plugin::PluginHandle)> {
+    let sample_rate = engine.sample_rate();
+    let block_size = 512;
+
+    let instance = load_plugin_instance(&path, sample_rate, block_size)?;
     let metadata = instance.metadata().clone();
     let num_channels = metadata
         .audio_io
@@ -233,6 +321,17 @@ fn load_plugin(
     let mut client =
         crate::plugin::PluginClient::from_bridge(bridge_arc.clone(), metadata.clone(), block_size);
 
+    // Offline renders get their own instance so the live one keeps playing
+    client.set_instance_loader(move || {
+        let instance = load_plugin_instance(&path, sample_rate, block_size)?;
+        let (bridge, thread_handle) =
+            crate::plugin::InProcessBridge::new(instance, num_channels, block_size);
+        Ok(crate::plugin::LoadedInstance {
+            bridge: std::sync::Arc::new(bridge),
+            owner: Box::new(thread_handle),
+        })
+    });
+
     // Inject MIDI registry so engine.note_on() reaches the plugin
     #[cfg(feature = "midi")]
     {
@@ -127,6 +127,31 @@ impl TuttiEngine {
         self.core.channels()
     }
 
+    /// Take the rendered-output tap of the null backend (once).
+    ///
+    /// See [`TuttiEngineBuilder::backend`](crate::TuttiEngineBuilder::backend).
+    #[cfg(feature = "std")]
+    pub fn take_output_tap(&self) -> Option<tutti_core::OutputTap> {
+        self.core.take_output_tap()
+    }
+
+    /// Install (or remove) the transport sync generator, e.g. a
+    /// `MidiSyncGenerator` leading external gear with MIDI Clock / MTC.
+    #[cfg(feature = "std")]
+    pub fn set_sync_output(&self, output: Option<Arc<dyn tutti_core::TransportSyncOutput>>) {
+        self.core.set_sync_output(output);
+    }
+
+    /// Install (or remove) the transport-driven MIDI source, e.g. a
+    /// `MidiFilePlayer` playing a Standard MIDI File into the graph.
+    #[cfg(all(feature = "std", feature = "midi"))]
+    pub fn set_transport_midi_source(
+        &self,
+        source: Option<Arc<dyn tutti_core::TransportMidiSource>>,
+    ) {
+        self.core.set_transport_midi_source(source);
+    }
+
     /// Access the DSP graph for reading, querying, or side-effects.
     ///
     /// Does **not** commit changes to the audio thread. Use for
@@ -312,6 +337,37 @@ impl TuttiEngine {
         crate::builders::SampleBuilder::new(self, path.as_ref().to_path_buf())
     }
 
+    /// Load an SFZ multisample instrument.
+    ///
+    /// Returns a fluent builder for polyphony and resampling quality. Call
+    /// `.build()` to get a MIDI-driven `SamplerInstrument`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let piano = engine.sfz("piano/piano.sfz").polyphony(64).build()?;
+    /// let piano_id = engine.graph_mut(|net| net.add(piano).master());
+    /// engine.note_on(piano_id, Note::C4, 100);
+    /// ```
+    #[cfg(feature = "instrument")]
+    pub fn sfz(&self, path: impl AsRef<Path>) -> crate::builders::InstrumentBuilder<'_> {
+        crate::builders::InstrumentBuilder::new(
+            self,
+            path.as_ref().to_path_buf(),
+            crate::builders::InstrumentFormat::Sfz,
+        )
+    }
+
+    /// Load a Decent Sampler `.dspreset` instrument. See [`sfz()`](Self::sfz) for builder API.
+    #[cfg(feature = "instrument")]
+    pub fn dspreset(&self, path: impl AsRef<Path>) -> crate::builders::InstrumentBuilder<'_> {
+        crate::builders::InstrumentBuilder::new(
+            self,
+            path.as_ref().to_path_buf(),
+            crate::builders::InstrumentFormat::DecentSampler,
+        )
+    }
+
     /// Load a VST3 plugin.
     ///
     /// Returns a fluent builder for configuring plugin parameters.
@@ -525,6 +581,29 @@ impl TuttiEngine {
         self
     }
 
+    /// Put a chain of MIDI effects (arpeggiator, chord generator, note repeat)
+    /// in front of a node. Pass an empty chain to remove it.
+    ///
+    /// # Example
+    /// ```ignore
+    /// use tutti::{ArpMode, Arpeggiator, ChordGenerator};
+    ///
+    /// engine.set_midi_effects(synth, vec![
+    ///     Box::new(ChordGenerator::minor()),
+    ///     Box::new(Arpeggiator::new().mode(ArpMode::UpDown).rate(0.25)),
+    /// ]);
+    /// ```
+    #[cfg(all(feature = "std", feature = "midi"))]
+    pub fn set_midi_effects(
+        &self,
+        node: NodeId,
+        effects: Vec<Box<dyn tutti_core::MidiEffect>>,
+    ) -> &Self {
+        let unit_id = self.graph(|net| net.node(node).get_id());
+        self.core.set_midi_effects(unit_id, effects);
+        self
+    }
+
     /// Events are delivered to the node before the next audio callback.
     #[cfg(feature = "midi")]
     pub fn queue_midi(&self, node: NodeId, events: &[MidiEvent]) -> &Self {
@@ -708,6 +787,10 @@ impl TuttiEngine {
     /// The export uses an isolated timeline so MIDI and automation can be
     /// properly rendered without interfering with live playback.
     ///
+    /// Hosted plugins render through a second instance with the live one's
+    /// state, in offline mode: the render waits for each plugin block rather
+    /// than dropping it, however long the plugin takes.
+    ///
     /// # Example
     /// ```ignore
     /// // Export 10 seconds to WAV
@@ -715,13 +798,21 @@ impl TuttiEngine {
     ///     .duration_seconds(10.0)
     ///     .to_file("output.wav")?;
     ///
-    /// // Export with options
+    /// // Export bars 1-4 in 4/4, following the tempo map
     /// engine.export()
-    ///     .duration_beats(16.0, 120.0)  // 16 beats at 120 BPM
+    ///     .beat_range(0.0, 16.0)
     ///     .format(AudioFormat::Flac)
     ///     .normalize(NormalizationMode::lufs(-14.0))
     ///     .to_file("output.flac")?;
+    ///
+    /// // From bar 9 to the end of the content, with the reverb tail
+    /// engine.export()
+    ///     .from_bbt(BBT::new(9, 1, 0))
+    ///     .tail(TailOptions::default())
+    ///     .to_file("outro.wav")?;
     /// ```
+    ///
+    /// Ranges without an explicit end stop at [`content_end_beat`](Self::content_end_beat).
     #[cfg(feature = "export")]
     pub fn export(&self) -> crate::export::ExportBuilder {
         let mut net = self.core.clone_net();
@@ -739,6 +830,10 @@ impl TuttiEngine {
                     .drain_into_snapshot(context.midi_snapshot_mut(), 0.0);
             });
 
+            // Run each unit's MIDI effect chain over its events, as live
+            #[cfg(feature = "std")]
+            self.core.render_midi_effects(context.midi_snapshot_mut());
+
             // Create a MidiSnapshotReader and inject it into all MIDI-consuming
             // nodes in the cloned net. Each node gets its own clone so cursors
             // are independent.
@@ -771,13 +866,56 @@ impl TuttiEngine {
             }
         }
 
-        crate::export::ExportBuilder::new(net, sample_rate).with_context(context)
+        // Give each hosted plugin its own offline instance carrying the live
+        // state and following the export timeline. The live instance keeps
+        // playing; a plugin that can't be loaded twice fails the export, since
+        // rendering through the shared instance wouldn't match playback.
+        #[cfg(feature = "plugin")]
+        let mut plugin_errors = Vec::new();
+        #[cfg(feature = "plugin")]
+        {
+            use tutti_core::AudioUnit;
+
+            let timeline: std::sync::Arc<dyn tutti_core::TransportReader> =
+                context.timeline.clone();
+            let node_ids: Vec<_> = net.ids().copied().collect();
+            for node_id in node_ids {
+                if let Some(plugin) = <dyn AudioUnit>::as_any_mut(net.node_mut(node_id))
+                    .downcast_mut::<crate::plugin::PluginClient>()
+                {
+                    if let Err(e) = plugin.prepare_offline_render(timeline.clone()) {
+                        plugin_errors.push(format!("{}: {e}", plugin.metadata().name));
+                    }
+                }
+            }
+        }
+
+        #[allow(unused_mut)]
+        let mut builder = crate::export::ExportBuilder::new(net, sample_rate).with_context(context);
+
+        #[cfg(feature = "plugin")]
+        if !plugin_errors.is_empty() {
+            builder = builder.setup_error(format!(
+                "Offline plugin instance failed: {}",
+                plugin_errors.join("; ")
+            ));
+        }
+
+        #[cfg(feature = "sampler")]
+        {
+            let end_beat = self.content_end_beat();
+            if end_beat > 0.0 {
+                builder = builder.default_end_beat(end_beat);
+            }
+        }
+
+        builder
     }
 
     /// Inject a `MidiSnapshotReader` into all MIDI-consuming nodes in a cloned net.
     ///
     /// Iterates all nodes, attempts to downcast to known MIDI-consuming types
-    /// (PolySynth, SoundFontUnit, NeuralSynthNode), and sets the MIDI source.
+    /// (PolySynth, SoundFontUnit, SamplerInstrument, NeuralSynthNode, PluginClient), and sets the MIDI source.
     #[cfg(all(feature = "export", feature = "midi"))]
     fn inject_midi_sources(
         net: &mut tutti_core::dsp::Net,
@@ -807,6 +945,15 @@ impl TuttiEngine {
                 continue;
             }
 
+            // Try SamplerInstrument
+            #[cfg(feature = "instrument")]
+            if let Some(instrument) =
+                <dyn AudioUnit>::as_any_mut(unit).downcast_mut::<tutti_sampler::SamplerInstrument>()
+            {
+                instrument.set_midi_source(Box::new(reader.clone()));
+                continue;
+            }
+
             // Try NeuralSynthNode
             #[cfg(all(feature = "neural", feature = "midi"))]
             if let Some(neural_synth) =
@@ -815,6 +962,15 @@ impl TuttiEngine {
                 neural_synth.set_midi_source(Box::new(reader.clone()));
                 continue;
             }
+
+            // Try PluginClient
+            #[cfg(feature = "plugin")]
+            if let Some(plugin) =
+                <dyn AudioUnit>::as_any_mut(unit).downcast_mut::<crate::plugin::PluginClient>()
+            {
+                plugin.set_midi_source(Box::new(reader.clone()));
+                continue;
+            }
         }
     }
 
@@ -65,6 +65,7 @@
 //! - `full` - Everything enabled
 //! - `midi` - MIDI subsystem
 //! - `sampler` - Sample playback and recording
+//! - `instrument` - MIDI multisample instruments (SFZ, Decent Sampler)
 //! - `plugin` - Plugin hosting
 //! - `neural` - Neural audio
 //! - `analysis` - Audio analysis tools
@@ -101,6 +102,7 @@ pub use tutti_core::{
     Direction,
     EventId,
     Fade,
+    MeterChange,
     // Metering (includes LUFS!)
     MeteringHandle,
     MeteringManager,
@@ -130,6 +132,7 @@ pub use tutti_core::{
     SignalFrame,
     Source,
     StereoAnalysisSnapshot,
+    TempoCurve,
     TempoMap,
     TimeSignature,
     TransportClock,
@@ -144,6 +147,18 @@ pub use tutti_core::{
     BBT,
 };
 
+// Timecode sync (LTC as AudioUnits)
+pub use tutti_core::{
+    ltc_generator, ltc_reader, LtcGenerator, LtcReader, LtcReaderConfig, SmpteFrameRate,
+    SyncSource, SyncStatus, Timecode,
+};
+
+// Audio output backends
+#[cfg(feature = "std")]
+pub use tutti_core::{AudioBackend, NullBackendConfig, NullPacing, OutputTap};
+#[cfg(feature = "std")]
+pub use tutti_core::{SyncBlock, TransportSyncOutput};
+
 // Atomic types (from core:: via tutti-core, no_std compatible)
 pub use tutti_core::{AtomicBool, AtomicU32, AtomicU64, AtomicU8, AtomicUsize, Ordering};
 
@@ -157,8 +172,9 @@ pub use tutti_midi_io as midi;
 #[cfg(feature = "midi")]
 pub use tutti_midi_io::{
     CCMapping, CCMappingManager, CCNumber, CCProcessResult, CCTarget, Channel, ChannelVoiceMsg,
-    ControlChange, MappingId, MidiChannel, MidiEvent, MidiHandle, MidiMsg, MidiSystem,
-    MidiSystemBuilder, Note, PortInfo, RawMidiEvent,
+    ControlChange, MappingId, MidiChannel, MidiEvent, MidiFilePlayer, MidiHandle, MidiMsg,
+    MidiSyncFollower, MidiSyncGenerator, MidiSystem, MidiSystemBuilder, Note, PortInfo,
+    RawMidiEvent, SyncFollowerConfig, SyncFollowerHandle, SyncGeneratorConfig, SyncMessage,
 };
 
 #[cfg(feature = "midi-hardware")]
@@ -172,7 +188,9 @@ pub use tutti_midi_io::{Midi2Event, Midi2Handle, Midi2MessageType, UnifiedMidiEv
 
 #[cfg(feature = "midi")]
 pub use tutti_core::{
-    MidiEventBuilder, MidiRegistry, MidiRoutingTable, MidiSnapshotReader, MidiSource,
+    ArpMode, Arpeggiator, ChordGenerator, MidiEffect, MidiEventBuilder, MidiRegistry, MidiRoute,
+    MidiRoutingTable, MidiSnapshotReader, MidiSource, MidiTransform, NoteRepeat, OctaveShift,
+    TransportMidiSource, VelocityCurve,
 };
 
 // Sampler subsystem (optional)
@@ -181,9 +199,17 @@ pub use tutti_sampler as sampler;
 
 #[cfg(feature = "sampler")]
 pub use tutti_sampler::{
-    AudioInput, AudioInputBackend, ImportHandle, ImportStatus, PlayDirection, SamplerHandle,
-    SamplerSystem, SamplerSystemBuilder, SamplerUnit, StreamingSamplerUnit, TimeStretchUnit,
-    Varispeed,
+    recover_capture, AudioInput, AudioInputBackend, CaptureContainer, CaptureFileInfo,
+    CaptureFormat, CaptureSampleFormat, ImportHandle, ImportStatus, PlayDirection, ResampleQuality,
+    SamplerHandle, SamplerSystem, SamplerSystemBuilder, SamplerUnit, StreamingSamplerUnit,
+    TimeStretchUnit, Varispeed,
+};
+
+// Multisample instruments from sampler subcrate
+#[cfg(feature = "instrument")]
+pub use tutti_sampler::{
+    SamplerInstrument, SamplerInstrumentBuilder, Zone, ZoneEnvelope, ZoneFilter, ZoneFilterType,
+    ZoneLoopMode,
 };
 
 // Time stretch types from sampler subcrate
@@ -193,9 +219,10 @@ pub use tutti_sampler::TimeStretchParams;
 // Recording types from sampler subcrate
 #[cfg(feature = "sampler")]
 pub use tutti_sampler::{
-    PunchEvent, QuantizeSettings, QuantizeSettingsBuilder, RecordedData, RecordingBuffer,
-    RecordingConfig, RecordingConfigBuilder, RecordingMode, RecordingSession, RecordingSource,
-    RecordingState as SamplerRecordingState, XRunEvent, XRunType,
+    Comp, CompSegment, PunchEvent, QuantizeSettings, QuantizeSettingsBuilder, RecordedData,
+    RecordingBuffer, RecordingConfig, RecordingConfigBuilder, RecordingMode, RecordingSession,
+    RecordingSource, RecordingState as SamplerRecordingState, Take, TakeAudio, TakeLane, XRunEvent,
+    XRunType,
 };
 
 // Synth subsystem (building blocks for synthesis)
@@ -239,7 +266,7 @@ pub use tutti_export as export;
 #[cfg(feature = "export")]
 pub use tutti_export::{
     AudioFormat, ExportBuilder, ExportConfig, ExportContext, ExportHandle, ExportOptions,
-    ExportStatus, NormalizationMode,
+    ExportQueue, ExportStatus, NormalizationMode, SpeakerLayout, Stem, StemAudio, TailOptions,
 };
 
 // Export timeline (for advanced export scenarios)
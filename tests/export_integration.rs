@@ -79,6 +79,67 @@ fn test_export_duration_beats() {
     let _ = builder;
 }
 
+/// A beat range is timed through the tempo map, not a flat tempo.
+#[test]
+fn test_export_beat_range_follows_tempo_map() {
+    let engine = test_engine();
+
+    engine.graph_mut(|net| {
+        net.add(sine_hz::<f64>(440.0) * 0.5).master();
+    });
+    let mut tempo_map = tutti::TempoMap::new(120.0, 48000.0);
+    tempo_map.add_tempo_point(4.0, 60.0);
+    engine.transport_manager().set_tempo_map(tempo_map);
+
+    // 4 beats at 120 BPM (2 s) plus 4 beats at 60 BPM (4 s)
+    let (left, _, sample_rate) = engine.export().beat_range(0.0, 8.0).render().unwrap();
+    assert_eq!(left.len(), (6.0 * sample_rate).round() as usize);
+}
+
+/// Test that a loop count replays the loop region inside the range.
+#[test]
+fn test_export_loop_count_extends_range() {
+    let engine = test_engine();
+
+    engine.graph_mut(|net| {
+        net.add(sine_hz::<f64>(440.0) * 0.5).master();
+    });
+    let transport = engine.transport_manager();
+    transport.set_tempo(120.0);
+    transport.set_loop_range(0.0, 2.0);
+    transport.set_loop_enabled(true);
+
+    // 4 beats (2 s) with the 1 s loop played three times
+    let (left, _, sample_rate) = engine
+        .export()
+        .beat_range(0.0, 4.0)
+        .loop_count(3)
+        .render()
+        .unwrap();
+    assert_eq!(left.len(), (4.0 * sample_rate).round() as usize);
+}
+
+/// Test that a tail which never decays stops at its cap.
+#[test]
+fn test_export_tail_capped() {
+    let engine = test_engine();
+
+    engine.graph_mut(|net| {
+        net.add(sine_hz::<f64>(440.0) * 0.5).master();
+    });
+
+    let (left, _, sample_rate) = engine
+        .export()
+        .duration_seconds(0.5)
+        .tail(tutti::TailOptions {
+            max_seconds: 0.25,
+            ..Default::default()
+        })
+        .render()
+        .unwrap();
+    assert_eq!(left.len(), (0.75 * sample_rate).round() as usize);
+}
+
 /// Test export to WAV file.
 #[test]
 fn test_export_to_wav() {
@@ -379,3 +440,102 @@ fn test_export_bit_depth() {
 
     cleanup_temp_dir("bit_depth");
 }
+
+/// Test stems are written next to the master from one render.
+#[test]
+fn test_export_stems() {
+    let engine = test_engine();
+    let dir = setup_temp_dir("stems");
+    let output_path = dir.join("mix.wav");
+
+    let (low, high) = engine.graph_mut(|net| {
+        let low = net.add(sine_hz::<f64>(220.0) * 0.2).master();
+        let high = net.add(sine_hz::<f64>(880.0) * 0.1).master();
+        (low, high)
+    });
+
+    engine
+        .export()
+        .duration_seconds(0.25)
+        .stem(tutti::export::Stem::new(low, "mix - low"))
+        .stem(
+            tutti::export::Stem::new(high, "mix - high")
+                .normalize(tutti::export::NormalizationMode::Peak(-1.0)),
+        )
+        .to_file(&output_path)
+        .expect("Stem export failed");
+
+    assert!(output_path.exists());
+    assert!(dir.join("mix - low.wav").exists());
+    assert!(dir.join("mix - high.wav").exists());
+
+    cleanup_temp_dir("stems");
+}
+
+/// Test lossy formats through the background export handle.
+#[test]
+#[cfg(feature = "export-lossy")]
+fn test_export_lossy_in_background() {
+    use tutti::export::Mp3Options;
+
+    let engine = test_engine();
+    let dir = setup_temp_dir("lossy");
+
+    engine.graph_mut(|net| {
+        net.add(sine_hz::<f64>(440.0) * 0.3).master();
+    });
+
+    for (name, format) in [
+        ("preview.opus", AudioFormat::Opus),
+        ("preview.ogg", AudioFormat::Vorbis),
+        ("preview.mp3", AudioFormat::Mp3),
+    ] {
+        let path = dir.join(name);
+        engine
+            .export()
+            .duration_seconds(0.5)
+            .format(format)
+            .mp3(Mp3Options { bitrate_kbps: 128 })
+            .start(&path)
+            .wait()
+            .unwrap();
+        assert!(std::fs::metadata(&path).unwrap().len() > 100, "{}", name);
+    }
+
+    cleanup_temp_dir("lossy");
+}
+
+/// Test a queue of renders with one job cancelled.
+#[test]
+fn test_export_queue() {
+    use tutti::export::{ExportQueue, JobStatus};
+
+    let engine = test_engine();
+    let dir = setup_temp_dir("queue");
+
+    engine.graph_mut(|net| {
+        net.add(sine_hz::<f64>(440.0) * 0.3).master();
+    });
+
+    let mut queue = ExportQueue::new().max_parallel(2);
+    queue.push(engine.export().duration_seconds(0.5), dir.join("a.wav"));
+    let cancelled = queue.push(engine.export().duration_seconds(600.0), dir.join("b.wav"));
+    queue.push(
+        engine
+            .export()
+            .duration_seconds(0.5)
+            .format(AudioFormat::Flac),
+        dir.join("c.flac"),
+    );
+    cancelled.cancel();
+
+    let statuses = queue.wait();
+    assert!(matches!(statuses[0], JobStatus::Complete(Some(_))));
+    assert!(matches!(statuses[1], JobStatus::Cancelled));
+    assert!(matches!(statuses[2], JobStatus::Complete(Some(_))));
+    assert!(dir.join("a.wav").exists());
+    assert!(!dir.join("b.wav").exists());
+    assert!(dir.join("c.flac").exists());
+
+    cleanup_temp_dir("queue");
+}
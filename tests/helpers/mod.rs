@@ -30,6 +30,23 @@ pub fn test_engine() -> TuttiEngine {
         .expect("Failed to create test engine")
 }
 
+/// Create a headless test engine on the null backend with an output tap.
+/// Renders free-running at [`TEST_SAMPLE_RATE`] / [`TEST_BUFFER_SIZE`].
+pub fn null_engine() -> (TuttiEngine, tutti::OutputTap) {
+    let engine = TuttiEngine::builder()
+        .backend(tutti::AudioBackend::Null(
+            tutti::NullBackendConfig::default()
+                .sample_rate(TEST_SAMPLE_RATE)
+                .block_size(TEST_BUFFER_SIZE)
+                .free_running()
+                .tap(TEST_SAMPLE_RATE as usize),
+        ))
+        .build()
+        .expect("Failed to create null-backend engine");
+    let tap = engine.take_output_tap().expect("null backend tap");
+    (engine, tap)
+}
+
 /// Generate a test signal: sine wave at given frequency for specified samples.
 pub fn generate_sine(frequency: f64, sample_rate: f64, num_samples: usize) -> Vec<f32> {
     (0..num_samples)
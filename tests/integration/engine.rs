@@ -62,3 +62,22 @@ fn test_graph_operations() {
 
     assert!(engine.is_running());
 }
+
+/// Test the headless null backend renders the graph without audio hardware.
+#[test]
+fn test_null_backend_renders_without_device() {
+    let (engine, mut tap) = null_engine();
+
+    assert!(engine.is_running());
+    assert_eq!(engine.sample_rate(), TEST_SAMPLE_RATE);
+
+    engine.graph_mut(|net| net.add(sine_hz::<f32>(440.0)).master());
+
+    // Drain blocks rendered before the commit, then capture a fresh second
+    let _ = tap.read_frames(TEST_SAMPLE_RATE as usize, std::time::Duration::from_secs(5));
+    let samples = tap.read_frames(TEST_SAMPLE_RATE as usize, std::time::Duration::from_secs(5));
+    let left: Vec<f32> = samples.chunks_exact(2).map(|f| f[0]).collect();
+
+    assert_eq!(left.len(), TEST_SAMPLE_RATE as usize);
+    assert_has_audio(&left, 0.5);
+}
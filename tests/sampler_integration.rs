@@ -2075,3 +2075,38 @@ fn test_content_end_beat_finds_samplers() {
         end_beat,
     );
 }
+
+// =============================================================================
+// Multisample Instruments
+// =============================================================================
+
+/// `engine.sfz()` and `engine.dspreset()` pick the parser, not the extension.
+#[test]
+#[cfg(all(feature = "wav", feature = "instrument"))]
+fn test_instrument_format_follows_entry_point() {
+    let dir = setup_temp_dir("instrument_format");
+    std::fs::copy(small_sample(), dir.join("a.wav")).unwrap();
+
+    let preset = dir.join("preset.xml");
+    std::fs::write(
+        &preset,
+        r#"<DecentSampler><groups><group>
+  <sample path="a.wav" rootNote="60" loNote="48" hiNote="72"/>
+</group></groups></DecentSampler>"#,
+    )
+    .unwrap();
+    // SFZ text behind a .dspreset name
+    let sfz = dir.join("misnamed.dspreset");
+    std::fs::write(&sfz, "<region> sample=a.wav lokey=36 hikey=84\n").unwrap();
+
+    let engine = test_engine();
+    let instrument = engine.dspreset(&preset).build().unwrap();
+    assert_eq!(instrument.zones().len(), 1);
+    assert_eq!(instrument.zones()[0].key_range(), (48, 72));
+
+    let instrument = engine.sfz(&sfz).build().unwrap();
+    assert_eq!(instrument.zones().len(), 1);
+    assert_eq!(instrument.zones()[0].key_range(), (36, 84));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}